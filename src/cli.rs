@@ -1,16 +1,39 @@
 // One module per subcommand
+mod api;
 mod collections;
+mod config;
+mod curl_proxy;
+mod db;
+mod env;
+mod export;
+mod fmt;
 mod generate;
 mod history;
 mod import;
+mod profiles;
+mod recipes;
+mod record;
+mod render;
 mod request;
+mod run;
+mod secret;
+mod seed;
+mod serve;
 mod show;
+mod stats;
+mod test;
 
 use crate::{
     cli::{
-        collections::CollectionsCommand, generate::GenerateCommand,
+        collections::CollectionsCommand, config::ConfigCommand,
+        curl_proxy::CurlProxyCommand, db::DbCommand, env::EnvCommand,
+        export::ExportCommand, fmt::FmtCommand, generate::GenerateCommand,
         history::HistoryCommand, import::ImportCommand,
-        request::RequestCommand, show::ShowCommand,
+        profiles::ProfilesCommand, recipes::RecipesCommand,
+        record::RecordCommand, render::RenderCommand, request::RequestCommand,
+        run::RunCommand, secret::SecretCommand, seed::SeedCommand,
+        serve::ServeCommand, show::ShowCommand, stats::StatsCommand,
+        test::TestCommand,
     },
     GlobalArgs,
 };
@@ -21,10 +44,27 @@ use std::process::ExitCode;
 pub enum CliCommand {
     Request(RequestCommand),
     Generate(GenerateCommand),
+    Render(RenderCommand),
+    Env(EnvCommand),
+    Run(RunCommand),
+    Test(TestCommand),
+    Seed(SeedCommand),
     Import(ImportCommand),
+    Fmt(FmtCommand),
+    #[command(visible_alias = "collection")]
     Collections(CollectionsCommand),
+    Config(ConfigCommand),
+    Db(DbCommand),
     History(HistoryCommand),
+    Export(ExportCommand),
+    Secret(SecretCommand),
+    Serve(ServeCommand),
     Show(ShowCommand),
+    CurlProxy(CurlProxyCommand),
+    Stats(StatsCommand),
+    Profiles(ProfilesCommand),
+    Recipes(RecipesCommand),
+    Record(RecordCommand),
 }
 
 /// An executable subcommand. This trait isn't strictly necessary because we do
@@ -40,11 +80,27 @@ impl CliCommand {
     pub async fn execute(self, global: GlobalArgs) -> anyhow::Result<ExitCode> {
         match self {
             Self::Generate(command) => command.execute(global).await,
+            Self::Render(command) => command.execute(global).await,
+            Self::Env(command) => command.execute(global).await,
             Self::Request(command) => command.execute(global).await,
+            Self::Run(command) => command.execute(global).await,
+            Self::Test(command) => command.execute(global).await,
+            Self::Seed(command) => command.execute(global).await,
             Self::Import(command) => command.execute(global).await,
+            Self::Fmt(command) => command.execute(global).await,
             Self::Collections(command) => command.execute(global).await,
+            Self::Config(command) => command.execute(global).await,
+            Self::Db(command) => command.execute(global).await,
             Self::History(command) => command.execute(global).await,
+            Self::Export(command) => command.execute(global).await,
+            Self::Secret(command) => command.execute(global).await,
+            Self::Serve(command) => command.execute(global).await,
             Self::Show(command) => command.execute(global).await,
+            Self::CurlProxy(command) => command.execute(global).await,
+            Self::Stats(command) => command.execute(global).await,
+            Self::Profiles(command) => command.execute(global).await,
+            Self::Recipes(command) => command.execute(global).await,
+            Self::Record(command) => command.execute(global).await,
         }
     }
 }
@@ -0,0 +1,339 @@
+//! Shared implementation for subcommands that expose the collection to other
+//! programs (`curl-proxy` and `serve`). Each subcommand handles its own
+//! transport, then defers to [ApiBackend] for the actual operations.
+
+use crate::{
+    cli::request::{
+        resolve_profile, serialize_headers, validate_profile, BodyEncoding,
+        ExchangeOutput,
+    },
+    collection::{Collection, CollectionFile, ProfileId, RecipeId},
+    config::Config,
+    db::{CollectionDatabase, Database},
+    http::{
+        BuildOptions, HttpEngine, RequestRecord, RequestSeed, RequestTicket,
+    },
+    template::{Prompt, Prompter, Template, TemplateContext},
+    GlobalArgs,
+};
+use anyhow::{anyhow, Context};
+use indexmap::IndexMap;
+use itertools::Itertools;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::{borrow::Cow, path::PathBuf};
+
+/// Operations available to API clients. This holds everything that outlives
+/// a single API call.
+pub(super) struct ApiBackend {
+    /// Collection is reloaded on each call, so edits are picked up without
+    /// restarting the server
+    collection_path: PathBuf,
+    database: CollectionDatabase,
+    http_engine: HttpEngine,
+    config: Config,
+}
+
+/// Options that apply to any call that renders templates
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub(super) struct RenderOptions {
+    /// ID of the profile to render with
+    pub profile: Option<ProfileId>,
+    /// Allow chains to trigger upstream requests. Always enabled when sending
+    pub execute_triggers: bool,
+    /// Template field overrides
+    pub overrides: IndexMap<String, String>,
+}
+
+/// An error that should be returned to the client. The status is used
+/// directly by HTTP transports, and mapped to an error code by others.
+#[derive(Debug)]
+pub(super) struct ApiError {
+    pub status: StatusCode,
+    pub error: anyhow::Error,
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, error: anyhow::Error) -> Self {
+        Self { status, error }
+    }
+
+    /// Get a single-line message containing the error and all its sources
+    pub fn message(&self) -> String {
+        self.error.chain().join(": ")
+    }
+}
+
+/// Most errors are the client's fault (bad recipe ID, template error, etc.)
+impl From<anyhow::Error> for ApiError {
+    fn from(error: anyhow::Error) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, error)
+    }
+}
+
+/// Serializable summary of a recipe
+#[derive(Debug, Serialize)]
+struct RecipeOutput<'a> {
+    id: &'a RecipeId,
+    name: &'a str,
+    method: String,
+    url: String,
+}
+
+/// Serializable summary of a profile
+#[derive(Debug, Serialize)]
+struct ProfileOutput<'a> {
+    id: &'a ProfileId,
+    name: &'a str,
+}
+
+/// Serializable built request
+#[derive(Debug, Serialize)]
+struct RequestOutput<'a> {
+    method: String,
+    url: &'a str,
+    headers: IndexMap<&'a str, String>,
+    body: Option<Cow<'a, str>>,
+    body_encoding: Option<BodyEncoding>,
+}
+
+impl<'a> From<&'a RequestRecord> for RequestOutput<'a> {
+    fn from(request: &'a RequestRecord) -> Self {
+        let body = request.body.as_deref().map(BodyEncoding::encode);
+        let (body, body_encoding) = body.unzip();
+        Self {
+            method: request.method.to_string(),
+            url: request.url.as_str(),
+            headers: serialize_headers(&request.headers),
+            body,
+            body_encoding,
+        }
+    }
+}
+
+impl ApiBackend {
+    pub fn load(global: GlobalArgs) -> anyhow::Result<Self> {
+        let collection_path = CollectionFile::try_path(None, global.file)?;
+        let config = Config::load(Some(&collection_path))?;
+        let database = Database::load()?
+            .into_collection(&collection_path)?
+            .with_retention(config.history.clone());
+        Ok(Self {
+            collection_path,
+            database,
+            http_engine: HttpEngine::new(&config),
+            config,
+        })
+    }
+
+    /// List all recipes in the collection
+    pub async fn list_recipes(&self) -> Result<serde_json::Value, ApiError> {
+        let collection = self.collection().await?;
+        let recipes = collection
+            .recipes
+            .iter()
+            .filter_map(|(_, node)| node.recipe())
+            .map(|recipe| RecipeOutput {
+                id: &recipe.id,
+                name: recipe.name(),
+                method: recipe.method.to_string(),
+                url: recipe.url.to_string(),
+            })
+            .collect_vec();
+        to_value(recipes)
+    }
+
+    /// List all profiles in the collection
+    pub async fn list_profiles(&self) -> Result<serde_json::Value, ApiError> {
+        let collection = self.collection().await?;
+        let profiles = collection
+            .profiles
+            .values()
+            .map(|profile| ProfileOutput {
+                id: &profile.id,
+                name: profile.name(),
+            })
+            .collect_vec();
+        to_value(profiles)
+    }
+
+    /// Render an arbitrary template
+    pub async fn render(
+        &self,
+        template: &str,
+        options: RenderOptions,
+    ) -> Result<serde_json::Value, ApiError> {
+        let template: Template =
+            template.parse().context("Invalid template")?;
+        let context = self.template_context(options).await?;
+        let rendered = template
+            .render(&context)
+            .await
+            .map_err(anyhow::Error::from)?;
+        let (rendered, encoding) = BodyEncoding::encode(&rendered);
+        Ok(json!({"rendered": rendered, "encoding": encoding}))
+    }
+
+    /// Render a recipe into a request, without sending it. Use
+    /// [Self::request_value] to serialize the result, or [Self::send] to send
+    /// it.
+    pub async fn build(
+        &self,
+        recipe_id: &RecipeId,
+        options: RenderOptions,
+    ) -> Result<RequestTicket, ApiError> {
+        let mut context = self.template_context(options).await?;
+        let recipe = context
+            .collection
+            .recipes
+            .get_recipe(recipe_id)
+            .cloned()
+            .ok_or_else(|| {
+                ApiError::new(
+                    StatusCode::NOT_FOUND,
+                    anyhow!("No recipe with ID `{recipe_id}`"),
+                )
+            })?;
+        // A profile pinned on the recipe takes precedence over the requested
+        // one, same as in the CLI
+        context.selected_profile = resolve_profile(
+            &context.collection,
+            &recipe,
+            context.selected_profile.as_ref(),
+        )?;
+        let seed = RequestSeed::new(recipe, BuildOptions::default());
+        let ticket = self
+            .http_engine
+            .build(seed, &context)
+            .await
+            .map_err(anyhow::Error::from)?;
+        Ok(ticket)
+    }
+
+    /// Send a built request, and serialize the response
+    pub async fn send(
+        &self,
+        ticket: RequestTicket,
+    ) -> Result<serde_json::Value, ApiError> {
+        let sent = ticket.send(&self.database).await.map_err(|error| {
+            ApiError::new(StatusCode::BAD_GATEWAY, anyhow::Error::from(error))
+        })?;
+        let mut value = to_value(ExchangeOutput::new(&sent.exchange, false))?;
+        // The request still succeeded, so include the error with the response
+        if let Some(error) = sent.save_error {
+            value["save_error"] = format!("{error:#}").into();
+        }
+        Ok(value)
+    }
+
+    /// Serialize a built request
+    pub fn request_value(
+        ticket: &RequestTicket,
+    ) -> Result<serde_json::Value, ApiError> {
+        to_value(RequestOutput::from(&**ticket.record()))
+    }
+
+    async fn collection(&self) -> Result<Collection, ApiError> {
+        let collection_file =
+            CollectionFile::load(self.collection_path.clone())
+                .await
+                .map_err(|error| {
+                    ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, error)
+                })?;
+        Ok(collection_file.collection)
+    }
+
+    async fn template_context(
+        &self,
+        options: RenderOptions,
+    ) -> Result<TemplateContext, ApiError> {
+        let collection = self.collection().await?;
+        if let Some(profile_id) = &options.profile {
+            validate_profile(&collection, profile_id)?;
+        }
+        Ok(TemplateContext {
+            overrides: options.overrides,
+            // Stdin is the JSON-RPC connection for `serve`
+            stdin_available: false,
+            ..TemplateContext::new(
+                collection,
+                options.profile,
+                options.execute_triggers.then(|| self.http_engine.clone()),
+                self.database.clone(),
+                Box::new(ApiPrompter),
+                &self.config,
+            )
+        })
+    }
+}
+
+fn to_value(value: impl Serialize) -> Result<serde_json::Value, ApiError> {
+    serde_json::to_value(value).map_err(|error| {
+        ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, error.into())
+    })
+}
+
+/// There's no one to answer prompts, so they fail immediately
+#[derive(Debug)]
+struct ApiPrompter;
+
+impl Prompter for ApiPrompter {
+    fn prompt(&self, _: Prompt) {
+        // Dropping the prompt closes its channel, which the template renderer
+        // treats as no response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{temp_dir, Factory, TempDir};
+    use rstest::rstest;
+    use std::fs;
+
+    /// A profile pinned on the recipe wins over the one in the options
+    #[rstest]
+    #[tokio::test]
+    async fn test_build_pinned_profile(temp_dir: TempDir) {
+        let collection_path = temp_dir.join("slumber.yml");
+        fs::write(
+            &collection_path,
+            "
+profiles:
+  dev:
+    data:
+      host: http://dev
+  prod:
+    data:
+      host: http://prod
+requests:
+  pinned: !request
+    method: GET
+    url: '{{host}}/users'
+    profile: prod
+",
+        )
+        .unwrap();
+        let config = Config::default();
+        let backend = ApiBackend {
+            collection_path,
+            database: CollectionDatabase::factory(()),
+            http_engine: HttpEngine::new(&config),
+            config,
+        };
+
+        let ticket = backend
+            .build(
+                &"pinned".into(),
+                RenderOptions {
+                    profile: Some("dev".into()),
+                    ..RenderOptions::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(ticket.record().url.as_str(), "http://prod/users");
+    }
+}
@@ -1,8 +1,13 @@
-use crate::{cli::Subcommand, db::Database, GlobalArgs};
+use crate::{
+    cli::Subcommand,
+    collection::{validate_collection, CollectionFile},
+    db::Database,
+    GlobalArgs,
+};
 use clap::Parser;
-use std::{path::PathBuf, process::ExitCode};
+use std::{fs, path::PathBuf, process::ExitCode};
 
-/// View and modify request collection metadata
+/// View and modify request collection metadata, or validate a collection
 #[derive(Clone, Debug, Parser)]
 pub struct CollectionsCommand {
     #[command(subcommand)]
@@ -14,6 +19,24 @@ enum CollectionsSubcommand {
     /// List all known request collections
     #[command(visible_alias = "ls")]
     List,
+    /// Add a collection to the list of known collections
+    ///
+    /// Collections are added automatically the first time they're used. Use
+    /// this to make a collection available in the TUI's collection switcher
+    /// before then.
+    Add {
+        /// Path to the collection file
+        path: PathBuf,
+    },
+    /// Remove a collection from the list of known collections
+    ///
+    /// This deletes all request history and UI state for the collection! The
+    /// collection file itself is not modified.
+    #[command(visible_alias = "rm")]
+    Remove {
+        /// Path to the collection file
+        path: PathBuf,
+    },
     /// Move all data from one collection to another.
     ///
     /// The data from the source collection will be merged into the target
@@ -24,22 +47,68 @@ enum CollectionsSubcommand {
         /// The path the collection to migrate *into*
         to: PathBuf,
     },
+    /// Check a collection file for errors
+    ///
+    /// Unlike loading the collection, which stops at the first error, this
+    /// reports every problem in the file at once, with line/column info and
+    /// suggestions for misspelled field names. Exits with a failure code if
+    /// there are any errors.
+    Validate {
+        /// Path to the collection file. Defaults to the same collection file
+        /// the TUI would load
+        path: Option<PathBuf>,
+    },
 }
 
 impl Subcommand for CollectionsCommand {
-    async fn execute(self, _global: GlobalArgs) -> anyhow::Result<ExitCode> {
-        let database = Database::load()?;
+    async fn execute(self, global: GlobalArgs) -> anyhow::Result<ExitCode> {
         match self.subcommand {
             CollectionsSubcommand::List => {
-                for path in database.collections()? {
+                for path in Database::load()?.collections()? {
                     println!("{}", path.display());
                 }
             }
+            CollectionsSubcommand::Add { path } => {
+                Database::load()?.add_collection(&path)?;
+                println!("Added {}", path.display());
+            }
+            CollectionsSubcommand::Remove { path } => {
+                Database::load()?.remove_collection(&path)?;
+                println!("Removed {}", path.display());
+            }
             CollectionsSubcommand::Migrate { from, to } => {
-                database.merge_collections(&from, &to)?;
+                Database::load()?.merge_collections(&from, &to)?;
                 println!("Migrated {} into {}", from.display(), to.display());
             }
+            CollectionsSubcommand::Validate { path } => {
+                return validate(path.or(global.file));
+            }
         }
         Ok(ExitCode::SUCCESS)
     }
 }
+
+/// Validate a collection file and print all errors
+fn validate(path: Option<PathBuf>) -> anyhow::Result<ExitCode> {
+    let path = CollectionFile::try_path(None, path)?;
+    let source = fs::read_to_string(&path)?;
+    let errors = validate_collection(&source);
+    if errors.is_empty() {
+        println!("{} is valid", path.display());
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    for error in &errors {
+        // Match the `file:line:col: message` format of compilers/linters, so
+        // editors can jump to the error
+        let separator = if error.location.is_some() { ":" } else { ": " };
+        eprintln!("{}{separator}{error}", path.display());
+    }
+    eprintln!(
+        "Found {} error{} in {}",
+        errors.len(),
+        if errors.len() == 1 { "" } else { "s" },
+        path.display()
+    );
+    Ok(ExitCode::FAILURE)
+}
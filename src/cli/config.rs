@@ -0,0 +1,45 @@
+use crate::{
+    cli::Subcommand, config::Config, util::get_editor_command, GlobalArgs,
+};
+use anyhow::{bail, Context};
+use clap::Parser;
+use std::process::ExitCode;
+
+/// View and modify the global configuration file
+#[derive(Clone, Debug, Parser)]
+pub struct ConfigCommand {
+    #[command(subcommand)]
+    subcommand: ConfigSubcommand,
+}
+
+#[derive(Clone, Debug, clap::Subcommand)]
+enum ConfigSubcommand {
+    /// Open the config file in your editor (`VISUAL` or `EDITOR`)
+    ///
+    /// Once the editor exits, the file is loaded to check for errors. A running
+    /// TUI picks up changes automatically.
+    Edit,
+    /// Print the path of the config file
+    Path,
+}
+
+impl Subcommand for ConfigCommand {
+    async fn execute(self, _global: GlobalArgs) -> anyhow::Result<ExitCode> {
+        match self.subcommand {
+            ConfigSubcommand::Edit => {
+                let path = Config::path().create_parent()?;
+                let mut command = get_editor_command(&path)?;
+                let status = command.status().with_context(|| {
+                    format!("Error spawning editor with command `{command:?}`")
+                })?;
+                if !status.success() {
+                    bail!("Editor exited with {status}");
+                }
+                // Make sure the user didn't leave behind a broken file
+                Config::load(None)?;
+            }
+            ConfigSubcommand::Path => println!("{}", Config::path()),
+        }
+        Ok(ExitCode::SUCCESS)
+    }
+}
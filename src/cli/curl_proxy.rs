@@ -0,0 +1,449 @@
+use crate::{
+    cli::{
+        api::{ApiBackend, ApiError, RenderOptions},
+        Subcommand,
+    },
+    collection::RecipeId,
+    GlobalArgs,
+};
+use anyhow::{anyhow, bail, Context};
+use clap::Parser;
+use itertools::Itertools;
+use reqwest::{
+    header::{self, HeaderMap, HeaderName, HeaderValue},
+    StatusCode, Url,
+};
+use serde_json::json;
+use std::{
+    net::{IpAddr, SocketAddr},
+    ops::Deref,
+    process::ExitCode,
+    sync::Arc,
+};
+use tokio::{
+    io::{
+        AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader,
+    },
+    net::{TcpListener, TcpStream},
+};
+use tracing::{error, info};
+
+/// Maximum size of a request body accepted by the API
+const MAX_BODY_SIZE: usize = 1024 * 1024;
+/// Maximum size of the request line, or of a single header line
+const MAX_LINE_SIZE: usize = 8 * 1024;
+/// Maximum number of headers in a request
+const MAX_HEADERS: usize = 100;
+
+/// Serve the collection over a local HTTP API
+///
+/// This allows editor plugins and other tools to drive Slumber
+/// programmatically, without spawning a new process for every call. All
+/// responses are JSON. Endpoints:
+///
+/// - `GET /recipes` - List all recipes
+///
+/// - `GET /profiles` - List all profiles
+///
+/// - `POST /render` - Render the template in the request body
+///
+/// - `POST /recipes/{id}/build` - Render a recipe into a request, without
+///   sending it
+///
+/// - `POST /recipes/{id}/send` - Build and send a request
+///
+/// Pass `?profile=<id>` to select a profile for rendering. Triggered
+/// sub-requests are only executed by `send`, unless `?execute_triggers=true`
+/// is passed.
+///
+/// To protect against cross-site requests and DNS rebinding from a browser,
+/// requests with an `Origin` header are rejected, as are requests whose `Host`
+/// is anything other than `localhost` or an IP address.
+#[derive(Clone, Debug, Parser)]
+pub struct CurlProxyCommand {
+    /// Address to listen on. Only bind to a non-local address if you
+    /// understand the risk; anyone who can reach the API can send requests
+    /// with your collection's credentials.
+    #[clap(long, default_value = "127.0.0.1:8787")]
+    address: SocketAddr,
+}
+
+impl Subcommand for CurlProxyCommand {
+    async fn execute(self, global: GlobalArgs) -> anyhow::Result<ExitCode> {
+        let backend = Arc::new(ApiBackend::load(global)?);
+
+        let listener = TcpListener::bind(self.address)
+            .await
+            .with_context(|| format!("Error binding to {}", self.address))?;
+        eprintln!("Listening on http://{}", self.address);
+
+        loop {
+            let (stream, address) = listener
+                .accept()
+                .await
+                .context("Error accepting connection")?;
+            info!(%address, "Accepted connection");
+            let backend = Arc::clone(&backend);
+            tokio::spawn(async move {
+                if let Err(error) = handle_connection(&backend, stream).await {
+                    error!(error = error.deref(), "Error handling connection");
+                }
+            });
+        }
+    }
+}
+
+/// A parsed incoming request
+#[derive(Debug)]
+pub(super) struct ApiRequest {
+    pub method: String,
+    pub url: Url,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+}
+
+/// An API endpoint, parsed from the request method and path
+#[derive(Debug, PartialEq)]
+enum Route {
+    ListRecipes,
+    ListProfiles,
+    Render,
+    Build(RecipeId),
+    Send(RecipeId),
+}
+
+impl Route {
+    /// Match a method and path to an endpoint
+    fn parse(method: &str, path: &str) -> Option<Self> {
+        let segments = path.trim_matches('/').split('/').collect_vec();
+        match (method, segments.as_slice()) {
+            ("GET", ["recipes"]) => Some(Self::ListRecipes),
+            ("GET", ["profiles"]) => Some(Self::ListProfiles),
+            ("POST", ["render"]) => Some(Self::Render),
+            ("POST", ["recipes", id, "build"]) => {
+                Some(Self::Build(id.to_string().into()))
+            }
+            ("POST", ["recipes", id, "send"]) => {
+                Some(Self::Send(id.to_string().into()))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Read a single request from the connection, route it, and write the
+/// response. Connections are closed after each request.
+async fn handle_connection(
+    backend: &ApiBackend,
+    stream: TcpStream,
+) -> anyhow::Result<()> {
+    let mut stream = BufReader::new(stream);
+    let (status, body) = match read_request(&mut stream, MAX_BODY_SIZE).await {
+        Ok(request) => {
+            info!(method = %request.method, url = %request.url, "Request");
+            match handle_request(backend, request).await {
+                Ok(body) => (StatusCode::OK, body),
+                Err(error) => (error.status, json!({"error": error.message()})),
+            }
+        }
+        Err(error) => (
+            StatusCode::BAD_REQUEST,
+            json!({"error": error.chain().join(": ")}),
+        ),
+    };
+
+    let body = serde_json::to_vec(&body)?;
+    let head = format!(
+        "HTTP/1.1 {status}\r\n\
+        Content-Type: application/json\r\n\
+        Content-Length: {}\r\n\
+        Connection: close\r\n\r\n",
+        body.len()
+    );
+    let stream = stream.get_mut();
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(&body).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+/// Execute an API request, returning the JSON response body
+async fn handle_request(
+    backend: &ApiBackend,
+    request: ApiRequest,
+) -> Result<serde_json::Value, ApiError> {
+    check_origin(&request.headers)?;
+    let route =
+        Route::parse(&request.method, request.url.path()).ok_or_else(|| {
+            ApiError::new(
+                StatusCode::NOT_FOUND,
+                anyhow!(
+                    "No endpoint for {} {}",
+                    request.method,
+                    request.url.path()
+                ),
+            )
+        })?;
+
+    // Grab query params that apply to every endpoint
+    let mut options = RenderOptions::default();
+    for (key, value) in request.url.query_pairs() {
+        match &*key {
+            "profile" => options.profile = Some(value.into_owned().into()),
+            "execute_triggers" => options.execute_triggers = value == "true",
+            _ => {}
+        }
+    }
+
+    match route {
+        Route::ListRecipes => backend.list_recipes().await,
+        Route::ListProfiles => backend.list_profiles().await,
+        Route::Render => {
+            let template = std::str::from_utf8(&request.body)
+                .context("Template must be valid UTF-8")?;
+            backend.render(template, options).await
+        }
+        Route::Build(recipe_id) => {
+            let ticket = backend.build(&recipe_id, options).await?;
+            ApiBackend::request_value(&ticket)
+        }
+        Route::Send(recipe_id) => {
+            // Sending already has side effects, so triggers are fine
+            options.execute_triggers = true;
+            let ticket = backend.build(&recipe_id, options).await?;
+            backend.send(ticket).await
+        }
+    }
+}
+
+/// Make sure a request didn't come from a web page. Browsers attach `Origin`
+/// to cross-origin requests, which blocks CSRF. A DNS rebinding attack sends
+/// the attacker's domain as the `Host`, so only accept hosts that can't be
+/// rebound: `localhost` and IP addresses.
+fn check_origin(headers: &HeaderMap) -> Result<(), ApiError> {
+    let forbidden = |message: &str| {
+        ApiError::new(StatusCode::FORBIDDEN, anyhow!("{message}"))
+    };
+    if headers.contains_key(header::ORIGIN) {
+        return Err(forbidden("Cross-origin requests are not allowed"));
+    }
+
+    let host = headers
+        .get(header::HOST)
+        .ok_or_else(|| forbidden("Missing Host header"))?
+        .to_str()
+        .ok()
+        // Drop the port, if any. IPv6 addresses are wrapped in brackets
+        .map(|host| match host.rsplit_once(':') {
+            Some((host, port)) if !port.contains(']') => host,
+            _ => host,
+        })
+        .map(|host| host.trim_start_matches('[').trim_end_matches(']'));
+    match host {
+        Some(host)
+            if host.eq_ignore_ascii_case("localhost")
+                || host.parse::<IpAddr>().is_ok() =>
+        {
+            Ok(())
+        }
+        _ => Err(forbidden("Host must be `localhost` or an IP address")),
+    }
+}
+
+/// Read an HTTP/1.1 request from a stream. This only supports what the API
+/// needs: a request line, headers, and a body with `Content-Length`. The size
+/// of each line and the number of headers are capped, so a misbehaving client
+/// can't make us buffer an unbounded amount of data.
+pub(super) async fn read_request(
+    stream: &mut (impl AsyncBufRead + Unpin),
+    max_body_size: usize,
+) -> anyhow::Result<ApiRequest> {
+    let mut line = String::new();
+    read_line(stream, &mut line).await?;
+    let (method, target) = match line.split_whitespace().collect_vec()[..] {
+        [method, target, _version] => (method.to_owned(), target.to_owned()),
+        _ => bail!("Invalid request line `{}`", line.trim_end()),
+    };
+    // The host doesn't matter, we just need a base to parse the path+query
+    let url = Url::parse("http://localhost")?
+        .join(&target)
+        .with_context(|| format!("Invalid request target `{target}`"))?;
+
+    let mut headers = HeaderMap::new();
+    let mut content_length = 0;
+    loop {
+        line.clear();
+        read_line(stream, &mut line).await?;
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if headers.len() >= MAX_HEADERS {
+            bail!("Too many headers; maximum is {MAX_HEADERS}");
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value
+                    .trim()
+                    .parse()
+                    .context("Invalid Content-Length header")?;
+            }
+            headers.append(
+                HeaderName::try_from(name.trim())
+                    .with_context(|| format!("Invalid header name `{name}`"))?,
+                HeaderValue::try_from(value.trim()).with_context(|| {
+                    format!("Invalid value for header `{name}`")
+                })?,
+            );
+        }
+    }
+    if content_length > max_body_size {
+        bail!("Request body too large; maximum is {max_body_size} bytes");
+    }
+
+    let mut body = vec![0; content_length];
+    stream.read_exact(&mut body).await?;
+    Ok(ApiRequest {
+        method,
+        url,
+        headers,
+        body,
+    })
+}
+
+/// Read a single line, including the trailing newline, into the buffer. Fail
+/// if the line is longer than [MAX_LINE_SIZE] or the stream ends before the
+/// newline.
+async fn read_line(
+    stream: &mut (impl AsyncBufRead + Unpin),
+    line: &mut String,
+) -> anyhow::Result<()> {
+    // Read one extra byte so we can tell a line that's exactly the max size
+    // from one that's too long
+    (&mut *stream)
+        .take(MAX_LINE_SIZE as u64 + 1)
+        .read_line(line)
+        .await?;
+    if line.ends_with('\n') {
+        Ok(())
+    } else if line.len() > MAX_LINE_SIZE {
+        bail!("Line too long; maximum is {MAX_LINE_SIZE} bytes")
+    } else {
+        bail!("Unexpected end of request")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{assert_err, header_map};
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::list_recipes("GET", "/recipes", Some(Route::ListRecipes))]
+    #[case::list_profiles("GET", "/profiles/", Some(Route::ListProfiles))]
+    #[case::render("POST", "/render", Some(Route::Render))]
+    #[case::build(
+        "POST",
+        "/recipes/login/build",
+        Some(Route::Build("login".into()))
+    )]
+    #[case::send(
+        "POST",
+        "/recipes/login/send",
+        Some(Route::Send("login".into()))
+    )]
+    #[case::wrong_method("GET", "/recipes/login/send", None)]
+    #[case::unknown("GET", "/fish", None)]
+    fn test_route(
+        #[case] method: &str,
+        #[case] path: &str,
+        #[case] expected: Option<Route>,
+    ) {
+        assert_eq!(Route::parse(method, path), expected);
+    }
+
+    #[rstest]
+    #[case::localhost("localhost:8787")]
+    #[case::localhost_no_port("localhost")]
+    #[case::localhost_uppercase("LOCALHOST:8787")]
+    #[case::ipv4("127.0.0.1:8787")]
+    #[case::ipv6("[::1]:8787")]
+    #[case::ipv6_no_port("[::1]")]
+    fn test_check_origin(#[case] host: &str) {
+        check_origin(&header_map([("host", host)])).unwrap();
+    }
+
+    #[rstest]
+    #[case::no_host(&[], "Missing Host header")]
+    #[case::domain(&[("host", "evil.com:8787")], "Host must be")]
+    #[case::localhost_subdomain(
+        &[("host", "localhost.evil.com")],
+        "Host must be",
+    )]
+    #[case::origin(
+        &[("host", "localhost:8787"), ("origin", "http://evil.com")],
+        "Cross-origin requests are not allowed",
+    )]
+    fn test_check_origin_error(
+        #[case] headers: &[(&str, &str)],
+        #[case] expected: &str,
+    ) {
+        let error =
+            check_origin(&header_map(headers.iter().copied())).unwrap_err();
+        assert_eq!(error.status, StatusCode::FORBIDDEN);
+        assert_err!(Err::<(), _>(error.error), expected);
+    }
+
+    #[tokio::test]
+    async fn test_read_request() {
+        let mut stream: &[u8] = b"POST /render?profile=p HTTP/1.1\r\n\
+            Host: localhost\r\n\
+            Content-Length: 5\r\n\
+            \r\n\
+            hello";
+        let request = read_request(&mut stream, MAX_BODY_SIZE).await.unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.url.as_str(), "http://localhost/render?profile=p");
+        assert_eq!(
+            request.headers,
+            header_map([("host", "localhost"), ("content-length", "5")])
+        );
+        assert_eq!(request.body, b"hello");
+    }
+
+    #[rstest]
+    #[case::request_line_too_long(
+        format!("GET /{} HTTP/1.1\r\n\r\n", "a".repeat(MAX_LINE_SIZE)),
+        "Line too long",
+    )]
+    #[case::header_too_long(
+        format!("GET / HTTP/1.1\r\nx: {}\r\n\r\n", "a".repeat(MAX_LINE_SIZE)),
+        "Line too long",
+    )]
+    #[case::too_many_headers(
+        format!("GET / HTTP/1.1\r\n{}\r\n", "x: 1\r\n".repeat(MAX_HEADERS + 1)),
+        "Too many headers",
+    )]
+    #[case::body_too_large(
+        format!(
+            "POST / HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+            MAX_BODY_SIZE + 1,
+        ),
+        "Request body too large",
+    )]
+    #[case::incomplete(
+        "GET / HTTP/1.1\r\nHost: localhost".into(),
+        "Unexpected end of request",
+    )]
+    #[tokio::test]
+    async fn test_read_request_error(
+        #[case] request: String,
+        #[case] expected: &str,
+    ) {
+        assert_err!(
+            read_request(&mut request.as_bytes(), MAX_BODY_SIZE).await,
+            expected
+        );
+    }
+}
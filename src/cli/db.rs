@@ -0,0 +1,123 @@
+use crate::{
+    cli::{request::parse_key_val, Subcommand},
+    collection::serde_duration,
+    db::Database,
+    util::format_time,
+    GlobalArgs,
+};
+use anyhow::{anyhow, bail, Context};
+use bytesize::ByteSize;
+use chrono::Utc;
+use clap::Parser;
+use std::{fs, path::PathBuf, process::ExitCode, time::Duration};
+
+/// Inspect and manage the history database
+///
+/// The database is shared by all collections, so these commands apply to every
+/// collection.
+#[derive(Clone, Debug, Parser)]
+pub struct DbCommand {
+    #[command(subcommand)]
+    subcommand: DbSubcommand,
+}
+
+#[derive(Clone, Debug, clap::Subcommand)]
+enum DbSubcommand {
+    /// Print the size of the database, and how much history each collection
+    /// is storing
+    Stats,
+    /// Delete old requests from history
+    ///
+    /// If both options are given, requests older than `--older-than` are
+    /// deleted first, then the oldest remaining requests are deleted until
+    /// history fits in `--max-size`.
+    Prune {
+        /// Delete requests older than this (e.g. `30d`, `12h`)
+        #[clap(long, value_parser = serde_duration::parse)]
+        older_than: Option<Duration>,
+        /// Delete the oldest requests until the total size of history is
+        /// below this (e.g. `100MB`)
+        #[clap(long)]
+        max_size: Option<ByteSize>,
+    },
+    /// Copy the entire database to a new SQLite file
+    ///
+    /// The file includes request history and UI state for every collection.
+    /// Load it on another machine with `slumber db import`.
+    Export {
+        /// Path to write to. Must not already exist
+        file: PathBuf,
+    },
+    /// Merge a file created by `slumber db export` into the database
+    ///
+    /// Collections are matched by their path. Existing requests and UI state
+    /// are never overwritten, so importing the same file twice is harmless.
+    Import {
+        /// Path to the exported file
+        file: PathBuf,
+        /// Import an exported collection under a different path, e.g. if the
+        /// collection is in a different directory on this machine. Pass as
+        /// `exported=local`. Exported paths are absolute
+        #[clap(
+            long = "map-path",
+            value_parser = parse_key_val::<PathBuf, PathBuf>,
+        )]
+        path_map: Vec<(PathBuf, PathBuf)>,
+    },
+}
+
+impl Subcommand for DbCommand {
+    async fn execute(self, _global: GlobalArgs) -> anyhow::Result<ExitCode> {
+        let database = Database::load()?;
+        match self.subcommand {
+            DbSubcommand::Stats => {
+                let path = Database::path().create_parent()?;
+                let file_size = fs::metadata(&path)
+                    .with_context(|| format!("Error reading {path:?}"))?
+                    .len();
+                println!("Database: {}", path.display());
+                println!("Size: {}", ByteSize(file_size).to_string_as(false));
+                for stats in database.stats()? {
+                    println!();
+                    println!("{}", stats.path.display());
+                    println!("  Requests: {}", stats.requests);
+                    println!("  Size: {}", stats.size.to_string_as(false));
+                    if let Some(oldest) = stats.oldest {
+                        println!("  Oldest: {}", format_time(&oldest));
+                    }
+                }
+            }
+            DbSubcommand::Prune {
+                older_than,
+                max_size,
+            } => {
+                if older_than.is_none() && max_size.is_none() {
+                    bail!("Pass `--older-than` and/or `--max-size`");
+                }
+                let before = older_than
+                    .map(|older_than| {
+                        let older_than = chrono::Duration::from_std(older_than)
+                            .map_err(|_| anyhow!("Duration is too large"))?;
+                        anyhow::Ok(Utc::now() - older_than)
+                    })
+                    .transpose()?;
+                let deleted = database.prune(before, max_size)?;
+                println!("Deleted {deleted} request(s)");
+            }
+            DbSubcommand::Export { file } => {
+                database.export(&file)?;
+                println!("Exported database to {}", file.display());
+            }
+            DbSubcommand::Import { file, path_map } => {
+                for collection in database.import(&file, &path_map)? {
+                    println!(
+                        "{}: imported {} request(s)",
+                        collection.path.display(),
+                        collection.requests
+                    );
+                }
+            }
+        }
+        Ok(ExitCode::SUCCESS)
+    }
+}
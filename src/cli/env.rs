@@ -0,0 +1,192 @@
+use crate::{
+    cli::{
+        request::{parse_key_val, validate_profile, CliPrompter},
+        Subcommand,
+    },
+    collection::{CollectionFile, ProfileId},
+    config::Config,
+    db::Database,
+    http::HttpEngine,
+    template::TemplateContext,
+    GlobalArgs,
+};
+use anyhow::{anyhow, Context};
+use clap::{Parser, ValueEnum};
+use indexmap::IndexMap;
+use std::process::ExitCode;
+
+/// Render a profile's fields as environment variables
+///
+/// Every field in the profile is rendered, then printed in a format that can
+/// be consumed by a shell or other tools. For example:
+/// `eval "$(slumber env production)"`
+#[derive(Clone, Debug, Parser)]
+pub struct EnvCommand {
+    /// ID of the profile to render
+    profile: ProfileId,
+
+    /// Output format
+    #[clap(long, default_value = "shell")]
+    format: EnvFormat,
+
+    /// List of key=value template field overrides
+    #[clap(
+        long = "override",
+        short = 'o',
+        value_parser = parse_key_val::<String, String>,
+    )]
+    overrides: Vec<(String, String)>,
+
+    /// Execute triggered sub-requests. By default, if a request dependency is
+    /// triggered (e.g. if it is expired), an error will be thrown instead
+    #[clap(long)]
+    execute_triggers: bool,
+}
+
+/// Formats for printing rendered profile fields
+#[derive(Copy, Clone, Debug, ValueEnum)]
+enum EnvFormat {
+    /// `export KEY='value'` lines, for POSIX shells
+    Shell,
+    /// `KEY="value"` lines, for .env files
+    Dotenv,
+    /// A single JSON object
+    Json,
+}
+
+impl Subcommand for EnvCommand {
+    async fn execute(self, global: GlobalArgs) -> anyhow::Result<ExitCode> {
+        let collection_path = CollectionFile::try_path(None, global.file)?;
+        let config = Config::load(Some(&collection_path))?;
+        let database = Database::load()?
+            .into_collection(&collection_path)?
+            .with_retention(config.history.clone());
+        let collection =
+            CollectionFile::load(collection_path).await?.collection;
+
+        validate_profile(&collection, &self.profile)?;
+        let fields = collection.profiles[&self.profile].data.clone();
+        let context = TemplateContext {
+            overrides: self.overrides.into_iter().collect(),
+            ..TemplateContext::new(
+                collection,
+                Some(self.profile),
+                // User has to explicitly opt into executing triggered requests
+                self.execute_triggers.then(|| HttpEngine::new(&config)),
+                database,
+                Box::new(CliPrompter),
+                &config,
+            )
+        };
+
+        let mut rendered = IndexMap::new();
+        for (field, template) in &fields {
+            let value = template
+                .render_string(&context)
+                .await
+                .with_context(|| format!("Error rendering field `{field}`"))?;
+            rendered.insert(field.clone(), value);
+        }
+        print!("{}", format_env(&rendered, self.format)?);
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+/// Format rendered fields for output
+fn format_env(
+    fields: &IndexMap<String, String>,
+    format: EnvFormat,
+) -> anyhow::Result<String> {
+    match format {
+        EnvFormat::Shell | EnvFormat::Dotenv => {
+            let mut output = String::new();
+            for (field, value) in fields {
+                // Shells and most dotenv parsers are strict about names, so
+                // catch invalid ones here rather than generate a broken file
+                if !is_env_name(field) {
+                    return Err(anyhow!(
+                        "Field `{field}` is not a valid environment variable \
+                        name; use `--format json` instead"
+                    ));
+                }
+                let line = match format {
+                    EnvFormat::Shell => {
+                        // Single quotes disable all expansion. The only thing
+                        // to escape is a single quote itself
+                        let value = value.replace('\'', r"'\''");
+                        format!("export {field}='{value}'\n")
+                    }
+                    _ => {
+                        let value = value
+                            .replace('\\', r"\\")
+                            .replace('"', "\\\"")
+                            .replace('\n', r"\n");
+                        format!("{field}=\"{value}\"\n")
+                    }
+                };
+                output.push_str(&line);
+            }
+            Ok(output)
+        }
+        EnvFormat::Json => {
+            let mut output = serde_json::to_string_pretty(fields)?;
+            output.push('\n');
+            Ok(output)
+        }
+    }
+}
+
+/// Is the string a valid POSIX environment variable name?
+fn is_env_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::assert_err;
+    use indexmap::indexmap;
+    use rstest::rstest;
+
+    fn fields() -> IndexMap<String, String> {
+        indexmap! {
+            "HOST".into() => "https://example.com".into(),
+            "greeting".into() => "it's \"fine\"\n$HOME".into(),
+        }
+    }
+
+    #[rstest]
+    #[case::shell(
+        EnvFormat::Shell,
+        "export HOST='https://example.com'\n\
+        export greeting='it'\\''s \"fine\"\n$HOME'\n"
+    )]
+    #[case::dotenv(
+        EnvFormat::Dotenv,
+        "HOST=\"https://example.com\"\n\
+        greeting=\"it's \\\"fine\\\"\\n$HOME\"\n"
+    )]
+    #[case::json(
+        EnvFormat::Json,
+        "{\n  \"HOST\": \"https://example.com\",\n  \
+        \"greeting\": \"it's \\\"fine\\\"\\n$HOME\"\n}\n"
+    )]
+    fn test_format_env(#[case] format: EnvFormat, #[case] expected: &str) {
+        assert_eq!(format_env(&fields(), format).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case::shell(EnvFormat::Shell)]
+    #[case::dotenv(EnvFormat::Dotenv)]
+    fn test_format_env_invalid_name(#[case] format: EnvFormat) {
+        let fields = indexmap! {"api-key".into() => "abc".into()};
+        assert_err!(
+            format_env(&fields, format),
+            "Field `api-key` is not a valid environment variable name"
+        );
+    }
+}
@@ -0,0 +1,261 @@
+use crate::{
+    cli::{
+        generate::trigger_disabled_error,
+        request::{validate_profile, BuildRequestCommand},
+        Subcommand,
+    },
+    collection::{Collection, CollectionFile, ProfileId, RecipeId},
+    db::Database,
+    http::{CodeLanguage, ResponseRecord},
+    GlobalArgs,
+};
+use anyhow::{bail, Context};
+use clap::{Parser, ValueEnum};
+use std::{
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+use tokio::fs;
+
+/// Export data from request history, or the collection and its requests in
+/// other formats
+#[derive(Clone, Debug, Parser)]
+pub struct ExportCommand {
+    #[command(subcommand)]
+    subcommand: ExportSubcommand,
+}
+
+#[derive(Clone, Debug, clap::Subcommand)]
+#[allow(clippy::large_enum_variant)]
+enum ExportSubcommand {
+    /// Write response bodies to files, one per recipe
+    ///
+    /// Each file is named after its recipe, with an extension based on the
+    /// response's Content-Type. Recipes that have never received a response
+    /// are skipped.
+    Responses {
+        /// Export the most recent response for each recipe. Currently this
+        /// is the only supported mode, so it's required
+        #[clap(long, required = true)]
+        latest: bool,
+
+        /// Directory to write files to. Created if it doesn't exist
+        #[clap(long)]
+        dir: PathBuf,
+
+        /// Profile to export responses for. If omitted, export responses for
+        /// requests with no profile
+        #[clap(long = "profile", short)]
+        profile: Option<ProfileId>,
+    },
+
+    /// Generate an OpenAPI 3 document from the collection's recipes
+    ///
+    /// This is best-effort: templates aren't rendered, so each `{{key}}`
+    /// becomes an OpenAPI placeholder. URLs that start with a key (e.g.
+    /// `{{host}}/users`) are split into a server and a path, and the server's
+    /// variables are filled in from profile data. Warnings for recipes that
+    /// can't be represented are printed to stderr.
+    #[command(name = "openapi")]
+    OpenApi {
+        /// Output format
+        #[clap(long, value_enum, default_value_t = OpenApiFormat::Yaml)]
+        format: OpenApiFormat,
+
+        /// Title of the API [default: name of the collection's directory]
+        #[clap(long)]
+        title: Option<String>,
+
+        /// File to write the document to [default: stdout]
+        #[clap(long, short)]
+        output: Option<PathBuf>,
+    },
+
+    /// Render a request and generate code that sends it
+    ///
+    /// The snippet uses a common HTTP library for the language: `requests` for
+    /// Python, `fetch` for JavaScript, and `net/http` for Go. Values are
+    /// rendered, so any credentials in the request end up in the code.
+    Code {
+        /// Language to generate
+        #[clap(long, value_enum)]
+        lang: CodeLang,
+
+        #[clap(flatten)]
+        build_request: BuildRequestCommand,
+
+        /// Execute triggered sub-requests. By default, if a request dependency
+        /// is triggered (e.g. if it is expired), an error will be thrown
+        /// instead
+        #[clap(long)]
+        execute_triggers: bool,
+    },
+}
+
+/// Language for `export code`
+#[derive(Copy, Clone, Debug, ValueEnum)]
+enum CodeLang {
+    Python,
+    Js,
+    Go,
+}
+
+impl From<CodeLang> for CodeLanguage {
+    fn from(lang: CodeLang) -> Self {
+        match lang {
+            CodeLang::Python => Self::Python,
+            CodeLang::Js => Self::JavaScript,
+            CodeLang::Go => Self::Go,
+        }
+    }
+}
+
+/// Output format for `export openapi`
+#[derive(Copy, Clone, Debug, ValueEnum)]
+enum OpenApiFormat {
+    Yaml,
+    Json,
+}
+
+impl Subcommand for ExportCommand {
+    async fn execute(self, global: GlobalArgs) -> anyhow::Result<ExitCode> {
+        match self.subcommand {
+            ExportSubcommand::Responses {
+                latest,
+                dir,
+                profile,
+            } => {
+                let (collection_path, collection) =
+                    load_collection(global).await?;
+                if !latest {
+                    bail!("No export mode specified; pass `--latest`");
+                }
+                let database =
+                    Database::load()?.into_collection(&collection_path)?;
+                if let Some(profile_id) = &profile {
+                    validate_profile(&collection, profile_id)?;
+                }
+                fs::create_dir_all(&dir).await.with_context(|| {
+                    format!("Error creating directory {dir:?}")
+                })?;
+
+                for recipe in collection
+                    .recipes
+                    .iter()
+                    .filter_map(|(_, node)| node.recipe())
+                {
+                    let Some(exchange) = database
+                        .get_latest_request(profile.as_ref(), &recipe.id)?
+                    else {
+                        continue;
+                    };
+                    let path =
+                        export_path(&dir, &recipe.id, &exchange.response);
+                    fs::write(&path, exchange.response.body.bytes())
+                        .await
+                        .with_context(|| format!("Error writing {path:?}"))?;
+                    println!("{}", path.display());
+                }
+            }
+            ExportSubcommand::OpenApi {
+                format,
+                title,
+                output,
+            } => {
+                let (collection_path, collection) =
+                    load_collection(global).await?;
+                let title = title.unwrap_or_else(|| {
+                    collection_path
+                        .parent()
+                        .and_then(Path::file_name)
+                        .map_or("API".to_owned(), |name| {
+                            name.to_string_lossy().into_owned()
+                        })
+                });
+                let (document, warnings) = collection.to_openapi(&title);
+                for warning in warnings {
+                    eprintln!("{warning}");
+                }
+                let content = match format {
+                    OpenApiFormat::Yaml => serde_yaml::to_string(&document)?,
+                    OpenApiFormat::Json => {
+                        serde_json::to_string_pretty(&document)? + "\n"
+                    }
+                };
+                match output {
+                    Some(path) => fs::write(&path, content)
+                        .await
+                        .with_context(|| format!("Error writing {path:?}"))?,
+                    None => print!("{content}"),
+                }
+            }
+            ExportSubcommand::Code {
+                lang,
+                build_request,
+                execute_triggers,
+            } => {
+                let (_, ticket) = build_request
+                    // User has to explicitly opt into executing triggered
+                    // requests
+                    .build_request(global, execute_triggers)
+                    .await
+                    .map_err(trigger_disabled_error)?;
+                print!("{}", ticket.record().to_code(lang.into())?);
+            }
+        }
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+/// Load the collection file, and return its path along with the collection
+async fn load_collection(
+    global: GlobalArgs,
+) -> anyhow::Result<(PathBuf, Collection)> {
+    let collection_path = CollectionFile::try_path(None, global.file)?;
+    let collection = CollectionFile::load(collection_path.clone())
+        .await?
+        .collection;
+    Ok((collection_path, collection))
+}
+
+/// Get the path to export a recipe's response to. If the response has no
+/// known content type, the file has no extension.
+fn export_path(
+    dir: &Path,
+    recipe_id: &RecipeId,
+    response: &ResponseRecord,
+) -> PathBuf {
+    // Don't use set_extension, because recipe IDs may contain a dot
+    match response.extension() {
+        Some(extension) => dir.join(format!("{recipe_id}.{extension}")),
+        None => dir.join(recipe_id.as_str()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{header_map, Factory};
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::json(Some("application/json"), "out/login.json")]
+    #[case::suffix(Some("application/problem+json"), "out/login.json")]
+    #[case::image(Some("image/png"), "out/login.png")]
+    #[case::none(None, "out/login")]
+    fn test_export_path(
+        #[case] content_type: Option<&str>,
+        #[case] expected: &str,
+    ) {
+        let response = ResponseRecord {
+            headers: header_map(
+                content_type.map(|content_type| ("content-type", content_type)),
+            ),
+            ..ResponseRecord::factory(())
+        };
+        assert_eq!(
+            export_path(Path::new("out"), &"login".into(), &response),
+            PathBuf::from(expected)
+        );
+    }
+}
@@ -0,0 +1,50 @@
+use crate::{
+    cli::Subcommand,
+    collection::{format_collection, CollectionFile},
+    GlobalArgs,
+};
+use anyhow::Context;
+use clap::Parser;
+use std::process::ExitCode;
+use tokio::fs;
+
+/// Format the collection file in place
+///
+/// Top-level fields are put in a consistent order, indentation is normalized
+/// to two spaces, and template escape sequences are normalized. Comments are
+/// preserved.
+#[derive(Clone, Debug, Parser)]
+pub struct FmtCommand {
+    /// Don't write anything; exit with an error if the file is not already
+    /// formatted. Useful for CI
+    #[clap(long)]
+    check: bool,
+}
+
+impl Subcommand for FmtCommand {
+    async fn execute(self, global: GlobalArgs) -> anyhow::Result<ExitCode> {
+        let path = CollectionFile::try_path(None, global.file)?;
+        // Load the collection first, so the user gets a helpful error for an
+        // invalid file instead of a YAML error from the formatter
+        CollectionFile::load(path.clone()).await?;
+
+        let input = fs::read_to_string(&path)
+            .await
+            .with_context(|| format!("Error reading {path:?}"))?;
+        let output = format_collection(&input)?;
+
+        if output == input {
+            println!("{} is already formatted", path.display());
+            Ok(ExitCode::SUCCESS)
+        } else if self.check {
+            eprintln!("{} is not formatted", path.display());
+            Ok(ExitCode::FAILURE)
+        } else {
+            fs::write(&path, output)
+                .await
+                .with_context(|| format!("Error writing {path:?}"))?;
+            println!("Formatted {}", path.display());
+            Ok(ExitCode::SUCCESS)
+        }
+    }
+}
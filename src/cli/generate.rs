@@ -32,19 +32,21 @@ impl Subcommand for GenerateCommand {
             // User has to explicitly opt into executing triggered requests
             .build_request(global, self.execute_triggers)
             .await
-            .map_err(|error| {
-                // If the build failed because triggered requests are disabled,
-                // replace it with a custom error message
-                if TemplateError::has_trigger_disabled_error(&error) {
-                    error.context(
-                        "Triggered requests are disabled by default; \
-                         pass `--execute-triggers` to enable",
-                    )
-                } else {
-                    error
-                }
-            })?;
+            .map_err(trigger_disabled_error)?;
         println!("{}", ticket.record().to_curl()?);
         Ok(ExitCode::SUCCESS)
     }
 }
+
+/// If a build failed because triggered requests are disabled, add a hint about
+/// `--execute-triggers` to the error
+pub fn trigger_disabled_error(error: anyhow::Error) -> anyhow::Error {
+    if TemplateError::has_trigger_disabled_error(&error) {
+        error.context(
+            "Triggered requests are disabled by default; \
+             pass `--execute-triggers` to enable",
+        )
+    } else {
+        error
+    }
+}
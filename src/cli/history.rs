@@ -6,11 +6,16 @@ use crate::{
     util::{format_duration, format_time, HeaderDisplay, MaybeStr},
     GlobalArgs,
 };
-use anyhow::anyhow;
+use anyhow::{anyhow, Context};
 use bytesize::ByteSize;
-use clap::Parser;
+use chrono::{DateTime, Utc};
+use clap::{Parser, ValueEnum};
 use dialoguer::console::Style;
-use std::process::ExitCode;
+use serde::Serialize;
+use std::{
+    io::{self, Write},
+    process::ExitCode,
+};
 use tracing::warn;
 
 /// View request collection history (unstable)
@@ -37,6 +42,65 @@ enum HistorySubcommand {
 
     /// Print an entire request/response by ID
     Get { request: RequestId },
+
+    /// List every profile selection made in the TUI, oldest first
+    Profiles,
+
+    /// Print a summary of every request in the collection's history, oldest
+    /// first, for analysis in a spreadsheet or other tools
+    Export {
+        /// Output format
+        #[clap(long, value_enum, default_value_t = ExportFormat::Csv)]
+        format: ExportFormat,
+
+        /// Only export requests for this recipe
+        #[clap(long)]
+        recipe: Option<RecipeId>,
+
+        /// Only export requests for this profile
+        #[clap(long = "profile", short)]
+        profile: Option<ProfileId>,
+    },
+}
+
+/// Output format for `history export`
+#[derive(Copy, Clone, Debug, ValueEnum)]
+enum ExportFormat {
+    /// Comma-separated values, with a header row
+    Csv,
+    /// JSON array of objects
+    Json,
+}
+
+/// One exported request. Field names double as the CSV column names
+#[derive(Debug, Serialize)]
+struct ExportRow {
+    id: RequestId,
+    recipe: RecipeId,
+    profile: Option<ProfileId>,
+    label: Option<String>,
+    status: u16,
+    start_time: DateTime<Utc>,
+    duration_ms: i64,
+    request_size: Option<u64>,
+    response_size: Option<u64>,
+}
+
+impl ExportRow {
+    fn new(recipe: RecipeId, exchange: ExchangeSummary) -> Self {
+        Self {
+            id: exchange.id,
+            recipe,
+            profile: exchange.profile_id,
+            label: exchange.label,
+            status: exchange.status.as_u16(),
+            start_time: exchange.start_time,
+            duration_ms: (exchange.end_time - exchange.start_time)
+                .num_milliseconds(),
+            request_size: exchange.request_size.map(|size| size.as_u64()),
+            response_size: exchange.response_size.map(|size| size.as_u64()),
+        }
+    }
 }
 
 impl Subcommand for HistoryCommand {
@@ -60,20 +124,80 @@ impl Subcommand for HistoryCommand {
                     .ok_or_else(|| anyhow!("Request `{request}` not found"))?;
                 Self::print_detail(exchange);
             }
+            HistorySubcommand::Export {
+                format,
+                recipe,
+                profile,
+            } => {
+                let rows = database
+                    .get_collection_requests()?
+                    .into_iter()
+                    .filter(|(recipe_id, exchange)| {
+                        recipe.as_ref().map_or(true, |r| r == recipe_id)
+                            && profile.as_ref().map_or(true, |p| {
+                                exchange.profile_id.as_ref() == Some(p)
+                            })
+                    })
+                    .map(|(recipe_id, exchange)| {
+                        ExportRow::new(recipe_id, exchange)
+                    })
+                    .collect::<Vec<_>>();
+                write_rows(&rows, format, io::stdout().lock())
+                    .context("Error writing history")?;
+            }
+            HistorySubcommand::Profiles => {
+                for (time, profile_id) in database.get_profile_history()? {
+                    println!(
+                        "{} {}",
+                        format_time(&time),
+                        profile_id.as_ref().map_or("<none>", |id| id.as_str())
+                    );
+                }
+            }
         }
         Ok(ExitCode::SUCCESS)
     }
 }
 
+/// Write exported rows in the given format
+fn write_rows(
+    rows: &[ExportRow],
+    format: ExportFormat,
+    mut writer: impl Write,
+) -> anyhow::Result<()> {
+    match format {
+        ExportFormat::Csv => {
+            // The header row is generated from the struct field names
+            let mut writer = csv::Writer::from_writer(writer);
+            for row in rows {
+                writer.serialize(row)?;
+            }
+            writer.flush()?;
+        }
+        ExportFormat::Json => {
+            serde_json::to_writer_pretty(&mut writer, rows)?;
+            writeln!(writer)?;
+        }
+    }
+    Ok(())
+}
+
 impl HistoryCommand {
     fn print_list(exchanges: Vec<ExchangeSummary>) {
         for exchange in exchanges {
-            println!(
+            print!(
                 "{} {} {}",
                 exchange.id,
                 exchange.status,
                 format_time(&exchange.start_time)
             );
+            if let Some(size) = exchange.response_size {
+                print!(" {}", size.to_string_as(false));
+            }
+            match &exchange.label {
+                Some(label) => println!(" {label}"),
+                None => println!(),
+            }
         }
     }
 
@@ -84,6 +208,9 @@ impl HistoryCommand {
         // Request
         let request = &exchange.request;
         println!("{}", header_style.apply_to("REQUEST"));
+        if let Some(label) = &request.label {
+            println!("{} {label}", subheader_style.apply_to("Label:"));
+        }
         println!("{} {}", subheader_style.apply_to("URL:"), request.url);
         println!("{} {}", subheader_style.apply_to("Method:"), request.method);
         print!(
@@ -91,6 +218,12 @@ impl HistoryCommand {
             subheader_style.apply_to("Headers"),
             HeaderDisplay(&request.headers)
         );
+        if !request.template_values.is_empty() {
+            println!("{}", subheader_style.apply_to("Template Values"));
+            for (key, value) in &request.template_values {
+                println!("{key}: {value}");
+            }
+        }
         if let Some(body) = &request.body {
             print!(
                 "{} ({})\n{}",
@@ -103,6 +236,14 @@ impl HistoryCommand {
 
         // Timing
         println!("{}", header_style.apply_to("METADATA"));
+        println!(
+            "{} {}",
+            subheader_style.apply_to("Profile:"),
+            request
+                .profile_id
+                .as_ref()
+                .map_or("<none>", |id| id.as_str())
+        );
         println!(
             "{} {}",
             subheader_style.apply_to("Start Time:"),
@@ -136,3 +277,40 @@ impl HistoryCommand {
         );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::Factory;
+    use chrono::TimeZone;
+    use reqwest::StatusCode;
+
+    #[test]
+    fn test_write_rows_csv() {
+        let start_time = Utc.with_ymd_and_hms(2024, 7, 1, 12, 0, 0).unwrap();
+        let exchange = ExchangeSummary {
+            id: RequestId::new(),
+            profile_id: None,
+            label: Some("Get \"user\", v2".into()),
+            start_time,
+            end_time: start_time + chrono::Duration::milliseconds(153),
+            status: StatusCode::NOT_FOUND,
+            request_size: None,
+            response_size: Some(ByteSize(42)),
+        };
+        let id = exchange.id;
+        let rows = [ExportRow::new(RecipeId::factory(()), exchange)];
+        let mut output = Vec::new();
+        write_rows(&rows, ExportFormat::Csv, &mut output).unwrap();
+        let recipe_id = &rows[0].recipe;
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            format!(
+                "id,recipe,profile,label,status,start_time,duration_ms,\
+                request_size,response_size\n\
+                {id},{recipe_id},,\"Get \"\"user\"\", v2\",404,\
+                2024-07-01T12:00:00Z,153,,42\n"
+            )
+        );
+    }
+}
@@ -17,6 +17,10 @@ pub struct ImportCommand {
     input_file: PathBuf,
     /// Destination for the new slumber collection file [default: stdout]
     output_file: Option<PathBuf>,
+    /// Write a YAML report of the import (items created, skipped, and
+    /// downgraded) to this file. A summary is always printed to stderr
+    #[clap(long)]
+    report: Option<PathBuf>,
 }
 
 #[derive(Copy, Clone, Debug, ValueEnum)]
@@ -27,7 +31,8 @@ enum Format {
 impl Subcommand for ImportCommand {
     async fn execute(self, _global: GlobalArgs) -> anyhow::Result<ExitCode> {
         // Load the input
-        let collection = match self.format {
+        eprintln!("Importing {:?}...", self.input_file);
+        let (collection, report) = match self.format {
             Format::Insomnia => Collection::from_insomnia(&self.input_file)?,
         };
 
@@ -48,6 +53,16 @@ impl Subcommand for ImportCommand {
         };
         serde_yaml::to_writer(&mut writer, &collection)?;
 
+        // Summarize to stderr so it doesn't pollute the output collection if
+        // that's going to stdout
+        eprint!("{report}");
+        if let Some(report_file) = self.report {
+            let file = File::create(&report_file).context(format!(
+                "Error opening import report file {report_file:?}"
+            ))?;
+            serde_yaml::to_writer(file, &report)?;
+        }
+
         Ok(ExitCode::SUCCESS)
     }
 }
@@ -0,0 +1,129 @@
+use crate::{
+    cli::{recipes::ListFormat, Subcommand},
+    collection::{
+        referenced_fields, CollectionFile, NewProfile, Profile, ProfileId,
+    },
+    template::Template,
+    GlobalArgs,
+};
+use anyhow::Context;
+use clap::Parser;
+use dialoguer::Input;
+use indexmap::IndexMap;
+use serde::Serialize;
+use std::{
+    fmt::{self, Display},
+    process::ExitCode,
+};
+
+/// Manage profiles in the collection file
+#[derive(Clone, Debug, Parser)]
+pub struct ProfilesCommand {
+    #[command(subcommand)]
+    subcommand: ProfilesSubcommand,
+}
+
+#[derive(Clone, Debug, clap::Subcommand)]
+enum ProfilesSubcommand {
+    /// List all profiles in the collection
+    ///
+    /// The default output is one profile per line with tab-separated columns
+    /// (ID, name), which is easy to pipe into tools like `fzf` or `cut`.
+    #[command(visible_alias = "ls")]
+    List {
+        /// Output format
+        #[clap(long, value_enum, default_value_t = ListFormat::Table)]
+        format: ListFormat,
+    },
+    /// Create a new profile, prompting for every field the collection uses
+    ///
+    /// Every template in the collection file is scanned for fields (e.g.
+    /// `{{host}}`), and you're prompted for a value for each one. The new
+    /// profile is added to the end of the collection's `profiles`. Values
+    /// can themselves be templates.
+    Add {
+        /// ID of the new profile
+        id: ProfileId,
+        /// Descriptive name for the profile
+        #[clap(long)]
+        name: Option<String>,
+    },
+}
+
+impl Subcommand for ProfilesCommand {
+    async fn execute(self, global: GlobalArgs) -> anyhow::Result<ExitCode> {
+        let collection_path = CollectionFile::try_path(None, global.file)?;
+        let collection_file = CollectionFile::load(collection_path).await?;
+        match self.subcommand {
+            ProfilesSubcommand::List { format } => {
+                let profiles: Vec<_> = collection_file
+                    .collection
+                    .profiles
+                    .values()
+                    .map(ProfileSummary::from)
+                    .collect();
+                format.print(&profiles)?;
+            }
+            ProfilesSubcommand::Add { id, name } => {
+                let path = collection_file.path();
+                let input = tokio::fs::read_to_string(path)
+                    .await
+                    .with_context(|| format!("Error reading {path:?}"))?;
+                let fields = referenced_fields(&input)?;
+                if fields.is_empty() {
+                    println!("No fields found in the collection");
+                }
+
+                let data = fields
+                    .into_iter()
+                    .map(|field| {
+                        let value: String = Input::new()
+                            .with_prompt(&field)
+                            .allow_empty(true)
+                            .validate_with(|value: &String| {
+                                value
+                                    .parse::<Template>()
+                                    .map(|_| ())
+                                    .map_err(|error| error.to_string())
+                            })
+                            .interact()
+                            .with_context(|| {
+                                format!("Error reading value for `{field}`")
+                            })?;
+                        // Validated above
+                        let value = value.parse::<Template>()?;
+                        anyhow::Ok((field, value))
+                    })
+                    .collect::<anyhow::Result<IndexMap<_, _>>>()?;
+
+                collection_file
+                    .add_profile(id.clone(), NewProfile { name, data })
+                    .await?;
+                println!("Added profile `{id}`");
+            }
+        }
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+/// Listing entry for a single profile
+#[derive(Debug, Serialize)]
+struct ProfileSummary {
+    id: ProfileId,
+    name: String,
+}
+
+impl From<&Profile> for ProfileSummary {
+    fn from(profile: &Profile) -> Self {
+        Self {
+            id: profile.id.clone(),
+            name: profile.name().to_owned(),
+        }
+    }
+}
+
+impl Display for ProfileSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}\t{}", self.id, self.name)
+    }
+}
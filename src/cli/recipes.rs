@@ -0,0 +1,177 @@
+use crate::{
+    cli::Subcommand,
+    collection::{Collection, CollectionFile, Method, RecipeId},
+    GlobalArgs,
+};
+use clap::{Parser, ValueEnum};
+use serde::Serialize;
+use std::{
+    fmt::{self, Display},
+    process::ExitCode,
+};
+
+/// View recipes in the collection file
+#[derive(Clone, Debug, Parser)]
+pub struct RecipesCommand {
+    #[command(subcommand)]
+    subcommand: RecipesSubcommand,
+}
+
+#[derive(Clone, Debug, clap::Subcommand)]
+enum RecipesSubcommand {
+    /// List all recipes in the collection
+    ///
+    /// Recipes in folders are included, in the same order as the collection
+    /// file. The default output is one recipe per line with tab-separated
+    /// columns (ID, name, method, URL), which is easy to pipe into tools like
+    /// `fzf` or `cut`.
+    #[command(visible_alias = "ls")]
+    List {
+        /// Output format
+        #[clap(long, value_enum, default_value_t = ListFormat::Table)]
+        format: ListFormat,
+    },
+}
+
+/// Output format for `list` subcommands
+#[derive(Copy, Clone, Debug, ValueEnum)]
+pub enum ListFormat {
+    /// One item per line, with tab-separated columns
+    Table,
+    /// JSON array of objects
+    Json,
+}
+
+impl ListFormat {
+    /// Print a list of items in this format
+    pub fn print<T: Display + Serialize>(
+        self,
+        items: &[T],
+    ) -> anyhow::Result<()> {
+        match self {
+            ListFormat::Table => {
+                for item in items {
+                    println!("{item}");
+                }
+            }
+            ListFormat::Json => {
+                println!("{}", serde_json::to_string_pretty(items)?)
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Subcommand for RecipesCommand {
+    async fn execute(self, global: GlobalArgs) -> anyhow::Result<ExitCode> {
+        let collection_path = CollectionFile::try_path(None, global.file)?;
+        let collection_file = CollectionFile::load(collection_path).await?;
+        match self.subcommand {
+            RecipesSubcommand::List { format } => format
+                .print(&RecipeSummary::all(&collection_file.collection))?,
+        }
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+/// Listing entry for a single recipe. The URL is the raw template, since
+/// rendering it may require a profile or trigger chains.
+#[derive(Debug, Serialize)]
+#[cfg_attr(test, derive(PartialEq))]
+struct RecipeSummary {
+    id: RecipeId,
+    name: String,
+    method: Method,
+    url: String,
+}
+
+impl RecipeSummary {
+    /// Get a summary of every recipe in the collection, in tree order
+    fn all(collection: &Collection) -> Vec<Self> {
+        collection
+            .recipes
+            .iter()
+            .filter_map(|(_, node)| node.recipe())
+            .map(|recipe| Self {
+                id: recipe.id.clone(),
+                name: recipe.name().to_owned(),
+                method: recipe.method,
+                url: recipe.url.to_string(),
+            })
+            .collect()
+    }
+}
+
+impl Display for RecipeSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}\t{}\t{}\t{}",
+            self.id, self.name, self.method, self.url
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        collection::{Folder, Recipe, RecipeNode},
+        test_util::{by_id, Factory},
+    };
+    use pretty_assertions::assert_eq;
+
+    /// Recipes in folders should be flattened into the list, and folders
+    /// themselves should be excluded
+    #[test]
+    fn test_recipe_summary() {
+        let collection = Collection {
+            recipes: by_id::<RecipeNode>([
+                Recipe {
+                    id: "r1".into(),
+                    name: Some("Get Users".into()),
+                    url: "{{host}}/users".into(),
+                    ..Recipe::factory(())
+                }
+                .into(),
+                Folder {
+                    id: "f1".into(),
+                    children: by_id([Recipe {
+                        id: "r2".into(),
+                        method: Method::Post,
+                        url: "{{host}}/login".into(),
+                        ..Recipe::factory(())
+                    }
+                    .into()]),
+                    ..Folder::factory(())
+                }
+                .into(),
+            ])
+            .into(),
+            ..Collection::factory(())
+        };
+
+        let summaries = RecipeSummary::all(&collection);
+        assert_eq!(
+            summaries,
+            vec![
+                RecipeSummary {
+                    id: "r1".into(),
+                    name: "Get Users".into(),
+                    method: Method::Get,
+                    url: "{{host}}/users".into(),
+                },
+                RecipeSummary {
+                    id: "r2".into(),
+                    name: "r2".into(),
+                    method: Method::Post,
+                    url: "{{host}}/login".into(),
+                },
+            ]
+        );
+        assert_eq!(
+            summaries[0].to_string(),
+            "r1\tGet Users\tGET\t{{host}}/users"
+        );
+    }
+}
@@ -0,0 +1,511 @@
+use crate::{
+    cli::{
+        curl_proxy::{read_request, ApiRequest},
+        Subcommand,
+    },
+    collection::{insert_recipe, CollectionFile, NewRecipe, RecipeId},
+    config::Config,
+    db::{CollectionDatabase, Database},
+    http::{
+        decompress_response, Exchange, RequestId, RequestRecord, ResponseRecord,
+    },
+    GlobalArgs,
+};
+use anyhow::{anyhow, Context};
+use bytes::Bytes;
+use chrono::Utc;
+use clap::Parser;
+use indexmap::IndexMap;
+use itertools::Itertools;
+use reqwest::{
+    header::{HeaderMap, HeaderName},
+    redirect, Client, Method, StatusCode, Url,
+};
+use std::{
+    collections::HashSet,
+    net::SocketAddr,
+    ops::Deref,
+    process::ExitCode,
+    sync::{Arc, Mutex},
+};
+use tokio::{
+    io::{AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+    signal,
+};
+use tracing::{error, info};
+
+/// Maximum size of a request body that can be proxied
+const MAX_BODY_SIZE: usize = 10 * 1024 * 1024;
+
+/// Record traffic to an API through a reverse proxy
+///
+/// Every request sent to the listen address is forwarded to the target, and
+/// the response is passed back to the client. Each exchange is saved to
+/// request history under a recipe ID generated from its method and path, e.g.
+/// `GET /users` is recorded as `get_users`. Point your app at the proxy to
+/// bootstrap a collection from its real traffic.
+///
+/// Request bodies must have a `Content-Length`; chunked uploads aren't
+/// supported. Stop the proxy with Ctrl-C.
+#[derive(Clone, Debug, Parser)]
+pub struct RecordCommand {
+    /// Address to listen on. `:8080` is shorthand for `127.0.0.1:8080`
+    #[clap(long, value_parser = parse_listen_address)]
+    listen: SocketAddr,
+
+    /// Base URL to forward requests to. The path of each request is appended
+    /// to it
+    #[clap(long)]
+    target: Url,
+
+    /// When the proxy is stopped, add a recipe to the collection file for
+    /// each method and path that was recorded. Paths that already have a
+    /// recipe are skipped
+    #[clap(long)]
+    generate: bool,
+}
+
+impl Subcommand for RecordCommand {
+    async fn execute(self, global: GlobalArgs) -> anyhow::Result<ExitCode> {
+        let collection_path = CollectionFile::try_path(None, global.file)?;
+        let config = Config::load(Some(&collection_path))?;
+        let database = Database::load()?
+            .into_collection(&collection_path)?
+            .with_retention(config.history.clone());
+        let collection_file = CollectionFile::load(collection_path).await?;
+        let recorder = Arc::new(Recorder::new(
+            self.target,
+            database,
+            collection_file
+                .collection
+                .recipes
+                .recipe_ids()
+                .cloned()
+                .collect(),
+        )?);
+
+        let listener = TcpListener::bind(self.listen)
+            .await
+            .with_context(|| format!("Error binding to {}", self.listen))?;
+        eprintln!("Forwarding http://{} to {}", self.listen, recorder.target);
+
+        let shutdown = signal::ctrl_c();
+        tokio::pin!(shutdown);
+        loop {
+            let (stream, address) = tokio::select! {
+                result = listener.accept() => {
+                    result.context("Error accepting connection")?
+                }
+                result = &mut shutdown => {
+                    result.context("Error listening for Ctrl-C")?;
+                    break;
+                }
+            };
+            info!(%address, "Accepted connection");
+            let recorder = Arc::clone(&recorder);
+            tokio::spawn(async move {
+                if let Err(error) = handle_connection(&recorder, stream).await {
+                    error!(error = error.deref(), "Error handling connection");
+                }
+            });
+        }
+
+        if self.generate {
+            recorder.generate(&collection_file).await?;
+        }
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+/// Forwards requests to the target and records each exchange
+struct Recorder {
+    client: Client,
+    target: Url,
+    database: CollectionDatabase,
+    /// IDs of recipes that were already in the collection when we started
+    existing: HashSet<RecipeId>,
+    /// The first request seen for each recipe that isn't in the collection
+    /// yet, along with its name. These are the recipes we can generate
+    observed: Mutex<IndexMap<RecipeId, (String, Arc<RequestRecord>)>>,
+}
+
+/// Status, headers, and body to send back to the client
+type ProxyResponse = (StatusCode, HeaderMap, Bytes);
+
+impl Recorder {
+    fn new(
+        target: Url,
+        database: CollectionDatabase,
+        existing: HashSet<RecipeId>,
+    ) -> anyhow::Result<Self> {
+        // Redirects are the client's business, so pass them through
+        let client = Client::builder()
+            .redirect(redirect::Policy::none())
+            .build()
+            .context("Error building HTTP client")?;
+        Ok(Self {
+            client,
+            target,
+            database,
+            existing,
+            observed: Default::default(),
+        })
+    }
+
+    /// Send a request to the target, and record the exchange in history
+    async fn forward(
+        &self,
+        request: ApiRequest,
+    ) -> anyhow::Result<ProxyResponse> {
+        let method = Method::from_bytes(request.method.as_bytes())
+            .with_context(|| format!("Invalid method `{}`", request.method))?;
+        let url = target_url(&self.target, &request.url);
+        let mut headers = request.headers;
+        remove_hop_by_hop(&mut headers);
+        let body = Bytes::from(request.body);
+
+        let start_time = Utc::now();
+        let response = self
+            .client
+            .request(method.clone(), url.clone())
+            .headers(headers.clone())
+            .body(body.clone())
+            .send()
+            .await
+            .with_context(|| format!("Error forwarding request to {url}"))?;
+        let status = response.status();
+        let version = response.version();
+        let mut response_headers = response.headers().clone();
+        let response_body = response
+            .bytes()
+            .await
+            .with_context(|| format!("Error reading response from {url}"))?;
+        let end_time = Utc::now();
+
+        let name = format!("{method} {}", request.url.path());
+        let recipe_id: RecipeId = NewRecipe::base_id(&name).into();
+        let record = Arc::new(RequestRecord {
+            id: RequestId::new(),
+            profile_id: None,
+            recipe_id: recipe_id.clone(),
+            method,
+            url,
+            headers,
+            body: (!body.is_empty()).then_some(body),
+            template_values: IndexMap::new(),
+            label: None,
+        });
+        self.observe(name, &record);
+        // History gets the decoded body, while the client gets the response
+        // exactly as the server sent it
+        let mut recorded_headers = response_headers.clone();
+        let recorded_body =
+            decompress_response(&mut recorded_headers, response_body.clone());
+        let exchange = Exchange {
+            id: record.id,
+            request: record,
+            response: ResponseRecord {
+                status,
+                version,
+                headers: recorded_headers,
+                body: recorded_body.into(),
+                redirects: Vec::new(),
+            }
+            .into(),
+            start_time,
+            end_time,
+        };
+        info!(%recipe_id, %status, "Recorded exchange");
+        if let Err(error) = self.database.insert_exchange(&exchange) {
+            error!(error = error.deref(), "Error recording exchange");
+        }
+
+        remove_hop_by_hop(&mut response_headers);
+        Ok((status, response_headers, response_body))
+    }
+
+    /// Remember the first request for each recipe that doesn't exist yet, so
+    /// it can be generated later
+    fn observe(&self, name: String, record: &Arc<RequestRecord>) {
+        if self.existing.contains(&record.recipe_id) {
+            return;
+        }
+        self.observed
+            .lock()
+            .expect("Recorder lock poisoned")
+            .entry(record.recipe_id.clone())
+            .or_insert_with(|| (name, Arc::clone(record)));
+    }
+
+    /// Add a recipe to the collection file for each observed request
+    async fn generate(
+        &self,
+        collection_file: &CollectionFile,
+    ) -> anyhow::Result<()> {
+        let observed = std::mem::take(
+            &mut *self.observed.lock().expect("Recorder lock poisoned"),
+        );
+        if observed.is_empty() {
+            eprintln!("No new recipes to generate");
+            return Ok(());
+        }
+
+        let path = collection_file.path();
+        let mut contents = tokio::fs::read_to_string(path)
+            .await
+            .with_context(|| format!("Error reading {path:?}"))?;
+        for (id, (name, record)) in observed {
+            let recipe = match NewRecipe::from_record(&record, Some(name)) {
+                Ok(recipe) => recipe,
+                Err(error) => {
+                    eprintln!("Skipping recipe `{id}`: {error}");
+                    continue;
+                }
+            };
+            contents = insert_recipe(&contents, &id, &recipe)
+                .with_context(|| format!("Error adding recipe `{id}`"))?;
+            eprintln!("Added recipe `{id}`");
+        }
+        tokio::fs::write(path, contents)
+            .await
+            .with_context(|| format!("Error writing {path:?}"))?;
+        Ok(())
+    }
+}
+
+/// Read a single request from the connection, forward it, and write the
+/// response. Connections are closed after each request.
+async fn handle_connection(
+    recorder: &Recorder,
+    stream: TcpStream,
+) -> anyhow::Result<()> {
+    let mut stream = BufReader::new(stream);
+    let (status, headers, body) =
+        match read_request(&mut stream, MAX_BODY_SIZE).await {
+            Ok(request) => {
+                info!(method = %request.method, url = %request.url, "Request");
+                recorder.forward(request).await.unwrap_or_else(|error| {
+                    error!(error = error.deref(), "Error forwarding request");
+                    error_response(StatusCode::BAD_GATEWAY, &error)
+                })
+            }
+            Err(error) => error_response(StatusCode::BAD_REQUEST, &error),
+        };
+
+    let mut head = format!("HTTP/1.1 {status}\r\n").into_bytes();
+    for (name, value) in &headers {
+        // Header values aren't necessarily UTF-8, so write the bytes as-is
+        head.extend_from_slice(name.as_str().as_bytes());
+        head.extend_from_slice(b": ");
+        head.extend_from_slice(value.as_bytes());
+        head.extend_from_slice(b"\r\n");
+    }
+    head.extend_from_slice(
+        format!(
+            "Content-Length: {}\r\nConnection: close\r\n\r\n",
+            body.len()
+        )
+        .as_bytes(),
+    );
+    let stream = stream.get_mut();
+    stream.write_all(&head).await?;
+    stream.write_all(&body).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+/// Build a plain text response for an error from the proxy itself
+fn error_response(status: StatusCode, error: &anyhow::Error) -> ProxyResponse {
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        reqwest::header::CONTENT_TYPE,
+        "text/plain".parse().expect("Valid header value"),
+    );
+    (status, headers, error.chain().join(": ").into())
+}
+
+/// Parse a listen address. A bare `:port` listens on localhost
+fn parse_listen_address(address: &str) -> anyhow::Result<SocketAddr> {
+    let address = match address.strip_prefix(':') {
+        Some(port) => format!("127.0.0.1:{port}"),
+        None => address.to_owned(),
+    };
+    address
+        .parse()
+        .map_err(|_| anyhow!("Invalid address `{address}`"))
+}
+
+/// Get the URL to forward a request to. The request's path is appended to the
+/// target's path, so a target can point at a sub-path of the API
+fn target_url(target: &Url, request: &Url) -> Url {
+    let mut url = target.clone();
+    let path = format!(
+        "{}/{}",
+        target.path().trim_end_matches('/'),
+        request.path().trim_start_matches('/')
+    );
+    url.set_path(&path);
+    url.set_query(request.query());
+    url
+}
+
+/// Remove headers that apply to a single connection, rather than to the
+/// request or response as a whole. These are replaced by whatever the next
+/// hop needs. `Content-Length` is included because it's recalculated from the
+/// forwarded body.
+fn remove_hop_by_hop(headers: &mut HeaderMap) {
+    const HOP_BY_HOP: &[&str] = &[
+        "connection",
+        "content-length",
+        "host",
+        "keep-alive",
+        "proxy-authenticate",
+        "proxy-authorization",
+        "te",
+        "trailer",
+        "transfer-encoding",
+        "upgrade",
+    ];
+    for name in HOP_BY_HOP {
+        headers.remove(HeaderName::from_static(name));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{assert_err, header_map, Factory};
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::port_only(":8080", "127.0.0.1:8080")]
+    #[case::full("0.0.0.0:3000", "0.0.0.0:3000")]
+    fn test_parse_listen_address(#[case] input: &str, #[case] expected: &str) {
+        assert_eq!(
+            parse_listen_address(input).unwrap(),
+            expected.parse::<SocketAddr>().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_listen_address_error() {
+        assert_err!(parse_listen_address("8080"), "Invalid address `8080`");
+    }
+
+    #[rstest]
+    #[case::root(
+        "https://api.example.com",
+        "/users?page=2",
+        "https://api.example.com/users?page=2"
+    )]
+    #[case::sub_path(
+        "https://api.example.com/v1/",
+        "/users",
+        "https://api.example.com/v1/users"
+    )]
+    #[case::drop_query(
+        "https://api.example.com?key=1",
+        "/users",
+        "https://api.example.com/users"
+    )]
+    fn test_target_url(
+        #[case] target: &str,
+        #[case] request: &str,
+        #[case] expected: &str,
+    ) {
+        let request = Url::parse("http://localhost").unwrap().join(request);
+        assert_eq!(
+            target_url(&target.parse().unwrap(), &request.unwrap()).as_str(),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_remove_hop_by_hop() {
+        let mut headers = header_map([
+            ("host", "localhost:8080"),
+            ("connection", "keep-alive"),
+            ("content-length", "2"),
+            ("content-type", "application/json"),
+        ]);
+        remove_hop_by_hop(&mut headers);
+        assert_eq!(headers, header_map([("content-type", "application/json")]));
+    }
+
+    /// Forwarded requests are recorded in history, and remembered so they can
+    /// be generated as recipes, unless the recipe already exists
+    #[tokio::test]
+    async fn test_forward() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/api/users?admin=true")
+            .match_header("x-test", "yes")
+            .match_body("{}")
+            .with_status(201)
+            .with_body("created")
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/api/health")
+            .with_status(200)
+            .create_async()
+            .await;
+
+        let database = CollectionDatabase::factory(());
+        let recorder = Recorder::new(
+            format!("{}/api", server.url()).parse().unwrap(),
+            database.clone(),
+            HashSet::from(["get_health".into()]),
+        )
+        .unwrap();
+
+        let request = |method: &str, path: &str, body: &str| ApiRequest {
+            method: method.to_owned(),
+            url: Url::parse("http://localhost:8080")
+                .unwrap()
+                .join(path)
+                .unwrap(),
+            headers: header_map([
+                ("host", "localhost:8080"),
+                ("x-test", "yes"),
+            ]),
+            body: body.into(),
+        };
+        let (status, _, body) = recorder
+            .forward(request("POST", "/users?admin=true", "{}"))
+            .await
+            .unwrap();
+        mock.assert_async().await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(body, Bytes::from("created"));
+        recorder
+            .forward(request("GET", "/health", ""))
+            .await
+            .unwrap();
+
+        let exchange = database
+            .get_latest_request(None, &"post_users".into())
+            .unwrap()
+            .expect("Exchange should be recorded");
+        assert_eq!(
+            exchange.request.url.as_str(),
+            format!("{}/api/users?admin=true", server.url())
+        );
+        assert_eq!(exchange.response.body.bytes(), b"created");
+        assert!(database
+            .get_latest_request(None, &"get_health".into())
+            .unwrap()
+            .is_some());
+
+        let observed = recorder.observed.lock().unwrap();
+        assert_eq!(
+            observed
+                .iter()
+                .map(|(id, (name, _))| (id.as_str(), name.as_str()))
+                .collect_vec(),
+            vec![("post_users", "POST /users")]
+        );
+    }
+}
@@ -0,0 +1,127 @@
+use crate::{
+    cli::{
+        request::{parse_key_val, select_profile, CliPrompter},
+        Subcommand,
+    },
+    collection::{CollectionFile, ProfileId},
+    config::Config,
+    db::Database,
+    http::HttpEngine,
+    template::{Template, TemplateContext, TemplateError},
+    GlobalArgs,
+};
+use anyhow::Context;
+use clap::Parser;
+use itertools::Itertools;
+use std::{
+    io::{self, Write},
+    process::ExitCode,
+};
+
+/// Render a template string against the collection and print the result
+///
+/// This is useful for testing chains and profile values, without having to
+/// build an entire request.
+#[derive(Clone, Debug, Parser)]
+pub struct RenderCommand {
+    /// Template to render, e.g. `{{host}}/users/{{chains.user_id}}`
+    template: Template,
+
+    /// ID of the profile to pull template values from. If omitted, use the
+    /// config's `default_profile`, if the collection defines it
+    #[clap(long = "profile", short, env = "SLUMBER_PROFILE")]
+    profile: Option<ProfileId>,
+
+    /// List of key=value template field overrides
+    #[clap(
+        long = "override",
+        short = 'o',
+        value_parser = parse_key_val::<String, String>,
+    )]
+    overrides: Vec<(String, String)>,
+
+    /// Execute triggered sub-requests. By default, if a request dependency is
+    /// triggered (e.g. if it is expired), an error will be thrown instead
+    #[clap(long)]
+    execute_triggers: bool,
+
+    /// Print where each template field comes from to stderr, including
+    /// values that are shadowed by a higher-precedence source
+    #[clap(long)]
+    explain: bool,
+}
+
+impl Subcommand for RenderCommand {
+    async fn execute(self, global: GlobalArgs) -> anyhow::Result<ExitCode> {
+        let collection_path = CollectionFile::try_path(None, global.file)?;
+        let config = Config::load(Some(&collection_path))?;
+        let database = Database::load()?
+            .into_collection(&collection_path)?
+            .with_retention(config.history.clone());
+        let collection =
+            CollectionFile::load(collection_path).await?.collection;
+
+        let profile =
+            select_profile(&collection, &config, self.profile.clone())?;
+
+        let context = TemplateContext {
+            overrides: self.overrides.into_iter().collect(),
+            ..TemplateContext::new(
+                collection,
+                profile,
+                // User has to explicitly opt into executing triggered requests
+                self.execute_triggers.then(|| HttpEngine::new(&config)),
+                database,
+                Box::new(CliPrompter),
+                &config,
+            )
+        };
+        if self.explain {
+            explain(&context);
+        }
+
+        let rendered = self
+            .template
+            .render(&context)
+            .await
+            .map_err(anyhow::Error::from)
+            .map_err(|error| {
+                // If the render failed because triggered requests are
+                // disabled, replace it with a custom error message
+                if TemplateError::has_trigger_disabled_error(&error) {
+                    error.context(
+                        "Triggered requests are disabled by default; \
+                         pass `--execute-triggers` to enable",
+                    )
+                } else {
+                    error
+                }
+            })?;
+
+        // Rendered value may not be UTF-8 (e.g. loaded from a binary file), so
+        // write the raw bytes
+        io::stdout()
+            .write_all(&rendered)
+            .context("Error writing to stdout")?;
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+/// Print the source of every field, in precedence order
+fn explain(context: &TemplateContext) {
+    eprintln!("Field precedence: {}", context.field_precedence);
+    for field in context.field_names() {
+        let sources = context.field_sources(field);
+        let Some((source, shadowed)) = sources.split_first() else {
+            continue;
+        };
+        if shadowed.is_empty() {
+            eprintln!("  {field}: {source}");
+        } else {
+            eprintln!(
+                "  {field}: {source} (shadows {})",
+                shadowed.iter().format(", ")
+            );
+        }
+    }
+}
@@ -1,33 +1,56 @@
 use crate::{
     cli::Subcommand,
-    collection::{CollectionFile, ProfileId, RecipeId},
+    collection::{
+        Collection, CollectionFile, HttpVersion, Method, ProfileId, Recipe,
+        RecipeBody, RecipeId, RecipeTree, RedirectOptions,
+    },
     config::Config,
     db::{CollectionDatabase, Database},
-    http::{BuildOptions, HttpEngine, RequestSeed, RequestTicket},
-    template::{Prompt, Prompter, TemplateContext, TemplateError},
-    util::{HeaderDisplay, ResultExt},
+    http::{
+        BuildOptions, Exchange, HostNotAllowedError, HttpEngine, RequestId,
+        RequestSeed, RequestTicket, ResponseBody,
+    },
+    template::{Prompt, Prompter, Template, TemplateContext, TemplateError},
+    util::{format_duration, wildcard_match, HeaderDisplay, ResultExt},
     GlobalArgs,
 };
-use anyhow::{anyhow, Context};
-use clap::Parser;
+use anyhow::{anyhow, bail, Context};
+use base64::{prelude::BASE64_STANDARD, Engine};
+use chrono::{DateTime, Duration, Utc};
+use clap::{ArgGroup, Parser, ValueEnum};
 use dialoguer::{Input, Password};
+use futures::{stream, StreamExt};
 use indexmap::IndexMap;
 use itertools::Itertools;
+use reqwest::{header::HeaderMap, StatusCode};
+use serde::Serialize;
 use std::{
+    borrow::Cow,
+    collections::BTreeMap,
     error::Error,
+    fmt::{self, Display},
     io::{self, Write},
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
     process::ExitCode,
     str::FromStr,
+    sync::Arc,
+    time::Instant,
 };
-use tracing::warn;
+use tokio::fs;
 
 /// Exit code to return when `exit_status` flag is set and the HTTP response has
 /// an error status code
-const HTTP_ERROR_EXIT_CODE: u8 = 2;
+pub(super) const HTTP_ERROR_EXIT_CODE: u8 = 2;
+
+/// Recipe ID that one-off requests built with `--url` are stored under in
+/// history
+const ADHOC_RECIPE_ID: &str = "_adhoc";
 
 /// Execute a single request, and print its response
 #[derive(Clone, Debug, Parser)]
 #[clap(aliases=&["req", "rq"])]
+#[clap(group = ArgGroup::new("batch").args(["repeat", "iterate", "all"]))]
 pub struct RequestCommand {
     #[clap(flatten)]
     build_request: BuildRequestCommand,
@@ -40,19 +63,103 @@ pub struct RequestCommand {
     #[clap(long)]
     headers: bool,
 
+    /// Print the response status line, including the negotiated HTTP
+    /// version, as well as all headers. Implies `--headers`
+    #[clap(long, short)]
+    verbose: bool,
+
     /// Do not print HTTP response body
     #[clap(long)]
     no_body: bool,
 
     /// Set process exit code based on HTTP response status. If the status is
     /// <400, exit code is 0. If it's >=400, exit code is 2.
-    #[clap(long)]
+    #[clap(long, conflicts_with_all = ["fail", "fail_with_body"])]
     exit_status: bool,
 
+    /// Fail on HTTP error statuses, similar to `curl --fail`. If the status
+    /// is 4xx, exit code is 4. If it's 5xx, exit code is 5. The response body
+    /// is not printed for error statuses.
+    #[clap(long, conflicts_with = "fail_with_body")]
+    fail: bool,
+
+    /// Same as `--fail`, but print the response body for error statuses too
+    #[clap(long)]
+    fail_with_body: bool,
+
     /// Just print the generated request, instead of sending it. Triggered
     /// sub-requests will also not be executed.
     #[clap(long)]
     dry_run: bool,
+
+    /// Print the response in a structured format, to stdout. Useful for
+    /// consuming responses from scripts and other tools
+    #[clap(
+        long,
+        value_enum,
+        conflicts_with_all = ["status", "headers", "verbose", "no_body"],
+    )]
+    format: Option<OutputFormat>,
+
+    /// Send the request this many times and print latency statistics,
+    /// instead of the response. The request is re-rendered for each
+    /// iteration, so dynamic values vary. These requests are not stored in
+    /// history.
+    #[clap(
+        long,
+        conflicts_with_all = [
+            "status", "headers", "verbose", "no_body", "fail",
+            "fail_with_body", "dry_run", "format",
+        ],
+    )]
+    repeat: Option<NonZeroUsize>,
+
+    /// Send one request per row of a data file, and print a summary of the
+    /// results. Each row's values are used as template field overrides, and
+    /// take precedence over `--override`. The file can be CSV (`.csv`, with a
+    /// header row) or JSON Lines (`.jsonl`, one object per line). These
+    /// requests are stored in history.
+    #[clap(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = [
+            "status", "headers", "verbose", "no_body", "fail",
+            "fail_with_body", "dry_run", "format",
+        ],
+    )]
+    iterate: Option<PathBuf>,
+
+    /// Send every recipe with the tag given by `--tag`, in collection order,
+    /// and print a summary of the results. These requests are stored in
+    /// history.
+    #[clap(
+        long,
+        requires = "tag",
+        conflicts_with_all = [
+            "status", "headers", "verbose", "no_body", "fail",
+            "fail_with_body", "dry_run", "format",
+        ],
+    )]
+    all: bool,
+
+    /// Maximum number of requests in flight at once with `--repeat`,
+    /// `--iterate`, or `--all`
+    #[clap(long, default_value = "1", requires = "batch")]
+    concurrency: NonZeroUsize,
+}
+
+/// Structured formats for printing a response
+#[derive(Copy, Clone, Debug, ValueEnum)]
+enum OutputFormat {
+    /// JSON object with status, headers, timing, and body
+    Json,
+    /// Same as `json`, but in YAML
+    Yaml,
+    /// Only the response headers
+    HeadersOnly,
+    /// Only the response body. This is the same as the default output,
+    /// without anything printed to stderr
+    BodyOnly,
 }
 
 /// A helper for any subcommand that needs to build requests. This handles
@@ -60,10 +167,59 @@ pub struct RequestCommand {
 #[derive(Clone, Debug, Parser)]
 pub struct BuildRequestCommand {
     /// ID of the recipe to render into a request
-    recipe_id: RecipeId,
+    #[clap(required_unless_present_any = ["url_match", "url", "tag"])]
+    recipe_id: Option<RecipeId>,
+
+    /// Find the recipe by URL instead of ID. `*` matches any sequence of
+    /// characters and `?` matches any one. The pattern is matched against the
+    /// end of each recipe's URL (before the query string), e.g. `/users/*`
+    #[clap(long = "match", conflicts_with_all = ["recipe_id", "url"])]
+    url_match: Option<String>,
+
+    /// Find the recipe by tag instead of ID. The tag must belong to exactly
+    /// one recipe, unless `--all` is passed to `slumber request`
+    #[clap(long, conflicts_with_all = ["recipe_id", "url_match", "url"])]
+    tag: Option<String>,
 
-    /// ID of the profile to pull template values from
-    #[clap(long = "profile", short)]
+    /// Build a one-off request to this URL, instead of using a recipe from
+    /// the collection. Use `--header`, `--query`, and `--body` to fill out the
+    /// request. The request is stored in history under the recipe ID
+    /// `_adhoc`
+    #[clap(long, conflicts_with = "recipe_id")]
+    url: Option<Template>,
+
+    /// HTTP method for a `--url` request
+    #[clap(long, short = 'X', default_value = "GET", requires = "url")]
+    method: Method,
+
+    /// Add a header, in the format `Name: value`. This replaces any header
+    /// of the same name in the recipe. The value is a template. Can be given
+    /// multiple times
+    #[clap(long = "header", short = 'H', value_parser = parse_header)]
+    headers: Vec<(String, Template)>,
+
+    /// Add a query parameter, in the format `key=value`. This replaces all
+    /// parameters of the same name in the recipe. The value is a template.
+    /// Can be given multiple times
+    #[clap(
+        long = "query",
+        short = 'q',
+        value_parser = parse_key_val::<String, Template>,
+    )]
+    query: Vec<(String, Template)>,
+
+    /// Replace the recipe's body with this template
+    #[clap(long, conflicts_with = "body_file")]
+    body: Option<Template>,
+
+    /// Replace the recipe's body with the contents of this file. The
+    /// contents are sent as-is, *not* rendered as a template
+    #[clap(long)]
+    body_file: Option<PathBuf>,
+
+    /// ID of the profile to pull template values from. If omitted, use the
+    /// config's `default_profile`, if the collection defines it
+    #[clap(long = "profile", short, env = "SLUMBER_PROFILE")]
     profile: Option<ProfileId>,
 
     /// List of key=value template field overrides
@@ -73,10 +229,25 @@ pub struct BuildRequestCommand {
         value_parser = parse_key_val::<String, String>,
     )]
     overrides: Vec<(String, String)>,
+
+    /// Send the request even if its host isn't in the profile's
+    /// `allowed_hosts`
+    #[clap(long)]
+    allow_any_host: bool,
 }
 
 impl Subcommand for RequestCommand {
     async fn execute(self, global: GlobalArgs) -> anyhow::Result<ExitCode> {
+        if let Some(repeat) = self.repeat {
+            return self.execute_repeated(global, repeat).await;
+        }
+        if let Some(path) = self.iterate.clone() {
+            return self.execute_iterated(global, &path).await;
+        }
+        if self.all {
+            return self.execute_all(global).await;
+        }
+
         let (database, ticket) = self
             .build_request
             // Don't execute sub-requests in a dry run
@@ -100,35 +271,40 @@ impl Subcommand for RequestCommand {
         } else {
             // Everything other than the body prints to stderr, to make it easy
             // to pipe the body to a file
-            if self.headers {
+            let headers = self.headers || self.verbose;
+            if headers {
                 eprintln!("{}", HeaderDisplay(&ticket.record().headers));
             }
 
-            // Run the request
-            let exchange = ticket.send(&database).await?;
+            // Run the request. If saving the body fails, the error is logged
+            // to stderr but the response is still printed
+            let exchange = ticket.send(&database).await?.exchange;
             let status = exchange.response.status;
+            let fail_code = (self.fail || self.fail_with_body)
+                .then(|| fail_exit_code(status))
+                .flatten();
 
             // Print stuff!
-            if self.status {
+            if self.verbose {
+                // Version doesn't impl Display; Debug gives "HTTP/1.1"
+                eprintln!("{:?} {status}", exchange.response.version);
+            } else if self.status {
                 eprintln!("{}", status.as_u16());
             }
-            if self.headers {
+            if headers {
                 eprintln!("{}", HeaderDisplay(&exchange.response.headers));
             }
-            if !self.no_body {
-                // If body is not UTF-8, write the raw bytes instead (e.g if
-                // downloading an image)
-                let body = &exchange.response.body;
-                if let Some(text) = body.text() {
-                    print!("{}", text);
-                } else {
-                    io::stdout()
-                        .write(body.bytes())
-                        .context("Error writing to stdout")?;
-                }
+            // Like curl, --fail hides the body of error responses
+            let hide_body = self.fail && fail_code.is_some();
+            if let Some(format) = self.format {
+                print_formatted(&exchange, format, hide_body)?;
+            } else if !self.no_body && !hide_body {
+                print_body(&exchange.response.body)?;
             }
 
-            if self.exit_status && status.as_u16() >= 400 {
+            if let Some(code) = fail_code {
+                Ok(ExitCode::from(code))
+            } else if self.exit_status && status.as_u16() >= 400 {
                 Ok(ExitCode::from(HTTP_ERROR_EXIT_CODE))
             } else {
                 Ok(ExitCode::SUCCESS)
@@ -137,6 +313,187 @@ impl Subcommand for RequestCommand {
     }
 }
 
+impl RequestCommand {
+    /// Send the request repeatedly with bounded concurrency, and print a
+    /// summary of the results
+    async fn execute_repeated(
+        self,
+        global: GlobalArgs,
+        repeat: NonZeroUsize,
+    ) -> anyhow::Result<ExitCode> {
+        let prepared = &self.build_request.prepare(global, true).await?;
+
+        let start = Instant::now();
+        let mut results = stream::iter(0..repeat.get())
+            .map(|_| async move {
+                let ticket = prepared.build().await?;
+                anyhow::Ok(ticket.send_unrecorded().await?)
+            })
+            .buffer_unordered(self.concurrency.get());
+        let mut report = RepeatReport::default();
+        while let Some(result) = results.next().await {
+            report.add(result);
+        }
+        report.elapsed = start.elapsed();
+
+        print!("{report}");
+        if self.exit_status && report.num_unsuccessful() > 0 {
+            Ok(ExitCode::from(HTTP_ERROR_EXIT_CODE))
+        } else {
+            Ok(ExitCode::SUCCESS)
+        }
+    }
+
+    /// Send the request once per row in a data file, with bounded
+    /// concurrency. Print the outcome of each row as it finishes, then a
+    /// summary of the results
+    async fn execute_iterated(
+        self,
+        global: GlobalArgs,
+        path: &Path,
+    ) -> anyhow::Result<ExitCode> {
+        let rows = load_rows(path).await?;
+        let prepared = &self.build_request.prepare(global, true).await?;
+
+        let start = Instant::now();
+        let mut results = stream::iter(rows.into_iter().enumerate())
+            .map(|(i, row)| async move {
+                let result = async {
+                    let ticket = prepared.build_with_overrides(row).await?;
+                    anyhow::Ok(ticket.send(&prepared.database).await?.exchange)
+                }
+                .await;
+                (i + 1, result)
+            })
+            .buffer_unordered(self.concurrency.get());
+        let mut report = RepeatReport::default();
+        while let Some((row, result)) = results.next().await {
+            print_outcome(format_args!("Row {row}"), &result);
+            report.add(result);
+        }
+        report.elapsed = start.elapsed();
+
+        print!("{report}");
+        if self.exit_status && report.num_unsuccessful() > 0 {
+            Ok(ExitCode::from(HTTP_ERROR_EXIT_CODE))
+        } else {
+            Ok(ExitCode::SUCCESS)
+        }
+    }
+
+    /// Send every recipe with the selected tag, with bounded concurrency.
+    /// Print the outcome of each recipe as it finishes, then a summary of the
+    /// results
+    async fn execute_all(self, global: GlobalArgs) -> anyhow::Result<ExitCode> {
+        let prepared = self.build_request.prepare_all(global, true).await?;
+
+        let start = Instant::now();
+        let mut results = stream::iter(&prepared)
+            .map(|prepared| async move {
+                let result = async {
+                    let ticket = prepared.build().await?;
+                    anyhow::Ok(ticket.send(&prepared.database).await?.exchange)
+                }
+                .await;
+                (&prepared.recipe.id, result)
+            })
+            .buffer_unordered(self.concurrency.get());
+        let mut report = RepeatReport::default();
+        while let Some((recipe_id, result)) = results.next().await {
+            print_outcome(recipe_id, &result);
+            report.add(result);
+        }
+        report.elapsed = start.elapsed();
+
+        print!("{report}");
+        if self.exit_status && report.num_unsuccessful() > 0 {
+            Ok(ExitCode::from(HTTP_ERROR_EXIT_CODE))
+        } else {
+            Ok(ExitCode::SUCCESS)
+        }
+    }
+}
+
+/// Print the outcome of a single request in a batch to stderr
+fn print_outcome(label: impl Display, result: &anyhow::Result<Exchange>) {
+    match result {
+        Ok(exchange) => eprintln!(
+            "{label}: {} ({})",
+            exchange.response.status,
+            format_duration(&exchange.duration())
+        ),
+        Err(error) => eprintln!("{label}: {error:#}"),
+    }
+}
+
+/// Load rows of template field overrides from a data file for `--iterate`.
+/// The format is determined by the file extension
+async fn load_rows(
+    path: &Path,
+) -> anyhow::Result<Vec<IndexMap<String, String>>> {
+    let content = fs::read(path)
+        .await
+        .with_context(|| format!("Error reading data file {path:?}"))?;
+    let extension = path.extension().and_then(|extension| extension.to_str());
+    match extension {
+        Some("csv") => parse_csv_rows(&content),
+        Some("jsonl" | "ndjson") => parse_json_rows(&content),
+        _ => Err(anyhow!(
+            "Unknown data file format for {path:?}; \
+            expected `.csv` or `.jsonl`"
+        )),
+    }
+    .with_context(|| format!("Error parsing data file {path:?}"))
+}
+
+/// Parse CSV into rows of overrides. The first row is the header, which gives
+/// the field name for each column
+fn parse_csv_rows(
+    content: &[u8],
+) -> anyhow::Result<Vec<IndexMap<String, String>>> {
+    let mut reader = csv::Reader::from_reader(content);
+    let headers = reader.headers()?.clone();
+    reader
+        .records()
+        .map(|record| {
+            let record = record?;
+            Ok(headers
+                .iter()
+                .zip(&record)
+                .map(|(field, value)| (field.to_owned(), value.to_owned()))
+                .collect())
+        })
+        .collect()
+}
+
+/// Parse JSON Lines into rows of overrides. Each line must be an object.
+/// String values are used as-is, and anything else is stringified as JSON
+fn parse_json_rows(
+    content: &[u8],
+) -> anyhow::Result<Vec<IndexMap<String, String>>> {
+    let content = std::str::from_utf8(content)?;
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            let object: serde_json::Map<String, serde_json::Value> =
+                serde_json::from_str(line)
+                    .with_context(|| format!("Error on line {}", i + 1))?;
+            Ok(object
+                .into_iter()
+                .map(|(field, value)| {
+                    let value = match value {
+                        serde_json::Value::String(value) => value,
+                        value => value.to_string(),
+                    };
+                    (field, value)
+                })
+                .collect())
+        })
+        .collect()
+}
+
 impl BuildRequestCommand {
     /// Render the request specified by the user. This returns the HTTP engine
     /// too so it can be re-used if necessary (iff `trigger_dependencies` is
@@ -149,62 +506,494 @@ impl BuildRequestCommand {
         global: GlobalArgs,
         trigger_dependencies: bool,
     ) -> anyhow::Result<(CollectionDatabase, RequestTicket)> {
+        let prepared = self.prepare(global, trigger_dependencies).await?;
+        let ticket = prepared.build().await?;
+        Ok((prepared.database, ticket))
+    }
+
+    /// Load the collection and find the recipe specified by the user, without
+    /// rendering anything yet
+    async fn prepare(
+        self,
+        global: GlobalArgs,
+        trigger_dependencies: bool,
+    ) -> anyhow::Result<PreparedRecipe> {
+        let tag = self.tag.clone();
+        let mut prepared =
+            self.prepare_all(global, trigger_dependencies).await?;
+        if prepared.len() > 1 {
+            // Only possible when selecting by tag
+            bail!(
+                "Multiple recipes have tag `{}`; pass a recipe ID instead, or \
+                use `slumber request --all` to send all of them. Matches: {}",
+                tag.unwrap_or_default(),
+                prepared
+                    .iter()
+                    .map(|prepared| &prepared.recipe.id)
+                    .format(", ")
+            );
+        }
+        Ok(prepared.remove(0))
+    }
+
+    /// Load the collection and find every recipe specified by the user,
+    /// without rendering anything yet. There's always at least one recipe,
+    /// and there can only be more than one when selecting by tag
+    async fn prepare_all(
+        self,
+        global: GlobalArgs,
+        trigger_dependencies: bool,
+    ) -> anyhow::Result<Vec<PreparedRecipe>> {
         let collection_path = CollectionFile::try_path(None, global.file)?;
-        let database = Database::load()?.into_collection(&collection_path)?;
+        // Shared by every prepared recipe
+        let config = Arc::new(Config::load(Some(&collection_path))?);
+        let database = Database::load()?
+            .into_collection(&collection_path)?
+            .with_retention(config.history.clone());
         let collection_file = CollectionFile::load(collection_path).await?;
         let collection = collection_file.collection;
-        let config = Config::load()?;
         let http_engine = HttpEngine::new(&config);
 
         // Validate profile ID, so we can provide a good error if it's invalid
-        if let Some(profile_id) = &self.profile {
-            collection.profiles.get(profile_id).ok_or_else(|| {
-                anyhow!(
-                    "No profile with ID `{profile_id}`; options are: {}",
-                    collection.profiles.keys().format(", ")
-                )
-            })?;
-        }
+        let profile =
+            select_profile(&collection, &config, self.profile.clone())?;
+
+        // Find recipe(s) by ID, URL pattern, or tag, or build one from scratch
+        let recipes =
+            match (&self.recipe_id, &self.url_match, &self.tag, self.url) {
+                (Some(recipe_id), _, _, _) => {
+                    vec![find_recipe(&collection.recipes, recipe_id)?.clone()]
+                }
+                (None, Some(pattern), _, _) => {
+                    vec![find_recipe_by_url(&collection.recipes, pattern)?
+                        .clone()]
+                }
+                (None, None, Some(tag), _) => {
+                    find_recipes_by_tag(&collection.recipes, tag)?
+                        .into_iter()
+                        .cloned()
+                        .collect()
+                }
+                (None, None, None, Some(url)) => {
+                    vec![adhoc_recipe(url, self.method)]
+                }
+                // Clap enforces that one of the four is given
+                (None, None, None, None) => {
+                    unreachable!("Recipe ID, URL pattern, tag, or URL required")
+                }
+            };
+
+        let body = if let Some(path) = self.body_file {
+            let body = fs::read_to_string(&path)
+                .await
+                .with_context(|| format!("Error reading body from {path:?}"))?;
+            Some(RecipeBody::Raw(Template::raw(body)))
+        } else {
+            self.body.map(RecipeBody::Raw)
+        };
+        let overrides: IndexMap<String, String> =
+            self.overrides.into_iter().collect();
 
-        // Find recipe by ID
-        let recipe = collection
-            .recipes
-            .get_recipe(&self.recipe_id)
-            .ok_or_else(|| {
-                anyhow!(
-                    "No recipe with ID `{}`; options are: {}",
-                    self.recipe_id,
-                    collection.recipes.recipe_ids().format(", ")
-                )
-            })?
-            .clone();
-
-        // Build the request
-        let overrides: IndexMap<_, _> = self.overrides.into_iter().collect();
+        recipes
+            .into_iter()
+            .map(|mut recipe| {
+                let profile =
+                    resolve_profile(&collection, &recipe, profile.as_ref())?;
+                if let (Some(pinned), Some(requested)) =
+                    (&recipe.profile, &self.profile)
+                {
+                    if pinned != requested {
+                        eprintln!(
+                            "Recipe `{}` is pinned to profile `{pinned}`; \
+                            ignoring profile `{requested}`",
+                            recipe.id
+                        );
+                    }
+                }
+
+                // Apply one-off modifications from the command line
+                override_recipe(
+                    &mut recipe,
+                    self.headers.clone(),
+                    self.query.clone(),
+                );
+                if let Some(body) = &body {
+                    recipe.body = Some(body.clone());
+                }
+                Ok(PreparedRecipe {
+                    database: database.clone(),
+                    http_engine: http_engine.clone(),
+                    collection: collection.clone(),
+                    recipe,
+                    profile,
+                    overrides: overrides.clone(),
+                    config: Arc::clone(&config),
+                    trigger_dependencies,
+                    allow_any_host: self.allow_any_host,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Everything needed to render the recipe specified by the user. The recipe
+/// can be built multiple times, e.g. for `--repeat`
+struct PreparedRecipe {
+    database: CollectionDatabase,
+    http_engine: HttpEngine,
+    collection: Collection,
+    recipe: Recipe,
+    profile: Option<ProfileId>,
+    overrides: IndexMap<String, String>,
+    config: Arc<Config>,
+    trigger_dependencies: bool,
+    allow_any_host: bool,
+}
+
+impl PreparedRecipe {
+    /// Render the recipe into a request. Each call gets a fresh template
+    /// context, so dynamic values are re-rendered every time
+    async fn build(&self) -> anyhow::Result<RequestTicket> {
+        self.build_with_overrides(IndexMap::new()).await
+    }
+
+    /// Render the recipe into a request, with additional overrides that take
+    /// precedence over those from the command line
+    async fn build_with_overrides(
+        &self,
+        overrides: IndexMap<String, String>,
+    ) -> anyhow::Result<RequestTicket> {
+        let mut all_overrides = self.overrides.clone();
+        all_overrides.extend(overrides);
         let template_context = TemplateContext {
-            selected_profile: self.profile.clone(),
-            collection,
-            // Passing the HTTP engine is how we tell the template renderer that
-            // it's ok to execute subrequests during render
-            http_engine: if trigger_dependencies {
-                Some(http_engine.clone())
-            } else {
-                None
+            overrides: all_overrides,
+            ..TemplateContext::new(
+                self.collection.clone(),
+                self.profile.clone(),
+                // Passing the HTTP engine is how we tell the template renderer
+                // that it's ok to execute subrequests during render
+                self.trigger_dependencies.then(|| self.http_engine.clone()),
+                self.database.clone(),
+                Box::new(CliPrompter),
+                &self.config,
+            )
+        };
+        let seed = RequestSeed::new(
+            self.recipe.clone(),
+            BuildOptions {
+                allow_any_host: self.allow_any_host,
+                ..Default::default()
             },
-            database: database.clone(),
-            overrides,
-            prompter: Box::new(CliPrompter),
-            recursion_count: Default::default(),
+        );
+        self.http_engine
+            .build(seed, &template_context)
+            .await
+            .map_err(|error| {
+                let blocked = error.error.is::<HostNotAllowedError>();
+                let error = anyhow::Error::from(error);
+                if blocked {
+                    error.context("Pass `--allow-any-host` to send it anyway")
+                } else {
+                    error
+                }
+            })
+    }
+}
+
+/// Shared state for subcommands that send several recipes in order (`slumber
+/// run`, `slumber seed`, and `slumber test`). Each response is stored in
+/// history before the next recipe is built, so chains in later recipes can
+/// consume responses from earlier ones.
+pub(super) struct RecipeRunner {
+    pub collection: Collection,
+    config: Config,
+    database: CollectionDatabase,
+    http_engine: HttpEngine,
+    profile: Option<ProfileId>,
+    overrides: IndexMap<String, String>,
+}
+
+impl RecipeRunner {
+    /// Load the collection, config, and database, and pick the profile to
+    /// render with
+    pub async fn load(
+        global: GlobalArgs,
+        profile: Option<ProfileId>,
+        overrides: Vec<(String, String)>,
+    ) -> anyhow::Result<Self> {
+        let collection_path = CollectionFile::try_path(None, global.file)?;
+        let config = Config::load(Some(&collection_path))?;
+        let database = Database::load()?
+            .into_collection(&collection_path)?
+            .with_retention(config.history.clone());
+        let collection =
+            CollectionFile::load(collection_path).await?.collection;
+        let http_engine = HttpEngine::new(&config);
+        let profile = select_profile(&collection, &config, profile)?;
+        Ok(Self {
+            collection,
+            config,
+            database,
+            http_engine,
+            profile,
+            overrides: overrides.into_iter().collect(),
+        })
+    }
+
+    /// Build and send a recipe, and store the exchange in history. Each
+    /// request gets a new template context, so nothing rendered for a
+    /// previous recipe leaks into this one. `captures` are available to the
+    /// recipe's templates as `{{captures.<name>}}`.
+    pub async fn send(
+        &self,
+        recipe: &Recipe,
+        captures: IndexMap<String, String>,
+    ) -> anyhow::Result<Exchange> {
+        let profile =
+            resolve_profile(&self.collection, recipe, self.profile.as_ref())?;
+        let template_context = TemplateContext {
+            overrides: self.overrides.clone(),
+            captures,
+            ..TemplateContext::new(
+                self.collection.clone(),
+                profile,
+                Some(self.http_engine.clone()),
+                self.database.clone(),
+                Box::new(CliPrompter),
+                &self.config,
+            )
         };
-        let seed = RequestSeed::new(recipe, BuildOptions::default());
-        let request = http_engine.build(seed, &template_context).await?;
-        Ok((database, request))
+        let seed = RequestSeed::new(recipe.clone(), BuildOptions::default());
+        let ticket = self.http_engine.build(seed, &template_context).await?;
+        Ok(ticket.send(&self.database).await?.exchange)
+    }
+}
+
+/// Aggregated results of sending a request many times with `--repeat` or
+/// `--iterate`
+#[derive(Debug, Default)]
+struct RepeatReport {
+    /// Latency of every request that got a response, in no particular order
+    latencies: Vec<Duration>,
+    /// Number of responses for each status code
+    statuses: BTreeMap<u16, usize>,
+    /// Number of occurrences of each error, for requests that failed to build
+    /// or send
+    errors: IndexMap<String, usize>,
+    /// Wall time for the whole run
+    elapsed: std::time::Duration,
+}
+
+impl RepeatReport {
+    fn add(&mut self, result: anyhow::Result<Exchange>) {
+        match result {
+            Ok(exchange) => {
+                self.latencies.push(exchange.duration());
+                *self
+                    .statuses
+                    .entry(exchange.response.status.as_u16())
+                    .or_default() += 1;
+            }
+            Err(error) => {
+                *self.errors.entry(format!("{error:#}")).or_default() += 1;
+            }
+        }
+    }
+
+    fn num_requests(&self) -> usize {
+        self.latencies.len() + self.errors.values().sum::<usize>()
+    }
+
+    /// Number of requests that failed outright or got an error status
+    fn num_unsuccessful(&self) -> usize {
+        self.num_error_statuses() + self.errors.values().sum::<usize>()
+    }
+
+    fn num_error_statuses(&self) -> usize {
+        self.statuses
+            .iter()
+            .filter(|(status, _)| **status >= 400)
+            .map(|(_, count)| count)
+            .sum()
+    }
+}
+
+impl Display for RepeatReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let num_requests = self.num_requests();
+        let num_failed = self.errors.values().sum::<usize>();
+        let num_error_statuses = self.num_error_statuses();
+        writeln!(
+            f,
+            "Requests:   {num_requests} total, {} succeeded, \
+            {num_error_statuses} error status, {num_failed} failed",
+            num_requests - num_error_statuses - num_failed,
+        )?;
+        writeln!(f, "Elapsed:    {:.2}s", self.elapsed.as_secs_f64())?;
+        writeln!(
+            f,
+            "Throughput: {:.2} req/s",
+            num_requests as f64 / self.elapsed.as_secs_f64()
+        )?;
+
+        let mut latencies = self.latencies.clone();
+        latencies.sort();
+        if let (Some(min), Some(max)) = (latencies.first(), latencies.last()) {
+            writeln!(
+                f,
+                "Latency:    min {} | p50 {} | p90 {} | p99 {} | max {}",
+                format_duration(min),
+                format_duration(&percentile(&latencies, 50)),
+                format_duration(&percentile(&latencies, 90)),
+                format_duration(&percentile(&latencies, 99)),
+                format_duration(max),
+            )?;
+        }
+        if !self.statuses.is_empty() {
+            writeln!(
+                f,
+                "Statuses:   {}",
+                self.statuses
+                    .iter()
+                    .format_with(", ", |(status, count), f| {
+                        f(&format_args!("{status} x{count}"))
+                    })
+            )?;
+        }
+        if !self.errors.is_empty() {
+            writeln!(f, "Errors:")?;
+            for (error, count) in &self.errors {
+                writeln!(f, "  {count}x {error}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Get the `p`th percentile of a sorted, non-empty list, using the
+/// nearest-rank method
+pub(super) fn percentile(sorted: &[Duration], p: usize) -> Duration {
+    let rank = (sorted.len() * p).div_ceil(100);
+    sorted[rank.saturating_sub(1)]
+}
+
+/// Pick the profile to use. A profile given with `--profile` or
+/// `SLUMBER_PROFILE` must exist in the collection. Otherwise, fall back to the
+/// config's `default_profile`, but only if the collection defines it
+pub(super) fn select_profile(
+    collection: &Collection,
+    config: &Config,
+    profile_id: Option<ProfileId>,
+) -> anyhow::Result<Option<ProfileId>> {
+    match profile_id {
+        Some(profile_id) => {
+            validate_profile(collection, &profile_id)?;
+            Ok(Some(profile_id))
+        }
+        None => Ok(config
+            .default_profile
+            .clone()
+            .filter(|profile_id| collection.profiles.contains_key(profile_id))),
+    }
+}
+
+/// Get the profile to render a recipe with. If the recipe pins a profile, that
+/// takes precedence over the selected profile
+pub(super) fn resolve_profile(
+    collection: &Collection,
+    recipe: &Recipe,
+    selected: Option<&ProfileId>,
+) -> anyhow::Result<Option<ProfileId>> {
+    match &recipe.profile {
+        Some(pinned) => {
+            validate_profile(collection, pinned).with_context(|| {
+                format!("Invalid pinned profile for recipe `{}`", recipe.id)
+            })?;
+            Ok(Some(pinned.clone()))
+        }
+        None => Ok(selected.cloned()),
+    }
+}
+
+/// Ensure a profile ID exists in the collection. The error lists the available
+/// profiles, to help the user out
+pub(super) fn validate_profile(
+    collection: &Collection,
+    profile_id: &ProfileId,
+) -> anyhow::Result<()> {
+    collection.profiles.get(profile_id).ok_or_else(|| {
+        anyhow!(
+            "No profile with ID `{profile_id}`; options are: {}",
+            collection.profiles.keys().format(", ")
+        )
+    })?;
+    Ok(())
+}
+
+/// Get a recipe by ID. The error lists the available recipes, to help the
+/// user out
+pub(super) fn find_recipe<'a>(
+    recipes: &'a RecipeTree,
+    recipe_id: &RecipeId,
+) -> anyhow::Result<&'a Recipe> {
+    recipes.get_recipe(recipe_id).ok_or_else(|| {
+        anyhow!(
+            "No recipe with ID `{recipe_id}`; options are: {}",
+            recipes.recipe_ids().format(", ")
+        )
+    })
+}
+
+/// Build a recipe for a one-off request from `--url`
+fn adhoc_recipe(url: Template, method: Method) -> Recipe {
+    Recipe {
+        id: ADHOC_RECIPE_ID.to_owned().into(),
+        name: None,
+        label: None,
+        method,
+        url,
+        body: None,
+        authentication: None,
+        query: Vec::new(),
+        headers: IndexMap::new(),
+        tls: None,
+        http_version: HttpVersion::default(),
+        connect_to: None,
+        unix_socket: None,
+        resolve: IndexMap::new(),
+        compress: None,
+        redirects: RedirectOptions::default(),
+        assertions: Vec::new(),
+        tags: Vec::new(),
+        profile: None,
+        captures: IndexMap::new(),
+        variables: IndexMap::new(),
+        save: Vec::new(),
+    }
+}
+
+/// Add headers and query parameters to a recipe, replacing any existing
+/// values with the same name. Header names are case-insensitive.
+fn override_recipe(
+    recipe: &mut Recipe,
+    headers: Vec<(String, Template)>,
+    query: Vec<(String, Template)>,
+) {
+    for (name, value) in headers {
+        recipe
+            .headers
+            .retain(|existing, _| !existing.eq_ignore_ascii_case(&name));
+        recipe.headers.insert(name, value);
+    }
+    for (key, value) in query {
+        recipe.query.retain(|(existing, _)| existing != &key);
+        recipe.query.push((key, value));
     }
 }
 
 /// Prompt the user for input on the CLI
 #[derive(Debug)]
-struct CliPrompter;
+pub(super) struct CliPrompter;
 
 impl Prompter for CliPrompter {
     fn prompt(&self, prompt: Prompt) {
@@ -212,18 +1001,22 @@ impl Prompter for CliPrompter {
         // Since the CLI has nothing else to do while waiting on a response,
         // that's fine.
         let result = if prompt.sensitive {
-            // Dialoguer doesn't support default values here so there's nothing
-            // we can do
-            if prompt.default.is_some() {
-                warn!(
-                    "Default value not supported for sensitive prompts in CLI"
-                );
-            }
-
+            // Dialoguer doesn't support default values for password input, so
+            // we emulate it: an empty submission falls back to the default.
+            // Never show the default itself, since it's probably a secret too
+            let message = if prompt.default.is_some() {
+                format!("{} (leave empty for default)", prompt.message)
+            } else {
+                prompt.message
+            };
             Password::new()
-                .with_prompt(prompt.message)
+                .with_prompt(message)
                 .allow_empty_password(true)
                 .interact()
+                .map(|value| match prompt.default {
+                    Some(default) if value.is_empty() => default,
+                    _ => value,
+                })
         } else {
             let mut input =
                 Input::new().with_prompt(prompt.message).allow_empty(true);
@@ -242,8 +1035,192 @@ impl Prompter for CliPrompter {
     }
 }
 
+/// Find the single recipe whose URL matches a pattern. Errors if zero or
+/// multiple recipes match, listing the candidates in the latter case.
+fn find_recipe_by_url<'a>(
+    recipes: &'a RecipeTree,
+    pattern: &str,
+) -> anyhow::Result<&'a Recipe> {
+    let matches = recipes
+        .iter()
+        .filter_map(|(_, node)| node.recipe())
+        .filter(|recipe| url_matches(pattern, &recipe.url.to_string()))
+        .collect_vec();
+    match matches.as_slice() {
+        [] => Err(anyhow!("No recipe with URL matching `{pattern}`")),
+        [recipe] => Ok(recipe),
+        _ => Err(anyhow!(
+            "Multiple recipes have a URL matching `{pattern}`; \
+            use a more specific pattern or pass a recipe ID instead. \
+            Matches:\n{}",
+            matches
+                .iter()
+                .format_with("\n", |recipe, f| f(&format_args!(
+                    "  {} ({} {})",
+                    recipe.id, recipe.method, recipe.url
+                )))
+        )),
+    }
+}
+
+/// Find every recipe with a tag, in collection order. Tags are matched
+/// exactly. Errors if no recipes have the tag, listing the available tags.
+fn find_recipes_by_tag<'a>(
+    recipes: &'a RecipeTree,
+    tag: &str,
+) -> anyhow::Result<Vec<&'a Recipe>> {
+    let all_recipes = || recipes.iter().filter_map(|(_, node)| node.recipe());
+    let matches = all_recipes()
+        .filter(|recipe| recipe.tags.iter().any(|t| t == tag))
+        .collect_vec();
+    if matches.is_empty() {
+        Err(anyhow!(
+            "No recipes with tag `{tag}`; options are: {}",
+            all_recipes()
+                .flat_map(|recipe| &recipe.tags)
+                .unique()
+                .format(", ")
+        ))
+    } else {
+        Ok(matches)
+    }
+}
+
+/// Check if a URL matches a wildcard pattern, where `*` matches any sequence
+/// of characters and `?` matches exactly one. The pattern can match any suffix
+/// of the URL, excluding the query string, so the user doesn't need to know
+/// the scheme or host.
+fn url_matches(pattern: &str, url: &str) -> bool {
+    let url = url.split_once('?').map(|(url, _)| url).unwrap_or(url);
+    // A leading star leaves the start unanchored
+    wildcard_match(&format!("*{pattern}"), url)
+}
+
+/// Print a response body to stdout. If the body is not UTF-8, write the raw
+/// bytes instead (e.g if downloading an image)
+fn print_body(body: &ResponseBody) -> anyhow::Result<()> {
+    if let Some(text) = body.text() {
+        print!("{}", text);
+    } else {
+        io::stdout()
+            .write(body.bytes())
+            .context("Error writing to stdout")?;
+    }
+    Ok(())
+}
+
+/// Print an exchange to stdout in a structured format
+fn print_formatted(
+    exchange: &Exchange,
+    format: OutputFormat,
+    hide_body: bool,
+) -> anyhow::Result<()> {
+    let response = &exchange.response;
+    match format {
+        OutputFormat::Json => {
+            let output = ExchangeOutput::new(exchange, hide_body);
+            println!("{}", serde_json::to_string_pretty(&output)?);
+        }
+        OutputFormat::Yaml => {
+            let output = ExchangeOutput::new(exchange, hide_body);
+            print!("{}", serde_yaml::to_string(&output)?);
+        }
+        OutputFormat::HeadersOnly => {
+            println!("{}", HeaderDisplay(&response.headers));
+        }
+        OutputFormat::BodyOnly => {
+            if !hide_body {
+                print_body(&response.body)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Serialize a header map for structured output. Multiple values for the same
+/// header are joined with `, `
+pub(super) fn serialize_headers(headers: &HeaderMap) -> IndexMap<&str, String> {
+    headers
+        .keys()
+        .map(|name| {
+            let value = headers
+                .get_all(name)
+                .iter()
+                .map(|value| String::from_utf8_lossy(value.as_bytes()))
+                .join(", ");
+            (name.as_str(), value)
+        })
+        .collect()
+}
+
+/// Serializable representation of an exchange, for `--format json|yaml`
+#[derive(Debug, Serialize)]
+pub(super) struct ExchangeOutput<'a> {
+    id: RequestId,
+    status: u16,
+    /// HTTP version, e.g. `HTTP/1.1`
+    version: String,
+    /// Multiple values for the same header are joined with `, `
+    headers: IndexMap<&'a str, String>,
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+    duration_ms: i64,
+    /// `None` if the body was hidden by `--fail`
+    body: Option<Cow<'a, str>>,
+    body_encoding: BodyEncoding,
+}
+
+/// How a body is encoded in serialized output. UTF-8 bodies are included as
+/// is, anything else is base64-encoded
+#[derive(Copy, Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(super) enum BodyEncoding {
+    Utf8,
+    Base64,
+}
+
+impl BodyEncoding {
+    /// Encode body bytes for serialized output, picking the encoding based on
+    /// whether the bytes are valid UTF-8
+    pub(super) fn encode(bytes: &[u8]) -> (Cow<'_, str>, Self) {
+        match std::str::from_utf8(bytes) {
+            Ok(text) => (Cow::Borrowed(text), Self::Utf8),
+            Err(_) => (Cow::Owned(BASE64_STANDARD.encode(bytes)), Self::Base64),
+        }
+    }
+}
+
+impl<'a> ExchangeOutput<'a> {
+    pub(super) fn new(exchange: &'a Exchange, hide_body: bool) -> Self {
+        let response = &exchange.response;
+        let (body, body_encoding) = BodyEncoding::encode(response.body.bytes());
+        Self {
+            id: exchange.id,
+            status: response.status.as_u16(),
+            // Version doesn't impl Display; Debug gives "HTTP/1.1"
+            version: format!("{:?}", response.version),
+            headers: serialize_headers(&response.headers),
+            start_time: exchange.start_time,
+            end_time: exchange.end_time,
+            duration_ms: exchange.duration().num_milliseconds(),
+            body: (!hide_body).then_some(body),
+            body_encoding,
+        }
+    }
+}
+
+/// Get the process exit code for an HTTP status when `--fail` is enabled. The
+/// code is the status class (4 for 4xx, 5 for 5xx), so scripts can branch on
+/// client vs server errors. Return `None` for non-error statuses.
+fn fail_exit_code(status: StatusCode) -> Option<u8> {
+    match status.as_u16() {
+        400..=599 => Some((status.as_u16() / 100) as u8),
+        _ => None,
+    }
+}
+
 /// Parse a single key=value pair for an argument
-fn parse_key_val<T, U>(
+pub(super) fn parse_key_val<T, U>(
     s: &str,
 ) -> Result<(T, U), Box<dyn Error + Send + Sync + 'static>>
 where
@@ -257,3 +1234,389 @@ where
         .ok_or_else(|| format!("invalid key=value: no \"=\" found in `{s}`"))?;
     Ok((key.parse()?, value.parse()?))
 }
+
+/// Parse a `Name: value` header, like curl's `-H`
+fn parse_header(
+    s: &str,
+) -> Result<(String, Template), Box<dyn Error + Send + Sync + 'static>> {
+    let (name, value) = s
+        .split_once(':')
+        .ok_or_else(|| format!("invalid header: no \":\" found in `{s}`"))?;
+    Ok((name.trim().to_owned(), value.trim().parse()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        collection::{Folder, Profile, RecipeNode},
+        http::ResponseRecord,
+        test_util::{
+            assert_err, by_id, header_map, temp_dir, Factory, TempDir,
+        },
+    };
+    use indexmap::indexmap;
+    use rstest::rstest;
+
+    /// Text bodies are included as-is, binary bodies are base64-encoded.
+    /// Repeated headers are joined
+    #[rstest]
+    #[case::text(b"hello", false, Some("hello"), BodyEncoding::Utf8)]
+    #[case::binary(b"\xc3\x28", false, Some("wyg="), BodyEncoding::Base64)]
+    #[case::hidden(b"hello", true, None, BodyEncoding::Utf8)]
+    fn test_exchange_output(
+        #[case] body: &'static [u8],
+        #[case] hide_body: bool,
+        #[case] expected_body: Option<&str>,
+        #[case] expected_encoding: BodyEncoding,
+    ) {
+        let exchange = Exchange {
+            response: ResponseRecord {
+                headers: header_map([
+                    ("content-type", "text/plain"),
+                    ("set-cookie", "a=1"),
+                    ("set-cookie", "b=2"),
+                ]),
+                body: body.to_vec().into(),
+                ..ResponseRecord::factory(())
+            }
+            .into(),
+            ..Exchange::factory(())
+        };
+        let output = ExchangeOutput::new(&exchange, hide_body);
+        assert_eq!(output.status, 200);
+        assert_eq!(
+            output.headers,
+            IndexMap::<_, String>::from_iter([
+                ("content-type", "text/plain".into()),
+                ("set-cookie", "a=1, b=2".into()),
+            ])
+        );
+        assert_eq!(output.body.as_deref(), expected_body);
+        assert_eq!(output.body_encoding, expected_encoding);
+    }
+
+    #[rstest]
+    #[case::success(200, None)]
+    #[case::redirect(302, None)]
+    #[case::not_found(404, Some(4))]
+    #[case::server_error(503, Some(5))]
+    fn test_fail_exit_code(#[case] status: u16, #[case] expected: Option<u8>) {
+        let status = StatusCode::from_u16(status).unwrap();
+        assert_eq!(fail_exit_code(status), expected);
+    }
+
+    #[rstest]
+    #[case::basic("Accept: application/json", "Accept", "application/json")]
+    #[case::no_space("Accept:text/plain", "Accept", "text/plain")]
+    #[case::template("X-Token: {{token}}", "X-Token", "{{token}}")]
+    #[case::colon_in_value("Referer: http://x", "Referer", "http://x")]
+    fn test_parse_header(
+        #[case] input: &str,
+        #[case] expected_name: &str,
+        #[case] expected_value: &str,
+    ) {
+        let (name, value) = parse_header(input).unwrap();
+        assert_eq!(name, expected_name);
+        assert_eq!(value.to_string(), expected_value);
+    }
+
+    #[rstest]
+    #[case::explicit(Some("profile1"), Some("profile2"), Some("profile1"))]
+    #[case::default(None, Some("profile2"), Some("profile2"))]
+    #[case::unknown_default(None, Some("unknown"), None)]
+    #[case::none(None, None, None)]
+    fn test_select_profile(
+        #[case] profile_id: Option<&str>,
+        #[case] default_profile: Option<&str>,
+        #[case] expected: Option<&str>,
+    ) {
+        let collection = Collection {
+            profiles: by_id(["profile1", "profile2"].map(|id| Profile {
+                id: id.into(),
+                ..Profile::factory(())
+            })),
+            ..Collection::factory(())
+        };
+        let config = Config {
+            default_profile: default_profile.map(ProfileId::from),
+            ..Config::default()
+        };
+        assert_eq!(
+            select_profile(
+                &collection,
+                &config,
+                profile_id.map(ProfileId::from)
+            )
+            .unwrap(),
+            expected.map(ProfileId::from)
+        );
+    }
+
+    /// An explicit profile has to exist
+    /// A recipe's pinned profile should take precedence over the selected one
+    #[rstest]
+    #[case::pinned(Some("profile2"), Some("profile1"), Some("profile2"))]
+    #[case::pinned_no_selection(Some("profile2"), None, Some("profile2"))]
+    #[case::not_pinned(None, Some("profile1"), Some("profile1"))]
+    #[case::none(None, None, None)]
+    fn test_resolve_profile(
+        #[case] pinned: Option<&str>,
+        #[case] selected: Option<&str>,
+        #[case] expected: Option<&str>,
+    ) {
+        let collection = Collection {
+            profiles: by_id(["profile1", "profile2"].map(|id| Profile {
+                id: id.into(),
+                ..Profile::factory(())
+            })),
+            ..Collection::factory(())
+        };
+        let recipe = Recipe {
+            profile: pinned.map(ProfileId::from),
+            ..Recipe::factory(())
+        };
+        assert_eq!(
+            resolve_profile(
+                &collection,
+                &recipe,
+                selected.map(ProfileId::from).as_ref()
+            )
+            .unwrap(),
+            expected.map(ProfileId::from)
+        );
+    }
+
+    /// A pinned profile that isn't in the collection is an error
+    #[test]
+    fn test_resolve_profile_unknown() {
+        let recipe = Recipe {
+            profile: Some("profile3".into()),
+            ..Recipe::factory(())
+        };
+        assert_err!(
+            resolve_profile(&Collection::factory(()), &recipe, None),
+            "Invalid pinned profile for recipe `recipe1`: \
+            No profile with ID `profile3`"
+        );
+    }
+
+    #[test]
+    fn test_select_profile_unknown() {
+        assert_err!(
+            select_profile(
+                &Collection::factory(()),
+                &Config::default(),
+                Some("unknown".into())
+            ),
+            "No profile with ID `unknown`"
+        );
+    }
+
+    #[test]
+    fn test_parse_header_invalid() {
+        assert!(parse_header("Accept").is_err());
+    }
+
+    /// Overrides replace values with the same name, and add the rest
+    #[test]
+    fn test_override_recipe() {
+        let mut recipe = Recipe {
+            headers: indexmap! {
+                "Accept".into() => "text/plain".into(),
+                "X-Keep".into() => "keep".into(),
+            },
+            query: vec![
+                ("tag".into(), "a".into()),
+                ("tag".into(), "b".into()),
+                ("page".into(), "1".into()),
+            ],
+            ..Recipe::factory(())
+        };
+        override_recipe(
+            &mut recipe,
+            vec![
+                ("accept".into(), "application/json".into()),
+                ("X-New".into(), "new".into()),
+            ],
+            vec![("tag".into(), "c".into()), ("limit".into(), "10".into())],
+        );
+        assert_eq!(
+            recipe.headers,
+            indexmap! {
+                "X-Keep".into() => "keep".into(),
+                "accept".into() => "application/json".into(),
+                "X-New".into() => "new".into(),
+            }
+        );
+        assert_eq!(
+            recipe.query,
+            vec![
+                ("page".into(), "1".into()),
+                ("tag".into(), "c".into()),
+                ("limit".into(), "10".into()),
+            ]
+        );
+    }
+
+    #[rstest]
+    #[case::exact("/users", "{{host}}/users", true)]
+    #[case::wildcard("/users/*", "{{host}}/users/{{user_id}}", true)]
+    #[case::wildcard_middle("/users/*/posts", "http://x/users/1/posts", true)]
+    #[case::full_url("https://*/users", "https://localhost/users", true)]
+    #[case::query_ignored("/users", "{{host}}/users?page=1", true)]
+    #[case::prefix_only("/users", "{{host}}/users/1", false)]
+    #[case::no_match("/posts/*", "{{host}}/users/1", false)]
+    fn test_url_matches(
+        #[case] pattern: &str,
+        #[case] url: &str,
+        #[case] expected: bool,
+    ) {
+        assert_eq!(url_matches(pattern, url), expected);
+    }
+
+    /// Tagged recipes are returned in collection order, including those in
+    /// folders
+    #[test]
+    fn test_find_recipes_by_tag() {
+        let recipes: RecipeTree = by_id::<RecipeNode>([
+            Recipe {
+                id: "login".into(),
+                tags: vec!["auth".into()],
+                ..Recipe::factory(())
+            }
+            .into(),
+            Folder {
+                id: "users".into(),
+                children: by_id([
+                    Recipe {
+                        id: "get_user".into(),
+                        tags: vec!["users".into()],
+                        ..Recipe::factory(())
+                    }
+                    .into(),
+                    Recipe {
+                        id: "refresh".into(),
+                        tags: vec!["users".into(), "auth".into()],
+                        ..Recipe::factory(())
+                    }
+                    .into(),
+                ]),
+                ..Folder::factory(())
+            }
+            .into(),
+        ])
+        .into();
+
+        let ids = |tag| {
+            find_recipes_by_tag(&recipes, tag)
+                .unwrap()
+                .into_iter()
+                .map(|recipe| recipe.id.as_str())
+                .collect_vec()
+        };
+        assert_eq!(ids("auth"), ["login", "refresh"]);
+        assert_eq!(ids("users"), ["get_user", "refresh"]);
+        assert_err!(
+            find_recipes_by_tag(&recipes, "Auth"),
+            "No recipes with tag `Auth`; options are: auth, users"
+        );
+    }
+
+    #[rstest]
+    #[case::single(&[7], 99, 7)]
+    #[case::p50(&[1, 2, 3, 4], 50, 2)]
+    #[case::p90(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 10], 90, 9)]
+    #[case::p99(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 10], 99, 10)]
+    fn test_percentile(
+        #[case] latencies: &[i64],
+        #[case] p: usize,
+        #[case] expected: i64,
+    ) {
+        let latencies = latencies
+            .iter()
+            .copied()
+            .map(Duration::milliseconds)
+            .collect_vec();
+        assert_eq!(percentile(&latencies, p), Duration::milliseconds(expected));
+    }
+
+    /// Responses and errors should be tallied separately
+    #[test]
+    fn test_repeat_report() {
+        let mut report = RepeatReport::default();
+        for status in [200, 200, 500] {
+            let exchange = Exchange {
+                response: ResponseRecord {
+                    status: StatusCode::from_u16(status).unwrap(),
+                    ..ResponseRecord::factory(())
+                }
+                .into(),
+                ..Exchange::factory(())
+            };
+            report.add(Ok(exchange));
+        }
+        report.add(Err(anyhow!("Connection refused")));
+        report.add(Err(anyhow!("Connection refused")));
+
+        assert_eq!(report.num_requests(), 5);
+        assert_eq!(report.num_error_statuses(), 1);
+        assert_eq!(report.num_unsuccessful(), 3);
+        assert_eq!(report.statuses, BTreeMap::from([(200, 2), (500, 1)]));
+        assert_eq!(
+            report.errors,
+            IndexMap::from([("Connection refused".to_owned(), 2)])
+        );
+    }
+
+    /// CSV columns are named by the header row, and JSON Lines values are
+    /// stringified. Blank lines are skipped
+    #[rstest]
+    #[case::csv("data.csv", "name,age\nAlice,30\n\"Bob, Jr.\",4\n")]
+    #[case::jsonl(
+        "data.jsonl",
+        "{\"name\": \"Alice\", \"age\": 30}\n\n\
+        {\"name\": \"Bob, Jr.\", \"age\": 4}\n"
+    )]
+    #[tokio::test]
+    async fn test_load_rows(
+        temp_dir: TempDir,
+        #[case] file_name: &str,
+        #[case] content: &str,
+    ) {
+        let path = temp_dir.join(file_name);
+        fs::write(&path, content).await.unwrap();
+        assert_eq!(
+            load_rows(&path).await.unwrap(),
+            vec![
+                indexmap! {
+                    "name".to_owned() => "Alice".to_owned(),
+                    "age".to_owned() => "30".to_owned(),
+                },
+                indexmap! {
+                    "name".to_owned() => "Bob, Jr.".to_owned(),
+                    "age".to_owned() => "4".to_owned(),
+                },
+            ]
+        );
+    }
+
+    #[rstest]
+    #[case::unknown_extension(
+        "data.txt",
+        "name\nAlice",
+        "expected `.csv` or `.jsonl`"
+    )]
+    #[case::not_object("data.jsonl", "{}\n[1, 2]", "Error on line 2")]
+    #[tokio::test]
+    async fn test_load_rows_error(
+        temp_dir: TempDir,
+        #[case] file_name: &str,
+        #[case] content: &str,
+        #[case] expected_error: &str,
+    ) {
+        let path = temp_dir.join(file_name);
+        fs::write(&path, content).await.unwrap();
+        assert_err!(load_rows(&path).await, expected_error);
+    }
+}
@@ -0,0 +1,200 @@
+use crate::{
+    cli::{
+        request::{
+            find_recipe, parse_key_val, RecipeRunner, HTTP_ERROR_EXIT_CODE,
+        },
+        Subcommand,
+    },
+    collection::{ProfileId, Recipe, RecipeId},
+    util::format_duration,
+    GlobalArgs,
+};
+use anyhow::anyhow;
+use chrono::Duration;
+use clap::Parser;
+use indexmap::IndexMap;
+use itertools::Itertools;
+use reqwest::StatusCode;
+use std::{fmt::Write, process::ExitCode};
+
+/// Execute multiple recipes in order, and print a summary of the results
+///
+/// Each request is stored in history before the next one is built, so chains
+/// in later recipes can consume responses from earlier ones. Execution stops
+/// at the first request that fails or returns an error status.
+#[derive(Clone, Debug, Parser)]
+pub struct RunCommand {
+    /// IDs of the recipes to execute, in order
+    #[clap(required = true)]
+    recipe_ids: Vec<RecipeId>,
+
+    /// ID of the profile to pull template values from. If omitted, use the
+    /// config's `default_profile`, if the collection defines it
+    #[clap(long = "profile", short, env = "SLUMBER_PROFILE")]
+    profile: Option<ProfileId>,
+
+    /// List of key=value template field overrides, applied to every recipe
+    #[clap(
+        long = "override",
+        short = 'o',
+        value_parser = parse_key_val::<String, String>,
+    )]
+    overrides: Vec<(String, String)>,
+}
+
+impl Subcommand for RunCommand {
+    async fn execute(self, global: GlobalArgs) -> anyhow::Result<ExitCode> {
+        let runner =
+            RecipeRunner::load(global, self.profile, self.overrides).await?;
+
+        // Look up every recipe before sending anything, so a typo in the last
+        // ID doesn't leave us with half a run
+        let recipes: Vec<Recipe> = self
+            .recipe_ids
+            .iter()
+            .map(|recipe_id| {
+                find_recipe(&runner.collection.recipes, recipe_id).cloned()
+            })
+            .try_collect()?;
+
+        let mut results: Vec<(RecipeId, RunOutcome)> =
+            Vec::with_capacity(recipes.len());
+        let mut failure: Option<anyhow::Error> = None;
+        for recipe in recipes {
+            let recipe_id = recipe.id.clone();
+            if failure.is_some() {
+                results.push((recipe_id, RunOutcome::Skipped));
+                continue;
+            }
+
+            let result = runner.send(&recipe, IndexMap::new()).await;
+
+            let outcome = match result {
+                Ok(exchange) => {
+                    let status = exchange.response.status;
+                    if status.as_u16() >= 400 {
+                        failure = Some(anyhow!(
+                            "Recipe `{recipe_id}` returned status {status}"
+                        ));
+                    }
+                    RunOutcome::Response {
+                        status,
+                        duration: exchange.duration(),
+                    }
+                }
+                Err(error) => {
+                    failure = Some(error.context(format!(
+                        "Error executing recipe `{recipe_id}`"
+                    )));
+                    RunOutcome::Error
+                }
+            };
+            results.push((recipe_id, outcome));
+        }
+
+        print!("{}", format_summary(&results));
+
+        match failure {
+            None => Ok(ExitCode::SUCCESS),
+            // An error status is a "successful" failure, so report it the
+            // same way `request --exit-status` does
+            Some(error)
+                if results.iter().all(|(_, outcome)| {
+                    !matches!(outcome, RunOutcome::Error)
+                }) =>
+            {
+                eprintln!("{error}");
+                Ok(ExitCode::from(HTTP_ERROR_EXIT_CODE))
+            }
+            Some(error) => Err(error),
+        }
+    }
+}
+
+/// Result of a single recipe in a run
+#[derive(Debug)]
+enum RunOutcome {
+    /// We got a response. It may still have an error status
+    Response {
+        status: StatusCode,
+        duration: Duration,
+    },
+    /// Request failed to build or send
+    Error,
+    /// Not executed because an earlier recipe failed
+    Skipped,
+}
+
+/// Format the results of a run as an aligned table
+fn format_summary(results: &[(RecipeId, RunOutcome)]) -> String {
+    let rows = results
+        .iter()
+        .map(|(recipe_id, outcome)| {
+            let (status, duration) = match outcome {
+                RunOutcome::Response { status, duration } => {
+                    (status.to_string(), format_duration(duration))
+                }
+                RunOutcome::Error => ("error".into(), "-".into()),
+                RunOutcome::Skipped => ("skipped".into(), "-".into()),
+            };
+            [recipe_id.to_string(), status, duration]
+        })
+        .collect_vec();
+    let header = ["RECIPE", "STATUS", "DURATION"].map(String::from);
+
+    // Pad each column to fit its widest cell. The last column doesn't need
+    // padding
+    let widths = [0, 1].map(|column| {
+        rows.iter()
+            .chain([&header])
+            .map(|row| row[column].chars().count())
+            .max()
+            .unwrap_or_default()
+    });
+    let mut output = String::new();
+    for [recipe, status, duration] in [&header].into_iter().chain(&rows) {
+        // Writing to a string can't fail
+        let _ = writeln!(
+            output,
+            "{recipe:<w0$}  {status:<w1$}  {duration}",
+            w0 = widths[0],
+            w1 = widths[1],
+        );
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_summary() {
+        let results = [
+            (
+                "login".into(),
+                RunOutcome::Response {
+                    status: StatusCode::OK,
+                    duration: Duration::milliseconds(120),
+                },
+            ),
+            (
+                "get_fishes".into(),
+                RunOutcome::Response {
+                    status: StatusCode::NOT_FOUND,
+                    duration: Duration::milliseconds(1500),
+                },
+            ),
+            ("logout".into(), RunOutcome::Skipped),
+        ];
+        assert_eq!(
+            format_summary(&results),
+            "\
+RECIPE      STATUS         DURATION
+login       200 OK         120ms
+get_fishes  404 Not Found  1.50s
+logout      skipped        -
+"
+        );
+    }
+}
@@ -0,0 +1,187 @@
+use crate::{
+    cli::{request::validate_profile, Subcommand},
+    collection::{
+        secrets::{self, SecretsFile},
+        CollectionFile, ProfileId,
+    },
+    util::keyring_service,
+    GlobalArgs,
+};
+use anyhow::{bail, Context};
+use clap::Parser;
+use dialoguer::Password;
+use std::{path::Path, process::ExitCode};
+use tokio::task;
+
+/// Manage secrets in the OS keyring, for use in `!keyring` chains, and
+/// encrypted profile fields
+#[derive(Clone, Debug, Parser)]
+pub struct SecretCommand {
+    #[command(subcommand)]
+    subcommand: SecretSubcommand,
+}
+
+#[derive(Clone, Debug, clap::Subcommand)]
+enum SecretSubcommand {
+    /// Store a secret in the keyring. The value will be read from a hidden
+    /// prompt, so it doesn't end up in your shell history
+    Set {
+        /// Key of the secret, to be referenced by a chain's `key` field
+        key: String,
+        /// Keyring service to store the secret under. If omitted, the secret
+        /// is stored specifically for the current collection
+        #[clap(long)]
+        service: Option<String>,
+    },
+    /// Delete a secret from the keyring
+    #[command(visible_alias = "rm")]
+    Delete {
+        /// Key of the secret
+        key: String,
+        /// Keyring service the secret is stored under. If omitted, use the
+        /// service for the current collection
+        #[clap(long)]
+        service: Option<String>,
+    },
+    /// Encrypt a value for a profile field and store it in the local secrets
+    /// file. The value will be read from a hidden prompt. The field must also
+    /// be listed in the profile's `encrypted` field
+    Encrypt {
+        /// Profile the field belongs to
+        profile: ProfileId,
+        /// Name of the field
+        field: String,
+    },
+    /// Delete the encrypted value for a profile field
+    Forget {
+        /// Profile the field belongs to
+        profile: ProfileId,
+        /// Name of the field
+        field: String,
+    },
+}
+
+impl Subcommand for SecretCommand {
+    async fn execute(self, global: GlobalArgs) -> anyhow::Result<ExitCode> {
+        // Resolve the service name the same way the template renderer does,
+        // so the stored value is found by `!keyring` chains in this collection
+        let get_service = |service: Option<String>| -> anyhow::Result<String> {
+            match service {
+                Some(service) => Ok(service),
+                None => {
+                    let path =
+                        CollectionFile::try_path(None, global.file.clone())?;
+                    let path = path.canonicalize().with_context(|| {
+                        format!("Error canonicalizing path {path:?}")
+                    })?;
+                    Ok(keyring_service(&path))
+                }
+            }
+        };
+
+        // Keyring calls are synchronous IPC, so they run on a blocking thread
+        match self.subcommand {
+            SecretSubcommand::Set { key, service } => {
+                let service = get_service(service)?;
+                let value = Password::new()
+                    .with_prompt(format!("Value for `{key}`"))
+                    .interact()
+                    .context("Error reading secret value")?;
+                let result = task::spawn_blocking({
+                    let (service, key) = (service.clone(), key.clone());
+                    move || {
+                        keyring::Entry::new(&service, &key)?
+                            .set_password(&value)
+                    }
+                })
+                .await?;
+                result.with_context(|| {
+                    format!(
+                        "Error storing key `{key}` in keyring service \
+                        `{service}`"
+                    )
+                })?;
+                println!("Stored `{key}` in keyring service `{service}`");
+            }
+            SecretSubcommand::Delete { key, service } => {
+                let service = get_service(service)?;
+                let result = task::spawn_blocking({
+                    let (service, key) = (service.clone(), key.clone());
+                    move || {
+                        keyring::Entry::new(&service, &key)?.delete_password()
+                    }
+                })
+                .await?;
+                result.with_context(|| {
+                    format!(
+                        "Error deleting key `{key}` from keyring service \
+                        `{service}`"
+                    )
+                })?;
+                println!("Deleted `{key}` from keyring service `{service}`");
+            }
+            SecretSubcommand::Encrypt { profile, field } => {
+                let path = CollectionFile::try_path(None, global.file)?;
+                let collection =
+                    CollectionFile::load(path.clone()).await?.collection;
+                validate_profile(&collection, &profile)?;
+                let path = path.canonicalize()?;
+
+                let mut file = SecretsFile::load(&path)?
+                    .unwrap_or_else(SecretsFile::create);
+                let passphrase = get_passphrase(&path, &file)?;
+                let value = Password::new()
+                    .with_prompt(format!("Value for `{profile}.{field}`"))
+                    .interact()
+                    .context("Error reading secret value")?;
+                file.set(&passphrase, &profile, &field, &value)?;
+                file.save(&path)?;
+                println!("Encrypted `{profile}.{field}`");
+
+                if !collection.profiles[&profile].encrypted.contains(&field) {
+                    println!(
+                        "Add `{field}` to the `encrypted` list of profile \
+                        `{profile}` to use it"
+                    );
+                }
+            }
+            SecretSubcommand::Forget { profile, field } => {
+                let path = CollectionFile::try_path(None, global.file)?
+                    .canonicalize()?;
+                let file = SecretsFile::load(&path)?;
+                let Some(mut file) =
+                    file.filter(|file| file.contains(&profile, &field))
+                else {
+                    bail!("No encrypted value for `{profile}.{field}`");
+                };
+                file.remove(&profile, &field);
+                file.save(&path)?;
+                println!("Deleted encrypted value for `{profile}.{field}`");
+            }
+        }
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+/// Get the passphrase for a collection's secrets file. If the file has no
+/// values yet, the user picks a new passphrase. Otherwise, the passphrase is
+/// checked against the existing values so they all use the same one.
+fn get_passphrase(
+    collection_path: &Path,
+    file: &SecretsFile,
+) -> anyhow::Result<String> {
+    let passphrase = match secrets::passphrase(collection_path)? {
+        Some(passphrase) => passphrase,
+        None if file.is_empty() => Password::new()
+            .with_prompt("New passphrase for encrypted profile fields")
+            .with_confirmation("Confirm passphrase", "Passphrases don't match")
+            .interact()
+            .context("Error reading passphrase")?,
+        None => Password::new()
+            .with_prompt("Passphrase for encrypted profile fields")
+            .interact()
+            .context("Error reading passphrase")?,
+    };
+    file.verify(&passphrase)?;
+    Ok(passphrase)
+}
@@ -0,0 +1,206 @@
+use crate::{
+    cli::{
+        request::{parse_key_val, RecipeRunner, HTTP_ERROR_EXIT_CODE},
+        Subcommand,
+    },
+    collection::{Folder, ProfileId, Recipe, RecipeId, RecipeNode},
+    http::{ContentType, ProtobufRegistry, ResponseRecord},
+    GlobalArgs,
+};
+use anyhow::{anyhow, Context};
+use clap::Parser;
+use indexmap::IndexMap;
+use std::process::ExitCode;
+
+/// Seed an environment by executing every recipe in a folder
+///
+/// Recipes are executed in order, including those in nested folders, and
+/// execution stops at the first request that fails or returns an error status.
+/// After each successful request, the recipe's captures are printed to stdout
+/// as `name=value`, so they can be consumed by a script. Captured values are
+/// also available to later recipes as `{{captures.<name>}}`. Progress is
+/// printed to stderr.
+#[derive(Clone, Debug, Parser)]
+pub struct SeedCommand {
+    /// ID of the folder containing the setup recipes
+    folder_id: RecipeId,
+
+    /// ID of the profile to pull template values from. If omitted, use the
+    /// config's `default_profile`, if the collection defines it
+    #[clap(long = "profile", short, env = "SLUMBER_PROFILE")]
+    profile: Option<ProfileId>,
+
+    /// List of key=value template field overrides, applied to every recipe
+    #[clap(
+        long = "override",
+        short = 'o',
+        value_parser = parse_key_val::<String, String>,
+    )]
+    overrides: Vec<(String, String)>,
+}
+
+impl Subcommand for SeedCommand {
+    async fn execute(self, global: GlobalArgs) -> anyhow::Result<ExitCode> {
+        let runner =
+            RecipeRunner::load(global, self.profile, self.overrides).await?;
+        let folder = runner
+            .collection
+            .recipes
+            .get(&self.folder_id)
+            .and_then(RecipeNode::folder)
+            .ok_or_else(|| anyhow!("No folder with ID `{}`", self.folder_id))?;
+        let mut recipes = Vec::new();
+        collect_recipes(folder, &mut recipes);
+
+        // Values captured so far, which later recipes can use
+        let mut all_captures = IndexMap::new();
+        for recipe in recipes {
+            let exchange = runner
+                .send(recipe, all_captures.clone())
+                .await
+                .with_context(|| {
+                    format!("Error executing recipe `{}`", recipe.id)
+                })?;
+
+            let status = exchange.response.status;
+            eprintln!("{} {status}", recipe.id);
+            if status.as_u16() >= 400 {
+                eprintln!("Recipe `{}` returned status {status}", recipe.id);
+                return Ok(ExitCode::from(HTTP_ERROR_EXIT_CODE));
+            }
+
+            let captures = capture(
+                recipe,
+                &exchange.response,
+                runner.collection.protobuf_registry.as_ref(),
+            )
+            .with_context(|| {
+                format!("Error capturing values for recipe `{}`", recipe.id)
+            })?;
+            for (name, value) in captures {
+                println!("{name}={value}");
+                all_captures.insert(name, value);
+            }
+        }
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+/// Get all recipes in a folder, depth first, in the order they're defined
+fn collect_recipes<'a>(folder: &'a Folder, recipes: &mut Vec<&'a Recipe>) {
+    for node in folder.children.values() {
+        match node {
+            RecipeNode::Recipe(recipe) => recipes.push(recipe),
+            RecipeNode::Folder(folder) => collect_recipes(folder, recipes),
+        }
+    }
+}
+
+/// Extract each of a recipe's captures from its response
+fn capture(
+    recipe: &Recipe,
+    response: &ResponseRecord,
+    protobuf: Option<&ProtobufRegistry>,
+) -> anyhow::Result<IndexMap<String, String>> {
+    if recipe.captures.is_empty() {
+        return Ok(IndexMap::new());
+    }
+    let content = ContentType::parse_response(response, protobuf)?;
+    recipe
+        .captures
+        .iter()
+        .map(|(name, query)| {
+            let value =
+                query.query_to_string(&*content).with_context(|| {
+                    format!("Error querying `{name}` with `{query}`")
+                })?;
+            Ok((name.clone(), value))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{assert_err, header_map, Factory};
+    use indexmap::indexmap;
+    use reqwest::StatusCode;
+    use serde_json::json;
+
+    fn response() -> ResponseRecord {
+        ResponseRecord {
+            status: StatusCode::CREATED,
+            headers: header_map([("content-type", "application/json")]),
+            body: json!({"id": 3, "owner": {"name": "Jimmy"}})
+                .to_string()
+                .into_bytes()
+                .into(),
+            ..ResponseRecord::factory(())
+        }
+    }
+
+    #[test]
+    fn test_capture() {
+        let recipe = Recipe {
+            captures: indexmap! {
+                "fish_id".into() => "$.id".parse().unwrap(),
+                "owner".into() => "$.owner.name".parse().unwrap(),
+            },
+            ..Recipe::factory(())
+        };
+        assert_eq!(
+            capture(&recipe, &response(), None).unwrap(),
+            indexmap! {
+                "fish_id".to_owned() => "3".to_owned(),
+                "owner".to_owned() => "Jimmy".to_owned(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_capture_error() {
+        let recipe = Recipe {
+            captures: indexmap! {"fish_id".into() => "$.bad".parse().unwrap()},
+            ..Recipe::factory(())
+        };
+        assert_err!(
+            capture(&recipe, &response(), None),
+            "Expected exactly one result from query"
+        );
+    }
+
+    #[test]
+    fn test_collect_recipes() {
+        let folder = Folder {
+            id: "setup".into(),
+            name: None,
+            defaults: Default::default(),
+            children: indexmap! {
+                "a".into() => RecipeNode::Recipe(Recipe {
+                    id: "a".into(),
+                    ..Recipe::factory(())
+                }),
+                "inner".into() => RecipeNode::Folder(Folder {
+                    id: "inner".into(),
+                    name: None,
+                    defaults: Default::default(),
+                    children: indexmap! {
+                        "b".into() => RecipeNode::Recipe(Recipe {
+                            id: "b".into(),
+                            ..Recipe::factory(())
+                        }),
+                    },
+                }),
+                "c".into() => RecipeNode::Recipe(Recipe {
+                    id: "c".into(),
+                    ..Recipe::factory(())
+                }),
+            },
+        };
+        let mut recipes = Vec::new();
+        collect_recipes(&folder, &mut recipes);
+        let ids: Vec<&str> =
+            recipes.iter().map(|recipe| recipe.id.as_str()).collect();
+        assert_eq!(ids, ["a", "b", "c"]);
+    }
+}
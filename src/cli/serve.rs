@@ -0,0 +1,277 @@
+use crate::{
+    cli::{
+        api::{ApiBackend, ApiError, RenderOptions},
+        Subcommand,
+    },
+    collection::RecipeId,
+    GlobalArgs,
+};
+use anyhow::{bail, Context};
+use clap::Parser;
+use reqwest::StatusCode;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::{process::ExitCode, sync::Arc};
+use tokio::{
+    io::{self, AsyncBufReadExt, AsyncWriteExt, BufReader},
+    sync::mpsc::{self, UnboundedSender},
+};
+use tracing::info;
+
+/// Version of the stdio protocol. Bumped on any breaking change, so clients
+/// can check compatibility during `initialize`
+const PROTOCOL_VERSION: u32 = 1;
+
+/// Serve the collection to an editor extension or other tool
+///
+/// Messages are JSON-RPC 2.0, one JSON object per line. Requests are handled
+/// concurrently, so responses may arrive out of order. See the docs for the
+/// list of methods.
+#[derive(Clone, Debug, Parser)]
+pub struct ServeCommand {
+    /// Communicate over stdin/stdout. This is currently the only supported
+    /// transport; for HTTP, see `slumber curl-proxy`
+    #[clap(long)]
+    stdio: bool,
+}
+
+impl Subcommand for ServeCommand {
+    async fn execute(self, global: GlobalArgs) -> anyhow::Result<ExitCode> {
+        if !self.stdio {
+            bail!("No transport specified; pass `--stdio`");
+        }
+        let backend = Arc::new(ApiBackend::load(global)?);
+
+        // All output goes through a single task, so concurrent handlers can't
+        // interleave their messages
+        let (tx, mut rx) = mpsc::unbounded_channel::<Value>();
+        let writer = tokio::spawn(async move {
+            let mut stdout = io::stdout();
+            while let Some(message) = rx.recv().await {
+                let mut line = serde_json::to_vec(&message)?;
+                line.push(b'\n');
+                stdout.write_all(&line).await?;
+                stdout.flush().await?;
+            }
+            anyhow::Ok(())
+        });
+
+        let mut lines = BufReader::new(io::stdin()).lines();
+        while let Some(line) =
+            lines.next_line().await.context("Error reading stdin")?
+        {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let backend = Arc::clone(&backend);
+            let tx = tx.clone();
+            tokio::spawn(
+                async move { handle_message(&backend, &tx, &line).await },
+            );
+        }
+
+        // Stdin closed, which means the client is done with us. Wait for
+        // in-flight handlers to finish writing, then exit
+        drop(tx);
+        writer.await??;
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+/// An incoming JSON-RPC message. If `id` is missing, it's a notification and
+/// gets no response.
+#[derive(Debug, Deserialize)]
+struct RpcRequest {
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+/// Params for `template/render`
+#[derive(Debug, Deserialize)]
+struct RenderParams {
+    template: String,
+    #[serde(flatten)]
+    options: RenderOptions,
+}
+
+/// Params for `request/build` and `request/send`
+#[derive(Debug, Deserialize)]
+struct RecipeParams {
+    recipe: RecipeId,
+    #[serde(flatten)]
+    options: RenderOptions,
+}
+
+/// JSON-RPC error, with a standard code where one applies
+#[derive(Debug, Serialize)]
+struct RpcError {
+    code: i32,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<Value>,
+}
+
+impl RpcError {
+    const PARSE_ERROR: i32 = -32700;
+    const METHOD_NOT_FOUND: i32 = -32601;
+    const INVALID_PARAMS: i32 = -32602;
+    /// Start of the range reserved for implementation-defined server errors
+    const SERVER_ERROR: i32 = -32000;
+
+    fn new(code: i32, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+}
+
+/// Map the error's status to a JSON-RPC code, and include the status itself so
+/// clients can make finer distinctions
+impl From<ApiError> for RpcError {
+    fn from(error: ApiError) -> Self {
+        let code = match error.status {
+            StatusCode::BAD_REQUEST | StatusCode::NOT_FOUND => {
+                Self::INVALID_PARAMS
+            }
+            _ => Self::SERVER_ERROR,
+        };
+        Self {
+            code,
+            message: error.message(),
+            data: Some(json!({"status": error.status.as_u16()})),
+        }
+    }
+}
+
+/// Parse and handle a single message, then send the response (if any)
+async fn handle_message(
+    backend: &ApiBackend,
+    tx: &UnboundedSender<Value>,
+    line: &str,
+) {
+    let request: RpcRequest = match serde_json::from_str(line) {
+        Ok(request) => request,
+        Err(error) => {
+            let error = RpcError::new(RpcError::PARSE_ERROR, error.to_string());
+            let _ = tx.send(response(Value::Null, Err(error)));
+            return;
+        }
+    };
+    info!(method = %request.method, id = ?request.id, "RPC request");
+
+    // Notifications emitted during a call include the call's ID, so the
+    // client can tie them together
+    let notify = |method: &str, params: Value| {
+        let _ = tx.send(json!({
+            "jsonrpc": "2.0",
+            "method": method,
+            "params": {"id": request.id, "data": params},
+        }));
+    };
+    let result =
+        handle_request(backend, &request.method, request.params, notify).await;
+    // Notifications don't get a response, even if they fail
+    if let Some(id) = request.id {
+        let _ = tx.send(response(id, result));
+    }
+}
+
+/// Execute a single method call
+async fn handle_request(
+    backend: &ApiBackend,
+    method: &str,
+    params: Value,
+    notify: impl Fn(&str, Value),
+) -> Result<Value, RpcError> {
+    match method {
+        "initialize" => Ok(json!({
+            "protocol_version": PROTOCOL_VERSION,
+            "slumber_version": env!("CARGO_PKG_VERSION"),
+        })),
+        "recipes/list" => Ok(backend.list_recipes().await?),
+        "profiles/list" => Ok(backend.list_profiles().await?),
+        "template/render" => {
+            let params: RenderParams = parse_params(params)?;
+            Ok(backend.render(&params.template, params.options).await?)
+        }
+        "request/build" => {
+            let params: RecipeParams = parse_params(params)?;
+            let ticket = backend.build(&params.recipe, params.options).await?;
+            Ok(ApiBackend::request_value(&ticket)?)
+        }
+        "request/send" => {
+            let mut params: RecipeParams = parse_params(params)?;
+            // Sending already has side effects, so triggers are fine
+            params.options.execute_triggers = true;
+            let ticket = backend.build(&params.recipe, params.options).await?;
+            // Let the client show the request while waiting on the response
+            notify(
+                "request/built",
+                json!({
+                    "recipe": params.recipe,
+                    "request": ApiBackend::request_value(&ticket)?,
+                }),
+            );
+            Ok(backend.send(ticket).await?)
+        }
+        _ => Err(RpcError::new(
+            RpcError::METHOD_NOT_FOUND,
+            format!("Unknown method `{method}`"),
+        )),
+    }
+}
+
+fn parse_params<T: DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    serde_json::from_value(params).map_err(|error| {
+        RpcError::new(RpcError::INVALID_PARAMS, error.to_string())
+    })
+}
+
+/// Build a JSON-RPC response message
+fn response(id: Value, result: Result<Value, RpcError>) -> Value {
+    match result {
+        Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+        Err(error) => json!({"jsonrpc": "2.0", "id": id, "error": error}),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::bad_request(StatusCode::BAD_REQUEST, RpcError::INVALID_PARAMS)]
+    #[case::not_found(StatusCode::NOT_FOUND, RpcError::INVALID_PARAMS)]
+    #[case::upstream(StatusCode::BAD_GATEWAY, RpcError::SERVER_ERROR)]
+    fn test_error_code(#[case] status: StatusCode, #[case] expected: i32) {
+        let error: RpcError =
+            ApiError::new(status, anyhow::anyhow!("Error!")).into();
+        assert_eq!(error.code, expected);
+        assert_eq!(error.message, "Error!");
+        assert_eq!(error.data, Some(json!({"status": status.as_u16()})));
+    }
+
+    #[test]
+    fn test_response() {
+        assert_eq!(
+            response(json!(1), Ok(json!({"a": 1}))),
+            json!({"jsonrpc": "2.0", "id": 1, "result": {"a": 1}})
+        );
+        assert_eq!(
+            response(
+                json!("x"),
+                Err(RpcError::new(RpcError::METHOD_NOT_FOUND, "nope"))
+            ),
+            json!({
+                "jsonrpc": "2.0",
+                "id": "x",
+                "error": {"code": -32601, "message": "nope"},
+            })
+        );
+    }
+}
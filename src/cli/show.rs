@@ -1,6 +1,10 @@
 use crate::{
-    cli::Subcommand, collection::CollectionFile, config::Config, db::Database,
-    util::paths::DataDirectory, GlobalArgs,
+    cli::Subcommand,
+    collection::{collection_schema, CollectionFile},
+    config::Config,
+    db::Database,
+    util::paths::DataDirectory,
+    GlobalArgs,
 };
 use clap::Parser;
 use serde::Serialize;
@@ -17,10 +21,17 @@ pub struct ShowCommand {
 enum ShowTarget {
     /// Print the path of all directories/files that Slumber uses
     Paths,
-    /// Print loaded configuration
+    /// Print loaded configuration, including the collection's project config
     Config,
     /// Print current request collection
     Collection,
+    /// Print a JSON Schema for the collection file format
+    ///
+    /// Point your editor's YAML plugin at this to get validation and
+    /// completion for collection files. YAML tags (e.g. `!request`) can't be
+    /// expressed in JSON Schema, so tagged values are described by their
+    /// content alone.
+    Schema,
 }
 
 impl Subcommand for ShowCommand {
@@ -32,6 +43,12 @@ impl Subcommand for ShowCommand {
                 println!("Data directory: {}", DataDirectory::root());
                 println!("Log file: {}", DataDirectory::log());
                 println!("Config: {}", Config::path());
+                if let Ok(collection_path) = &collection_path {
+                    println!(
+                        "Project config: {}",
+                        Config::project_path(collection_path).display()
+                    );
+                }
                 println!("Database: {}", Database::path());
                 println!(
                     "Collection: {}",
@@ -42,7 +59,10 @@ impl Subcommand for ShowCommand {
                 )
             }
             ShowTarget::Config => {
-                let config = Config::load()?;
+                // Include the project config if we're in a project
+                let collection_path =
+                    CollectionFile::try_path(None, global.file).ok();
+                let config = Config::load(collection_path.as_deref())?;
                 println!("{}", to_yaml(&config));
             }
             ShowTarget::Collection => {
@@ -52,6 +72,12 @@ impl Subcommand for ShowCommand {
                     CollectionFile::load(collection_path).await?;
                 println!("{}", to_yaml(&collection_file.collection));
             }
+            ShowTarget::Schema => {
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(&collection_schema())?
+                );
+            }
         }
         Ok(ExitCode::SUCCESS)
     }
@@ -0,0 +1,219 @@
+use crate::{
+    cli::{request::percentile, Subcommand},
+    collection::{serde_duration, CollectionFile, ProfileId, RecipeId},
+    db::Database,
+    http::ExchangeSummary,
+    util::format_duration,
+    GlobalArgs,
+};
+use anyhow::anyhow;
+use chrono::{Duration, Utc};
+use clap::{Parser, ValueEnum};
+use serde::Serialize;
+use std::{
+    fmt::{self, Display},
+    process::ExitCode,
+};
+
+/// Summarize latency and error rate for a recipe from request history
+///
+/// Only requests stored in history are included, so anything deleted with
+/// `slumber db prune` isn't counted.
+#[derive(Clone, Debug, Parser)]
+pub struct StatsCommand {
+    /// ID of the recipe to summarize
+    recipe_id: RecipeId,
+
+    /// Only include requests sent with this profile. If omitted, include
+    /// requests for all profiles
+    #[clap(long = "profile", short)]
+    profile: Option<ProfileId>,
+
+    /// Only include requests sent within this long ago (e.g. `7d`, `12h`)
+    #[clap(long, value_parser = serde_duration::parse)]
+    since: Option<std::time::Duration>,
+
+    /// Output format
+    #[clap(long, value_enum, default_value_t = StatsFormat::Table)]
+    format: StatsFormat,
+}
+
+/// Output format for `stats`
+#[derive(Copy, Clone, Debug, ValueEnum)]
+enum StatsFormat {
+    /// Human-readable summary
+    Table,
+    /// JSON object
+    Json,
+}
+
+impl Subcommand for StatsCommand {
+    async fn execute(self, global: GlobalArgs) -> anyhow::Result<ExitCode> {
+        let collection_path = CollectionFile::try_path(None, global.file)?;
+        let database = Database::load()?.into_collection(&collection_path)?;
+
+        let cutoff = self
+            .since
+            .map(|since| {
+                let since = Duration::from_std(since)
+                    .map_err(|_| anyhow!("Duration is too large"))?;
+                anyhow::Ok(Utc::now() - since)
+            })
+            .transpose()?;
+        let exchanges: Vec<_> = database
+            .get_recipe_requests(&self.recipe_id)?
+            .into_iter()
+            .filter(|exchange| {
+                self.profile.as_ref().map_or(true, |profile| {
+                    exchange.profile_id.as_ref() == Some(profile)
+                }) && cutoff
+                    .map_or(true, |cutoff| exchange.start_time >= cutoff)
+            })
+            .collect();
+
+        let stats = Stats::new(&exchanges);
+        match self.format {
+            StatsFormat::Table => print!("{stats}"),
+            StatsFormat::Json => {
+                println!("{}", serde_json::to_string_pretty(&stats)?)
+            }
+        }
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+/// Aggregated stats for a set of stored requests. Latencies are in
+/// milliseconds, and are absent if there are no requests.
+#[derive(Debug, PartialEq, Serialize)]
+struct Stats {
+    count: usize,
+    /// Number of responses with a 4xx or 5xx status
+    errors: usize,
+    /// Fraction of requests that got an error status, from 0 to 1
+    error_rate: f64,
+    p50_ms: Option<i64>,
+    p95_ms: Option<i64>,
+    p99_ms: Option<i64>,
+}
+
+impl Stats {
+    fn new(exchanges: &[ExchangeSummary]) -> Self {
+        let count = exchanges.len();
+        let errors = exchanges
+            .iter()
+            .filter(|exchange| {
+                exchange.status.is_client_error()
+                    || exchange.status.is_server_error()
+            })
+            .count();
+        let mut latencies: Vec<Duration> = exchanges
+            .iter()
+            .map(|exchange| exchange.end_time - exchange.start_time)
+            .collect();
+        latencies.sort();
+        let p = |p: usize| {
+            (!latencies.is_empty())
+                .then(|| percentile(&latencies, p).num_milliseconds())
+        };
+
+        Self {
+            count,
+            errors,
+            error_rate: if count == 0 {
+                0.0
+            } else {
+                errors as f64 / count as f64
+            },
+            p50_ms: p(50),
+            p95_ms: p(95),
+            p99_ms: p(99),
+        }
+    }
+}
+
+impl Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Requests:   {}", self.count)?;
+        writeln!(
+            f,
+            "Errors:     {} ({:.1}%)",
+            self.errors,
+            self.error_rate * 100.0
+        )?;
+        if let (Some(p50), Some(p95), Some(p99)) =
+            (self.p50_ms, self.p95_ms, self.p99_ms)
+        {
+            let format = |ms| format_duration(&Duration::milliseconds(ms));
+            writeln!(
+                f,
+                "Latency:    p50 {} | p95 {} | p99 {}",
+                format(p50),
+                format(p95),
+                format(p99),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::RequestId;
+    use reqwest::StatusCode;
+
+    fn exchange(status: u16, latency_ms: i64) -> ExchangeSummary {
+        let start_time = Utc::now();
+        ExchangeSummary {
+            id: RequestId::new(),
+            profile_id: None,
+            label: None,
+            start_time,
+            end_time: start_time + Duration::milliseconds(latency_ms),
+            status: StatusCode::from_u16(status).unwrap(),
+            request_size: None,
+            response_size: None,
+        }
+    }
+
+    #[test]
+    fn test_stats() {
+        let exchanges: Vec<_> = (1..=100)
+            .map(|i| exchange(if i % 10 == 0 { 500 } else { 200 }, i * 10))
+            .collect();
+        let stats = Stats::new(&exchanges);
+        assert_eq!(
+            stats,
+            Stats {
+                count: 100,
+                errors: 10,
+                error_rate: 0.1,
+                p50_ms: Some(500),
+                p95_ms: Some(950),
+                p99_ms: Some(990),
+            }
+        );
+        assert_eq!(
+            stats.to_string(),
+            "Requests:   100\nErrors:     10 (10.0%)\n\
+            Latency:    p50 500ms | p95 950ms | p99 990ms\n"
+        );
+    }
+
+    #[test]
+    fn test_stats_empty() {
+        let stats = Stats::new(&[]);
+        assert_eq!(
+            stats,
+            Stats {
+                count: 0,
+                errors: 0,
+                error_rate: 0.0,
+                p50_ms: None,
+                p95_ms: None,
+                p99_ms: None,
+            }
+        );
+        assert_eq!(stats.to_string(), "Requests:   0\nErrors:     0 (0.0%)\n");
+    }
+}
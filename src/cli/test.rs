@@ -0,0 +1,115 @@
+use crate::{
+    cli::{
+        request::{find_recipe, parse_key_val, RecipeRunner},
+        Subcommand,
+    },
+    collection::{ProfileId, Recipe, RecipeId},
+    GlobalArgs,
+};
+use clap::Parser;
+use dialoguer::console::Style;
+use indexmap::IndexMap;
+use itertools::Itertools;
+use std::process::ExitCode;
+
+/// Send requests and check their responses against the recipes' assertions
+///
+/// Recipes are executed in order, and every response is stored in history, so
+/// chains in later recipes can consume responses from earlier ones. Unlike
+/// `slumber run`, a failure does not stop the remaining recipes.
+#[derive(Clone, Debug, Parser)]
+pub struct TestCommand {
+    /// IDs of the recipes to test. If omitted, test every recipe in the
+    /// collection that has at least one assertion
+    recipe_ids: Vec<RecipeId>,
+
+    /// ID of the profile to pull template values from. If omitted, use the
+    /// config's `default_profile`, if the collection defines it
+    #[clap(long = "profile", short, env = "SLUMBER_PROFILE")]
+    profile: Option<ProfileId>,
+
+    /// List of key=value template field overrides, applied to every recipe
+    #[clap(
+        long = "override",
+        short = 'o',
+        value_parser = parse_key_val::<String, String>,
+    )]
+    overrides: Vec<(String, String)>,
+}
+
+impl Subcommand for TestCommand {
+    async fn execute(self, global: GlobalArgs) -> anyhow::Result<ExitCode> {
+        let runner =
+            RecipeRunner::load(global, self.profile, self.overrides).await?;
+
+        let recipes: Vec<Recipe> = if self.recipe_ids.is_empty() {
+            runner
+                .collection
+                .recipes
+                .iter()
+                .filter_map(|(_, node)| node.recipe())
+                .filter(|recipe| !recipe.assertions.is_empty())
+                .cloned()
+                .collect()
+        } else {
+            self.recipe_ids
+                .iter()
+                .map(|recipe_id| {
+                    find_recipe(&runner.collection.recipes, recipe_id).cloned()
+                })
+                .try_collect()?
+        };
+        if recipes.is_empty() {
+            println!("No recipes with assertions");
+            return Ok(ExitCode::SUCCESS);
+        }
+
+        let pass_style = Style::new().green().bold();
+        let fail_style = Style::new().red().bold();
+        let mut num_failed = 0;
+        for recipe in &recipes {
+            let result = runner.send(recipe, IndexMap::new()).await;
+
+            // Collect a message for each failed assertion. If the request
+            // itself failed, that's the only message
+            let failures: Vec<String> = match result {
+                Ok(exchange) => recipe
+                    .assertions
+                    .iter()
+                    .filter_map(|assertion| {
+                        assertion
+                            .check(
+                                &exchange.response,
+                                runner.collection.protobuf_registry.as_ref(),
+                            )
+                            .err()
+                    })
+                    // Alternate format includes the error's sources
+                    .map(|error| format!("{:#}", anyhow::Error::from(error)))
+                    .collect(),
+                Err(error) => vec![format!("{error:#}")],
+            };
+
+            if failures.is_empty() {
+                println!("{} {}", pass_style.apply_to("PASS"), recipe.id);
+            } else {
+                num_failed += 1;
+                println!("{} {}", fail_style.apply_to("FAIL"), recipe.id);
+                for failure in failures {
+                    println!("  {failure}");
+                }
+            }
+        }
+
+        println!(
+            "\n{} passed, {} failed",
+            recipes.len() - num_failed,
+            num_failed
+        );
+        Ok(if num_failed > 0 {
+            ExitCode::FAILURE
+        } else {
+            ExitCode::SUCCESS
+        })
+    }
+}
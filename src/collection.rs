@@ -2,16 +2,33 @@
 //! possible
 
 mod cereal;
+mod format;
+mod import;
+mod insert;
 mod insomnia;
 mod models;
+mod openapi;
 mod recipe_tree;
+mod schema;
+pub mod secrets;
+mod validate;
 
-pub use cereal::HasId;
+pub use cereal::{serde_duration, HasId};
+pub use format::format_collection;
+pub use import::ImportReport;
+pub use insert::{
+    insert_profile, insert_recipe, referenced_fields, NewProfile, NewRecipe,
+};
 pub use models::*;
 pub use recipe_tree::*;
+pub use schema::collection_schema;
+pub use validate::validate_collection;
 
-use crate::util::{parse_yaml, ResultExt};
-use anyhow::{anyhow, Context};
+use crate::{
+    http::ProtobufRegistry,
+    util::{parse_yaml, ResultExt},
+};
+use anyhow::{anyhow, bail, Context};
 use itertools::Itertools;
 use std::{
     env,
@@ -68,6 +85,58 @@ impl CollectionFile {
         load_collection(self.path.clone())
     }
 
+    /// Add a new recipe to the end of the collection file, and return its
+    /// generated ID. The in-memory collection is *not* updated; reload it
+    /// afterward to pick up the new recipe.
+    ///
+    /// Returns `impl Future` to unlink the future from `&self`'s lifetime.
+    pub fn add_recipe(
+        &self,
+        recipe: NewRecipe,
+    ) -> impl Future<Output = anyhow::Result<RecipeId>> {
+        let path = self.path.clone();
+        let id = recipe.id(&self.collection.recipes);
+        async move {
+            info!(?path, %id, "Adding recipe to collection file");
+            let input = tokio::fs::read_to_string(&path)
+                .await
+                .with_context(|| format!("Error reading {path:?}"))?;
+            let output = insert_recipe(&input, &id, &recipe)?;
+            tokio::fs::write(&path, output)
+                .await
+                .with_context(|| format!("Error writing {path:?}"))?;
+            Ok(id)
+        }
+    }
+
+    /// Add a new profile to the end of the collection file. Return an error
+    /// if the ID is already taken. The in-memory collection is *not* updated;
+    /// reload it afterward to pick up the new profile.
+    ///
+    /// Returns `impl Future` to unlink the future from `&self`'s lifetime.
+    pub fn add_profile(
+        &self,
+        id: ProfileId,
+        profile: NewProfile,
+    ) -> impl Future<Output = anyhow::Result<()>> {
+        let path = self.path.clone();
+        let exists = self.collection.profiles.contains_key(&id);
+        async move {
+            if exists {
+                bail!("Profile `{id}` already exists");
+            }
+            info!(?path, %id, "Adding profile to collection file");
+            let input = tokio::fs::read_to_string(&path)
+                .await
+                .with_context(|| format!("Error reading {path:?}"))?;
+            let output = insert_profile(&input, &id, &profile)?;
+            tokio::fs::write(&path, output)
+                .await
+                .with_context(|| format!("Error writing {path:?}"))?;
+            Ok(())
+        }
+    }
+
     /// Get the path of the file that this collection was loaded from
     pub fn path(&self) -> &Path {
         &self.path
@@ -159,8 +228,21 @@ async fn load_collection(path: PathBuf) -> anyhow::Result<Collection> {
     // tokio::fs for this but that just uses std::fs underneath anyway.
     let result =
         task::spawn_blocking::<_, anyhow::Result<Collection>>(move || {
-            let bytes = fs::read(path)?;
-            let collection = parse_yaml(&bytes)?;
+            let bytes = fs::read(&path)?;
+            let mut collection: Collection = parse_yaml(&bytes)?;
+            secrets::decrypt_profiles(&path, &mut collection)?;
+            collection.recipes.apply_defaults(&collection.defaults);
+            collection.protobuf_registry = collection
+                .protobuf
+                .as_ref()
+                .map(|schema| {
+                    ProtobufRegistry::compile(
+                        schema,
+                        path.parent().unwrap_or(Path::new(".")),
+                    )
+                })
+                .transpose()
+                .context("Error loading protobuf schema")?;
             Ok(collection)
         })
         .await;
@@ -180,11 +262,14 @@ async fn load_collection(path: PathBuf) -> anyhow::Result<Collection> {
 mod tests {
     use super::*;
     use crate::{
-        http::ContentType,
-        test_util::{assert_err, by_id, temp_dir, test_data_dir, TempDir},
+        http::{ContentType, ResponseRecord},
+        test_util::{
+            assert_err, by_id, temp_dir, test_data_dir, Factory, TempDir,
+        },
     };
-    use indexmap::indexmap;
+    use indexmap::{indexmap, IndexMap};
     use pretty_assertions::assert_eq;
+    use reqwest::header::{self, HeaderValue};
     use rstest::rstest;
     use serde::de::IgnoredAny;
     use serde_json::json;
@@ -235,6 +320,66 @@ mod tests {
         drop(temp_dir); // Dropping deletes the directory
     }
 
+    /// The protobuf schema is compiled onto each loaded collection, and used
+    /// to decode protobuf responses. Loading another collection shouldn't
+    /// affect the first one's schema
+    #[rstest]
+    #[tokio::test]
+    async fn test_load_protobuf_schema(temp_dir: TempDir) {
+        fs::write(
+            temp_dir.join("users.proto"),
+            r#"
+            syntax = "proto3";
+            package users;
+            message User {
+                string name = 1;
+                int32 id = 2;
+            }
+            "#,
+        )
+        .unwrap();
+        let with_schema = temp_dir.join("with_schema.yml");
+        fs::write(
+            &with_schema,
+            "protobuf:\n  files: [users.proto]\n  message: users.User\n",
+        )
+        .unwrap();
+        let without_schema = temp_dir.join("without_schema.yml");
+        fs::write(&without_schema, "requests: {}\n").unwrap();
+
+        let collection =
+            CollectionFile::load(with_schema).await.unwrap().collection;
+        let other = CollectionFile::load(without_schema)
+            .await
+            .unwrap()
+            .collection;
+        assert!(other.protobuf_registry.is_none());
+
+        // name = "alice", id = 7
+        let response = ResponseRecord {
+            headers: [(
+                header::CONTENT_TYPE,
+                HeaderValue::from_static("application/x-protobuf"),
+            )]
+            .into_iter()
+            .collect(),
+            body: b"\x0a\x05alice\x10\x07".as_slice().into(),
+            ..ResponseRecord::factory(())
+        };
+        response.parse_body(collection.protobuf_registry.as_ref());
+        assert_eq!(
+            response.body.parsed().unwrap().to_json().into_owned(),
+            json!({"name": "alice", "id": 7})
+        );
+        assert_err!(
+            ContentType::parse_response(
+                &response,
+                other.protobuf_registry.as_ref()
+            ),
+            "Protobuf content requires a schema"
+        );
+    }
+
     /// A catch-all regression test, to make sure we don't break anything in the
     /// collection format. This lives at the bottom because it's huge.
     #[rstest]
@@ -255,6 +400,13 @@ mod tests {
                         "host".into() => "https://httpbin.org".into(),
 
                     },
+                    base_url: None,
+                    tls: None,
+                    proxy: None,
+                    allowed_hosts: Vec::new(),
+                    encrypted: Vec::new(),
+                    unix_socket: None,
+                    resolve: IndexMap::new(),
                 },
                 Profile {
                     id: "profile2".into(),
@@ -263,6 +415,13 @@ mod tests {
                         "host".into() => "https://httpbin.org".into(),
 
                     },
+                    base_url: None,
+                    tls: None,
+                    proxy: None,
+                    allowed_hosts: Vec::new(),
+                    encrypted: Vec::new(),
+                    unix_socket: None,
+                    resolve: IndexMap::new(),
                 },
             ]),
             chains: by_id([
@@ -271,6 +430,9 @@ mod tests {
                     source: ChainSource::command(["whoami"]),
                     sensitive: false,
                     selector: None,
+                    selector_type: SelectorType::default(),
+                    multi: None,
+                    transforms: Vec::new(),
                     content_type: None,
                     trim: ChainOutputTrim::None,
                 },
@@ -282,6 +444,9 @@ mod tests {
                     },
                     sensitive: false,
                     selector: None,
+                    selector_type: SelectorType::default(),
+                    multi: None,
+                    transforms: Vec::new(),
                     content_type: None,
                     trim: ChainOutputTrim::None,
                 },
@@ -290,6 +455,9 @@ mod tests {
                     source: ChainSource::command(["whoami"]),
                     sensitive: false,
                     selector: None,
+                    selector_type: SelectorType::default(),
+                    multi: None,
+                    transforms: Vec::new(),
                     content_type: None,
                     trim: ChainOutputTrim::None,
                 },
@@ -298,6 +466,9 @@ mod tests {
                     source: ChainSource::command(["whoami"]),
                     sensitive: false,
                     selector: None,
+                    selector_type: SelectorType::default(),
+                    multi: None,
+                    transforms: Vec::new(),
                     content_type: None,
                     trim: ChainOutputTrim::Start,
                 },
@@ -306,6 +477,9 @@ mod tests {
                     source: ChainSource::command(["whoami"]),
                     sensitive: false,
                     selector: None,
+                    selector_type: SelectorType::default(),
+                    multi: None,
+                    transforms: Vec::new(),
                     content_type: None,
                     trim: ChainOutputTrim::End,
                 },
@@ -314,6 +488,9 @@ mod tests {
                     source: ChainSource::command(["whoami"]),
                     sensitive: false,
                     selector: None,
+                    selector_type: SelectorType::default(),
+                    multi: None,
+                    transforms: Vec::new(),
                     content_type: None,
                     trim: ChainOutputTrim::Both,
                 },
@@ -325,6 +502,9 @@ mod tests {
                     },
                     sensitive: true,
                     selector: None,
+                    selector_type: SelectorType::default(),
+                    multi: None,
+                    transforms: Vec::new(),
                     content_type: None,
                     trim: ChainOutputTrim::None,
                 },
@@ -336,6 +516,9 @@ mod tests {
                     },
                     sensitive: false,
                     selector: None,
+                    selector_type: SelectorType::default(),
+                    multi: None,
+                    transforms: Vec::new(),
                     content_type: None,
                     trim: ChainOutputTrim::None,
                 },
@@ -343,9 +526,14 @@ mod tests {
                     id: "file".into(),
                     source: ChainSource::File {
                         path: "./README.md".into(),
+                        glob: false,
+                        watch: false,
                     },
                     sensitive: false,
                     selector: None,
+                    selector_type: SelectorType::default(),
+                    multi: None,
+                    transforms: Vec::new(),
                     content_type: None,
                     trim: ChainOutputTrim::None,
                 },
@@ -353,21 +541,86 @@ mod tests {
                     id: "file_content_type".into(),
                     source: ChainSource::File {
                         path: "./data.json".into(),
+                        glob: false,
+                        watch: false,
                     },
                     sensitive: false,
                     selector: None,
+                    selector_type: SelectorType::default(),
+                    multi: None,
+                    transforms: Vec::new(),
                     content_type: Some(ContentType::Json),
                     trim: ChainOutputTrim::None,
                 },
+                Chain {
+                    id: "keyring".into(),
+                    source: ChainSource::Keyring {
+                        service: None,
+                        key: "api_token".into(),
+                    },
+                    sensitive: false,
+                    selector: None,
+                    selector_type: SelectorType::default(),
+                    multi: None,
+                    transforms: Vec::new(),
+                    content_type: None,
+                    trim: ChainOutputTrim::None,
+                },
+                Chain {
+                    id: "keyring_service".into(),
+                    source: ChainSource::Keyring {
+                        service: Some("slumber-shared".into()),
+                        key: "{{username}}".into(),
+                    },
+                    sensitive: false,
+                    selector: None,
+                    selector_type: SelectorType::default(),
+                    multi: None,
+                    transforms: Vec::new(),
+                    content_type: None,
+                    trim: ChainOutputTrim::None,
+                },
+                Chain {
+                    id: "secret_pass".into(),
+                    source: ChainSource::Secret {
+                        manager: SecretManager::Pass,
+                        name: "api/token".into(),
+                    },
+                    sensitive: false,
+                    selector: None,
+                    selector_type: SelectorType::default(),
+                    multi: None,
+                    transforms: Vec::new(),
+                    content_type: None,
+                    trim: ChainOutputTrim::None,
+                },
+                Chain {
+                    id: "secret_op".into(),
+                    source: ChainSource::Secret {
+                        manager: SecretManager::OnePassword,
+                        name: "op://vault/api/token".into(),
+                    },
+                    sensitive: false,
+                    selector: None,
+                    selector_type: SelectorType::default(),
+                    multi: None,
+                    transforms: Vec::new(),
+                    content_type: None,
+                    trim: ChainOutputTrim::None,
+                },
                 Chain {
                     id: "request_selector".into(),
                     source: ChainSource::Request {
                         recipe: "login".into(),
                         trigger: ChainRequestTrigger::Never,
                         section: ChainRequestSection::Body,
+                        poll: None,
                     },
                     sensitive: false,
                     selector: Some("$.data".parse().unwrap()),
+                    selector_type: SelectorType::default(),
+                    multi: None,
+                    transforms: Vec::new(),
                     content_type: None,
                     trim: ChainOutputTrim::None,
                 },
@@ -377,9 +630,13 @@ mod tests {
                         recipe: "login".into(),
                         trigger: ChainRequestTrigger::Never,
                         section: ChainRequestSection::Body,
+                        poll: None,
                     },
                     sensitive: false,
                     selector: None,
+                    selector_type: SelectorType::default(),
+                    multi: None,
+                    transforms: Vec::new(),
                     content_type: None,
                     trim: ChainOutputTrim::None,
                 },
@@ -389,9 +646,13 @@ mod tests {
                         recipe: "login".into(),
                         trigger: ChainRequestTrigger::Never,
                         section: ChainRequestSection::Body,
+                        poll: None,
                     },
                     sensitive: false,
                     selector: None,
+                    selector_type: SelectorType::default(),
+                    multi: None,
+                    transforms: Vec::new(),
                     content_type: None,
                     trim: ChainOutputTrim::None,
                 },
@@ -403,9 +664,13 @@ mod tests {
                             Duration::from_secs(12 * 60 * 60),
                         ),
                         section: ChainRequestSection::Body,
+                        poll: None,
                     },
                     sensitive: false,
                     selector: None,
+                    selector_type: SelectorType::default(),
+                    multi: None,
+                    transforms: Vec::new(),
                     content_type: None,
                     trim: ChainOutputTrim::None,
                 },
@@ -415,9 +680,13 @@ mod tests {
                         recipe: "login".into(),
                         trigger: ChainRequestTrigger::Never,
                         section: ChainRequestSection::Body,
+                        poll: None,
                     },
                     sensitive: false,
                     selector: None,
+                    selector_type: SelectorType::default(),
+                    multi: None,
+                    transforms: Vec::new(),
                     content_type: None,
                     trim: ChainOutputTrim::None,
                 },
@@ -427,9 +696,13 @@ mod tests {
                         recipe: "login".into(),
                         trigger: ChainRequestTrigger::Never,
                         section: ChainRequestSection::Body,
+                        poll: None,
                     },
                     sensitive: false,
                     selector: None,
+                    selector_type: SelectorType::default(),
+                    multi: None,
+                    transforms: Vec::new(),
                     content_type: None,
                     trim: ChainOutputTrim::None,
                 },
@@ -441,9 +714,13 @@ mod tests {
                         section: ChainRequestSection::Header(
                             "content-type".into(),
                         ),
+                        poll: None,
                     },
                     sensitive: false,
                     selector: None,
+                    selector_type: SelectorType::default(),
+                    multi: None,
+                    transforms: Vec::new(),
                     content_type: None,
                     trim: ChainOutputTrim::None,
                 },
@@ -468,10 +745,25 @@ mod tests {
                     headers: indexmap! {
                         "Accept".into() => "application/json".into(),
                     },
+                    tls: None,
+                    http_version: HttpVersion::default(),
+                    connect_to: None,
+                    unix_socket: None,
+                    resolve: IndexMap::new(),
+                    compress: None,
+                    redirects: RedirectOptions::default(),
+                    assertions: Vec::new(),
+                    tags: Vec::new(),
+                    profile: None,
+                    label: None,
+                    captures: IndexMap::new(),
+                    variables: IndexMap::new(),
+                    save: Vec::new(),
                 }),
                 RecipeNode::Folder(Folder {
                     id: "users".into(),
                     name: Some("Users".into()),
+                    defaults: RecipeDefaults::default(),
                     children: by_id([
                         RecipeNode::Recipe(Recipe {
                             id: "simple".into(),
@@ -485,6 +777,20 @@ mod tests {
                                 ("value".into(), "{{field2}}".into()),
                             ],
                             headers: indexmap! {},
+                            tls: None,
+                            http_version: HttpVersion::default(),
+                            connect_to: None,
+                            unix_socket: None,
+                            resolve: IndexMap::new(),
+                            compress: None,
+                            redirects: RedirectOptions::default(),
+                            assertions: Vec::new(),
+                            tags: vec!["users".into()],
+                            profile: None,
+                            label: None,
+                            captures: IndexMap::new(),
+                            variables: IndexMap::new(),
+                            save: Vec::new(),
                         }),
                         RecipeNode::Recipe(Recipe {
                             id: "json_body".into(),
@@ -504,6 +810,20 @@ mod tests {
                             headers: indexmap! {
                                 "Accept".into() => "application/json".into(),
                             },
+                            tls: None,
+                            http_version: HttpVersion::default(),
+                            connect_to: None,
+                            unix_socket: None,
+                            resolve: IndexMap::new(),
+                            compress: None,
+                            redirects: RedirectOptions::default(),
+                            assertions: Vec::new(),
+                            tags: Vec::new(),
+                            profile: None,
+                            label: None,
+                            captures: IndexMap::new(),
+                            variables: IndexMap::new(),
+                            save: Vec::new(),
                         }),
                         RecipeNode::Recipe(Recipe {
                             id: "json_body_but_not".into(),
@@ -522,6 +842,20 @@ mod tests {
                             headers: indexmap! {
                                 "Accept".into() => "application/json".into(),
                             },
+                            tls: None,
+                            http_version: HttpVersion::default(),
+                            connect_to: None,
+                            unix_socket: None,
+                            resolve: IndexMap::new(),
+                            compress: None,
+                            redirects: RedirectOptions::default(),
+                            assertions: Vec::new(),
+                            tags: Vec::new(),
+                            profile: None,
+                            label: None,
+                            captures: IndexMap::new(),
+                            variables: IndexMap::new(),
+                            save: Vec::new(),
                         }),
                         RecipeNode::Recipe(Recipe {
                             id: "form_urlencoded_body".into(),
@@ -537,11 +871,29 @@ mod tests {
                             headers: indexmap! {
                                 "Accept".into() => "application/json".into(),
                             },
+                            tls: None,
+                            http_version: HttpVersion::default(),
+                            connect_to: None,
+                            unix_socket: None,
+                            resolve: IndexMap::new(),
+                            compress: None,
+                            redirects: RedirectOptions::default(),
+                            assertions: Vec::new(),
+                            tags: Vec::new(),
+                            profile: None,
+                            label: None,
+                            captures: IndexMap::new(),
+                            variables: IndexMap::new(),
+                            save: Vec::new(),
                         }),
                     ]),
                 }),
             ])
             .into(),
+            base_url: None,
+            defaults: RecipeDefaults::default(),
+            protobuf: None,
+            protobuf_registry: None,
             _ignore: IgnoredAny,
         };
         assert_eq!(loaded, expected);
@@ -348,6 +348,13 @@ pub mod serde_duration {
     where
         D: Deserializer<'de>,
     {
+        let input = String::deserialize(deserializer)?;
+        parse(&input).map_err(D::Error::custom)
+    }
+
+    /// Parse a duration string. Exposed separately from [deserialize] so it
+    /// can be used to parse CLI arguments
+    pub fn parse(input: &str) -> Result<Duration, String> {
         fn quantity(input: &mut &str) -> PResult<u64> {
             digit1.parse_to().parse_next(input)
         }
@@ -356,23 +363,21 @@ pub mod serde_duration {
             take_while(1.., char::is_alphabetic).parse_next(input)
         }
 
-        let input = String::deserialize(deserializer)?;
         let (quantity, unit) = (quantity, unit)
-            .parse(&input)
+            .parse(input)
             // The format is so simple there isn't much value in spitting out a
             // specific parsing error, just use a canned one
             .map_err(|_| {
-                D::Error::custom(
-                    "Invalid duration, must be `<quantity><unit>` (e.g. `12d`)",
-                )
+                "Invalid duration, must be `<quantity><unit>` (e.g. `12d`)"
+                    .to_owned()
             })?;
 
         let unit = unit.parse().map_err(|_| {
-            D::Error::custom(format!(
+            format!(
                 "Unknown duration unit `{unit}`; must be one of {}",
                 Unit::iter()
                     .format_with(", ", |unit, f| f(&format_args!("`{unit}`")))
-            ))
+            )
         })?;
         let seconds = match unit {
             Unit::Second => quantity,
@@ -382,6 +387,36 @@ pub mod serde_duration {
         };
         Ok(Duration::from_secs(seconds))
     }
+
+    /// Serialize/deserialize an optional duration, in the same format
+    pub mod option {
+        use serde::{de::Error, Deserialize, Deserializer, Serializer};
+        use std::time::Duration;
+
+        pub fn serialize<S>(
+            duration: &Option<Duration>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            match duration {
+                Some(duration) => super::serialize(duration, serializer),
+                None => serializer.serialize_none(),
+            }
+        }
+
+        pub fn deserialize<'de, D>(
+            deserializer: D,
+        ) -> Result<Option<Duration>, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            Option::<String>::deserialize(deserializer)?
+                .map(|input| super::parse(&input).map_err(D::Error::custom))
+                .transpose()
+        }
+    }
 }
 
 #[cfg(test)]
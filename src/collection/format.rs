@@ -0,0 +1,468 @@
+//! Canonical formatting for collection files. YAML libraries don't preserve
+//! comments, so this operates on the file line-by-line rather than
+//! round-tripping through a data structure. To make sure that's safe, the
+//! formatted output is re-parsed and compared to the input before it's
+//! returned.
+
+use crate::{template::Template, util::parse_yaml};
+use anyhow::{anyhow, Context};
+use serde_yaml::Value;
+
+/// Number of spaces per indentation level in formatted output
+const INDENT: usize = 2;
+/// Canonical order for top-level fields. Any other top-level fields (e.g.
+/// hidden fields used to define anchors) go before these, so anchors are
+/// always defined before they're used.
+const TOP_LEVEL_ORDER: [&str; 3] = ["profiles", "chains", "requests"];
+
+/// Format the contents of a collection file:
+/// - Top-level fields in a consistent order
+/// - Two-space indentation
+/// - No trailing whitespace, and no more than one consecutive blank line
+/// - Templates written with normalized escape sequences
+///
+/// Comments are preserved. Profiles, chains, and recipes are not reordered,
+/// because their order is meaningful in the TUI. Return an error if the input
+/// is invalid YAML, or if formatting would change the meaning of the file.
+pub fn format_collection(input: &str) -> anyhow::Result<String> {
+    let expected = normalize_templates(
+        parse_yaml::<Value>(input.as_bytes()).context("Invalid YAML")?,
+    );
+    let lines = reindent(input);
+    let output = reorder(lines);
+
+    // This is our safeguard against any mistakes in the formatter. If the
+    // re-parsed output differs at all, it's our fault
+    let actual = parse_yaml::<Value>(output.as_bytes())
+        .map_err(|error| anyhow!(error))
+        .and_then(|value| {
+            if value == expected {
+                Ok(())
+            } else {
+                Err(anyhow!("Formatted value does not match input"))
+            }
+        });
+    actual.context(
+        "Formatting would change the meaning of the collection; \
+        this is a bug, please report it",
+    )?;
+    Ok(output)
+}
+
+/// A single formatted line
+#[derive(Debug)]
+struct Line {
+    text: String,
+    kind: LineKind,
+}
+
+#[derive(Debug, PartialEq)]
+enum LineKind {
+    Blank,
+    Comment,
+    /// Content that isn't nested under anything, e.g. `requests:`
+    TopLevel,
+    /// Anything else, including block scalar and multi-line flow content
+    Other,
+}
+
+/// Rewrite indentation to two spaces per level, strip trailing whitespace,
+/// and normalize templates. Block scalars and multi-line flow collections are
+/// shifted along with their parent, but their internal layout is untouched.
+fn reindent(input: &str) -> Vec<Line> {
+    let mut output = Vec::new();
+    // Original indentation of each open level
+    let mut levels: Vec<usize> = Vec::new();
+    // Comments and blank lines are buffered until we see the next content
+    // line, so they can be indented to match it
+    let mut pending: Vec<&str> = Vec::new();
+    // Set while inside a block scalar: (parent's original indent, parent's
+    // new indent, content's original indent)
+    let mut block: Option<(usize, usize, Option<usize>)> = None;
+    // Set while inside a multi-line flow collection: (remaining open
+    // brackets, amount to shift each line by)
+    let mut flow: Option<(usize, isize)> = None;
+
+    for line in input.lines() {
+        let trimmed = line.trim();
+        let indent = line.len() - line.trim_start().len();
+
+        if let Some((parent_indent, parent_new, content_indent)) = &mut block {
+            if trimmed.is_empty() {
+                pending.push("");
+                continue;
+            }
+            if indent > *parent_indent {
+                let content_indent = *content_indent.get_or_insert(indent);
+                // Blank lines within the block belong to it, and they're
+                // significant so they can't be collapsed
+                for _ in pending.drain(..) {
+                    output.push(Line::new(String::new(), LineKind::Other));
+                }
+                let new_indent = *parent_new
+                    + INDENT
+                    + indent.saturating_sub(content_indent);
+                output.push(Line::new(
+                    format!("{:new_indent$}{}", "", line.trim_start()),
+                    LineKind::Other,
+                ));
+                continue;
+            }
+            block = None;
+        }
+
+        if let Some((depth, shift)) = &mut flow {
+            let new_indent = (indent as isize + *shift).max(0) as usize;
+            output.push(Line::new(
+                format!("{:new_indent$}{trimmed}", ""),
+                LineKind::Other,
+            ));
+            *depth = bracket_depth(trimmed, *depth);
+            if *depth == 0 {
+                flow = None;
+            }
+            continue;
+        }
+
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            pending.push(trimmed);
+            continue;
+        }
+
+        while levels.last().is_some_and(|level| indent < *level) {
+            levels.pop();
+        }
+        if levels.last() != Some(&indent) {
+            levels.push(indent);
+        }
+        let new_indent = (levels.len() - 1) * INDENT;
+
+        for text in pending.drain(..) {
+            output.push(if text.is_empty() {
+                Line::new(String::new(), LineKind::Blank)
+            } else {
+                Line::new(
+                    format!("{:new_indent$}{text}", ""),
+                    LineKind::Comment,
+                )
+            });
+        }
+
+        let content = normalize_line(trimmed);
+        let kind = if new_indent == 0 {
+            LineKind::TopLevel
+        } else {
+            LineKind::Other
+        };
+        output.push(Line::new(format!("{:new_indent$}{content}", ""), kind));
+
+        let value = line_value(&content);
+        if is_block_scalar_header(value) {
+            block = Some((indent, new_indent, None));
+        } else if strip_tag(value).starts_with(['{', '[']) {
+            let depth = bracket_depth(strip_tag(value), 0);
+            if depth > 0 {
+                flow = Some((depth, new_indent as isize - indent as isize));
+            }
+        }
+    }
+
+    // Trailing comments go at the root level
+    for text in pending {
+        output.push(if text.is_empty() {
+            Line::new(String::new(), LineKind::Blank)
+        } else {
+            Line::new(text.to_owned(), LineKind::Comment)
+        });
+    }
+    output
+}
+
+/// Sort top-level fields into their canonical order, and collapse repeated
+/// blank lines. Comments directly above a field move with it.
+fn reorder(lines: Vec<Line>) -> String {
+    // Split into a preamble (anything before the first field) and one chunk
+    // per top-level field
+    let mut preamble: Vec<Line> = Vec::new();
+    let mut chunks: Vec<(String, Vec<Line>)> = Vec::new();
+    let mut attached: Vec<Line> = Vec::new();
+    for line in lines {
+        match line.kind {
+            LineKind::Comment => attached.push(line),
+            LineKind::Blank => {
+                // A blank line detaches any comments above it from the next
+                // field
+                let target = chunks
+                    .last_mut()
+                    .map(|(_, lines)| lines)
+                    .unwrap_or(&mut preamble);
+                target.append(&mut attached);
+                target.push(line);
+            }
+            LineKind::TopLevel => {
+                let key = line_key(&line.text).unwrap_or_default().to_owned();
+                attached.push(line);
+                chunks.push((key, std::mem::take(&mut attached)));
+            }
+            LineKind::Other => {
+                let target = chunks
+                    .last_mut()
+                    .map(|(_, lines)| lines)
+                    .unwrap_or(&mut preamble);
+                target.append(&mut attached);
+                target.push(line);
+            }
+        }
+    }
+    let trailing = attached;
+
+    // Stable sort, so unknown fields retain their relative order
+    chunks.sort_by_key(|(key, _)| {
+        TOP_LEVEL_ORDER
+            .iter()
+            .position(|field| field == key)
+            .map_or(0, |position| position + 1)
+    });
+
+    // Join everything with exactly one blank line between chunks
+    let mut output = String::new();
+    let groups = [preamble]
+        .into_iter()
+        .chain(chunks.into_iter().map(|(_, lines)| lines))
+        .chain([trailing]);
+    for group in groups {
+        let mut group_lines = Vec::new();
+        for line in group {
+            let is_blank = line.kind == LineKind::Blank;
+            if is_blank
+                && group_lines
+                    .last()
+                    .map_or(true, |last: &Line| last.kind == LineKind::Blank)
+            {
+                continue;
+            }
+            group_lines.push(line);
+        }
+        while group_lines
+            .last()
+            .is_some_and(|line| line.kind == LineKind::Blank)
+        {
+            group_lines.pop();
+        }
+        if group_lines.is_empty() {
+            continue;
+        }
+        if !output.is_empty() {
+            output.push('\n');
+        }
+        for line in group_lines {
+            output.push_str(&line.text);
+            output.push('\n');
+        }
+    }
+    output
+}
+
+impl Line {
+    fn new(text: String, kind: LineKind) -> Self {
+        Self { text, kind }
+    }
+}
+
+/// Get the key of a `key: value` line, if it has one
+fn line_key(content: &str) -> Option<&str> {
+    let content = content.trim_start();
+    if content.starts_with(['"', '\'', '-', '{', '[']) {
+        return None;
+    }
+    let (key, _) = content.split_once(':')?;
+    Some(key.trim())
+}
+
+/// Get the value portion of a line: everything after the key and/or sequence
+/// item markers
+fn line_value(content: &str) -> &str {
+    let mut content = content.trim_start();
+    while let Some(rest) = content.strip_prefix("- ") {
+        content = rest.trim_start();
+    }
+    if line_key(content).is_some() {
+        content = content
+            .split_once(": ")
+            .map(|(_, value)| value.trim_start())
+            .unwrap_or_default();
+    }
+    content
+}
+
+/// Is this value the header of a block scalar, e.g. `|` or `>-`? Also matches
+/// tagged block scalars, e.g. `!json |`
+fn is_block_scalar_header(value: &str) -> bool {
+    let value = match value.split_once(" #") {
+        Some((value, _)) => value,
+        None => value,
+    };
+    let header = strip_tag(value.trim_end());
+    header.starts_with(['|', '>'])
+        && header[1..]
+            .chars()
+            .all(|c| c == '-' || c == '+' || c.is_ascii_digit())
+}
+
+/// Remove a leading tag from a value, e.g. `!json {` becomes `{`
+fn strip_tag(value: &str) -> &str {
+    if value.starts_with('!') {
+        value
+            .split_once(' ')
+            .map_or("", |(_, value)| value.trim_start())
+    } else {
+        value
+    }
+}
+
+/// Count unclosed brackets in a line of flow content, starting from an
+/// initial depth. Brackets inside quotes don't count.
+fn bracket_depth(text: &str, mut depth: usize) -> usize {
+    let mut quote: Option<char> = None;
+    for c in text.chars() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), _) if c == q => quote = None,
+            (None, '{' | '[') => depth += 1,
+            (None, '}' | ']') => depth = depth.saturating_sub(1),
+            (None, '#') => break, // Comment
+            _ => {}
+        }
+    }
+    depth
+}
+
+/// Rewrite a single-line template value so its escape sequences are
+/// normalized. Only values that contain a template key are touched, since
+/// anything else may not be a template at all. Lines with comments are left
+/// alone, because the value can't be separated from the comment reliably.
+fn normalize_line(content: &str) -> String {
+    let value = line_value(content);
+    if !value.contains("{{")
+        || value.contains('#')
+        || value.starts_with(['!', '{', '[', '|', '>', '&', '*'])
+    {
+        return content.to_owned();
+    }
+    let Ok(Value::String(raw)) = serde_yaml::from_str::<Value>(value) else {
+        return content.to_owned();
+    };
+    let Some(normalized) = normalize_template(&raw) else {
+        return content.to_owned();
+    };
+    if normalized == raw {
+        return content.to_owned();
+    }
+    // JSON strings are valid double-quoted YAML strings
+    let Ok(quoted) = serde_json::to_string(&normalized) else {
+        return content.to_owned();
+    };
+    format!("{}{quoted}", &content[..content.len() - value.len()])
+}
+
+/// Parse and re-stringify a string as a template. `None` if it's not a valid
+/// template
+fn normalize_template(value: &str) -> Option<String> {
+    value
+        .parse::<Template>()
+        .ok()
+        .map(|template| template.to_string())
+}
+
+/// Apply the same template normalization as [normalize_line] to every string
+/// in a YAML value. The formatter only normalizes single-line values, so this
+/// only applies to strings without newlines.
+fn normalize_templates(value: Value) -> Value {
+    match value {
+        Value::String(s) if s.contains("{{") && !s.contains('\n') => {
+            Value::String(normalize_template(&s).unwrap_or(s))
+        }
+        Value::Sequence(sequence) => Value::Sequence(
+            sequence.into_iter().map(normalize_templates).collect(),
+        ),
+        Value::Mapping(mapping) => Value::Mapping(
+            mapping
+                .into_iter()
+                .map(|(key, value)| (key, normalize_templates(value)))
+                .collect(),
+        ),
+        Value::Tagged(mut tagged) => {
+            tagged.value = normalize_templates(tagged.value);
+            Value::Tagged(tagged)
+        }
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::assert_err;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::already_formatted(
+        "profiles:\n  local:\n    data:\n      host: http://localhost\n",
+        "profiles:\n  local:\n    data:\n      host: http://localhost\n"
+    )]
+    #[case::indentation(
+        "requests:\n    login: !request\n        method: POST\n        \
+        query:\n            - a=1\n",
+        "requests:\n  login: !request\n    method: POST\n    query:\n      \
+        - a=1\n"
+    )]
+    #[case::whitespace(
+        "profiles:   \n\n\n\n  local:\n    name: Local  \n\n\n",
+        "profiles:\n\n  local:\n    name: Local\n"
+    )]
+    #[case::reorder(
+        "requests:\n  a: !request\n    method: GET\n    url: x\n\n\
+        # Profiles!\nprofiles:\n  local: {}\n\n\
+        .base: &base\n  x: 1\n",
+        ".base: &base\n  x: 1\n\n# Profiles!\nprofiles:\n  local: {}\n\n\
+        requests:\n  a: !request\n    method: GET\n    url: x\n"
+    )]
+    #[case::comments(
+        "# Header\n\nprofiles:\n    # Local\n    local:\n        \
+        name: Local # inline\n",
+        "# Header\n\nprofiles:\n  # Local\n  local:\n    \
+        name: Local # inline\n"
+    )]
+    #[case::block_scalar(
+        "requests:\n    a: !request\n        method: POST\n        \
+        url: x\n        body: |\n            line 1\n\n              \
+        line 2\n",
+        "requests:\n  a: !request\n    method: POST\n    url: x\n    \
+        body: |\n      line 1\n\n        line 2\n"
+    )]
+    #[case::flow(
+        "requests:\n    a: !request\n        method: POST\n        \
+        url: x\n        body: !json {\n            \"a\": 1,\n        \
+        }\n",
+        "requests:\n  a: !request\n    method: POST\n    url: x\n    \
+        body: !json {\n        \"a\": 1,\n    }\n"
+    )]
+    #[case::template_escape(
+        "requests:\n  a: !request\n    method: GET\n    \
+        url: \"{{host}}/a\\\\d\"\n",
+        "requests:\n  a: !request\n    method: GET\n    \
+        url: \"{{host}}/a\\\\\\\\d\"\n"
+    )]
+    fn test_format(#[case] input: &str, #[case] expected: &str) {
+        let formatted = format_collection(input).unwrap();
+        assert_eq!(formatted, expected);
+        // Formatting should be idempotent
+        assert_eq!(format_collection(&formatted).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_format_invalid() {
+        assert_err!(format_collection("requests: [\n"), "Invalid YAML");
+    }
+}
@@ -0,0 +1,106 @@
+//! Reporting for collection importers
+
+use crate::collection::{Collection, RecipeNode};
+use serde::Serialize;
+use std::fmt::{self, Display, Formatter};
+
+/// A summary of what an importer did, so the user can audit the generated
+/// collection. Importers are approximate, so it's important to be upfront
+/// about anything that was lost or changed in translation.
+#[derive(Debug, Default, Serialize)]
+#[cfg_attr(test, derive(PartialEq))]
+pub struct ImportReport {
+    /// Number of each item type in the generated collection
+    pub created: ImportCounts,
+    /// Items from the source collection that were dropped entirely
+    pub skipped: Vec<ImportIssue>,
+    /// Items that were imported, but with some features removed or changed
+    pub downgraded: Vec<ImportIssue>,
+}
+
+/// Item counts for an import
+#[derive(Debug, Default, Serialize)]
+#[cfg_attr(test, derive(PartialEq))]
+pub struct ImportCounts {
+    pub profiles: usize,
+    pub folders: usize,
+    pub recipes: usize,
+    pub chains: usize,
+}
+
+/// A single problem encountered during import
+#[derive(Debug, Serialize)]
+#[cfg_attr(test, derive(PartialEq))]
+pub struct ImportIssue {
+    /// ID of the item in the source collection
+    pub item: String,
+    /// Human-readable explanation of what happened
+    pub reason: String,
+}
+
+impl ImportReport {
+    /// Record an item that was dropped from the import
+    pub fn skip(&mut self, item: impl Into<String>, reason: impl Into<String>) {
+        self.skipped.push(ImportIssue {
+            item: item.into(),
+            reason: reason.into(),
+        });
+    }
+
+    /// Record an item that was imported, but without some of its features
+    pub fn downgrade(
+        &mut self,
+        item: impl Into<String>,
+        reason: impl Into<String>,
+    ) {
+        self.downgraded.push(ImportIssue {
+            item: item.into(),
+            reason: reason.into(),
+        });
+    }
+
+    /// Count the items in the generated collection. This should be called
+    /// once the import is complete.
+    pub fn count(&mut self, collection: &Collection) {
+        let (folders, recipes) = collection.recipes.iter().fold(
+            (0, 0),
+            |(folders, recipes), (_, node)| match node {
+                RecipeNode::Folder(_) => (folders + 1, recipes),
+                RecipeNode::Recipe(_) => (folders, recipes + 1),
+            },
+        );
+        self.created = ImportCounts {
+            profiles: collection.profiles.len(),
+            folders,
+            recipes,
+            chains: collection.chains.len(),
+        };
+    }
+}
+
+impl Display for ImportReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let ImportCounts {
+            profiles,
+            folders,
+            recipes,
+            chains,
+        } = self.created;
+        writeln!(
+            f,
+            "Created {profiles} profile(s), {folders} folder(s), \
+            {recipes} recipe(s), {chains} chain(s)"
+        )?;
+        for (label, issues) in
+            [("Skipped", &self.skipped), ("Downgraded", &self.downgraded)]
+        {
+            if !issues.is_empty() {
+                writeln!(f, "{label} {} item(s):", issues.len())?;
+                for ImportIssue { item, reason } in issues {
+                    writeln!(f, "  {item}: {reason}")?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,490 @@
+//! Add new recipes and profiles to a collection file. Like formatting, this
+//! operates on the file text rather than round-tripping through a data
+//! structure, so comments and layout are preserved. The output is re-parsed to
+//! make sure nothing but the new entry changed.
+
+use crate::{
+    collection::{Collection, Method, ProfileId, RecipeId, RecipeTree},
+    http::RequestRecord,
+    template::Template,
+    util::parse_yaml,
+};
+use anyhow::{anyhow, bail, Context};
+use indexmap::{IndexMap, IndexSet};
+use reqwest::header::{AUTHORIZATION, CONTENT_LENGTH};
+use serde::Serialize;
+use serde_yaml::Value;
+
+/// Top-level key that recipes live under
+const REQUESTS_KEY: &str = "requests";
+/// Top-level key that profiles live under
+const PROFILES_KEY: &str = "profiles";
+/// Indentation for entry fields, if the file has no entries to copy from
+const DEFAULT_INDENT: usize = 2;
+
+/// A recipe to be added to a collection file. This only has the fields that
+/// can be filled in from the TUI, and skips anything empty so the generated
+/// YAML looks like something a person would write.
+#[derive(Debug, Serialize)]
+pub struct NewRecipe {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub method: Method,
+    pub url: Template,
+    #[serde(skip_serializing_if = "IndexMap::is_empty")]
+    pub headers: IndexMap<String, Template>,
+    /// Raw body
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body: Option<Template>,
+}
+
+impl NewRecipe {
+    /// Copy a request from history into a new recipe. Values are taken
+    /// verbatim, so anything that looks like a template key is escaped.
+    /// `Authorization` isn't copied, so credentials don't end up in the
+    /// collection file, and `Content-Length` is dropped because it's computed
+    /// when the request is built.
+    pub fn from_record(
+        record: &RequestRecord,
+        name: Option<String>,
+    ) -> anyhow::Result<Self> {
+        let headers = record
+            .headers
+            .iter()
+            .filter(|(name, _)| {
+                ![AUTHORIZATION, CONTENT_LENGTH].contains(*name)
+            })
+            .map(|(name, value)| {
+                let value = value.to_str().with_context(|| {
+                    format!("Header `{name}` is not valid UTF-8")
+                })?;
+                Ok((name.to_string(), Template::raw(value.to_owned())))
+            })
+            .collect::<anyhow::Result<_>>()?;
+        let body = record
+            .body
+            .as_ref()
+            .map(|body| {
+                let body = std::str::from_utf8(body).map_err(|_| {
+                    anyhow!("Binary bodies can't be saved to a recipe")
+                })?;
+                anyhow::Ok(Template::raw(body.to_owned()))
+            })
+            .transpose()?;
+        Ok(Self {
+            name,
+            method: record.method.as_str().parse().map_err(|_| {
+                anyhow!("Unsupported HTTP method `{}`", record.method)
+            })?,
+            url: Template::raw(record.url.to_string()),
+            headers,
+            body,
+        })
+    }
+
+    /// Generate an ID for this recipe from its name. If the ID is already
+    /// taken in the collection, a number is appended to make it unique.
+    pub fn id(&self, recipes: &RecipeTree) -> RecipeId {
+        let base = Self::base_id(self.name.as_deref().unwrap_or_default());
+        let mut id: RecipeId = base.clone().into();
+        let mut suffix = 2;
+        while recipes.get(&id).is_some() {
+            id = format!("{base}_{suffix}").into();
+            suffix += 1;
+        }
+        id
+    }
+
+    /// Convert a recipe name to an ID, e.g. `Get Users` becomes `get_users`.
+    /// This doesn't check if the ID is already taken.
+    pub fn base_id(name: &str) -> String {
+        let mut base = String::new();
+        for c in name.chars() {
+            if c.is_ascii_alphanumeric() {
+                base.push(c.to_ascii_lowercase());
+            } else if !base.is_empty() && !base.ends_with('_') {
+                base.push('_');
+            }
+        }
+        match base.trim_end_matches('_') {
+            "" => "new_recipe".to_owned(),
+            base => base.to_owned(),
+        }
+    }
+}
+
+/// A profile to be added to a collection file
+#[derive(Debug, Serialize)]
+pub struct NewProfile {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub data: IndexMap<String, Template>,
+}
+
+/// Get the name of every field referenced by any template in the given
+/// collection file contents, in the order they first appear. Chain and
+/// environment keys aren't fields, so they're excluded. Strings that aren't
+/// valid templates are skipped; they'll be reported when the collection is
+/// loaded.
+pub fn referenced_fields(input: &str) -> anyhow::Result<IndexSet<String>> {
+    fn visit(value: &Value, fields: &mut IndexSet<String>) {
+        match value {
+            Value::String(s) => {
+                if let Ok(template) = s.parse::<Template>() {
+                    fields.extend(template.fields().map(String::from));
+                }
+            }
+            Value::Sequence(values) => {
+                values.iter().for_each(|value| visit(value, fields));
+            }
+            // Keys are IDs and field names, never templates
+            Value::Mapping(mapping) => {
+                mapping.values().for_each(|value| visit(value, fields));
+            }
+            Value::Tagged(tagged) => visit(&tagged.value, fields),
+            Value::Null | Value::Bool(_) | Value::Number(_) => {}
+        }
+    }
+
+    // Don't apply merge keys, because that reorders the mapping. Aliases are
+    // still expanded, so merged values are visited where they're referenced
+    let value = serde_yaml::from_str::<Value>(input)?;
+    let mut fields = IndexSet::new();
+    if let Value::Mapping(mapping) = value {
+        for (key, value) in mapping {
+            // Hidden fields hold YAML anchors, which are already expanded
+            // wherever they're used
+            if !key.as_str().is_some_and(|key| key.starts_with('.')) {
+                visit(&value, &mut fields);
+            }
+        }
+    }
+    Ok(fields)
+}
+
+/// Add a recipe to the end of the `requests` field in the given collection
+/// file contents. If there's no `requests` field, one is added to the end of
+/// the file. Return an error if the recipe couldn't be added without
+/// rewriting the rest of the file, e.g. if `requests` is a flow mapping.
+pub fn insert_recipe(
+    input: &str,
+    id: &RecipeId,
+    recipe: &NewRecipe,
+) -> anyhow::Result<String> {
+    insert_entry(input, REQUESTS_KEY, id, Some("!request"), recipe)
+}
+
+/// Add a profile to the end of the `profiles` field in the given collection
+/// file contents. Like [insert_recipe], the field is added if it's missing.
+pub fn insert_profile(
+    input: &str,
+    id: &ProfileId,
+    profile: &NewProfile,
+) -> anyhow::Result<String> {
+    insert_entry(input, PROFILES_KEY, id, None, profile)
+}
+
+/// Add an entry to the end of a top-level mapping. `entry` is serialized as
+/// the value, with an optional YAML tag
+fn insert_entry(
+    input: &str,
+    key: &str,
+    id: &str,
+    tag: Option<&str>,
+    entry: &impl Serialize,
+) -> anyhow::Result<String> {
+    let header = match tag {
+        Some(tag) => format!("{id}: {tag}"),
+        None => format!("{id}:"),
+    };
+    let lines: Vec<&str> = input.lines().collect();
+    let key_line = lines.iter().position(|line| {
+        line.strip_prefix(key)
+            .and_then(|rest| rest.strip_prefix(':'))
+            .is_some_and(|rest| is_blank(rest) || rest.trim() == "{}")
+    });
+
+    let mut output: Vec<String> =
+        lines.iter().map(|line| (*line).to_owned()).collect();
+    match key_line {
+        Some(start) => {
+            // An empty flow mapping is easy enough to convert to a block
+            if lines[start].contains("{}") {
+                output[start] = format!("{key}:");
+            }
+            // The block ends at the next line with no indentation, but leave
+            // trailing blank lines and comments where they are. They probably
+            // belong to whatever comes after
+            let content = &lines[start + 1..];
+            let block_len = content
+                .iter()
+                .position(|line| !is_blank(line) && !line.starts_with(' '))
+                .unwrap_or(content.len());
+            let end = content[..block_len]
+                .iter()
+                .rposition(|line| !is_blank(line))
+                .map_or(start + 1, |i| start + i + 2);
+            // Match the indentation of the existing entries
+            let indent = content[..block_len]
+                .iter()
+                .find(|line| !is_blank(line))
+                .map_or(DEFAULT_INDENT, |line| {
+                    line.len() - line.trim_start().len()
+                });
+            output.splice(end..end, entry_lines(&header, entry, indent)?);
+        }
+        None => {
+            let prefix = format!("{key}:");
+            if lines.iter().any(|line| line.starts_with(&prefix)) {
+                bail!("Cannot add entry: `{key}` must be a block mapping");
+            }
+            if output.last().is_some_and(|line| !line.is_empty()) {
+                output.push(String::new());
+            }
+            output.push(format!("{key}:"));
+            output.extend(entry_lines(&header, entry, DEFAULT_INDENT)?);
+        }
+    }
+    let mut output = output.join("\n");
+    output.push('\n');
+
+    verify(input, &output, key, id).context(
+        "Adding the entry would change the rest of the collection; \
+        this is a bug, please report it",
+    )?;
+    Ok(output)
+}
+
+/// Generate YAML lines for an entry, indented to sit under its top-level key
+fn entry_lines(
+    header: &str,
+    entry: &impl Serialize,
+    indent: usize,
+) -> anyhow::Result<Vec<String>> {
+    let indent = " ".repeat(indent);
+    let fields = serde_yaml::to_string(entry)?;
+    let mut lines = vec![format!("{indent}{header}")];
+    lines.extend(fields.lines().map(|line| {
+        // Don't add trailing whitespace to blank lines in block scalars
+        if line.is_empty() {
+            String::new()
+        } else {
+            format!("{indent}{indent}{line}")
+        }
+    }));
+    Ok(lines)
+}
+
+/// Make sure the output is a valid collection, and the only difference from
+/// the input is the new recipe
+fn verify(
+    input: &str,
+    output: &str,
+    key: &str,
+    id: &str,
+) -> anyhow::Result<()> {
+    parse_yaml::<Collection>(output.as_bytes())?;
+
+    let expected = parse_yaml::<Value>(input.as_bytes())?;
+    let mut actual = parse_yaml::<Value>(output.as_bytes())?;
+    let entries = actual
+        .get_mut(key)
+        .and_then(Value::as_mapping_mut)
+        .ok_or_else(|| anyhow!("`{key}` is missing"))?;
+    entries
+        .remove(id)
+        .ok_or_else(|| anyhow!("Entry `{id}` is missing"))?;
+    // If we added the top-level field, get rid of that too
+    let had_entries = expected.get(key).is_some_and(|value| !value.is_null());
+    if entries.is_empty() && !had_entries {
+        if let Some(mapping) = actual.as_mapping_mut() {
+            mapping.remove(key);
+        }
+    }
+
+    if actual == expected {
+        Ok(())
+    } else {
+        Err(anyhow!("Output does not match input"))
+    }
+}
+
+fn is_blank(line: &str) -> bool {
+    let line = line.trim();
+    line.is_empty() || line.starts_with('#')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        collection::{Recipe, RecipeNode},
+        http::RequestRecord,
+        test_util::{assert_err, Factory},
+    };
+    use indexmap::indexmap;
+    use reqwest::{header::HeaderMap, Url};
+    use rstest::rstest;
+
+    fn recipe() -> NewRecipe {
+        NewRecipe {
+            name: Some("Get User".into()),
+            method: Method::Get,
+            url: "{{host}}/users/1".into(),
+            headers: IndexMap::new(),
+            body: None,
+        }
+    }
+
+    #[rstest]
+    #[case::append(
+        "profiles:\n  local:\n    data: {}\n\n# Recipes\nrequests:\n  \
+        # First recipe\n  ping: !request\n    method: GET\n    \
+        url: /ping\n\n# Trailing comment\n",
+        "profiles:\n  local:\n    data: {}\n\n# Recipes\nrequests:\n  \
+        # First recipe\n  ping: !request\n    method: GET\n    \
+        url: /ping\n  new: !request\n    name: Get User\n    method: GET\n    \
+        url: '{{host}}/users/1'\n\n# Trailing comment\n"
+    )]
+    #[case::match_indent(
+        "requests:\n    ping: !request\n        method: GET\n        \
+        url: /ping\nchains: {}\n",
+        "requests:\n    ping: !request\n        method: GET\n        \
+        url: /ping\n    new: !request\n        name: Get User\n        \
+        method: GET\n        url: '{{host}}/users/1'\nchains: {}\n"
+    )]
+    #[case::empty_flow(
+        "requests: {}\n",
+        "requests:\n  new: !request\n    name: Get User\n    method: GET\n    \
+        url: '{{host}}/users/1'\n"
+    )]
+    #[case::no_requests(
+        "profiles: {}",
+        "profiles: {}\n\nrequests:\n  new: !request\n    name: Get User\n    \
+        method: GET\n    url: '{{host}}/users/1'\n"
+    )]
+    fn test_insert_recipe(#[case] input: &str, #[case] expected: &str) {
+        let output = insert_recipe(input, &"new".into(), &recipe()).unwrap();
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn test_insert_recipe_flow() {
+        assert_err!(
+            insert_recipe(
+                "requests: {ping: !request {method: GET, url: /ping}}",
+                &"new".into(),
+                &recipe()
+            ),
+            "`requests` must be a block mapping"
+        );
+    }
+
+    #[rstest]
+    #[case::append(
+        "profiles:\n  local:\n    data:\n      host: localhost\n\n\
+        requests: {}\n",
+        "profiles:\n  local:\n    data:\n      host: localhost\n  \
+        new:\n    name: New\n    data:\n      \
+        host: '{{protocol}}://example'\n\n\
+        requests: {}\n"
+    )]
+    #[case::no_profiles(
+        "requests: {}\n",
+        "requests: {}\n\nprofiles:\n  new:\n    name: New\n    data:\n      \
+        host: '{{protocol}}://example'\n"
+    )]
+    fn test_insert_profile(#[case] input: &str, #[case] expected: &str) {
+        let profile = NewProfile {
+            name: Some("New".into()),
+            data: indexmap! {"host".into() => "{{protocol}}://example".into()},
+        };
+        let output = insert_profile(input, &"new".into(), &profile).unwrap();
+        assert_eq!(output, expected);
+    }
+
+    /// Fields are collected from every template, in order of first
+    /// appearance. Chains, environment variables, mapping keys, and hidden
+    /// fields are skipped
+    #[test]
+    fn test_referenced_fields() {
+        let input = r#"
+.ignore:
+  base: &base
+    headers:
+      Accept: "{{accept}}"
+profiles:
+  local:
+    data:
+      host: "{{protocol}}://localhost"
+chains:
+  token:
+    source: !file
+      path: "{{token_dir}}/token.txt"
+requests:
+  "{{not_a_field}}": !request
+    <<: *base
+    method: GET
+    url: "{{host}}/users/{{user_id}}"
+    query:
+      - "page={{page}}"
+    body: !json
+      token: "{{chains.token}}"
+      user: "{{env.USER}}"
+      id: ["{{user_id}}"]
+"#;
+        let fields = referenced_fields(input).unwrap();
+        assert_eq!(
+            fields.into_iter().collect::<Vec<_>>(),
+            ["protocol", "token_dir", "accept", "host", "user_id", "page",]
+        );
+    }
+
+    /// Generated IDs are slugified from the name, and unique
+    #[rstest]
+    #[case::slug(Some("Get User (v2)"), "get_user_v2")]
+    #[case::no_name(None, "new_recipe")]
+    #[case::symbols(Some("!!!"), "new_recipe")]
+    #[case::taken(Some("Ping"), "ping_2")]
+    fn test_id(#[case] name: Option<&str>, #[case] expected: &str) {
+        let recipes = RecipeTree::new(indexmap! {
+            "ping".into() => RecipeNode::Recipe(Recipe {
+                id: "ping".into(),
+                ..Recipe::factory(())
+            }),
+        })
+        .unwrap();
+        let recipe = NewRecipe {
+            name: name.map(String::from),
+            ..recipe()
+        };
+        assert_eq!(recipe.id(&recipes), RecipeId::from(expected));
+    }
+
+    #[test]
+    fn test_from_record() {
+        let mut headers = HeaderMap::new();
+        headers.insert("content-type", "text/plain".parse().unwrap());
+        headers.insert("authorization", "Bearer secret".parse().unwrap());
+        headers.insert("content-length", "9".parse().unwrap());
+        let record = RequestRecord {
+            method: reqwest::Method::POST,
+            url: Url::parse("http://localhost/users?id={{id}}").unwrap(),
+            headers,
+            body: Some("{{hello}}".into()),
+            ..RequestRecord::factory(())
+        };
+        let recipe = NewRecipe::from_record(&record, None).unwrap();
+        assert_eq!(recipe.method, Method::Post);
+        // Template syntax is escaped, so it's sent as-is
+        assert_eq!(
+            recipe.url.to_string(),
+            "http://localhost/users?id=\\{{id}}"
+        );
+        assert_eq!(
+            recipe.headers,
+            indexmap! {"content-type".into() => "text/plain".into()}
+        );
+        assert_eq!(recipe.body.unwrap().to_string(), "\\{{hello}}");
+    }
+}
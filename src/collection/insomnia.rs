@@ -3,9 +3,10 @@
 
 use crate::{
     collection::{
-        self, cereal::deserialize_from_str, Chain, ChainId, ChainSource,
-        Collection, Folder, HasId, JsonBody, Method, Profile, ProfileId,
-        Recipe, RecipeBody, RecipeId, RecipeNode, RecipeTree,
+        self, cereal::deserialize_from_str, ApiKeyLocation, Chain, ChainId,
+        ChainSource, Collection, Folder, HasId, HttpVersion, ImportReport,
+        JsonBody, Method, Profile, ProfileId, Recipe, RecipeBody,
+        RecipeDefaults, RecipeId, RecipeNode, RecipeTree, RedirectOptions,
     },
     template::Template,
 };
@@ -20,13 +21,14 @@ use tracing::{debug, info, warn};
 
 impl Collection {
     /// Convert an Insomnia exported collection into the slumber format. This
-    /// supports YAML *or* JSON input.
+    /// supports YAML *or* JSON input. Along with the collection, return a
+    /// report of everything that couldn't be imported faithfully.
     ///
     /// This is not async because it's only called by the CLI, where we don't
     /// care about blocking. It keeps the code simpler.
     pub fn from_insomnia(
         insomnia_file: impl AsRef<Path>,
-    ) -> anyhow::Result<Self> {
+    ) -> anyhow::Result<(Self, ImportReport)> {
         let insomnia_file = insomnia_file.as_ref();
         // First, deserialize into the insomnia format
         info!(file = ?insomnia_file, "Loading Insomnia collection");
@@ -51,25 +53,36 @@ impl Collection {
         // but it should get us the right order within each layer
         insomnia.resources.sort_by_key(Resource::sort_key);
 
+        let mut report = ImportReport::default();
         let Grouped {
             workspace_id,
             environments,
             request_groups,
             requests,
-        } = Grouped::group(insomnia)?;
+        } = Grouped::group(insomnia, &mut report)?;
 
         // Convert everything we care about
         let profiles = build_profiles(&workspace_id, environments);
-        let chains = build_chains(&requests);
-        let recipes =
-            build_recipe_tree(&workspace_id, request_groups, requests)?;
+        let chains = build_chains(&requests, &mut report);
+        let recipes = build_recipe_tree(
+            &workspace_id,
+            request_groups,
+            requests,
+            &mut report,
+        )?;
 
-        Ok(Collection {
+        let collection = Collection {
+            base_url: None,
+            defaults: RecipeDefaults::default(),
             profiles,
             recipes,
             chains,
+            protobuf: None,
+            protobuf_registry: None,
             _ignore: serde::de::IgnoredAny,
-        })
+        };
+        report.count(&collection);
+        Ok((collection, report))
     }
 }
 
@@ -165,6 +178,14 @@ enum Authentication {
     Bearer {
         token: String,
     },
+    #[serde(rename = "apikey")]
+    ApiKey {
+        key: String,
+        value: String,
+        /// `header`, `queryParams`, or `cookie`
+        #[serde(rename = "addTo", default)]
+        add_to: Option<String>,
+    },
     /// Catch-all for unknown variants
     #[serde(untagged)]
     Other {
@@ -231,7 +252,10 @@ enum FormParamKind {
 
 impl Grouped {
     /// Group resources by type and throw away what we don't need
-    fn group(insomnia: Insomnia) -> anyhow::Result<Self> {
+    fn group(
+        insomnia: Insomnia,
+        report: &mut ImportReport,
+    ) -> anyhow::Result<Self> {
         let mut workspace_id = None;
         let mut environments = Vec::new();
         let mut request_groups = Vec::new();
@@ -251,9 +275,9 @@ impl Grouped {
                 Resource::Workspace { id } => workspace_id = Some(id),
                 // These are known types but we don't need to do anything
                 Resource::ApiSpec => {}
-                // Anything unknown should give a warning
+                // Anything unknown should be reported
                 Resource::Other { id, kind } => {
-                    warn!("Ignoring resource `{id}` of unknown type `{kind}`");
+                    report.skip(id, format!("Unknown resource type `{kind}`"));
                 }
             }
         }
@@ -293,6 +317,13 @@ impl From<Environment> for Profile {
                 .into_iter()
                 .map(|(k, v)| (k, Template::raw(v)))
                 .collect(),
+            base_url: None,
+            tls: None,
+            proxy: None,
+            allowed_hosts: Vec::new(),
+            encrypted: Vec::new(),
+            unix_socket: None,
+            resolve: IndexMap::new(),
         }
     }
 }
@@ -302,25 +333,28 @@ impl From<RequestGroup> for RecipeNode {
         RecipeNode::Folder(Folder {
             id: folder.id.into(),
             name: Some(folder.name),
+            defaults: RecipeDefaults::default(),
             // This will be populated later
             children: IndexMap::new(),
         })
     }
 }
 
-impl From<Request> for RecipeNode {
-    fn from(request: Request) -> Self {
+impl Request {
+    /// Convert to a recipe. Any features that can't be converted will be
+    /// dropped and reported
+    fn into_recipe(self, report: &mut ImportReport) -> Recipe {
         let mut headers: IndexMap<String, Template> = IndexMap::new();
 
         // Preload headers from implicit sources
-        if let Some(Body { mime_type, .. }) = &request.body {
+        if let Some(Body { mime_type, .. }) = &self.body {
             headers.insert(
                 header::CONTENT_TYPE.as_str().into(),
                 Template::raw(mime_type.to_string()),
             );
         }
         // Load explicit headers *after* so we can override the implicit stuff
-        for header in request.headers {
+        for header in self.headers {
             headers.insert(
                 header.name.to_lowercase(),
                 Template::raw(header.value),
@@ -328,40 +362,40 @@ impl From<Request> for RecipeNode {
         }
         headers.shift_remove(header::USER_AGENT.as_str());
 
-        let body = request
+        let body = self
             .body
             .map(RecipeBody::try_from)
             .transpose()
-            .inspect_err(|error| {
-                warn!(
-                    "Error importing body for request `{id}`: {error}",
-                    id = request.id
-                )
-            })
-            .ok()
-            .flatten();
-
-        // Load authentication scheme
-        let authentication =
-            request.authentication.and_then(|authentication| {
-                let result = authentication.try_into();
-                if let Err(kind) = &result {
-                    warn!(
-                        "Ignoring authentication of unknown type `{kind}` \
-                        for request `{}`",
-                        request.id
-                    );
-                }
-                result.ok()
+            .unwrap_or_else(|error| {
+                report.downgrade(
+                    &self.id,
+                    format!("Error importing body: {error:#}"),
+                );
+                None
             });
 
-        RecipeNode::Recipe(Recipe {
-            id: request.id.into(),
-            name: Some(request.name),
-            method: request.method,
-            url: Template::raw(request.url),
+        // Load authentication scheme
+        let authentication = self.authentication.and_then(|authentication| {
+            let result = authentication.try_into();
+            if let Err(kind) = &result {
+                report.downgrade(
+                    &self.id,
+                    format!(
+                        "Authentication of unsupported type `{kind}` \
+                            was removed"
+                    ),
+                );
+            }
+            result.ok()
+        });
+
+        Recipe {
+            id: self.id.into(),
+            name: Some(self.name),
+            method: self.method,
+            url: Template::raw(self.url),
             body,
-            query: request
+            query: self
                 .parameters
                 .into_iter()
                 .map(|parameter| {
@@ -370,7 +404,21 @@ impl From<Request> for RecipeNode {
                 .collect(),
             headers,
             authentication,
-        })
+            tls: None,
+            http_version: HttpVersion::default(),
+            connect_to: None,
+            unix_socket: None,
+            resolve: IndexMap::new(),
+            compress: None,
+            redirects: RedirectOptions::default(),
+            assertions: Vec::new(),
+            tags: Vec::new(),
+            profile: None,
+            label: None,
+            captures: IndexMap::new(),
+            variables: IndexMap::new(),
+            save: Vec::new(),
+        }
     }
 }
 
@@ -437,6 +485,18 @@ impl TryFrom<Authentication> for collection::Authentication {
             Authentication::Bearer { token } => {
                 Ok(collection::Authentication::Bearer(Template::raw(token)))
             }
+            Authentication::ApiKey { key, value, add_to } => {
+                let location = match add_to.as_deref() {
+                    None | Some("header") => ApiKeyLocation::Header,
+                    Some("queryParams") => ApiKeyLocation::Query,
+                    Some(other) => return Err(format!("apikey ({other})")),
+                };
+                Ok(collection::Authentication::ApiKey {
+                    name: key,
+                    value: Template::raw(value),
+                    location,
+                })
+            }
             // Caller should print a warning for this
             Authentication::Other { kind } => Err(kind),
         }
@@ -485,6 +545,13 @@ fn build_profiles(
                     id,
                     name: Some(environment.name),
                     data,
+                    base_url: None,
+                    tls: None,
+                    proxy: None,
+                    allowed_hosts: Vec::new(),
+                    encrypted: Vec::new(),
+                    unix_socket: None,
+                    resolve: IndexMap::new(),
                 },
             )
         })
@@ -497,7 +564,10 @@ fn build_profiles(
 /// crawl over the Insomnia collection to find where chains need to exist. For
 /// each generated chain, we'll need to pick a consistent ID so the consumer can
 /// link to the same chain.
-fn build_chains(requests: &[Request]) -> IndexMap<ChainId, Chain> {
+fn build_chains(
+    requests: &[Request],
+    report: &mut ImportReport,
+) -> IndexMap<ChainId, Chain> {
     let mut chains = IndexMap::new();
 
     for request in requests {
@@ -510,10 +580,10 @@ fn build_chains(requests: &[Request]) -> IndexMap<ChainId, Chain> {
             if let FormParamKind::File = param.kind {
                 let id: ChainId = param.id.as_str().into();
                 let Some(path) = &param.file_name else {
-                    warn!(
-                        "Form param `{}` is of type `file` \
-                        but missing `file_name` field",
-                        param.id
+                    report.skip(
+                        &param.id,
+                        "Form param is of type `file` but missing \
+                        `file_name` field",
                     );
                     continue;
                 };
@@ -523,9 +593,14 @@ fn build_chains(requests: &[Request]) -> IndexMap<ChainId, Chain> {
                         id,
                         source: ChainSource::File {
                             path: Template::raw(path.to_owned()),
+                            glob: false,
+                            watch: false,
                         },
                         sensitive: false,
                         selector: None,
+                        selector_type: Default::default(),
+                        multi: None,
+                        transforms: Vec::new(),
                         content_type: None,
                         trim: Default::default(),
                     },
@@ -542,6 +617,7 @@ fn build_recipe_tree(
     workspace_id: &str,
     request_groups: Vec<RequestGroup>,
     requests: Vec<Request>,
+    report: &mut ImportReport,
 ) -> anyhow::Result<RecipeTree> {
     // First, we want to match each parent with its children. Hashmap is fine
     // because we won't be iterating over it
@@ -554,7 +630,10 @@ fn build_recipe_tree(
             )
         })
         .chain(requests.into_iter().map(|request| {
-            (request.parent_id.clone(), RecipeNode::from(request))
+            (
+                request.parent_id.clone(),
+                RecipeNode::Recipe(request.into_recipe(report)),
+            )
         }))
         .into_group_map();
 
@@ -618,7 +697,13 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{collection::CollectionFile, test_util::test_data_dir};
+    use crate::{
+        collection::{
+            import::{ImportCounts, ImportIssue},
+            CollectionFile,
+        },
+        test_util::test_data_dir,
+    };
     use indexmap::indexmap;
     use pretty_assertions::assert_eq;
     use rstest::rstest;
@@ -637,7 +722,7 @@ mod tests {
     #[rstest]
     #[tokio::test]
     async fn test_insomnia_import(test_data_dir: PathBuf) {
-        let imported =
+        let (imported, report) =
             Collection::from_insomnia(test_data_dir.join(INSOMNIA_FILE))
                 .unwrap();
         let expected =
@@ -646,6 +731,32 @@ mod tests {
                 .unwrap()
                 .collection;
         assert_eq!(imported, expected);
+
+        assert_eq!(
+            report.created,
+            ImportCounts {
+                profiles: 2,
+                folders: 2,
+                recipes: 8,
+                chains: 1,
+            }
+        );
+        assert_eq!(
+            report.skipped,
+            vec![ImportIssue {
+                item: "jar_99d30891da4bdcebc63947a8fc17f076de878684".into(),
+                reason: "Unknown resource type `cookie_jar`".into(),
+            }]
+        );
+        assert_eq!(
+            report.downgraded,
+            vec![ImportIssue {
+                item: "req_b08ee35904784b5f9af598f9b7fd7ca0".into(),
+                reason: "Authentication of unsupported type `digest` was \
+                    removed"
+                    .into(),
+            }]
+        );
     }
 
     #[test]
@@ -4,24 +4,36 @@ use crate::{
     collection::{
         cereal,
         recipe_tree::{RecipeNode, RecipeTree},
+        schema,
+    },
+    http::{
+        ContentType, Pattern, ProtobufRegistry, ProxyOptions, Query, Selector,
     },
-    http::{ContentType, Query},
     template::Template,
+    util::mime_matches,
 };
 use anyhow::anyhow;
 use derive_more::{Deref, Display, From, FromStr};
 use indexmap::IndexMap;
 use itertools::Itertools;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::time::Duration;
+use std::{path::PathBuf, time::Duration};
 use strum::{EnumIter, IntoEnumIterator};
 
 /// A collection of profiles, requests, etc. This is the primary Slumber unit
 /// of configuration.
-#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, JsonSchema, Serialize, Deserialize)]
 #[cfg_attr(test, derive(PartialEq))]
 #[serde(deny_unknown_fields)]
 pub struct Collection {
+    /// Prefix for relative recipe URLs. A profile's `base_url` takes
+    /// precedence over this
+    pub base_url: Option<Template>,
+    /// Headers, query parameters, and authentication applied to every recipe
+    /// in the collection. See [RecipeDefaults]
+    #[serde(default)]
+    pub defaults: RecipeDefaults,
     #[serde(default, deserialize_with = "cereal::deserialize_id_map")]
     pub profiles: IndexMap<ProfileId, Profile>,
     #[serde(default, deserialize_with = "cereal::deserialize_id_map")]
@@ -30,16 +42,40 @@ pub struct Collection {
     /// intuitive
     #[serde(default, rename = "requests")]
     pub recipes: RecipeTree,
+    /// Schema for decoding protobuf response bodies
+    pub protobuf: Option<ProtobufSchema>,
+    /// Compiled version of [Self::protobuf]. Populated when the collection is
+    /// loaded from its file
+    #[serde(skip)]
+    pub protobuf_registry: Option<ProtobufRegistry>,
     /// A hack-ish to allow users to add arbitrary data to their collection
     /// file without triggering a unknown field error. Ideally we could
     /// ignore anything that starts with `.` (recursively) but that
     /// requires a custom serde impl for each type, or changes to the macro
     #[serde(default, skip_serializing, rename = ".ignore")]
+    #[schemars(with = "serde_json::Value")]
     pub _ignore: serde::de::IgnoredAny,
 }
 
+/// `.proto` files used to decode `application/x-protobuf` responses. Protobuf
+/// isn't self-describing, so we can't show anything useful without these.
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize)]
+#[cfg_attr(test, derive(PartialEq))]
+#[serde(deny_unknown_fields)]
+pub struct ProtobufSchema {
+    /// Files to compile, relative to the collection file
+    pub files: Vec<PathBuf>,
+    /// Directories to search for imported files, relative to the collection
+    /// file. The collection's directory is always searched
+    #[serde(default)]
+    pub include: Vec<PathBuf>,
+    /// Fully qualified name of the message type to decode responses as, if
+    /// the response's `Content-Type` doesn't specify one
+    pub message: Option<String>,
+}
+
 /// Mutually exclusive hot-swappable config group
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize)]
 #[cfg_attr(test, derive(PartialEq))]
 #[serde(deny_unknown_fields)]
 pub struct Profile {
@@ -47,6 +83,31 @@ pub struct Profile {
     pub id: ProfileId,
     pub name: Option<String>,
     pub data: IndexMap<String, Template>,
+    /// Prefix for relative recipe URLs in this profile. Overrides the
+    /// collection's `base_url`
+    pub base_url: Option<Template>,
+    /// TLS settings for all requests made with this profile
+    pub tls: Option<TlsOptions>,
+    /// Proxy settings for all requests made with this profile. If defined,
+    /// these replace the global proxy settings from the config
+    pub proxy: Option<ProxyOptions>,
+    /// Glob patterns for hosts that requests with this profile can be sent
+    /// to. Requests to any other host are blocked unless the user confirms
+    /// them. If empty, all hosts are allowed
+    #[serde(default)]
+    pub allowed_hosts: Vec<String>,
+    /// Fields whose values are stored encrypted in the local secrets file,
+    /// instead of in `data`. Decrypted values are added to `data` when the
+    /// collection is loaded
+    #[serde(default)]
+    pub encrypted: Vec<String>,
+    /// Send requests made with this profile over a Unix domain socket at
+    /// this path
+    pub unix_socket: Option<Template>,
+    /// Override DNS resolution for requests made with this profile, like
+    /// curl's `--resolve`. Maps hostname to IP address (and optional port)
+    #[serde(default)]
+    pub resolve: IndexMap<String, Template>,
 }
 
 impl Profile {
@@ -63,6 +124,13 @@ impl crate::test_util::Factory for Profile {
             id: "profile1".into(),
             name: None,
             data: IndexMap::new(),
+            base_url: None,
+            tls: None,
+            proxy: None,
+            allowed_hosts: Vec::new(),
+            encrypted: Vec::new(),
+            unix_socket: None,
+            resolve: IndexMap::new(),
         }
     }
 }
@@ -77,6 +145,7 @@ impl crate::test_util::Factory for Profile {
     From,
     Hash,
     PartialEq,
+    JsonSchema,
     Serialize,
     Deserialize,
 )]
@@ -97,13 +166,18 @@ impl crate::test_util::Factory for ProfileId {
 }
 
 /// A gathering of like-minded recipes and/or folders
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize)]
 #[cfg_attr(test, derive(PartialEq))]
 #[serde(deny_unknown_fields)]
 pub struct Folder {
     #[serde(skip)] // This will be auto-populated from the map key
     pub id: RecipeId,
     pub name: Option<String>,
+    /// Headers, query parameters, and authentication applied to every recipe
+    /// in this folder, including nested folders. These take precedence over
+    /// the defaults of any parent folder and the collection.
+    #[serde(default)]
+    pub defaults: RecipeDefaults,
     /// RECURSION. Use `requests` in serde to match the root field.
     #[serde(
         default,
@@ -126,11 +200,93 @@ impl crate::test_util::Factory for Folder {
         Self {
             id: "folder1".into(),
             name: None,
+            defaults: RecipeDefaults::default(),
             children: IndexMap::new(),
         }
     }
 }
 
+/// Request components shared by a group of recipes, defined on the collection
+/// or a folder. Defaults are merged into each recipe when the collection is
+/// loaded. Anything the recipe defines itself takes precedence:
+/// - Headers are overridden by a recipe header of the same name (ignoring
+///   case)
+/// - Query parameters are overridden by any recipe parameter with the same
+///   name. Parameters can be repeated, so *all* default values for that
+///   parameter are dropped
+/// - Authentication is only used if the recipe has no `authentication` and
+///   no `Authorization` header
+#[derive(Clone, Debug, Default, JsonSchema, Serialize, Deserialize)]
+#[cfg_attr(test, derive(PartialEq))]
+#[serde(default, deny_unknown_fields)]
+pub struct RecipeDefaults {
+    pub headers: IndexMap<String, Template>,
+    #[serde(deserialize_with = "cereal::deserialize_query_parameters")]
+    #[schemars(schema_with = "schema::query_parameters")]
+    pub query: Vec<(String, Template)>,
+    pub authentication: Option<Authentication>,
+}
+
+impl RecipeDefaults {
+    /// Layer another set of defaults on top of these, e.g. a folder's
+    /// defaults on top of its parent's. Values in `other` take precedence
+    pub fn merge(&self, other: &Self) -> Self {
+        Self {
+            headers: merge_headers(&self.headers, &other.headers),
+            query: merge_query(&self.query, &other.query),
+            authentication: other
+                .authentication
+                .clone()
+                .or_else(|| self.authentication.clone()),
+        }
+    }
+
+    /// Apply these defaults to a recipe, without overwriting anything the
+    /// recipe defines itself
+    pub fn apply(&self, recipe: &mut Recipe) {
+        // The Authorization header would conflict with the default scheme
+        let has_authorization = recipe
+            .headers
+            .keys()
+            .any(|header| header.eq_ignore_ascii_case("authorization"));
+        if recipe.authentication.is_none() && !has_authorization {
+            recipe.authentication = self.authentication.clone();
+        }
+        recipe.headers = merge_headers(&self.headers, &recipe.headers);
+        recipe.query = merge_query(&self.query, &recipe.query);
+    }
+}
+
+/// Combine two header maps. Headers in `overrides` replace headers of the same
+/// name in `base`. Defaults come first, so their order is stable.
+fn merge_headers(
+    base: &IndexMap<String, Template>,
+    overrides: &IndexMap<String, Template>,
+) -> IndexMap<String, Template> {
+    base.iter()
+        .filter(|(header, _)| {
+            !overrides
+                .keys()
+                .any(|other| other.eq_ignore_ascii_case(header))
+        })
+        .chain(overrides)
+        .map(|(header, value)| (header.clone(), value.clone()))
+        .collect()
+}
+
+/// Combine two sets of query parameters. Any parameter in `overrides` replaces
+/// *every* value of the same parameter in `base`
+fn merge_query(
+    base: &[(String, Template)],
+    overrides: &[(String, Template)],
+) -> Vec<(String, Template)> {
+    base.iter()
+        .filter(|(param, _)| !overrides.iter().any(|(other, _)| other == param))
+        .chain(overrides)
+        .cloned()
+        .collect()
+}
+
 impl Recipe {
     /// Get a presentable name for this recipe
     pub fn name(&self) -> &str {
@@ -150,6 +306,20 @@ impl crate::test_util::Factory for Recipe {
             authentication: None,
             query: Vec::new(),
             headers: IndexMap::new(),
+            tls: None,
+            http_version: HttpVersion::default(),
+            connect_to: None,
+            unix_socket: None,
+            resolve: IndexMap::new(),
+            compress: None,
+            redirects: RedirectOptions::default(),
+            assertions: Vec::new(),
+            tags: Vec::new(),
+            profile: None,
+            label: None,
+            captures: IndexMap::new(),
+            variables: IndexMap::new(),
+            save: Vec::new(),
         }
     }
 }
@@ -158,13 +328,23 @@ impl crate::test_util::Factory for Recipe {
 /// order to distinguish it from a single instance of an HTTP request. And it's
 /// not called `RequestTemplate` because the word "template" has a specific
 /// meaning related to string interpolation.
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize)]
 #[cfg_attr(test, derive(PartialEq))]
 #[serde(deny_unknown_fields)]
 pub struct Recipe {
     #[serde(skip)] // This will be auto-populated from the map key
     pub id: RecipeId,
     pub name: Option<String>,
+    /// Template rendered for each request and stored with it in history, to
+    /// distinguish requests from the same recipe
+    pub label: Option<Template>,
+    /// Arbitrary labels for grouping recipes. Tags can be used to filter the
+    /// recipe list, and to select recipes from the CLI
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Always render this recipe with the given profile, regardless of which
+    /// profile is selected
+    pub profile: Option<ProfileId>,
     /// *Not* a template string because the usefulness doesn't justify the
     /// complexity. This gives the user an immediate error if the method is
     /// wrong which is helpful.
@@ -172,13 +352,72 @@ pub struct Recipe {
     pub url: Template,
     pub body: Option<RecipeBody>,
     pub authentication: Option<Authentication>,
+    /// Values local to this recipe, accessible from its templates via
+    /// `{{vars.<name>}}`. Each value is itself a template
+    #[serde(default)]
+    pub variables: IndexMap<String, Template>,
     #[serde(
         default,
         deserialize_with = "cereal::deserialize_query_parameters"
     )]
+    #[schemars(schema_with = "schema::query_parameters")]
     pub query: Vec<(String, Template)>,
     #[serde(default)]
     pub headers: IndexMap<String, Template>,
+    /// TLS settings. Takes precedence over the selected profile's settings
+    pub tls: Option<TlsOptions>,
+    #[serde(default)]
+    pub http_version: HttpVersion,
+    /// Connect to this host instead of the one in the URL, like curl's
+    /// `--connect-to`. The URL's host is still used for TLS SNI and the
+    /// `Host` header, and the URL's port is still used
+    pub connect_to: Option<Template>,
+    /// Send the request over a Unix domain socket at this path. Takes
+    /// precedence over the selected profile's socket
+    pub unix_socket: Option<Template>,
+    /// Override DNS resolution, like curl's `--resolve`. Maps hostname to IP
+    /// address (and optional port). Merged with the selected profile's
+    /// mapping; entries here take precedence
+    #[serde(default)]
+    pub resolve: IndexMap<String, Template>,
+    /// Compress the rendered body before sending it
+    pub compress: Option<Compression>,
+    /// How to handle 3xx redirect responses
+    #[serde(default)]
+    pub redirects: RedirectOptions,
+    /// Conditions the response must satisfy, checked by `slumber test`
+    #[serde(default)]
+    pub assertions: Vec<Assertion>,
+    /// Named values to extract from the response, printed by `slumber seed`
+    #[serde(default)]
+    pub captures: IndexMap<String, Query>,
+    /// Write the response body to a file, depending on its content type
+    #[serde(default)]
+    pub save: Vec<SaveRule>,
+}
+
+/// Write the response body to a file if its `Content-Type` matches. Rules are
+/// checked in order, and only the first match is used.
+///
+/// Type parameter allows this to be re-used for post-render purposes (with
+/// `T=String`).
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize)]
+#[cfg_attr(test, derive(PartialEq))]
+#[serde(deny_unknown_fields)]
+pub struct SaveRule<T = Template> {
+    /// MIME type to match, e.g. `application/pdf`. The subtype can be `*`
+    /// (e.g. `text/*`) to match anything. Parameters such as `charset` are
+    /// ignored.
+    pub content_type: String,
+    /// File to write to, relative to the current directory
+    pub path: T,
+}
+
+impl<T> SaveRule<T> {
+    /// Does this rule apply to a response with the given `Content-Type`?
+    pub fn matches(&self, content_type: &str) -> bool {
+        mime_matches(&self.content_type, content_type)
+    }
 }
 
 #[derive(
@@ -191,6 +430,7 @@ pub struct Recipe {
     From,
     Hash,
     PartialEq,
+    JsonSchema,
     Serialize,
     Deserialize,
 )]
@@ -210,6 +450,31 @@ impl crate::test_util::Factory for RecipeId {
     }
 }
 
+/// A condition that a response must satisfy. Assertions are declared on a
+/// recipe and checked by `slumber test`.
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize)]
+#[cfg_attr(test, derive(PartialEq))]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+#[schemars(untagged)]
+pub enum Assertion {
+    /// Response status code must equal this value
+    Status(u16),
+    /// Response must include a header. If a condition is given, the header's
+    /// value must satisfy it as well
+    Header {
+        name: String,
+        equals: Option<String>,
+        matches: Option<Pattern>,
+    },
+    /// A query against the response body must return exactly one value. If a
+    /// condition is given, the stringified value must satisfy it as well
+    Body {
+        selector: Query,
+        equals: Option<String>,
+        matches: Option<Pattern>,
+    },
+}
+
 /// HTTP method. This is duplicated from reqwest's Method so we can enforce
 /// the method is valid during deserialization. This is also generally more
 /// ergonomic at the cost of some flexibility.
@@ -248,6 +513,88 @@ impl From<Method> for String {
     }
 }
 
+/// Compression algorithm for request bodies. The body is compressed after
+/// rendering, and `Content-Encoding` is set to match
+#[derive(
+    Copy,
+    Clone,
+    Debug,
+    Display,
+    Eq,
+    PartialEq,
+    JsonSchema,
+    Serialize,
+    Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Compression {
+    #[display("gzip")]
+    Gzip,
+    #[display("deflate")]
+    Deflate,
+    #[display("br")]
+    #[serde(rename = "br", alias = "brotli")]
+    Brotli,
+}
+
+/// HTTP protocol version to use for a request
+#[derive(
+    Copy,
+    Clone,
+    Debug,
+    Default,
+    Display,
+    Eq,
+    Hash,
+    PartialEq,
+    JsonSchema,
+    Serialize,
+    Deserialize,
+)]
+pub enum HttpVersion {
+    /// Use whatever the server supports. For HTTPS this is negotiated during
+    /// the TLS handshake; plain HTTP always uses HTTP/1.1
+    #[default]
+    #[display("auto")]
+    #[serde(rename = "auto")]
+    Auto,
+    #[display("1.1")]
+    #[serde(rename = "1.1")]
+    Http1_1,
+    /// Require HTTP/2. For plain HTTP, this uses h2c with prior knowledge,
+    /// i.e. the client assumes the server supports HTTP/2 without upgrading
+    #[display("2")]
+    #[serde(rename = "2")]
+    Http2,
+}
+
+/// Redirect handling for a request. Redirects are followed by default, the
+/// same as in most HTTP clients
+#[derive(
+    Copy, Clone, Debug, Eq, Hash, PartialEq, JsonSchema, Serialize, Deserialize,
+)]
+#[serde(default, deny_unknown_fields)]
+pub struct RedirectOptions {
+    /// Follow redirects? If disabled, the 3xx response is returned as-is
+    pub follow: bool,
+    /// Maximum number of redirects to follow before giving up with an error
+    pub max: usize,
+    /// Keep the method and body when following a 301 or 302. Most clients
+    /// switch to `GET` for these. 307 and 308 always preserve the method, and
+    /// 303 always switches to `GET`
+    pub preserve_method: bool,
+}
+
+impl Default for RedirectOptions {
+    fn default() -> Self {
+        Self {
+            follow: true,
+            max: 10,
+            preserve_method: false,
+        }
+    }
+}
+
 #[cfg(test)]
 impl crate::test_util::Factory for Chain {
     fn factory(_: ()) -> Self {
@@ -257,9 +604,13 @@ impl crate::test_util::Factory for Chain {
                 recipe: "recipe1".into(),
                 trigger: Default::default(),
                 section: Default::default(),
+                poll: None,
             },
             sensitive: false,
             selector: None,
+            selector_type: SelectorType::default(),
+            multi: None,
+            transforms: Vec::new(),
             content_type: None,
             trim: ChainOutputTrim::default(),
         }
@@ -272,14 +623,93 @@ impl crate::test_util::Factory for Chain {
 ///
 /// Type parameter allows this to be re-used for post-render purposes (with
 /// `T=String`).
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize)]
 #[cfg_attr(test, derive(PartialEq))]
 #[serde(rename_all = "snake_case", deny_unknown_fields)]
+#[schemars(untagged)]
 pub enum Authentication<T = Template> {
     /// `Authorization: Basic {username:password | base64}`
     Basic { username: T, password: Option<T> },
     /// `Authorization: Bearer {token}`
     Bearer(T),
+    /// An API key, sent in a header or query parameter
+    ApiKey {
+        /// Name of the header or query parameter
+        name: String,
+        value: T,
+        #[serde(default)]
+        location: ApiKeyLocation,
+    },
+    /// Run an external helper command, and use its output as the entire
+    /// `Authorization` header value (e.g. `Negotiate <token>`). This enables
+    /// schemes that Slumber doesn't implement itself, such as Kerberos/SPNEGO
+    Command { command: Vec<T> },
+}
+
+/// Where to put an API key in the request
+#[derive(
+    Copy,
+    Clone,
+    Debug,
+    Default,
+    Display,
+    Eq,
+    Hash,
+    PartialEq,
+    JsonSchema,
+    Serialize,
+    Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiKeyLocation {
+    #[default]
+    #[display("header")]
+    Header,
+    #[display("query")]
+    Query,
+}
+
+/// TLS settings for a request. These can be defined on both profiles and
+/// recipes. Each field defined on the recipe takes precedence over the same
+/// field on the selected profile.
+///
+/// Type parameter allows this to be re-used for post-render purposes (with
+/// `T=String`).
+#[derive(
+    Clone,
+    Debug,
+    Default,
+    Eq,
+    Hash,
+    PartialEq,
+    JsonSchema,
+    Serialize,
+    Deserialize,
+)]
+#[serde(default, deny_unknown_fields)]
+#[schemars(bound = "T: JsonSchema + Default + Serialize")]
+pub struct TlsOptions<T = Template> {
+    /// Certificate to present to the server, for mutual TLS
+    pub client_certificate: Option<ClientCertificate<T>>,
+    /// Path to a PEM file of additional CA certificates to trust
+    pub ca_bundle: Option<T>,
+    /// Disable certificate validation entirely. Dangerous!
+    pub danger_accept_invalid_certs: Option<bool>,
+}
+
+/// A client certificate+key pair, for mutual TLS. All fields are paths to
+/// files, relative to the current directory.
+#[derive(
+    Clone, Debug, Eq, Hash, PartialEq, JsonSchema, Serialize, Deserialize,
+)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+#[schemars(untagged)]
+pub enum ClientCertificate<T = Template> {
+    /// PEM-encoded certificate chain and private key. If `key` is omitted,
+    /// the key must be in the certificate file.
+    Pem { certificate: T, key: Option<T> },
+    /// PKCS#12 archive (`.p12`/`.pfx`) containing the certificate and key
+    Pkcs12 { path: T, password: Option<T> },
 }
 
 /// Template for a request body. `Raw` is the "default" variant, which repesents
@@ -312,7 +742,7 @@ impl From<&str> for RecipeBody {
 /// a generic param `S`. For recipes, `S = Template`, so that strings can be
 /// templatized. Other type params are used throughout the app to represent the
 /// result of certain transformations.
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize)]
 #[cfg_attr(test, derive(PartialEq))]
 #[serde(untagged)]
 pub enum JsonBody<S = Template> {
@@ -420,7 +850,7 @@ impl From<serde_json::Value> for JsonBody<Template> {
 /// A chain is a means to data from one response in another request. The chain
 /// is the middleman: it defines where and how to pull the value, then recipes
 /// can use it in a template via `{{chains.<chain_id>}}`.
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize)]
 #[cfg_attr(test, derive(PartialEq))]
 #[serde(deny_unknown_fields)]
 pub struct Chain {
@@ -430,10 +860,20 @@ pub struct Chain {
     /// Mask chained value in the UI
     #[serde(default)]
     pub sensitive: bool,
-    /// Selector to extract a value from the response. This uses JSONPath
-    /// regardless of the content type. Non-JSON values will be converted to
-    /// JSON, then converted back.
-    pub selector: Option<Query>,
+    /// Selector to extract a value from the response. The query language
+    /// depends on the content type: JSONPath for JSON, XPath for XML, and CSS
+    /// selectors for HTML
+    pub selector: Option<Selector>,
+    /// Override how the selector is interpreted
+    #[serde(default)]
+    pub selector_type: SelectorType,
+    /// What to do when the selector matches multiple values (or none). If
+    /// omitted, regex selectors use the first match, and all other selectors
+    /// require exactly one result
+    pub multi: Option<ChainMultiValue>,
+    /// Modifications to make to the value, in order, after the selector
+    #[serde(default)]
+    pub transforms: Vec<ChainTransform>,
     /// Hard-code the content type of the response. Only needed if a selector
     /// is given and the content type can't be dynamically determined
     /// correctly. This is needed if the chain source is not an HTTP
@@ -456,6 +896,7 @@ pub struct Chain {
     From,
     Hash,
     PartialEq,
+    JsonSchema,
     Serialize,
     Deserialize,
 )]
@@ -468,9 +909,10 @@ impl From<&str> for ChainId {
 }
 
 /// The source of data for a chain
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize)]
 #[cfg_attr(test, derive(PartialEq))]
 #[serde(rename_all = "snake_case", deny_unknown_fields)]
+#[schemars(untagged)]
 pub enum ChainSource {
     /// Run an external command to get a result
     Command {
@@ -481,7 +923,26 @@ pub enum ChainSource {
     #[serde(rename = "env")]
     Environment { variable: Template },
     /// Load data from a file
-    File { path: Template },
+    File {
+        path: Template,
+        /// Treat the path as a glob pattern, and use the most recently
+        /// modified matching file. Opt-in, so literal paths containing glob
+        /// characters (e.g. `data[1].json`) work as-is
+        #[serde(default)]
+        glob: bool,
+        /// Refresh previews in the TUI when the file changes
+        #[serde(default)]
+        watch: bool,
+    },
+    /// Load a secret from the OS keyring (e.g. macOS Keychain, Windows
+    /// Credential Manager, Secret Service on Linux)
+    Keyring {
+        /// Keyring service name. If omitted, use a service unique to the
+        /// current collection, which is what `slumber secret set` uses
+        service: Option<Template>,
+        /// Key (username) of the secret within the service
+        key: Template,
+    },
     /// Prompt the user for a value
     Prompt {
         /// Descriptor to show to the user
@@ -497,9 +958,53 @@ pub enum ChainSource {
         trigger: ChainRequestTrigger,
         #[serde(default)]
         section: ChainRequestSection,
+        /// Re-send the request until its response satisfies a condition
+        #[serde(default)]
+        poll: Option<ChainRequestPoll>,
+    },
+    /// Read data piped to the process's stdin. If stdin is a terminal (e.g.
+    /// in the TUI, or nothing was piped), prompt the user instead
+    #[schemars(schema_with = "schema::unit_variant")]
+    Stdin,
+    /// Load a secret from an external password manager CLI. This is a preset
+    /// over [Self::Command], so the user doesn't have to remember the exact
+    /// invocation for each tool.
+    Secret {
+        manager: SecretManager,
+        /// Name/path/reference of the secret, as understood by the manager
+        name: Template,
     },
 }
 
+/// An external password manager, which can be invoked via its CLI to load a
+/// secret
+#[derive(Copy, Clone, Debug, JsonSchema, Serialize, Deserialize)]
+#[cfg_attr(test, derive(PartialEq))]
+#[serde(rename_all = "snake_case")]
+pub enum SecretManager {
+    /// [pass](https://www.passwordstore.org/)
+    Pass,
+    /// [1Password CLI](https://developer.1password.com/docs/cli/)
+    #[serde(rename = "op")]
+    OnePassword,
+}
+
+impl ChainSource {
+    /// Name of this source type, as it appears in the collection file
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Command { .. } => "command",
+            Self::Environment { .. } => "env",
+            Self::File { .. } => "file",
+            Self::Keyring { .. } => "keyring",
+            Self::Prompt { .. } => "prompt",
+            Self::Request { .. } => "request",
+            Self::Secret { .. } => "secret",
+            Self::Stdin => "stdin",
+        }
+    }
+}
+
 /// Test-only helpers
 #[cfg(test)]
 impl ChainSource {
@@ -513,37 +1018,185 @@ impl ChainSource {
 }
 
 /// The component of the response to use as the chain source
-#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, JsonSchema, Serialize, Deserialize)]
 #[cfg_attr(test, derive(PartialEq))]
 #[serde(rename_all = "snake_case", deny_unknown_fields)]
+#[schemars(untagged)]
 pub enum ChainRequestSection {
     #[default]
+    #[schemars(schema_with = "schema::unit_variant")]
     Body,
     Header(String),
+    /// All headers, as a JSON object so a selector can be applied to them.
+    /// Each header name maps to a list of all its values
+    #[schemars(schema_with = "schema::unit_variant")]
+    Headers,
 }
 
 /// Define when a recipe with a chained request should auto-execute the
 /// dependency request.
-#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, JsonSchema, Serialize, Deserialize)]
 #[cfg_attr(test, derive(PartialEq))]
 #[serde(rename_all = "snake_case", deny_unknown_fields)]
+#[schemars(untagged)]
 pub enum ChainRequestTrigger {
     /// Never trigger the request. This is the default because upstream
     /// requests could be mutating, so we want the user to explicitly opt into
     /// automatic execution.
     #[default]
+    #[schemars(schema_with = "schema::unit_variant")]
     Never,
     /// Trigger the request if there is none in history
+    #[schemars(schema_with = "schema::unit_variant")]
     NoHistory,
     /// Trigger the request if the last response is older than some
     /// duration (or there is none in history)
-    Expire(#[serde(with = "cereal::serde_duration")] Duration),
+    Expire(
+        #[serde(with = "cereal::serde_duration")]
+        #[schemars(with = "String")]
+        Duration,
+    ),
+    /// Trigger the request if the last response has expired, according to a
+    /// lifetime (in seconds) read from its own body via JSONPath, e.g.
+    /// `$.expires_in`. The lifetime is measured from when the response was
+    /// received. If the lifetime can't be determined, the request is
+    /// triggered.
+    ExpireIn(Query),
+    /// Trigger the request if the last response has expired, according to an
+    /// expiration time read from its own body via JSONPath, e.g.
+    /// `$.expires_at`. The value can be a Unix timestamp (in seconds) or an
+    /// RFC 3339 date-time. If the expiration can't be determined, the request
+    /// is triggered.
+    ExpireAt(Query),
     /// Trigger the request every time the dependent request is rendered
+    #[schemars(schema_with = "schema::unit_variant")]
     Always,
 }
 
+/// Repeatedly re-send a chained request until its response body matches a
+/// condition. Useful for async APIs, where one request starts a job and
+/// another reports its status.
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize)]
+#[cfg_attr(test, derive(PartialEq))]
+#[serde(deny_unknown_fields)]
+pub struct ChainRequestPoll {
+    /// JSONPath query to apply to the response body
+    pub until: Query,
+    /// The condition is met when the query result stringifies to this value
+    pub equals: String,
+    /// Time to wait between requests
+    #[serde(
+        default = "ChainRequestPoll::default_interval",
+        with = "cereal::serde_duration"
+    )]
+    #[schemars(with = "String")]
+    pub interval: Duration,
+    /// Give up after this much time has passed since polling started
+    #[serde(
+        default = "ChainRequestPoll::default_timeout",
+        with = "cereal::serde_duration"
+    )]
+    #[schemars(with = "String")]
+    pub timeout: Duration,
+}
+
+impl ChainRequestPoll {
+    fn default_interval() -> Duration {
+        Duration::from_secs(1)
+    }
+
+    fn default_timeout() -> Duration {
+        Duration::from_secs(60)
+    }
+}
+
+/// How a chain's selector should be interpreted
+#[derive(Copy, Clone, Debug, Default, JsonSchema, Serialize, Deserialize)]
+#[cfg_attr(test, derive(PartialEq))]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum SelectorType {
+    /// Choose the query language based on the content type
+    #[default]
+    Auto,
+    /// Regex with exactly one capture group, applied to the raw value. This
+    /// doesn't require a content type, so it works for plain text, HTML, or
+    /// command output
+    Regex,
+}
+
+/// How to reduce multiple selector results into a single value
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize)]
+#[cfg_attr(test, derive(PartialEq))]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+#[schemars(untagged)]
+pub enum ChainMultiValue {
+    /// Fail unless there is exactly one result
+    #[schemars(schema_with = "schema::unit_variant")]
+    Error,
+    /// Use the first result
+    #[schemars(schema_with = "schema::unit_variant")]
+    First,
+    /// Use the last result
+    #[schemars(schema_with = "schema::unit_variant")]
+    Last,
+    /// Join all results with a separator. No results gives an empty string
+    Join(String),
+}
+
+/// A modification to a chain's value. Most transforms only apply to text, and
+/// will fail if the value isn't valid UTF-8.
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize)]
+#[cfg_attr(test, derive(PartialEq))]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+#[schemars(untagged)]
+pub enum ChainTransform {
+    /// Trim whitespace from both ends
+    #[schemars(schema_with = "schema::unit_variant")]
+    Trim,
+    /// Replace all occurrences of a string
+    Replace { from: String, to: String },
+    /// Replace all matches of a regex. The replacement can refer to capture
+    /// groups with `$1`, `$name`, etc.
+    RegexReplace {
+        pattern: String,
+        replacement: String,
+    },
+    /// Convert to uppercase
+    #[schemars(schema_with = "schema::unit_variant")]
+    Upper,
+    /// Convert to lowercase
+    #[schemars(schema_with = "schema::unit_variant")]
+    Lower,
+    /// Encode as standard base64. This works on any value, including binary
+    #[schemars(schema_with = "schema::unit_variant")]
+    Base64Encode,
+    /// Decode from standard base64
+    #[schemars(schema_with = "schema::unit_variant")]
+    Base64Decode,
+    /// Parse as JSON and apply a JSONPath query, which must return exactly
+    /// one result
+    #[serde(rename = "jsonpath")]
+    JsonPath(Query),
+}
+
+impl ChainTransform {
+    /// Name of this transform, as it appears in the collection file
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Trim => "trim",
+            Self::Replace { .. } => "replace",
+            Self::RegexReplace { .. } => "regex_replace",
+            Self::Upper => "upper",
+            Self::Lower => "lower",
+            Self::Base64Encode => "base64_encode",
+            Self::Base64Decode => "base64_decode",
+            Self::JsonPath(_) => "jsonpath",
+        }
+    }
+}
+
 /// Trim whitespace from rendered output
-#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize)]
+#[derive(Copy, Clone, Debug, Default, JsonSchema, Serialize, Deserialize)]
 #[cfg_attr(test, derive(PartialEq))]
 #[serde(rename_all = "snake_case", deny_unknown_fields)]
 pub enum ChainOutputTrim {
@@ -0,0 +1,587 @@
+//! Export a collection as an OpenAPI 3 document. Rendering templates requires
+//! a profile and could trigger chains, so the export works from the raw
+//! templates instead: each `{{key}}` becomes an OpenAPI placeholder `{key}`.
+//! This is lossy, but it's enough to seed API documentation.
+
+use crate::{
+    collection::{
+        ApiKeyLocation, Authentication, Collection, Recipe, RecipeBody,
+    },
+    template::Template,
+};
+use indexmap::{IndexMap, IndexSet};
+use itertools::Itertools;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+/// Version of the spec that we generate
+const OPENAPI_VERSION: &str = "3.0.3";
+/// Headers that OpenAPI describes with other fields, and ignores if they're
+/// given as parameters
+const RESERVED_HEADERS: &[&str] = &["accept", "authorization", "content-type"];
+
+/// An OpenAPI document. This only includes the parts of the spec that we can
+/// fill in from a collection. Schemas and examples are plain JSON values.
+#[derive(Debug, Serialize)]
+pub struct OpenApi {
+    openapi: &'static str,
+    info: Info,
+    servers: Vec<Server>,
+    /// Path, then lowercase method
+    paths: IndexMap<String, IndexMap<String, Operation>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    components: Option<Components>,
+}
+
+#[derive(Debug, Serialize)]
+struct Info {
+    title: String,
+    version: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+struct Server {
+    url: String,
+    #[serde(skip_serializing_if = "IndexMap::is_empty")]
+    variables: IndexMap<String, ServerVariable>,
+}
+
+#[derive(Debug, Serialize)]
+struct ServerVariable {
+    default: String,
+    #[serde(rename = "enum", skip_serializing_if = "Vec::is_empty")]
+    values: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Operation {
+    operation_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    summary: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    parameters: Vec<Parameter>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request_body: Option<RequestBody>,
+    /// Each entry maps a security scheme name to its required scopes
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    security: Vec<IndexMap<String, Vec<String>>>,
+    responses: IndexMap<&'static str, Response>,
+}
+
+#[derive(Debug, Serialize)]
+struct Parameter {
+    name: String,
+    #[serde(rename = "in")]
+    location: &'static str,
+    /// Path parameters are required by definition
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    required: bool,
+    schema: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    example: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct RequestBody {
+    /// Content type, then media type object
+    content: IndexMap<String, Value>,
+}
+
+#[derive(Debug, Serialize)]
+struct Response {
+    description: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Components {
+    security_schemes: IndexMap<String, Value>,
+}
+
+impl Collection {
+    /// Generate a best-effort OpenAPI document from the collection's recipes.
+    /// Along with the document, return a warning for each recipe that
+    /// couldn't be exported faithfully.
+    pub fn to_openapi(&self, title: &str) -> (OpenApi, Vec<String>) {
+        let mut warnings = Vec::new();
+        let mut servers: IndexSet<String> = self
+            .profiles
+            .values()
+            .filter_map(|profile| profile.base_url.as_ref())
+            .chain(&self.base_url)
+            .map(placeholders)
+            .collect();
+        let mut paths: IndexMap<String, IndexMap<String, Operation>> =
+            IndexMap::new();
+        let mut security_schemes = IndexMap::new();
+
+        for recipe in self.recipes.iter().filter_map(|(_, node)| node.recipe())
+        {
+            let url = split_url(&placeholders(&recipe.url));
+            servers.extend(url.server.clone());
+            let method = recipe.method.to_string().to_lowercase();
+            let operations = paths.entry(url.path.clone()).or_default();
+            if let Some(existing) = operations.get(&method) {
+                warnings.push(format!(
+                    "Skipping recipe `{}`: {} {} is already defined by `{}`",
+                    recipe.id, recipe.method, url.path, existing.operation_id
+                ));
+                continue;
+            }
+
+            let mut operation = operation(recipe, &url);
+            if let Some(authentication) = &recipe.authentication {
+                match security_scheme(authentication) {
+                    Some((name, scheme)) => {
+                        operation
+                            .security
+                            .push(IndexMap::from([(name.clone(), Vec::new())]));
+                        security_schemes.insert(name, scheme);
+                    }
+                    None => warnings.push(format!(
+                        "Recipe `{}`: command authentication can't be \
+                        represented in OpenAPI",
+                        recipe.id
+                    )),
+                }
+            }
+            operations.insert(method, operation);
+        }
+
+        let document = OpenApi {
+            openapi: OPENAPI_VERSION,
+            info: Info {
+                title: title.to_owned(),
+                version: "1.0.0",
+            },
+            servers: servers.iter().map(|url| self.server(url)).collect(),
+            paths,
+            components: (!security_schemes.is_empty())
+                .then_some(Components { security_schemes }),
+        };
+        (document, warnings)
+    }
+
+    /// Describe a server URL. Each placeholder in the URL is a variable, with
+    /// the values defined for that field across all profiles
+    fn server(&self, url: &str) -> Server {
+        let variables = placeholder_names(url)
+            .map(|name| {
+                let values: Vec<String> = self
+                    .profiles
+                    .values()
+                    .filter_map(|profile| profile.data.get(name))
+                    .map(Template::to_string)
+                    .unique()
+                    .collect();
+                // A default is required, even if no profile defines the field
+                let variable = ServerVariable {
+                    default: values.first().cloned().unwrap_or_default(),
+                    values: if values.len() > 1 { values } else { Vec::new() },
+                };
+                (name.to_owned(), variable)
+            })
+            .collect();
+        Server {
+            url: url.to_owned(),
+            variables,
+        }
+    }
+}
+
+/// A recipe URL, split into the parts that OpenAPI describes separately
+#[derive(Debug, PartialEq)]
+struct OpenApiUrl {
+    /// Scheme and host, if the URL isn't relative to the base URL
+    server: Option<String>,
+    /// Path, with placeholders for path parameters. Always starts with `/`
+    path: String,
+    /// Query parameters that are embedded in the URL
+    query: Vec<(String, String)>,
+}
+
+/// Describe a single recipe as an OpenAPI operation
+fn operation(recipe: &Recipe, url: &OpenApiUrl) -> Operation {
+    let mut parameters: Vec<Parameter> = placeholder_names(&url.path)
+        .map(|name| parameter(name, "path", None))
+        .collect();
+    parameters.extend(url.query.iter().map(|(name, value)| {
+        // Values with placeholders make poor examples
+        let example = (!value.contains('{')).then(|| value.clone());
+        parameter(name, "query", example)
+    }));
+    parameters.extend(
+        recipe
+            .query
+            .iter()
+            .map(|(name, value)| parameter(name, "query", literal(value))),
+    );
+    parameters.extend(
+        recipe
+            .headers
+            .iter()
+            .filter(|(name, _)| {
+                !RESERVED_HEADERS.contains(&name.to_lowercase().as_str())
+            })
+            .map(|(name, value)| parameter(name, "header", literal(value))),
+    );
+
+    Operation {
+        operation_id: recipe.id.to_string(),
+        summary: recipe.name.clone(),
+        tags: recipe.tags.clone(),
+        parameters,
+        request_body: recipe
+            .body
+            .as_ref()
+            .map(|body| request_body(recipe, body)),
+        security: Vec::new(),
+        responses: IndexMap::from([(
+            "default",
+            Response {
+                description: "Response",
+            },
+        )]),
+    }
+}
+
+/// Describe a path, query, or header parameter
+fn parameter(
+    name: &str,
+    location: &'static str,
+    example: Option<String>,
+) -> Parameter {
+    Parameter {
+        name: name.to_owned(),
+        location,
+        required: location == "path",
+        schema: json!({"type": "string"}),
+        example,
+    }
+}
+
+/// Describe a request body, with the recipe's body as an example
+fn request_body(recipe: &Recipe, body: &RecipeBody) -> RequestBody {
+    let form = |fields: &IndexMap<String, Template>| {
+        let properties: IndexMap<&str, Value> = fields
+            .keys()
+            .map(|field| (field.as_str(), json!({"type": "string"})))
+            .collect();
+        let example: IndexMap<&str, String> = fields
+            .iter()
+            .map(|(field, value)| (field.as_str(), value.to_string()))
+            .collect();
+        json!({
+            "schema": {"type": "object", "properties": properties},
+            "example": example,
+        })
+    };
+    let (content_type, media_type) = match body {
+        RecipeBody::Raw(body) => {
+            let content_type = recipe
+                .headers
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+                .and_then(|(_, value)| literal(value))
+                .unwrap_or_else(|| "text/plain".to_owned());
+            (content_type, json!({"example": body.to_string()}))
+        }
+        RecipeBody::Json(body) => {
+            let example: Value = body.map_ref(|value| value.to_string()).into();
+            ("application/json".to_owned(), json!({"example": example}))
+        }
+        RecipeBody::FormUrlencoded(fields) => {
+            ("application/x-www-form-urlencoded".to_owned(), form(fields))
+        }
+        RecipeBody::FormMultipart(fields) => {
+            ("multipart/form-data".to_owned(), form(fields))
+        }
+    };
+    RequestBody {
+        content: IndexMap::from([(content_type, media_type)]),
+    }
+}
+
+/// Get the name and definition of the security scheme for an authentication
+/// method. Returns `None` for methods that OpenAPI can't describe
+fn security_scheme(authentication: &Authentication) -> Option<(String, Value)> {
+    match authentication {
+        Authentication::Basic { .. } => Some((
+            "basicAuth".to_owned(),
+            json!({"type": "http", "scheme": "basic"}),
+        )),
+        Authentication::Bearer(_) => Some((
+            "bearerAuth".to_owned(),
+            json!({"type": "http", "scheme": "bearer"}),
+        )),
+        Authentication::ApiKey { name, location, .. } => {
+            let location = match location {
+                ApiKeyLocation::Header => "header",
+                ApiKeyLocation::Query => "query",
+            };
+            Some((
+                format!("apiKey_{name}"),
+                json!({"type": "apiKey", "name": name, "in": location}),
+            ))
+        }
+        Authentication::Command { .. } => None,
+    }
+}
+
+/// Convert a template's keys to OpenAPI placeholders
+fn placeholders(template: &Template) -> String {
+    template.replace_keys(|key| format!("{{{key}}}"))
+}
+
+/// Get a template's value if it has no keys, to use as an example
+fn literal(template: &Template) -> Option<String> {
+    (!template.is_dynamic()).then(|| placeholders(template))
+}
+
+/// Get the name of each `{placeholder}` in a string
+fn placeholder_names(s: &str) -> impl Iterator<Item = &str> {
+    s.split('{')
+        .skip(1)
+        .filter_map(|part| part.split_once('}'))
+        .map(|(name, _)| name)
+}
+
+/// Split a URL, with keys already converted to placeholders, into server,
+/// path, and query. If the URL starts with a placeholder (e.g.
+/// `{host}/users`), the placeholder is the server.
+fn split_url(url: &str) -> OpenApiUrl {
+    let url = url.split_once('#').map_or(url, |(url, _)| url);
+    let (url, query) = url.split_once('?').unwrap_or((url, ""));
+    let query = query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (name.to_owned(), value.to_owned())
+        })
+        .collect();
+
+    // Find where the path starts. For absolute URLs, skip the scheme
+    let server_end = if let Some((scheme, rest)) = url.split_once("://") {
+        rest.find('/').map_or(url.len(), |i| scheme.len() + 3 + i)
+    } else if url.starts_with('{') {
+        url.find('/').unwrap_or(url.len())
+    } else {
+        0
+    };
+    let (server, path) = url.split_at(server_end);
+    let path = if path.starts_with('/') {
+        path.to_owned()
+    } else {
+        format!("/{path}")
+    };
+    OpenApiUrl {
+        server: (!server.is_empty()).then(|| server.to_owned()),
+        path,
+        query,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        collection::{JsonBody, Method, Profile, RecipeNode},
+        test_util::{by_id, Factory},
+    };
+    use indexmap::indexmap;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::placeholder(
+        "{host}/users/{id}",
+        Some("{host}"),
+        "/users/{id}",
+        &[]
+    )]
+    #[case::absolute(
+        "https://api.example.com/users?page=2&all",
+        Some("https://api.example.com"),
+        "/users",
+        &[("page", "2"), ("all", "")]
+    )]
+    #[case::host_only(
+        "https://api.example.com",
+        Some("https://api.example.com"),
+        "/",
+        &[]
+    )]
+    #[case::relative("users/{id}#top", None, "/users/{id}", &[])]
+    fn test_split_url(
+        #[case] url: &str,
+        #[case] server: Option<&str>,
+        #[case] path: &str,
+        #[case] query: &[(&str, &str)],
+    ) {
+        assert_eq!(
+            split_url(url),
+            OpenApiUrl {
+                server: server.map(String::from),
+                path: path.into(),
+                query: query
+                    .iter()
+                    .map(|(name, value)| (name.to_string(), value.to_string()))
+                    .collect(),
+            }
+        );
+    }
+
+    /// Test a full export, including server variables, parameters, bodies,
+    /// and recipes that can't be exported
+    #[test]
+    fn test_to_openapi() {
+        let collection = Collection {
+            profiles: by_id([
+                Profile {
+                    id: "local".into(),
+                    data: indexmap! {
+                        "host".into() => "http://localhost".into(),
+                    },
+                    ..Profile::factory(())
+                },
+                Profile {
+                    id: "prod".into(),
+                    data: indexmap! {
+                        "host".into() => "https://example.com".into(),
+                    },
+                    ..Profile::factory(())
+                },
+            ]),
+            recipes: by_id::<RecipeNode>([
+                Recipe {
+                    id: "get_user".into(),
+                    name: Some("Get User".into()),
+                    url: "{{host}}/users/{{user_id}}".into(),
+                    query: vec![
+                        ("expand".into(), "true".into()),
+                        ("token".into(), "{{chains.token}}".into()),
+                    ],
+                    headers: indexmap! {
+                        "Accept".into() => "application/json".into(),
+                        "X-Trace".into() => "1".into(),
+                    },
+                    authentication: Some(Authentication::Bearer(
+                        "{{chains.token}}".into(),
+                    )),
+                    ..Recipe::factory(())
+                }
+                .into(),
+                Recipe {
+                    id: "create_user".into(),
+                    method: Method::Post,
+                    url: "{{host}}/users".into(),
+                    tags: vec!["users".into()],
+                    body: Some(RecipeBody::Json(JsonBody::Object(indexmap! {
+                        "name".into() => JsonBody::String("{{name}}".into()),
+                        "admin".into() => JsonBody::Bool(false),
+                    }))),
+                    ..Recipe::factory(())
+                }
+                .into(),
+                Recipe {
+                    id: "get_user_again".into(),
+                    url: "{{host}}/users/{{user_id}}".into(),
+                    ..Recipe::factory(())
+                }
+                .into(),
+            ])
+            .into(),
+            ..Collection::factory(())
+        };
+
+        let (document, warnings) = collection.to_openapi("Test");
+        assert_eq!(
+            serde_json::to_value(document).unwrap(),
+            json!({
+                "openapi": "3.0.3",
+                "info": {"title": "Test", "version": "1.0.0"},
+                "servers": [{
+                    "url": "{host}",
+                    "variables": {
+                        "host": {
+                            "default": "http://localhost",
+                            "enum": ["http://localhost", "https://example.com"],
+                        },
+                    },
+                }],
+                "paths": {
+                    "/users/{user_id}": {
+                        "get": {
+                            "operationId": "get_user",
+                            "summary": "Get User",
+                            "parameters": [
+                                {
+                                    "name": "user_id",
+                                    "in": "path",
+                                    "required": true,
+                                    "schema": {"type": "string"},
+                                },
+                                {
+                                    "name": "expand",
+                                    "in": "query",
+                                    "schema": {"type": "string"},
+                                    "example": "true",
+                                },
+                                {
+                                    "name": "token",
+                                    "in": "query",
+                                    "schema": {"type": "string"},
+                                },
+                                {
+                                    "name": "X-Trace",
+                                    "in": "header",
+                                    "schema": {"type": "string"},
+                                    "example": "1",
+                                },
+                            ],
+                            "security": [{"bearerAuth": []}],
+                            "responses": {
+                                "default": {"description": "Response"},
+                            },
+                        },
+                    },
+                    "/users": {
+                        "post": {
+                            "operationId": "create_user",
+                            "tags": ["users"],
+                            "requestBody": {
+                                "content": {
+                                    "application/json": {
+                                        "example": {
+                                            "name": "{{name}}",
+                                            "admin": false,
+                                        },
+                                    },
+                                },
+                            },
+                            "responses": {
+                                "default": {"description": "Response"},
+                            },
+                        },
+                    },
+                },
+                "components": {
+                    "securitySchemes": {
+                        "bearerAuth": {"type": "http", "scheme": "bearer"},
+                    },
+                },
+            })
+        );
+        assert_eq!(
+            warnings,
+            vec![
+                "Skipping recipe `get_user_again`: GET /users/{user_id} is \
+                already defined by `get_user`"
+            ]
+        );
+    }
+}
@@ -1,10 +1,11 @@
 //! Recipe/folder tree structure
 
 use crate::collection::{
-    cereal::deserialize_id_map, Folder, HasId, Recipe, RecipeId,
+    cereal::deserialize_id_map, Folder, HasId, Recipe, RecipeDefaults, RecipeId,
 };
 use derive_more::From;
 use indexmap::{map::Values, IndexMap};
+use schemars::JsonSchema;
 use serde::{de::Error, Deserialize, Deserializer, Serialize};
 
 /// A folder/recipe tree. This is exactly what the user inputs in their
@@ -33,9 +34,10 @@ pub struct RecipeTree {
 pub struct RecipeLookupKey(Vec<RecipeId>);
 
 /// A node in the recipe tree, either a folder or recipe
-#[derive(Clone, Debug, From, Serialize, Deserialize)]
+#[derive(Clone, Debug, From, JsonSchema, Serialize, Deserialize)]
 #[cfg_attr(test, derive(PartialEq))]
 #[serde(rename_all = "snake_case", deny_unknown_fields)]
+#[schemars(untagged)]
 #[allow(clippy::large_enum_variant)]
 pub enum RecipeNode {
     Folder(Folder),
@@ -155,6 +157,28 @@ impl RecipeTree {
             path: Vec::new(),
         }
     }
+
+    /// Merge defaults into every recipe in the tree. Each folder's defaults
+    /// are layered on top of the given root defaults, so the closest folder
+    /// wins. The tree structure doesn't change, so lookup keys remain valid.
+    pub fn apply_defaults(&mut self, defaults: &RecipeDefaults) {
+        fn apply(
+            nodes: &mut IndexMap<RecipeId, RecipeNode>,
+            defaults: &RecipeDefaults,
+        ) {
+            for node in nodes.values_mut() {
+                match node {
+                    RecipeNode::Folder(folder) => {
+                        let defaults = defaults.merge(&folder.defaults);
+                        apply(&mut folder.children, &defaults);
+                    }
+                    RecipeNode::Recipe(recipe) => defaults.apply(recipe),
+                }
+            }
+        }
+
+        apply(&mut self.tree, defaults);
+    }
 }
 
 impl Serialize for RecipeTree {
@@ -234,7 +258,10 @@ impl From<&Vec<&RecipeId>> for RecipeLookupKey {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::test_util::{assert_err, by_id, Factory};
+    use crate::{
+        collection::Authentication,
+        test_util::{assert_err, by_id, Factory},
+    };
     use indexmap::indexmap;
     use itertools::Itertools;
     use rstest::{fixture, rstest};
@@ -335,6 +362,82 @@ mod tests {
         );
     }
 
+    /// Defaults are layered from the root down, and recipes always win
+    #[rstest]
+    fn test_apply_defaults() {
+        let mut tree: RecipeTree = by_id::<RecipeNode>([
+            Recipe {
+                id: id("r1"),
+                headers: indexmap! {"accept".into() => "text/plain".into()},
+                ..Recipe::factory(())
+            }
+            .into(),
+            Folder {
+                id: id("f1"),
+                defaults: RecipeDefaults {
+                    query: vec![("page".into(), "1".into())],
+                    authentication: Some(Authentication::Bearer(
+                        "folder".into(),
+                    )),
+                    ..RecipeDefaults::default()
+                },
+                children: by_id([
+                    Recipe {
+                        id: id("r2"),
+                        ..Recipe::factory(())
+                    }
+                    .into(),
+                    Recipe {
+                        id: id("r3"),
+                        query: vec![("page".into(), "2".into())],
+                        headers: indexmap! {
+                            "Authorization".into() => "custom".into(),
+                        },
+                        ..Recipe::factory(())
+                    }
+                    .into(),
+                ]),
+                ..Folder::factory(())
+            }
+            .into(),
+        ])
+        .into();
+        tree.apply_defaults(&RecipeDefaults {
+            headers: indexmap! {
+                "Accept".into() => "application/json".into(),
+                "X-Client".into() => "slumber".into(),
+            },
+            query: vec![("page".into(), "0".into())],
+            authentication: Some(Authentication::Bearer("root".into())),
+        });
+
+        let r1 = tree.get_recipe(&id("r1")).unwrap();
+        assert_eq!(
+            r1.headers,
+            indexmap! {
+                "X-Client".into() => "slumber".into(),
+                "accept".into() => "text/plain".into(),
+            }
+        );
+        assert_eq!(r1.query, vec![("page".into(), "0".into())]);
+        assert_eq!(
+            r1.authentication,
+            Some(Authentication::Bearer("root".into()))
+        );
+
+        let r2 = tree.get_recipe(&id("r2")).unwrap();
+        assert_eq!(r2.query, vec![("page".into(), "1".into())]);
+        assert_eq!(
+            r2.authentication,
+            Some(Authentication::Bearer("folder".into()))
+        );
+
+        // Recipe's own Authorization header disables default authentication
+        let r3 = tree.get_recipe(&id("r3")).unwrap();
+        assert_eq!(r3.query, vec![("page".into(), "2".into())]);
+        assert_eq!(r3.authentication, None);
+    }
+
     /// Deserializing with a duplicate ID anywhere in the tree should fail
     #[rstest]
     #[case::anywhere(
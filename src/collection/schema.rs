@@ -0,0 +1,216 @@
+//! JSON Schema generation for the collection format. Most of the schema is
+//! derived from the model types, but types with custom (de)serialization need
+//! hand-written impls, which live here.
+//!
+//! YAML tags (e.g. `!request`) have no equivalent in JSON Schema. Editors such
+//! as the YAML language server strip custom tags before validating, so tagged
+//! enums are described as untagged: each variant is just the schema of its
+//! content.
+
+use crate::{
+    collection::{
+        Collection, JsonBody, Method, RecipeBody, RecipeId, RecipeNode,
+        RecipeTree,
+    },
+    http::{Pattern, ProxyUrl, Query},
+    template::Template,
+};
+use indexmap::IndexMap;
+use schemars::{
+    gen::{SchemaGenerator, SchemaSettings},
+    schema::{InstanceType, RootSchema, Schema, SchemaObject},
+    JsonSchema,
+};
+use strum::IntoEnumIterator;
+
+/// Generate a JSON Schema for the collection file format
+pub fn collection_schema() -> RootSchema {
+    SchemaSettings::draft07()
+        .into_generator()
+        .into_root_schema_for::<Collection>()
+}
+
+/// Build a schema that accepts any of the given instance types
+fn instance_types(types: Vec<InstanceType>) -> SchemaObject {
+    SchemaObject {
+        instance_type: Some(types.into()),
+        ..Default::default()
+    }
+}
+
+/// Attach a description to a schema
+fn describe(mut schema: SchemaObject, description: &str) -> Schema {
+    schema.metadata().description = Some(description.to_owned());
+    schema.into()
+}
+
+/// Schema for query parameters, which can be given as a map of
+/// `param: value` or a list of `"param=value"` strings
+pub fn query_parameters(gen: &mut SchemaGenerator) -> Schema {
+    let mut schema = SchemaObject::default();
+    schema.subschemas().any_of = Some(vec![
+        gen.subschema_for::<IndexMap<String, Template>>(),
+        gen.subschema_for::<Vec<String>>(),
+    ]);
+    describe(
+        schema,
+        "Map of `param: value`, or list of `\"param=value\"` to allow \
+        repeated parameters",
+    )
+}
+
+/// Schema for a unit variant of a tagged enum. The variant can be written as a
+/// bare tag (e.g. `!headers`), which has no content, or as a plain string
+/// (e.g. `headers`)
+pub fn unit_variant(_: &mut SchemaGenerator) -> Schema {
+    instance_types(vec![InstanceType::Null, InstanceType::String]).into()
+}
+
+impl JsonSchema for Template {
+    fn is_referenceable() -> bool {
+        false
+    }
+
+    fn schema_name() -> String {
+        "Template".to_owned()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        describe(
+            instance_types(vec![
+                InstanceType::String,
+                InstanceType::Number,
+                InstanceType::Boolean,
+            ]),
+            "Template string. `{{...}}` keys are replaced when the template \
+            is rendered",
+        )
+    }
+}
+
+impl JsonSchema for Method {
+    fn is_referenceable() -> bool {
+        false
+    }
+
+    fn schema_name() -> String {
+        "Method".to_owned()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        // Methods are parsed case-insensitively, but mixed case is silly
+        let values: Vec<serde_json::Value> = Method::iter()
+            .flat_map(|method| {
+                let method = method.to_string();
+                [method.to_lowercase(), method]
+            })
+            .map(serde_json::Value::String)
+            .collect();
+        SchemaObject {
+            enum_values: Some(values),
+            ..Default::default()
+        }
+        .into()
+    }
+}
+
+impl JsonSchema for RecipeTree {
+    fn is_referenceable() -> bool {
+        false
+    }
+
+    fn schema_name() -> String {
+        "RecipeTree".to_owned()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        <IndexMap<RecipeId, RecipeNode>>::json_schema(gen)
+    }
+}
+
+impl JsonSchema for RecipeBody {
+    fn schema_name() -> String {
+        "RecipeBody".to_owned()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        // The raw variant is a plain template. Structured variants are tagged:
+        // !json takes any JSON value, and the form variants take a map of
+        // fields. The JSON schema covers all of those, so we can lean on it
+        gen.subschema_for::<JsonBody>()
+    }
+}
+
+/// Implement [JsonSchema] for types that are deserialized from a string
+macro_rules! string_schema {
+    ($type:ty, $description:expr) => {
+        impl JsonSchema for $type {
+            fn is_referenceable() -> bool {
+                false
+            }
+
+            fn schema_name() -> String {
+                stringify!($type).to_owned()
+            }
+
+            fn json_schema(_: &mut SchemaGenerator) -> Schema {
+                describe(
+                    instance_types(vec![InstanceType::String]),
+                    $description,
+                )
+            }
+        }
+    };
+}
+
+string_schema!(Query, "JSONPath query, e.g. `$.data[0].id`");
+string_schema!(Pattern, "Regular expression");
+string_schema!(
+    ProxyUrl,
+    "Proxy URL, with a scheme of `http`, `https`, `socks5`, or `socks5h`"
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Look up a definition in the generated schema, as JSON
+    fn definition(schema: &RootSchema, name: &str) -> serde_json::Value {
+        let definition = schema
+            .definitions
+            .get(name)
+            .unwrap_or_else(|| panic!("Missing definition `{name}`"));
+        serde_json::to_value(definition).unwrap()
+    }
+
+    /// Test that the generated schema matches the serde representation of
+    /// some of the trickier types
+    #[test]
+    fn test_collection_schema() {
+        let schema = collection_schema();
+        let root = serde_json::to_value(&schema.schema).unwrap();
+        assert_eq!(root["additionalProperties"], json!(false));
+        assert!(root["properties"].get(".ignore").is_some());
+        assert!(root["properties"]["requests"].is_object());
+
+        // IDs are populated from map keys, so they aren't fields
+        let recipe = definition(&schema, "Recipe");
+        assert_eq!(recipe["required"], json!(["method", "url"]));
+        assert!(recipe["properties"].get("id").is_none());
+        assert_eq!(recipe["properties"]["method"]["enum"][1], json!("CONNECT"));
+
+        // Tagged enums are described by their content
+        let source = definition(&schema, "ChainSource");
+        let variants = source["anyOf"].as_array().unwrap();
+        assert_eq!(
+            variants[0]["required"],
+            json!(["command"]),
+            "Expected command variant, got {}",
+            variants[0]
+        );
+        assert!(variants
+            .iter()
+            .any(|variant| variant["type"] == json!(["null", "string"])));
+    }
+}
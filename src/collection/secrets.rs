@@ -0,0 +1,414 @@
+//! Encrypted profile fields. Values for fields listed in a profile's
+//! `encrypted` list are stored in a local secrets file in the data directory,
+//! rather than in the collection, so the collection can be shared without
+//! exposing them. Values are encrypted with a key derived from a passphrase,
+//! which is found via [passphrase] whenever the collection is loaded.
+
+use crate::{
+    collection::{Collection, ProfileId},
+    template::Template,
+    util::{keyring_service, parse_yaml, paths::DataDirectory},
+};
+use anyhow::{anyhow, bail, Context};
+use argon2::Argon2;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chacha20poly1305::{
+    aead::{rand_core::RngCore, Aead, AeadCore, KeyInit, OsRng, Payload},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use dialoguer::Password;
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    env, fs,
+    io::{self, IsTerminal},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+use tracing::{info, warn};
+
+/// Environment variable to read the passphrase from
+pub const PASSPHRASE_VARIABLE: &str = "SLUMBER_SECRETS_PASSPHRASE";
+/// Key the passphrase is stored under in the collection's keyring service
+pub const PASSPHRASE_KEYRING_KEY: &str = "secrets_passphrase";
+/// Shown in place of decrypted values in the TUI
+pub const ENCRYPTED_MASK: &str = "<encrypted>";
+
+const SALT_LENGTH: usize = 16;
+const NONCE_LENGTH: usize = 12;
+
+/// Passphrases that have been entered this session, keyed by collection path,
+/// so the user only has to enter it once
+static PASSPHRASES: Mutex<Option<HashMap<PathBuf, String>>> = Mutex::new(None);
+
+/// Contents of a secrets file. Each value is base64 of the nonce followed by
+/// the ciphertext.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SecretsFile {
+    /// Salt for deriving the key from the passphrase, base64-encoded
+    salt: String,
+    #[serde(default)]
+    values: IndexMap<ProfileId, IndexMap<String, String>>,
+}
+
+impl SecretsFile {
+    /// Path to the secrets file for a collection. The collection path should
+    /// be canonicalized, so the path is stable regardless of working directory
+    pub fn path(collection_path: &Path) -> anyhow::Result<PathBuf> {
+        let hash =
+            Sha256::digest(collection_path.as_os_str().as_encoded_bytes());
+        DataDirectory::root()
+            .file(format!("secrets/{hash:x}.yml"))
+            .create_parent()
+    }
+
+    /// Load the secrets file for a collection. Return `None` if it doesn't
+    /// exist.
+    pub fn load(collection_path: &Path) -> anyhow::Result<Option<Self>> {
+        let path = Self::path(collection_path)?;
+        match fs::read(&path) {
+            Ok(bytes) => parse_yaml(&bytes).map(Some).with_context(|| {
+                format!("Error loading secrets from {path:?}")
+            }),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(anyhow::Error::from(error)
+                .context(format!("Error reading secrets file {path:?}"))),
+        }
+    }
+
+    /// Create a new empty file with a random salt. Nothing is written to
+    /// disk until [Self::save] is called.
+    pub fn create() -> Self {
+        let mut salt = [0; SALT_LENGTH];
+        OsRng.fill_bytes(&mut salt);
+        Self {
+            salt: STANDARD.encode(salt),
+            values: IndexMap::new(),
+        }
+    }
+
+    pub fn save(&self, collection_path: &Path) -> anyhow::Result<()> {
+        let path = Self::path(collection_path)?;
+        let contents = serde_yaml::to_string(self)?;
+        fs::write(&path, contents)
+            .with_context(|| format!("Error writing secrets file {path:?}"))
+    }
+
+    /// Is there at least one value to decrypt?
+    pub fn is_empty(&self) -> bool {
+        self.values.values().all(IndexMap::is_empty)
+    }
+
+    /// Encrypt a value and store it for a profile field, replacing any
+    /// existing value
+    pub fn set(
+        &mut self,
+        passphrase: &str,
+        profile_id: &ProfileId,
+        field: &str,
+        value: &str,
+    ) -> anyhow::Result<()> {
+        let cipher = self.cipher(passphrase)?;
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let aad = aad(profile_id, field);
+        let ciphertext = cipher
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: value.as_bytes(),
+                    aad: aad.as_bytes(),
+                },
+            )
+            .map_err(|_| anyhow!("Error encrypting `{field}`"))?;
+        let mut bytes = nonce.to_vec();
+        bytes.extend(ciphertext);
+        self.values
+            .entry(profile_id.clone())
+            .or_default()
+            .insert(field.to_owned(), STANDARD.encode(bytes));
+        Ok(())
+    }
+
+    /// Is there a stored value for a profile field?
+    pub fn contains(&self, profile_id: &ProfileId, field: &str) -> bool {
+        self.values
+            .get(profile_id)
+            .is_some_and(|fields| fields.contains_key(field))
+    }
+
+    /// Remove the stored value for a profile field, if any
+    pub fn remove(&mut self, profile_id: &ProfileId, field: &str) {
+        if let Some(fields) = self.values.get_mut(profile_id) {
+            fields.shift_remove(field);
+            if fields.is_empty() {
+                self.values.shift_remove(profile_id);
+            }
+        }
+    }
+
+    /// Decrypt the value for a profile field. Return `None` if there's no
+    /// stored value.
+    fn get(
+        &self,
+        cipher: &ChaCha20Poly1305,
+        profile_id: &ProfileId,
+        field: &str,
+    ) -> anyhow::Result<Option<String>> {
+        let Some(encoded) = self
+            .values
+            .get(profile_id)
+            .and_then(|fields| fields.get(field))
+        else {
+            return Ok(None);
+        };
+        let bytes = STANDARD.decode(encoded).with_context(|| {
+            format!("Stored value for `{profile_id}.{field}` is invalid")
+        })?;
+        if bytes.len() < NONCE_LENGTH {
+            bail!("Stored value for `{profile_id}.{field}` is invalid");
+        }
+        let (nonce, ciphertext) = bytes.split_at(NONCE_LENGTH);
+        let aad = aad(profile_id, field);
+        let plaintext = cipher
+            .decrypt(
+                Nonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: aad.as_bytes(),
+                },
+            )
+            // The only likely cause is a wrong passphrase
+            .map_err(|_| {
+                anyhow!(
+                    "Error decrypting `{profile_id}.{field}`; \
+                    is the passphrase correct?"
+                )
+            })?;
+        let value = String::from_utf8(plaintext).with_context(|| {
+            format!("Value for `{profile_id}.{field}` is not valid UTF-8")
+        })?;
+        Ok(Some(value))
+    }
+
+    /// Derive the encryption key from the passphrase and this file's salt
+    fn cipher(&self, passphrase: &str) -> anyhow::Result<ChaCha20Poly1305> {
+        let salt = STANDARD
+            .decode(&self.salt)
+            .context("Secrets file salt is invalid")?;
+        let mut key = Key::default();
+        Argon2::default()
+            .hash_password_into(passphrase.as_bytes(), &salt, &mut key)
+            .map_err(|error| anyhow!("Error deriving key: {error}"))?;
+        Ok(ChaCha20Poly1305::new(&key))
+    }
+
+    /// Make sure the passphrase can decrypt every stored value
+    pub fn verify(&self, passphrase: &str) -> anyhow::Result<()> {
+        let cipher = self.cipher(passphrase)?;
+        for (profile_id, fields) in &self.values {
+            for field in fields.keys() {
+                self.get(&cipher, profile_id, field)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Associated data for a value, so ciphertexts can't be moved between fields
+fn aad(profile_id: &ProfileId, field: &str) -> String {
+    format!("{profile_id}.{field}")
+}
+
+/// Decrypt the values of every encrypted profile field in the collection, and
+/// insert them into the profile's data. Encrypted values take precedence over
+/// any value in `data`. If there's no stored value for a field, it's left
+/// alone.
+pub fn decrypt_profiles(
+    collection_path: &Path,
+    collection: &mut Collection,
+) -> anyhow::Result<()> {
+    if collection
+        .profiles
+        .values()
+        .all(|profile| profile.encrypted.is_empty())
+    {
+        return Ok(());
+    }
+
+    let collection_path = collection_path.canonicalize()?;
+    let Some(file) = SecretsFile::load(&collection_path)? else {
+        warn!(
+            ?collection_path,
+            "Collection has encrypted fields, but no secrets file"
+        );
+        return Ok(());
+    };
+    if file.is_empty() {
+        return Ok(());
+    }
+    let passphrase = passphrase(&collection_path)?.ok_or_else(|| {
+        anyhow!(
+            "Encrypted profile fields are locked. Set `{PASSPHRASE_VARIABLE}`, \
+            or store the passphrase with \
+            `slumber secret set {PASSPHRASE_KEYRING_KEY}`"
+        )
+    })?;
+    let cipher = file.cipher(&passphrase)?;
+
+    for profile in collection.profiles.values_mut() {
+        for field in &profile.encrypted {
+            match file.get(&cipher, &profile.id, field)? {
+                Some(value) => {
+                    profile.data.insert(field.clone(), Template::raw(value));
+                }
+                None => warn!(
+                    profile_id = %profile.id,
+                    field,
+                    "No stored value for encrypted field"
+                ),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Find the passphrase for a collection's secrets, without prompting. Checked
+/// in order: passphrases entered this session, the environment variable, and
+/// the OS keyring. The collection path should be canonicalized.
+pub fn passphrase(collection_path: &Path) -> anyhow::Result<Option<String>> {
+    if let Some(passphrase) = PASSPHRASES
+        .lock()
+        .expect("Passphrase lock poisoned")
+        .as_ref()
+        .and_then(|passphrases| passphrases.get(collection_path))
+    {
+        return Ok(Some(passphrase.clone()));
+    }
+    if let Ok(passphrase) = env::var(PASSPHRASE_VARIABLE) {
+        return Ok(Some(passphrase));
+    }
+    let service = keyring_service(collection_path);
+    match keyring::Entry::new(&service, PASSPHRASE_KEYRING_KEY)
+        .and_then(|entry| entry.get_password())
+    {
+        Ok(passphrase) => Ok(Some(passphrase)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(error) => Err(anyhow::Error::from(error).context(format!(
+            "Error loading secrets passphrase from keyring service `{service}`"
+        ))),
+    }
+}
+
+/// Unlock the collection's encrypted fields at startup. If the collection has
+/// stored secrets and the passphrase can't be found elsewhere, prompt for it
+/// and remember it for the rest of the session. This must be called before
+/// the TUI takes over the terminal. If the terminal isn't interactive, do
+/// nothing; loading the collection will fail with a helpful error instead.
+pub fn unlock(collection_path: &Path) -> anyhow::Result<()> {
+    let collection_path = collection_path.canonicalize()?;
+    let Some(file) = SecretsFile::load(&collection_path)? else {
+        return Ok(());
+    };
+    if file.is_empty()
+        || passphrase(&collection_path)?.is_some()
+        || !io::stdin().is_terminal()
+    {
+        return Ok(());
+    }
+
+    let passphrase = Password::new()
+        .with_prompt("Passphrase for encrypted profile fields")
+        .interact()
+        .context("Error reading passphrase")?;
+    file.verify(&passphrase)?;
+    info!(?collection_path, "Unlocked encrypted profile fields");
+    remember(collection_path, passphrase);
+    Ok(())
+}
+
+/// Store a passphrase for the rest of the session
+pub fn remember(collection_path: PathBuf, passphrase: String) {
+    PASSPHRASES
+        .lock()
+        .expect("Passphrase lock poisoned")
+        .get_or_insert_with(HashMap::new)
+        .insert(collection_path, passphrase);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        collection::Profile,
+        test_util::{assert_err, by_id, Factory},
+    };
+    use indexmap::indexmap;
+
+    const PASSPHRASE: &str = "hunter2";
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let profile_id: ProfileId = "profile1".into();
+        let mut file = SecretsFile::create();
+        file.set(PASSPHRASE, &profile_id, "token", "secret!")
+            .unwrap();
+        // Value isn't stored in plaintext
+        assert!(!serde_yaml::to_string(&file).unwrap().contains("secret!"));
+
+        let cipher = file.cipher(PASSPHRASE).unwrap();
+        assert_eq!(
+            file.get(&cipher, &profile_id, "token").unwrap().as_deref(),
+            Some("secret!")
+        );
+        assert_eq!(file.get(&cipher, &profile_id, "unknown").unwrap(), None);
+
+        assert!(file.contains(&profile_id, "token"));
+        file.remove(&profile_id, "token");
+        assert!(!file.contains(&profile_id, "token"));
+        assert!(file.is_empty());
+    }
+
+    #[test]
+    fn test_wrong_passphrase() {
+        let profile_id: ProfileId = "profile1".into();
+        let mut file = SecretsFile::create();
+        file.set(PASSPHRASE, &profile_id, "token", "secret!")
+            .unwrap();
+        assert_err!(file.verify("wrong"), "is the passphrase correct?");
+    }
+
+    /// Ciphertexts are bound to their field, so they can't be swapped around
+    #[test]
+    fn test_moved_value() {
+        let profile_id: ProfileId = "profile1".into();
+        let mut file = SecretsFile::create();
+        file.set(PASSPHRASE, &profile_id, "token", "secret!")
+            .unwrap();
+        let value = file.values[&profile_id]["token"].clone();
+        file.values[&profile_id].insert("other".into(), value);
+
+        let cipher = file.cipher(PASSPHRASE).unwrap();
+        assert_err!(
+            file.get(&cipher, &profile_id, "other"),
+            "Error decrypting `profile1.other`"
+        );
+    }
+
+    /// Collections without encrypted fields don't touch the secrets file
+    #[test]
+    fn test_decrypt_profiles_none() {
+        let profile = Profile {
+            data: indexmap! {"host".into() => "localhost".into()},
+            ..Profile::factory(())
+        };
+        let mut collection = Collection {
+            profiles: by_id([profile]),
+            ..Collection::factory(())
+        };
+        decrypt_profiles(Path::new("/does/not/exist"), &mut collection)
+            .unwrap();
+    }
+}
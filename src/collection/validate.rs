@@ -0,0 +1,510 @@
+//! Thorough validation of collection files. Loading a collection stops at the
+//! first deserialization error, which makes fixing a broken file a slow loop.
+//! Here we check each profile, chain, and recipe independently so every
+//! problem can be reported at once.
+
+use crate::{
+    collection::{Chain, Collection, Profile, RecipeDefaults, RecipeNode},
+    template::Template,
+    util::parse_yaml,
+};
+use regex::Regex;
+use serde::de::DeserializeOwned;
+use serde_yaml::{Mapping, Value};
+use std::{
+    fmt::{self, Display},
+    sync::OnceLock,
+};
+
+/// Maximum number of unknown fields to report for a single item, before we
+/// assume something has gone very wrong and move on
+const MAX_ERRORS_PER_ITEM: usize = 20;
+
+/// A single problem in a collection file
+#[derive(Debug)]
+#[cfg_attr(test, derive(PartialEq))]
+pub struct ValidationError {
+    /// Keys leading to the offending value, e.g. `requests.login.method`.
+    /// Empty for errors in the file as a whole
+    pub path: Vec<String>,
+    /// 1-indexed line and column of the offending value, if it could be
+    /// located in the source
+    pub location: Option<(usize, usize)>,
+    pub message: String,
+    /// For an unknown field or tag, the closest valid name
+    pub suggestion: Option<String>,
+}
+
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some((line, column)) = self.location {
+            write!(f, "{line}:{column}: ")?;
+        }
+        if !self.path.is_empty() {
+            write!(f, "{}: ", self.path.join("."))?;
+        }
+        write!(f, "{}", self.message)?;
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, " (did you mean `{suggestion}`?)")?;
+        }
+        Ok(())
+    }
+}
+
+/// Check a collection file's contents, and return *all* errors that would
+/// prevent it from loading. Returns an empty list if the collection is valid.
+pub fn validate_collection(source: &str) -> Vec<ValidationError> {
+    let mut validator = Validator {
+        source,
+        errors: Vec::new(),
+    };
+    validator.validate();
+    validator.errors
+}
+
+struct Validator<'a> {
+    source: &'a str,
+    errors: Vec<ValidationError>,
+}
+
+impl<'a> Validator<'a> {
+    fn validate(&mut self) {
+        // Syntax errors prevent us from checking anything else
+        let mut value = match serde_yaml::from_str::<Value>(self.source) {
+            Ok(value) => value,
+            Err(error) => {
+                self.errors.push(ValidationError {
+                    path: Vec::new(),
+                    location: error
+                        .location()
+                        .map(|location| (location.line(), location.column())),
+                    message: error.to_string(),
+                    suggestion: None,
+                });
+                return;
+            }
+        };
+        if let Err(error) = value.apply_merge() {
+            self.push(Vec::new(), error.to_string(), None);
+            return;
+        }
+
+        match value {
+            // An empty file is a valid (empty) collection
+            Value::Null => {}
+            Value::Mapping(root) => self.validate_root(root),
+            _ => self.push(
+                Vec::new(),
+                "Collection must be a mapping".into(),
+                None,
+            ),
+        }
+
+        // Some errors, such as duplicate recipe IDs, only show up when the
+        // whole collection is loaded. Make sure we never call a collection
+        // valid if it won't actually load
+        if self.errors.is_empty() {
+            if let Err(error) = parse_yaml::<Collection>(self.source.as_bytes())
+            {
+                self.push(Vec::new(), error.to_string(), None);
+            }
+        }
+    }
+
+    fn validate_root(&mut self, root: Mapping) {
+        let mut unknown = Mapping::new();
+        for (key, value) in root {
+            let Some(key) = key.as_str() else {
+                self.push(Vec::new(), format!("Invalid key {key:?}"), None);
+                continue;
+            };
+            let path = vec![key.to_owned()];
+            match key {
+                "base_url" => self.check::<Option<Template>>(path, value),
+                "defaults" => self.check::<RecipeDefaults>(path, value),
+                "profiles" => self.check_map::<Profile>(path, value),
+                "chains" => self.check_map::<Chain>(path, value),
+                "requests" => self.check_nodes(path, value),
+                ".ignore" => {}
+                _ => {
+                    unknown.insert(key.into(), value);
+                }
+            }
+        }
+
+        // Let serde tell us what's wrong with the unknown fields, so we get
+        // the list of expected fields for suggestions
+        if !unknown.is_empty() {
+            self.check::<Collection>(Vec::new(), Value::Mapping(unknown));
+        }
+    }
+
+    /// Check each value in a mapping of ID:item
+    fn check_map<T: DeserializeOwned>(
+        &mut self,
+        path: Vec<String>,
+        value: Value,
+    ) {
+        match value {
+            Value::Null => {}
+            Value::Mapping(mapping) => {
+                for (key, value) in mapping {
+                    let path = with_key(&path, &key);
+                    self.check::<T>(path, value);
+                }
+            }
+            _ => self.push(path, "Expected a mapping".into(), None),
+        }
+    }
+
+    /// Check each node in a recipe tree, recursing into folders
+    fn check_nodes(&mut self, path: Vec<String>, value: Value) {
+        let Value::Mapping(mapping) = value else {
+            return self.check_map::<RecipeNode>(path, value);
+        };
+        for (key, mut value) in mapping {
+            let path = with_key(&path, &key);
+            // Check the folder's own fields separately from its children
+            let children = match &mut value {
+                Value::Tagged(tagged) if tagged.tag == "folder" => tagged
+                    .value
+                    .as_mapping_mut()
+                    .and_then(|folder| folder.remove("requests")),
+                _ => None,
+            };
+            self.check::<RecipeNode>(path.clone(), value);
+            if let Some(children) = children {
+                self.check_nodes(with_key(&path, &"requests".into()), children);
+            }
+        }
+    }
+
+    /// Deserialize a single item and record any errors. Unknown fields are
+    /// removed and the item is checked again, so that we can find other
+    /// errors in the same item.
+    fn check<T: DeserializeOwned>(
+        &mut self,
+        path: Vec<String>,
+        mut value: Value,
+    ) {
+        for _ in 0..MAX_ERRORS_PER_ITEM {
+            let Err(error) = serde_yaml::from_value::<T>(value.clone()) else {
+                return;
+            };
+            let message = error.to_string();
+            let Some(unknown) = UnknownName::parse(&message) else {
+                self.push(path, message, None);
+                return;
+            };
+
+            let suggestion = unknown.suggestion();
+            if unknown.is_field {
+                // Find the field, so we can report where it is and remove it
+                let mut field_path = path.clone();
+                if remove_key(&mut value, &unknown.name, &mut field_path) {
+                    self.push(
+                        field_path,
+                        format!("Unknown field `{}`", unknown.name),
+                        suggestion,
+                    );
+                    continue;
+                }
+            }
+            // We can't remove the offending value, so give up on this item
+            self.push(path, message, suggestion);
+            return;
+        }
+    }
+
+    fn push(
+        &mut self,
+        path: Vec<String>,
+        message: String,
+        suggestion: Option<String>,
+    ) {
+        let location = locate(self.source, &path);
+        self.errors.push(ValidationError {
+            path,
+            location,
+            message,
+            suggestion,
+        });
+    }
+}
+
+/// An unknown field or enum variant (i.e. tag), parsed from a serde error
+struct UnknownName {
+    is_field: bool,
+    name: String,
+    expected: Vec<String>,
+}
+
+impl UnknownName {
+    /// Parse serde's error message for an unknown field/variant. This is
+    /// brittle, but serde doesn't expose the information any other way.
+    fn parse(message: &str) -> Option<Self> {
+        static UNKNOWN: OnceLock<Regex> = OnceLock::new();
+        static QUOTED: OnceLock<Regex> = OnceLock::new();
+        let unknown = UNKNOWN.get_or_init(|| {
+            Regex::new(r"unknown (field|variant) `([^`]*)`, (.*)").unwrap()
+        });
+        let quoted = QUOTED.get_or_init(|| Regex::new(r"`([^`]*)`").unwrap());
+
+        let captures = unknown.captures(message)?;
+        Some(Self {
+            is_field: &captures[1] == "field",
+            name: captures[2].to_owned(),
+            expected: quoted
+                .captures_iter(&captures[3])
+                .map(|captures| captures[1].to_owned())
+                .collect(),
+        })
+    }
+
+    /// Get the expected name most similar to the unknown one, if any are
+    /// close enough to be a plausible typo
+    fn suggestion(&self) -> Option<String> {
+        self.expected
+            .iter()
+            .map(|expected| {
+                (strsim::levenshtein(&self.name, expected), expected)
+            })
+            .filter(|(distance, expected)| {
+                *distance <= 3 && *distance < expected.len().max(2)
+            })
+            .min_by_key(|(distance, _)| *distance)
+            .map(|(_, expected)| expected.clone())
+    }
+}
+
+/// Append a mapping key to a path
+fn with_key(path: &[String], key: &Value) -> Vec<String> {
+    let key = match key {
+        Value::String(key) => key.clone(),
+        other => serde_yaml::to_string(other)
+            .unwrap_or_default()
+            .trim()
+            .to_owned(),
+    };
+    let mut path = path.to_owned();
+    path.push(key);
+    path
+}
+
+/// Remove the first occurrence of a mapping key anywhere in a value, and
+/// append the keys leading to it to the path. Return whether it was found
+fn remove_key(value: &mut Value, key: &str, path: &mut Vec<String>) -> bool {
+    match value {
+        Value::Mapping(mapping) => {
+            if mapping.remove(key).is_some() {
+                path.push(key.to_owned());
+                return true;
+            }
+            for (child_key, child) in mapping.iter_mut() {
+                let Some(child_key) = child_key.as_str() else {
+                    continue;
+                };
+                path.push(child_key.to_owned());
+                if remove_key(child, key, path) {
+                    return true;
+                }
+                path.pop();
+            }
+            false
+        }
+        Value::Sequence(sequence) => sequence
+            .iter_mut()
+            .any(|child| remove_key(child, key, path)),
+        Value::Tagged(tagged) => remove_key(&mut tagged.value, key, path),
+        _ => false,
+    }
+}
+
+/// Find the line and column of the key at the end of a path. This only
+/// understands block-style mappings, which is what nearly every collection
+/// uses. Anything fancier just doesn't get a location.
+fn locate(source: &str, path: &[String]) -> Option<(usize, usize)> {
+    let mut depth = 0;
+    let mut parent_indent: Option<usize> = None;
+    // Indentation of the keys in the current block. Deeper keys belong to
+    // some other child, so they're skipped
+    let mut child_indent: Option<usize> = None;
+    for (i, line) in source.lines().enumerate() {
+        // List items are indented by their dash
+        let trimmed = line.trim_start().trim_start_matches("- ");
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let indent = line.len() - trimmed.len();
+        if parent_indent.is_some_and(|parent_indent| indent <= parent_indent) {
+            // Out of the parent's block, so the key isn't there
+            return None;
+        }
+        if *child_indent.get_or_insert(indent) != indent {
+            continue;
+        }
+
+        let Some((key, _)) = trimmed.split_once(':') else {
+            continue;
+        };
+        let key = key.trim().trim_matches(|c| c == '"' || c == '\'');
+        if key == path.get(depth)? {
+            if depth == path.len() - 1 {
+                return Some((i + 1, indent + 1));
+            }
+            depth += 1;
+            parent_indent = Some(indent);
+            child_indent = None;
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn error(
+        path: &[&str],
+        location: (usize, usize),
+        message: &str,
+        suggestion: Option<&str>,
+    ) -> ValidationError {
+        ValidationError {
+            path: path.iter().map(|key| key.to_string()).collect(),
+            location: Some(location),
+            message: message.into(),
+            suggestion: suggestion.map(String::from),
+        }
+    }
+
+    /// Errors throughout the file should all be reported, with locations
+    #[test]
+    fn test_validate_collection() {
+        let source = r#"
+profiles:
+  dev:
+    nmae: Dev
+    data:
+      host: http://localhost
+chains:
+  token:
+    source: !file
+      path: token.txt
+    selectr: $.token
+requests:
+  login: !request
+    method: POST
+    url: "{{host}}/login"
+    hedaers:
+      Accept: application/json
+  users: !folder
+    requests:
+      get_user: !reqest
+        method: GET
+        url: "{{host}}/users"
+profils: {}
+"#;
+
+        assert_eq!(
+            validate_collection(source),
+            vec![
+                error(
+                    &["profiles", "dev", "nmae"],
+                    (4, 5),
+                    "Unknown field `nmae`",
+                    Some("name"),
+                ),
+                error(
+                    &["chains", "token", "selectr"],
+                    (11, 5),
+                    "Unknown field `selectr`",
+                    Some("selector"),
+                ),
+                error(
+                    &["requests", "login", "hedaers"],
+                    (16, 5),
+                    "Unknown field `hedaers`",
+                    Some("headers"),
+                ),
+                error(
+                    &["requests", "users", "requests", "get_user"],
+                    (20, 7),
+                    "unknown variant `reqest`, expected `folder` or `request`",
+                    Some("request"),
+                ),
+                error(
+                    &["profils"],
+                    (23, 1),
+                    "Unknown field `profils`",
+                    Some("profiles"),
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_collection_valid() {
+        let source = r#"
+profiles:
+  dev:
+    data:
+      host: http://localhost
+requests:
+  login: !request
+    method: POST
+    url: "{{host}}/login"
+"#;
+        assert_eq!(validate_collection(source), vec![]);
+        assert_eq!(validate_collection(""), vec![]);
+    }
+
+    /// Syntax errors are reported alone, with the parser's location
+    #[test]
+    fn test_validate_collection_syntax() {
+        let errors = validate_collection("requests: [\n");
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].location.is_some());
+    }
+
+    /// Some errors only show up when loading the whole collection
+    #[test]
+    fn test_validate_collection_duplicate_id() {
+        let source = r#"
+requests:
+  login: !request
+    method: POST
+    url: /login
+  folder: !folder
+    requests:
+      login: !request
+        method: POST
+        url: /login
+"#;
+        let errors = validate_collection(source);
+        assert_eq!(errors.len(), 1);
+        assert!(
+            errors[0]
+                .message
+                .contains("Duplicate recipe/folder ID `login`"),
+            "{}",
+            errors[0]
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        let error = error(
+            &["requests", "login", "hedaers"],
+            (16, 5),
+            "Unknown field `hedaers`",
+            Some("headers"),
+        );
+        assert_eq!(
+            error.to_string(),
+            "16:5: requests.login.hedaers: Unknown field `hedaers` \
+            (did you mean `headers`?)"
+        );
+    }
+}
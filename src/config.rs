@@ -1,54 +1,182 @@
 use crate::{
+    collection::{serde_duration, ProfileId},
+    db::HistoryRetention,
+    http::ProxyOptions,
+    template::FieldPrecedence,
     tui::{
         input::{Action, InputBinding},
         view::Theme,
     },
     util::{
-        parse_yaml,
+        file_command, get_editor_command, mime_matches, parse_yaml,
         paths::{DataDirectory, FileGuard},
         ResultExt,
     },
 };
 use anyhow::Context;
+use bytesize::ByteSize;
 use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
-use std::fs;
-use tracing::info;
+use serde_yaml::Value;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+    time::Duration,
+};
+use tracing::{info, warn};
 
 /// App-level configuration, which is global across all sessions and
-/// collections. This is *not* meant to modifiable during a session. If changes
-/// are made to the config file while a session is running, they won't be
-/// picked up until the app restarts.
+/// collections. A project can overlay its own settings on top of the global
+/// file, in `.slumber/config.yml` next to the collection file. This is *not*
+/// meant to modifiable from within a session, but the TUI reloads it when
+/// either config file changes.
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(default, deny_unknown_fields)]
 pub struct Config {
     /// TLS cert errors on these hostnames are ignored. Be careful!
     #[serde(default)]
     pub ignore_certificate_hosts: Vec<String>,
+    /// Proxy settings for all requests. Can be overridden per-profile
+    pub proxy: Option<ProxyOptions>,
     /// Should templates be rendered inline in the UI, or should we show the
     /// raw text?
     pub preview_templates: bool,
+    /// Request and response bodies larger than this are truncated in the
+    /// TUI until the user asks for the full body, to keep the UI responsive
+    pub body_preview_size: ByteSize,
     /// Overrides for default key bindings
     pub input_bindings: IndexMap<Action, InputBinding>,
+    /// Enable vim-style navigation keys (hjkl, gg/G, ctrl-d/ctrl-u)
+    pub vim_bindings: bool,
     /// Visual configuration for the TUI (e.g. colors)
     pub theme: Theme,
+    /// Limits on how much request history is stored
+    pub history: HistoryRetention,
+    /// Order in which template field sources are checked. The first source
+    /// that defines a field wins
+    pub field_precedence: FieldPrecedence,
+    /// Profile for CLI commands to use when none is given with `--profile`
+    /// or `SLUMBER_PROFILE`. Ignored for collections that don't define it
+    pub default_profile: Option<ProfileId>,
+    /// Maximum time a chain can take to fetch its value from an external
+    /// source, e.g. running a command. No limit by default
+    #[serde(with = "serde_duration::option")]
+    pub render_timeout: Option<Duration>,
+    /// Programs to open response bodies with in the TUI, by content type.
+    /// Rules are checked in order. If none match, the user's editor is used
+    pub viewers: Vec<ViewerRule>,
+    /// Background commands (e.g. `kubectl port-forward`) that need to be
+    /// running before requests with certain profiles are sent, by name
+    pub port_forwards: IndexMap<String, PortForward>,
+}
+
+/// Open response bodies with a particular command if their `Content-Type`
+/// matches
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ViewerRule {
+    /// MIME type to match, e.g. `text/html`. Either half can be `*` (e.g.
+    /// `image/*`) to match anything
+    pub content_type: String,
+    /// Program to run, with space-separated arguments. The path of the file
+    /// to open is added as the last argument
+    pub command: String,
+}
+
+/// A long-running command that makes a remote service available on a local
+/// port. It's started the first time a request is sent with one of its
+/// profiles, and stopped when Slumber exits.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PortForward {
+    /// Program to run, followed by its arguments
+    pub command: Vec<String>,
+    /// Profiles whose requests need this forward
+    pub profiles: Vec<ProfileId>,
+    /// Local port that the command listens on. The forward is ready once
+    /// this port accepts connections
+    pub port: u16,
+    /// Maximum time to wait for the port to be ready
+    #[serde(default = "PortForward::default_timeout", with = "serde_duration")]
+    pub timeout: Duration,
+}
+
+impl PortForward {
+    fn default_timeout() -> Duration {
+        Duration::from_secs(10)
+    }
 }
 
 impl Config {
     const FILE: &'static str = "config.yml";
 
-    /// Load configuration from the file, if present. If not, just return a
-    /// default value. This only returns an error if the file could be read, but
-    /// deserialization failed. This is *not* async because it's only run during
-    /// startup, when all operations are synchronous.
-    pub fn load() -> anyhow::Result<Self> {
+    /// Directory, relative to the collection file, containing project config
+    const PROJECT_DIRECTORY: &'static str = ".slumber";
+
+    /// Fields that project config is allowed to set. Project config comes
+    /// from whatever repo the user happens to open, so anything that runs
+    /// commands or changes where/how requests are sent (proxy, TLS
+    /// exceptions, viewers, port forwards) can only be set globally. History
+    /// retention is global too, so a project can't purge the user's history.
+    const PROJECT_FIELDS: &'static [&'static str] = &[
+        "preview_templates",
+        "body_preview_size",
+        "input_bindings",
+        "vim_bindings",
+        "theme",
+        "field_precedence",
+        "default_profile",
+        "render_timeout",
+    ];
+
+    /// Load configuration from the global file, then overlay the project
+    /// config for the given collection, if either is present. Missing files
+    /// are treated as empty. This only returns an error if a file could be
+    /// read, but deserialization failed. This is *not* async because it's only
+    /// run during startup, when all operations are synchronous.
+    pub fn load(collection_path: Option<&Path>) -> anyhow::Result<Self> {
         let path = Self::path().create_parent()?;
-        info!(?path, "Loading configuration file");
+        let mut value = Self::load_value(&path)?.unwrap_or_default();
+        if let Some(collection_path) = collection_path {
+            let project_path = Self::project_path(collection_path);
+            if let Some(project_value) = Self::load_value(&project_path)? {
+                let project_value =
+                    Self::restrict_project_value(project_value, &project_path);
+                info!(
+                    path = ?project_path,
+                    value = ?project_value,
+                    "Applying project configuration"
+                );
+                merge_yaml(&mut value, project_value);
+            }
+        }
+        // Each file was already checked on its own, so this should only fail
+        // if the combination is somehow invalid
+        serde_yaml::from_value(value)
+            .context("Error combining global and project configuration")
+            .traced()
+    }
 
-        match fs::read(&path) {
-            Ok(bytes) => parse_yaml::<Self>(&bytes)
-                .context(format!("Error loading configuration from {path:?}"))
-                .traced(),
+    /// Load a single config file as YAML, and check that it's valid on its
+    /// own. Return `None` if the file can't be read
+    fn load_value(path: &Path) -> anyhow::Result<Option<Value>> {
+        info!(?path, "Loading configuration file");
+        match fs::read(path) {
+            Ok(bytes) => {
+                let value = parse_yaml::<Value>(&bytes)
+                    .and_then(|value| {
+                        // Every field has a default, so a partial file is
+                        // valid on its own
+                        serde_yaml::from_value::<Self>(value.clone())?;
+                        Ok(value)
+                    })
+                    .context(format!(
+                        "Error loading configuration from {path:?}"
+                    ))
+                    .traced()?;
+                Ok(Some(value))
+            }
             // An error here is probably just the file missing, so don't make
             // a big stink about it
             Err(error) => {
@@ -57,24 +185,237 @@ impl Config {
                     error = &error as &dyn std::error::Error,
                     "Error reading configuration file"
                 );
-                Ok(Self::default())
+                Ok(None)
             }
         }
     }
 
-    /// Path to the configuration file
+    /// Remove any fields that project config isn't allowed to set (see
+    /// [Self::PROJECT_FIELDS]), with a warning for each
+    fn restrict_project_value(mut value: Value, path: &Path) -> Value {
+        if let Value::Mapping(mapping) = &mut value {
+            mapping.retain(|field, _| {
+                let allowed = field
+                    .as_str()
+                    .is_some_and(|field| Self::PROJECT_FIELDS.contains(&field));
+                if !allowed {
+                    warn!(
+                        ?path,
+                        ?field,
+                        "Ignoring field in project configuration; it can \
+                        only be set in the global configuration"
+                    );
+                }
+                allowed
+            });
+        }
+        value
+    }
+
+    /// Get a command to open a file of the given content type. Use the first
+    /// matching viewer, or the user's editor if there is none
+    pub fn viewer_command(
+        &self,
+        file: &Path,
+        content_type: Option<&str>,
+    ) -> anyhow::Result<Command> {
+        let viewer = content_type.and_then(|content_type| {
+            self.viewers
+                .iter()
+                .find(|viewer| mime_matches(&viewer.content_type, content_type))
+        });
+        match viewer {
+            Some(viewer) => Ok(file_command(&viewer.command, file)),
+            None => get_editor_command(file),
+        }
+    }
+
+    /// Path to the global configuration file
     pub fn path() -> FileGuard {
         DataDirectory::root().file(Self::FILE)
     }
+
+    /// Path to the project configuration file for a collection. The file may
+    /// not exist
+    pub fn project_path(collection_path: &Path) -> PathBuf {
+        collection_path
+            .parent()
+            .unwrap_or(Path::new(""))
+            .join(Self::PROJECT_DIRECTORY)
+            .join(Self::FILE)
+    }
+}
+
+/// Merge an overlay YAML value into a base value. Mappings are merged
+/// recursively, so the overlay only has to include the keys it changes. Any
+/// other value in the overlay replaces the base value entirely.
+fn merge_yaml(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Mapping(base), Value::Mapping(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(base_value) => merge_yaml(base_value, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
             ignore_certificate_hosts: Vec::new(),
+            proxy: None,
             preview_templates: true,
+            body_preview_size: ByteSize::mb(1),
             input_bindings: IndexMap::default(),
+            vim_bindings: false,
             theme: Theme::default(),
+            history: HistoryRetention::default(),
+            field_precedence: FieldPrecedence::default(),
+            default_profile: None,
+            render_timeout: None,
+            viewers: Vec::new(),
+            port_forwards: IndexMap::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_util::{assert_err, temp_dir, TempDir},
+        tui::test_util::EnvGuard,
+    };
+    use ratatui::style::Color;
+    use rstest::rstest;
+
+    /// Test selecting a viewer by content type, with fallback to the editor
+    #[rstest]
+    #[case::exact(Some("text/html; charset=utf-8"), "firefox")]
+    #[case::wildcard(Some("image/png"), "feh")]
+    #[case::first_wins(Some("application/json"), "jless")]
+    #[case::fallthrough(Some("application/xml"), "less")]
+    #[case::no_match(Some("text/plain"), "vim")]
+    #[case::no_content_type(None, "vim")]
+    fn test_viewer_command(
+        #[case] content_type: Option<&str>,
+        #[case] expected_program: &str,
+    ) {
+        let config = Config {
+            viewers: vec![
+                ViewerRule {
+                    content_type: "text/html".into(),
+                    command: "firefox".into(),
+                },
+                ViewerRule {
+                    content_type: "image/*".into(),
+                    command: "feh".into(),
+                },
+                ViewerRule {
+                    content_type: "application/json".into(),
+                    command: "jless".into(),
+                },
+                ViewerRule {
+                    content_type: "application/*".into(),
+                    command: "less".into(),
+                },
+            ],
+            ..Config::default()
+        };
+        let command = {
+            let _guard =
+                EnvGuard::lock([("VISUAL", Some("vim")), ("EDITOR", None)]);
+            config.viewer_command(Path::new("file"), content_type)
         }
+        .unwrap();
+        assert_eq!(command.get_program(), expected_program);
+    }
+
+    /// Project config should be merged into the global config. Mappings are
+    /// merged key-by-key, everything else is replaced
+    #[test]
+    fn test_merge_yaml() {
+        let mut global: Value = serde_yaml::from_str(
+            "
+vim_bindings: true
+render_timeout: 10s
+ignore_certificate_hosts: [localhost]
+theme:
+  primary_color: red
+  error_color: yellow
+",
+        )
+        .unwrap();
+        let project: Value = serde_yaml::from_str(
+            "
+render_timeout: 30s
+input_bindings:
+  submit: [enter]
+theme:
+  primary_color: green
+",
+        )
+        .unwrap();
+        merge_yaml(&mut global, project);
+        let config: Config = serde_yaml::from_value(global).unwrap();
+
+        assert!(config.vim_bindings);
+        assert_eq!(config.render_timeout, Some(Duration::from_secs(30)));
+        assert_eq!(config.ignore_certificate_hosts, vec!["localhost"]);
+        assert_eq!(config.input_bindings.len(), 1);
+        assert_eq!(config.theme.primary_color, Color::Green);
+        assert_eq!(config.theme.error_color, Color::Yellow);
+    }
+
+    /// Project config can't set fields that run commands or weaken security
+    #[test]
+    fn test_restrict_project_value() {
+        let project: Value = serde_yaml::from_str(
+            "
+vim_bindings: true
+proxy: http://evil.io
+ignore_certificate_hosts: [example.com]
+viewers:
+  - content_type: '*/*'
+    command: rm
+port_forwards: {}
+history:
+  max_age: 1s
+",
+        )
+        .unwrap();
+        assert_eq!(
+            Config::restrict_project_value(project, Path::new("config.yml")),
+            serde_yaml::from_str::<Value>("vim_bindings: true").unwrap()
+        );
+    }
+
+    /// Project config is loaded from `.slumber/` next to the collection, and
+    /// errors point at the offending file
+    #[rstest]
+    fn test_load_project_value(temp_dir: TempDir) {
+        let collection_path = temp_dir.join("slumber.yml");
+        let path = Config::project_path(&collection_path);
+        assert_eq!(path, temp_dir.join(".slumber").join("config.yml"));
+        assert_eq!(Config::load_value(&path).unwrap(), None);
+
+        fs::create_dir(temp_dir.join(".slumber")).unwrap();
+        fs::write(&path, "vim_bindings: true").unwrap();
+        assert_eq!(
+            Config::load_value(&path).unwrap(),
+            Some(serde_yaml::from_str("vim_bindings: true").unwrap())
+        );
+
+        fs::write(&path, "vim_bindigns: true").unwrap();
+        assert_err!(
+            Config::load_value(&path),
+            "Error loading configuration from"
+        );
     }
 }
@@ -1,15 +1,20 @@
 //! The database is responsible for persisting data, including requests and
 //! responses.
 
+mod writer;
+
 use crate::{
-    collection::{ProfileId, RecipeId},
+    collection::{serde_duration, ProfileId, RecipeId},
+    db::writer::DatabaseWriter,
     http::{Exchange, ExchangeSummary, RequestId},
     util::{
         paths::{DataDirectory, FileGuard},
         ResultExt,
     },
 };
-use anyhow::{anyhow, Context};
+use anyhow::{anyhow, bail, Context};
+use bytesize::ByteSize;
+use chrono::{DateTime, Utc};
 use derive_more::Display;
 use reqwest::StatusCode;
 use rusqlite::{
@@ -18,12 +23,15 @@ use rusqlite::{
     Connection, DatabaseName, OptionalExtension, Row, ToSql,
 };
 use rusqlite_migration::{Migrations, M};
-use serde::{de::DeserializeOwned, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::{
+    collections::HashMap,
     fmt::Debug,
+    fs,
     ops::Deref,
     path::{Path, PathBuf},
     sync::{Arc, Mutex},
+    time::Duration,
 };
 use tracing::{debug, info, trace};
 use uuid::Uuid;
@@ -32,7 +40,8 @@ use uuid::Uuid;
 /// occurs *after* opening the DB connection should be an internal bug, but
 /// should be shown to the user whenever possible. All operations are blocking,
 /// to enable calling from the view code. Do not call on every frame though,
-/// cache results in UI state for as long as they're needed.
+/// cache results in UI state for as long as they're needed. Writes can be
+/// moved off the calling thread with [Database::with_background_writes].
 ///
 /// There is only one database for an entire system. All collection share the
 /// same DB, and can modify concurrently. Generally any data that is unique
@@ -47,12 +56,14 @@ pub struct Database {
     /// one connection per thread, but the code would be a bit more
     /// complicated.
     connection: Arc<Mutex<Connection>>,
+    /// If enabled, collection writes are sent here to be executed in the
+    /// background. See [Self::with_background_writes]
+    writer: Option<Arc<DatabaseWriter>>,
 }
 
 /// A unique ID for a collection. This is generated when the collection is
 /// inserted into the DB.
-#[derive(Copy, Clone, Debug, Display)]
-#[cfg_attr(test, derive(Eq, Hash, PartialEq))]
+#[derive(Copy, Clone, Debug, Display, Eq, Hash, PartialEq)]
 pub struct CollectionId(Uuid);
 
 impl Database {
@@ -75,9 +86,32 @@ impl Database {
         Self::migrate(&mut connection)?;
         Ok(Self {
             connection: Arc::new(Mutex::new(connection)),
+            writer: None,
         })
     }
 
+    /// Execute writes from all [CollectionDatabase] handles derived from this
+    /// one on a dedicated background thread, so callers never block on them.
+    /// Errors from background writes are passed to the given callback. Call
+    /// [Self::flush] before exiting to make sure nothing is lost.
+    pub fn with_background_writes(
+        self,
+        on_error: impl 'static + Fn(anyhow::Error) + Send,
+    ) -> Self {
+        Self {
+            writer: Some(Arc::new(DatabaseWriter::spawn(on_error))),
+            ..self
+        }
+    }
+
+    /// Block until all queued background writes are done. No-op if
+    /// background writes aren't enabled.
+    pub fn flush(&self) {
+        if let Some(writer) = &self.writer {
+            writer.flush();
+        }
+    }
+
     /// Path to the database file
     pub fn path() -> FileGuard {
         DataDirectory::root().file(Self::FILE)
@@ -130,6 +164,32 @@ impl Database {
             // serialization of all binary blobs, so there's no easy way to
             // migrate it all. It's easiest just to wipe it all out.
             M::up("DELETE FROM requests; DELETE FROM ui_state;").down(""),
+            M::up(
+                // A log of every profile selection, so we can tell which
+                // profile was selected at any point in time
+                "CREATE TABLE profile_history (
+                    collection_id   UUID NOT NULL,
+                    profile_id      TEXT,
+                    time            TEXT NOT NULL,
+                    FOREIGN KEY(collection_id) REFERENCES collections(id)
+                )",
+            )
+            .down("DROP TABLE profile_history"),
+            // Rendered recipe label, duplicated out of the request blob so it
+            // can be loaded for history lists
+            M::up("ALTER TABLE requests ADD COLUMN label TEXT")
+                .down("ALTER TABLE requests DROP COLUMN label"),
+            // Body sizes, so history lists can show them without loading the
+            // blobs. Existing rows are left NULL because the sizes can't be
+            // computed in SQL
+            M::up(
+                "ALTER TABLE requests ADD COLUMN request_size INTEGER;
+                ALTER TABLE requests ADD COLUMN response_size INTEGER;",
+            )
+            .down(
+                "ALTER TABLE requests DROP COLUMN request_size;
+                ALTER TABLE requests DROP COLUMN response_size;",
+            ),
         ]);
         migrations.to_latest(connection)?;
         Ok(())
@@ -157,37 +217,14 @@ impl Database {
         source: &Path,
         target: &Path,
     ) -> anyhow::Result<()> {
-        fn get_collection_id(
-            connection: &Connection,
-            path: &Path,
-        ) -> anyhow::Result<CollectionId> {
-            // Convert to canonicalize and make serializable
-            let path: CollectionPath = path.try_into()?;
-
-            connection
-                .query_row(
-                    "SELECT id FROM collections WHERE path = :path",
-                    named_params! {":path": &path},
-                    |row| row.get::<_, CollectionId>("id"),
-                )
-                .map_err(|err| match err {
-                    rusqlite::Error::QueryReturnedNoRows => {
-                        // Use Display impl here because this will get shown in
-                        // CLI output
-                        anyhow!("Unknown collection `{path}`")
-                    }
-                    other => anyhow::Error::from(other)
-                        .context("Error fetching collection ID"),
-                })
-                .traced()
-        }
-
         info!(?source, ?target, "Merging database state");
         let connection = self.connection();
 
         // Exchange each path for an ID
-        let source = get_collection_id(&connection, source)?;
-        let target = get_collection_id(&connection, target)?;
+        let source =
+            get_collection_id(&connection, &CollectionPath::try_from(source)?)?;
+        let target =
+            get_collection_id(&connection, &CollectionPath::try_from(target)?)?;
 
         // Update each table in individually
         connection
@@ -209,6 +246,14 @@ impl Database {
             )
             .context("Error migrating table `ui_state`")
             .traced()?;
+        connection
+            .execute(
+                "UPDATE profile_history SET collection_id = :target
+                WHERE collection_id = :source",
+                named_params! {":source": source, ":target": target},
+            )
+            .context("Error migrating table `profile_history`")
+            .traced()?;
 
         connection
             .execute(
@@ -220,19 +265,16 @@ impl Database {
         Ok(())
     }
 
-    /// Convert this database connection into a handle for a single collection
-    /// file. This will store the collection in the DB if it isn't already,
-    /// then grab its generated ID to create a [CollectionDatabase].
-    pub fn into_collection(
-        self,
-        path: &Path,
-    ) -> anyhow::Result<CollectionDatabase> {
+    /// Add a collection to the database, if it isn't already there. Return
+    /// its ID either way.
+    pub fn add_collection(&self, path: &Path) -> anyhow::Result<CollectionId> {
         // Convert to canonicalize and make serializable
         let path: CollectionPath = path.try_into()?;
 
         // We have to set/get in two separate queries, because RETURNING doesn't
         // return anything if the insert didn't modify
-        self.connection()
+        let connection = self.connection();
+        connection
             .execute(
                 "INSERT INTO collections (id, path) VALUES (:id, :path)
                 ON CONFLICT(path) DO NOTHING",
@@ -243,23 +285,371 @@ impl Database {
             )
             .context("Error setting collection ID")
             .traced()?;
-        let collection_id = self
-            .connection()
-            .query_row(
-                "SELECT id FROM collections WHERE path = :path",
-                named_params! {":path": &path},
-                |row| row.get::<_, CollectionId>("id"),
+        get_collection_id(&connection, &path)
+    }
+
+    /// Delete a collection, along with all of its history and UI state. The
+    /// file doesn't need to exist anymore, in which case the path has to
+    /// match the stored path exactly.
+    pub fn remove_collection(&self, path: &Path) -> anyhow::Result<()> {
+        let path = CollectionPath::try_from(path)
+            .unwrap_or_else(|_| CollectionPath(ByteEncoded(path.to_owned())));
+        info!(%path, "Removing collection");
+
+        let connection = self.connection();
+        let collection_id = get_collection_id(&connection, &path)?;
+        let transaction = connection.unchecked_transaction()?;
+        for table in ["requests", "ui_state", "profile_history"] {
+            transaction
+                .execute(
+                    &format!(
+                        "DELETE FROM {table}
+                        WHERE collection_id = :collection_id"
+                    ),
+                    named_params! {":collection_id": collection_id},
+                )
+                .with_context(|| format!("Error deleting from `{table}`"))
+                .traced()?;
+        }
+        transaction
+            .execute(
+                "DELETE FROM collections WHERE id = :collection_id",
+                named_params! {":collection_id": collection_id},
+            )
+            .context("Error deleting collection")
+            .traced()?;
+        transaction.commit()?;
+        Ok(())
+    }
+
+    /// Get statistics on stored requests for each collection. Sizes are the
+    /// stored size of each request+response, which includes headers and
+    /// metadata, so they're larger than the body sizes shown elsewhere.
+    pub fn stats(&self) -> anyhow::Result<Vec<CollectionStats>> {
+        self.connection()
+            .prepare(
+                "SELECT collections.path, COUNT(requests.id) AS count,
+                    COALESCE(
+                        SUM(length(requests.request)
+                            + length(requests.response)),
+                        0
+                    ) AS size,
+                    MIN(requests.start_time) AS oldest
+                FROM collections
+                LEFT JOIN requests ON requests.collection_id = collections.id
+                GROUP BY collections.id
+                ORDER BY size DESC",
+            )?
+            .query_map([], |row| {
+                Ok(CollectionStats {
+                    path: row.get::<_, ByteEncoded<_>>("path")?.0,
+                    requests: row.get("count")?,
+                    size: ByteSize(row.get("size")?),
+                    oldest: row.get("oldest")?,
+                })
+            })
+            .context("Error fetching database stats")?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .context("Error extracting database stats")
+    }
+
+    /// Delete old requests from history, across all collections. First,
+    /// anything that started before `before` is deleted. Then, if the
+    /// remaining requests exceed `max_size` (by stored size), the oldest are
+    /// deleted until they fit. Return the number of deleted requests.
+    pub fn prune(
+        &self,
+        before: Option<DateTime<Utc>>,
+        max_size: Option<ByteSize>,
+    ) -> anyhow::Result<usize> {
+        info!(?before, ?max_size, "Pruning request history");
+        let connection = self.connection();
+        let mut deleted = 0;
+        if let Some(before) = before {
+            deleted += connection
+                .execute(
+                    "DELETE FROM requests WHERE start_time < :before",
+                    named_params! {":before": before},
+                )
+                .context("Error deleting old requests")
+                .traced()?;
+        }
+        if let Some(max_size) = max_size {
+            // Running total from newest to oldest; everything past the limit
+            // gets deleted
+            deleted += connection
+                .execute(
+                    "DELETE FROM requests WHERE id IN (
+                        SELECT id FROM (
+                            SELECT id, SUM(length(request) + length(response))
+                                OVER (ORDER BY start_time DESC) AS total
+                            FROM requests
+                        )
+                        WHERE total > :max_size
+                    )",
+                    named_params! {":max_size": max_size.as_u64()},
+                )
+                .context("Error deleting requests over size limit")
+                .traced()?;
+        }
+        // Deleting rows doesn't shrink the file on its own
+        if deleted > 0 {
+            connection
+                .execute("VACUUM", [])
+                .context("Error vacuuming database")
+                .traced()?;
+        }
+        Ok(deleted)
+    }
+
+    /// Write a copy of the entire database to a new SQLite file. The file can
+    /// be loaded into another database with [Self::import], e.g. to move
+    /// history to another machine.
+    pub fn export(&self, path: &Path) -> anyhow::Result<()> {
+        info!(?path, "Exporting database");
+        // VACUUM INTO refuses to overwrite a non-empty file, but the error
+        // isn't very clear
+        if path.exists() {
+            bail!("{path:?} already exists");
+        }
+        let path_str = path
+            .to_str()
+            .ok_or_else(|| anyhow!("Path {path:?} is not valid UTF-8"))?;
+        self.connection()
+            .execute("VACUUM INTO :path", named_params! {":path": path_str})
+            .context(format!("Error exporting database to {path:?}"))
+            .traced()?;
+        Ok(())
+    }
+
+    /// Merge data from a file created by [Self::export] into this database.
+    /// Collections are matched by path. Each entry in `path_map` is an
+    /// `(exported, local)` pair, to import an exported collection under a
+    /// different path. Existing requests and UI state are never overwritten,
+    /// so importing the same file twice is harmless.
+    pub fn import(
+        &self,
+        path: &Path,
+        path_map: &[(PathBuf, PathBuf)],
+    ) -> anyhow::Result<Vec<ImportedCollection>> {
+        info!(?path, ?path_map, "Importing database");
+        // The export may be from an older version, so it needs migrations.
+        // Run them on a copy so we don't modify the user's file
+        let copy_path = std::env::temp_dir()
+            .join(format!("slumber-import-{}.sqlite", Uuid::new_v4()));
+        fs::copy(path, &copy_path)
+            .context(format!("Error reading {path:?}"))
+            .traced()?;
+        let result = (|| -> anyhow::Result<_> {
+            let mut import = Connection::open(&copy_path)?;
+            Self::migrate(&mut import)
+                .context(format!("Error loading {path:?}"))?;
+            drop(import);
+            self.import_attached(&copy_path, path_map)
+        })();
+        let _ = fs::remove_file(&copy_path);
+        result
+    }
+
+    /// Attach a migrated export file and copy its data into this database
+    fn import_attached(
+        &self,
+        path: &Path,
+        path_map: &[(PathBuf, PathBuf)],
+    ) -> anyhow::Result<Vec<ImportedCollection>> {
+        let connection = self.connection();
+        let path_str = path
+            .to_str()
+            .ok_or_else(|| anyhow!("Path {path:?} is not valid UTF-8"))?;
+        connection
+            .execute(
+                "ATTACH DATABASE :path AS import",
+                named_params! {":path": path_str},
             )
-            .context("Error fetching collection ID")
+            .context("Error attaching import file")
             .traced()?;
 
+        let result = (|| -> anyhow::Result<_> {
+            // Do it all in one transaction so a failure doesn't leave us with
+            // half an import
+            let transaction = connection.unchecked_transaction()?;
+            let collections = transaction
+                .prepare("SELECT id, path FROM import.collections")?
+                .query_map([], |row| {
+                    Ok((
+                        row.get::<_, CollectionId>("id")?,
+                        row.get::<_, CollectionPath>("path")?,
+                    ))
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()
+                .context("Error fetching collections")?;
+
+            let mut imported = Vec::new();
+            for (imported_id, imported_path) in collections {
+                let imported_path: PathBuf = imported_path.into();
+                let path: CollectionPath = match path_map
+                    .iter()
+                    .find(|(exported, _)| exported == &imported_path)
+                {
+                    Some((_, local)) => local.as_path().try_into()?,
+                    None => CollectionPath(ByteEncoded(imported_path)),
+                };
+
+                transaction.execute(
+                    "INSERT OR IGNORE INTO collections (id, path)
+                    VALUES (:id, :path)",
+                    named_params! {
+                        ":id": CollectionId(Uuid::new_v4()),
+                        ":path": &path,
+                    },
+                )?;
+                let local_id = transaction.query_row(
+                    "SELECT id FROM collections WHERE path = :path",
+                    named_params! {":path": &path},
+                    |row| row.get::<_, CollectionId>("id"),
+                )?;
+                let params = named_params! {
+                    ":imported": imported_id,
+                    ":local": local_id,
+                };
+
+                let requests = transaction
+                    .execute(
+                        "INSERT OR IGNORE INTO requests (
+                            id, collection_id, profile_id, recipe_id,
+                            start_time, end_time, request, response,
+                            status_code, label, request_size, response_size
+                        )
+                        SELECT
+                            id, :local, profile_id, recipe_id, start_time,
+                            end_time, request, response, status_code, label,
+                            request_size, response_size
+                        FROM import.requests
+                        WHERE collection_id = :imported",
+                        params,
+                    )
+                    .context("Error importing table `requests`")?;
+                transaction
+                    .execute(
+                        "INSERT OR IGNORE INTO ui_state
+                            (key, collection_id, value)
+                        SELECT key, :local, value FROM import.ui_state
+                        WHERE collection_id = :imported",
+                        params,
+                    )
+                    .context("Error importing table `ui_state`")?;
+                // This table has no key, so check for duplicates manually
+                transaction
+                    .execute(
+                        "INSERT INTO profile_history
+                            (collection_id, profile_id, time)
+                        SELECT :local, profile_id, time
+                        FROM import.profile_history AS imported
+                        WHERE collection_id = :imported AND NOT EXISTS (
+                            SELECT 1 FROM profile_history
+                            WHERE collection_id = :local
+                                AND profile_id IS imported.profile_id
+                                AND time = imported.time
+                        )",
+                        params,
+                    )
+                    .context("Error importing table `profile_history`")?;
+
+                imported.push(ImportedCollection {
+                    path: path.into(),
+                    requests,
+                });
+            }
+            transaction.commit()?;
+            Ok(imported)
+        })()
+        .context("Error importing database")
+        .traced();
+
+        // Detach even if the import failed, so the connection is reusable
+        connection
+            .execute("DETACH DATABASE import", [])
+            .context("Error detaching import file")
+            .traced()?;
+        result
+    }
+
+    /// Convert this database connection into a handle for a single collection
+    /// file. This will store the collection in the DB if it isn't already,
+    /// then grab its generated ID to create a [CollectionDatabase].
+    pub fn into_collection(
+        self,
+        path: &Path,
+    ) -> anyhow::Result<CollectionDatabase> {
+        let collection_id = self.add_collection(path)?;
         Ok(CollectionDatabase {
             collection_id,
             database: self,
+            retention: HistoryRetention::default(),
         })
     }
 }
 
+/// Look up a collection's ID by its path
+fn get_collection_id(
+    connection: &Connection,
+    path: &CollectionPath,
+) -> anyhow::Result<CollectionId> {
+    connection
+        .query_row(
+            "SELECT id FROM collections WHERE path = :path",
+            named_params! {":path": path},
+            |row| row.get::<_, CollectionId>("id"),
+        )
+        .map_err(|err| match err {
+            rusqlite::Error::QueryReturnedNoRows => {
+                // Use Display impl here because this will get shown in CLI
+                // output
+                anyhow!("Unknown collection `{path}`")
+            }
+            other => anyhow::Error::from(other)
+                .context("Error fetching collection ID"),
+        })
+        .traced()
+}
+
+/// Request history statistics for a single collection
+#[derive(Debug)]
+pub struct CollectionStats {
+    pub path: PathBuf,
+    /// Number of stored requests
+    pub requests: usize,
+    /// Total stored size of all requests and responses
+    pub size: ByteSize,
+    /// Start time of the oldest stored request
+    pub oldest: Option<DateTime<Utc>>,
+}
+
+/// Result of importing a single collection with [Database::import]
+#[derive(Debug)]
+pub struct ImportedCollection {
+    /// Local path the collection was imported under
+    pub path: PathBuf,
+    /// Number of new requests
+    pub requests: usize,
+}
+
+/// Limits on how much request history is kept for each collection. Every
+/// limit is optional; by default, history is kept forever. Limits are enforced
+/// whenever a request is stored, and periodically while the TUI is running.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HistoryRetention {
+    /// Maximum number of requests to keep for each recipe. Once exceeded, the
+    /// oldest requests are deleted
+    pub max_per_recipe: Option<usize>,
+    /// Delete requests older than this
+    #[serde(with = "serde_duration::option")]
+    pub max_age: Option<Duration>,
+    /// Response bodies larger than this are truncated before being stored
+    pub max_body_size: Option<ByteSize>,
+}
+
 /// A collection-specific database handle. This is a wrapper around a [Database]
 /// that restricts all queries to a specific collection ID. Use
 /// [Database::into_collection] to obtain one. You can freely clone this.
@@ -267,9 +657,22 @@ impl Database {
 pub struct CollectionDatabase {
     collection_id: CollectionId,
     database: Database,
+    retention: HistoryRetention,
 }
 
 impl CollectionDatabase {
+    /// Enforce the given history limits on this handle. Without this, history
+    /// is unlimited
+    pub fn with_retention(self, retention: HistoryRetention) -> Self {
+        Self { retention, ..self }
+    }
+
+    /// Get a handle to the whole database, for operations that aren't specific
+    /// to this collection
+    pub fn root(&self) -> &Database {
+        &self.database
+    }
+
     /// Get the full path for the collection file associated with this DB handle
     pub fn collection_path(&self) -> anyhow::Result<PathBuf> {
         self.database
@@ -357,12 +760,25 @@ impl CollectionDatabase {
     /// response should be stored. In-flight requests, invalid requests, and
     /// requests that failed to complete (e.g. because of a network error)
     /// should not (and cannot) be stored.
+    ///
+    /// This always writes immediately, even if background writes are enabled,
+    /// so the exchange can be read back as soon as this returns.
     pub fn insert_exchange(&self, exchange: &Exchange) -> anyhow::Result<()> {
         debug!(
             id = %exchange.id,
             url = %exchange.request.url,
             "Adding exchange to database",
         );
+        // Cap the stored body size. The exchange itself is left alone, so the
+        // caller still has the full response
+        let truncated;
+        let response = match self.retention.max_body_size {
+            Some(max_size) if exchange.response.body.size() > max_size => {
+                truncated = exchange.response.truncated(max_size);
+                &truncated
+            }
+            _ => &*exchange.response,
+        };
         self.database
             .connection()
             .execute(
@@ -376,10 +792,14 @@ impl CollectionDatabase {
                     end_time,
                     request,
                     response,
-                    status_code
+                    status_code,
+                    label,
+                    request_size,
+                    response_size
                 )
                 VALUES (:id, :collection_id, :profile_id, :recipe_id,
-                    :start_time, :end_time, :request, :response, :status_code)",
+                    :start_time, :end_time, :request, :response, :status_code,
+                    :label, :request_size, :response_size)",
                 named_params! {
                     ":id": exchange.id,
                     ":collection_id": self.collection_id,
@@ -388,8 +808,11 @@ impl CollectionDatabase {
                     ":start_time": &exchange.start_time,
                     ":end_time": &exchange.end_time,
                     ":request": &ByteEncoded(&*exchange.request),
-                    ":response": &ByteEncoded(&*exchange.response),
+                    ":response": &ByteEncoded(response),
                     ":status_code": exchange.response.status.as_u16(),
+                    ":label": &exchange.request.label,
+                    ":request_size": exchange.request.body_size().as_u64(),
+                    ":response_size": exchange.response.body.size().as_u64(),
                 },
             )
             .context(format!(
@@ -397,9 +820,79 @@ impl CollectionDatabase {
                 exchange.id
             ))
             .traced()?;
+        self.delete_expired()?;
         Ok(())
     }
 
+    /// Delete all requests in this collection that fall outside the retention
+    /// limits, then vacuum the database to reclaim the space. Vacuuming is
+    /// slow, so this is meant to be run periodically in the background rather
+    /// than on every insert. Return the number of deleted requests.
+    pub fn enforce_retention(&self) -> anyhow::Result<usize> {
+        let deleted = self.delete_expired()?;
+        if deleted > 0 {
+            info!(deleted, "Deleted expired requests from history");
+            self.database
+                .connection()
+                .execute("VACUUM", [])
+                .context("Error vacuuming database")
+                .traced()?;
+        }
+        Ok(deleted)
+    }
+
+    /// Delete requests in this collection that are older than the max age, or
+    /// past the max count for their recipe. Return the number of deleted
+    /// requests.
+    fn delete_expired(&self) -> anyhow::Result<usize> {
+        let HistoryRetention {
+            max_per_recipe,
+            max_age,
+            ..
+        } = &self.retention;
+        let connection = self.database.connection();
+        let mut deleted = 0;
+        if let Some(max_age) = max_age {
+            let max_age = chrono::Duration::from_std(*max_age)
+                .context("History max age is too large")?;
+            deleted += connection
+                .execute(
+                    "DELETE FROM requests
+                    WHERE collection_id = :collection_id
+                        AND start_time < :before",
+                    named_params! {
+                        ":collection_id": self.collection_id,
+                        ":before": Utc::now() - max_age,
+                    },
+                )
+                .context("Error deleting old requests")
+                .traced()?;
+        }
+        if let Some(max_per_recipe) = max_per_recipe {
+            // Number each recipe's requests from newest to oldest
+            deleted += connection
+                .execute(
+                    "DELETE FROM requests WHERE id IN (
+                        SELECT id FROM (
+                            SELECT id, ROW_NUMBER() OVER (
+                                PARTITION BY recipe_id ORDER BY start_time DESC
+                            ) AS position
+                            FROM requests
+                            WHERE collection_id = :collection_id
+                        )
+                        WHERE position > :max
+                    )",
+                    named_params! {
+                        ":collection_id": self.collection_id,
+                        ":max": *max_per_recipe as u64,
+                    },
+                )
+                .context("Error deleting excess requests")
+                .traced()?;
+        }
+        Ok(deleted)
+    }
+
     /// Get a list of all requests for a profile+recipe combo
     pub fn get_all_requests(
         &self,
@@ -414,7 +907,9 @@ impl CollectionDatabase {
         self.database
             .connection()
             .prepare(
-                "SELECT id, start_time, end_time, status_code FROM requests
+                "SELECT id, profile_id, start_time, end_time, status_code,
+                    label, request_size, response_size
+                FROM requests
                 WHERE collection_id = :collection_id
                     AND profile_id IS :profile_id
                     AND recipe_id = :recipe_id
@@ -434,12 +929,168 @@ impl CollectionDatabase {
             .context("Error extracting request history")
     }
 
+    /// Get a list of all requests for a recipe, across all profiles
+    pub fn get_recipe_requests(
+        &self,
+        recipe_id: &RecipeId,
+    ) -> anyhow::Result<Vec<ExchangeSummary>> {
+        trace!(
+            recipe_id = %recipe_id,
+            "Fetching request history from database"
+        );
+        self.database
+            .connection()
+            .prepare(
+                "SELECT id, profile_id, start_time, end_time, status_code,
+                    label, request_size, response_size
+                FROM requests
+                WHERE collection_id = :collection_id
+                    AND recipe_id = :recipe_id
+                ORDER BY start_time DESC",
+            )?
+            .query_map(
+                named_params! {
+                    ":collection_id": self.collection_id,
+                    ":recipe_id": recipe_id,
+                },
+                |row| row.try_into(),
+            )
+            .context("Error fetching request history from database")
+            .traced()?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .context("Error extracting request history")
+    }
+
+    /// Get a list of every request in the collection, across all recipes and
+    /// profiles, oldest first. Each summary is paired with its recipe ID
+    pub fn get_collection_requests(
+        &self,
+    ) -> anyhow::Result<Vec<(RecipeId, ExchangeSummary)>> {
+        trace!("Fetching all request history from database");
+        self.database
+            .connection()
+            .prepare(
+                "SELECT id, recipe_id, profile_id, start_time, end_time,
+                    status_code, label, request_size, response_size
+                FROM requests
+                WHERE collection_id = :collection_id
+                ORDER BY start_time ASC",
+            )?
+            .query_map(
+                named_params! {":collection_id": self.collection_id},
+                |row| Ok((row.get("recipe_id")?, row.try_into()?)),
+            )
+            .context("Error fetching request history from database")
+            .traced()?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .context("Error extracting request history")
+    }
+
+    /// Get the start time of the most recent request for each recipe in the
+    /// collection, across all profiles. Recipes that have never been sent are
+    /// omitted.
+    pub fn get_recipe_send_times(
+        &self,
+    ) -> anyhow::Result<HashMap<RecipeId, DateTime<Utc>>> {
+        trace!("Fetching recipe send times from database");
+        self.database
+            .connection()
+            .prepare(
+                "SELECT recipe_id, MAX(start_time) AS start_time
+                FROM requests
+                WHERE collection_id = :collection_id
+                GROUP BY recipe_id",
+            )?
+            .query_map(
+                named_params! {":collection_id": self.collection_id},
+                |row| Ok((row.get("recipe_id")?, row.get("start_time")?)),
+            )
+            .context("Error fetching recipe send times from database")
+            .traced()?
+            .collect::<rusqlite::Result<HashMap<_, _>>>()
+            .context("Error extracting recipe send times")
+    }
+
+    /// Record that a profile was selected at the current time
+    pub fn insert_profile_selection(
+        &self,
+        profile_id: Option<&ProfileId>,
+    ) -> anyhow::Result<()> {
+        let time = Utc::now();
+        if let Some(writer) = &self.database.writer {
+            writer.insert_profile_selection(
+                self.direct(),
+                profile_id.cloned(),
+                time,
+            );
+            Ok(())
+        } else {
+            self.insert_profile_selection_at(profile_id, time)
+        }
+    }
+
+    fn insert_profile_selection_at(
+        &self,
+        profile_id: Option<&ProfileId>,
+        time: DateTime<Utc>,
+    ) -> anyhow::Result<()> {
+        debug!(?profile_id, "Recording profile selection");
+        self.database
+            .connection()
+            .execute(
+                "INSERT INTO profile_history (collection_id, profile_id, time)
+                VALUES (:collection_id, :profile_id, :time)",
+                named_params! {
+                    ":collection_id": self.collection_id,
+                    ":profile_id": profile_id,
+                    ":time": time,
+                },
+            )
+            .context("Error saving profile selection to database")
+            .traced()?;
+        Ok(())
+    }
+
+    /// Get every recorded profile selection, sorted by time ascending
+    pub fn get_profile_history(
+        &self,
+    ) -> anyhow::Result<Vec<(DateTime<Utc>, Option<ProfileId>)>> {
+        self.database
+            .connection()
+            .prepare(
+                "SELECT time, profile_id FROM profile_history
+                WHERE collection_id = :collection_id
+                ORDER BY time ASC",
+            )?
+            .query_map(
+                named_params! {":collection_id": self.collection_id},
+                |row| Ok((row.get("time")?, row.get("profile_id")?)),
+            )
+            .context("Error fetching profile history from database")
+            .traced()?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .context("Error extracting profile history")
+    }
+
     /// Get the value of a UI state field
     pub fn get_ui<K, V>(&self, key: K) -> anyhow::Result<Option<V>>
     where
         K: Debug + Serialize,
         V: Debug + DeserializeOwned,
     {
+        // Check for a value that hasn't been written yet
+        if let Some(writer) = &self.database.writer {
+            let encoded_key = encode_ui(&key)?;
+            if let Some(value) = writer.get_ui(self.collection_id, &encoded_key)
+            {
+                let value = rmp_serde::from_slice(&value)
+                    .context(format!("Error decoding UI state for {key:?}"))
+                    .traced()?;
+                debug!(?key, ?value, "Fetched pending UI state");
+                return Ok(Some(value));
+            }
+        }
+
         let value = self
             .database
             .connection()
@@ -462,13 +1113,27 @@ impl CollectionDatabase {
         Ok(value)
     }
 
-    /// Set the value of a UI state field
+    /// Set the value of a UI state field. If background writes are enabled,
+    /// the value is queued, and any previously queued value for the same key
+    /// is discarded.
     pub fn set_ui<K, V>(&self, key: K, value: V) -> anyhow::Result<()>
     where
         K: Debug + Serialize,
         V: Debug + Serialize,
     {
         debug!(?key, ?value, "Setting UI state");
+        let encoded_key = encode_ui(&key)?;
+        let encoded_value = encode_ui(&value)?;
+        if let Some(writer) = &self.database.writer {
+            writer.set_ui(self.direct(), encoded_key, encoded_value);
+            Ok(())
+        } else {
+            self.set_ui_encoded(&encoded_key, &encoded_value)
+        }
+    }
+
+    /// Store a UI value whose key and value have already been encoded
+    fn set_ui_encoded(&self, key: &[u8], value: &[u8]) -> anyhow::Result<()> {
         self.database
             .connection()
             .execute(
@@ -478,8 +1143,8 @@ impl CollectionDatabase {
                 ON CONFLICT DO UPDATE SET value = excluded.value",
                 named_params! {
                     ":collection_id": self.collection_id,
-                    ":key": ByteEncoded(key),
-                    ":value": ByteEncoded(value),
+                    ":key": key,
+                    ":value": value,
                 },
             )
             .context("Error saving UI state to database")
@@ -487,6 +1152,18 @@ impl CollectionDatabase {
         Ok(())
     }
 
+    /// Get a copy of this handle that executes writes immediately, even if
+    /// background writes are enabled. Used by the background writer itself
+    fn direct(&self) -> Self {
+        Self {
+            database: Database {
+                writer: None,
+                ..self.database.clone()
+            },
+            ..self.clone()
+        }
+    }
+
     #[cfg(test)]
     pub fn collection_id(&self) -> CollectionId {
         self.collection_id
@@ -501,6 +1178,7 @@ impl crate::test_util::Factory for Database {
         Self::migrate(&mut connection).unwrap();
         Self {
             connection: Arc::new(Mutex::new(connection)),
+            writer: None,
         }
     }
 }
@@ -602,6 +1280,14 @@ impl FromSql for CollectionPath {
 #[derive(Debug)]
 struct ByteEncoded<T>(T);
 
+/// Encode a UI state key or value the same way as [ByteEncoded]. Used when the
+/// bytes are needed before they get to the DB
+fn encode_ui(value: &impl Serialize) -> anyhow::Result<Vec<u8>> {
+    rmp_serde::to_vec_named(value)
+        .context("Error encoding UI state")
+        .traced()
+}
+
 impl<T: Serialize> ToSql for ByteEncoded<T> {
     fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
         let bytes = rmp_serde::to_vec_named(&self.0).map_err(|err| {
@@ -659,9 +1345,17 @@ impl<'a, 'b> TryFrom<&'a Row<'b>> for ExchangeSummary {
 
         Ok(Self {
             id: row.get("id")?,
+            profile_id: row.get("profile_id")?,
+            label: row.get("label")?,
             start_time: row.get("start_time")?,
             end_time: row.get("end_time")?,
             status: row.get::<_, StatusCodeWrapper>("status_code")?.0,
+            request_size: row
+                .get::<_, Option<u64>>("request_size")?
+                .map(ByteSize),
+            response_size: row
+                .get::<_, Option<u64>>("response_size")?
+                .map(ByteSize),
         })
     }
 }
@@ -669,9 +1363,12 @@ impl<'a, 'b> TryFrom<&'a Row<'b>> for ExchangeSummary {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::test_util::Factory;
+    use crate::{
+        http::{RequestRecord, ResponseBody, ResponseRecord},
+        test_util::{assert_err, temp_dir, Factory, TempDir},
+    };
     use itertools::Itertools;
-    use std::collections::HashMap;
+    use rstest::rstest;
 
     #[test]
     fn test_merge() {
@@ -741,6 +1438,87 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_add_remove_collection() {
+        let database = Database::factory(());
+        let path1 = Path::new("slumber.yml");
+        let path2 = Path::new("README.md"); // Has to be a real file
+        let id = database.add_collection(path1).unwrap();
+        // Adding again is a no-op
+        assert_eq!(database.add_collection(path1).unwrap(), id);
+        let collection2 = database.clone().into_collection(path2).unwrap();
+        collection2.insert_exchange(&Exchange::factory(())).unwrap();
+        collection2.set_ui("key1", "value1").unwrap();
+        collection2.insert_profile_selection(None).unwrap();
+
+        database.remove_collection(path2).unwrap();
+        assert_eq!(
+            database.collections().unwrap(),
+            vec![path1.canonicalize().unwrap()]
+        );
+        // All of its data should be gone too
+        for table in ["requests", "ui_state", "profile_history"] {
+            let count: u32 = database
+                .connection()
+                .query_row(
+                    &format!("SELECT COUNT(*) FROM {table}"),
+                    [],
+                    |row| row.get(0),
+                )
+                .unwrap();
+            assert_eq!(count, 0, "{table}");
+        }
+
+        assert_err!(
+            database.remove_collection(Path::new("/missing.yml")),
+            "Unknown collection `/missing.yml`"
+        );
+    }
+
+    /// Export a database, then import it into a fresh one
+    #[rstest]
+    fn test_export_import(temp_dir: TempDir) {
+        let path1 = Path::new("slumber.yml");
+        let path2 = Path::new("README.md"); // Has to be a real file
+        let source = Database::factory(());
+        let collection = source.clone().into_collection(path1).unwrap();
+        let exchange = Exchange::factory(());
+        collection.insert_exchange(&exchange).unwrap();
+        collection.set_ui("key1", "value1").unwrap();
+
+        let export_path = temp_dir.join("export.sqlite");
+        source.export(&export_path).unwrap();
+        // Never overwrite an existing file
+        assert_err!(source.export(&export_path), "already exists");
+
+        let target = Database::factory(());
+        let imported = target.import(&export_path, &[]).unwrap();
+        assert_eq!(imported.len(), 1);
+        assert_eq!(imported[0].path, path1.canonicalize().unwrap());
+        assert_eq!(imported[0].requests, 1);
+        let collection = target.clone().into_collection(path1).unwrap();
+        assert!(collection.get_request(exchange.id).unwrap().is_some());
+        assert_eq!(
+            collection.get_ui::<_, String>("key1").unwrap(),
+            Some("value1".into())
+        );
+
+        // Importing again doesn't duplicate anything
+        let imported = target.import(&export_path, &[]).unwrap();
+        assert_eq!(imported[0].requests, 0);
+
+        // Import under a different path
+        let target = Database::factory(());
+        let path_map = [(path1.canonicalize().unwrap(), path2.to_owned())];
+        target.import(&export_path, &path_map).unwrap();
+        assert_eq!(
+            target.collections().unwrap(),
+            vec![path2.canonicalize().unwrap()]
+        );
+        let collection = target.into_collection(path2).unwrap();
+        assert!(collection.get_request(exchange.id).unwrap().is_some());
+    }
+
     /// Test request storage and retrieval
     #[test]
     fn test_request() {
@@ -879,6 +1657,341 @@ mod tests {
         }
     }
 
+    /// Requests for a recipe should be loaded across all profiles, and each
+    /// should carry the profile it was sent with
+    #[test]
+    fn test_get_recipe_requests() {
+        let database = CollectionDatabase::factory(());
+        let recipe_id: RecipeId = "recipe1".into();
+        let time = |seconds| DateTime::from_timestamp(seconds, 0).unwrap();
+        let expected = [
+            (None, time(100)),
+            (Some("profile1"), time(200)),
+            (Some("profile2"), time(300)),
+        ]
+        .into_iter()
+        .map(|(profile_id, start_time)| {
+            let exchange = Exchange {
+                start_time,
+                ..Exchange::factory((
+                    profile_id.map(ProfileId::from),
+                    recipe_id.clone(),
+                ))
+            };
+            database.insert_exchange(&exchange).unwrap();
+            (exchange.id, exchange.request.profile_id.clone())
+        })
+        .rev() // Newest first
+        .collect_vec();
+        // Confounder
+        database
+            .insert_exchange(&Exchange::factory((None, "recipe2".into())))
+            .unwrap();
+
+        let loaded = database
+            .get_recipe_requests(&recipe_id)
+            .unwrap()
+            .into_iter()
+            .map(|exchange| (exchange.id, exchange.profile_id))
+            .collect_vec();
+        assert_eq!(loaded, expected);
+    }
+
+    #[test]
+    fn test_get_collection_requests() {
+        let database = CollectionDatabase::factory(());
+        let expected = [
+            (None, "recipe1"),
+            (Some("profile1"), "recipe2"),
+            (Some("profile1"), "recipe1"),
+        ]
+        .into_iter()
+        .map(|(profile_id, recipe_id)| {
+            let exchange = Exchange::factory((
+                profile_id.map(ProfileId::from),
+                recipe_id.into(),
+            ));
+            database.insert_exchange(&exchange).unwrap();
+            (RecipeId::from(recipe_id), exchange.id)
+        })
+        .collect_vec();
+        // Confounder in another collection. Has to be a real file
+        database
+            .root()
+            .clone()
+            .into_collection(Path::new("README.md"))
+            .unwrap()
+            .insert_exchange(&Exchange::factory((None, "recipe1".into())))
+            .unwrap();
+
+        let loaded = database
+            .get_collection_requests()
+            .unwrap()
+            .into_iter()
+            .map(|(recipe_id, exchange)| (recipe_id, exchange.id))
+            .collect_vec();
+        assert_eq!(loaded, expected);
+    }
+
+    /// Only the latest request for each recipe should be used, regardless of
+    /// profile
+    #[test]
+    fn test_get_recipe_send_times() {
+        let database = CollectionDatabase::factory(());
+        let time = |seconds| DateTime::from_timestamp(seconds, 0).unwrap();
+        for (profile_id, recipe_id, start_time) in [
+            (None, "recipe1", time(100)),
+            (Some("profile1"), "recipe1", time(300)),
+            (Some("profile1"), "recipe2", time(200)),
+            (None, "recipe1", time(150)),
+        ] {
+            let exchange = Exchange {
+                start_time,
+                ..Exchange::factory((
+                    profile_id.map(ProfileId::from),
+                    recipe_id.into(),
+                ))
+            };
+            database.insert_exchange(&exchange).unwrap();
+        }
+        // Confounder in another collection. Has to be a real file
+        database
+            .root()
+            .clone()
+            .into_collection(Path::new("README.md"))
+            .unwrap()
+            .insert_exchange(&Exchange {
+                start_time: time(1000),
+                ..Exchange::factory((None, "recipe1".into()))
+            })
+            .unwrap();
+
+        assert_eq!(
+            database.get_recipe_send_times().unwrap(),
+            HashMap::from([
+                ("recipe1".into(), time(300)),
+                ("recipe2".into(), time(200)),
+            ])
+        );
+    }
+
+    /// Request labels should be included in history summaries
+    #[test]
+    fn test_request_label() {
+        let database = CollectionDatabase::factory(());
+        let exchange = Exchange {
+            request: RequestRecord {
+                label: Some("Get user 3".into()),
+                ..RequestRecord::factory(())
+            }
+            .into(),
+            ..Exchange::factory(())
+        };
+        database.insert_exchange(&exchange).unwrap();
+
+        let loaded = database
+            .get_all_requests(
+                exchange.request.profile_id.as_ref(),
+                &exchange.request.recipe_id,
+            )
+            .unwrap();
+        assert_eq!(
+            loaded
+                .iter()
+                .map(|summary| summary.label.as_deref())
+                .collect_vec(),
+            [Some("Get user 3")]
+        );
+    }
+
+    /// Body sizes are stored with each request
+    #[test]
+    fn test_request_size() {
+        let database = CollectionDatabase::factory(());
+        let exchange = Exchange {
+            request: RequestRecord {
+                body: Some("{}".into()),
+                ..RequestRecord::factory(())
+            }
+            .into(),
+            ..Exchange::factory(())
+        };
+        database.insert_exchange(&exchange).unwrap();
+
+        let loaded = database
+            .get_recipe_requests(&exchange.request.recipe_id)
+            .unwrap();
+        assert_eq!(loaded[0].request_size, Some(ByteSize(2)));
+        assert_eq!(
+            loaded[0].response_size,
+            Some(exchange.response.body.size())
+        );
+    }
+
+    #[test]
+    fn test_prune() {
+        let database = Database::factory(());
+        let collection = database
+            .clone()
+            .into_collection(Path::new("slumber.yml"))
+            .unwrap();
+        let now = Utc::now();
+        let exchanges = [40, 20, 0].map(|days_ago| {
+            let start_time = now - chrono::Duration::days(days_ago);
+            let exchange = Exchange {
+                start_time,
+                end_time: start_time,
+                ..Exchange::factory(())
+            };
+            collection.insert_exchange(&exchange).unwrap();
+            exchange
+        });
+        let count = || database.stats().unwrap()[0].requests;
+        assert_eq!(count(), 3);
+
+        // Nothing is older than this, so nothing goes
+        let deleted = database
+            .prune(Some(now - chrono::Duration::days(50)), None)
+            .unwrap();
+        assert_eq!(deleted, 0);
+
+        let deleted = database
+            .prune(Some(now - chrono::Duration::days(30)), None)
+            .unwrap();
+        assert_eq!(deleted, 1);
+        assert!(collection.get_request(exchanges[0].id).unwrap().is_none());
+        assert_eq!(count(), 2);
+
+        // Limit is smaller than any single request, so everything goes
+        let deleted = database.prune(None, Some(ByteSize(1))).unwrap();
+        assert_eq!(deleted, 2);
+        assert_eq!(count(), 0);
+    }
+
+    /// Only the newest requests for each recipe are kept
+    #[test]
+    fn test_retention_max_per_recipe() {
+        let database =
+            CollectionDatabase::factory(()).with_retention(HistoryRetention {
+                max_per_recipe: Some(2),
+                ..Default::default()
+            });
+        let recipe1 = RecipeId::from("recipe1");
+        let recipe2 = RecipeId::from("recipe2");
+        let now = Utc::now();
+        let exchanges = [3, 2, 1].map(|minutes_ago| {
+            let start_time = now - chrono::Duration::minutes(minutes_ago);
+            let exchange = Exchange {
+                start_time,
+                end_time: start_time,
+                ..Exchange::factory((None, recipe1.clone()))
+            };
+            database.insert_exchange(&exchange).unwrap();
+            exchange
+        });
+        // Other recipes don't count against the limit
+        database
+            .insert_exchange(&Exchange::factory((None, recipe2.clone())))
+            .unwrap();
+
+        let ids = |recipe_id| {
+            database
+                .get_recipe_requests(recipe_id)
+                .unwrap()
+                .into_iter()
+                .map(|summary| summary.id)
+                .collect_vec()
+        };
+        assert_eq!(ids(&recipe1), [exchanges[2].id, exchanges[1].id]);
+        assert_eq!(ids(&recipe2).len(), 1);
+    }
+
+    /// Old requests are deleted on insert and by the background task
+    #[test]
+    fn test_retention_max_age() {
+        let database = CollectionDatabase::factory(());
+        let start_time = Utc::now() - chrono::Duration::days(10);
+        let old = Exchange {
+            start_time,
+            end_time: start_time,
+            ..Exchange::factory(())
+        };
+        database.insert_exchange(&old).unwrap();
+
+        let database = database.with_retention(HistoryRetention {
+            max_age: Some(Duration::from_secs(60 * 60 * 24)),
+            ..Default::default()
+        });
+        assert_eq!(database.enforce_retention().unwrap(), 1);
+        assert!(database.get_request(old.id).unwrap().is_none());
+
+        // Inserting something old should delete it immediately
+        database.insert_exchange(&old).unwrap();
+        assert!(database.get_request(old.id).unwrap().is_none());
+        let new = Exchange::factory(());
+        database.insert_exchange(&new).unwrap();
+        assert!(database.get_request(new.id).unwrap().is_some());
+    }
+
+    /// Large response bodies are truncated before being stored
+    #[test]
+    fn test_retention_max_body_size() {
+        let database =
+            CollectionDatabase::factory(()).with_retention(HistoryRetention {
+                max_body_size: Some(ByteSize(5)),
+                ..Default::default()
+            });
+        let exchange = Exchange {
+            response: ResponseRecord {
+                body: ResponseBody::new(b"hello world".as_slice().into()),
+                ..ResponseRecord::factory(())
+            }
+            .into(),
+            ..Exchange::factory(())
+        };
+        database.insert_exchange(&exchange).unwrap();
+
+        let loaded = database.get_request(exchange.id).unwrap().unwrap();
+        assert_eq!(loaded.response.body.bytes(), b"hello");
+        // The caller's copy is untouched
+        assert_eq!(exchange.response.body.bytes(), b"hello world");
+    }
+
+    /// Profile selections should be recorded per collection, in order
+    #[test]
+    fn test_profile_history() {
+        let database = Database::factory(());
+        let collection1 = database
+            .clone()
+            .into_collection(Path::new("slumber.yml"))
+            .unwrap();
+        let collection2 = database
+            .clone()
+            .into_collection(Path::new("README.md"))
+            .unwrap();
+
+        let profile1 = ProfileId::from("profile1");
+        let profile2 = ProfileId::from("profile2");
+        collection1
+            .insert_profile_selection(Some(&profile1))
+            .unwrap();
+        collection2
+            .insert_profile_selection(Some(&profile2))
+            .unwrap();
+        collection1.insert_profile_selection(None).unwrap();
+
+        let history = |collection: &CollectionDatabase| {
+            collection
+                .get_profile_history()
+                .unwrap()
+                .into_iter()
+                .map(|(_, profile_id)| profile_id)
+                .collect_vec()
+        };
+        assert_eq!(history(&collection1), vec![Some(profile1), None]);
+        assert_eq!(history(&collection2), vec![Some(profile2)]);
+    }
+
     /// Test UI state storage and retrieval
     #[test]
     fn test_ui_state() {
@@ -905,4 +2018,54 @@ mod tests {
             Some("value2".into())
         );
     }
+
+    /// Test background writes. UI values should be visible before they're
+    /// written, repeated writes to one key should be coalesced, and everything
+    /// should be in the DB after a flush
+    #[test]
+    fn test_background_writes() {
+        let database = Database::factory(())
+            .with_background_writes(|error| panic!("{error:#}"));
+        let collection = database
+            .clone()
+            .into_collection(Path::new("slumber.yml"))
+            .unwrap();
+
+        collection
+            .insert_profile_selection(Some(&"profile1".into()))
+            .unwrap();
+        for i in 0..100 {
+            collection.set_ui("key1", i).unwrap();
+        }
+        // Visible immediately, whether written or not
+        assert_eq!(collection.get_ui::<_, i32>("key1").unwrap(), Some(99));
+
+        database.flush();
+        let direct = collection.direct();
+        assert_eq!(direct.get_ui::<_, i32>("key1").unwrap(), Some(99));
+        assert_eq!(direct.get_profile_history().unwrap().len(), 1);
+    }
+
+    /// Exchanges aren't queued with other background writes, so they can be
+    /// read back immediately without a flush
+    #[test]
+    fn test_background_writes_exchange() {
+        let database = Database::factory(())
+            .with_background_writes(|error| panic!("{error:#}"));
+        let collection =
+            database.into_collection(Path::new("slumber.yml")).unwrap();
+
+        let exchange = Exchange::factory(());
+        collection.insert_exchange(&exchange).unwrap();
+        assert_eq!(
+            collection
+                .get_latest_request(
+                    exchange.request.profile_id.as_ref(),
+                    &exchange.request.recipe_id
+                )
+                .unwrap()
+                .map(|exchange| exchange.id),
+            Some(exchange.id)
+        );
+    }
 }
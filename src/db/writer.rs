@@ -0,0 +1,169 @@
+//! Background database writes
+
+use crate::{
+    collection::ProfileId,
+    db::{CollectionDatabase, CollectionId},
+};
+use chrono::{DateTime, Utc};
+use std::{
+    collections::HashMap,
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread,
+};
+use tracing::{info, trace};
+
+/// Pending UI state values, keyed by collection and encoded key. Values are
+/// encoded as well, so the map doesn't need to know any types.
+type PendingUi = Arc<Mutex<HashMap<(CollectionId, Vec<u8>), Vec<u8>>>>;
+
+/// Runs database writes on a dedicated thread, so the caller never blocks on
+/// SQLite. Writes are executed in the order they're queued. UI state writes
+/// are coalesced: if a key is written again before its previous value is
+/// stored, only the latest value is written. Pending UI values are visible to
+/// reads, so the caller always sees its own writes.
+///
+/// Exchanges are *not* written here. History is read directly from SQLite in
+/// too many places to overlay pending inserts, so they're inserted on a
+/// blocking thread by the HTTP engine instead. See [RequestTicket::send].
+///
+/// [RequestTicket::send]: crate::http::RequestTicket::send
+#[derive(Debug)]
+pub(super) struct DatabaseWriter {
+    tx: Sender<Write>,
+    pending_ui: PendingUi,
+}
+
+/// A queued write. Each one carries a handle *without* background writes
+/// enabled, so the writer thread can execute it directly.
+enum Write {
+    ProfileSelection {
+        database: CollectionDatabase,
+        profile_id: Option<ProfileId>,
+        time: DateTime<Utc>,
+    },
+    /// The value lives in the pending map, so it can be replaced until it's
+    /// written
+    Ui {
+        database: CollectionDatabase,
+        key: Vec<u8>,
+    },
+    /// Notify the sender once every previous write is done
+    Flush(Sender<()>),
+}
+
+impl DatabaseWriter {
+    /// Spawn the writer thread. Any error that occurs during a write is passed
+    /// to the callback. The thread exits once the writer is dropped and every
+    /// queued write is done.
+    pub fn spawn(on_error: impl 'static + Fn(anyhow::Error) + Send) -> Self {
+        let (tx, rx) = mpsc::channel();
+        let pending_ui = PendingUi::default();
+        let pending_ui_clone = Arc::clone(&pending_ui);
+        thread::Builder::new()
+            .name("database-writer".into())
+            .spawn(move || run(rx, &pending_ui_clone, on_error))
+            .expect("Error spawning database writer thread");
+        Self { tx, pending_ui }
+    }
+
+    pub fn insert_profile_selection(
+        &self,
+        database: CollectionDatabase,
+        profile_id: Option<ProfileId>,
+        time: DateTime<Utc>,
+    ) {
+        self.send(Write::ProfileSelection {
+            database,
+            profile_id,
+            time,
+        });
+    }
+
+    /// Queue a UI value to be written. If the key already has a pending value,
+    /// it's replaced and nothing new is queued.
+    pub fn set_ui(
+        &self,
+        database: CollectionDatabase,
+        key: Vec<u8>,
+        value: Vec<u8>,
+    ) {
+        let previous = self
+            .pending_ui
+            .lock()
+            .expect("Pending UI lock poisoned")
+            .insert((database.collection_id, key.clone()), value);
+        if previous.is_none() {
+            self.send(Write::Ui { database, key });
+        } else {
+            trace!(?key, "Coalesced UI state write");
+        }
+    }
+
+    /// Get a UI value that's been queued but not yet written
+    pub fn get_ui(
+        &self,
+        collection_id: CollectionId,
+        key: &[u8],
+    ) -> Option<Vec<u8>> {
+        self.pending_ui
+            .lock()
+            .expect("Pending UI lock poisoned")
+            .get(&(collection_id, key.to_owned()))
+            .cloned()
+    }
+
+    /// Block until every write queued so far is done
+    pub fn flush(&self) {
+        let (tx, rx) = mpsc::channel();
+        self.send(Write::Flush(tx));
+        // If the thread is dead, there's nothing to wait for
+        let _ = rx.recv();
+    }
+
+    fn send(&self, write: Write) {
+        // The receiver only closes if the thread panicked, in which case we
+        // have bigger problems
+        let _ = self.tx.send(write);
+    }
+}
+
+/// Main loop for the writer thread
+fn run(
+    rx: Receiver<Write>,
+    pending_ui: &PendingUi,
+    on_error: impl Fn(anyhow::Error),
+) {
+    info!("Starting database writer");
+    for write in rx {
+        let result = match write {
+            Write::ProfileSelection {
+                database,
+                profile_id,
+                time,
+            } => {
+                database.insert_profile_selection_at(profile_id.as_ref(), time)
+            }
+            Write::Ui { database, key } => {
+                // Hold the lock until the value is stored, so a concurrent
+                // read can't miss it in both the map *and* the DB
+                let mut pending =
+                    pending_ui.lock().expect("Pending UI lock poisoned");
+                match pending.remove(&(database.collection_id, key.clone())) {
+                    Some(value) => database.set_ui_encoded(&key, &value),
+                    None => Ok(()),
+                }
+            }
+            Write::Flush(done) => {
+                let _ = done.send(());
+                Ok(())
+            }
+        };
+        if let Err(error) = result {
+            on_error(error);
+        }
+    }
+    info!("Database writer closed");
+}
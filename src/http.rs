@@ -33,23 +33,42 @@
 //! | Exchange |
 //! +----------+
 
+mod assertion;
 mod cereal;
+mod code;
+mod compression;
 mod content_type;
+mod host;
+mod inspect;
 mod models;
+mod port_forward;
+mod proxy;
 mod query;
+mod tls;
 
+pub use assertion::*;
+pub use code::CodeLanguage;
+pub use compression::decompress_response;
 pub use content_type::*;
+pub use host::*;
+pub use inspect::*;
 pub use models::*;
+pub use port_forward::*;
+pub use proxy::*;
 pub use query::*;
 
 use crate::{
-    collection::{Authentication, JsonBody, Method, Recipe, RecipeBody},
+    collection::{
+        ApiKeyLocation, Authentication, HttpVersion, JsonBody, Method, Recipe,
+        RecipeBody, SaveRule, TlsOptions,
+    },
     config::Config,
     db::CollectionDatabase,
-    template::{Template, TemplateContext},
+    http::tls::TlsFiles,
+    template::{with_recipe_variables, Template, TemplateContext},
     util::ResultExt,
 };
-use anyhow::Context;
+use anyhow::{anyhow, bail, Context};
 use async_recursion::async_recursion;
 use bytes::Bytes;
 use chrono::Utc;
@@ -57,14 +76,23 @@ use futures::{
     future::{self, try_join_all, OptionFuture},
     Future,
 };
+use indexmap::IndexMap;
 use mime::Mime;
 use reqwest::{
     header::{self, HeaderMap, HeaderName, HeaderValue},
     multipart::{Form, Part},
-    Client, RequestBuilder, Response, Url,
+    redirect, Client, ClientBuilder, Request, RequestBuilder, Response,
+    StatusCode, Url,
 };
-use std::{collections::HashSet, sync::Arc};
-use tokio::try_join;
+use std::{
+    collections::{HashMap, HashSet},
+    mem,
+    net::{IpAddr, SocketAddr},
+    path::{Path, PathBuf},
+    process::Stdio,
+    sync::{Arc, Mutex},
+};
+use tokio::{fs, net::lookup_host, process::Command, task, try_join};
 use tracing::{info, info_span};
 
 const USER_AGENT: &str =
@@ -84,18 +112,44 @@ pub struct HttpEngine {
     danger_client: Client,
     /// Hostnames for which we should ignore TLS
     danger_hostnames: HashSet<String>,
+    /// Global proxy settings, from the config
+    proxy: Option<ProxyOptions>,
+    /// Clients for requests with custom TLS or proxy options, keyed by the
+    /// options. These are built lazily, then cached so connections can be
+    /// reused across requests.
+    custom_clients: Arc<Mutex<HashMap<ClientOptions, Client>>>,
+    /// Commands to start before sending requests with certain profiles
+    port_forwards: PortForwards,
+}
+
+/// Request options that require a dedicated client, rather than one of the
+/// shared defaults
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+struct ClientOptions {
+    /// TLS options, with referenced files already loaded. Including the file
+    /// contents in the key means changes to those files are picked up
+    tls: Option<TlsFiles>,
+    /// Overrides the global proxy settings
+    proxy: Option<ProxyOptions>,
+    http_version: HttpVersion,
+    /// Domain to override DNS resolution for, and the addresses to connect
+    /// to instead
+    connect_to: Option<(String, Vec<SocketAddr>)>,
+    /// DNS overrides from `resolve`, as (hostname, address) pairs
+    resolve: Vec<(String, SocketAddr)>,
+    /// Send all requests over a Unix domain socket at this path
+    unix_socket: Option<PathBuf>,
 }
 
 impl HttpEngine {
     /// Build a new HTTP engine, which can be used for the entire program life
     pub fn new(config: &Config) -> Self {
+        let proxy = config.proxy.clone();
         Self {
-            client: Client::builder()
-                .user_agent(USER_AGENT)
+            client: Self::client_builder(proxy.as_ref())
                 .build()
                 .expect("Error building reqwest client"),
-            danger_client: Client::builder()
-                .user_agent(USER_AGENT)
+            danger_client: Self::client_builder(proxy.as_ref())
                 .danger_accept_invalid_certs(true)
                 .build()
                 .expect("Error building reqwest client"),
@@ -104,9 +158,17 @@ impl HttpEngine {
                 .iter()
                 .cloned()
                 .collect(),
+            proxy,
+            custom_clients: Default::default(),
+            port_forwards: PortForwards::new(config.port_forwards.clone()),
         }
     }
 
+    /// Stop all port forwards started by this engine
+    pub fn stop_port_forwards(&self) {
+        self.port_forwards.stop();
+    }
+
     /// Build a [RequestTicket] from a [RequestSeed]. This will render the
     /// recipe into a request. The returned ticket can then be launched.
     pub async fn build(
@@ -124,20 +186,70 @@ impl HttpEngine {
                 .entered();
 
         let future = async {
-            // Render everything up front so we can parallelize it
-            let (url, query, headers, authentication, body) = try_join!(
-                recipe.render_url(template_context),
+            // Make sure we're not about to send the profile's credentials to
+            // the wrong host because of a typo. Check this before rendering
+            // anything else, so auth helpers and port forwards don't run for
+            // a request that isn't allowed
+            let url = recipe.render_url(template_context).await?;
+            let allowed_hosts = HostAllowlist::new(
+                template_context.profile(),
+                &url,
+                options.allow_any_host,
+            )?;
+
+            // Render everything else up front so we can parallelize it
+            let (
+                query,
+                headers,
+                authentication,
+                body,
+                tls,
+                label,
+                connect_to,
+                resolve,
+                unix_socket,
+                save,
+            ) = try_join!(
                 recipe.render_query(options, template_context),
                 recipe.render_headers(options, template_context),
                 recipe.render_authentication(template_context),
                 recipe.render_body(options, template_context),
+                recipe.render_tls(template_context),
+                recipe.render_label(template_context),
+                recipe.render_connect_to(template_context),
+                recipe.render_resolve(template_context),
+                recipe.render_unix_socket(template_context),
+                recipe.render_save(template_context),
             )?;
 
+            if let Some(profile) = template_context.profile() {
+                self.port_forwards.start(&profile.id).await?;
+            }
+
             // Build the reqwest request first, so we can have it do all the
             // hard work of encoding query params/authorization/etc.
             // We'll just copy its homework at the end to get our
             // RequestRecord
-            let client = self.get_client(&url);
+            let proxy = template_context
+                .profile()
+                .and_then(|profile| profile.proxy.clone());
+            let connect_to = match connect_to {
+                Some(host) => Some(resolve_connect_to(&host, &url).await?),
+                None => None,
+            };
+            let options = ClientOptions {
+                tls,
+                proxy,
+                http_version: recipe.http_version,
+                connect_to,
+                resolve,
+                unix_socket,
+            };
+            let client = if options == ClientOptions::default() {
+                self.get_client(&url).clone()
+            } else {
+                self.get_custom_client(options, &url)?
+            };
             let mut builder =
                 client.request(recipe.method.into(), url).query(&query);
             if let Some(body) = body {
@@ -146,25 +258,41 @@ impl HttpEngine {
             // Set headers *after* body so the use can override the Content-Type
             // header that was set if they want to
             builder = builder.headers(headers);
+            let auth_header = authentication
+                .as_ref()
+                .and_then(Authentication::header_name);
             if let Some(authentication) = authentication {
-                builder = authentication.apply(builder);
+                builder = authentication.apply(builder).await?;
             }
 
-            let request = builder.build()?;
-            Ok((client, request))
+            let mut request = builder.build()?;
+            // Compress last, so it applies to the fully encoded body
+            if let Some(compression) = recipe.compress {
+                compression.apply(&mut request)?;
+            }
+            let redirects = RedirectPolicy {
+                options: recipe.redirects,
+                allowed_hosts,
+                auth_header,
+            };
+            Ok((client, request, label, save, redirects))
         };
-        let (client, request) =
+        let (client, request, label, save, redirects) =
             seed.convert_error(future, template_context).await?;
 
         Ok(RequestTicket {
             record: RequestRecord::new(
                 seed,
                 template_context.selected_profile.clone(),
+                template_context.rendered_values.snapshot(),
+                label,
                 &request,
             )
             .into(),
-            client: client.clone(),
+            client,
             request,
+            save,
+            redirects,
         })
     }
 
@@ -259,22 +387,146 @@ impl HttpEngine {
     /// host is one for which the user wants to ignore TLS certs, use the
     /// dangerous client.
     fn get_client(&self, url: &Url) -> &Client {
-        let host = url.host_str().unwrap_or_default();
-        if self.danger_hostnames.contains(host) {
+        if self.is_danger_host(url) {
             &self.danger_client
         } else {
             &self.client
         }
     }
+
+    /// Get a client configured with custom TLS/proxy/version options. Clients
+    /// are cached by their options, so each distinct configuration is only
+    /// built once.
+    fn get_custom_client(
+        &self,
+        mut options: ClientOptions,
+        url: &Url,
+    ) -> anyhow::Result<Client> {
+        // Global config still applies
+        if self.is_danger_host(url) {
+            options
+                .tls
+                .get_or_insert_with(TlsFiles::default)
+                .danger_accept_invalid_certs = Some(true);
+        }
+
+        let mut clients = self
+            .custom_clients
+            .lock()
+            .expect("Client cache lock poisoned");
+        if let Some(client) = clients.get(&options) {
+            return Ok(client.clone());
+        }
+        let mut builder = Self::client_builder(
+            options.proxy.as_ref().or(self.proxy.as_ref()),
+        );
+        if let Some(tls) = &options.tls {
+            builder = tls.apply(builder)?;
+        }
+        builder = match options.http_version {
+            HttpVersion::Auto => builder,
+            HttpVersion::Http1_1 => builder.http1_only(),
+            HttpVersion::Http2 => builder.http2_prior_knowledge(),
+        };
+        for (domain, address) in &options.resolve {
+            builder = builder.resolve(domain, *address);
+        }
+        // Apply this after `resolve`, so it wins for the URL's domain
+        if let Some((domain, addresses)) = &options.connect_to {
+            builder = builder.resolve_to_addrs(domain, addresses);
+        }
+        // The socket replaces the whole transport, so any proxy configured
+        // above is ignored
+        if let Some(path) = &options.unix_socket {
+            builder = unix_socket(builder, path)?;
+        }
+        let client = builder.build().context("Error building HTTP client")?;
+        clients.insert(options, client.clone());
+        Ok(client)
+    }
+
+    /// Get a client builder with all the settings shared between clients.
+    /// Redirects are followed manually (see [RequestTicket::send]) so they can
+    /// be configured per recipe and recorded, so reqwest's handling is disabled
+    fn client_builder(proxy: Option<&ProxyOptions>) -> ClientBuilder {
+        let builder = Client::builder()
+            .user_agent(USER_AGENT)
+            .redirect(redirect::Policy::none());
+        match proxy {
+            Some(proxy) => proxy.apply(builder),
+            None => builder,
+        }
+    }
+
+    /// Should TLS cert errors be ignored for this URL's host?
+    fn is_danger_host(&self, url: &Url) -> bool {
+        let host = url.host_str().unwrap_or_default();
+        self.danger_hostnames.contains(host)
+    }
+}
+
+/// Resolve a `connect_to` host, using the port from the request URL. Returns
+/// the URL's domain, so the client can override DNS resolution for it
+async fn resolve_connect_to(
+    host: &str,
+    url: &Url,
+) -> anyhow::Result<(String, Vec<SocketAddr>)> {
+    // DNS overrides can't apply to an IP address, since there's no lookup
+    let domain = url.domain().ok_or_else(|| {
+        anyhow!("`connect_to` requires the URL host to be a domain name")
+    })?;
+    let port = url.port_or_known_default().unwrap_or_default();
+    let addresses: Vec<SocketAddr> = lookup_host((host, port))
+        .await
+        .with_context(|| format!("Error resolving `connect_to` host `{host}`"))?
+        .collect();
+    if addresses.is_empty() {
+        bail!("`connect_to` host `{host}` did not resolve to any addresses");
+    }
+    Ok((domain.to_owned(), addresses))
+}
+
+/// Parse a `resolve` address, which is an IP address with an optional port.
+/// Without a port, the URL's port is used.
+fn parse_resolve_address(address: &str) -> anyhow::Result<SocketAddr> {
+    address
+        .parse::<SocketAddr>()
+        // Port 0 tells the connector to use the URL's port
+        .or_else(|_| address.parse::<IpAddr>().map(|ip| SocketAddr::new(ip, 0)))
+        .map_err(|_| {
+            anyhow!(
+                "Invalid address `{address}`; expected an IP address with \
+                optional port, e.g. `127.0.0.1` or `127.0.0.1:3000`"
+            )
+        })
+}
+
+/// Send all requests from a client over a Unix domain socket
+#[cfg(unix)]
+fn unix_socket(
+    builder: ClientBuilder,
+    path: &Path,
+) -> anyhow::Result<ClientBuilder> {
+    Ok(builder.unix_socket(path.to_owned()))
+}
+
+/// Send all requests from a client over a Unix domain socket
+#[cfg(not(unix))]
+fn unix_socket(_: ClientBuilder, _: &Path) -> anyhow::Result<ClientBuilder> {
+    bail!("`unix_socket` is not supported on this platform")
 }
 
 impl RequestSeed {
-    /// Run the given future and convert any error into [RequestBuildError]
+    /// Run the given future and convert any error into [RequestBuildError].
+    /// The recipe's variables are available to any template rendered within
+    /// the future.
     async fn convert_error<T>(
         &self,
         future: impl Future<Output = anyhow::Result<T>>,
         template_context: &TemplateContext,
     ) -> Result<T, RequestBuildError> {
+        let future =
+            with_recipe_variables(self.recipe.variables.clone(), future);
         future.await.traced().map_err(|error| RequestBuildError {
             profile_id: template_context.selected_profile.clone(),
             recipe_id: self.recipe.id.clone(),
@@ -295,10 +547,37 @@ impl RequestTicket {
     /// launched until the consumer starts awaiting the future. For in-flight
     /// time tracking, track your own start time immediately before/after
     /// sending the request.
+    ///
+    /// If the recipe has a `save` rule matching the response's content type,
+    /// the response body is written to that file as well. A failure to save
+    /// is returned alongside the exchange, rather than failing the request.
     pub async fn send(
-        self,
+        mut self,
         database: &CollectionDatabase,
-    ) -> Result<Exchange, RequestError> {
+    ) -> Result<SentExchange, RequestError> {
+        let save = mem::take(&mut self.save);
+        let exchange = self.send_unrecorded().await?;
+        // Errors here should *not* kill the request. Storing a large response
+        // can take a moment, so do it on a blocking thread. It's still done
+        // before we return, so the exchange is in history as soon as the
+        // caller sees it
+        let _ = task::spawn_blocking({
+            let database = database.clone();
+            let exchange = exchange.clone();
+            move || database.insert_exchange(&exchange)
+        })
+        .await;
+        let save_error = save_body(&save, &exchange).await.traced().err();
+        Ok(SentExchange {
+            exchange,
+            save_error,
+        })
+    }
+
+    /// Launch an HTTP request, *without* storing the result in the database.
+    /// Useful when sending many requests that the user doesn't care to keep
+    /// in history, such as in a load test.
+    pub async fn send_unrecorded(self) -> Result<Exchange, RequestError> {
         let id = self.record.id;
 
         // Capture the rest of this method in a span
@@ -308,9 +587,12 @@ impl RequestTicket {
         // until this whole future is awaited
         let start_time = Utc::now();
         let result = async {
-            let response = self.client.execute(self.request).await?;
+            let (response, redirects) =
+                execute(&self.client, self.request, &self.redirects).await?;
             // Load the full response and convert it to our format
-            ResponseRecord::from_response(response).await
+            ResponseRecord::from_response(response, redirects)
+                .await
+                .map_err(anyhow::Error::from)
         }
         .await;
         let end_time = Utc::now();
@@ -318,17 +600,13 @@ impl RequestTicket {
         match result {
             Ok(response) => {
                 info!(status = response.status.as_u16(), "Response");
-                let exchange = Exchange {
+                Ok(Exchange {
                     id,
                     request: self.record,
                     response: Arc::new(response),
                     start_time,
                     end_time,
-                };
-
-                // Error here should *not* kill the request
-                let _ = database.insert_exchange(&exchange);
-                Ok(exchange)
+                })
             }
 
             // Attach metadata to the error and yeet it. Can't use map_err
@@ -337,7 +615,7 @@ impl RequestTicket {
                 request: self.record,
                 start_time,
                 end_time,
-                error: error.into(),
+                error,
             })
             .traced(),
         }
@@ -350,26 +628,141 @@ impl ResponseRecord {
     /// the response. Only fails if the response content fails to load.
     async fn from_response(
         response: Response,
+        redirects: Vec<Redirect>,
     ) -> reqwest::Result<ResponseRecord> {
         // Copy response metadata out first, because we need to move the
         // response to resolve content (not sure why...)
         let status = response.status();
-        let headers = response.headers().clone();
+        let version = response.version();
+        let mut headers = response.headers().clone();
 
         // Pre-resolve the content, so we get all the async work done
-        let body = response.bytes().await?.into();
+        let body = compression::decompress_response(
+            &mut headers,
+            response.bytes().await?,
+        )
+        .into();
 
         Ok(ResponseRecord {
             status,
+            version,
             headers,
             body,
+            redirects,
         })
     }
 }
 
+/// Send a request, following redirects according to the recipe's options.
+/// Returns the final response, and each redirect that led to it
+async fn execute(
+    client: &Client,
+    mut request: Request,
+    policy: &RedirectPolicy,
+) -> anyhow::Result<(Response, Vec<Redirect>)> {
+    let options = policy.options;
+    let mut redirects = Vec::new();
+    loop {
+        // Hang onto a copy in case we need to re-send it somewhere else.
+        // Streamed bodies can't be cloned, so those are never redirected
+        let next = if options.follow {
+            request.try_clone()
+        } else {
+            None
+        };
+        let url = request.url().clone();
+        let response = client.execute(request).await?;
+        let status = response.status();
+
+        let (Some(next), Some(location)) = (next, redirect_location(&response))
+        else {
+            return Ok((response, redirects));
+        };
+        if redirects.len() >= options.max {
+            bail!("Too many redirects (maximum is {})", options.max);
+        }
+        // Each hop has to be allowed, just like the original request
+        policy
+            .allowed_hosts
+            .check(&location)
+            .with_context(|| format!("Redirect to `{location}` blocked"))?;
+        info!(%status, %location, "Following redirect");
+        request = redirect_request(next, status, &location, policy);
+        redirects.push(Redirect {
+            url,
+            status,
+            location,
+        });
+    }
+}
+
+/// If this response is a followable redirect, get the URL it points to
+fn redirect_location(response: &Response) -> Option<Url> {
+    match response.status() {
+        StatusCode::MOVED_PERMANENTLY
+        | StatusCode::FOUND
+        | StatusCode::SEE_OTHER
+        | StatusCode::TEMPORARY_REDIRECT
+        | StatusCode::PERMANENT_REDIRECT => {}
+        _ => return None,
+    }
+    let location = response.headers().get(header::LOCATION)?.to_str().ok()?;
+    // Location can be relative to the current URL
+    response.url().join(location).ok()
+}
+
+/// Modify a copy of the previous request so it can be sent to the redirect
+/// location. This mirrors what browsers (and reqwest) do by default.
+fn redirect_request(
+    mut request: Request,
+    status: StatusCode,
+    location: &Url,
+    policy: &RedirectPolicy,
+) -> Request {
+    let switch_to_get = match status {
+        StatusCode::SEE_OTHER => true,
+        StatusCode::MOVED_PERMANENTLY | StatusCode::FOUND => {
+            !policy.options.preserve_method
+        }
+        _ => false,
+    };
+    if switch_to_get && request.method() != reqwest::Method::HEAD {
+        *request.method_mut() = reqwest::Method::GET;
+        *request.body_mut() = None;
+        for header in [
+            header::CONTENT_TYPE,
+            header::CONTENT_LENGTH,
+            header::CONTENT_ENCODING,
+            header::TRANSFER_ENCODING,
+        ] {
+            request.headers_mut().remove(header);
+        }
+    }
+
+    // Don't leak credentials to a different origin. This includes whatever
+    // header the recipe's authentication was sent in, e.g. an API key
+    if request.url().origin() != location.origin() {
+        for header in [
+            header::AUTHORIZATION,
+            header::PROXY_AUTHORIZATION,
+            header::COOKIE,
+            header::WWW_AUTHENTICATE,
+        ]
+        .iter()
+        .chain(&policy.auth_header)
+        {
+            request.headers_mut().remove(header);
+        }
+    }
+
+    *request.url_mut() = location.clone();
+    request
+}
+
 /// Render steps for individual pieces of a recipe
 impl Recipe {
-    /// Render base URL, *excluding* query params
+    /// Render base URL, *excluding* query params. If the URL isn't absolute,
+    /// it's joined onto the profile or collection's `base_url`
     async fn render_url(
         &self,
         template_context: &TemplateContext,
@@ -379,10 +772,36 @@ impl Recipe {
             .render_string(template_context)
             .await
             .context("Error rendering URL")?;
+        let url = match (url.parse::<Url>(), template_context.base_url()) {
+            (Ok(url), _) => return Ok(url),
+            (Err(_), Some(base_url)) => {
+                let base_url = base_url
+                    .render_string(template_context)
+                    .await
+                    .context("Error rendering base URL")?;
+                join_url(&base_url, &url)
+            }
+            (Err(_), None) => url,
+        };
         url.parse::<Url>()
             .with_context(|| format!("Invalid URL: `{url}`"))
     }
 
+    /// Render the label to store with the request, if the recipe has one
+    async fn render_label(
+        &self,
+        template_context: &TemplateContext,
+    ) -> anyhow::Result<Option<String>> {
+        let Some(label) = &self.label else {
+            return Ok(None);
+        };
+        let label = label
+            .render_string(template_context)
+            .await
+            .context("Error rendering label")?;
+        Ok(Some(label))
+    }
+
     /// Render query key=value params
     async fn render_query(
         &self,
@@ -518,10 +937,135 @@ impl Recipe {
                     .context("Error rendering bearer token")?;
                 Ok(Some(Authentication::Bearer(token)))
             }
+            Some(Authentication::ApiKey {
+                name,
+                value,
+                location,
+            }) => {
+                let value = value
+                    .render_string(template_context)
+                    .await
+                    .context("Error rendering API key")?;
+                Ok(Some(Authentication::ApiKey {
+                    name: name.clone(),
+                    value,
+                    location: *location,
+                }))
+            }
+            Some(Authentication::Command { command }) => {
+                let command = future::try_join_all(
+                    command
+                        .iter()
+                        .map(|arg| arg.render_string(template_context)),
+                )
+                .await
+                .context("Error rendering authentication command")?;
+                Ok(Some(Authentication::Command { command }))
+            }
             None => Ok(None),
         }
     }
 
+    /// Render TLS options, combining the recipe's options with the selected
+    /// profile's, and load all referenced files. Return `None` if neither has
+    /// any TLS options.
+    async fn render_tls(
+        &self,
+        template_context: &TemplateContext,
+    ) -> anyhow::Result<Option<TlsFiles>> {
+        let profile_tls = template_context
+            .profile()
+            .and_then(|profile| profile.tls.as_ref());
+        let Some(tls) = TlsOptions::merge(self.tls.as_ref(), profile_tls)
+        else {
+            return Ok(None);
+        };
+        Ok(Some(tls.render(template_context).await?.load().await?))
+    }
+
+    /// Render the host to connect to in place of the URL's host
+    async fn render_connect_to(
+        &self,
+        template_context: &TemplateContext,
+    ) -> anyhow::Result<Option<String>> {
+        let Some(connect_to) = &self.connect_to else {
+            return Ok(None);
+        };
+        let host = connect_to
+            .render_string(template_context)
+            .await
+            .context("Error rendering `connect_to`")?;
+        Ok(Some(host))
+    }
+
+    /// Render DNS overrides. The recipe's mapping is merged on top of the
+    /// profile's, so recipe entries win for the same hostname
+    async fn render_resolve(
+        &self,
+        template_context: &TemplateContext,
+    ) -> anyhow::Result<Vec<(String, SocketAddr)>> {
+        let mut resolve: IndexMap<&str, &Template> = template_context
+            .profile()
+            .into_iter()
+            .flat_map(|profile| &profile.resolve)
+            .map(|(host, address)| (host.as_str(), address))
+            .collect();
+        resolve.extend(
+            self.resolve
+                .iter()
+                .map(|(host, address)| (host.as_str(), address)),
+        );
+        let iter = resolve.into_iter().map(|(host, address)| async move {
+            let address = address
+                .render_string(template_context)
+                .await
+                .with_context(|| {
+                    format!("Error rendering `resolve` address for `{host}`")
+                })?;
+            let address = parse_resolve_address(&address)?;
+            anyhow::Ok((host.to_owned(), address))
+        });
+        future::try_join_all(iter).await
+    }
+
+    /// Render the Unix socket path. The recipe's takes precedence over the
+    /// profile's
+    async fn render_unix_socket(
+        &self,
+        template_context: &TemplateContext,
+    ) -> anyhow::Result<Option<PathBuf>> {
+        let profile_socket = template_context
+            .profile()
+            .and_then(|profile| profile.unix_socket.as_ref());
+        let Some(path) = self.unix_socket.as_ref().or(profile_socket) else {
+            return Ok(None);
+        };
+        let path = path
+            .render_string(template_context)
+            .await
+            .context("Error rendering `unix_socket`")?;
+        Ok(Some(path.into()))
+    }
+
+    /// Render the path of each `save` rule
+    async fn render_save(
+        &self,
+        template_context: &TemplateContext,
+    ) -> anyhow::Result<Vec<SaveRule<String>>> {
+        let iter = self.save.iter().map(|rule| async move {
+            let path = rule
+                .path
+                .render_string(template_context)
+                .await
+                .context("Error rendering `save` path")?;
+            anyhow::Ok(SaveRule {
+                content_type: rule.content_type.clone(),
+                path,
+            })
+        });
+        future::try_join_all(iter).await
+    }
+
     /// Render request body
     async fn render_body(
         &self,
@@ -531,6 +1075,10 @@ impl Recipe {
         let Some(body) = &self.body else {
             return Ok(None);
         };
+        // A body override replaces the recipe body entirely
+        if let Some(bytes) = &options.body {
+            return Ok(Some(RenderedBody::Raw(bytes.clone())));
+        }
 
         let rendered = match body {
             RecipeBody::Raw(body) => RenderedBody::Raw(
@@ -588,14 +1136,85 @@ impl Recipe {
 }
 
 impl Authentication<String> {
-    fn apply(self, builder: RequestBuilder) -> RequestBuilder {
+    /// Get the header that this authentication is sent in, if any
+    fn header_name(&self) -> Option<HeaderName> {
         match self {
+            Authentication::Basic { .. }
+            | Authentication::Bearer(_)
+            | Authentication::Command { .. } => Some(header::AUTHORIZATION),
+            Authentication::ApiKey {
+                name,
+                location: ApiKeyLocation::Header,
+                ..
+            } => HeaderName::try_from(name.as_str()).ok(),
+            Authentication::ApiKey {
+                location: ApiKeyLocation::Query,
+                ..
+            } => None,
+        }
+    }
+
+    /// Add authentication to the request. For [Authentication::Command], this
+    /// is where the helper command is executed.
+    async fn apply(
+        self,
+        builder: RequestBuilder,
+    ) -> anyhow::Result<RequestBuilder> {
+        let builder = match self {
             Authentication::Basic { username, password } => {
                 builder.basic_auth(username, password)
             }
             Authentication::Bearer(token) => builder.bearer_auth(token),
-        }
+            Authentication::ApiKey {
+                name,
+                value,
+                location: ApiKeyLocation::Header,
+            } => builder.header(name, value),
+            Authentication::ApiKey {
+                name,
+                value,
+                location: ApiKeyLocation::Query,
+            } => builder.query(&[(name, value)]),
+            Authentication::Command { command } => {
+                let value = run_authentication_command(&command).await?;
+                builder.header(header::AUTHORIZATION, value)
+            }
+        };
+        Ok(builder)
+    }
+}
+
+/// Execute an authentication helper command and return its trimmed stdout,
+/// to be used as the `Authorization` header
+async fn run_authentication_command(
+    command: &[String],
+) -> anyhow::Result<String> {
+    let [program, args @ ..] = command else {
+        bail!("Authentication command is empty");
+    };
+    let output = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .kill_on_drop(true)
+        .output()
+        .await
+        .with_context(|| {
+            format!("Error executing authentication command {command:?}")
+        })?;
+    if !output.status.success() {
+        bail!(
+            "Authentication command {command:?} failed with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    let value = String::from_utf8(output.stdout)
+        .context("Authentication command output is not valid UTF-8")?;
+    let value = value.trim();
+    if value.is_empty() {
+        bail!("Authentication command {command:?} printed nothing");
     }
+    Ok(value.to_owned())
 }
 
 impl RecipeBody {
@@ -719,19 +1338,68 @@ fn trim_bytes(bytes: &mut Vec<u8>, f: impl Fn(u8) -> bool) {
     }
 }
 
+/// Write the response body to the first `save` rule that matches the
+/// response's content type. Parent directories are created as needed
+async fn save_body(
+    rules: &[SaveRule<String>],
+    exchange: &Exchange,
+) -> anyhow::Result<()> {
+    let response = &exchange.response;
+    let Some(content_type) = response
+        .headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+    else {
+        return Ok(());
+    };
+    let Some(rule) = rules.iter().find(|rule| rule.matches(content_type))
+    else {
+        return Ok(());
+    };
+
+    let path = Path::new(&rule.path);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .await
+            .with_context(|| format!("Error creating directory {parent:?}"))?;
+    }
+    fs::write(path, response.body.bytes())
+        .await
+        .with_context(|| format!("Error saving response body to {path:?}"))?;
+    info!(?path, "Saved response body");
+    Ok(())
+}
+
+/// Join a relative URL onto a base URL. Unlike [Url::join], the base's path
+/// is always kept, so `https://example.com/v1` + `/users` is
+/// `https://example.com/v1/users`. Exactly one slash separates the two.
+fn join_url(base_url: &str, path: &str) -> String {
+    let base_url = base_url.trim_end_matches('/');
+    if path.is_empty() || path.starts_with(['?', '#']) {
+        format!("{base_url}{path}")
+    } else {
+        format!("{base_url}/{}", path.trim_start_matches('/'))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::{
         collection::{
             self, Authentication, Chain, ChainSource, Collection, Profile,
+            RedirectOptions,
+        },
+        test_util::{
+            assert_err, by_id, header_map, temp_dir, Factory, TempDir,
         },
-        test_util::{by_id, header_map, Factory},
     };
     use indexmap::{indexmap, IndexMap};
+    use itertools::Itertools;
+    use mockito::Matcher;
     use pretty_assertions::assert_eq;
     use regex::Regex;
-    use reqwest::{Body, Method, StatusCode};
+    use reqwest::{Body, Method, StatusCode, Version};
     use rstest::{fixture, rstest};
     use serde_json::json;
 
@@ -829,10 +1497,194 @@ mod tests {
                 url: expected_url,
                 body: Some(Vec::from(expected_body).into()),
                 headers: expected_headers,
+                template_values: Default::default(),
+                label: None,
             }
         );
     }
 
+    /// Overrides and chain values used during the build should be recorded on
+    /// the request, with sensitive chains masked
+    #[rstest]
+    #[tokio::test]
+    async fn test_build_template_values(http_engine: HttpEngine) {
+        let public_chain = Chain {
+            id: "public".into(),
+            source: ChainSource::command(["echo", "-n", "abc"]),
+            ..Chain::factory(())
+        };
+        let secret_chain = Chain {
+            id: "secret".into(),
+            source: ChainSource::command(["echo", "-n", "hunter2"]),
+            sensitive: true,
+            ..Chain::factory(())
+        };
+        let template_context = TemplateContext {
+            collection: Collection {
+                chains: by_id([public_chain, secret_chain]),
+                ..Collection::factory(())
+            },
+            overrides: indexmap! {"host".into() => "http://localhost".into()},
+            ..TemplateContext::factory(())
+        };
+        let recipe = Recipe {
+            url: "{{host}}/{{chains.public}}".into(),
+            headers: indexmap! {"Token".into() => "{{chains.secret}}".into()},
+            ..Recipe::factory(())
+        };
+
+        let seed = RequestSeed::new(recipe, BuildOptions::default());
+        let ticket = http_engine.build(seed, &template_context).await.unwrap();
+
+        // Keys are rendered concurrently, so order isn't guaranteed
+        let values = ticket
+            .record
+            .template_values
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .sorted()
+            .collect_vec();
+        assert_eq!(
+            values,
+            [
+                ("chains.public", "abc"),
+                ("chains.secret", "<sensitive>"),
+                ("host", "http://localhost"),
+            ]
+        );
+    }
+
+    /// The recipe's label template should be rendered onto the record
+    #[rstest]
+    #[case::none(None, None)]
+    #[case::label(Some("Get user {{user_id}}"), Some("Get user 3"))]
+    #[tokio::test]
+    async fn test_build_label(
+        http_engine: HttpEngine,
+        #[case] label: Option<&str>,
+        #[case] expected: Option<&str>,
+    ) {
+        let template_context = TemplateContext {
+            overrides: indexmap! {"user_id".into() => "3".into()},
+            ..TemplateContext::factory(())
+        };
+        let recipe = Recipe {
+            label: label.map(Template::from),
+            ..Recipe::factory(())
+        };
+
+        let seed = RequestSeed::new(recipe, BuildOptions::default());
+        let ticket = http_engine.build(seed, &template_context).await.unwrap();
+        assert_eq!(ticket.record.label.as_deref(), expected);
+    }
+
+    /// Relative URLs are joined onto the profile's base URL, or the
+    /// collection's if the profile doesn't have one
+    #[rstest]
+    #[case::profile(
+        Some("http://profile/v1"),
+        None,
+        "/users",
+        "http://profile/v1/users"
+    )]
+    #[case::collection(
+        None,
+        Some("http://collection/"),
+        "users",
+        "http://collection/users"
+    )]
+    #[case::precedence(
+        Some("http://profile"),
+        Some("http://collection"),
+        "users?id=1",
+        "http://profile/users?id=1"
+    )]
+    #[case::query(
+        Some("http://profile/v1"),
+        None,
+        "?id=1",
+        "http://profile/v1?id=1"
+    )]
+    #[case::absolute(
+        Some("http://profile"),
+        None,
+        "http://other/users",
+        "http://other/users"
+    )]
+    #[tokio::test]
+    async fn test_build_url_base_url(
+        http_engine: HttpEngine,
+        #[case] profile_base_url: Option<&str>,
+        #[case] collection_base_url: Option<&str>,
+        #[case] url: &str,
+        #[case] expected: &str,
+    ) {
+        let profile = Profile {
+            base_url: profile_base_url.map(Template::from),
+            ..Profile::factory(())
+        };
+        let template_context = TemplateContext {
+            selected_profile: Some(profile.id.clone()),
+            collection: Collection {
+                base_url: collection_base_url.map(Template::from),
+                profiles: by_id([profile]),
+                ..Collection::factory(())
+            },
+            ..TemplateContext::factory(())
+        };
+        let recipe = Recipe {
+            url: url.into(),
+            ..Recipe::factory(())
+        };
+
+        let seed = RequestSeed::new(recipe, BuildOptions::default());
+        let url = http_engine
+            .build_url(seed, &template_context)
+            .await
+            .unwrap();
+        assert_eq!(url.as_str(), expected);
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_build_url_relative(http_engine: HttpEngine) {
+        let recipe = Recipe {
+            url: "/users".into(),
+            ..Recipe::factory(())
+        };
+        let seed = RequestSeed::new(recipe, BuildOptions::default());
+        assert_err!(
+            http_engine
+                .build_url(seed, &TemplateContext::factory(()))
+                .await
+                .map_err(|error| error.error),
+            "Invalid URL: `/users`"
+        );
+    }
+
+    /// Recipe variables are available to all of the recipe's templates
+    #[rstest]
+    #[tokio::test]
+    async fn test_build_variables(
+        http_engine: HttpEngine,
+        template_context: TemplateContext,
+    ) {
+        let recipe = Recipe {
+            url: "{{host}}/users/{{vars.user}}".into(),
+            query: vec![("user".into(), "{{vars.user}}".into())],
+            variables: indexmap! {"user".into() => "user-{{user_id}}".into()},
+            ..Recipe::factory(())
+        };
+
+        let seed = RequestSeed::new(recipe, BuildOptions::default());
+        let url = http_engine
+            .build_url(seed, &template_context)
+            .await
+            .unwrap();
+
+        assert_eq!(url.as_str(), "http://localhost/users/user-1?user=user-1");
+    }
+
     /// Test building just a URL. Should include query params, but headers/body
     /// should *not* be built
     #[rstest]
@@ -913,6 +1765,12 @@ mod tests {
         "Basic dXNlcjo="
     )]
     #[case::bearer(Authentication::Bearer("{{token}}".into()), "Bearer token!")]
+    #[case::command(
+        Authentication::Command {
+            command: vec!["echo".into(), "Negotiate {{token}}".into()],
+        },
+        "Negotiate token!"
+    )]
     #[tokio::test]
     async fn test_authentication(
         http_engine: HttpEngine,
@@ -963,10 +1821,83 @@ mod tests {
                     ("authorization", expected_header)
                 ]),
                 body: None,
+                template_values: Default::default(),
+                label: None,
             }
         );
     }
 
+    /// Authentication helper commands must succeed and print something
+    #[rstest]
+    #[case::empty(&[], "Authentication command is empty")]
+    #[case::failed(&["false"], "Authentication command [\"false\"] failed")]
+    #[case::no_output(
+        &["true"],
+        "Authentication command [\"true\"] printed nothing"
+    )]
+    #[tokio::test]
+    async fn test_authentication_command_error(
+        http_engine: HttpEngine,
+        template_context: TemplateContext,
+        #[case] command: &[&str],
+        #[case] expected_error: &str,
+    ) {
+        let recipe = Recipe {
+            authentication: Some(Authentication::Command {
+                command: command.iter().copied().map(Template::from).collect(),
+            }),
+            ..Recipe::factory(())
+        };
+
+        let seed = RequestSeed::new(recipe, BuildOptions::default());
+        assert_err!(
+            http_engine
+                .build(seed, &template_context)
+                .await
+                .map_err(|error| error.error),
+            expected_error
+        );
+    }
+
+    /// API keys can go in either a header or the query
+    #[rstest]
+    #[case::header(
+        ApiKeyLocation::Header,
+        "http://localhost/url",
+        &[("x-api-key", "hunter2")],
+    )]
+    #[case::query(
+        ApiKeyLocation::Query,
+        "http://localhost/url?x-api-key=hunter2",
+        &[],
+    )]
+    #[tokio::test]
+    async fn test_authentication_api_key(
+        http_engine: HttpEngine,
+        template_context: TemplateContext,
+        #[case] location: ApiKeyLocation,
+        #[case] expected_url: &str,
+        #[case] expected_headers: &[(&str, &str)],
+    ) {
+        let recipe = Recipe {
+            authentication: Some(Authentication::ApiKey {
+                name: "x-api-key".into(),
+                value: "{{token}}".into(),
+                location,
+            }),
+            ..Recipe::factory(())
+        };
+
+        let seed = RequestSeed::new(recipe, BuildOptions::default());
+        let ticket = http_engine.build(seed, &template_context).await.unwrap();
+
+        assert_eq!(ticket.record.url.as_str(), expected_url);
+        assert_eq!(
+            ticket.record.headers,
+            header_map(expected_headers.iter().copied())
+        );
+    }
+
     /// Test each possible type of body. Raw bodies are covered by
     /// [test_build_request]. This seems redundant with [test_build_body], but
     /// we need this to test that the `content-type` header is set correctly.
@@ -1078,6 +2009,9 @@ mod tests {
                         .into_iter()
                         .chain(extra_headers.iter().copied())
                 ),
+                // Recorded chain values are covered by
+                // test_build_template_values
+                template_values: ticket.record.template_values.clone(),
                 ..RequestRecord::factory((
                     Some(
                         template_context.collection.first_profile_id().clone()
@@ -1124,6 +2058,8 @@ mod tests {
                 disabled_headers: vec![1],
                 disabled_query_parameters: vec![2],
                 disabled_form_fields: vec![1],
+                allow_any_host: false,
+                body: None,
             },
         );
         let ticket = http_engine.build(seed, &template_context).await.unwrap();
@@ -1143,10 +2079,46 @@ mod tests {
                     ("content-type", "application/x-www-form-urlencoded"),
                 ]),
                 body: Some(b"user_id=1".as_slice().into()),
+                template_values: Default::default(),
+                label: None,
             }
         );
     }
 
+    /// Test overriding the body with edited bytes. The recipe's content type
+    /// should still be applied
+    #[rstest]
+    #[tokio::test]
+    async fn test_build_options_body(
+        http_engine: HttpEngine,
+        template_context: TemplateContext,
+    ) {
+        let recipe = Recipe {
+            body: Some(RecipeBody::Json(
+                json!({"group_id": "{{group_id}}"}).into(),
+            )),
+            ..Recipe::factory(())
+        };
+
+        let seed = RequestSeed::new(
+            recipe,
+            BuildOptions {
+                body: Some(br#"{"group_id":"4"}"#.as_slice().into()),
+                ..Default::default()
+            },
+        );
+        let ticket = http_engine.build(seed, &template_context).await.unwrap();
+
+        assert_eq!(
+            ticket.record.headers.get("content-type"),
+            Some(&HeaderValue::from_static("application/json"))
+        );
+        assert_eq!(
+            ticket.record.body.as_deref(),
+            Some(br#"{"group_id":"4"}"#.as_slice())
+        );
+    }
+
     /// Test launching a built request
     #[rstest]
     #[tokio::test]
@@ -1172,7 +2144,11 @@ mod tests {
         // Build+send the request
         let seed = RequestSeed::new(recipe, BuildOptions::default());
         let ticket = http_engine.build(seed, &template_context).await.unwrap();
-        let exchange = ticket.send(&template_context.database).await.unwrap();
+        let exchange = ticket
+            .send(&template_context.database)
+            .await
+            .unwrap()
+            .exchange;
 
         // Cheat on this one, because we don't know exactly when the server
         // resolved it
@@ -1187,18 +2163,534 @@ mod tests {
             *exchange.response,
             ResponseRecord {
                 status: StatusCode::OK,
+                version: Version::HTTP_11,
                 headers: header_map([
                     ("connection", "close"),
                     ("content-length", "6"),
                     ("date", date_header),
                 ]),
-                body: ResponseBody::new(b"hello!".as_slice().into())
+                body: ResponseBody::new(b"hello!".as_slice().into()),
+                redirects: Vec::new(),
             }
         );
 
         mock.assert();
     }
 
+    /// `connect_to` sends the request to a different host, but the URL's host
+    /// is still used for the `Host` header
+    #[rstest]
+    #[tokio::test]
+    async fn test_connect_to(
+        http_engine: HttpEngine,
+        template_context: TemplateContext,
+    ) {
+        let mut server = mockito::Server::new_async().await;
+        let port = server
+            .host_with_port()
+            .rsplit_once(':')
+            .unwrap()
+            .1
+            .to_owned();
+        let mock = server
+            .mock("GET", "/get")
+            .match_header("host", format!("fish.test:{port}").as_str())
+            .with_status(200)
+            .create_async()
+            .await;
+
+        let recipe = Recipe {
+            url: format!("http://fish.test:{port}/get").as_str().into(),
+            connect_to: Some("127.0.0.1".into()),
+            ..Recipe::factory(())
+        };
+        let seed = RequestSeed::new(recipe, BuildOptions::default());
+        let ticket = http_engine.build(seed, &template_context).await.unwrap();
+        let exchange = ticket
+            .send(&template_context.database)
+            .await
+            .unwrap()
+            .exchange;
+
+        assert_eq!(exchange.response.status, StatusCode::OK);
+        mock.assert();
+    }
+
+    /// `resolve` overrides DNS for the mapped hostname, and recipe entries
+    /// override profile entries
+    #[rstest]
+    #[tokio::test]
+    async fn test_resolve(http_engine: HttpEngine) {
+        let mut server = mockito::Server::new_async().await;
+        let port = server
+            .host_with_port()
+            .rsplit_once(':')
+            .unwrap()
+            .1
+            .to_owned();
+        let mock = server
+            .mock("GET", "/get")
+            .match_header("host", format!("fish.test:{port}").as_str())
+            .with_status(200)
+            .create_async()
+            .await;
+
+        let profile = Profile {
+            resolve: indexmap! {"fish.test".into() => "10.0.0.1".into()},
+            ..Profile::factory(())
+        };
+        let recipe = Recipe {
+            url: format!("http://fish.test:{port}/get").as_str().into(),
+            resolve: indexmap! {"fish.test".into() => "127.0.0.1".into()},
+            ..Recipe::factory(())
+        };
+        let template_context = TemplateContext {
+            selected_profile: Some(profile.id.clone()),
+            collection: Collection {
+                profiles: by_id([profile]),
+                ..Collection::factory(())
+            },
+            ..TemplateContext::factory(())
+        };
+        let seed = RequestSeed::new(recipe, BuildOptions::default());
+        let ticket = http_engine.build(seed, &template_context).await.unwrap();
+        let exchange = ticket
+            .send(&template_context.database)
+            .await
+            .unwrap()
+            .exchange;
+
+        assert_eq!(exchange.response.status, StatusCode::OK);
+        mock.assert();
+    }
+
+    #[rstest]
+    #[case::ip("127.0.0.1", Ok("127.0.0.1:0"))]
+    #[case::ip_port("127.0.0.1:3000", Ok("127.0.0.1:3000"))]
+    #[case::ipv6("::1", Ok("[::1]:0"))]
+    #[case::ipv6_port("[::1]:3000", Ok("[::1]:3000"))]
+    #[case::hostname("localhost", Err("Invalid address `localhost`"))]
+    fn test_parse_resolve_address(
+        #[case] address: &str,
+        #[case] expected: Result<&str, &str>,
+    ) {
+        let result = parse_resolve_address(address);
+        match expected {
+            Ok(expected) => {
+                assert_eq!(result.unwrap(), expected.parse().unwrap())
+            }
+            Err(expected) => assert_err!(result, expected),
+        }
+    }
+
+    /// Requests can be sent over a Unix domain socket. The URL is still used
+    /// for the path and `Host` header
+    #[cfg(unix)]
+    #[rstest]
+    #[tokio::test]
+    async fn test_unix_socket(
+        http_engine: HttpEngine,
+        template_context: TemplateContext,
+        temp_dir: TempDir,
+    ) {
+        use tokio::{
+            io::{AsyncReadExt, AsyncWriteExt},
+            net::UnixListener,
+        };
+
+        let path = temp_dir.join("server.sock");
+        let listener = UnixListener::bind(&path).unwrap();
+        // Minimal server: read one request, and echo its first line back
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buffer = [0; 1024];
+            while !request.ends_with(b"\r\n\r\n") {
+                let n = stream.read(&mut buffer).await.unwrap();
+                request.extend_from_slice(&buffer[..n]);
+            }
+            let request = String::from_utf8(request).unwrap();
+            let line = request.lines().next().unwrap();
+            let response = format!(
+                "HTTP/1.1 200 OK\r\ncontent-length: {}\r\n\r\n{line}",
+                line.len()
+            );
+            stream.write_all(response.as_bytes()).await.unwrap();
+        });
+
+        let recipe = Recipe {
+            url: "http://localhost/containers/json".into(),
+            unix_socket: Some(path.to_str().unwrap().into()),
+            ..Recipe::factory(())
+        };
+        let seed = RequestSeed::new(recipe, BuildOptions::default());
+        let ticket = http_engine.build(seed, &template_context).await.unwrap();
+        let exchange = ticket
+            .send(&template_context.database)
+            .await
+            .unwrap()
+            .exchange;
+        server.await.unwrap();
+
+        assert_eq!(exchange.response.status, StatusCode::OK);
+        assert_eq!(
+            exchange.response.body.bytes(),
+            b"GET /containers/json HTTP/1.1"
+        );
+    }
+
+    /// The body is saved to the path of the first rule that matches the
+    /// response's content type
+    #[rstest]
+    #[tokio::test]
+    async fn test_save(
+        http_engine: HttpEngine,
+        template_context: TemplateContext,
+        temp_dir: TempDir,
+    ) {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/report")
+            .with_status(200)
+            .with_header("content-type", "application/pdf; charset=binary")
+            .with_body("%PDF")
+            .create_async()
+            .await;
+
+        let dir = temp_dir.to_str().unwrap();
+        let rule = |content_type: &str, path: &str| SaveRule {
+            content_type: content_type.into(),
+            path: format!("{dir}/{path}").into(),
+        };
+        let recipe = Recipe {
+            url: format!("{}/report", server.url()).into(),
+            save: vec![
+                rule("text/*", "report.txt"),
+                rule("application/PDF", "{{user_id}}/report.pdf"),
+                rule("*/*", "report"),
+            ],
+            ..Recipe::factory(())
+        };
+        let seed = RequestSeed::new(recipe, BuildOptions::default());
+        let ticket = http_engine.build(seed, &template_context).await.unwrap();
+        let sent = ticket.send(&template_context.database).await.unwrap();
+
+        mock.assert();
+        assert!(sent.save_error.is_none());
+        assert_eq!(
+            std::fs::read_to_string(temp_dir.join("1/report.pdf")).unwrap(),
+            "%PDF"
+        );
+        assert!(!temp_dir.join("report.txt").exists());
+        assert!(!temp_dir.join("report").exists());
+    }
+
+    /// A failure to save the body doesn't fail the request, but is returned
+    /// with the exchange
+    #[rstest]
+    #[tokio::test]
+    async fn test_save_error(
+        http_engine: HttpEngine,
+        template_context: TemplateContext,
+        temp_dir: TempDir,
+    ) {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/report")
+            .with_status(200)
+            .with_header("content-type", "text/plain")
+            .with_body("report")
+            .create_async()
+            .await;
+
+        // Parent of the save path is a file, so it can't be created
+        std::fs::write(temp_dir.join("file"), "").unwrap();
+        let recipe = Recipe {
+            url: format!("{}/report", server.url()).into(),
+            save: vec![SaveRule {
+                content_type: "*/*".into(),
+                path: format!("{}/file/report.txt", temp_dir.to_str().unwrap())
+                    .into(),
+            }],
+            ..Recipe::factory(())
+        };
+        let seed = RequestSeed::new(recipe, BuildOptions::default());
+        let ticket = http_engine.build(seed, &template_context).await.unwrap();
+        let sent = ticket.send(&template_context.database).await.unwrap();
+
+        assert_eq!(sent.exchange.response.status, StatusCode::OK);
+        assert_err!(
+            Err::<(), _>(sent.save_error.unwrap()),
+            "Error creating directory"
+        );
+    }
+
+    /// Redirects are followed, with the method changed according to the
+    /// status code and options, and each hop is recorded
+    #[rstest]
+    #[case::moved_permanently(301, false, "GET")]
+    #[case::moved_permanently_preserve(301, true, "POST")]
+    #[case::found(302, false, "GET")]
+    #[case::see_other(303, true, "GET")]
+    #[case::temporary_redirect(307, false, "POST")]
+    #[case::permanent_redirect(308, false, "POST")]
+    #[tokio::test]
+    async fn test_redirects(
+        http_engine: HttpEngine,
+        template_context: TemplateContext,
+        #[case] status: usize,
+        #[case] preserve_method: bool,
+        #[case] expected_method: &str,
+    ) {
+        let mut server = mockito::Server::new_async().await;
+        let url = server.url();
+        let redirect_mock = server
+            .mock("POST", "/old")
+            .with_status(status)
+            .with_header("location", "/new")
+            .create_async()
+            .await;
+        let mock = server
+            .mock(expected_method, "/new")
+            .with_status(200)
+            .create_async()
+            .await;
+
+        let recipe = Recipe {
+            method: collection::Method::Post,
+            url: format!("{url}/old").as_str().into(),
+            body: Some("data".into()),
+            redirects: RedirectOptions {
+                preserve_method,
+                ..RedirectOptions::default()
+            },
+            ..Recipe::factory(())
+        };
+        let seed = RequestSeed::new(recipe, BuildOptions::default());
+        let ticket = http_engine.build(seed, &template_context).await.unwrap();
+        let exchange = ticket
+            .send(&template_context.database)
+            .await
+            .unwrap()
+            .exchange;
+
+        redirect_mock.assert();
+        mock.assert();
+        assert_eq!(exchange.response.status, StatusCode::OK);
+        assert_eq!(
+            exchange.response.redirects,
+            vec![Redirect {
+                url: format!("{url}/old").parse().unwrap(),
+                status: StatusCode::from_u16(status as u16).unwrap(),
+                location: format!("{url}/new").parse().unwrap(),
+            }]
+        );
+    }
+
+    /// Redirects can be disabled or capped
+    #[rstest]
+    #[tokio::test]
+    async fn test_redirects_disabled(
+        http_engine: HttpEngine,
+        template_context: TemplateContext,
+    ) {
+        let mut server = mockito::Server::new_async().await;
+        let url = server.url();
+        server
+            .mock("GET", "/old")
+            .with_status(302)
+            .with_header("location", "/old")
+            .create_async()
+            .await;
+        let send = |redirects: RedirectOptions| {
+            let recipe = Recipe {
+                url: format!("{url}/old").as_str().into(),
+                redirects,
+                ..Recipe::factory(())
+            };
+            let seed = RequestSeed::new(recipe, BuildOptions::default());
+            let http_engine = &http_engine;
+            let template_context = &template_context;
+            async move {
+                http_engine
+                    .build(seed, template_context)
+                    .await
+                    .unwrap()
+                    .send(&template_context.database)
+                    .await
+            }
+        };
+
+        // Not followed, so we get the redirect response itself
+        let exchange = send(RedirectOptions {
+            follow: false,
+            ..RedirectOptions::default()
+        })
+        .await
+        .unwrap()
+        .exchange;
+        assert_eq!(exchange.response.status, StatusCode::FOUND);
+        assert_eq!(exchange.response.redirects, vec![]);
+
+        // This redirects to itself forever
+        assert_err!(
+            send(RedirectOptions {
+                max: 3,
+                ..RedirectOptions::default()
+            })
+            .await,
+            "Too many redirects (maximum is 3)"
+        );
+    }
+
+    /// Redirects to a host outside the profile's `allowed_hosts` are blocked,
+    /// even though the original host was allowed
+    #[rstest]
+    #[tokio::test]
+    async fn test_redirect_host_not_allowed(http_engine: HttpEngine) {
+        let mut server = mockito::Server::new_async().await;
+        let url = server.url();
+        let location = format!("{}/new", url.replace("127.0.0.1", "localhost"));
+        server
+            .mock("GET", "/old")
+            .with_status(302)
+            .with_header("location", &location)
+            .create_async()
+            .await;
+        let mock = server.mock("GET", "/new").expect(0).create_async().await;
+
+        let profile = Profile {
+            allowed_hosts: vec!["127.0.0.1".into()],
+            ..Profile::factory(())
+        };
+        let profile_id = profile.id.clone();
+        let template_context = TemplateContext {
+            collection: Collection {
+                profiles: by_id([profile]),
+                ..Collection::factory(())
+            },
+            selected_profile: Some(profile_id),
+            ..TemplateContext::factory(())
+        };
+        let recipe = Recipe {
+            url: format!("{url}/old").as_str().into(),
+            ..Recipe::factory(())
+        };
+        let seed = RequestSeed::new(recipe, BuildOptions::default());
+        let ticket = http_engine.build(seed, &template_context).await.unwrap();
+        assert_err!(
+            ticket.send(&template_context.database).await,
+            "Host `localhost` is not in the allowed hosts"
+        );
+        mock.assert();
+    }
+
+    /// The authentication header is dropped when a redirect goes to a
+    /// different origin, even if it's not `Authorization`
+    #[rstest]
+    #[tokio::test]
+    async fn test_redirect_strip_authentication(
+        http_engine: HttpEngine,
+        template_context: TemplateContext,
+    ) {
+        let mut server = mockito::Server::new_async().await;
+        let mut other_server = mockito::Server::new_async().await;
+        let redirect_mock = server
+            .mock("GET", "/old")
+            .match_header("x-api-key", "hunter2")
+            .with_status(302)
+            .with_header("location", &format!("{}/new", other_server.url()))
+            .create_async()
+            .await;
+        let mock = other_server
+            .mock("GET", "/new")
+            .match_header("x-api-key", Matcher::Missing)
+            .with_status(200)
+            .create_async()
+            .await;
+
+        let recipe = Recipe {
+            url: format!("{}/old", server.url()).as_str().into(),
+            authentication: Some(Authentication::ApiKey {
+                name: "x-api-key".into(),
+                value: "{{token}}".into(),
+                location: ApiKeyLocation::Header,
+            }),
+            ..Recipe::factory(())
+        };
+        let seed = RequestSeed::new(recipe, BuildOptions::default());
+        let ticket = http_engine.build(seed, &template_context).await.unwrap();
+        let exchange = ticket
+            .send(&template_context.database)
+            .await
+            .unwrap()
+            .exchange;
+
+        redirect_mock.assert();
+        mock.assert();
+        assert_eq!(exchange.response.status, StatusCode::OK);
+    }
+
+    /// The host allowlist is checked before anything else is rendered, so
+    /// auth helpers don't run for a request that's going to be rejected
+    #[rstest]
+    #[tokio::test]
+    async fn test_host_not_allowed(http_engine: HttpEngine, temp_dir: TempDir) {
+        let marker = temp_dir.join("ran");
+        let profile = Profile {
+            allowed_hosts: vec!["api.example.com".into()],
+            ..Profile::factory(())
+        };
+        let profile_id = profile.id.clone();
+        let template_context = TemplateContext {
+            collection: Collection {
+                profiles: by_id([profile]),
+                ..Collection::factory(())
+            },
+            selected_profile: Some(profile_id),
+            ..TemplateContext::factory(())
+        };
+        let recipe = Recipe {
+            url: "http://localhost/url".into(),
+            authentication: Some(Authentication::Command {
+                command: vec!["touch".into(), marker.to_str().unwrap().into()],
+            }),
+            ..Recipe::factory(())
+        };
+
+        let seed = RequestSeed::new(recipe, BuildOptions::default());
+        assert_err!(
+            http_engine
+                .build(seed, &template_context)
+                .await
+                .map_err(|error| error.error),
+            "Host `localhost` is not in the allowed hosts"
+        );
+        assert!(!marker.exists());
+    }
+
+    /// DNS can't be overridden for a URL with an IP address
+    #[rstest]
+    #[tokio::test]
+    async fn test_connect_to_ip_url(
+        http_engine: HttpEngine,
+        template_context: TemplateContext,
+    ) {
+        let recipe = Recipe {
+            url: "http://127.0.0.1/get".into(),
+            connect_to: Some("localhost".into()),
+            ..Recipe::factory(())
+        };
+        let seed = RequestSeed::new(recipe, BuildOptions::default());
+        assert_err!(
+            http_engine
+                .build(seed, &template_context)
+                .await
+                .map_err(|error| error.error),
+            "`connect_to` requires the URL host to be a domain name"
+        );
+    }
+
     /// Leading/trailing newlines should be stripped from rendered header
     /// values. These characters are invalid and trigger an error, so we assume
     /// they're unintentional and the user won't miss them.
@@ -0,0 +1,273 @@
+//! Checking responses against collection-defined assertions
+
+use crate::{
+    collection::Assertion,
+    http::{ContentType, ProtobufRegistry, Query, QueryError, ResponseRecord},
+};
+use derive_more::Display;
+use regex::Regex;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// A regular expression, deserialized from a string. The expression is
+/// compiled during deserialization, so invalid patterns are caught when the
+/// collection is loaded.
+#[derive(Clone, Debug, Display, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Pattern(Regex);
+
+impl Pattern {
+    pub fn is_match(&self, value: &str) -> bool {
+        self.0.is_match(value)
+    }
+}
+
+impl TryFrom<String> for Pattern {
+    type Error = regex::Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Regex::new(&value).map(Self)
+    }
+}
+
+impl From<Pattern> for String {
+    fn from(pattern: Pattern) -> Self {
+        pattern.0.as_str().to_owned()
+    }
+}
+
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.as_str() == other.0.as_str()
+    }
+}
+
+/// Why a response did not satisfy an [Assertion]
+#[derive(Debug, Error)]
+pub enum AssertionError {
+    #[error("Expected status {expected}, got {actual}")]
+    Status { expected: u16, actual: StatusCode },
+
+    #[error("Expected header `{header}`, but it is missing")]
+    MissingHeader { header: String },
+
+    #[error("Expected {subject} to equal `{expected}`, got `{actual}`")]
+    NotEqual {
+        subject: String,
+        expected: String,
+        actual: String,
+    },
+
+    #[error("Expected {subject} to match `{pattern}`, got `{actual}`")]
+    NoMatch {
+        subject: String,
+        pattern: Pattern,
+        actual: String,
+    },
+
+    #[error("Error parsing response body")]
+    Parse {
+        #[source]
+        error: anyhow::Error,
+    },
+
+    #[error("Error querying response body with `{selector}`")]
+    Query {
+        selector: Query,
+        #[source]
+        error: QueryError,
+    },
+}
+
+impl Assertion {
+    /// Check if a response satisfies this assertion. Protobuf bodies are
+    /// decoded with the given schema
+    pub fn check(
+        &self,
+        response: &ResponseRecord,
+        protobuf: Option<&ProtobufRegistry>,
+    ) -> Result<(), AssertionError> {
+        match self {
+            Self::Status(expected) => {
+                if response.status.as_u16() == *expected {
+                    Ok(())
+                } else {
+                    Err(AssertionError::Status {
+                        expected: *expected,
+                        actual: response.status,
+                    })
+                }
+            }
+            Self::Header {
+                name,
+                equals,
+                matches,
+            } => {
+                // If the header has multiple values, just use the first
+                let value = response.headers.get(name).ok_or_else(|| {
+                    AssertionError::MissingHeader {
+                        header: name.clone(),
+                    }
+                })?;
+                check_value(
+                    || format!("header `{name}`"),
+                    &String::from_utf8_lossy(value.as_bytes()),
+                    equals.as_deref(),
+                    matches.as_ref(),
+                )
+            }
+            Self::Body {
+                selector,
+                equals,
+                matches,
+            } => {
+                let content =
+                    ContentType::parse_response(response, protobuf)
+                        .map_err(|error| AssertionError::Parse { error })?;
+                let value =
+                    selector.query_to_string(&*content).map_err(|error| {
+                        AssertionError::Query {
+                            selector: selector.clone(),
+                            error,
+                        }
+                    })?;
+                check_value(
+                    || format!("`{selector}`"),
+                    &value,
+                    equals.as_deref(),
+                    matches.as_ref(),
+                )
+            }
+        }
+    }
+}
+
+/// Check a value against optional equality and regex conditions. `subject`
+/// describes the value, for error messages.
+fn check_value(
+    subject: impl Fn() -> String,
+    value: &str,
+    equals: Option<&str>,
+    matches: Option<&Pattern>,
+) -> Result<(), AssertionError> {
+    if let Some(expected) = equals {
+        if value != expected {
+            return Err(AssertionError::NotEqual {
+                subject: subject(),
+                expected: expected.to_owned(),
+                actual: value.to_owned(),
+            });
+        }
+    }
+    if let Some(pattern) = matches {
+        if !pattern.is_match(value) {
+            return Err(AssertionError::NoMatch {
+                subject: subject(),
+                pattern: pattern.clone(),
+                actual: value.to_owned(),
+            });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{assert_err, header_map, Factory};
+    use rstest::rstest;
+    use serde_json::json;
+
+    /// Build a response with a JSON body
+    fn response() -> ResponseRecord {
+        ResponseRecord {
+            status: StatusCode::CREATED,
+            headers: header_map([
+                ("content-type", "application/json"),
+                ("x-request-id", "abc123"),
+            ]),
+            body: json!({"id": 3, "name": "fish"})
+                .to_string()
+                .into_bytes()
+                .into(),
+            ..ResponseRecord::factory(())
+        }
+    }
+
+    fn pattern(pattern: &str) -> Option<Pattern> {
+        Some(pattern.to_owned().try_into().unwrap())
+    }
+
+    #[rstest]
+    #[case::status(Assertion::Status(201))]
+    #[case::header_present(Assertion::Header {
+        name: "x-request-id".into(),
+        equals: None,
+        matches: None,
+    })]
+    #[case::header_equals(Assertion::Header {
+        name: "X-Request-ID".into(),
+        equals: Some("abc123".into()),
+        matches: None,
+    })]
+    #[case::header_matches(Assertion::Header {
+        name: "x-request-id".into(),
+        equals: None,
+        matches: pattern("^[a-z]+[0-9]+$"),
+    })]
+    #[case::body_equals(Assertion::Body {
+        selector: "$.id".parse().unwrap(),
+        equals: Some("3".into()),
+        matches: None,
+    })]
+    #[case::body_matches(Assertion::Body {
+        selector: "$.name".parse().unwrap(),
+        equals: None,
+        matches: pattern("^fi"),
+    })]
+    fn test_check_success(#[case] assertion: Assertion) {
+        assertion.check(&response(), None).unwrap();
+    }
+
+    #[rstest]
+    #[case::status(Assertion::Status(200), "Expected status 200, got 201")]
+    #[case::header_missing(
+        Assertion::Header {
+            name: "x-fish".into(),
+            equals: None,
+            matches: None,
+        },
+        "Expected header `x-fish`, but it is missing",
+    )]
+    #[case::header_equals(
+        Assertion::Header {
+            name: "x-request-id".into(),
+            equals: Some("def456".into()),
+            matches: None,
+        },
+        "Expected header `x-request-id` to equal `def456`, got `abc123`",
+    )]
+    #[case::body_matches(
+        Assertion::Body {
+            selector: "$.name".parse().unwrap(),
+            equals: None,
+            matches: pattern("^[0-9]+$"),
+        },
+        "to match `^[0-9]+$`, got `fish`",
+    )]
+    #[case::body_query(
+        Assertion::Body {
+            selector: "$.fish".parse().unwrap(),
+            equals: None,
+            matches: None,
+        },
+        "Expected exactly one result from query",
+    )]
+    fn test_check_failure(
+        #[case] assertion: Assertion,
+        #[case] expected_error: &str,
+    ) {
+        assert_err!(assertion.check(&response(), None), expected_error);
+    }
+}
@@ -1,6 +1,6 @@
 //! Serialization/deserialization for HTTP-releated types
 
-use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use serde::{de, Deserialize, Deserializer, Serializer};
 
 /// Serialization/deserialization for [reqwest::Method]
 pub mod serde_method {
@@ -27,11 +27,14 @@ pub mod serde_method {
     }
 }
 
-/// Serialization/deserialization for [reqwest::header::HeaderMap]
+/// Serialization/deserialization for [reqwest::header::HeaderMap]. Headers
+/// with multiple values are serialized as repeated keys, so all values survive
+/// a round trip.
 pub mod serde_header_map {
     use super::*;
-    use indexmap::IndexMap;
     use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+    use serde::de::{MapAccess, Visitor};
+    use std::fmt;
 
     pub fn serialize<S>(
         headers: &HeaderMap,
@@ -41,12 +44,8 @@ pub mod serde_header_map {
         S: Serializer,
     {
         // HeaderValue -> str is fallible, so we'll serialize as bytes instead
-        <IndexMap<&str, &[u8]>>::serialize(
-            &headers
-                .into_iter()
-                .map(|(k, v)| (k.as_str(), v.as_bytes()))
-                .collect(),
-            serializer,
+        serializer.collect_map(
+            headers.iter().map(|(k, v)| (k.as_str(), v.as_bytes())),
         )
     }
 
@@ -54,16 +53,36 @@ pub mod serde_header_map {
     where
         D: Deserializer<'de>,
     {
-        <IndexMap<String, Vec<u8>>>::deserialize(deserializer)?
-            .into_iter()
-            .map::<Result<(HeaderName, HeaderValue), _>, _>(|(k, v)| {
-                // Fallibly map each key and value to header types
-                Ok((
-                    k.try_into().map_err(de::Error::custom)?,
-                    v.try_into().map_err(de::Error::custom)?,
-                ))
-            })
-            .collect()
+        struct HeaderMapVisitor;
+
+        impl<'de> Visitor<'de> for HeaderMapVisitor {
+            type Value = HeaderMap;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a map of header names to values")
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                let mut headers = HeaderMap::new();
+                // Append instead of insert, so repeated keys are retained
+                while let Some((name, value)) =
+                    map.next_entry::<String, Vec<u8>>()?
+                {
+                    headers.append(
+                        HeaderName::try_from(name)
+                            .map_err(de::Error::custom)?,
+                        HeaderValue::try_from(value)
+                            .map_err(de::Error::custom)?,
+                    );
+                }
+                Ok(headers)
+            }
+        }
+
+        deserializer.deserialize_map(HeaderMapVisitor)
     }
 }
 
@@ -90,3 +109,35 @@ pub mod serde_status_code {
             .map_err(de::Error::custom)
     }
 }
+
+/// Serialization/deserialization for [reqwest::Version]
+pub mod serde_version {
+    use super::*;
+    use reqwest::Version;
+
+    pub fn serialize<S>(
+        version: &Version,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        // Version doesn't implement Display, but Debug gives "HTTP/1.1" etc.
+        serializer.serialize_str(&format!("{version:?}"))
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Version, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = <&str>::deserialize(deserializer)?;
+        match s {
+            "HTTP/0.9" => Ok(Version::HTTP_09),
+            "HTTP/1.0" => Ok(Version::HTTP_10),
+            "HTTP/1.1" => Ok(Version::HTTP_11),
+            "HTTP/2.0" => Ok(Version::HTTP_2),
+            "HTTP/3.0" => Ok(Version::HTTP_3),
+            _ => Err(de::Error::custom(format!("Invalid HTTP version `{s}`"))),
+        }
+    }
+}
@@ -0,0 +1,279 @@
+//! Generate code that sends a rendered request, so it can be shared with
+//! people who don't use Slumber
+
+use crate::http::RequestRecord;
+use anyhow::Context;
+use derive_more::Display;
+use indexmap::IndexMap;
+use std::fmt::Write;
+use strum::{EnumCount, EnumIter};
+
+/// A language, and the library used to send the request in that language
+#[derive(Copy, Clone, Debug, Display, EnumCount, EnumIter, PartialEq)]
+pub enum CodeLanguage {
+    #[display("Python (requests)")]
+    Python,
+    #[display("JavaScript (fetch)")]
+    JavaScript,
+    #[display("Go (net/http)")]
+    Go,
+}
+
+impl RequestRecord {
+    /// Generate a snippet that sends this request in the given language
+    ///
+    /// This only fails if one of the headers or body is binary and can't be
+    /// converted to UTF-8.
+    pub fn to_code(&self, language: CodeLanguage) -> anyhow::Result<String> {
+        let mut headers: IndexMap<&str, Vec<&str>> = IndexMap::new();
+        for (name, value) in &self.headers {
+            let value =
+                value.to_str().context("Error decoding header value")?;
+            headers.entry(name.as_str()).or_default().push(value);
+        }
+        let request = CodeRequest {
+            method: self.method.as_str(),
+            url: quote(self.url.as_str()),
+            headers,
+            body: self.body_str()?.map(quote),
+        };
+        // These writes are all infallible because we're writing to a string,
+        // but use ? because it's shorter than unwrap()
+        let mut buf = String::new();
+        match language {
+            CodeLanguage::Python => request.write_python(&mut buf)?,
+            CodeLanguage::JavaScript => request.write_javascript(&mut buf)?,
+            CodeLanguage::Go => request.write_go(&mut buf)?,
+        }
+        Ok(buf)
+    }
+}
+
+/// Request data, ready to be written as code. The URL and body are already
+/// quoted as string literals
+struct CodeRequest<'a> {
+    method: &'a str,
+    url: String,
+    /// Header names and values, unquoted. Repeated headers are grouped
+    headers: IndexMap<&'a str, Vec<&'a str>>,
+    body: Option<String>,
+}
+
+impl CodeRequest<'_> {
+    fn write_python(&self, buf: &mut String) -> std::fmt::Result {
+        writeln!(buf, "import requests\n")?;
+        writeln!(buf, "response = requests.request(")?;
+        writeln!(buf, "    \"{}\",", self.method)?;
+        writeln!(buf, "    {},", self.url)?;
+        if !self.headers.is_empty() {
+            writeln!(buf, "    headers={{")?;
+            for (name, value) in self.joined_headers() {
+                writeln!(buf, "        {name}: {value},")?;
+            }
+            writeln!(buf, "    }},")?;
+        }
+        if let Some(body) = &self.body {
+            writeln!(buf, "    data={body},")?;
+        }
+        writeln!(buf, ")")?;
+        writeln!(buf, "print(response.status_code)")?;
+        writeln!(buf, "print(response.text)")
+    }
+
+    fn write_javascript(&self, buf: &mut String) -> std::fmt::Result {
+        writeln!(buf, "const response = await fetch({}, {{", self.url)?;
+        writeln!(buf, "  method: \"{}\",", self.method)?;
+        if !self.headers.is_empty() {
+            writeln!(buf, "  headers: {{")?;
+            for (name, value) in self.joined_headers() {
+                writeln!(buf, "    {name}: {value},")?;
+            }
+            writeln!(buf, "  }},")?;
+        }
+        if let Some(body) = &self.body {
+            writeln!(buf, "  body: {body},")?;
+        }
+        writeln!(buf, "}});")?;
+        writeln!(buf, "console.log(response.status);")?;
+        writeln!(buf, "console.log(await response.text());")
+    }
+
+    fn write_go(&self, buf: &mut String) -> std::fmt::Result {
+        writeln!(buf, "package main\n")?;
+        writeln!(buf, "import (")?;
+        writeln!(buf, "\t\"fmt\"")?;
+        writeln!(buf, "\t\"io\"")?;
+        writeln!(buf, "\t\"net/http\"")?;
+        if self.body.is_some() {
+            writeln!(buf, "\t\"strings\"")?;
+        }
+        writeln!(buf, ")\n")?;
+        writeln!(buf, "func main() {{")?;
+        let body = match &self.body {
+            Some(body) => {
+                writeln!(buf, "\tbody := strings.NewReader({body})")?;
+                "body"
+            }
+            None => "nil",
+        };
+        writeln!(
+            buf,
+            "\treq, err := http.NewRequest(\"{}\", {}, {body})",
+            self.method, self.url
+        )?;
+        write_go_check(buf)?;
+        for (name, values) in &self.headers {
+            for value in values {
+                let (name, value) = (quote(name), quote(value));
+                writeln!(buf, "\treq.Header.Add({name}, {value})")?;
+            }
+        }
+        writeln!(buf, "\tresp, err := http.DefaultClient.Do(req)")?;
+        write_go_check(buf)?;
+        writeln!(buf, "\tdefer resp.Body.Close()")?;
+        writeln!(buf, "\trespBody, err := io.ReadAll(resp.Body)")?;
+        write_go_check(buf)?;
+        writeln!(buf, "\tfmt.Println(resp.Status)")?;
+        writeln!(buf, "\tfmt.Println(string(respBody))")?;
+        writeln!(buf, "}}")
+    }
+
+    /// Get quoted headers with one value each. Languages that take headers as
+    /// a map can't repeat a header, so repeated values are joined with a
+    /// comma, which HTTP treats as equivalent.
+    fn joined_headers(&self) -> impl Iterator<Item = (String, String)> + '_ {
+        self.headers
+            .iter()
+            .map(|(name, values)| (quote(name), quote(&values.join(", "))))
+    }
+}
+
+/// Write Go's error check boilerplate
+fn write_go_check(buf: &mut String) -> std::fmt::Result {
+    writeln!(buf, "\tif err != nil {{")?;
+    writeln!(buf, "\t\tpanic(err)")?;
+    writeln!(buf, "\t}}")
+}
+
+/// Quote a string as a double-quoted literal. JSON's escaping rules are valid
+/// in all the languages we generate
+fn quote(s: &str) -> String {
+    serde_json::to_string(s).expect("String serialization is infallible")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{header_map, Factory};
+    use reqwest::Method;
+    use rstest::rstest;
+
+    /// A request with repeated headers, and a body that needs escaping
+    fn request() -> RequestRecord {
+        let mut headers =
+            header_map([("content-type", "application/json"), ("x-tag", "a")]);
+        headers.append("x-tag", "b".parse().unwrap());
+        RequestRecord {
+            method: Method::POST,
+            headers,
+            body: Some(br#"{"data":"value"}"#.to_vec().into()),
+            ..RequestRecord::factory(())
+        }
+    }
+
+    #[rstest]
+    #[case::python(
+        CodeLanguage::Python,
+        &[
+            r#"import requests"#,
+            r#""#,
+            r#"response = requests.request("#,
+            r#"    "POST","#,
+            r#"    "http://localhost/url","#,
+            r#"    headers={"#,
+            r#"        "content-type": "application/json","#,
+            r#"        "x-tag": "a, b","#,
+            r#"    },"#,
+            r#"    data="{\"data\":\"value\"}","#,
+            r#")"#,
+            r#"print(response.status_code)"#,
+            r#"print(response.text)"#,
+        ]
+    )]
+    #[case::javascript(
+        CodeLanguage::JavaScript,
+        &[
+            r#"const response = await fetch("http://localhost/url", {"#,
+            r#"  method: "POST","#,
+            r#"  headers: {"#,
+            r#"    "content-type": "application/json","#,
+            r#"    "x-tag": "a, b","#,
+            r#"  },"#,
+            r#"  body: "{\"data\":\"value\"}","#,
+            r#"});"#,
+            r#"console.log(response.status);"#,
+            r#"console.log(await response.text());"#,
+        ]
+    )]
+    #[case::go(
+        CodeLanguage::Go,
+        &[
+            r#"package main"#,
+            r#""#,
+            r#"import ("#,
+            "\t\"fmt\"",
+            "\t\"io\"",
+            "\t\"net/http\"",
+            "\t\"strings\"",
+            r#")"#,
+            r#""#,
+            r#"func main() {"#,
+            "\tbody := strings.NewReader(\"{\\\"data\\\":\\\"value\\\"}\")",
+            "\treq, err := http.NewRequest(\"POST\", \"http://localhost/url\", body)",
+            "\tif err != nil {",
+            "\t\tpanic(err)",
+            "\t}",
+            "\treq.Header.Add(\"content-type\", \"application/json\")",
+            "\treq.Header.Add(\"x-tag\", \"a\")",
+            "\treq.Header.Add(\"x-tag\", \"b\")",
+            "\tresp, err := http.DefaultClient.Do(req)",
+            "\tif err != nil {",
+            "\t\tpanic(err)",
+            "\t}",
+            "\tdefer resp.Body.Close()",
+            "\trespBody, err := io.ReadAll(resp.Body)",
+            "\tif err != nil {",
+            "\t\tpanic(err)",
+            "\t}",
+            "\tfmt.Println(resp.Status)",
+            "\tfmt.Println(string(respBody))",
+            r#"}"#,
+        ]
+    )]
+    fn test_to_code(#[case] language: CodeLanguage, #[case] expected: &[&str]) {
+        let expected = expected.iter().map(|line| format!("{line}\n"));
+        assert_eq!(
+            request().to_code(language).unwrap(),
+            expected.collect::<String>()
+        );
+    }
+
+    /// Without headers or a body, those sections are left out entirely
+    #[rstest]
+    #[case::python(
+        CodeLanguage::Python,
+        "    \"GET\",\n    \"http://localhost/url\",\n)"
+    )]
+    #[case::javascript(CodeLanguage::JavaScript, "  method: \"GET\",\n});")]
+    #[case::go(
+        CodeLanguage::Go,
+        "http.NewRequest(\"GET\", \"http://localhost/url\", nil)"
+    )]
+    fn test_to_code_empty(
+        #[case] language: CodeLanguage,
+        #[case] expected: &str,
+    ) {
+        let code = RequestRecord::factory(()).to_code(language).unwrap();
+        assert!(code.contains(expected), "{code}");
+    }
+}
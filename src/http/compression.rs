@@ -0,0 +1,294 @@
+//! Compression of request bodies, and decompression of response bodies
+
+use crate::collection::Compression;
+use anyhow::{anyhow, Context};
+use bytes::Bytes;
+use flate2::{
+    read::{GzDecoder, ZlibDecoder},
+    write::{GzEncoder, ZlibEncoder},
+    Compression as Level,
+};
+use reqwest::{
+    header::{HeaderMap, HeaderValue, CONTENT_ENCODING, CONTENT_LENGTH},
+    Request,
+};
+use std::io::{self, Read, Write};
+use tracing::warn;
+
+/// Buffer size for brotli encoding/decoding. This is the size recommended by
+/// the brotli crate
+const BROTLI_BUFFER_SIZE: usize = 4096;
+/// Brotli compression level, from 0-11
+const BROTLI_QUALITY: u32 = 9;
+/// Brotli window size, as a power of 2
+const BROTLI_WINDOW: u32 = 22;
+/// Maximum size of a decompressed response body. A small compressed body can
+/// expand to an enormous one, so stop decoding past this and keep the raw body
+const MAX_DECOMPRESSED_SIZE: u64 = 100 * 1024 * 1024; // 100 MiB
+
+/// Decode a response body according to its `Content-Encoding` header. Reqwest
+/// is built without automatic decompression, so a server only compresses a
+/// response when the user asks for it with `Accept-Encoding`. In that case
+/// we'd rather show the content than a pile of compressed bytes. Once the body
+/// is decoded, `Content-Encoding` and `Content-Length` no longer describe it,
+/// so they're removed from the headers. If the encoding is unknown, decoding
+/// fails, or the decoded body is too large, the body and headers are returned
+/// as-is.
+pub fn decompress_response(headers: &mut HeaderMap, body: Bytes) -> Bytes {
+    let Some(header) = headers.get(CONTENT_ENCODING) else {
+        return body;
+    };
+    let Ok(header) = header.to_str() else {
+        return body;
+    };
+
+    // Multiple encodings are listed in the order they were applied, so undo
+    // them in reverse
+    let mut decoded = body.clone();
+    for encoding in header.split(',').map(str::trim).rev() {
+        if encoding.eq_ignore_ascii_case("identity") {
+            continue;
+        }
+        let Some(compression) = Compression::from_encoding(encoding) else {
+            warn!(encoding, "Unknown content encoding, not decompressing");
+            return body;
+        };
+        match compression.decompress(&decoded, MAX_DECOMPRESSED_SIZE) {
+            Ok(bytes) => decoded = bytes.into(),
+            Err(error) => {
+                warn!(
+                    encoding,
+                    error = %error,
+                    "Error decompressing response body"
+                );
+                return body;
+            }
+        }
+    }
+    headers.remove(CONTENT_ENCODING);
+    headers.remove(CONTENT_LENGTH);
+    decoded
+}
+
+impl Compression {
+    /// Compress a built request's body in place, and set `Content-Encoding`
+    /// to match. Requests without a body are left alone.
+    pub fn apply(self, request: &mut Request) -> anyhow::Result<()> {
+        let Some(body) = request.body() else {
+            return Ok(());
+        };
+        // Multipart forms are streamed, so the bytes aren't available here
+        let bytes = body.as_bytes().ok_or_else(|| {
+            anyhow!("Compression is not supported for multipart form bodies")
+        })?;
+        let compressed = self
+            .compress(bytes)
+            .context("Error compressing request body")?;
+        *request.body_mut() = Some(compressed.into());
+        request.headers_mut().insert(
+            CONTENT_ENCODING,
+            HeaderValue::from_static(self.encoding()),
+        );
+        Ok(())
+    }
+
+    /// Value for the `Content-Encoding` header
+    fn encoding(self) -> &'static str {
+        match self {
+            Self::Gzip => "gzip",
+            Self::Deflate => "deflate",
+            Self::Brotli => "br",
+        }
+    }
+
+    /// Parse a single value from a `Content-Encoding` header
+    fn from_encoding(encoding: &str) -> Option<Self> {
+        [Self::Gzip, Self::Deflate, Self::Brotli]
+            .into_iter()
+            .find(|compression| {
+                encoding.eq_ignore_ascii_case(compression.encoding())
+            })
+            // Legacy alias, per RFC 9110 §8.4.1.3
+            .or_else(|| {
+                encoding
+                    .eq_ignore_ascii_case("x-gzip")
+                    .then_some(Self::Gzip)
+            })
+    }
+
+    fn compress(self, bytes: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            Self::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), Level::default());
+                encoder.write_all(bytes)?;
+                encoder.finish()
+            }
+            // The `deflate` content coding is actually zlib-wrapped deflate
+            // (RFC 9110 §8.4.1.2), not the raw deflate stream
+            Self::Deflate => {
+                let mut encoder =
+                    ZlibEncoder::new(Vec::new(), Level::default());
+                encoder.write_all(bytes)?;
+                encoder.finish()
+            }
+            Self::Brotli => {
+                let mut encoder = brotli::CompressorWriter::new(
+                    Vec::new(),
+                    BROTLI_BUFFER_SIZE,
+                    BROTLI_QUALITY,
+                    BROTLI_WINDOW,
+                );
+                encoder.write_all(bytes)?;
+                Ok(encoder.into_inner())
+            }
+        }
+    }
+
+    /// Decode bytes, failing if the output would exceed `limit` bytes
+    fn decompress(self, bytes: &[u8], limit: u64) -> io::Result<Vec<u8>> {
+        let decoder: Box<dyn Read> = match self {
+            Self::Gzip => Box::new(GzDecoder::new(bytes)),
+            Self::Deflate => Box::new(ZlibDecoder::new(bytes)),
+            Self::Brotli => {
+                Box::new(brotli::Decompressor::new(bytes, BROTLI_BUFFER_SIZE))
+            }
+        };
+        // Read one byte past the limit, so we can tell if there was more
+        let mut decompressed = Vec::new();
+        decoder
+            .take(limit.saturating_add(1))
+            .read_to_end(&mut decompressed)?;
+        if decompressed.len() as u64 > limit {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!("Decompressed body exceeds {limit} bytes"),
+            ));
+        }
+        Ok(decompressed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{assert_err, header_map};
+    use reqwest::{multipart::Form, Client};
+    use rstest::rstest;
+
+    const BODY: &str = "{\"message\": \"hello, hello, hello\"}";
+
+    fn request() -> reqwest::RequestBuilder {
+        Client::new().post("http://localhost/")
+    }
+
+    #[rstest]
+    #[case::gzip(Compression::Gzip, "gzip")]
+    #[case::deflate(Compression::Deflate, "deflate")]
+    #[case::brotli(Compression::Brotli, "br")]
+    fn test_apply(#[case] compression: Compression, #[case] encoding: &str) {
+        let mut request = request().body(BODY).build().unwrap();
+        compression.apply(&mut request).unwrap();
+
+        assert_eq!(request.headers()[CONTENT_ENCODING], encoding);
+        let compressed = request.body().unwrap().as_bytes().unwrap();
+        assert_ne!(compressed, BODY.as_bytes());
+        let decompressed = compression
+            .decompress(compressed, MAX_DECOMPRESSED_SIZE)
+            .unwrap();
+        assert_eq!(std::str::from_utf8(&decompressed).unwrap(), BODY);
+    }
+
+    /// Response bodies are decoded according to `Content-Encoding`, and the
+    /// encoding headers are dropped once they no longer apply
+    #[rstest]
+    #[case::none(None, BODY.into())]
+    #[case::gzip(Some("gzip"), compressed(&[Compression::Gzip]))]
+    #[case::x_gzip(Some("x-gzip"), compressed(&[Compression::Gzip]))]
+    #[case::deflate(Some("deflate"), compressed(&[Compression::Deflate]))]
+    #[case::brotli(Some("BR"), compressed(&[Compression::Brotli]))]
+    #[case::multiple(
+        Some("gzip, identity, br"),
+        compressed(&[Compression::Gzip, Compression::Brotli])
+    )]
+    fn test_decompress_response(
+        #[case] encoding: Option<&str>,
+        #[case] body: Vec<u8>,
+    ) {
+        let mut headers = header_map(
+            encoding
+                .map(|encoding| ("Content-Encoding", encoding))
+                .into_iter()
+                .chain([("Content-Length", "100")]),
+        );
+        assert_eq!(
+            decompress_response(&mut headers, body.into()),
+            BODY.as_bytes()
+        );
+        if encoding.is_some() {
+            assert!(headers.get(CONTENT_ENCODING).is_none());
+            assert!(headers.get(CONTENT_LENGTH).is_none());
+        }
+    }
+
+    /// Unknown encodings and invalid bodies are left alone
+    #[rstest]
+    #[case::unknown("zstd")]
+    #[case::invalid("gzip")]
+    fn test_decompress_response_passthrough(#[case] encoding: &str) {
+        let mut headers = header_map([("Content-Encoding", encoding)]);
+        assert_eq!(
+            decompress_response(
+                &mut headers,
+                Bytes::from_static(b"not compressed")
+            ),
+            b"not compressed".as_slice()
+        );
+        assert_eq!(headers[CONTENT_ENCODING], encoding);
+    }
+
+    /// Decoding stops once the output passes the size limit
+    #[rstest]
+    #[case::gzip(Compression::Gzip)]
+    #[case::deflate(Compression::Deflate)]
+    #[case::brotli(Compression::Brotli)]
+    fn test_decompress_limit(#[case] compression: Compression) {
+        let compressed = compressed(&[compression]);
+        let size = BODY.len() as u64;
+        assert_eq!(
+            compression.decompress(&compressed, size).unwrap(),
+            BODY.as_bytes()
+        );
+        assert_err!(
+            compression.decompress(&compressed, size - 1),
+            "Decompressed body exceeds"
+        );
+    }
+
+    /// Compress the test body with each algorithm in order
+    fn compressed(compressions: &[Compression]) -> Vec<u8> {
+        compressions.iter().fold(BODY.into(), |bytes, compression| {
+            compression.compress(&bytes).unwrap()
+        })
+    }
+
+    /// Requests without a body don't get a `Content-Encoding`
+    #[test]
+    fn test_apply_no_body() {
+        let mut request = request().build().unwrap();
+        Compression::Gzip.apply(&mut request).unwrap();
+        assert!(request.body().is_none());
+        assert!(request.headers().get(CONTENT_ENCODING).is_none());
+    }
+
+    #[test]
+    fn test_apply_multipart() {
+        let mut request = request()
+            .multipart(Form::new().text("field", "value"))
+            .build()
+            .unwrap();
+        assert_err!(
+            Compression::Gzip.apply(&mut request),
+            "Compression is not supported for multipart form bodies"
+        );
+    }
+}
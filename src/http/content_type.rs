@@ -5,13 +5,22 @@
 //! not a value, use [ContentType]. If you want to parse dynamically based on
 //! the response's metadata, use [ContentType::parse_response].
 
-use crate::{http::ResponseRecord, util::Mapping};
+use crate::{collection::ProtobufSchema, http::ResponseRecord, util::Mapping};
 use anyhow::{anyhow, Context};
 use derive_more::{Deref, Display, From};
-use mime::{Mime, APPLICATION, JSON};
+use itertools::Itertools;
+use mime::Mime;
+use prost_reflect::{DescriptorPool, DynamicMessage};
 use reqwest::header::{self, HeaderValue};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::{borrow::Cow, ffi::OsStr, fmt::Debug, path::Path};
+use std::{
+    borrow::Cow,
+    ffi::OsStr,
+    fmt::{self, Debug},
+    path::Path,
+};
+use tracing::info;
 
 /// All supported content types. Each variant should have a corresponding
 /// implementation of [ResponseContent].
@@ -24,16 +33,38 @@ use std::{borrow::Cow, ffi::OsStr, fmt::Debug, path::Path};
 ///
 /// For the serialization string, obviously use serde. For the others, use
 /// the corresponding methods/associated functions.
-#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Copy, Clone, Debug, PartialEq, JsonSchema, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum ContentType {
     Json,
+    /// XML can't be parsed into [ResponseContent], so it isn't prettified or
+    /// queryable in the TUI. It's only supported for chain selectors (XPath)
+    Xml,
+    /// Same restrictions as [Self::Xml]. Chain selectors use CSS selectors
+    Html,
+    /// MessagePack is decoded to JSON
+    #[serde(rename = "msgpack")]
+    MsgPack,
+    /// Protobuf is decoded to JSON, using the collection's protobuf schema
+    Protobuf,
+    /// CSV with a header row. Displayed as a table, and converted to JSON as
+    /// an array of objects (one per row) for querying
+    Csv,
+    /// Newline-delimited JSON. Converted to JSON as an array of records
+    Ndjson,
 }
 
 impl ContentType {
     /// File extensions for each content type
-    const EXTENSIONS: Mapping<'static, ContentType> =
-        Mapping::new(&[(Self::Json, &["json"])]);
+    const EXTENSIONS: Mapping<'static, ContentType> = Mapping::new(&[
+        (Self::Json, &["json"]),
+        (Self::Xml, &["xml"]),
+        (Self::Html, &["html", "htm"]),
+        (Self::MsgPack, &["msgpack", "mpk"]),
+        (Self::Protobuf, &["pb", "binpb"]),
+        (Self::Csv, &["csv"]),
+        (Self::Ndjson, &["ndjson", "jsonl"]),
+    ]);
 
     /// Parse the value of the content-type header and map it to a known content
     /// type
@@ -42,13 +73,37 @@ impl ContentType {
             .parse()
             .with_context(|| format!("Invalid content type `{mime_type}`"))?;
 
+        // Compare as strings, so subtypes that mime doesn't have constants
+        // for can be matched the same way as the ones it does
         let suffix = mime_type.suffix().map(|name| name.as_str());
-        match (mime_type.type_(), mime_type.subtype(), suffix) {
+        match (
+            mime_type.type_().as_str(),
+            mime_type.subtype().as_str(),
+            suffix,
+        ) {
             // JSON has a lot of extended types that follow the pattern
             // "application/*+json", match those too
-            (APPLICATION, JSON, _) | (APPLICATION, _, Some("json")) => {
+            ("application", "json", _) | ("application", _, Some("json")) => {
                 Ok(Self::Json)
             }
+            // Same for XML, e.g. application/soap+xml
+            ("application" | "text", "xml", _)
+            | ("application", _, Some("xml")) => Ok(Self::Xml),
+            ("text", "html", _) => Ok(Self::Html),
+            ("text", "csv", _) => Ok(Self::Csv),
+            (
+                "application",
+                "x-ndjson" | "ndjson" | "jsonl" | "x-jsonlines",
+                _,
+            ) => Ok(Self::Ndjson),
+            ("application", "msgpack" | "x-msgpack" | "vnd.msgpack", _) => {
+                Ok(Self::MsgPack)
+            }
+            (
+                "application",
+                "protobuf" | "x-protobuf" | "vnd.google.protobuf",
+                _,
+            ) => Ok(Self::Protobuf),
             _ => Err(anyhow!("Unknown content type `{mime_type}`")),
         }
     }
@@ -66,24 +121,28 @@ impl ContentType {
 
     /// Parse the content type from a response's `Content-Type` header
     pub fn from_response(response: &ResponseRecord) -> anyhow::Result<Self> {
-        let header_value = response
-            .headers
-            .get(header::CONTENT_TYPE)
-            .map(HeaderValue::as_bytes)
-            .ok_or_else(|| anyhow!("Response has no content-type header"))?;
-        let header_value = std::str::from_utf8(header_value)
-            .context("content-type header is not valid utf-8")?;
-        Self::from_mime(header_value)
+        Self::from_mime(content_type_header(response)?)
     }
 
     /// Parse some content of this type. Return a dynamically dispatched content
-    /// object.
+    /// object. Protobuf content is decoded with the collection's compiled
+    /// schema, if it has one.
     pub fn parse_content(
         self,
         content: &[u8],
+        protobuf: Option<&ProtobufRegistry>,
     ) -> anyhow::Result<Box<dyn ResponseContent>> {
         match self {
             Self::Json => Ok(Box::new(Json::parse(content)?)),
+            Self::MsgPack => Ok(Box::new(MsgPack::parse(content)?)),
+            Self::Protobuf => {
+                Ok(Box::new(Protobuf::decode(content, None, protobuf)?))
+            }
+            Self::Csv => Ok(Box::new(Csv::parse(content)?)),
+            Self::Ndjson => Ok(Box::new(Ndjson::parse(content)?)),
+            Self::Xml | Self::Html => {
+                Err(anyhow!("{self:?} content can only be used with selectors"))
+            }
         }
     }
 
@@ -96,17 +155,120 @@ impl ContentType {
         content: Cow<'_, serde_json::Value>,
     ) -> Box<dyn ResponseContent> {
         match self {
-            Self::Json => Box::new(Json(content.into_owned())),
+            // Markup is never parsed into content, so we can't get here with
+            // it. Fall back to JSON just in case. Other formats are converted
+            // to JSON for querying anyway, and query results don't
+            // necessarily fit the original format (e.g. a single CSV cell),
+            // so JSON is the most readable
+            Self::Json
+            | Self::Xml
+            | Self::Html
+            | Self::MsgPack
+            | Self::Protobuf
+            | Self::Csv
+            | Self::Ndjson => Box::new(Json(content.into_owned())),
         }
     }
 
-    /// Helper for parsing the body of a response. Use
-    /// [ResponseRecord::parse_body] for external usage.
-    pub(super) fn parse_response(
+    /// Helper for parsing the body of a response. Prefer
+    /// [ResponseRecord::parse_body] if the parsed body should be cached on the
+    /// response.
+    pub fn parse_response(
         response: &ResponseRecord,
+        protobuf: Option<&ProtobufRegistry>,
     ) -> anyhow::Result<Box<dyn ResponseContent>> {
         let content_type = Self::from_response(response)?;
-        content_type.parse_content(response.body.bytes())
+        match content_type {
+            // The message type may be specified in the Content-Type
+            Self::Protobuf => Ok(Box::new(Protobuf::decode(
+                response.body.bytes(),
+                protobuf_message(response).as_deref(),
+                protobuf,
+            )?)),
+            _ => content_type.parse_content(response.body.bytes(), protobuf),
+        }
+    }
+}
+
+/// Get the value of a response's `Content-Type` header, as a string
+fn content_type_header(response: &ResponseRecord) -> anyhow::Result<&str> {
+    let header_value = response
+        .headers
+        .get(header::CONTENT_TYPE)
+        .map(HeaderValue::as_bytes)
+        .ok_or_else(|| anyhow!("Response has no content-type header"))?;
+    std::str::from_utf8(header_value)
+        .context("content-type header is not valid utf-8")
+}
+
+/// Get the protobuf message type from a response's `Content-Type` header, e.g.
+/// `application/x-protobuf; proto=users.v1.User`. There's no standard for this,
+/// so we check the two most common parameter names.
+fn protobuf_message(response: &ResponseRecord) -> Option<String> {
+    let mime_type: Mime = content_type_header(response).ok()?.parse().ok()?;
+    mime_type
+        .get_param("proto")
+        .or_else(|| mime_type.get_param("messageType"))
+        .map(|name| name.as_str().to_owned())
+}
+
+/// Compiled protobuf schema. This is built once when the collection is loaded,
+/// and stored on the collection. It's cheap to clone.
+#[derive(Clone, Debug)]
+#[cfg_attr(test, derive(PartialEq))]
+pub struct ProtobufRegistry {
+    pool: DescriptorPool,
+    default_message: Option<String>,
+}
+
+impl ProtobufRegistry {
+    /// Compile `.proto` files into a descriptor pool. Paths are relative to
+    /// `root`
+    pub fn compile(
+        schema: &ProtobufSchema,
+        root: &Path,
+    ) -> anyhow::Result<Self> {
+        let files = schema.files.iter().map(|path| root.join(path));
+        let include = schema
+            .include
+            .iter()
+            .map(|path| root.join(path))
+            .chain([root.to_owned()]);
+        let descriptors = protox::compile(files, include)?;
+        let pool = DescriptorPool::from_file_descriptor_set(descriptors)?;
+        info!(
+            files = ?schema.files,
+            messages = pool.all_messages().len(),
+            "Loaded protobuf schema"
+        );
+        Ok(Self {
+            pool,
+            default_message: schema.message.clone(),
+        })
+    }
+
+    /// Decode a message to JSON. If no message type is given, fall back to
+    /// the schema's default
+    fn decode(
+        &self,
+        body: &[u8],
+        message: Option<&str>,
+    ) -> anyhow::Result<serde_json::Value> {
+        let message =
+            message.or(self.default_message.as_deref()).ok_or_else(|| {
+                anyhow!(
+                    "Protobuf message type is unknown. Set `protobuf.message` \
+                    in the collection, or a `proto` parameter in the \
+                    response's content-type"
+                )
+            })?;
+        let descriptor =
+            self.pool.get_message_by_name(message).ok_or_else(|| {
+                anyhow!("Unknown protobuf message type `{message}`")
+            })?;
+        let message = DynamicMessage::decode(descriptor, body)
+            .with_context(|| format!("Error decoding `{message}`"))?;
+        Ok(serde_json::to_value(&message)?)
     }
 }
 
@@ -162,16 +324,242 @@ impl ResponseContent for Json {
     }
 }
 
+/// MessagePack content, decoded to JSON
+#[derive(Debug, Display, Deref, From, PartialEq)]
+pub struct MsgPack(serde_json::Value);
+
+impl ResponseContent for MsgPack {
+    fn content_type(&self) -> ContentType {
+        ContentType::MsgPack
+    }
+
+    fn parse(body: &[u8]) -> anyhow::Result<Self> {
+        Ok(Self(rmp_serde::from_slice(body)?))
+    }
+
+    fn prettify(&self) -> String {
+        // serde_json can't fail serializing its own Value type
+        serde_json::to_string_pretty(&self.0).unwrap()
+    }
+
+    fn to_json(&self) -> Cow<'_, serde_json::Value> {
+        Cow::Borrowed(&self.0)
+    }
+
+    #[cfg(test)]
+    fn as_any(&self) -> &dyn std::any::Any {
+        self as &dyn std::any::Any
+    }
+}
+
+/// Protobuf content, decoded to JSON according to the collection's protobuf
+/// schema
+#[derive(Debug, Display, Deref, From, PartialEq)]
+pub struct Protobuf(serde_json::Value);
+
+impl Protobuf {
+    /// Decode a protobuf message. If the message type isn't given, use the
+    /// default from the schema
+    fn decode(
+        body: &[u8],
+        message: Option<&str>,
+        registry: Option<&ProtobufRegistry>,
+    ) -> anyhow::Result<Self> {
+        let registry = registry.ok_or_else(|| {
+            anyhow!(
+                "Protobuf content requires a schema. Add `protobuf.files` to \
+                the collection"
+            )
+        })?;
+        Ok(Self(registry.decode(body, message)?))
+    }
+}
+
+impl ResponseContent for Protobuf {
+    fn content_type(&self) -> ContentType {
+        ContentType::Protobuf
+    }
+
+    /// Without a schema, there's no way to decode the message. Use
+    /// [ContentType::parse_content] instead
+    fn parse(body: &[u8]) -> anyhow::Result<Self> {
+        Self::decode(body, None, None)
+    }
+
+    fn prettify(&self) -> String {
+        // serde_json can't fail serializing its own Value type
+        serde_json::to_string_pretty(&self.0).unwrap()
+    }
+
+    fn to_json(&self) -> Cow<'_, serde_json::Value> {
+        Cow::Borrowed(&self.0)
+    }
+
+    #[cfg(test)]
+    fn as_any(&self) -> &dyn std::any::Any {
+        self as &dyn std::any::Any
+    }
+}
+
+/// CSV content. The first row is the header
+#[derive(Debug, PartialEq)]
+pub struct Csv {
+    headers: Vec<String>,
+    rows: Vec<Vec<String>>,
+}
+
+impl ResponseContent for Csv {
+    fn content_type(&self) -> ContentType {
+        ContentType::Csv
+    }
+
+    fn parse(body: &[u8]) -> anyhow::Result<Self> {
+        let mut reader = csv::ReaderBuilder::new()
+            // Allow rows to be shorter/longer than the header
+            .flexible(true)
+            .from_reader(body);
+        let headers = reader.headers()?.iter().map(String::from).collect();
+        let rows = reader
+            .records()
+            .map(|record| Ok(record?.iter().map(String::from).collect()))
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Self { headers, rows })
+    }
+
+    fn prettify(&self) -> String {
+        self.to_string()
+    }
+
+    fn to_json(&self) -> Cow<'_, serde_json::Value> {
+        let rows = self
+            .rows
+            .iter()
+            .map(|row| {
+                // Missing cells are null. Extra cells don't have a header, so
+                // there's no key to put them under; drop them
+                let object = self
+                    .headers
+                    .iter()
+                    .enumerate()
+                    .map(|(i, header)| {
+                        let value = row
+                            .get(i)
+                            .map_or(serde_json::Value::Null, |cell| {
+                                cell.as_str().into()
+                            });
+                        (header.clone(), value)
+                    })
+                    .collect();
+                serde_json::Value::Object(object)
+            })
+            .collect();
+        Cow::Owned(serde_json::Value::Array(rows))
+    }
+
+    #[cfg(test)]
+    fn as_any(&self) -> &dyn std::any::Any {
+        self as &dyn std::any::Any
+    }
+}
+
+/// Display as a table, with each column padded to its widest cell
+impl fmt::Display for Csv {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut widths: Vec<usize> = self
+            .headers
+            .iter()
+            .map(|header| header.chars().count())
+            .collect();
+        for row in &self.rows {
+            for (i, cell) in row.iter().enumerate() {
+                let width = cell.chars().count();
+                match widths.get_mut(i) {
+                    Some(max) => *max = (*max).max(width),
+                    None => widths.push(width),
+                }
+            }
+        }
+
+        write_row(f, self.headers.iter().map(String::as_str), &widths)?;
+        let dividers =
+            widths.iter().map(|width| "-".repeat(*width)).collect_vec();
+        write_row(f, dividers.iter().map(String::as_str), &widths)?;
+        for row in &self.rows {
+            write_row(f, row.iter().map(String::as_str), &widths)?;
+        }
+        Ok(())
+    }
+}
+
+/// Write one row of a text table, padding each cell to its column's width
+fn write_row<'a>(
+    f: &mut fmt::Formatter<'_>,
+    cells: impl Iterator<Item = &'a str>,
+    widths: &[usize],
+) -> fmt::Result {
+    let line = cells
+        .zip(widths.iter().copied())
+        .map(|(cell, width)| format!("{cell:width$}"))
+        .join("  ");
+    writeln!(f, "{}", line.trim_end())
+}
+
+/// Newline-delimited JSON. Each non-empty line is a JSON value
+#[derive(Debug, PartialEq)]
+pub struct Ndjson(Vec<serde_json::Value>);
+
+impl ResponseContent for Ndjson {
+    fn content_type(&self) -> ContentType {
+        ContentType::Ndjson
+    }
+
+    fn parse(body: &[u8]) -> anyhow::Result<Self> {
+        let records = body
+            .split(|byte| *byte == b'\n')
+            .enumerate()
+            .filter(|(_, line)| !line.iter().all(u8::is_ascii_whitespace))
+            .map(|(i, line)| {
+                serde_json::from_slice(line)
+                    .with_context(|| format!("Invalid JSON on line {}", i + 1))
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Self(records))
+    }
+
+    fn prettify(&self) -> String {
+        self.to_string()
+    }
+
+    fn to_json(&self) -> Cow<'_, serde_json::Value> {
+        Cow::Owned(serde_json::Value::Array(self.0.clone()))
+    }
+
+    #[cfg(test)]
+    fn as_any(&self) -> &dyn std::any::Any {
+        self as &dyn std::any::Any
+    }
+}
+
+/// Display one record per line, with whitespace normalized
+impl fmt::Display for Ndjson {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for record in &self.0 {
+            writeln!(f, "{record}")?;
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::test_util::{assert_err, Factory};
+    use crate::test_util::{assert_err, temp_dir, Factory, TempDir};
     use reqwest::header::{
         HeaderMap, HeaderValue, InvalidHeaderValue, CONTENT_TYPE,
     };
     use rstest::rstest;
     use serde_json::json;
-    use std::ops::Deref;
+    use std::{fs, ops::Deref};
 
     /// Test all content types and their variants
     #[rstest]
@@ -183,6 +571,20 @@ mod tests {
     )]
     // Test extended MIME type
     #[case::json_extended("application/geo+json", ContentType::Json)]
+    #[case::xml("application/xml", ContentType::Xml)]
+    #[case::xml_text("text/xml; charset=utf-8", ContentType::Xml)]
+    #[case::xml_extended("application/soap+xml", ContentType::Xml)]
+    #[case::html("text/html", ContentType::Html)]
+    #[case::msgpack("application/msgpack", ContentType::MsgPack)]
+    #[case::msgpack_legacy("application/x-msgpack", ContentType::MsgPack)]
+    #[case::protobuf("application/x-protobuf", ContentType::Protobuf)]
+    #[case::protobuf_with_message(
+        "application/protobuf; proto=users.v1.User",
+        ContentType::Protobuf
+    )]
+    #[case::csv("text/csv; header=present", ContentType::Csv)]
+    #[case::ndjson("application/x-ndjson", ContentType::Ndjson)]
+    #[case::jsonl("application/jsonl", ContentType::Ndjson)]
     fn test_try_from_mime(
         #[case] mime_type: &str,
         #[case] expected: ContentType,
@@ -195,7 +597,7 @@ mod tests {
     #[case::invalid("json", "Invalid content type")]
     #[case::json_empty_extension("application/+json", "Unknown content type")]
     #[case::whitespace("application/ +json", "Invalid content type")]
-    #[case::unknown("text/html", "Unknown content type")]
+    #[case::unknown("text/plain", "Unknown content type")]
     fn test_try_from_mime_error(
         #[case] mime_type: &str,
         #[case] expected_error: &str,
@@ -228,6 +630,22 @@ mod tests {
         "{\"hello\": \"goodbye\"}",
         Json(json!({"hello": "goodbye"}))
     )]
+    #[case::csv(
+        "text/csv",
+        "name,age\nAlice,30\n\"Bob, Jr.\",4\n",
+        Csv {
+            headers: vec!["name".into(), "age".into()],
+            rows: vec![
+                vec!["Alice".into(), "30".into()],
+                vec!["Bob, Jr.".into(), "4".into()],
+            ],
+        }
+    )]
+    #[case::ndjson(
+        "application/x-ndjson",
+        "{\"id\": 1}\n\n{\"id\": 2}\n",
+        Ndjson(vec![json!({"id": 1}), json!({"id": 2})])
+    )]
     fn test_parse_body<T: ResponseContent + PartialEq + 'static>(
         #[case] content_type: &str,
         #[case] body: &str,
@@ -239,7 +657,7 @@ mod tests {
             ..ResponseRecord::factory(())
         };
         assert_eq!(
-            ContentType::parse_response(&response)
+            ContentType::parse_response(&response, None)
                 .unwrap()
                 .deref()
                 // Downcast the result to desired type
@@ -264,6 +682,11 @@ mod tests {
         "not json!",
         "expected ident"
     )]
+    #[case::invalid_ndjson(
+        Some("application/x-ndjson"),
+        "{\"id\": 1}\nnope\n",
+        "Invalid JSON on line 2"
+    )]
     fn test_parse_body_error<
         T: TryInto<HeaderValue, Error = InvalidHeaderValue>,
     >(
@@ -280,7 +703,87 @@ mod tests {
             body: body.into(),
             ..ResponseRecord::factory(())
         };
-        assert_err!(ContentType::parse_response(&response), expected_error);
+        assert_err!(
+            ContentType::parse_response(&response, None),
+            expected_error
+        );
+    }
+
+    /// MessagePack is decoded to JSON
+    #[test]
+    fn test_parse_msgpack() {
+        let value = json!({"id": 3, "tags": ["a", "b"], "active": true});
+        let response = ResponseRecord {
+            headers: headers("application/msgpack"),
+            body: rmp_serde::to_vec_named(&value).unwrap().into(),
+            ..ResponseRecord::factory(())
+        };
+        let content = ContentType::parse_response(&response, None).unwrap();
+        assert_eq!(content.content_type(), ContentType::MsgPack);
+        assert_eq!(content.to_json().into_owned(), value);
+    }
+
+    /// Protobuf is decoded to JSON according to the schema. The message type
+    /// can be given explicitly, or fall back to the schema's default
+    #[rstest]
+    fn test_protobuf_decode(temp_dir: TempDir) {
+        fs::write(
+            temp_dir.join("users.proto"),
+            r#"
+            syntax = "proto3";
+            package users;
+            message User {
+                string name = 1;
+                int32 id = 2;
+            }
+            message Empty {}
+            "#,
+        )
+        .unwrap();
+        let schema = ProtobufSchema {
+            files: vec!["users.proto".into()],
+            include: vec![],
+            message: Some("users.User".into()),
+        };
+        let registry = ProtobufRegistry::compile(&schema, &temp_dir).unwrap();
+
+        // name = "alice", id = 7
+        let body = b"\x0a\x05alice\x10\x07";
+        assert_eq!(
+            registry.decode(body, None).unwrap(),
+            json!({"name": "alice", "id": 7})
+        );
+        assert_eq!(
+            registry.decode(body, Some("users.User")).unwrap(),
+            json!({"name": "alice", "id": 7})
+        );
+
+        // Errors
+        assert_err!(
+            registry.decode(body, Some("users.Unknown")),
+            "Unknown protobuf message type `users.Unknown`"
+        );
+        assert_err!(registry.decode(b"\x0a\x09", None), "Error decoding");
+    }
+
+    /// CSV is displayed as a table, and converted to JSON as one object per
+    /// row. Missing cells are null
+    #[test]
+    fn test_csv() {
+        let csv = Csv::parse(b"name,age\nAlice,30\nBob\n").unwrap();
+        assert_eq!(csv.prettify(), "name   age\n-----  ---\nAlice  30\nBob\n");
+        assert_eq!(
+            csv.to_json().into_owned(),
+            json!([{"name": "Alice", "age": "30"}, {"name": "Bob", "age": null}])
+        );
+    }
+
+    /// NDJSON is displayed one record per line, and converted to a JSON array
+    #[test]
+    fn test_ndjson() {
+        let ndjson = Ndjson::parse(b"{ \"a\": 1 }\n[1, 2]\n").unwrap();
+        assert_eq!(ndjson.prettify(), "{\"a\":1}\n[1,2]\n");
+        assert_eq!(ndjson.to_json().into_owned(), json!([{"a": 1}, [1, 2]]));
     }
 
     /// Create header map with the given value for the content-type header
@@ -0,0 +1,164 @@
+//! Restrictions on which hosts a profile can send requests to
+
+use crate::{
+    collection::{Profile, ProfileId},
+    util::wildcard_match,
+};
+use reqwest::Url;
+use thiserror::Error;
+
+/// A request's host isn't in its profile's `allowed_hosts`. Builds fail with
+/// this unless [BuildOptions::allow_any_host](super::BuildOptions) is set, in
+/// which case the caller should ask the user first.
+#[derive(Debug, Error)]
+#[error("Host `{host}` is not in the allowed hosts for profile `{profile_id}`")]
+pub struct HostNotAllowedError {
+    pub host: String,
+    pub profile_id: ProfileId,
+}
+
+/// Hosts that a request, and any redirects it follows, can be sent to. This
+/// comes from the profile's `allowed_hosts`. If the profile doesn't define
+/// any, every host is allowed.
+#[derive(Clone, Debug, Default)]
+pub struct HostAllowlist {
+    /// `None` if hosts aren't restricted
+    restriction: Option<(ProfileId, Vec<String>)>,
+}
+
+impl HostAllowlist {
+    /// Get the allowlist for a request, and check the request's URL against
+    /// it. If `allow_any_host` is set, the URL's host is added to the list
+    /// instead, so the request can be sent but redirects are still checked.
+    pub fn new(
+        profile: Option<&Profile>,
+        url: &Url,
+        allow_any_host: bool,
+    ) -> Result<Self, HostNotAllowedError> {
+        let Some(profile) =
+            profile.filter(|profile| !profile.allowed_hosts.is_empty())
+        else {
+            return Ok(Self::default());
+        };
+        let mut allowlist = Self {
+            restriction: Some((
+                profile.id.clone(),
+                profile.allowed_hosts.clone(),
+            )),
+        };
+        match allowlist.check(url) {
+            Ok(()) => {}
+            Err(_) if allow_any_host => {
+                if let Some((_, patterns)) = &mut allowlist.restriction {
+                    patterns
+                        .push(url.host_str().unwrap_or_default().to_owned());
+                }
+            }
+            Err(error) => return Err(error),
+        }
+        Ok(allowlist)
+    }
+
+    /// Check if a URL's host is in the list
+    pub fn check(&self, url: &Url) -> Result<(), HostNotAllowedError> {
+        match &self.restriction {
+            Some((profile_id, patterns)) if !is_host_allowed(patterns, url) => {
+                Err(HostNotAllowedError {
+                    host: url.host_str().unwrap_or_default().to_owned(),
+                    profile_id: profile_id.clone(),
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Check if a URL's host matches any of the given wildcard patterns. `*`
+/// matches any number of characters (including `.`) and `?` matches exactly
+/// one. Matching is case-insensitive, because hostnames are.
+fn is_host_allowed(patterns: &[String], url: &Url) -> bool {
+    let host = url.host_str().unwrap_or_default().to_ascii_lowercase();
+    patterns
+        .iter()
+        .any(|pattern| wildcard_match(&pattern.to_ascii_lowercase(), &host))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{assert_err, Factory};
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::exact("api.example.com", "https://api.example.com/users", true)]
+    #[case::case_insensitive(
+        "API.example.com",
+        "https://api.EXAMPLE.com",
+        true
+    )]
+    #[case::subdomain("*.example.com", "https://api.example.com", true)]
+    #[case::nested_subdomain("*.example.com", "https://a.b.example.com", true)]
+    #[case::subdomain_not_root("*.example.com", "https://example.com", false)]
+    #[case::suffix_attack(
+        "*.example.com",
+        "https://example.com.evil.io",
+        false
+    )]
+    #[case::question("api-?.example.com", "https://api-2.example.com", true)]
+    #[case::port_ignored("localhost", "http://localhost:3000", true)]
+    #[case::mismatch("api.example.com", "https://api.exmaple.com", false)]
+    #[case::wildcard("*", "https://anything.io", true)]
+    fn test_is_host_allowed(
+        #[case] pattern: &str,
+        #[case] url: &str,
+        #[case] expected: bool,
+    ) {
+        let url: Url = url.parse().unwrap();
+        assert_eq!(is_host_allowed(&[pattern.to_owned()], &url), expected);
+    }
+
+    fn profile(allowed_hosts: &[&str]) -> Profile {
+        Profile {
+            allowed_hosts: allowed_hosts
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+            ..Profile::factory(())
+        }
+    }
+
+    /// Without a profile or allowed hosts, anything goes
+    #[rstest]
+    #[case::no_profile(None)]
+    #[case::empty(Some(profile(&[])))]
+    fn test_allowlist_unrestricted(#[case] profile: Option<Profile>) {
+        let url: Url = "https://api.example.com".parse().unwrap();
+        let allowlist =
+            HostAllowlist::new(profile.as_ref(), &url, false).unwrap();
+        allowlist
+            .check(&"https://evil.io".parse().unwrap())
+            .unwrap();
+    }
+
+    /// Allowing any host only adds the request's own host to the list.
+    /// Redirects elsewhere are still blocked
+    #[test]
+    fn test_allowlist_allow_any_host() {
+        let profile = profile(&["*.example.com"]);
+        let url: Url = "https://staging.io/users".parse().unwrap();
+        assert_err!(
+            HostAllowlist::new(Some(&profile), &url, false),
+            "Host `staging.io` is not in the allowed hosts for profile"
+        );
+
+        let allowlist = HostAllowlist::new(Some(&profile), &url, true).unwrap();
+        allowlist.check(&url).unwrap();
+        allowlist
+            .check(&"https://api.example.com".parse().unwrap())
+            .unwrap();
+        assert_err!(
+            allowlist.check(&"https://evil.io".parse().unwrap()),
+            "Host `evil.io` is not in the allowed hosts for profile"
+        );
+    }
+}
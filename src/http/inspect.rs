@@ -0,0 +1,150 @@
+//! Inspection of binary bodies, which can't be shown as text. We detect what
+//! we can from the content itself, so it can be shown alongside the hex dump.
+
+use bytesize::ByteSize;
+use derive_more::Display;
+use sha2::{Digest, Sha256};
+
+/// Metadata about a binary (non-UTF-8) body
+#[derive(Clone, Debug, Display, PartialEq)]
+#[display(
+    "{} · {} · SHA-256 {sha256}",
+    file_type.map(|file_type| file_type.name).unwrap_or("Unknown binary"),
+    size.to_string_as(false)
+)]
+pub struct BodyMetadata {
+    /// File type detected from magic bytes, if recognized
+    pub file_type: Option<FileType>,
+    pub size: ByteSize,
+    /// Hex-encoded SHA-256 checksum of the body
+    pub sha256: String,
+}
+
+impl BodyMetadata {
+    /// Inspect a body. Return `None` if the body is valid UTF-8, because text
+    /// bodies are displayed as-is and don't need any of this.
+    pub fn inspect(bytes: &[u8]) -> Option<Self> {
+        if std::str::from_utf8(bytes).is_ok() {
+            return None;
+        }
+        Some(Self {
+            file_type: FileType::detect(bytes),
+            size: ByteSize(bytes.len() as u64),
+            sha256: format!("{:x}", Sha256::digest(bytes)),
+        })
+    }
+}
+
+/// A list of `(offset, bytes)` pairs that identify a file type
+type Signature = &'static [(usize, &'static [u8])];
+
+/// A file type that can be identified by its leading bytes
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FileType {
+    /// Human-readable name
+    pub name: &'static str,
+    /// Conventional file extension, without the leading dot
+    pub extension: &'static str,
+}
+
+impl FileType {
+    /// Known file signatures. All pairs in a signature must match. More
+    /// specific signatures go first.
+    const SIGNATURES: &'static [(Signature, Self)] = &[
+        (&[(0, b"\x89PNG\r\n\x1a\n")], Self::new("PNG image", "png")),
+        (&[(0, b"\xff\xd8\xff")], Self::new("JPEG image", "jpg")),
+        (&[(0, b"GIF87a")], Self::new("GIF image", "gif")),
+        (&[(0, b"GIF89a")], Self::new("GIF image", "gif")),
+        (
+            &[(0, b"RIFF"), (8, b"WEBP")],
+            Self::new("WebP image", "webp"),
+        ),
+        (&[(0, b"RIFF"), (8, b"WAVE")], Self::new("WAV audio", "wav")),
+        (&[(0, b"\x00\x00\x01\x00")], Self::new("ICO image", "ico")),
+        (&[(0, b"%PDF-")], Self::new("PDF document", "pdf")),
+        (&[(0, b"PK\x03\x04")], Self::new("ZIP archive", "zip")),
+        (&[(0, b"\x1f\x8b")], Self::new("gzip archive", "gz")),
+        (&[(0, b"BZh")], Self::new("bzip2 archive", "bz2")),
+        (&[(0, b"\xfd7zXZ\x00")], Self::new("XZ archive", "xz")),
+        (
+            &[(0, b"\x28\xb5\x2f\xfd")],
+            Self::new("Zstandard archive", "zst"),
+        ),
+        (
+            &[(0, b"7z\xbc\xaf\x27\x1c")],
+            Self::new("7-Zip archive", "7z"),
+        ),
+        (&[(257, b"ustar")], Self::new("tar archive", "tar")),
+        (&[(0, b"\x00asm")], Self::new("WebAssembly module", "wasm")),
+        (&[(0, b"ID3")], Self::new("MP3 audio", "mp3")),
+        (&[(0, b"OggS")], Self::new("Ogg media", "ogg")),
+        (&[(0, b"fLaC")], Self::new("FLAC audio", "flac")),
+        (&[(4, b"ftyp")], Self::new("MP4 video", "mp4")),
+        (&[(0, b"wOFF")], Self::new("WOFF font", "woff")),
+        (&[(0, b"wOF2")], Self::new("WOFF2 font", "woff2")),
+        (
+            &[(0, b"SQLite format 3\x00")],
+            Self::new("SQLite database", "db"),
+        ),
+    ];
+
+    const fn new(name: &'static str, extension: &'static str) -> Self {
+        Self { name, extension }
+    }
+
+    /// Detect file type from the leading bytes of some content
+    pub fn detect(bytes: &[u8]) -> Option<Self> {
+        Self::SIGNATURES.iter().find_map(|(signature, file_type)| {
+            signature
+                .iter()
+                .all(|(offset, magic)| {
+                    bytes.get(*offset..offset + magic.len()) == Some(*magic)
+                })
+                .then_some(*file_type)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::png(b"\x89PNG\r\n\x1a\n\x00\x00", Some("png"))]
+    #[case::jpeg(b"\xff\xd8\xff\xe0", Some("jpg"))]
+    #[case::webp(b"RIFF\x10\x00\x00\x00WEBPVP8 ", Some("webp"))]
+    #[case::wav(b"RIFF\x10\x00\x00\x00WAVEfmt ", Some("wav"))]
+    #[case::riff_unknown(b"RIFF\x10\x00\x00\x00AVI LIST", None)]
+    #[case::mp4(b"\x00\x00\x00\x18ftypmp42", Some("mp4"))]
+    #[case::too_short(b"\x89PN", None)]
+    #[case::unknown(b"\x01\x02\x03", None)]
+    fn test_detect(#[case] bytes: &[u8], #[case] expected: Option<&str>) {
+        assert_eq!(
+            FileType::detect(bytes).map(|file_type| file_type.extension),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_inspect() {
+        assert_eq!(BodyMetadata::inspect(b"hello"), None);
+
+        let metadata = BodyMetadata::inspect(b"\x1f\x8b\x08\x00").unwrap();
+        assert_eq!(
+            metadata,
+            BodyMetadata {
+                file_type: Some(FileType::new("gzip archive", "gz")),
+                size: ByteSize(4),
+                sha256: "fd72d30440b0bae1b1c6db6c8ad807f2\
+                    38ef3ca613aa7e8d5329e1e8ddf7da72"
+                    .into(),
+            }
+        );
+        assert_eq!(
+            metadata.to_string(),
+            "gzip archive · 4 B · SHA-256 \
+            fd72d30440b0bae1b1c6db6c8ad807f238ef3ca613aa7e8d5329e1e8ddf7da72"
+        );
+    }
+}
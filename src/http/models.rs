@@ -4,8 +4,11 @@
 //! exchange is incomplete or failed.
 
 use crate::{
-    collection::{ProfileId, Recipe, RecipeId},
-    http::{cereal, ContentType, ResponseContent},
+    collection::{ProfileId, Recipe, RecipeId, RedirectOptions, SaveRule},
+    http::{
+        cereal, BodyMetadata, ContentType, HostAllowlist, ProtobufRegistry,
+        ResponseContent,
+    },
     util::ResultExt,
 };
 use anyhow::Context;
@@ -13,10 +16,11 @@ use bytes::Bytes;
 use bytesize::ByteSize;
 use chrono::{DateTime, Duration, Utc};
 use derive_more::{Display, From, FromStr};
+use indexmap::IndexMap;
 use mime::Mime;
 use reqwest::{
-    header::{self, HeaderMap},
-    Client, Method, Request, StatusCode, Url,
+    header::{self, HeaderMap, HeaderName},
+    Client, Method, Request, StatusCode, Url, Version,
 };
 use serde::{Deserialize, Serialize};
 use std::{
@@ -100,6 +104,13 @@ pub struct BuildOptions {
     pub disabled_query_parameters: Vec<usize>,
     /// For form bodies, which form fields should be excluded?
     pub disabled_form_fields: Vec<usize>,
+    /// Send the request even if its host isn't in the profile's
+    /// `allowed_hosts`. Only set this once the user has confirmed it
+    pub allow_any_host: bool,
+    /// Send these bytes instead of the recipe's rendered body. Used to send a
+    /// one-off edit of the body without modifying the collection. Ignored if
+    /// the recipe has no body
+    pub body: Option<Bytes>,
 }
 
 /// A request ready to be launched into through the stratosphere. This is
@@ -114,6 +125,22 @@ pub struct RequestTicket {
     pub(super) client: Client,
     /// Our brave little astronaut, ready to be launched...
     pub(super) request: Request,
+    /// Rendered rules for writing the response body to a file
+    pub(super) save: Vec<SaveRule<String>>,
+    /// How to handle redirect responses
+    pub(super) redirects: RedirectPolicy,
+}
+
+/// Everything needed to follow a request's redirects safely
+#[derive(Debug)]
+pub(super) struct RedirectPolicy {
+    /// The recipe's redirect options
+    pub options: RedirectOptions,
+    /// Hosts that redirects can go to
+    pub allowed_hosts: HostAllowlist,
+    /// Header that the recipe's authentication was sent in. This is removed
+    /// if a redirect goes to a different origin
+    pub auth_header: Option<HeaderName>,
 }
 
 impl RequestTicket {
@@ -122,10 +149,21 @@ impl RequestTicket {
     }
 }
 
+/// The result of [RequestTicket::send]. The response body is written to the
+/// recipe's `save` file *after* the exchange completes, so saving can fail
+/// without failing the request.
+#[derive(Debug)]
+pub struct SentExchange {
+    pub exchange: Exchange,
+    /// Error writing the response body to a `save` file. This should be shown
+    /// to the user, but doesn't invalidate the exchange
+    pub save_error: Option<anyhow::Error>,
+}
+
 /// A complete request+response pairing. This is generated by
 /// [RequestTicket::send] when a response is received successfully for a sent
 /// request.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 #[cfg_attr(test, derive(PartialEq))]
 pub struct Exchange {
     /// ID to uniquely refer to this exchange
@@ -149,21 +187,33 @@ impl Exchange {
 
 /// Metadata about an exchange. Useful in lists where request/response content
 /// isn't needed.
-#[derive(Copy, Clone, Debug)]
+#[derive(Clone, Debug)]
 pub struct ExchangeSummary {
     pub id: RequestId,
+    pub profile_id: Option<ProfileId>,
+    pub label: Option<String>,
     pub start_time: DateTime<Utc>,
     pub end_time: DateTime<Utc>,
     pub status: StatusCode,
+    /// Size of the request body. `None` for requests stored before sizes were
+    /// tracked
+    pub request_size: Option<ByteSize>,
+    /// Size of the response body. `None` for requests stored before sizes
+    /// were tracked
+    pub response_size: Option<ByteSize>,
 }
 
 impl From<&Exchange> for ExchangeSummary {
     fn from(exchange: &Exchange) -> Self {
         Self {
             id: exchange.id,
+            profile_id: exchange.request.profile_id.clone(),
+            label: exchange.request.label.clone(),
             start_time: exchange.start_time,
             end_time: exchange.end_time,
             status: exchange.response.status,
+            request_size: Some(exchange.request.body_size()),
+            response_size: Some(exchange.response.body.size()),
         }
     }
 }
@@ -197,6 +247,15 @@ pub struct RequestRecord {
     pub headers: HeaderMap,
     /// Body content as bytes. This should be decoded as needed
     pub body: Option<Bytes>,
+    /// Values of overrides and chains used to render this request, keyed by
+    /// their template key (e.g. `chains.token`). Sensitive values are masked.
+    /// This makes it possible to explain old requests, since chain outputs
+    /// aren't reproducible.
+    #[serde(default)]
+    pub template_values: IndexMap<String, String>,
+    /// Rendered from the recipe's label template, if it has one
+    #[serde(default)]
+    pub label: Option<String>,
 }
 
 impl RequestRecord {
@@ -212,6 +271,8 @@ impl RequestRecord {
     pub(super) fn new(
         seed: RequestSeed,
         profile_id: Option<ProfileId>,
+        template_values: IndexMap<String, String>,
+        label: Option<String>,
         request: &Request,
     ) -> Self {
         Self {
@@ -227,9 +288,16 @@ impl RequestRecord {
                 // https://github.com/LucasPickering/slumber/issues/256
                 Some(body.as_bytes()?.to_owned().into())
             }),
+            template_values,
+            label,
         }
     }
 
+    /// Size of the request *body*. Zero if there is no body
+    pub fn body_size(&self) -> ByteSize {
+        ByteSize(self.body.as_ref().map_or(0, |body| body.len()) as u64)
+    }
+
     /// Generate a cURL command equivalent to this request
     ///
     /// This only fails if one of the headers or body is binary and can't be
@@ -280,6 +348,8 @@ impl crate::test_util::Factory for RequestRecord {
             url: "http://localhost/url".parse().unwrap(),
             headers: HeaderMap::new(),
             body: None,
+            template_values: IndexMap::new(),
+            label: None,
         }
     }
 }
@@ -298,6 +368,8 @@ impl crate::test_util::Factory<(Option<ProfileId>, RecipeId)>
             url: "http://localhost/url".parse().unwrap(),
             headers: HeaderMap::new(),
             body: None,
+            template_values: IndexMap::new(),
+            label: None,
         }
     }
 }
@@ -307,8 +379,10 @@ impl crate::test_util::Factory for ResponseRecord {
     fn factory(_: ()) -> Self {
         Self {
             status: StatusCode::OK,
+            version: Version::HTTP_11,
             headers: HeaderMap::new(),
             body: ResponseBody::default(),
+            redirects: Vec::new(),
         }
     }
 }
@@ -357,19 +431,29 @@ impl crate::test_util::Factory<(Option<ProfileId>, RecipeId)> for Exchange {
 pub struct ResponseRecord {
     #[serde(with = "cereal::serde_status_code")]
     pub status: StatusCode,
+    /// Negotiated HTTP version. Defaulted for responses stored before this
+    /// field was added
+    #[serde(default, with = "cereal::serde_version")]
+    pub version: Version,
     #[serde(with = "cereal::serde_header_map")]
     pub headers: HeaderMap,
     pub body: ResponseBody,
+    /// Redirects that were followed to get to this response, in order. Empty
+    /// if the original request got the final response directly
+    #[serde(default)]
+    pub redirects: Vec<Redirect>,
 }
 
 impl ResponseRecord {
     /// Attempt to parse the body of this response, and store it in the body
-    /// struct. If parsing fails, we'll store `None` instead.
-    pub fn parse_body(&self) {
-        let body = ContentType::parse_response(self)
+    /// struct. If parsing fails, we'll store `None` instead. Protobuf bodies
+    /// need the collection's compiled schema to be decoded.
+    pub fn parse_body(&self, protobuf: Option<&ProtobufRegistry>) {
+        let body = ContentType::parse_response(self, protobuf)
             .context("Error parsing response body")
             .traced()
-            .ok();
+            .ok()
+            .map(Arc::from);
         // Store whether we succeeded or not, so we know not to try again
         if self.body.parsed.set(body).is_err() {
             // Unfortunately we don't have any helpful context to include here.
@@ -398,16 +482,57 @@ impl ResponseRecord {
                     }
                 })
             })
-            .or_else(|| {
-                // Grab the extension from the Content-Type header. Don't use
-                // self.conten_type() because we want to accept unknown types.
-                let content_type = self.headers.get(header::CONTENT_TYPE)?;
-                let mime: Mime = content_type.to_str().ok()?.parse().ok()?;
-                Some(format!("data.{}", mime.subtype()))
-            })
+            .or_else(|| Some(format!("data.{}", self.extension()?)))
+    }
+
+    /// Guess a file extension for the content of this response. For binary
+    /// bodies with a recognized file signature, the signature wins, because
+    /// servers often send a generic `application/octet-stream`. Otherwise
+    /// use the Content-Type header. Don't use [ContentType::from_response]
+    /// because we want to accept unknown types. For structured types like
+    /// `application/problem+json`, the suffix is used.
+    pub fn extension(&self) -> Option<String> {
+        if let Some(file_type) =
+            self.body.metadata().and_then(|metadata| metadata.file_type)
+        {
+            return Some(file_type.extension.to_owned());
+        }
+        let content_type = self.headers.get(header::CONTENT_TYPE)?;
+        let mime: Mime = content_type.to_str().ok()?.parse().ok()?;
+        let extension = mime.suffix().unwrap_or(mime.subtype());
+        Some(extension.as_str().to_owned())
+    }
+
+    /// Get a copy of this response with the body cut down to at most
+    /// `max_size` bytes. Used to limit how much data is stored in history.
+    /// The body isn't parsed, so a truncated body may not parse at all.
+    pub fn truncated(&self, max_size: ByteSize) -> Self {
+        let data = &self.body.data;
+        let len = data.len().min(max_size.as_u64() as usize);
+        Self {
+            status: self.status,
+            version: self.version,
+            headers: self.headers.clone(),
+            body: ResponseBody::new(data.slice(..len)),
+            redirects: self.redirects.clone(),
+        }
     }
 }
 
+/// A single hop in a redirect chain. This records the redirect response, but
+/// not its body, which is typically empty or useless
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(test, derive(PartialEq))]
+pub struct Redirect {
+    /// URL that was requested
+    pub url: Url,
+    /// Status of the redirect response (3xx)
+    #[serde(with = "cereal::serde_status_code")]
+    pub status: StatusCode,
+    /// Where the server sent us next, resolved from the `Location` header
+    pub location: Url,
+}
+
 /// HTTP response body. Content is stored as bytes because it may not
 /// necessarily be valid UTF-8. Converted to text only as needed.
 #[derive(Default, Deserialize)]
@@ -419,7 +544,11 @@ pub struct ResponseBody {
     /// real data structure. This is populated *eagerly*. Call
     /// [ResponseRecord::parse_body] to set the parsed body.
     #[serde(skip)]
-    parsed: OnceLock<Option<Box<dyn ResponseContent>>>,
+    parsed: OnceLock<Option<Arc<dyn ResponseContent>>>,
+    /// Metadata for binary bodies. Computed lazily on first access, because
+    /// it requires hashing the whole body
+    #[serde(skip)]
+    metadata: OnceLock<Option<BodyMetadata>>,
 }
 
 impl ResponseBody {
@@ -427,6 +556,7 @@ impl ResponseBody {
         Self {
             data,
             parsed: Default::default(),
+            metadata: Default::default(),
         }
     }
 
@@ -435,6 +565,12 @@ impl ResponseBody {
         &self.data
     }
 
+    /// Get a cheap clone of the raw content, so it can be processed on
+    /// another thread
+    pub fn shared_bytes(&self) -> Bytes {
+        self.data.clone()
+    }
+
     /// Owned raw content bytes
     pub fn into_bytes(self) -> Bytes {
         self.data
@@ -459,6 +595,20 @@ impl ResponseBody {
     pub fn parsed(&self) -> Option<&dyn ResponseContent> {
         self.parsed.get().and_then(Option::as_deref)
     }
+
+    /// Get a cheap clone of the parsed body, so it can be processed on another
+    /// thread. Same rules as [Self::parsed] apply
+    pub fn shared_parsed(&self) -> Option<Arc<dyn ResponseContent>> {
+        self.parsed.get().cloned().flatten()
+    }
+
+    /// Get file type, size, and checksum for this body. Return `None` if the
+    /// body is text.
+    pub fn metadata(&self) -> Option<&BodyMetadata> {
+        self.metadata
+            .get_or_init(|| BodyMetadata::inspect(&self.data))
+            .as_ref()
+    }
 }
 
 impl Debug for ResponseBody {
@@ -553,8 +703,8 @@ impl PartialEq for RequestBuildError {
     .request.id,
 )]
 pub struct RequestError {
-    /// Underlying error. This will always be a `reqwest::Error`, but wrapping
-    /// it in anyhow makes it easier to render
+    /// Underlying error. This is usually a `reqwest::Error`, but can also be
+    /// a redirect failure. Wrapping it in anyhow makes it easier to render
     #[source]
     pub error: anyhow::Error,
 
@@ -615,6 +765,25 @@ mod tests {
         },
         Some("data.jpeg")
     )]
+    #[case::content_type_suffix(
+        ResponseRecord {
+            headers: header_map(indexmap! {
+                "content-type" => "application/problem+json",
+            }),
+            ..ResponseRecord::factory(())
+        },
+        Some("data.json")
+    )]
+    #[case::file_signature(
+        ResponseRecord {
+            headers: header_map(indexmap! {
+                "content-type" => "application/octet-stream",
+            }),
+            body: b"\x89PNG\r\n\x1a\n\x00".as_slice().into(),
+            ..ResponseRecord::factory(())
+        },
+        Some("data.png")
+    )]
     #[case::none(ResponseRecord::factory(()), None)]
     fn test_file_name(
         #[case] response: ResponseRecord,
@@ -0,0 +1,216 @@
+//! Background commands, such as `kubectl port-forward`, that need to be running
+//! before requests with certain profiles can be sent
+
+use crate::{collection::ProfileId, config::PortForward};
+use anyhow::{anyhow, bail, Context};
+use indexmap::IndexMap;
+use std::{
+    collections::HashMap,
+    process::Stdio,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::{
+    net::TcpStream,
+    process::{Child, Command},
+    time,
+};
+use tracing::info;
+
+/// How often to check if a forward's port is ready
+const READY_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Manages the process for each configured port forward. A forward is started
+/// the first time it's needed, and runs until [Self::stop] is called or the
+/// last clone of this is dropped.
+#[derive(Clone, Debug, Default)]
+pub struct PortForwards {
+    forwards: Arc<IndexMap<String, PortForward>>,
+    /// Running processes, keyed by forward name
+    children: Arc<Mutex<HashMap<String, Child>>>,
+    /// Held while starting forwards, so concurrent requests don't start the
+    /// same forward twice
+    start_lock: Arc<tokio::sync::Mutex<()>>,
+}
+
+impl PortForwards {
+    pub fn new(forwards: IndexMap<String, PortForward>) -> Self {
+        Self {
+            forwards: Arc::new(forwards),
+            ..Self::default()
+        }
+    }
+
+    /// Make sure every forward needed by a profile is running and ready to
+    /// accept connections
+    pub async fn start(&self, profile_id: &ProfileId) -> anyhow::Result<()> {
+        // Collect up front, because a lazy iterator held across the awaits
+        // below makes the future !Send
+        let forwards: Vec<_> = self
+            .forwards
+            .iter()
+            .filter(|(_, forward)| forward.profiles.contains(profile_id))
+            .collect();
+        if forwards.is_empty() {
+            return Ok(());
+        }
+
+        let _guard = self.start_lock.lock().await;
+        for (name, forward) in forwards {
+            if self.is_running(name) {
+                continue;
+            }
+            // The user may already be forwarding this port themselves
+            if is_port_open(forward.port).await {
+                info!(name, port = forward.port, "Port is already open");
+                continue;
+            }
+            let child = forward.spawn(name).await.with_context(|| {
+                format!("Error starting port forward `{name}`")
+            })?;
+            self.children().insert(name.clone(), child);
+        }
+        Ok(())
+    }
+
+    /// Kill all running forwards
+    pub fn stop(&self) {
+        for (name, mut child) in self.children().drain() {
+            info!(name, "Stopping port forward");
+            // The process may have already exited
+            let _ = child.start_kill();
+        }
+    }
+
+    /// Is a forward's process still running?
+    fn is_running(&self, name: &str) -> bool {
+        self.children()
+            .get_mut(name)
+            .is_some_and(|child| matches!(child.try_wait(), Ok(None)))
+    }
+
+    fn children(&self) -> std::sync::MutexGuard<'_, HashMap<String, Child>> {
+        self.children.lock().expect("Port forward lock poisoned")
+    }
+}
+
+impl PortForward {
+    /// Spawn the command, and wait until its port accepts connections. If the
+    /// command exits or the timeout expires first, it's killed and an error is
+    /// returned.
+    async fn spawn(&self, name: &str) -> anyhow::Result<Child> {
+        let [program, args @ ..] = self.command.as_slice() else {
+            bail!("`command` is empty");
+        };
+        info!(name, command = ?self.command, "Starting port forward");
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            // Make sure the process doesn't outlive us
+            .kill_on_drop(true)
+            .spawn()
+            .with_context(|| format!("Error executing `{program}`"))?;
+
+        let ready = async {
+            loop {
+                if let Some(status) = child.try_wait()? {
+                    bail!(
+                        "`{program}` exited with {status} before port {} was \
+                        ready",
+                        self.port
+                    );
+                }
+                if is_port_open(self.port).await {
+                    return Ok(());
+                }
+                time::sleep(READY_INTERVAL).await;
+            }
+        };
+        let result = time::timeout(self.timeout, ready).await;
+        // Dropping the child on error kills it
+        result.map_err(|_| {
+            anyhow!(
+                "Port {} was not ready after {}s",
+                self.port,
+                self.timeout.as_secs_f32()
+            )
+        })??;
+        info!(name, port = self.port, "Port forward is ready");
+        Ok(child)
+    }
+}
+
+/// Is something listening on a local port?
+async fn is_port_open(port: u16) -> bool {
+    TcpStream::connect(("127.0.0.1", port)).await.is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::assert_err;
+    use std::net::TcpListener;
+
+    /// Get a port that nothing is listening on
+    fn free_port() -> u16 {
+        TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port()
+    }
+
+    fn forwards(command: &[&str], port: u16) -> PortForwards {
+        PortForwards::new(IndexMap::from([(
+            "api".to_owned(),
+            PortForward {
+                command: command.iter().map(|arg| arg.to_string()).collect(),
+                profiles: vec!["staging".into()],
+                port,
+                timeout: Duration::from_millis(500),
+            },
+        )]))
+    }
+
+    /// Profiles that don't need a forward don't start anything
+    #[tokio::test]
+    async fn test_start_other_profile() {
+        let forwards = forwards(&["false"], free_port());
+        forwards.start(&"production".into()).await.unwrap();
+        assert!(forwards.children().is_empty());
+    }
+
+    /// If the port is already open, the command isn't run
+    #[tokio::test]
+    async fn test_start_already_open() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let forwards = forwards(&["false"], port);
+        forwards.start(&"staging".into()).await.unwrap();
+        assert!(forwards.children().is_empty());
+    }
+
+    /// The command exits before the port is ready
+    #[tokio::test]
+    async fn test_start_exited() {
+        let forwards = forwards(&["false"], free_port());
+        assert_err!(
+            forwards.start(&"staging".into()).await,
+            "`false` exited with exit status: 1"
+        );
+        assert!(forwards.children().is_empty());
+    }
+
+    /// The port never opens
+    #[tokio::test]
+    async fn test_start_timeout() {
+        let port = free_port();
+        let forwards = forwards(&["sleep", "5"], port);
+        assert_err!(
+            forwards.start(&"staging".into()).await,
+            &format!("Port {port} was not ready after 0.5s")
+        );
+    }
+}
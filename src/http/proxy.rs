@@ -0,0 +1,122 @@
+//! HTTP proxy configuration
+
+use derive_more::Display;
+use itertools::Itertools;
+use reqwest::{ClientBuilder, NoProxy, Proxy, Url};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Proxy settings. These are defined globally in the config, and can be
+/// overridden per-profile. A profile's proxy settings *replace* the global
+/// settings entirely, rather than being merged with them.
+///
+/// If no proxy settings are defined anywhere, the standard `HTTP_PROXY`/
+/// `HTTPS_PROXY`/`ALL_PROXY`/`NO_PROXY` environment variables are used.
+#[derive(
+    Clone,
+    Debug,
+    Default,
+    Eq,
+    Hash,
+    PartialEq,
+    JsonSchema,
+    Serialize,
+    Deserialize,
+)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProxyOptions {
+    /// Proxy for `http://` requests
+    pub http: Option<ProxyUrl>,
+    /// Proxy for `https://` requests
+    pub https: Option<ProxyUrl>,
+    /// Proxy for all requests. `http` and `https` take precedence over this
+    pub all: Option<ProxyUrl>,
+    /// Hosts that should bypass the proxy. Supports domains (which include
+    /// subdomains), IP addresses, and CIDR blocks. `*` bypasses all hosts.
+    pub no_proxy: Vec<String>,
+}
+
+/// URL of a proxy server. The scheme is validated during deserialization, so
+/// that building the proxy can't fail later.
+#[derive(
+    Clone, Debug, Display, Eq, Hash, PartialEq, Serialize, Deserialize,
+)]
+#[serde(into = "String", try_from = "String")]
+pub struct ProxyUrl(Url);
+
+impl ProxyUrl {
+    const SCHEMES: &'static [&'static str] =
+        &["http", "https", "socks5", "socks5h"];
+}
+
+impl TryFrom<String> for ProxyUrl {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let url: Url = value.parse()?;
+        if Self::SCHEMES.contains(&url.scheme()) {
+            Ok(Self(url))
+        } else {
+            Err(anyhow::anyhow!(
+                "Unsupported proxy scheme `{}`; must be one of: {}",
+                url.scheme(),
+                Self::SCHEMES.iter().format(", ")
+            ))
+        }
+    }
+}
+
+impl From<ProxyUrl> for String {
+    fn from(url: ProxyUrl) -> Self {
+        url.0.into()
+    }
+}
+
+impl ProxyOptions {
+    /// Apply these settings to a client. If no proxies are defined, proxying
+    /// is disabled entirely, including the system proxy. This allows a
+    /// profile to opt out of the global proxy with `proxy: {}`.
+    pub fn apply(&self, mut builder: ClientBuilder) -> ClientBuilder {
+        let no_proxy = NoProxy::from_string(&self.no_proxy.join(","));
+        let proxies = [
+            self.http.as_ref().map(|url| Proxy::http(url.0.clone())),
+            self.https.as_ref().map(|url| Proxy::https(url.0.clone())),
+            self.all.as_ref().map(|url| Proxy::all(url.0.clone())),
+        ];
+
+        let mut any = false;
+        for proxy in proxies.into_iter().flatten() {
+            // The scheme was validated during deserialization
+            let proxy = proxy.expect("Invalid proxy URL");
+            builder = builder.proxy(proxy.no_proxy(no_proxy.clone()));
+            any = true;
+        }
+        if !any {
+            builder = builder.no_proxy();
+        }
+        builder
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::assert_err;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::http("http://localhost:8080")]
+    #[case::https("https://proxy.example.com")]
+    #[case::socks("socks5://127.0.0.1:1080")]
+    fn test_parse_proxy_url(#[case] url: &str) {
+        let parsed = ProxyUrl::try_from(url.to_owned()).unwrap();
+        assert_eq!(parsed.0.as_str().trim_end_matches('/'), url);
+    }
+
+    #[rstest]
+    #[case::invalid("not a url", "relative URL without a base")]
+    #[case::scheme("ftp://localhost", "Unsupported proxy scheme `ftp`")]
+    fn test_parse_proxy_url_error(#[case] url: &str, #[case] error: &str) {
+        assert_err!(ProxyUrl::try_from(url.to_owned()), error);
+    }
+}
@@ -1,10 +1,14 @@
 //! Utilities for querying HTTP response data
 
-use crate::http::ResponseContent;
+use crate::http::{ContentType, ResponseContent};
+use anyhow::anyhow;
 use derive_more::{Display, FromStr};
+use regex::Regex;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_json_path::{ExactlyOneError, JsonPath};
 use std::borrow::Cow;
+use sxd_xpath::{nodeset::Node, Value};
 use thiserror::Error;
 
 /// A wrapper around a JSONPath. This combines some common behavior, and will
@@ -22,6 +26,22 @@ pub enum QueryError {
         #[source]
         error: ExactlyOneError,
     },
+    /// Got either 0 or 2+ results for a query, when exactly one was needed
+    #[error("Expected exactly one result from query, but got {count}")]
+    ResultCount { count: usize },
+    /// Selector isn't valid in the query language of the content type
+    #[error("Invalid selector `{selector}`")]
+    InvalidSelector {
+        selector: Selector,
+        #[source]
+        error: anyhow::Error,
+    },
+    /// Content couldn't be parsed as a document to query
+    #[error("Parsing response")]
+    ParseDocument {
+        #[source]
+        error: anyhow::Error,
+    },
 }
 
 impl Query {
@@ -55,20 +75,168 @@ impl Query {
         // back. This is fucky but we need *some* common format
         let json_value = value.to_json();
         let queried = self.0.query(&json_value).exactly_one()?;
+        Ok(stringify(content_type, queried))
+    }
+
+    /// Apply a query to some content, returning *all* results as strings.
+    /// Each result is stringified the same as in [Self::query_to_string].
+    pub fn query_to_strings(&self, value: &dyn ResponseContent) -> Vec<String> {
+        let content_type = value.content_type();
+        let json_value = value.to_json();
+        self.0
+            .query(&json_value)
+            .into_iter()
+            .map(|queried| stringify(content_type, queried))
+            .collect()
+    }
+}
+
+/// Convert a query result to a string. If we got a scalar value, use that.
+/// Otherwise convert back to the input content type to re-stringify
+fn stringify(content_type: ContentType, queried: &serde_json::Value) -> String {
+    match queried {
+        serde_json::Value::Null => "".into(),
+        serde_json::Value::Number(n) => n.to_string(),
+        serde_json::Value::Bool(b) => b.to_string(),
+        serde_json::Value::String(s) => s.clone(),
+        serde_json::Value::Array(_) | serde_json::Value::Object(_) => {
+            content_type.parse_json(Cow::Borrowed(queried)).to_string()
+        }
+    }
+}
+
+/// A chain selector, to extract a single value from some content. The query
+/// language depends on the content type: JSONPath for JSON, XPath for XML, and
+/// CSS selectors for HTML. The content type often isn't known until the content
+/// is loaded, so the selector is stored raw and only parsed when it's applied.
+#[derive(
+    Clone,
+    Debug,
+    Display,
+    FromStr,
+    PartialEq,
+    JsonSchema,
+    Serialize,
+    Deserialize,
+)]
+#[serde(transparent)]
+pub struct Selector(String);
 
-        // If we got a scalar value, use that. Otherwise convert back to the
-        // input content type to re-stringify
-        let stringified = match queried {
-            serde_json::Value::Null => "".into(),
-            serde_json::Value::Number(n) => n.to_string(),
-            serde_json::Value::Bool(b) => b.to_string(),
-            serde_json::Value::String(s) => s.clone(),
-            serde_json::Value::Array(_) | serde_json::Value::Object(_) => {
-                content_type.parse_json(Cow::Borrowed(queried)).to_string()
-            }
+impl Selector {
+    /// Start of the suffix for CSS selectors to select an attribute, rather
+    /// than the text of the matched element, e.g.
+    /// `input[name=csrf]::attr(value)`. CSS has no way to do this natively, so
+    /// we borrow the syntax from Scrapy.
+    const ATTRIBUTE_PREFIX: &'static str = "::attr(";
+
+    /// Parse as a JSONPath query
+    pub fn json(&self) -> Result<Query, QueryError> {
+        self.0
+            .parse::<Query>()
+            .map_err(|error| self.invalid(anyhow!("{error}")))
+    }
+
+    /// Apply as an XPath query to an XML document. Each matched node is
+    /// converted to its text content. If the query returns a scalar value
+    /// instead, that will be the only result.
+    pub fn query_xml(&self, document: &str) -> Result<Vec<String>, QueryError> {
+        let package =
+            sxd_document::parser::parse(document).map_err(|error| {
+                QueryError::ParseDocument {
+                    error: anyhow!("{error}"),
+                }
+            })?;
+        let document = package.as_document();
+        let xpath = sxd_xpath::Factory::new()
+            .build(&self.0)
+            .map_err(|error| self.invalid(error.into()))?
+            .ok_or_else(|| self.invalid(anyhow!("Empty XPath")))?;
+        let value = xpath
+            .evaluate(&sxd_xpath::Context::new(), document.root())
+            .map_err(|error| self.invalid(error.into()))?;
+        let results = match value {
+            Value::Nodeset(nodes) => nodes
+                .document_order()
+                .iter()
+                .map(Node::string_value)
+                .collect(),
+            Value::String(s) => vec![s],
+            Value::Number(n) => vec![n.to_string()],
+            Value::Boolean(b) => vec![b.to_string()],
+        };
+        Ok(results)
+    }
+
+    /// Apply as a CSS selector to an HTML document. For each matched element,
+    /// its text content will be returned, or the value of one of its
+    /// attributes if the selector ends in `::attr(<name>)`.
+    pub fn query_html(
+        &self,
+        document: &str,
+    ) -> Result<Vec<String>, QueryError> {
+        let (selector, attribute) =
+            match self.0.rsplit_once(Self::ATTRIBUTE_PREFIX) {
+                Some((selector, attribute)) => {
+                    let attribute =
+                        attribute.strip_suffix(')').ok_or_else(|| {
+                            self.invalid(anyhow!(
+                                "Missing `)` after attribute name"
+                            ))
+                        })?;
+                    (selector, Some(attribute.trim()))
+                }
+                None => (self.0.as_str(), None),
+            };
+        let selector = scraper::Selector::parse(selector)
+            .map_err(|error| self.invalid(anyhow!("{error}")))?;
+
+        let html = scraper::Html::parse_document(document);
+        let elements = html.select(&selector);
+        let results = match attribute {
+            // Elements without the attribute are skipped, so the attribute
+            // doesn't need to be repeated in the selector
+            Some(attribute) => elements
+                .filter_map(|element| element.value().attr(attribute))
+                .map(String::from)
+                .collect(),
+            None => elements
+                .map(|element| element.text().collect::<String>())
+                .collect(),
         };
+        Ok(results)
+    }
+
+    /// Apply as a regex to some text. The regex must have exactly one capture
+    /// group. The value of that group in each match is returned.
+    pub fn query_regex(&self, text: &str) -> Result<Vec<String>, QueryError> {
+        let regex =
+            Regex::new(&self.0).map_err(|error| self.invalid(error.into()))?;
+        // The implicit group for the entire match counts as one
+        if regex.captures_len() != 2 {
+            return Err(self.invalid(anyhow!(
+                "Regex must have exactly one capture group"
+            )));
+        }
+        let results = regex
+            .captures_iter(text)
+            .map(|captures| {
+                // If the group is optional and didn't participate, use an
+                // empty string
+                captures
+                    .get(1)
+                    .map(|group| group.as_str())
+                    .unwrap_or_default()
+                    .to_owned()
+            })
+            .collect();
+        Ok(results)
+    }
 
-        Ok(stringified)
+    fn invalid(&self, error: anyhow::Error) -> QueryError {
+        QueryError::InvalidSelector {
+            selector: self.clone(),
+            error,
+        }
     }
 }
 
@@ -106,6 +274,112 @@ mod tests {
         assert_err!(query.query_to_string(&*content), expected_err);
     }
 
+    #[rstest]
+    #[case::multiple(
+        "$[*]",
+        json(json!([1, "a", {"b": 2}])),
+        &["1", "a", r#"{"b":2}"#]
+    )]
+    #[case::no_results("$.missing", json(json!({})), &[])]
+    fn test_query_to_strings(
+        #[case] query: &str,
+        #[case] content: Box<dyn ResponseContent>,
+        #[case] expected: &[&str],
+    ) {
+        let query = Query::from_str(query).unwrap();
+        assert_eq!(query.query_to_strings(&*content), expected);
+    }
+
+    #[rstest]
+    #[case::text("/fish/name", &["Jimmy"])]
+    #[case::attribute("/fish/@id", &["3"])]
+    #[case::predicate("//tag[@primary='true']", &["blue"])]
+    #[case::multiple("//tag", &["blue", "red"])]
+    #[case::no_results("//missing", &[])]
+    #[case::number("count(//tag)", &["2"])]
+    #[case::namespace(
+        "//*[local-name()='Body']/*[local-name()='token']",
+        &["abc"]
+    )]
+    fn test_query_xml(#[case] selector: &str, #[case] expected: &[&str]) {
+        let document = r#"<fish id="3">
+            <name>Jimmy</name>
+            <tag primary="true">blue</tag>
+            <tag>red</tag>
+            <s:Body xmlns:s="http://schemas.xmlsoap.org/soap/envelope/">
+                <s:token>abc</s:token>
+            </s:Body>
+        </fish>"#;
+        let selector = Selector::from_str(selector).unwrap();
+        assert_eq!(selector.query_xml(document).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case::invalid_selector("//[", "<a/>", "Invalid selector `//[`")]
+    #[case::invalid_document("/a", "<a>", "Parsing response")]
+    fn test_query_xml_error(
+        #[case] selector: &str,
+        #[case] document: &str,
+        #[case] expected_err: &str,
+    ) {
+        let selector = Selector::from_str(selector).unwrap();
+        assert_err!(selector.query_xml(document), expected_err);
+    }
+
+    #[rstest]
+    #[case::text("h1.title", &["Fish"])]
+    #[case::multiple("a", &["Previous", "Next"])]
+    #[case::attribute("input[name=csrf]::attr(value)", &["hunter2"])]
+    #[case::attribute_filter("a::attr(href)", &["/next"])]
+    #[case::no_results("table", &[])]
+    fn test_query_html(#[case] selector: &str, #[case] expected: &[&str]) {
+        let document = r#"<html><body>
+            <h1 class="title">Fish</h1>
+            <a class="prev">Previous</a>
+            <a href="/next">Next</a>
+            <form><input name="csrf" value="hunter2"></form>
+        </body></html>"#;
+        let selector = Selector::from_str(selector).unwrap();
+        assert_eq!(selector.query_html(document).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case::invalid_selector("a[", "Invalid selector `a[`")]
+    #[case::invalid_attribute("a::attr(href", "Missing `)`")]
+    fn test_query_html_error(
+        #[case] selector: &str,
+        #[case] expected_err: &str,
+    ) {
+        let selector = Selector::from_str(selector).unwrap();
+        assert_err!(selector.query_html("<a>1</a>"), expected_err);
+    }
+
+    #[rstest]
+    #[case::simple(r"token=(\w+)", "token=abc123; other=1", &["abc123"])]
+    #[case::multiple(r"id: (\d+)", "id: 1\nid: 2", &["1", "2"])]
+    #[case::multiline(r"(?m)^version (.+)$", "name x\nversion 1.2", &["1.2"])]
+    #[case::no_match(r"token=(\d+)", "token=abc", &[])]
+    fn test_query_regex(
+        #[case] selector: &str,
+        #[case] text: &str,
+        #[case] expected: &[&str],
+    ) {
+        let selector = Selector::from_str(selector).unwrap();
+        assert_eq!(selector.query_regex(text).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case::no_group(r"token=\w+", "exactly one capture group")]
+    #[case::two_groups(r"(\w+)=(\w+)", "exactly one capture group")]
+    #[case::invalid(r"token=(", "Invalid selector")]
+    fn test_query_regex_error(
+        #[case] selector: &str,
+        #[case] expected_err: &str,
+    ) {
+        let selector = Selector::from_str(selector).unwrap();
+        assert_err!(selector.query_regex("token=abc"), expected_err);
+    }
+
     /// Helper to create JSON content
     fn json(value: serde_json::Value) -> Box<dyn ResponseContent> {
         Box::new(Json::from(value))
@@ -0,0 +1,275 @@
+//! TLS configuration for individual requests
+
+use crate::{
+    collection::{ClientCertificate, TlsOptions},
+    template::{Template, TemplateContext},
+};
+use anyhow::{anyhow, Context};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use futures::future::OptionFuture;
+use reqwest::{Certificate, ClientBuilder, Identity};
+use std::{fmt::Write, path::Path};
+use tokio::fs;
+
+impl TlsOptions {
+    /// Combine recipe-level options with profile-level options. Each field
+    /// defined on the recipe takes precedence over the profile. Return `None`
+    /// if neither defines any options.
+    pub fn merge(
+        recipe: Option<&Self>,
+        profile: Option<&Self>,
+    ) -> Option<Self> {
+        match (recipe, profile) {
+            (None, None) => None,
+            (Some(options), None) | (None, Some(options)) => {
+                Some(options.clone())
+            }
+            (Some(recipe), Some(profile)) => Some(Self {
+                client_certificate: recipe
+                    .client_certificate
+                    .clone()
+                    .or_else(|| profile.client_certificate.clone()),
+                ca_bundle: recipe
+                    .ca_bundle
+                    .clone()
+                    .or_else(|| profile.ca_bundle.clone()),
+                danger_accept_invalid_certs: recipe
+                    .danger_accept_invalid_certs
+                    .or(profile.danger_accept_invalid_certs),
+            }),
+        }
+    }
+
+    /// Render all file paths and passwords
+    pub async fn render(
+        &self,
+        template_context: &TemplateContext,
+    ) -> anyhow::Result<TlsOptions<String>> {
+        let client_certificate = match &self.client_certificate {
+            Some(ClientCertificate::Pem { certificate, key }) => {
+                Some(ClientCertificate::Pem {
+                    certificate: certificate
+                        .render_string(template_context)
+                        .await
+                        .context("Error rendering client certificate path")?,
+                    key: render_optional(key.as_ref(), template_context)
+                        .await
+                        .context("Error rendering client key path")?,
+                })
+            }
+            Some(ClientCertificate::Pkcs12 { path, password }) => {
+                Some(ClientCertificate::Pkcs12 {
+                    path: path
+                        .render_string(template_context)
+                        .await
+                        .context("Error rendering client certificate path")?,
+                    password: render_optional(
+                        password.as_ref(),
+                        template_context,
+                    )
+                    .await
+                    .context("Error rendering client certificate password")?,
+                })
+            }
+            None => None,
+        };
+        let ca_bundle =
+            render_optional(self.ca_bundle.as_ref(), template_context)
+                .await
+                .context("Error rendering CA bundle path")?;
+        Ok(TlsOptions {
+            client_certificate,
+            ca_bundle,
+            danger_accept_invalid_certs: self.danger_accept_invalid_certs,
+        })
+    }
+}
+
+impl TlsOptions<String> {
+    /// Load all referenced files from disk. This is done up front, before the
+    /// client cache is checked, so that the file contents can be part of the
+    /// cache key. That way a rotated certificate gets a new client, rather
+    /// than reusing one built from the old file.
+    pub async fn load(&self) -> anyhow::Result<TlsFiles> {
+        let ca_bundle = match &self.ca_bundle {
+            Some(path) => Some(read_file(path, "CA bundle").await?),
+            None => None,
+        };
+        let identity = match &self.client_certificate {
+            Some(client_certificate) => Some(client_certificate.load().await?),
+            None => None,
+        };
+        Ok(TlsFiles {
+            ca_bundle,
+            identity,
+            danger_accept_invalid_certs: self.danger_accept_invalid_certs,
+        })
+    }
+}
+
+/// TLS options with all referenced files loaded into memory. Used as part of
+/// the key for the client cache.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct TlsFiles {
+    /// Contents of the CA bundle file
+    ca_bundle: Option<Vec<u8>>,
+    /// Client certificate and key, as PEM
+    identity: Option<Vec<u8>>,
+    pub danger_accept_invalid_certs: Option<bool>,
+}
+
+impl TlsFiles {
+    /// Apply these options to a client builder
+    pub fn apply(
+        &self,
+        mut builder: ClientBuilder,
+    ) -> anyhow::Result<ClientBuilder> {
+        if let Some(pem) = &self.ca_bundle {
+            let certificates = Certificate::from_pem_bundle(pem)
+                .context("Error parsing CA bundle")?;
+            for certificate in certificates {
+                builder = builder.add_root_certificate(certificate);
+            }
+        }
+        if let Some(pem) = &self.identity {
+            builder = builder.identity(
+                Identity::from_pem(pem)
+                    .context("Error loading client certificate")?,
+            );
+        }
+        if self.danger_accept_invalid_certs.unwrap_or(false) {
+            builder = builder.danger_accept_invalid_certs(true);
+        }
+        Ok(builder)
+    }
+}
+
+impl ClientCertificate<String> {
+    /// Load the certificate+key from disk, as PEM
+    async fn load(&self) -> anyhow::Result<Vec<u8>> {
+        match self {
+            Self::Pem { certificate, key } => {
+                let mut pem =
+                    read_file(certificate, "client certificate").await?;
+                if let Some(key) = key {
+                    pem.push(b'\n');
+                    pem.extend(read_file(key, "client key").await?);
+                }
+                Ok(pem)
+            }
+            // The rustls backend can only load identities from PEM, so we
+            // have to convert
+            Self::Pkcs12 { path, password } => Ok(pkcs12_to_pem(
+                &read_file(path, "client certificate").await?,
+                password.as_deref().unwrap_or_default(),
+            )
+            .with_context(|| format!("Error decoding PKCS#12 file {path:?}"))?
+            .into_bytes()),
+        }
+    }
+}
+
+/// Render a template that may not be defined
+async fn render_optional(
+    template: Option<&Template>,
+    template_context: &TemplateContext,
+) -> anyhow::Result<Option<String>> {
+    Ok(OptionFuture::from(
+        template.map(|template| template.render_string(template_context)),
+    )
+    .await
+    .transpose()?)
+}
+
+/// Read a file referenced by TLS options, with a descriptive error
+async fn read_file(
+    path: impl AsRef<Path>,
+    label: &str,
+) -> anyhow::Result<Vec<u8>> {
+    let path = path.as_ref();
+    fs::read(path)
+        .await
+        .with_context(|| format!("Error reading {label} {path:?}"))
+}
+
+/// Convert a PKCS#12 archive into a PEM string containing its private key(s)
+/// and certificate(s)
+fn pkcs12_to_pem(der: &[u8], password: &str) -> anyhow::Result<String> {
+    // The p12 error type is very sparse so just stringify it
+    let pfx = p12::PFX::parse(der).map_err(|error| anyhow!("{error:?}"))?;
+    let keys = pfx
+        .key_bags(password)
+        .map_err(|error| anyhow!("Error decrypting private key: {error:?}"))?;
+    let certificates = pfx
+        .cert_x509_bags(password)
+        .map_err(|error| anyhow!("Error decrypting certificate: {error:?}"))?;
+
+    let mut pem = String::new();
+    for key in keys {
+        write_pem(&mut pem, "PRIVATE KEY", &key);
+    }
+    for certificate in certificates {
+        write_pem(&mut pem, "CERTIFICATE", &certificate);
+    }
+    Ok(pem)
+}
+
+/// Encode DER bytes as a PEM block
+fn write_pem(pem: &mut String, label: &str, der: &[u8]) {
+    let encoded = STANDARD.encode(der);
+    // Writing to a string can't fail
+    let _ = writeln!(pem, "-----BEGIN {label}-----");
+    // Base64 is pure ASCII, so we can safely split into lines by byte
+    for line in encoded.as_bytes().chunks(64) {
+        let _ = writeln!(pem, "{}", String::from_utf8_lossy(line));
+    }
+    let _ = writeln!(pem, "-----END {label}-----");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Recipe fields should take precedence over profile fields
+    #[test]
+    fn test_merge() {
+        let recipe = TlsOptions {
+            ca_bundle: Some("recipe.pem".into()),
+            ..Default::default()
+        };
+        let profile = TlsOptions {
+            ca_bundle: Some("profile.pem".into()),
+            client_certificate: Some(ClientCertificate::Pem {
+                certificate: "client.pem".into(),
+                key: None,
+            }),
+            danger_accept_invalid_certs: Some(true),
+        };
+
+        assert_eq!(TlsOptions::merge(None, None), None);
+        assert_eq!(
+            TlsOptions::merge(Some(&recipe), None),
+            Some(recipe.clone())
+        );
+        assert_eq!(
+            TlsOptions::merge(Some(&recipe), Some(&profile)),
+            Some(TlsOptions {
+                ca_bundle: Some("recipe.pem".into()),
+                ..profile
+            })
+        );
+    }
+
+    #[test]
+    fn test_write_pem() {
+        let mut pem = String::new();
+        write_pem(&mut pem, "CERTIFICATE", &[0; 51]);
+        assert_eq!(
+            pem,
+            "-----BEGIN CERTIFICATE-----\n\
+            AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA\n\
+            AAAA\n\
+            -----END CERTIFICATE-----\n"
+        );
+    }
+}
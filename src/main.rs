@@ -12,7 +12,12 @@ mod test_util;
 mod tui;
 mod util;
 
-use crate::{cli::CliCommand, tui::Tui, util::paths::DataDirectory};
+use crate::{
+    cli::CliCommand,
+    collection::{secrets, CollectionFile},
+    tui::Tui,
+    util::paths::DataDirectory,
+};
 use clap::Parser;
 use std::{fs::File, io, path::PathBuf, process::ExitCode};
 use tracing::level_filters::LevelFilter;
@@ -49,6 +54,16 @@ async fn main() -> anyhow::Result<ExitCode> {
     let args = Args::parse();
     initialize_tracing(args.subcommand.is_some()).unwrap();
 
+    // Prompt for the secrets passphrase up front, if needed, so it's never
+    // requested while the TUI has the terminal. If there's no collection,
+    // the subcommand will report that itself
+    if let Ok(path) = CollectionFile::try_path(None, args.global.file.clone()) {
+        if let Err(error) = secrets::unlock(&path) {
+            eprintln!("{error:#}");
+            return Ok(ExitCode::FAILURE);
+        }
+    }
+
     // Select mode based on whether request ID(s) were given
     match args.subcommand {
         // Run the TUI
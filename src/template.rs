@@ -7,18 +7,33 @@ pub use error::{ChainError, TemplateError};
 pub use prompt::{Prompt, PromptChannel, Prompter};
 
 use crate::{
-    collection::{ChainId, Collection, ProfileId},
+    collection::{ChainId, Collection, Profile, ProfileId},
+    config::Config,
     db::CollectionDatabase,
     http::HttpEngine,
-    template::parse::{TemplateInputChunk, CHAIN_PREFIX, ENV_PREFIX},
+    template::{
+        parse::{
+            TemplateInputChunk, CAPTURE_PREFIX, CHAIN_PREFIX, ENV_PREFIX,
+            VARIABLE_PREFIX,
+        },
+        render::RenderedChunk,
+    },
 };
 use derive_more::Display;
-use indexmap::IndexMap;
-use serde::Serialize;
+use futures::Future;
+use indexmap::{IndexMap, IndexSet};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
 use std::{
-    fmt::Debug,
-    sync::{atomic::AtomicU8, Arc},
+    collections::{HashMap, HashSet},
+    fmt::{self, Debug},
+    ops::DerefMut,
+    path::PathBuf,
+    sync::{atomic::AtomicU8, Arc, Mutex},
+    time::Duration,
 };
+use strum::{EnumIter, IntoEnumIterator};
+use tokio::sync::OnceCell;
 
 /// Maximum number of layers of nested templates
 const RECURSION_LIMIT: u8 = 10;
@@ -63,6 +78,12 @@ pub struct TemplateContext {
     pub database: CollectionDatabase,
     /// Additional key=value overrides passed directly from the user
     pub overrides: IndexMap<String, String>,
+    /// Values captured from earlier responses, accessible as
+    /// `{{captures.<name>}}`. In the TUI, these last for the whole session.
+    /// `slumber seed` collects them from each recipe's `captures` field
+    pub captures: IndexMap<String, String>,
+    /// Order in which sources are checked for field values
+    pub field_precedence: FieldPrecedence,
     /// A conduit to ask the user questions
     pub prompter: Box<dyn Prompter>,
     /// A count of how many templates have *already* been rendered with this
@@ -74,6 +95,453 @@ pub struct TemplateContext {
     /// templates is the same as a template that renders a single child 5
     /// times.
     pub recursion_count: AtomicU8,
+    /// Overrides and chain values resolved while rendering with this context.
+    /// These are attached to built requests, so historical requests can be
+    /// explained later. For all external calls, you can start this empty.
+    pub rendered_values: RenderedValues,
+    /// Chain values resolved while rendering with this context, so each chain
+    /// is only resolved once even if it's used in multiple templates (e.g.
+    /// URL and headers). Use a new context for each request you build. For
+    /// all external calls, you can start this empty.
+    pub chain_cache: ChainCache,
+    /// Keys that are currently being rendered with this context, so a slow
+    /// render can show what it's waiting on. For all external calls, you can
+    /// start this empty.
+    pub progress: RenderProgress,
+    /// Files read by file chains with `watch` enabled while rendering with
+    /// this context, so the TUI can refresh when they change. For all external
+    /// calls, you can start this empty.
+    pub watched_files: WatchedFiles,
+    /// Maximum time a chain can spend getting its value from an external
+    /// source (e.g. running a command). `None` means no limit. Time spent
+    /// waiting on the user isn't counted.
+    pub render_timeout: Option<Duration>,
+    /// Can `!stdin` chains read from the process's stdin? This should be
+    /// disabled anywhere stdin is used for something else, e.g. the JSON-RPC
+    /// connection in `slumber serve`, or key input in the TUI.
+    pub stdin_available: bool,
+}
+
+/// A place that a template field (e.g. `{{host}}`) can get its value from
+#[derive(
+    Copy,
+    Clone,
+    Debug,
+    Display,
+    EnumIter,
+    Eq,
+    Hash,
+    PartialEq,
+    Serialize,
+    Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldSource {
+    /// Passed with `--override`/`-o` on the CLI
+    #[display("override")]
+    Override,
+    /// The selected profile's `data`
+    #[display("profile")]
+    Profile,
+}
+
+/// The order in which field sources are checked. The first source that
+/// defines a field wins. Any source left out is checked last, in the default
+/// order, so every source is always present exactly once.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(from = "Vec<FieldSource>", into = "Vec<FieldSource>")]
+pub struct FieldPrecedence(Vec<FieldSource>);
+
+impl FieldPrecedence {
+    pub fn sources(&self) -> &[FieldSource] {
+        &self.0
+    }
+}
+
+impl Default for FieldPrecedence {
+    fn default() -> Self {
+        Self(FieldSource::iter().collect())
+    }
+}
+
+impl From<Vec<FieldSource>> for FieldPrecedence {
+    fn from(sources: Vec<FieldSource>) -> Self {
+        let sources = sources
+            .into_iter()
+            .chain(FieldSource::iter())
+            .unique()
+            .collect();
+        Self(sources)
+    }
+}
+
+impl From<FieldPrecedence> for Vec<FieldSource> {
+    fn from(precedence: FieldPrecedence) -> Self {
+        precedence.0
+    }
+}
+
+/// Render as `override > profile`
+impl fmt::Display for FieldPrecedence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.iter().format(" > "))
+    }
+}
+
+/// A record of the values that template keys resolved to during rendering.
+/// Only overrides and chains are recorded, because profile fields can be
+/// looked up from the collection. Sensitive values are masked before being
+/// stored, so they never end up in history.
+#[derive(Debug, Default)]
+pub struct RenderedValues(Mutex<IndexMap<String, String>>);
+
+impl RenderedValues {
+    /// Placeholder stored in place of sensitive values
+    pub const SENSITIVE_MASK: &'static str = "<sensitive>";
+
+    /// Record the value for a template key. If the key was already rendered,
+    /// the latest value wins
+    fn insert(&self, key: String, value: &[u8], sensitive: bool) {
+        let value = if sensitive {
+            Self::SENSITIVE_MASK.to_owned()
+        } else {
+            String::from_utf8_lossy(value).into_owned()
+        };
+        self.0
+            .lock()
+            .expect("Rendered values lock poisoned")
+            .insert(key, value);
+    }
+
+    /// Get a copy of every recorded value, in the order they were rendered
+    pub fn snapshot(&self) -> IndexMap<String, String> {
+        self.0
+            .lock()
+            .expect("Rendered values lock poisoned")
+            .clone()
+    }
+}
+
+/// Memoized chain values, keyed by chain ID. Each entry is a cell that's
+/// filled by the first render to reach it. Any other render that needs the
+/// same chain waits for that value, instead of running the chain's command,
+/// prompt, or request again. Errors are not cached.
+#[derive(Debug, Default)]
+pub struct ChainCache(Mutex<ChainCacheInner>);
+
+#[derive(Debug, Default)]
+struct ChainCacheInner {
+    cells: HashMap<ChainId, Arc<OnceCell<RenderedChunk>>>,
+    /// Dependencies between chains that are still being resolved, as
+    /// `(dependent, dependency)`. This is shared by every render using the
+    /// cache, so a cycle is caught even when its chains are being resolved by
+    /// different concurrent renders, which would otherwise wait on each other
+    /// forever.
+    dependencies: Vec<(ChainId, ChainId)>,
+}
+
+tokio::task_local! {
+    /// The chain that's currently being resolved in this render tree, so we
+    /// know which chain depends on any chain rendered beneath it
+    static RESOLVING_CHAIN: ChainId;
+}
+
+impl ChainCache {
+    /// Get the value of a chain from the cache, or resolve it with the given
+    /// function if it hasn't been resolved yet. Return an error if the chain
+    /// depends on itself, directly or through other chains.
+    async fn get_or_resolve<F, Fut>(
+        &self,
+        chain_id: &ChainId,
+        resolve: F,
+    ) -> Result<RenderedChunk, TemplateError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<RenderedChunk, TemplateError>>,
+    {
+        let (cell, _guard) = {
+            let mut inner = self.lock();
+            let cell =
+                Arc::clone(inner.cells.entry(chain_id.clone()).or_default());
+            // If we're inside another chain, that chain can't finish until
+            // this one does. Once the value is cached, there's nothing to wait
+            // on
+            let dependent = RESOLVING_CHAIN.try_with(Clone::clone).ok();
+            let guard = match dependent {
+                Some(dependent) if !cell.initialized() => {
+                    if inner.depends_on(chain_id, &dependent) {
+                        return Err(TemplateError::ChainCycle {
+                            chain_id: chain_id.clone(),
+                        });
+                    }
+                    let dependency = (dependent, chain_id.clone());
+                    inner.dependencies.push(dependency.clone());
+                    Some(ChainDependencyGuard {
+                        cache: self,
+                        dependency,
+                    })
+                }
+                _ => None,
+            };
+            (cell, guard)
+        };
+
+        RESOLVING_CHAIN
+            .scope(chain_id.clone(), cell.get_or_try_init(resolve))
+            .await
+            .cloned()
+    }
+
+    fn lock(&self) -> impl '_ + DerefMut<Target = ChainCacheInner> {
+        self.0.lock().expect("Chain cache lock poisoned")
+    }
+}
+
+impl ChainCacheInner {
+    /// Does the first chain depend on the second, directly or indirectly?
+    /// Every chain depends on itself.
+    fn depends_on(&self, dependent: &ChainId, dependency: &ChainId) -> bool {
+        let mut visited = HashSet::new();
+        let mut queue = vec![dependent];
+        while let Some(chain_id) = queue.pop() {
+            if chain_id == dependency {
+                return true;
+            }
+            if visited.insert(chain_id) {
+                queue.extend(
+                    self.dependencies
+                        .iter()
+                        .filter(|(dependent, _)| dependent == chain_id)
+                        .map(|(_, dependency)| dependency),
+                );
+            }
+        }
+        false
+    }
+}
+
+/// Removes a dependency from the chain cache once the dependent chain is done
+/// waiting on it, whether it succeeded, failed, or was cancelled
+struct ChainDependencyGuard<'a> {
+    cache: &'a ChainCache,
+    dependency: (ChainId, ChainId),
+}
+
+impl<'a> Drop for ChainDependencyGuard<'a> {
+    fn drop(&mut self) {
+        let mut inner = self.cache.lock();
+        if let Some(index) = inner
+            .dependencies
+            .iter()
+            .position(|dependency| dependency == &self.dependency)
+        {
+            inner.dependencies.swap_remove(index);
+        }
+    }
+}
+
+/// Template keys currently being rendered, in the order they started. Clones
+/// share the same list, so the caller can hang onto one and watch the render.
+#[derive(Clone, Debug, Default)]
+pub struct RenderProgress(Arc<Mutex<Vec<String>>>);
+
+impl RenderProgress {
+    /// Mark a key as in progress until the returned guard is dropped
+    fn start(&self, key: String) -> RenderProgressGuard<'_> {
+        self.keys().push(key.clone());
+        RenderProgressGuard {
+            progress: self,
+            key,
+        }
+    }
+
+    /// Get all keys currently being rendered. Nested keys come after their
+    /// parents.
+    pub fn active(&self) -> Vec<String> {
+        self.keys().clone()
+    }
+
+    fn keys(&self) -> impl '_ + DerefMut<Target = Vec<String>> {
+        self.0.lock().expect("Render progress lock poisoned")
+    }
+}
+
+/// Files that were read with `watch` enabled, in the order they were first
+/// read
+#[derive(Debug, Default)]
+pub struct WatchedFiles(Mutex<IndexSet<WatchedFile>>);
+
+impl WatchedFiles {
+    /// Record a watched file. Duplicates are ignored
+    fn insert(&self, file: WatchedFile) {
+        self.0
+            .lock()
+            .expect("Watched files lock poisoned")
+            .insert(file);
+    }
+
+    /// Get a copy of every recorded file
+    pub fn files(&self) -> Vec<WatchedFile> {
+        self.0
+            .lock()
+            .expect("Watched files lock poisoned")
+            .iter()
+            .cloned()
+            .collect()
+    }
+}
+
+/// A file read by a `!file` chain with `watch` enabled
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct WatchedFile {
+    pub path: PathBuf,
+    /// Is the path a glob pattern? If so, any matching file is watched
+    pub glob: bool,
+}
+
+/// Removes a key from [RenderProgress] when dropped
+struct RenderProgressGuard<'a> {
+    progress: &'a RenderProgress,
+    key: String,
+}
+
+impl<'a> Drop for RenderProgressGuard<'a> {
+    fn drop(&mut self) {
+        let mut keys = self.progress.keys();
+        if let Some(index) = keys.iter().position(|key| key == &self.key) {
+            keys.remove(index);
+        }
+    }
+}
+
+tokio::task_local! {
+    /// Variables defined by the recipe being rendered, which can be accessed
+    /// via `{{vars.<name>}}`. See [with_recipe_variables].
+    static RECIPE_VARIABLES: Arc<IndexMap<String, Template>>;
+}
+
+/// Run a future with access to a recipe's local `variables`. Any template
+/// rendered within the future can refer to them via `{{vars.<name>}}`. Scopes
+/// can be nested, e.g. when a chain triggers an upstream request, in which
+/// case the innermost recipe's variables are used.
+pub async fn with_recipe_variables<F: Future>(
+    variables: IndexMap<String, Template>,
+    future: F,
+) -> F::Output {
+    RECIPE_VARIABLES.scope(Arc::new(variables), future).await
+}
+
+/// Synchronous version of [with_recipe_variables]
+pub fn with_recipe_variables_sync<T>(
+    variables: IndexMap<String, Template>,
+    f: impl FnOnce() -> T,
+) -> T {
+    RECIPE_VARIABLES.sync_scope(Arc::new(variables), f)
+}
+
+/// Get the variables of the recipe currently being rendered. Return `None` if
+/// we're not in the scope of a recipe.
+fn recipe_variables() -> Option<Arc<IndexMap<String, Template>>> {
+    RECIPE_VARIABLES.try_with(Arc::clone).ok()
+}
+
+impl TemplateContext {
+    /// Create a context for rendering a single request (or a standalone set of
+    /// templates). Overrides and captures start empty, and all render state
+    /// (recursion count, chain cache, etc.) starts fresh. Use struct update
+    /// syntax to set overrides or captures.
+    pub fn new(
+        collection: Collection,
+        selected_profile: Option<ProfileId>,
+        http_engine: Option<HttpEngine>,
+        database: CollectionDatabase,
+        prompter: Box<dyn Prompter>,
+        config: &Config,
+    ) -> Self {
+        Self {
+            collection,
+            selected_profile,
+            http_engine,
+            database,
+            overrides: IndexMap::new(),
+            captures: IndexMap::new(),
+            field_precedence: config.field_precedence.clone(),
+            prompter,
+            recursion_count: Default::default(),
+            rendered_values: Default::default(),
+            chain_cache: Default::default(),
+            progress: Default::default(),
+            watched_files: Default::default(),
+            render_timeout: config.render_timeout,
+            stdin_available: true,
+        }
+    }
+
+    /// Get the selected profile. Return `None` if no profile is selected, or
+    /// the selected ID is unknown
+    pub fn profile(&self) -> Option<&Profile> {
+        self.selected_profile
+            .as_ref()
+            .and_then(|profile_id| self.collection.profiles.get(profile_id))
+    }
+
+    /// Get the base URL that relative recipe URLs are joined to. The selected
+    /// profile's takes precedence over the collection's.
+    pub fn base_url(&self) -> Option<&Template> {
+        self.profile()
+            .and_then(|profile| profile.base_url.as_ref())
+            .or(self.collection.base_url.as_ref())
+    }
+
+    /// Get every source that defines a field, in precedence order. The first
+    /// one is the one that will be used.
+    pub fn field_sources(&self, field: &str) -> Vec<FieldSource> {
+        self.field_precedence
+            .sources()
+            .iter()
+            .copied()
+            .filter(|source| match source {
+                FieldSource::Override => self.overrides.contains_key(field),
+                FieldSource::Profile => self
+                    .profile()
+                    .is_some_and(|profile| profile.data.contains_key(field)),
+            })
+            .collect()
+    }
+
+    /// Get the name of every field defined by any source, for debugging
+    /// precedence. Overrides for chains and environment variables aren't
+    /// fields, so they're excluded.
+    pub fn field_names(&self) -> Vec<&str> {
+        let overrides = self.overrides.keys().filter(|key| {
+            ![CAPTURE_PREFIX, CHAIN_PREFIX, ENV_PREFIX, VARIABLE_PREFIX]
+                .iter()
+                .any(|prefix| key.starts_with(prefix))
+        });
+        let profile = self.profile().into_iter().flat_map(|p| p.data.keys());
+        overrides
+            .chain(profile)
+            .map(String::as_str)
+            .unique()
+            .collect()
+    }
+
+    /// Get the override value for a template key, if it should be used. For
+    /// fields, an override is only used if no higher-precedence source
+    /// defines the field.
+    fn get_override(&self, key: &TemplateKey, raw: &str) -> Option<&String> {
+        let value = self.overrides.get(raw)?;
+        match key {
+            TemplateKey::Field(field) => (self.field_sources(field).first()
+                == Some(&FieldSource::Override))
+            .then_some(value),
+            // Nothing else competes with overrides
+            TemplateKey::Chain(_)
+            | TemplateKey::Capture(_)
+            | TemplateKey::Environment(_)
+            | TemplateKey::Variable(_) => Some(value),
+        }
+    }
 }
 
 impl Template {
@@ -88,6 +556,63 @@ impl Template {
             chunks: vec![TemplateInputChunk::Raw(template.into())],
         }
     }
+
+    /// Get the name of every plain field (e.g. `{{host}}`) referenced
+    /// directly by this template. Fields referenced by nested templates, such
+    /// as profile values, aren't included.
+    pub fn fields(&self) -> impl Iterator<Item = &str> {
+        self.chunks.iter().filter_map(|chunk| match chunk {
+            TemplateInputChunk::Key(TemplateKey::Field(field)) => {
+                Some(field.as_str())
+            }
+            _ => None,
+        })
+    }
+
+    /// Does the template contain any keys? If not, it always renders to the
+    /// same text
+    pub fn is_dynamic(&self) -> bool {
+        self.chunks
+            .iter()
+            .any(|chunk| matches!(chunk, TemplateInputChunk::Key(_)))
+    }
+
+    /// Stringify the template, replacing each key with the output of a
+    /// function. The function is given the key's source text without the
+    /// `{{ }}`, e.g. `chains.token`. Raw text is written unescaped. This is
+    /// useful for exporting to formats that have their own placeholder syntax.
+    pub fn replace_keys(&self, replace: impl Fn(&str) -> String) -> String {
+        self.chunks
+            .iter()
+            .map(|chunk| match chunk {
+                TemplateInputChunk::Raw(text) => text.as_str().to_owned(),
+                TemplateInputChunk::Key(key) => replace(&key.to_string()),
+            })
+            .collect()
+    }
+
+    /// Split the template back into its source text, checking each key
+    /// against the collection. This catches references to unknown fields and
+    /// chains without rendering anything, so no chains are triggered. Errors
+    /// are the same ones that rendering would return.
+    pub fn validate(
+        &self,
+        collection: &Collection,
+        profile_id: Option<&ProfileId>,
+    ) -> Vec<TemplateSourceChunk> {
+        self.chunks
+            .iter()
+            .map(|chunk| match chunk {
+                TemplateInputChunk::Raw(_) => {
+                    TemplateSourceChunk::Raw(chunk.to_string())
+                }
+                TemplateInputChunk::Key(key) => TemplateSourceChunk::Key {
+                    source: chunk.to_string(),
+                    error: key.validate(collection, profile_id).err(),
+                },
+            })
+            .collect()
+    }
 }
 
 #[cfg(test)]
@@ -119,6 +644,20 @@ pub enum TemplateChunk {
     Error(TemplateError),
 }
 
+/// A piece of a template's source text, annotated with any problems that can
+/// be found without rendering. See [Template::validate].
+#[derive(Debug)]
+#[cfg_attr(test, derive(PartialEq))]
+pub enum TemplateSourceChunk {
+    /// Raw text, escaped exactly as it appears in the source
+    Raw(String),
+    /// A key, including the surrounding `{{ }}`
+    Key {
+        source: String,
+        error: Option<TemplateError>,
+    },
+}
+
 #[cfg(test)]
 impl TemplateChunk {
     /// Shorthand for creating a new raw chunk
@@ -148,25 +687,85 @@ enum TemplateKey {
     /// A value from a predefined chain of another recipe
     #[display("{CHAIN_PREFIX}{_0}")]
     Chain(ChainId),
+    /// A value captured from a response in the TUI
+    #[display("{CAPTURE_PREFIX}{_0}")]
+    Capture(String),
     /// A value pulled from the process environment
     /// DEPRECATED: To be removed in 2.0, replaced by !env chain source
     #[display("{ENV_PREFIX}{_0}")]
     Environment(String),
+    /// A variable defined locally on the recipe being rendered
+    #[display("{VARIABLE_PREFIX}{_0}")]
+    Variable(String),
+}
+
+impl TemplateKey {
+    /// Check that whatever this key refers to exists
+    fn validate(
+        &self,
+        collection: &Collection,
+        profile_id: Option<&ProfileId>,
+    ) -> Result<(), TemplateError> {
+        match self {
+            Self::Field(field) => {
+                let profile_id =
+                    profile_id.ok_or(TemplateError::NoProfileSelected)?;
+                let profile =
+                    collection.profiles.get(profile_id).ok_or_else(|| {
+                        TemplateError::ProfileUnknown {
+                            profile_id: profile_id.clone(),
+                        }
+                    })?;
+                if profile.data.contains_key(field) {
+                    Ok(())
+                } else {
+                    Err(TemplateError::FieldUnknown {
+                        field: field.clone(),
+                    })
+                }
+            }
+            Self::Chain(chain_id) => {
+                if collection.chains.contains_key(chain_id) {
+                    Ok(())
+                } else {
+                    Err(TemplateError::Chain {
+                        chain_id: chain_id.clone(),
+                        error: ChainError::ChainUnknown(chain_id.clone()),
+                    })
+                }
+            }
+            // Captures only exist for the duration of a TUI session, so
+            // there's nothing to check against
+            Self::Capture(_) => Ok(()),
+            // Any variable name is valid; unset ones render as empty
+            Self::Environment(_) => Ok(()),
+            Self::Variable(variable) => {
+                let variables =
+                    recipe_variables().ok_or(TemplateError::NoRecipe)?;
+                if variables.contains_key(variable) {
+                    Ok(())
+                } else {
+                    Err(TemplateError::VariableUnknown {
+                        variable: variable.clone(),
+                    })
+                }
+            }
+        }
+    }
 }
 
 #[cfg(test)]
 impl crate::test_util::Factory for TemplateContext {
     fn factory(_: ()) -> Self {
         use crate::test_util::TestPrompter;
-        Self {
-            collection: Collection::default(),
-            selected_profile: None,
-            http_engine: None,
-            database: CollectionDatabase::factory(()),
-            overrides: IndexMap::new(),
-            prompter: Box::<TestPrompter>::default(),
-            recursion_count: 0.into(),
-        }
+        Self::new(
+            Collection::default(),
+            None,
+            None,
+            CollectionDatabase::factory(()),
+            Box::<TestPrompter>::default(),
+            &Config::default(),
+        )
     }
 }
 
@@ -175,14 +774,15 @@ mod tests {
     use super::*;
     use crate::{
         collection::{
-            Chain, ChainOutputTrim, ChainRequestSection, ChainRequestTrigger,
-            ChainSource, Profile, Recipe, RecipeId,
+            Chain, ChainMultiValue, ChainOutputTrim, ChainRequestPoll,
+            ChainRequestSection, ChainRequestTrigger, ChainSource,
+            ChainTransform, Profile, Recipe, RecipeId, SelectorType,
         },
         config::Config,
         http::{ContentType, Exchange, RequestRecord, ResponseRecord},
         test_util::{
-            assert_err, by_id, header_map, temp_dir, Factory, TempDir,
-            TestPrompter,
+            assert_err, assert_matches, by_id, header_map, temp_dir, Factory,
+            TempDir, TestPrompter,
         },
         tui::test_util::EnvGuard,
     };
@@ -190,8 +790,11 @@ mod tests {
     use indexmap::indexmap;
     use rstest::rstest;
     use serde_json::json;
-    use std::time::Duration;
-    use tokio::fs;
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        time::{Duration, SystemTime},
+    };
+    use tokio::{fs, time};
 
     /// Test overriding all key types, as well as missing keys
     #[tokio::test]
@@ -241,6 +844,60 @@ mod tests {
         );
     }
 
+    /// Overrides win by default, but profile fields can be given precedence
+    #[rstest]
+    #[case::default(vec![], "override")]
+    #[case::profile_first(vec![FieldSource::Profile], "profile")]
+    #[tokio::test]
+    async fn test_field_precedence(
+        #[case] precedence: Vec<FieldSource>,
+        #[case] expected: &str,
+    ) {
+        let profile = Profile {
+            data: indexmap! {
+                "field1".into() => "profile".into(),
+                "field2".into() => "profile".into(),
+            },
+            ..Profile::factory(())
+        };
+        let profile_id = profile.id.clone();
+        let context = TemplateContext {
+            collection: Collection {
+                profiles: by_id([profile]),
+                ..Collection::factory(())
+            },
+            selected_profile: Some(profile_id),
+            overrides: indexmap! {
+                "field1".into() => "override".into(),
+                "override_only".into() => "override".into(),
+            },
+            field_precedence: precedence.into(),
+            ..TemplateContext::factory(())
+        };
+
+        assert_eq!(render!("{{field1}}", context).unwrap(), expected);
+        // Fields defined by only one source aren't affected
+        assert_eq!(render!("{{field2}}", context).unwrap(), "profile");
+        assert_eq!(render!("{{override_only}}", context).unwrap(), "override");
+        assert_eq!(
+            context.field_names(),
+            vec!["field1", "override_only", "field2"]
+        );
+    }
+
+    /// Sources left out of the config are filled in, in the default order
+    #[rstest]
+    #[case::empty("[]", "override > profile")]
+    #[case::partial("[profile]", "profile > override")]
+    #[case::duplicate("[profile, override, profile]", "profile > override")]
+    fn test_field_precedence_deserialize(
+        #[case] yaml: &str,
+        #[case] expected: &str,
+    ) {
+        let precedence: FieldPrecedence = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(precedence.to_string(), expected);
+    }
+
     /// Test that a field key renders correctly
     #[tokio::test]
     async fn test_field() {
@@ -283,6 +940,64 @@ mod tests {
         assert_err!(render!(template, context), expected);
     }
 
+    /// Test that recipe variables render correctly, including nested keys
+    #[tokio::test]
+    async fn test_variable() {
+        let context = profile_context(indexmap! {
+            "user_id".into() => "1".into(),
+        });
+        let variables = indexmap! {
+            "path".into() => "/users/{{user_id}}".into(),
+            "nested".into() => "path: {{vars.path}}".into(),
+        };
+
+        let rendered = with_recipe_variables(variables, async {
+            (
+                render!("{{vars.path}}", context).unwrap(),
+                render!("{{vars.nested}}", context).unwrap(),
+            )
+        })
+        .await;
+        assert_eq!(rendered, ("/users/1".into(), "path: /users/1".into()));
+    }
+
+    /// Potential error cases for a recipe variable
+    #[rstest]
+    #[case::no_recipe(
+        None,
+        "{{vars.var1}}",
+        "Variables are only available within a recipe"
+    )]
+    #[case::unknown(
+        Some(indexmap! {}),
+        "{{vars.var1}}",
+        "Unknown variable `var1`"
+    )]
+    #[case::nested(
+        Some(indexmap! {"var1".into() => "{{vars.var2}}".into()}),
+        "{{vars.var1}}",
+        "Rendering nested template for variable `var1`: \
+        Unknown variable `var2`"
+    )]
+    #[tokio::test]
+    async fn test_variable_error(
+        #[case] variables: Option<IndexMap<String, Template>>,
+        #[case] template: &str,
+        #[case] expected: &str,
+    ) {
+        let context = TemplateContext::factory(());
+        let result = match variables {
+            Some(variables) => {
+                with_recipe_variables(variables, async {
+                    render!(template, context)
+                })
+                .await
+            }
+            None => render!(template, context),
+        };
+        assert_err!(result, expected);
+    }
+
     /// Test success cases with chained responses
     #[rstest]
     #[case::no_selector(
@@ -296,6 +1011,21 @@ mod tests {
     #[case::array(Some("$.array"), ChainRequestSection::Body, "[1,2]")]
     #[case::object(Some("$.object"), ChainRequestSection::Body, "{\"a\":1}")]
     #[case::header(None, ChainRequestSection::Header("Token".into()), "Secret Value")]
+    #[case::headers_multi_value(
+        Some("$.accept[1]"),
+        ChainRequestSection::Headers,
+        "text/plain"
+    )]
+    #[case::headers_cookie(
+        Some("$['set-cookie'][?(@.name == 'session')].path"),
+        ChainRequestSection::Headers,
+        "/api"
+    )]
+    #[case::headers_cookie_flag(
+        Some("$['set-cookie'][1].httponly"),
+        ChainRequestSection::Headers,
+        "true"
+    )]
     #[tokio::test]
     async fn test_chain_request(
         #[case] selector: Option<&str>,
@@ -311,8 +1041,13 @@ mod tests {
             "array": [1,2],
             "object": {"a": 1},
         });
-        let response_headers =
-            header_map(indexmap! {"Token" => "Secret Value"});
+        let response_headers = header_map([
+            ("Token", "Secret Value"),
+            ("Accept", "application/json"),
+            ("Accept", "text/plain"),
+            ("Set-Cookie", "theme=dark"),
+            ("Set-Cookie", "session=abc123; Path=/api; HttpOnly"),
+        ]);
         let request = RequestRecord {
             recipe_id: recipe_id.clone(),
             ..RequestRecord::factory(())
@@ -339,6 +1074,7 @@ mod tests {
                 recipe: recipe_id.clone(),
                 trigger: Default::default(),
                 section,
+                poll: None,
             },
             selector,
             content_type: Some(ContentType::Json),
@@ -380,6 +1116,7 @@ mod tests {
                 recipe: "unknown".into(),
                 trigger: Default::default(),
                 section: Default::default(),
+                poll: None,
             },
             ..Chain::factory(())
         },
@@ -394,6 +1131,7 @@ mod tests {
                 recipe: "recipe1".into(),
                 trigger: Default::default(),
                 section: Default::default(),
+                poll: None,
             },
             ..Chain::factory(())
         },
@@ -408,6 +1146,7 @@ mod tests {
                 recipe: "recipe1".into(),
                 trigger: ChainRequestTrigger::Always,
                 section: Default::default(),
+                poll: None,
             },
             ..Chain::factory(())
         },
@@ -422,6 +1161,7 @@ mod tests {
                 recipe: "recipe1".into(),
                 trigger: Default::default(),
                 section: Default::default(),
+                poll: None,
             },
             selector: Some("$.message".parse().unwrap()),
             ..Chain::factory(())
@@ -443,6 +1183,7 @@ mod tests {
                 recipe: "recipe1".into(),
                 trigger: Default::default(),
                 section: Default::default(),
+                poll: None,
             },
             selector: Some("$.message".parse().unwrap()),
             content_type: Some(ContentType::Json),
@@ -464,7 +1205,8 @@ mod tests {
             source: ChainSource::Request {
                 recipe: "recipe1".into(),
                 trigger: Default::default(),
-                section:Default::default()
+                section: Default::default(),
+                poll: None,
             },
             selector: Some("$.*".parse().unwrap()),
             content_type: Some(ContentType::Json),
@@ -534,6 +1276,28 @@ mod tests {
             end_time: Utc::now() - Duration::from_secs(100),
             ..Exchange::factory(())})
     )]
+    #[case::expire_in_expired(
+        ChainRequestTrigger::ExpireIn("$.expires_in".parse().unwrap()),
+        Some(token_exchange(json!({"expires_in": 60}), 100))
+    )]
+    #[case::expire_in_missing(
+        ChainRequestTrigger::ExpireIn("$.expires_in".parse().unwrap()),
+        Some(token_exchange(json!({}), 0))
+    )]
+    #[case::expire_at_timestamp_expired(
+        ChainRequestTrigger::ExpireAt("$.expires_at".parse().unwrap()),
+        Some(token_exchange(
+            json!({"expires_at": (Utc::now().timestamp() - 10)}),
+            0,
+        ))
+    )]
+    #[case::expire_at_rfc3339_expired(
+        ChainRequestTrigger::ExpireAt("$.expires_at".parse().unwrap()),
+        Some(token_exchange(
+            json!({"expires_at": "2020-01-01T00:00:00Z"}),
+            0,
+        ))
+    )]
     #[case::always_no_history(ChainRequestTrigger::Always, None)]
     #[case::always_with_history(
         ChainRequestTrigger::Always,
@@ -571,6 +1335,7 @@ mod tests {
                 recipe: recipe.id.clone(),
                 trigger,
                 section: Default::default(),
+                poll: None,
             },
             ..Chain::factory(())
         };
@@ -591,6 +1356,127 @@ mod tests {
         mock.assert();
     }
 
+    /// Test expiration triggers that read the expiration from the response.
+    /// The response in history hasn't expired, so we expect it to be reused.
+    /// No HTTP engine is provided, so triggering would fail.
+    #[rstest]
+    #[case::expire_in(
+        ChainRequestTrigger::ExpireIn("$.expires_in".parse().unwrap()),
+        json!({"expires_in": 3600}),
+    )]
+    #[case::expire_at_timestamp(
+        ChainRequestTrigger::ExpireAt("$.expires_at".parse().unwrap()),
+        json!({"expires_at": Utc::now().timestamp() + 3600}),
+    )]
+    #[case::expire_at_rfc3339(
+        ChainRequestTrigger::ExpireAt("$.expires_at".parse().unwrap()),
+        json!({"expires_at": "3000-01-01T00:00:00Z"}),
+    )]
+    #[tokio::test]
+    async fn test_triggered_request_not_expired(
+        #[case] trigger: ChainRequestTrigger,
+        #[case] body: serde_json::Value,
+    ) {
+        let database = CollectionDatabase::factory(());
+        let recipe = Recipe::factory(());
+        database
+            .insert_exchange(&token_exchange(body.clone(), 0))
+            .unwrap();
+
+        let chain = Chain {
+            source: ChainSource::Request {
+                recipe: recipe.id.clone(),
+                trigger,
+                section: Default::default(),
+                poll: None,
+            },
+            ..Chain::factory(())
+        };
+        let context = TemplateContext {
+            collection: Collection {
+                recipes: by_id([recipe]).into(),
+                chains: by_id([chain]),
+                ..Collection::factory(())
+            },
+            database,
+            ..TemplateContext::factory(())
+        };
+
+        assert_eq!(
+            render!("{{chains.chain1}}", context).unwrap(),
+            body.to_string()
+        );
+    }
+
+    /// Test polling a triggered request until the condition is met. The
+    /// response changes on each request, so we can count how many were sent
+    #[rstest]
+    #[case::met(3, Ok("done"))]
+    #[case::timeout(0, Err("Polling timed out"))]
+    #[tokio::test]
+    async fn test_triggered_request_poll(
+        #[case] timeout_secs: u64,
+        #[case] expected: Result<&str, &str>,
+    ) {
+        let database = CollectionDatabase::factory(());
+
+        // Mock HTTP response. The job finishes on the third request
+        let mut server = mockito::Server::new_async().await;
+        let url = server.url();
+        let count = AtomicUsize::new(0);
+        server
+            .mock("GET", "/job")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body_from_request(move |_| {
+                let status = if count.fetch_add(1, Ordering::Relaxed) >= 2 {
+                    "done"
+                } else {
+                    "pending"
+                };
+                json!({"status": status}).to_string().into()
+            })
+            .create_async()
+            .await;
+
+        let recipe = Recipe {
+            url: format!("{url}/job").into(),
+            ..Recipe::factory(())
+        };
+        let chain = Chain {
+            source: ChainSource::Request {
+                recipe: recipe.id.clone(),
+                trigger: ChainRequestTrigger::Always,
+                section: Default::default(),
+                poll: Some(ChainRequestPoll {
+                    until: "$.status".parse().unwrap(),
+                    equals: "done".into(),
+                    interval: Duration::ZERO,
+                    timeout: Duration::from_secs(timeout_secs),
+                }),
+            },
+            selector: Some("$.status".parse().unwrap()),
+            ..Chain::factory(())
+        };
+        let http_engine = HttpEngine::new(&Config::default());
+        let context = TemplateContext {
+            collection: Collection {
+                recipes: by_id([recipe]).into(),
+                chains: by_id([chain]),
+                ..Collection::factory(())
+            },
+            http_engine: Some(http_engine),
+            database,
+            ..TemplateContext::factory(())
+        };
+
+        let result = render!("{{chains.chain1}}", context);
+        match expected {
+            Ok(expected) => assert_eq!(result.unwrap(), expected),
+            Err(expected) => assert_err!(result, expected),
+        }
+    }
+
     /// Test success with chained command
     #[rstest]
     #[case::with_stdin(&["tail"], Some("hello!"), "hello!")]
@@ -647,13 +1533,160 @@ mod tests {
         assert_eq!(render!("{{chains.chain1}}", context).unwrap(), expected);
     }
 
-    /// Test failure with chained command
-    #[rstest]
-    #[case::no_command(&[], None, "No command given")]
-    #[case::unknown_command(
-        &["totally not a program"], None, "No such file or directory"
-    )]
-    #[case::command_error(
+    /// Regex selector is applied to command output, with no content type
+    #[tokio::test]
+    async fn test_chain_regex_selector() {
+        let chain = Chain {
+            source: ChainSource::command([
+                "echo",
+                "-n",
+                "Version: 1.2.3 (beta)",
+            ]),
+            selector: Some(r"Version: ([\d.]+)".parse().unwrap()),
+            selector_type: SelectorType::Regex,
+            ..Chain::factory(())
+        };
+        let context = TemplateContext {
+            collection: Collection {
+                chains: by_id([chain]),
+                ..Collection::factory(())
+            },
+            ..TemplateContext::factory(())
+        };
+
+        assert_eq!(render!("{{chains.chain1}}", context).unwrap(), "1.2.3");
+    }
+
+    /// Selectors with multiple results are reduced according to `multi`
+    #[rstest]
+    #[case::default(None, "$[*]", Err("Expected exactly one result"))]
+    #[case::error(Some(ChainMultiValue::Error), "$[0]", Ok("1"))]
+    #[case::first(Some(ChainMultiValue::First), "$[*]", Ok("1"))]
+    #[case::last(Some(ChainMultiValue::Last), "$[*]", Ok("3"))]
+    #[case::join(Some(ChainMultiValue::Join(",".into())), "$[*]", Ok("1,2,3"))]
+    #[case::join_empty(Some(ChainMultiValue::Join(",".into())), "$.x", Ok(""))]
+    #[case::first_empty(
+        Some(ChainMultiValue::First),
+        "$[5]",
+        Err("Expected exactly one result from query, but got 0")
+    )]
+    #[tokio::test]
+    async fn test_chain_multi(
+        #[case] multi: Option<ChainMultiValue>,
+        #[case] selector: &str,
+        #[case] expected: Result<&str, &str>,
+    ) {
+        let chain = Chain {
+            source: ChainSource::command(["echo", "-n", "[1, 2, 3]"]),
+            selector: Some(selector.parse().unwrap()),
+            multi,
+            content_type: Some(ContentType::Json),
+            ..Chain::factory(())
+        };
+        let context = TemplateContext {
+            collection: Collection {
+                chains: by_id([chain]),
+                ..Collection::factory(())
+            },
+            ..TemplateContext::factory(())
+        };
+
+        let result = render!("{{chains.chain1}}", context);
+        match expected {
+            Ok(expected) => assert_eq!(result.unwrap(), expected),
+            Err(expected) => assert_err!(result, expected),
+        }
+    }
+
+    /// Transforms are applied in order
+    #[rstest]
+    #[case::trim(vec![ChainTransform::Trim], "  hi  ", Ok("hi"))]
+    #[case::replace(
+        vec![ChainTransform::Replace { from: "-".into(), to: "_".into() }],
+        "a-b-c",
+        Ok("a_b_c")
+    )]
+    #[case::regex_replace(
+        vec![ChainTransform::RegexReplace {
+            pattern: r"(\w+)@(\w+)".into(),
+            replacement: "$2/$1".into(),
+        }],
+        "user@host",
+        Ok("host/user")
+    )]
+    #[case::upper(vec![ChainTransform::Upper], "abc", Ok("ABC"))]
+    #[case::lower(vec![ChainTransform::Lower], "ABC", Ok("abc"))]
+    #[case::base64_encode(
+        vec![ChainTransform::Base64Encode],
+        "hi!",
+        Ok("aGkh")
+    )]
+    #[case::base64_decode(
+        vec![ChainTransform::Base64Decode],
+        "aGkh",
+        Ok("hi!")
+    )]
+    #[case::jsonpath(
+        vec![ChainTransform::JsonPath("$.a".parse().unwrap())],
+        r#"{"a": "b"}"#,
+        Ok("b")
+    )]
+    #[case::chained(
+        vec![
+            ChainTransform::Base64Decode,
+            ChainTransform::JsonPath("$.name".parse().unwrap()),
+            ChainTransform::Upper,
+        ],
+        // {"name":"fish"}
+        "eyJuYW1lIjoiZmlzaCJ9",
+        Ok("FISH")
+    )]
+    #[case::invalid_base64(
+        vec![ChainTransform::Base64Decode],
+        "???",
+        Err("Applying transform `base64_decode`: Invalid symbol")
+    )]
+    #[case::invalid_regex(
+        vec![ChainTransform::RegexReplace {
+            pattern: "(".into(),
+            replacement: "".into(),
+        }],
+        "abc",
+        Err("Applying transform `regex_replace`: Invalid regex `(`")
+    )]
+    #[tokio::test]
+    async fn test_chain_transforms(
+        #[case] transforms: Vec<ChainTransform>,
+        #[case] input: &str,
+        #[case] expected: Result<&str, &str>,
+    ) {
+        let chain = Chain {
+            source: ChainSource::command(["echo", "-n", input]),
+            transforms,
+            ..Chain::factory(())
+        };
+        let context = TemplateContext {
+            collection: Collection {
+                chains: by_id([chain]),
+                ..Collection::factory(())
+            },
+            ..TemplateContext::factory(())
+        };
+
+        let result = render!("{{chains.chain1}}", context);
+        match expected {
+            Ok(expected) => assert_eq!(result.unwrap(), expected),
+            Err(expected) => assert_err!(result, expected),
+        }
+    }
+
+    /// Test failure with chained command
+    #[rstest]
+    #[case::no_command(&[], None, "No command given")]
+    #[case::unknown_command(
+        &["totally not a program"], None, "No such file or directory"
+    )]
+    #[case::command_error(
         &["head", "/dev/random"], None, "invalid utf-8 sequence"
     )]
     #[case::stdin_error(
@@ -687,6 +1720,57 @@ mod tests {
         assert_err!(render!("{{chains.chain1}}", context), expected_error);
     }
 
+    /// A command that runs longer than the render timeout is killed
+    #[rstest]
+    #[tokio::test]
+    async fn test_chain_command_timeout() {
+        let chain = Chain {
+            source: ChainSource::command(["sleep", "10"]),
+            ..Chain::factory(())
+        };
+        let context = TemplateContext {
+            collection: Collection {
+                chains: by_id([chain]),
+                ..Collection::factory(())
+            },
+            render_timeout: Some(Duration::from_millis(100)),
+            ..TemplateContext::factory(())
+        };
+
+        assert_err!(
+            render!("{{chains.chain1}}", context),
+            "Resolving chain `chain1`: Timed out after 100ms"
+        );
+    }
+
+    /// In-progress keys are tracked while rendering, and cleared after
+    #[rstest]
+    #[tokio::test]
+    async fn test_render_progress() {
+        let chain = Chain {
+            source: ChainSource::command(["sleep", "0.2"]),
+            ..Chain::factory(())
+        };
+        let context = TemplateContext {
+            collection: Collection {
+                chains: by_id([chain]),
+                ..Collection::factory(())
+            },
+            ..TemplateContext::factory(())
+        };
+        let template: Template = "{{chains.chain1}}".into();
+
+        let render = template.render_string(&context);
+        let check = async {
+            time::sleep(Duration::from_millis(100)).await;
+            context.progress.active()
+        };
+        let (result, active) = tokio::join!(render, check);
+        result.unwrap();
+        assert_eq!(active, vec!["chains.chain1".to_owned()]);
+        assert_eq!(context.progress.active(), Vec::<String>::new());
+    }
+
     /// Test success with a chained environment variable
     #[rstest]
     #[case::present(Some("test!"), "test!")]
@@ -719,19 +1803,26 @@ mod tests {
         assert_eq!(result.unwrap(), expected);
     }
 
-    /// Test success with chained file
+    /// Test success with chained file. Glob characters in a path are literal
+    /// unless `glob` is enabled
     #[rstest]
+    #[case::plain("stuff.txt")]
+    #[case::glob_characters("data[1].txt")]
     #[tokio::test]
-    async fn test_chain_file(temp_dir: TempDir) {
+    async fn test_chain_file(temp_dir: TempDir, #[case] file_name: &str) {
         // Create a temp file that we'll read from
-        let path = temp_dir.join("stuff.txt");
+        let path = temp_dir.join(file_name);
         fs::write(&path, "hello!").await.unwrap();
         // Sanity check to debug race condition
         assert_eq!(fs::read_to_string(&path).await.unwrap(), "hello!");
         let path: Template = path.to_str().unwrap().into();
 
         let chain = Chain {
-            source: ChainSource::File { path: path.clone() },
+            source: ChainSource::File {
+                path: path.clone(),
+                glob: false,
+                watch: false,
+            },
             ..Chain::factory(())
         };
         let context = TemplateContext {
@@ -749,12 +1840,67 @@ mod tests {
         );
     }
 
-    /// Test failure with chained file
+    /// Selectors can address rows and columns of CSV data, detected by the
+    /// file extension
+    #[rstest]
+    #[case::cell("$[1].name", "Bob, Jr.")]
+    #[case::column("$[*].age", "30,4")]
+    #[tokio::test]
+    async fn test_chain_file_csv(
+        temp_dir: TempDir,
+        #[case] selector: &str,
+        #[case] expected: &str,
+    ) {
+        let path = temp_dir.join("people.csv");
+        fs::write(&path, "name,age\nAlice,30\n\"Bob, Jr.\",4\n")
+            .await
+            .unwrap();
+
+        let chain = Chain {
+            source: ChainSource::File {
+                path: path.to_str().unwrap().into(),
+                glob: false,
+                watch: false,
+            },
+            selector: Some(selector.parse().unwrap()),
+            multi: Some(ChainMultiValue::Join(",".into())),
+            ..Chain::factory(())
+        };
+        let context = TemplateContext {
+            collection: Collection {
+                chains: by_id([chain]),
+                ..Collection::factory(())
+            },
+            ..TemplateContext::factory(())
+        };
+
+        assert_eq!(render!("{{chains.chain1}}", context).unwrap(), expected);
+    }
+
+    /// Test a glob path, which should load the most recently modified match.
+    /// Watched paths should be recorded in the context.
+    #[rstest]
     #[tokio::test]
-    async fn test_chain_file_error() {
+    async fn test_chain_file_glob(temp_dir: TempDir) {
+        for (name, age_secs) in
+            [("old.json", 60), ("new.json", 0), ("newer.txt", 0)]
+        {
+            let path = temp_dir.join(name);
+            fs::write(&path, name).await.unwrap();
+            std::fs::File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(SystemTime::now() - Duration::from_secs(age_secs))
+                .unwrap();
+        }
+        let pattern = temp_dir.join("*.json");
+
         let chain = Chain {
             source: ChainSource::File {
-                path: "not-real".into(),
+                path: pattern.to_str().unwrap().into(),
+                glob: true,
+                watch: true,
             },
             ..Chain::factory(())
         };
@@ -766,12 +1912,51 @@ mod tests {
             ..TemplateContext::factory(())
         };
 
-        assert_err!(
-            render!("{{chains.chain1}}", context),
-            "Reading file `not-real`"
+        assert_eq!(render!("{{chains.chain1}}", context).unwrap(), "new.json");
+        assert_eq!(
+            context.watched_files.files(),
+            vec![WatchedFile {
+                path: pattern,
+                glob: true
+            }]
         );
     }
 
+    /// Test failure with chained file
+    #[rstest]
+    #[case::missing("not-real", false, "Reading file `not-real`")]
+    #[case::literal("not-real/*.json", false, "Reading file `not-real/*.json`")]
+    #[case::glob_empty(
+        "not-real/*.json",
+        true,
+        "No files match `not-real/*.json`"
+    )]
+    #[case::glob_invalid("[.json", true, "Invalid glob pattern `[.json`")]
+    #[tokio::test]
+    async fn test_chain_file_error(
+        #[case] path: &str,
+        #[case] glob: bool,
+        #[case] expected_error: &str,
+    ) {
+        let chain = Chain {
+            source: ChainSource::File {
+                path: path.into(),
+                glob,
+                watch: false,
+            },
+            ..Chain::factory(())
+        };
+        let context = TemplateContext {
+            collection: Collection {
+                chains: by_id([chain]),
+                ..Collection::factory(())
+            },
+            ..TemplateContext::factory(())
+        };
+
+        assert_err!(render!("{{chains.chain1}}", context), expected_error);
+    }
+
     #[tokio::test]
     async fn test_chain_prompt() {
         let chain = Chain {
@@ -783,7 +1968,7 @@ mod tests {
         };
 
         // Test value from prompter
-        let mut context = TemplateContext {
+        let context = TemplateContext {
             collection: Collection {
                 chains: by_id([chain]),
                 ..Collection::factory(())
@@ -794,11 +1979,120 @@ mod tests {
         };
         assert_eq!(render!("{{chains.chain1}}", context).unwrap(), "hello!");
 
-        // Test default value
-        context.prompter = Box::new(TestPrompter::new::<String>(None));
+        // Test default value. This needs a new context, because the old one
+        // has the value cached
+        let context = TemplateContext {
+            collection: context.collection,
+            prompter: Box::new(TestPrompter::new::<String>(None)),
+            ..TemplateContext::factory(())
+        };
         assert_eq!(render!("{{chains.chain1}}", context).unwrap(), "default");
     }
 
+    /// A chain used multiple times with the same context should only be
+    /// resolved once, even when the uses are rendered concurrently
+    #[tokio::test]
+    async fn test_chain_cache() {
+        /// Respond to each prompt with the number of prompts so far
+        #[derive(Debug, Default)]
+        struct CountingPrompter(Arc<AtomicUsize>);
+
+        impl Prompter for CountingPrompter {
+            fn prompt(&self, prompt: Prompt) {
+                let count = self.0.fetch_add(1, Ordering::Relaxed) + 1;
+                prompt.channel.respond(count.to_string());
+            }
+        }
+
+        let chain = Chain {
+            source: ChainSource::Prompt {
+                message: None,
+                default: None,
+            },
+            ..Chain::factory(())
+        };
+        let count = Arc::new(AtomicUsize::new(0));
+        let context = TemplateContext {
+            collection: Collection {
+                chains: by_id([chain]),
+                ..Collection::factory(())
+            },
+            prompter: Box::new(CountingPrompter(Arc::clone(&count))),
+            ..TemplateContext::factory(())
+        };
+
+        assert_eq!(
+            render!("{{chains.chain1}} {{chains.chain1}}", context).unwrap(),
+            "1 1"
+        );
+        assert_eq!(render!("{{chains.chain1}}", context).unwrap(), "1");
+        assert_eq!(count.load(Ordering::Relaxed), 1);
+    }
+
+    /// A chain that refers to itself is an error, rather than recursing
+    /// forever
+    #[tokio::test]
+    async fn test_chain_self_reference() {
+        let chain = Chain {
+            source: ChainSource::command(["echo", "{{chains.chain1}}"]),
+            ..Chain::factory(())
+        };
+        let context = TemplateContext {
+            collection: Collection {
+                chains: by_id([chain]),
+                ..Collection::factory(())
+            },
+            ..TemplateContext::factory(())
+        };
+        assert_err!(
+            render!("{{chains.chain1}}", context),
+            "Chain `chain1` depends on itself"
+        );
+    }
+
+    /// Chains that depend on each other are an error, even when they're
+    /// resolved by concurrent renders that would otherwise wait on each other
+    /// forever
+    #[tokio::test]
+    async fn test_chain_cycle() {
+        // Each chain waits on a slow chain before rendering the other one, so
+        // both are in progress before either one needs the other
+        let slow = Chain {
+            id: "slow".into(),
+            source: ChainSource::command(["sleep", "0.1"]),
+            ..Chain::factory(())
+        };
+        let chain = |id: &str, other: &str| Chain {
+            id: id.into(),
+            source: ChainSource::Command {
+                command: vec!["echo".into(), "{{chains.slow}}".into()],
+                stdin: Some(format!("{{{{chains.{other}}}}}").as_str().into()),
+            },
+            ..Chain::factory(())
+        };
+        let context = TemplateContext {
+            collection: Collection {
+                chains: by_id([slow, chain("a", "b"), chain("b", "a")]),
+                ..Collection::factory(())
+            },
+            ..TemplateContext::factory(())
+        };
+
+        let a = Template::from("{{chains.a}}");
+        let b = Template::from("{{chains.b}}");
+        let (a_result, b_result) =
+            time::timeout(Duration::from_secs(5), async {
+                tokio::join!(
+                    a.render_string(&context),
+                    b.render_string(&context)
+                )
+            })
+            .await
+            .expect("Render deadlocked");
+        assert_err!(a_result, "depends on itself");
+        assert_err!(b_result, "depends on itself");
+    }
+
     /// Prompting gone wrong
     #[tokio::test]
     async fn test_chain_prompt_error() {
@@ -856,6 +2150,57 @@ mod tests {
         );
     }
 
+    /// Stdin chains fail if stdin is reserved for something else
+    #[tokio::test]
+    async fn test_chain_stdin_unavailable() {
+        let chain = Chain {
+            source: ChainSource::Stdin,
+            ..Chain::factory(())
+        };
+        let context = TemplateContext {
+            collection: Collection {
+                chains: by_id([chain]),
+                ..Collection::factory(())
+            },
+            stdin_available: false,
+            ..TemplateContext::factory(())
+        };
+        assert_err!(
+            render!("{{chains.chain1}}", context),
+            "Stdin is not available"
+        );
+    }
+
+    /// Overriding a sensitive chain should keep the value masked
+    #[tokio::test]
+    async fn test_chain_sensitive_override() {
+        let chain = Chain {
+            sensitive: true,
+            ..Chain::factory(())
+        };
+        let context = TemplateContext {
+            collection: Collection {
+                chains: by_id([chain]),
+                ..Collection::factory(())
+            },
+            overrides: indexmap! {"chains.chain1".into() => "hunter2".into()},
+            ..TemplateContext::factory(())
+        };
+        assert_eq!(
+            Template::from("{{chains.chain1}}")
+                .render_chunks(&context)
+                .await,
+            vec![TemplateChunk::Rendered {
+                value: "hunter2".into(),
+                sensitive: true
+            }]
+        );
+        assert_eq!(
+            context.rendered_values.snapshot()["chains.chain1"],
+            RenderedValues::SENSITIVE_MASK
+        );
+    }
+
     /// Test linking two chains together. This example is contribed because the
     /// command could just read the file itself, but don't worry about it it's
     /// just a test.
@@ -868,7 +2213,11 @@ mod tests {
         let path: Template = path.to_str().unwrap().into();
         let file_chain = Chain {
             id: "file".into(),
-            source: ChainSource::File { path },
+            source: ChainSource::File {
+                path,
+                glob: false,
+                watch: false,
+            },
             ..Chain::factory(())
         };
 
@@ -904,6 +2253,8 @@ mod tests {
             id: "file".into(),
             source: ChainSource::File {
                 path: "bogus.txt".into(),
+                glob: false,
+                watch: false,
             },
 
             ..Chain::factory(())
@@ -953,6 +2304,27 @@ mod tests {
         assert_eq!(result.unwrap(), expected);
     }
 
+    /// Captured values are rendered, and recorded with the request
+    #[tokio::test]
+    async fn test_capture() {
+        let context = TemplateContext {
+            captures: indexmap! {"token".into() => "abc123".into()},
+            ..TemplateContext::factory(())
+        };
+        assert_eq!(
+            render!("Bearer {{captures.token}}", context).unwrap(),
+            "Bearer abc123"
+        );
+        assert!(context
+            .rendered_values
+            .snapshot()
+            .contains_key("captures.token"));
+        assert_err!(
+            render!("{{captures.unknown}}", context),
+            "Unknown capture `unknown`"
+        );
+    }
+
     /// Test rendering non-UTF-8 data
     #[tokio::test]
     async fn test_render_binary() {
@@ -1036,6 +2408,97 @@ mod tests {
         );
     }
 
+    /// Test static validation, which checks keys without rendering
+    #[test]
+    fn test_validate() {
+        let context =
+            profile_context(indexmap! { "user_id".into() => "user1".into() });
+        let collection = Collection {
+            chains: by_id([Chain::factory(())]),
+            ..context.collection
+        };
+        let profile_id = context.selected_profile.as_ref();
+
+        let template = Template::from(
+            r#"{{user_id}} \{{ {{unknown}} {{chains.chain1}} {{chains.bogus}}"#,
+        );
+        let chunks = template.validate(&collection, profile_id);
+        // ChainError doesn't support equality, so check the last one manually
+        let (last, chunks) = chunks.split_last().unwrap();
+        assert_eq!(
+            chunks,
+            [
+                TemplateSourceChunk::Key {
+                    source: "{{user_id}}".into(),
+                    error: None
+                },
+                TemplateSourceChunk::Raw(r#" \{{ "#.into()),
+                TemplateSourceChunk::Key {
+                    source: "{{unknown}}".into(),
+                    error: Some(TemplateError::FieldUnknown {
+                        field: "unknown".into()
+                    }),
+                },
+                TemplateSourceChunk::Raw(" ".into()),
+                TemplateSourceChunk::Key {
+                    source: "{{chains.chain1}}".into(),
+                    error: None
+                },
+                TemplateSourceChunk::Raw(" ".into()),
+            ]
+        );
+        let (source, chain_id, unknown) = assert_matches!(
+            last,
+            TemplateSourceChunk::Key {
+                source,
+                error: Some(TemplateError::Chain {
+                    chain_id,
+                    error: ChainError::ChainUnknown(unknown),
+                }),
+            } => (source, chain_id, unknown)
+        );
+        assert_eq!(source, "{{chains.bogus}}");
+        assert_eq!(chain_id, &ChainId::from("bogus"));
+        assert_eq!(unknown, &ChainId::from("bogus"));
+
+        // Fields can't be checked without a profile
+        assert_eq!(
+            Template::from("{{user_id}}").validate(&collection, None),
+            vec![TemplateSourceChunk::Key {
+                source: "{{user_id}}".into(),
+                error: Some(TemplateError::NoProfileSelected),
+            }]
+        );
+    }
+
+    /// Test replacing keys with another placeholder syntax. Escaped keys are
+    /// raw text, so they're left alone
+    #[test]
+    fn test_replace_keys() {
+        let template =
+            Template::from(r#"{{host}}/users/{{chains.user_id}} \{{raw}}"#);
+        assert!(template.is_dynamic());
+        assert_eq!(
+            template.replace_keys(|key| format!("<{key}>")),
+            "<host>/users/<chains.user_id> {{raw}}"
+        );
+        assert!(!Template::from(r#"static \{{raw}}"#).is_dynamic());
+    }
+
+    /// Build an exchange with a JSON response body, received `age_secs` ago
+    fn token_exchange(body: serde_json::Value, age_secs: u64) -> Exchange {
+        Exchange {
+            response: ResponseRecord {
+                headers: header_map([("content-type", "application/json")]),
+                body: body.to_string().into_bytes().into(),
+                ..ResponseRecord::factory(())
+            }
+            .into(),
+            end_time: Utc::now() - Duration::from_secs(age_secs),
+            ..Exchange::factory(())
+        }
+    }
+
     /// Build a template context that only has simple profile data
     fn profile_context(data: IndexMap<String, Template>) -> TemplateContext {
         let profile = Profile {
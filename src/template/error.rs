@@ -4,7 +4,7 @@ use crate::{
     template::RECURSION_LIMIT,
     util::doc_link,
 };
-use std::{io, path::PathBuf, string::FromUtf8Error};
+use std::{io, path::PathBuf, string::FromUtf8Error, time::Duration};
 use thiserror::Error;
 use winnow::error::{ContextError, ParseError};
 
@@ -54,6 +54,30 @@ pub enum TemplateError {
         error: Box<Self>,
     },
 
+    /// Tried to load a recipe variable outside the scope of a recipe
+    #[error("Variables are only available within a recipe")]
+    NoRecipe,
+
+    /// A capture key referred to a name that hasn't been captured in this
+    /// session
+    #[error(
+        "Unknown capture `{name}`; use the Capture Value action on a response \
+        to capture it"
+    )]
+    CaptureUnknown { name: String },
+
+    /// A variable key referred to a variable not defined by the recipe
+    #[error("Unknown variable `{variable}`")]
+    VariableUnknown { variable: String },
+
+    /// An bubbled-up error from rendering a recipe variable value
+    #[error("Rendering nested template for variable `{variable}`")]
+    VariableNested {
+        variable: String,
+        #[source]
+        error: Box<Self>,
+    },
+
     /// In many contexts, the render output needs to be usable as a string.
     /// This error occurs when we wanted to render to a string, but whatever
     /// bytes we got were not valid UTF-8. The underlying error message is
@@ -68,6 +92,11 @@ pub enum TemplateError {
     )]
     RecursionLimit,
 
+    /// A chain's value depends on itself, either directly or through other
+    /// chains. Without this, the render would wait on itself forever.
+    #[error("Chain `{chain_id}` depends on itself")]
+    ChainCycle { chain_id: ChainId },
+
     #[error("Resolving chain `{chain_id}`")]
     Chain {
         chain_id: ChainId,
@@ -147,6 +176,39 @@ pub enum ChainError {
         error: io::Error,
     },
 
+    /// File path is an invalid glob pattern
+    #[error("Invalid glob pattern `{pattern}`")]
+    FileGlob {
+        pattern: String,
+        #[source]
+        error: glob::PatternError,
+    },
+
+    /// File path is a glob pattern, but no files match it
+    #[error("No files match `{pattern}`")]
+    FileGlobEmpty { pattern: String },
+
+    /// Error reading piped data from stdin
+    #[error("Reading stdin")]
+    Stdin(#[source] io::Error),
+
+    /// Stdin is used for something else in this context, so `!stdin` chains
+    /// can't read from it
+    #[error(
+        "Stdin is not available here; `!stdin` chains can only be used from \
+        `slumber request` and similar CLI commands"
+    )]
+    StdinUnavailable,
+
+    /// Error loading a secret from the OS keyring
+    #[error("Loading key `{key}` from keyring service `{service}`")]
+    Keyring {
+        service: String,
+        key: String,
+        #[source]
+        error: keyring::Error,
+    },
+
     /// Never got a response from the prompt channel. Do *not* store the
     /// `RecvError` here, because it provides useless extra output to the user.
     #[error("No response from prompt")]
@@ -161,9 +223,33 @@ pub enum ChainError {
         error: Box<TemplateError>,
     },
 
+    /// One of the chain's transforms failed
+    #[error("Applying transform `{transform}`")]
+    Transform {
+        transform: &'static str,
+        #[source]
+        error: anyhow::Error,
+    },
+
     /// Specified !header did not exist in the response
     #[error("Header `{header}` not in response")]
     MissingHeader { header: String },
+
+    /// Polled request never returned the expected value
+    #[error(
+        "Polling timed out after {timeout:?}; expected `{expected}`, last \
+        response had `{}`",
+        last_value.as_deref().unwrap_or("<no value>")
+    )]
+    PollTimeout {
+        timeout: Duration,
+        expected: String,
+        last_value: Option<String>,
+    },
+
+    /// Chain took longer than the configured render timeout
+    #[error("Timed out after {timeout:?}")]
+    Timeout { timeout: Duration },
 }
 
 /// Error occurred while trying to build/execute a triggered request
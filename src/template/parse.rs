@@ -33,8 +33,10 @@ const KEY_CLOSE: &str = "}}";
 /// Any sequence that can be escaped to strip its semantic meaning
 const ESCAPABLE: [&str; 2] = [ESCAPE, KEY_OPEN];
 // Export these so they can be used in TemplateKey's Display impl
+pub const CAPTURE_PREFIX: &str = "captures.";
 pub const CHAIN_PREFIX: &str = "chains.";
 pub const ENV_PREFIX: &str = "env.";
+pub const VARIABLE_PREFIX: &str = "vars.";
 
 impl Template {
     /// Create a template that renders a single chain. This creates a template
@@ -72,31 +74,36 @@ impl From<Template> for String {
 
 impl Display for Template {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        // Re-stringify the template. For raw spans, we need to escape special
-        // characters to get them to re-parse correctly later.
         for chunk in &self.chunks {
-            match chunk {
-                TemplateInputChunk::Raw(s) => {
-                    let s = s.as_str();
-                    let searcher = AhoCorasick::new(ESCAPABLE)
-                        .expect("Invalid search string");
-                    // Find each special sequence, and add a backslash before it
-                    let mut i = 0;
-                    for m in searcher.find_iter(s) {
-                        // Write everything before the special char, then escape
-                        // The escaped sequence will be written on the next iter
-                        write!(f, "{}{ESCAPE}", &s[i..m.start()])?;
-                        i = m.start();
-                    }
-                    // Fencepost: segment betwen last match and end
-                    write!(f, "{}", &s[i..])?;
-                }
-                TemplateInputChunk::Key(key) => {
-                    write!(f, "{KEY_OPEN}{key}{KEY_CLOSE}")?;
+            write!(f, "{chunk}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Re-stringify a single chunk, as it would appear in the source
+impl Display for TemplateInputChunk {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            // For raw spans, we need to escape special characters to get them
+            // to re-parse correctly later
+            Self::Raw(s) => {
+                let s = s.as_str();
+                let searcher =
+                    AhoCorasick::new(ESCAPABLE).expect("Invalid search string");
+                // Find each special sequence, and add a backslash before it
+                let mut i = 0;
+                for m in searcher.find_iter(s) {
+                    // Write everything before the special char, then escape
+                    // The escaped sequence will be written on the next iter
+                    write!(f, "{}{ESCAPE}", &s[i..m.start()])?;
+                    i = m.start();
                 }
+                // Fencepost: segment betwen last match and end
+                write!(f, "{}", &s[i..])
             }
+            Self::Key(key) => write!(f, "{KEY_OPEN}{key}{KEY_CLOSE}"),
         }
-        Ok(())
     }
 }
 
@@ -221,8 +228,12 @@ fn key_contents(input: &mut &str) -> PResult<TemplateKey> {
             identifier.map(|id| TemplateKey::Chain(id.into())),
         )
         .context(StrContext::Label("chain")),
+        preceded(CAPTURE_PREFIX, identifier.map(TemplateKey::Capture))
+            .context(StrContext::Label("capture")),
         preceded(ENV_PREFIX, identifier.map(TemplateKey::Environment))
             .context(StrContext::Label("environment")),
+        preceded(VARIABLE_PREFIX, identifier.map(TemplateKey::Variable))
+            .context(StrContext::Label("variable")),
         identifier
             .map(TemplateKey::Field)
             .context(StrContext::Label("field")),
@@ -273,6 +284,16 @@ mod tests {
         TemplateInputChunk::Key(TemplateKey::Chain(chain_id.into()))
     }
 
+    /// Shorthand for creating a capture key chunk
+    fn key_capture(name: &str) -> TemplateInputChunk {
+        TemplateInputChunk::Key(TemplateKey::Capture(name.into()))
+    }
+
+    /// Shorthand for creating a recipe variable key chunk
+    fn key_variable(variable: &str) -> TemplateInputChunk {
+        TemplateInputChunk::Key(TemplateKey::Variable(variable.into()))
+    }
+
     /// Test parsing success cases
     #[rstest]
     #[case::empty("", tmpl([]))]
@@ -281,7 +302,9 @@ mod tests {
     #[case::field("{{field1}}", tmpl([key_field("field1")]))]
     #[case::field_number_id("{{1}}", tmpl([key_field("1")]))]
     #[case::chain("{{chains.chain1}}", tmpl([key_chain("chain1")]))]
+    #[case::capture("{{captures.token}}", tmpl([key_capture("token")]))]
     #[case::env("{{env.ENV}}", tmpl([key_env("ENV")]))]
+    #[case::variable("{{vars.var1}}", tmpl([key_variable("var1")]))]
     #[case::utf8(
         "intro\n{{user_id}} 💚💙💜 {{chains.chain}}\noutro\r\nmore outro",
         tmpl([
@@ -319,6 +342,7 @@ mod tests {
     #[case::invalid_dotted_key("{{bogus.one}}", "invalid key")]
     #[case::invalid_chain("{{chains.one.two}}", "invalid key")]
     #[case::invalid_env("{{env.one.two}}", "invalid key")]
+    #[case::invalid_variable("{{vars.one.two}}", "invalid key")]
     #[case::whitespace_key("{{ field }}", "invalid identifier")]
     fn test_parse_error(#[case] template: &str, #[case] expected_error: &str) {
         assert_err!(template.parse::<Template>(), expected_error);
@@ -348,6 +372,7 @@ mod tests {
     #[case::field(tmpl([key_field("user_id")]), "{{user_id}}")]
     #[case::env(tmpl([key_env("ENV1")]), "{{env.ENV1}}")]
     #[case::chain(tmpl([key_chain("chain1")]), "{{chains.chain1}}")]
+    #[case::capture(tmpl([key_capture("token")]), "{{captures.token}}")]
     #[case::escape_key(
         tmpl([raw(r#"esc: {{user_id}}"#)]), r#"esc: \{{user_id}}"#
     )]
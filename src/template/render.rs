@@ -2,33 +2,51 @@
 
 use crate::{
     collection::{
-        ChainId, ChainOutputTrim, ChainRequestSection, ChainRequestTrigger,
-        ChainSource, RecipeId,
+        ChainId, ChainMultiValue, ChainOutputTrim, ChainRequestPoll,
+        ChainRequestSection, ChainRequestTrigger, ChainSource, ChainTransform,
+        RecipeId, SecretManager, SelectorType,
+    },
+    http::{
+        ContentType, Exchange, ProtobufRegistry, Query, QueryError,
+        RequestSeed, ResponseRecord, Selector,
     },
-    http::{ContentType, Exchange, RequestSeed, ResponseRecord},
     template::{
-        error::TriggeredRequestError, parse::TemplateInputChunk, ChainError,
-        Prompt, Template, TemplateChunk, TemplateContext, TemplateError,
-        TemplateKey, RECURSION_LIMIT,
+        error::TriggeredRequestError, parse::TemplateInputChunk,
+        recipe_variables, ChainError, Prompt, Template, TemplateChunk,
+        TemplateContext, TemplateError, TemplateKey, WatchedFile,
+        RECURSION_LIMIT,
     },
-    util::ResultExt,
+    util::{keyring_service, ResultExt},
 };
+use anyhow::Context;
 use async_trait::async_trait;
-use chrono::Utc;
-use futures::future;
+use base64::{prelude::BASE64_STANDARD, Engine};
+use chrono::{DateTime, Utc};
+use futures::{future, Future};
+use regex::Regex;
+use reqwest::header::{self, HeaderMap};
 use std::{
     env,
-    path::PathBuf,
+    io::{self, IsTerminal},
+    panic,
+    path::{Path, PathBuf},
     process::Stdio,
     sync::{atomic::Ordering, Arc},
+    time::{Duration, Instant},
+};
+use tokio::{
+    fs,
+    io::{AsyncReadExt, AsyncWriteExt},
+    process::Command,
+    sync::{oneshot, OnceCell},
+    task, time,
 };
-use tokio::{fs, io::AsyncWriteExt, process::Command, sync::oneshot};
 use tracing::{debug, debug_span, instrument, trace};
 
 /// Outcome of rendering a single chunk. This allows attaching some metadata to
 /// the render.
-#[derive(Debug)]
-struct RenderedChunk {
+#[derive(Clone, Debug)]
+pub(super) struct RenderedChunk {
     value: Vec<u8>,
     sensitive: bool,
 }
@@ -107,23 +125,36 @@ impl Template {
                     let raw = key.to_string();
                     // If the key is in the overrides, use the given value
                     // without parsing it
-                    let result = match context.overrides.get(&raw) {
+                    let result = match context.get_override(&key, &raw) {
                         Some(value) => {
                             trace!(
                                 key = raw,
                                 value,
                                 "Rendered template key from override"
                             );
+                            // Overriding a sensitive chain doesn't make the
+                            // value any less sensitive
+                            let sensitive = match &key {
+                                TemplateKey::Chain(chain_id) => context
+                                    .collection
+                                    .chains
+                                    .get(chain_id)
+                                    .is_some_and(|chain| chain.sensitive),
+                                _ => false,
+                            };
+                            context.rendered_values.insert(
+                                raw,
+                                value.as_bytes(),
+                                sensitive,
+                            );
                             Ok(RenderedChunk {
                                 value: value.clone().into_bytes(),
-                                // The overriden value *could* be marked
-                                // sensitive, but we're taking a shortcut and
-                                // assuming it isn't
-                                sensitive: false,
+                                sensitive,
                             })
                         }
                         None => {
                             // Standard case - parse the key and render it
+                            let _guard = context.progress.start(raw.clone());
                             let result = key.to_source().render(context).await;
                             if let Ok(value) = &result {
                                 trace!(
@@ -131,6 +162,18 @@ impl Template {
                                     ?value,
                                     "Rendered template key"
                                 );
+                                // Chain and capture values can't be
+                                // reproduced later, so record them with the
+                                // request
+                                if let TemplateKey::Chain(_)
+                                | TemplateKey::Capture(_) = key
+                                {
+                                    context.rendered_values.insert(
+                                        raw,
+                                        &value.value,
+                                        value.sensitive,
+                                    );
+                                }
                             }
                             result
                         }
@@ -179,9 +222,13 @@ impl TemplateKey {
         match self {
             Self::Field(field) => Box::new(FieldTemplateSource { field }),
             Self::Chain(chain_id) => Box::new(ChainTemplateSource { chain_id }),
+            Self::Capture(name) => Box::new(CaptureTemplateSource { name }),
             Self::Environment(variable) => {
                 Box::new(EnvironmentTemplateSource { variable })
             }
+            Self::Variable(variable) => {
+                Box::new(VariableTemplateSource { variable })
+            }
         }
     }
 }
@@ -246,6 +293,26 @@ impl<'a> TemplateSource<'a> for FieldTemplateSource<'a> {
     }
 }
 
+/// A value captured from a response in the TUI
+struct CaptureTemplateSource<'a> {
+    name: &'a str,
+}
+
+#[async_trait]
+impl<'a> TemplateSource<'a> for CaptureTemplateSource<'a> {
+    async fn render(&self, context: &'a TemplateContext) -> TemplateResult {
+        let value = context.captures.get(self.name).ok_or_else(|| {
+            TemplateError::CaptureUnknown {
+                name: self.name.to_owned(),
+            }
+        })?;
+        Ok(RenderedChunk {
+            value: value.clone().into_bytes(),
+            sensitive: false,
+        })
+    }
+}
+
 /// A chained value from a complex source. Could be an HTTP response, file, etc.
 struct ChainTemplateSource<'a> {
     chain_id: &'a ChainId,
@@ -254,6 +321,18 @@ struct ChainTemplateSource<'a> {
 #[async_trait]
 impl<'a> TemplateSource<'a> for ChainTemplateSource<'a> {
     async fn render(&self, context: &'a TemplateContext) -> TemplateResult {
+        // A chain can be used many times in one request, but it should only
+        // be resolved once
+        context
+            .chain_cache
+            .get_or_resolve(self.chain_id, || self.resolve(context))
+            .await
+    }
+}
+
+impl<'a> ChainTemplateSource<'a> {
+    /// Resolve the chain's value from its source, skipping the cache
+    async fn resolve(&self, context: &'a TemplateContext) -> TemplateResult {
         // Any error in here is the chain error subtype
         let result: Result<_, ChainError> = async {
             // Resolve chained value
@@ -276,14 +355,29 @@ impl<'a> TemplateSource<'a> for ChainTemplateSource<'a> {
                     // No way to guess content type on this
                     None,
                 ),
-                ChainSource::File { path } => {
-                    self.render_file(context, path).await?
+                ChainSource::File { path, glob, watch } => {
+                    self.render_file(context, path, *glob, *watch).await?
                 }
+                ChainSource::Keyring { service, key } => (
+                    self.render_keyring(context, service.as_ref(), key).await?,
+                    // No way to guess content type on this
+                    None,
+                ),
+                ChainSource::Secret { manager, name } => (
+                    self.render_secret(context, *manager, name).await?,
+                    // No way to guess content type on this
+                    None,
+                ),
                 ChainSource::Environment { variable } => (
                     self.render_environment_variable(context, variable).await?,
                     // No way to guess content type on this
                     None,
                 ),
+                ChainSource::Stdin => (
+                    self.render_stdin(context, chain.sensitive).await?,
+                    // No way to guess content type on this
+                    None,
+                ),
                 ChainSource::Prompt { message, default } => (
                     self.render_prompt(
                         context,
@@ -300,15 +394,42 @@ impl<'a> TemplateSource<'a> for ChainTemplateSource<'a> {
                     recipe,
                     trigger,
                     section,
+                    poll,
                 } => {
-                    let response =
-                        self.get_response(context, recipe, *trigger).await?;
-                    // Guess content type based on HTTP header
-                    let content_type =
-                        ContentType::from_response(&response).ok();
-                    let value =
-                        self.extract_response_value(response, section)?;
-                    (value, content_type)
+                    let response = self
+                        .get_response(context, recipe, trigger, poll.as_ref())
+                        .await?;
+                    // Guess content type based on HTTP header. Headers are
+                    // always converted to JSON
+                    let content_type = match section {
+                        ChainRequestSection::Headers => Some(ContentType::Json),
+                        _ => ContentType::from_response(&response).ok(),
+                    };
+                    match (section, content_type) {
+                        // Binary formats are decoded to JSON up front, while
+                        // we still have the response's Content-Type. Protobuf
+                        // may need it to know the message type
+                        (
+                            ChainRequestSection::Body,
+                            Some(ContentType::MsgPack | ContentType::Protobuf),
+                        ) => {
+                            let content = ContentType::parse_response(
+                                &response,
+                                context.collection.protobuf_registry.as_ref(),
+                            )
+                            .map_err(|error| ChainError::ParseResponse {
+                                error,
+                            })?;
+                            (
+                                content.to_json().to_string().into_bytes(),
+                                Some(ContentType::Json),
+                            )
+                        }
+                        _ => (
+                            self.extract_response_value(response, section)?,
+                            content_type,
+                        ),
+                    }
                 }
             };
             // If the user provided a content type, prefer that over the
@@ -316,18 +437,45 @@ impl<'a> TemplateSource<'a> for ChainTemplateSource<'a> {
             let content_type = chain.content_type.or(content_type);
 
             // If a selector path is present, filter down the value
-            let value = if let Some(selector) = &chain.selector {
-                let content_type =
-                    content_type.ok_or(ChainError::UnknownContentType)?;
-                // Parse according to detected content type
-                let value = content_type
-                    .parse_content(&value)
-                    .map_err(|err| ChainError::ParseResponse { error: err })?;
-                selector.query_to_string(&*value)?.into_bytes()
-            } else {
-                value
+            let value = match (&chain.selector, chain.selector_type) {
+                (None, _) => value,
+                // Regex is applied to the raw text, regardless of type
+                (Some(selector), SelectorType::Regex) => {
+                    let text = std::str::from_utf8(&value).map_err(|err| {
+                        ChainError::ParseResponse { error: err.into() }
+                    })?;
+                    let results = selector.query_regex(text)?;
+                    let multi =
+                        chain.multi.as_ref().unwrap_or(&ChainMultiValue::First);
+                    multi.apply(results)?.into_bytes()
+                }
+                (Some(selector), SelectorType::Auto) => {
+                    let content_type =
+                        content_type.ok_or(ChainError::UnknownContentType)?;
+                    let results = apply_selector(
+                        selector,
+                        content_type,
+                        &value,
+                        context.collection.protobuf_registry.as_ref(),
+                    )?;
+                    let multi =
+                        chain.multi.as_ref().unwrap_or(&ChainMultiValue::Error);
+                    multi.apply(results)?.into_bytes()
+                }
             };
 
+            let value = chain.transforms.iter().try_fold(
+                value,
+                |value, transform| {
+                    transform.apply(value).map_err(|error| {
+                        ChainError::Transform {
+                            transform: transform.kind(),
+                            error,
+                        }
+                    })
+                },
+            )?;
+
             Ok(RenderedChunk {
                 value: chain.trim.apply(value),
                 sensitive: chain.sensitive,
@@ -341,17 +489,17 @@ impl<'a> TemplateSource<'a> for ChainTemplateSource<'a> {
             error,
         })
     }
-}
 
-impl<'a> ChainTemplateSource<'a> {
     /// Get an HTTP response for a recipe. This will either get the most recent
     /// response from history or re-execute the request, depending on trigger
-    /// behavior.
+    /// behavior. If polling is enabled, the request will then be re-executed
+    /// until the response matches the poll condition.
     async fn get_response(
         &self,
         context: &'a TemplateContext,
         recipe_id: &RecipeId,
-        trigger: ChainRequestTrigger,
+        trigger: &ChainRequestTrigger,
+        poll: Option<&ChainRequestPoll>,
     ) -> Result<ResponseRecord, ChainError> {
         // Get the referenced recipe. We actually only need the whole recipe if
         // we're executing the request, but we want this to error out if the
@@ -403,9 +551,12 @@ impl<'a> ChainTemplateSource<'a> {
                     )
                     .await
                     .map_err(TriggeredRequestError::Build)?;
+                // There's nowhere to show a save error from here, but it's
+                // already logged
                 ticket
                     .send(&context.database)
                     .await
+                    .map(|sent| sent.exchange)
                     .map_err(TriggeredRequestError::Send)
             };
             result.await.map_err(|error| ChainError::Trigger {
@@ -415,7 +566,7 @@ impl<'a> ChainTemplateSource<'a> {
         };
 
         // Grab the most recent request in history, or send a new request
-        let exchange = match trigger {
+        let mut exchange = match trigger {
             ChainRequestTrigger::Never => {
                 get_most_recent()?.ok_or(ChainError::NoResponse)?
             }
@@ -427,9 +578,16 @@ impl<'a> ChainTemplateSource<'a> {
                     send_request().await?
                 }
             }
-            ChainRequestTrigger::Expire(duration) => match get_most_recent()? {
+            ChainRequestTrigger::Expire(_)
+            | ChainRequestTrigger::ExpireIn(_)
+            | ChainRequestTrigger::ExpireAt(_) => match get_most_recent()? {
                 Some(exchange)
-                    if exchange.end_time + duration >= Utc::now() =>
+                    if trigger
+                        .expiration(
+                            &exchange,
+                            context.collection.protobuf_registry.as_ref(),
+                        )
+                        .is_some_and(|expiration| expiration >= Utc::now()) =>
                 {
                     exchange
                 }
@@ -438,6 +596,26 @@ impl<'a> ChainTemplateSource<'a> {
             ChainRequestTrigger::Always => send_request().await?,
         };
 
+        // Keep re-sending until the condition is met or we run out of time
+        if let Some(poll) = poll {
+            let start = Instant::now();
+            while let Err(last_value) = poll.check(
+                &exchange.response,
+                context.collection.protobuf_registry.as_ref(),
+            ) {
+                if start.elapsed() + poll.interval > poll.timeout {
+                    return Err(ChainError::PollTimeout {
+                        timeout: poll.timeout,
+                        expected: poll.equals.clone(),
+                        last_value,
+                    });
+                }
+                trace!(?last_value, "Poll condition not met, retrying");
+                time::sleep(poll.interval).await;
+                exchange = send_request().await?;
+            }
+        }
+
         // We haven't passed the exchange around so we can unwrap the Arc safely
         Ok(Arc::try_unwrap(exchange.response)
             .expect("Request Arc should have only one reference"))
@@ -465,6 +643,9 @@ impl<'a> ChainTemplateSource<'a> {
                     .as_bytes()
                     .to_vec()
             }
+            ChainRequestSection::Headers => {
+                headers_to_json(&response.headers).to_string().into_bytes()
+            }
         })
     }
 
@@ -480,19 +661,77 @@ impl<'a> ChainTemplateSource<'a> {
     }
 
     /// Render a chained value from a file. Return the files bytes, as well as
-    /// its content type if it's known
+    /// its content type if it's known. If `glob` is enabled, the path is a
+    /// pattern and the most recently modified match is read.
     async fn render_file(
         &self,
         context: &TemplateContext,
         path: &Template,
+        glob: bool,
+        watch: bool,
     ) -> Result<(Vec<u8>, Option<ContentType>), ChainError> {
         let path: PathBuf = path.render_nested("path", context).await?.into();
-        // Guess content type based on file extension
-        let content_type = ContentType::from_path(&path).ok();
-        let content = fs::read(&path)
-            .await
-            .map_err(|error| ChainError::File { path, error })?;
-        Ok((content, content_type))
+        if watch {
+            context.watched_files.insert(WatchedFile {
+                path: path.clone(),
+                glob,
+            });
+        }
+        with_timeout(context, async move {
+            let path = if glob {
+                // Globbing walks the file system, which is blocking
+                task::spawn_blocking(move || latest_glob_match(&path))
+                    .await
+                    // The task can only fail by panicking, so pass that along
+                    .unwrap_or_else(|error| {
+                        panic::resume_unwind(error.into_panic())
+                    })?
+            } else {
+                path
+            };
+            // Guess content type based on file extension
+            let content_type = ContentType::from_path(&path).ok();
+            let content = fs::read(&path)
+                .await
+                .map_err(|error| ChainError::File { path, error })?;
+            Ok((content, content_type))
+        })
+        .await
+    }
+
+    /// Render a chained value from stdin. Stdin can only be read once, so the
+    /// data is shared by every chain that uses it. If stdin is a terminal,
+    /// there's nothing piped to read, so prompt the user instead.
+    async fn render_stdin(
+        &self,
+        context: &'a TemplateContext,
+        sensitive: bool,
+    ) -> Result<Vec<u8>, ChainError> {
+        static STDIN: OnceCell<Vec<u8>> = OnceCell::const_new();
+
+        if !context.stdin_available {
+            return Err(ChainError::StdinUnavailable);
+        }
+        if io::stdin().is_terminal() {
+            debug!("Stdin is a terminal, falling back to prompt");
+            return Ok(self
+                .render_prompt(context, None, None, sensitive)
+                .await?
+                .into_bytes());
+        }
+        let content = STDIN
+            .get_or_try_init(|| {
+                with_timeout(context, async {
+                    let mut content = Vec::new();
+                    tokio::io::stdin()
+                        .read_to_end(&mut content)
+                        .await
+                        .map_err(ChainError::Stdin)?;
+                    Ok(content)
+                })
+            })
+            .await?;
+        Ok(content.clone())
     }
 
     /// Render a chained value from an external command
@@ -512,12 +751,6 @@ impl<'a> ChainTemplateSource<'a> {
         ))
         .await?;
 
-        let [program, args @ ..] = command.as_slice() else {
-            return Err(ChainError::CommandMissing);
-        };
-
-        let _ = debug_span!("Executing command", ?command).entered();
-
         // Render the stdin template, if present
         let input = if let Some(template) = stdin {
             let input = template.render_nested("stdin", context).await?;
@@ -527,12 +760,30 @@ impl<'a> ChainTemplateSource<'a> {
             None
         };
 
+        with_timeout(context, self.run_command(&command, input.as_deref()))
+            .await
+    }
+
+    /// Execute an external command, with optional stdin. Return its stdout
+    async fn run_command(
+        &self,
+        command: &[String],
+        input: Option<&str>,
+    ) -> Result<Vec<u8>, ChainError> {
+        let [program, args @ ..] = command else {
+            return Err(ChainError::CommandMissing);
+        };
+
+        let _ = debug_span!("Executing command", ?command).entered();
+
         // Spawn the command process
         let mut process = Command::new(program)
             .args(args)
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
+            // Don't leave the process hanging if the render times out
+            .kill_on_drop(true)
             .spawn()
             .map_err(|error| ChainError::Command {
                 command: command.to_owned(),
@@ -574,6 +825,59 @@ impl<'a> ChainTemplateSource<'a> {
         Ok(output.stdout)
     }
 
+    /// Render a value from the OS keyring
+    async fn render_keyring(
+        &self,
+        context: &TemplateContext,
+        service: Option<&Template>,
+        key: &Template,
+    ) -> Result<Vec<u8>, ChainError> {
+        let key = key.render_nested("key", context).await?;
+        let service = if let Some(template) = service {
+            template.render_nested("service", context).await?
+        } else {
+            let collection_path = context
+                .database
+                .collection_path()
+                .map_err(ChainError::Database)?;
+            keyring_service(&collection_path)
+        };
+
+        // Keyring backends make synchronous IPC calls (e.g. to D-Bus), so
+        // keep them off the async runtime
+        let result = task::spawn_blocking({
+            let service = service.clone();
+            let key = key.clone();
+            move || keyring::Entry::new(&service, &key)?.get_password()
+        })
+        .await
+        .unwrap_or_else(|error| {
+            Err(keyring::Error::PlatformFailure(error.into()))
+        });
+        let password = result.map_err(|error| ChainError::Keyring {
+            service,
+            key,
+            error,
+        })?;
+        Ok(password.into_bytes())
+    }
+
+    /// Render a value from an external password manager
+    async fn render_secret(
+        &self,
+        context: &TemplateContext,
+        manager: SecretManager,
+        name: &Template,
+    ) -> Result<Vec<u8>, ChainError> {
+        let name = name.render_nested("name", context).await?;
+        let output = with_timeout(
+            context,
+            self.run_command(&manager.command(name), None),
+        )
+        .await?;
+        Ok(manager.extract(output))
+    }
+
     /// Render a value by asking the user to provide it
     async fn render_prompt(
         &self,
@@ -606,6 +910,44 @@ impl<'a> ChainTemplateSource<'a> {
     }
 }
 
+/// Apply the context's render timeout (if any) to a chain's external lookup.
+/// Only wrap the lookup itself, not the rendering of its nested templates, so
+/// time spent on prompts isn't counted.
+async fn with_timeout<T>(
+    context: &TemplateContext,
+    future: impl Future<Output = Result<T, ChainError>>,
+) -> Result<T, ChainError> {
+    match context.render_timeout {
+        Some(timeout) => time::timeout(timeout, future)
+            .await
+            .map_err(|_| ChainError::Timeout { timeout })?,
+        None => future.await,
+    }
+}
+
+/// Get the most recently modified file that matches a glob pattern. Entries
+/// that can't be read are skipped.
+fn latest_glob_match(pattern: &Path) -> Result<PathBuf, ChainError> {
+    let pattern = pattern.to_string_lossy();
+    let paths = glob::glob(&pattern).map_err(|error| ChainError::FileGlob {
+        pattern: pattern.to_string(),
+        error,
+    })?;
+    paths
+        .filter_map(|path| {
+            let path = path.ok()?;
+            let metadata = path.metadata().ok()?;
+            metadata
+                .is_file()
+                .then_some((metadata.modified().ok()?, path))
+        })
+        .max_by_key(|(modified, _)| *modified)
+        .map(|(_, path)| path)
+        .ok_or_else(|| ChainError::FileGlobEmpty {
+            pattern: pattern.to_string(),
+        })
+}
+
 /// A value sourced from the process's environment
 struct EnvironmentTemplateSource<'a> {
     variable: &'a str,
@@ -622,6 +964,169 @@ impl<'a> TemplateSource<'a> for EnvironmentTemplateSource<'a> {
     }
 }
 
+/// A variable defined locally on the recipe being rendered
+struct VariableTemplateSource<'a> {
+    variable: &'a str,
+}
+
+#[async_trait]
+impl<'a> TemplateSource<'a> for VariableTemplateSource<'a> {
+    async fn render(&self, context: &'a TemplateContext) -> TemplateResult {
+        let variable = self.variable;
+        let variables = recipe_variables().ok_or(TemplateError::NoRecipe)?;
+        let template = variables.get(variable).ok_or_else(|| {
+            TemplateError::VariableUnknown {
+                variable: variable.to_owned(),
+            }
+        })?;
+
+        // recursion!
+        trace!(%variable, %template, "Rendering recursive template");
+        context.recursion_count.fetch_add(1, Ordering::Relaxed);
+        let rendered = template.render(context).await.map_err(|error| {
+            TemplateError::VariableNested {
+                variable: variable.to_owned(),
+                error: Box::new(error),
+            }
+        })?;
+        Ok(RenderedChunk {
+            value: rendered,
+            sensitive: false,
+        })
+    }
+}
+
+impl SecretManager {
+    /// Get the command to execute to load the secret with the given name
+    fn command(self, name: String) -> Vec<String> {
+        match self {
+            Self::Pass => vec!["pass".into(), "show".into(), name],
+            Self::OnePassword => vec!["op".into(), "read".into(), name],
+        }
+    }
+
+    /// Extract the secret from the command's stdout. Password managers
+    /// generally print a trailing newline, and `pass` allows additional
+    /// metadata on subsequent lines, so only the first line is the secret.
+    fn extract(self, mut output: Vec<u8>) -> Vec<u8> {
+        if let Some(index) = output.iter().position(|b| *b == b'\n') {
+            output.truncate(index);
+        }
+        if output.last() == Some(&b'\r') {
+            output.pop();
+        }
+        output
+    }
+}
+
+impl ChainRequestPoll {
+    /// Check if a response satisfies the poll condition. If not, return the
+    /// value that the query produced (if any), for error reporting
+    fn check(
+        &self,
+        response: &ResponseRecord,
+        protobuf: Option<&ProtobufRegistry>,
+    ) -> Result<(), Option<String>> {
+        let value = ContentType::parse_response(response, protobuf)
+            .ok()
+            .and_then(|content| self.until.query_to_string(&*content).ok());
+        match value {
+            Some(value) if value == self.equals => Ok(()),
+            value => Err(value),
+        }
+    }
+}
+
+impl ChainRequestTrigger {
+    /// Get the time at which a previous exchange expires, according to this
+    /// trigger. Return `None` if the trigger isn't expiration-based, or the
+    /// expiration couldn't be read from the response. In either case, the
+    /// exchange should be treated as expired.
+    fn expiration(
+        &self,
+        exchange: &Exchange,
+        protobuf: Option<&ProtobufRegistry>,
+    ) -> Option<DateTime<Utc>> {
+        let query_response = |query: &Query| {
+            let value =
+                ContentType::parse_response(&exchange.response, protobuf)
+                    .ok()
+                    .and_then(|content| query.query_to_string(&*content).ok());
+            if value.is_none() {
+                trace!(%query, "Could not read expiration from response");
+            }
+            value
+        };
+        match self {
+            Self::Never | Self::NoHistory | Self::Always => None,
+            Self::Expire(duration) => Some(exchange.end_time + *duration),
+            Self::ExpireIn(query) => {
+                let seconds = query_response(query)?.trim().parse().ok()?;
+                Some(exchange.end_time + Duration::from_secs(seconds))
+            }
+            Self::ExpireAt(query) => {
+                let value = query_response(query)?;
+                let value = value.trim();
+                if let Ok(timestamp) = value.parse::<i64>() {
+                    DateTime::from_timestamp(timestamp, 0)
+                } else {
+                    DateTime::parse_from_rfc3339(value)
+                        .ok()
+                        .map(|expiration| expiration.with_timezone(&Utc))
+                }
+            }
+        }
+    }
+}
+
+impl ChainMultiValue {
+    /// Reduce all of a selector's results into a single value
+    fn apply(&self, mut results: Vec<String>) -> Result<String, QueryError> {
+        let count = results.len();
+        match self {
+            Self::Error if count == 1 => Ok(results.remove(0)),
+            Self::First if count > 0 => Ok(results.remove(0)),
+            Self::Last if count > 0 => Ok(results.remove(count - 1)),
+            Self::Join(separator) => Ok(results.join(separator)),
+            Self::Error | Self::First | Self::Last => {
+                Err(QueryError::ResultCount { count })
+            }
+        }
+    }
+}
+
+impl ChainTransform {
+    /// Apply this transformation to a value
+    fn apply(&self, value: Vec<u8>) -> anyhow::Result<Vec<u8>> {
+        let text =
+            || std::str::from_utf8(&value).context("Value is not valid UTF-8");
+        let transformed = match self {
+            Self::Trim => text()?.trim().into(),
+            Self::Replace { from, to } => text()?.replace(from, to).into(),
+            Self::RegexReplace {
+                pattern,
+                replacement,
+            } => {
+                let regex = Regex::new(pattern)
+                    .with_context(|| format!("Invalid regex `{pattern}`"))?;
+                regex
+                    .replace_all(text()?, replacement.as_str())
+                    .into_owned()
+                    .into()
+            }
+            Self::Upper => text()?.to_uppercase().into(),
+            Self::Lower => text()?.to_lowercase().into(),
+            Self::Base64Encode => BASE64_STANDARD.encode(&value).into(),
+            Self::Base64Decode => BASE64_STANDARD.decode(text()?.trim())?,
+            Self::JsonPath(query) => {
+                let content = ContentType::Json.parse_content(&value, None)?;
+                query.query_to_string(&*content)?.into()
+            }
+        };
+        Ok(transformed)
+    }
+}
+
 impl ChainOutputTrim {
     /// Apply whitespace trimming to string values. If the value is not a valid
     /// string, no trimming is applied
@@ -641,6 +1146,87 @@ impl ChainOutputTrim {
     }
 }
 
+/// Query a chained value with a selector, according to its content type.
+/// Returns all results
+fn apply_selector(
+    selector: &Selector,
+    content_type: ContentType,
+    value: &[u8],
+    protobuf: Option<&ProtobufRegistry>,
+) -> Result<Vec<String>, ChainError> {
+    match content_type {
+        ContentType::Json
+        | ContentType::MsgPack
+        | ContentType::Protobuf
+        | ContentType::Csv
+        | ContentType::Ndjson => {
+            let query = selector.json()?;
+            let value = content_type
+                .parse_content(value, protobuf)
+                .map_err(|err| ChainError::ParseResponse { error: err })?;
+            Ok(query.query_to_strings(&*value))
+        }
+        ContentType::Xml | ContentType::Html => {
+            let document = std::str::from_utf8(value).map_err(|err| {
+                ChainError::ParseResponse { error: err.into() }
+            })?;
+            let result = if content_type == ContentType::Xml {
+                selector.query_xml(document)
+            } else {
+                selector.query_html(document)
+            };
+            Ok(result?)
+        }
+    }
+}
+
+/// Convert response headers to JSON, so they can be queried with a selector.
+/// Each header maps to a list of all its values. `Set-Cookie` values are
+/// parsed into objects, so individual cookie attributes are accessible.
+fn headers_to_json(headers: &HeaderMap) -> serde_json::Value {
+    let map: serde_json::Map<_, _> = headers
+        .keys()
+        .map(|name| {
+            let values = headers
+                .get_all(name)
+                .iter()
+                .map(|value| {
+                    let value = String::from_utf8_lossy(value.as_bytes());
+                    if name == header::SET_COOKIE {
+                        parse_set_cookie(&value)
+                    } else {
+                        value.into()
+                    }
+                })
+                .collect();
+            (name.as_str().to_owned(), serde_json::Value::Array(values))
+        })
+        .collect();
+    map.into()
+}
+
+/// Parse a `Set-Cookie` header value into an object with the cookie's `name`
+/// and `value`, plus each attribute under its lowercased name. Attributes
+/// without a value (e.g. `HttpOnly`) are `true`.
+fn parse_set_cookie(value: &str) -> serde_json::Value {
+    let mut parts = value.split(';').map(str::trim);
+    let (name, value) = parts
+        .next()
+        .and_then(|cookie| cookie.split_once('='))
+        .unwrap_or_default();
+    let mut map = serde_json::Map::new();
+    map.insert("name".into(), name.trim().into());
+    map.insert("value".into(), value.trim().into());
+    for part in parts.filter(|part| !part.is_empty()) {
+        let (key, value) = match part.split_once('=') {
+            Some((key, value)) => (key, value.trim().into()),
+            None => (part, true.into()),
+        };
+        map.insert(key.trim().to_ascii_lowercase(), value);
+    }
+    map.into()
+}
+
 /// Load variable from environment. If the variable is missing or otherwise
 /// inaccessible, return an empty string. This models standard shell behavior,
 /// so it should be intuitive for users.
@@ -652,3 +1238,37 @@ impl ChainOutputTrim {
 fn load_environment_variable(variable: &str) -> String {
     env::var(variable).unwrap_or_default()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::pass(SecretManager::Pass, &["pass", "show", "api/token"])]
+    #[case::one_password(
+        SecretManager::OnePassword,
+        &["op", "read", "api/token"],
+    )]
+    fn test_secret_manager_command(
+        #[case] manager: SecretManager,
+        #[case] expected: &[&str],
+    ) {
+        assert_eq!(manager.command("api/token".into()), expected);
+    }
+
+    #[rstest]
+    #[case::plain(b"hunter2", b"hunter2")]
+    #[case::empty(b"", b"")]
+    #[case::trailing_newline(b"hunter2\n", b"hunter2")]
+    #[case::trailing_crlf(b"hunter2\r\n", b"hunter2")]
+    #[case::metadata(b"hunter2\nurl: example.com\n", b"hunter2")]
+    fn test_secret_manager_extract(
+        #[case] output: &[u8],
+        #[case] expected: &[u8],
+    ) {
+        for manager in [SecretManager::Pass, SecretManager::OnePassword] {
+            assert_eq!(manager.extract(output.to_owned()), expected);
+        }
+    }
+}
@@ -7,42 +7,67 @@ mod util;
 pub mod view;
 
 use crate::{
-    collection::{Collection, CollectionFile, ProfileId, Recipe, RecipeId},
+    collection::{
+        Collection, CollectionFile, ProfileId, Recipe, RecipeBody, RecipeId,
+    },
     config::Config,
     db::{CollectionDatabase, Database},
-    http::RequestSeed,
-    template::{Prompter, Template, TemplateChunk, TemplateContext},
+    http::{
+        BuildOptions, HostNotAllowedError, RequestBuildError, RequestRecord,
+        RequestSeed,
+    },
+    template::{
+        with_recipe_variables, with_recipe_variables_sync, Prompter, Template,
+        TemplateChunk, TemplateContext, WatchedFile,
+    },
     tui::{
         context::TuiContext,
         input::Action,
         message::{Message, MessageSender, RequestConfig},
-        util::{get_editor_command, save_file, signals},
-        view::{ModalPriority, PreviewPrompter, RequestState, View},
+        util::{
+            capture_value, confirm, new_recipe, save_file, save_request,
+            signals, ChainFileWatcher,
+        },
+        view::{
+            BuildProgress, ModalPriority, PreviewPrompter, RequestState, View,
+        },
     },
-    util::{Replaceable, ResultExt},
+    util::{get_editor_command, Replaceable, ResultExt},
 };
-use anyhow::{anyhow, Context};
+use anyhow::{anyhow, bail, Context};
+use bytes::Bytes;
 use chrono::Utc;
 use crossterm::{
     event::{DisableMouseCapture, EnableMouseCapture, EventStream},
     terminal::{EnterAlternateScreen, LeaveAlternateScreen},
 };
 use futures::{Future, StreamExt};
-use notify::{event::ModifyKind, RecursiveMode, Watcher};
+use indexmap::IndexMap;
+use notify::{event::ModifyKind, RecommendedWatcher, RecursiveMode, Watcher};
 use ratatui::{prelude::CrosstermBackend, Terminal};
 use std::{
+    env, fs,
     io::{self, Stdout},
     ops::Deref,
     path::PathBuf,
-    sync::{Arc, OnceLock},
+    process::Command,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, OnceLock,
+    },
     time::Duration,
 };
 use tokio::{
     select,
-    sync::mpsc::{self, UnboundedReceiver},
+    sync::{
+        mpsc::{self, UnboundedReceiver},
+        oneshot,
+    },
+    task::JoinHandle,
     time,
 };
-use tracing::{debug, error, info, trace};
+use tracing::{debug, error, info, trace, warn};
+use uuid::Uuid;
 
 /// Main controller struct for the TUI. The app uses a React-ish architecture
 /// for the view, with a wrapping controller (this struct)
@@ -63,6 +88,23 @@ pub struct Tui {
     /// before the new one is created.
     view: Replaceable<View>,
     collection_file: CollectionFile,
+    /// Reloads the collection when its file changes. It stops watching when
+    /// dropped, so this is replaced whenever the collection is switched
+    watcher: Option<RecommendedWatcher>,
+    /// Reloads the config when the global or project config file changes.
+    /// Replaced when the collection is switched, because the project file is
+    /// tied to the collection
+    config_watcher: Option<RecommendedWatcher>,
+    /// Refreshes previews when files read by chains change. Created the first
+    /// time a chain asks for it, and dropped when the collection is switched
+    chain_watcher: Option<ChainFileWatcher>,
+    /// Background task that enforces history limits. It's bound to a single
+    /// collection, so it gets restarted whenever the collection is switched
+    retention_task: Option<JoinHandle<()>>,
+    /// Values captured from responses, available to templates as
+    /// `{{captures.<name>}}`. These last for the whole session, even across
+    /// collection switches, and are never persisted
+    captures: IndexMap<String, String>,
     should_run: bool,
 }
 
@@ -71,6 +113,10 @@ type Term = Terminal<CrosstermBackend<Stdout>>;
 impl Tui {
     /// Rough **maximum** time for each iteration of the main loop
     const TICK_TIME: Duration = Duration::from_millis(250);
+    /// How often to delete history that falls outside the retention limits
+    const RETENTION_INTERVAL: Duration = Duration::from_secs(60 * 60);
+    /// How long a request can spend building before we show its progress
+    const BUILD_PROGRESS_DELAY: Duration = Duration::from_secs(1);
 
     /// Start the TUI. Any errors that occur during startup will be panics,
     /// because they prevent TUI execution.
@@ -81,25 +127,35 @@ impl Tui {
         // ===== Initialize global state =====
         // This stuff only needs to be set up *once per session*
 
-        let config = Config::load()?;
+        let config = Config::load(Some(&collection_path))?;
         // Create a message queue for handling async tasks
         let (messages_tx, messages_rx) = mpsc::unbounded_channel();
         let messages_tx = MessageSender::new(messages_tx);
-        // Load a database for this particular collection
-        let database = Database::load()?.into_collection(&collection_path)?;
+        // Load a database for this particular collection. Writes happen in
+        // the background so they don't stall the UI
+        let database = Database::load()?
+            .with_background_writes({
+                let messages_tx = messages_tx.clone();
+                move |error| messages_tx.send(Message::Error { error })
+            })
+            .into_collection(&collection_path)?
+            .with_retention(config.history.clone());
         // Initialize global view context
         TuiContext::init(config);
 
         // ===== Initialize collection & view =====
 
-        // If the collection fails to load, create an empty one just so we can
-        // move along. We'll watch the file and hopefully the user can fix it
-        let collection_file = CollectionFile::load(collection_path.clone())
-            .await
-            .reported(&messages_tx)
-            .unwrap_or_else(|| CollectionFile::with_path(collection_path));
-        let view =
-            View::new(&collection_file, database.clone(), messages_tx.clone());
+        // Large collections can take a while to parse, so start with an empty
+        // one and load the real thing in the background. If it fails to load,
+        // we'll stick with the empty one; we watch the file so hopefully the
+        // user can fix it
+        let collection_file = CollectionFile::with_path(collection_path);
+        let view = View::loading(
+            &collection_file,
+            database.clone(),
+            messages_tx.clone(),
+        );
+        messages_tx.send(Message::CollectionStartReload);
 
         // The code to revert the terminal takeover is in `Tui::drop`, so we
         // shouldn't take over the terminal until right before creating the
@@ -113,6 +169,11 @@ impl Tui {
             messages_tx,
 
             collection_file,
+            watcher: None,
+            config_watcher: None,
+            chain_watcher: None,
+            retention_task: None,
+            captures: IndexMap::new(),
             should_run: true,
 
             view: Replaceable::new(view),
@@ -127,10 +188,10 @@ impl Tui {
     async fn run(mut self) -> anyhow::Result<()> {
         // Spawn background tasks
         self.listen_for_signals();
-        // Hang onto this because it stops running when dropped
-        let _watcher = self.watch_collection()?;
+        self.enforce_retention();
+        self.watch_collection()?;
+        self.watch_config()?;
 
-        let input_engine = &TuiContext::get().input_engine;
         // Stream of terminal input events
         let mut input_stream = EventStream::new();
 
@@ -150,7 +211,9 @@ impl Tui {
                 event_result = input_stream.next() => {
                     if let Some(event) = event_result {
                         let event = event.expect("Error reading terminal input");
-                        input_engine.event_to_message(event)
+                        // Grab the input engine fresh each time, because it
+                        // changes when the config is reloaded
+                        TuiContext::get().input_engine.event_to_message(event)
                     } else {
                         // We ran out of input, just end the program
                         break;
@@ -176,6 +239,14 @@ impl Tui {
             self.view.handle_events();
         }
 
+        // The view persists its state on drop, and those writes are done in
+        // the background. Tear everything down, then wait for pending writes
+        // so they aren't lost on exit
+        let database = self.database.root().clone();
+        drop(self);
+        database.flush();
+        // The global context is never dropped, so stop forwards manually
+        TuiContext::get().http_engine.stop_port_forwards();
         Ok(())
     }
 
@@ -183,18 +254,66 @@ impl Tui {
     fn handle_message(&mut self, message: Message) -> anyhow::Result<()> {
         match message {
             Message::CollectionStartReload => {
+                self.view.set_loading(true);
                 let future = self.collection_file.reload();
                 let messages_tx = self.messages_tx();
+                // Errors are passed back too, so the loading indicator can be
+                // cleared
                 self.spawn(async move {
-                    let collection = future.await?;
-                    messages_tx.send(Message::CollectionEndReload(collection));
+                    messages_tx
+                        .send(Message::CollectionEndReload(future.await));
                     Ok(())
                 });
             }
-            Message::CollectionEndReload(collection) => {
+            Message::CollectionEndReload(Ok(collection)) => {
                 self.reload_collection(collection)
             }
+            Message::CollectionEndReload(Err(error)) => {
+                self.view.set_loading(false);
+                return Err(error);
+            }
             Message::CollectionEdit => self.edit_collection()?,
+            Message::CollectionSelect(path) => self.select_collection(path),
+            Message::CollectionEndSelect(collection_file) => {
+                self.switch_collection(collection_file)?
+            }
+            Message::CollectionNewRecipe => {
+                self.spawn(new_recipe(self.messages_tx()))
+            }
+            Message::CollectionSaveRequest(request) => {
+                self.spawn(save_request(self.messages_tx(), request))
+            }
+            Message::ConfigReload => self.reload_config()?,
+
+            Message::CaptureStart { response, query } => {
+                self.spawn(capture_value(self.messages_tx(), response, query))
+            }
+            Message::CaptureEnd { name, value } => {
+                let message =
+                    format!("Captured `{{{{captures.{name}}}}}` = {value}");
+                self.captures.insert(name, value);
+                // Re-render previews that use the capture
+                self.rebuild_view();
+                self.view.notify(message);
+            }
+
+            Message::CollectionInsertRecipe(recipe) => {
+                let add = self.collection_file.add_recipe(recipe);
+                let reload = self.collection_file.reload();
+                let messages_tx = self.messages_tx();
+                self.spawn(async move {
+                    let id = add.await?;
+                    // Reload now rather than waiting for the file watcher, so
+                    // the notification isn't clobbered by the reload
+                    let collection = reload.await?;
+                    messages_tx
+                        .send(Message::CollectionEndReload(Ok(collection)));
+                    messages_tx.send(Message::Notify(format!(
+                        "Added recipe `{id}` to the collection"
+                    )));
+                    Ok(())
+                });
+            }
 
             Message::CopyRequestUrl(request_config) => {
                 self.copy_request_url(request_config)?;
@@ -203,7 +322,15 @@ impl Tui {
                 self.copy_request_body(request_config)?;
             }
             Message::CopyRequestCurl(request_config) => {
-                self.copy_request_curl(request_config)?;
+                self.copy_request_as(request_config, RequestRecord::to_curl)?;
+            }
+            Message::CopyRequestCode {
+                request_config,
+                language,
+            } => {
+                self.copy_request_as(request_config, move |request| {
+                    request.to_code(language)
+                })?;
             }
             Message::CopyText(text) => self.view.copy_text(text),
             Message::SaveFile { default_path, data } => {
@@ -218,6 +345,14 @@ impl Tui {
             Message::HttpBeginRequest(request_config) => {
                 self.send_request(request_config)?
             }
+            Message::HttpBeginRequestEdit(request_config) => {
+                self.edit_request_body(request_config)?
+            }
+            Message::HttpEditBody {
+                request_config,
+                body,
+                extension,
+            } => self.send_edited_request(request_config, body, extension)?,
             Message::HttpBuildError { error } => {
                 self.view
                     .set_request_state(RequestState::BuildError { error });
@@ -227,7 +362,13 @@ impl Tui {
             }
             Message::HttpComplete(result) => {
                 let state = match result {
-                    Ok(exchange) => RequestState::response(exchange),
+                    Ok(exchange) => RequestState::response(
+                        exchange,
+                        self.collection_file
+                            .collection
+                            .protobuf_registry
+                            .as_ref(),
+                    ),
                     Err(error) => RequestState::RequestError { error },
                 };
                 self.view.set_request_state(state);
@@ -250,19 +391,56 @@ impl Tui {
             Message::ConfirmStart(confirm) => {
                 self.view.open_modal(confirm, ModalPriority::Low);
             }
+            Message::HttpBuildProgress(progress) => {
+                self.view.open_modal(progress, ModalPriority::Low);
+            }
 
             Message::TemplatePreview {
                 template,
                 profile_id,
+                recipe_id,
                 destination,
+                can_trigger,
             } => {
                 self.render_template_preview(
                     template,
                     profile_id,
+                    recipe_id,
                     destination,
+                    can_trigger,
                 )?;
             }
 
+            Message::TemplateValidate {
+                template,
+                profile_id,
+                recipe_id,
+                destination,
+            } => {
+                let collection = &self.collection_file.collection;
+                let chunks = with_recipe_variables_sync(
+                    recipe_variables(collection, recipe_id.as_ref()),
+                    || template.validate(collection, profile_id.as_ref()),
+                );
+                destination.set(chunks).map_err(|_| {
+                    anyhow!("Multiple writes to template validation lock")
+                })?;
+            }
+
+            Message::ViewFile {
+                data,
+                extension,
+                content_type,
+            } => self.view_file(data, extension, content_type)?,
+
+            Message::WatchFiles(files) => self.watch_chain_files(files)?,
+            Message::WatchedFileChanged => {
+                // Rebuilding the view is the easiest way to re-render every
+                // preview
+                self.rebuild_view();
+                self.view.notify("Watched file changed, refreshed previews");
+            }
+
             Message::Quit => self.quit(),
         }
         Ok(())
@@ -283,9 +461,37 @@ impl Tui {
         });
     }
 
+    /// Spawn a task to periodically delete history that falls outside the
+    /// configured retention limits. Limits are also enforced on every insert,
+    /// but this catches requests that have aged out since then, and reclaims
+    /// disk space. Any previous task is cancelled.
+    fn enforce_retention(&mut self) {
+        if let Some(task) = self.retention_task.take() {
+            task.abort();
+        }
+        let database = self.database.clone();
+        let messages_tx = self.messages_tx();
+        self.retention_task = Some(tokio::spawn(async move {
+            // First tick is immediate, so this also runs at startup
+            let mut interval = time::interval(Self::RETENTION_INTERVAL);
+            loop {
+                interval.tick().await;
+                if database
+                    .enforce_retention()
+                    .reported(&messages_tx)
+                    .is_none()
+                {
+                    break;
+                }
+            }
+        }));
+    }
+
     /// Spawn a watcher to automatically reload the collection when the file
-    /// changes. Return the watcher because it stops when dropped.
-    fn watch_collection(&self) -> anyhow::Result<impl Watcher> {
+    /// changes. This replaces the previous watcher, if any.
+    fn watch_collection(&mut self) -> anyhow::Result<()> {
+        // Stop watching the old file first
+        self.watcher = None;
         // Spawn a watcher for the collection file
         let messages_tx = self.messages_tx();
         let f = move |result: notify::Result<_>| {
@@ -314,15 +520,159 @@ impl Tui {
             path = ?self.collection_file.path(), ?watcher,
             "Watching collection file for changes"
         );
-        Ok(watcher)
+        self.watcher = Some(watcher);
+        Ok(())
+    }
+
+    /// Spawn a watcher to automatically reload the config when the global or
+    /// project config file changes. This replaces the previous watcher, if any.
+    fn watch_config(&mut self) -> anyhow::Result<()> {
+        self.config_watcher = None;
+        let global_path = Config::path().create_parent()?;
+        let mut paths = vec![global_path];
+        // The project config directory is optional. If it doesn't exist yet,
+        // it won't be watched until the collection is reloaded
+        let project_path = Config::project_path(self.collection_file.path());
+        if let Some(directory) = project_path
+            .parent()
+            .and_then(|directory| directory.canonicalize().ok())
+        {
+            paths.push(
+                directory.join(project_path.file_name().unwrap_or_default()),
+            );
+        }
+
+        let messages_tx = self.messages_tx();
+        let f = {
+            let paths = paths.clone();
+            move |result: notify::Result<notify::Event>| match result {
+                // Only reload if the file *content* changes. Editors that
+                // replace the file show up as a rename or create
+                Ok(
+                    event @ notify::Event {
+                        kind:
+                            notify::EventKind::Create(_)
+                            | notify::EventKind::Modify(
+                                ModifyKind::Data(_) | ModifyKind::Name(_),
+                            ),
+                        ..
+                    },
+                ) if event.paths.iter().any(|path| paths.contains(path)) => {
+                    info!(?event, "Config file changed, reloading");
+                    messages_tx.send(Message::ConfigReload);
+                }
+                // Do nothing for other event kinds
+                Ok(_) => {}
+                Err(err) => {
+                    error!(error = %err, "Error watching config file");
+                }
+            }
+        };
+        let mut watcher = notify::recommended_watcher(f)?;
+        // Watch the directories rather than the files, because the files may
+        // not exist yet
+        for path in &paths {
+            let directory = path.parent().unwrap_or(path);
+            watcher.watch(directory, RecursiveMode::NonRecursive)?;
+        }
+        info!(?paths, ?watcher, "Watching config files for changes");
+        self.config_watcher = Some(watcher);
+        Ok(())
+    }
+
+    /// Watch files read by chains, so previews are refreshed when they change.
+    /// Paths that are already being watched are skipped.
+    fn watch_chain_files(
+        &mut self,
+        files: Vec<WatchedFile>,
+    ) -> anyhow::Result<()> {
+        let chain_watcher = match self.chain_watcher.take() {
+            Some(chain_watcher) => chain_watcher,
+            None => ChainFileWatcher::new(self.messages_tx())?,
+        };
+        let chain_watcher = self.chain_watcher.insert(chain_watcher);
+        for file in files {
+            // Don't show an error to the user; if the file can't be watched,
+            // it probably can't be read either, so the preview shows the error
+            if let Err(error) = chain_watcher.watch(&file) {
+                warn!(?file, error = error.deref(), "Error watching file");
+            }
+        }
+        Ok(())
+    }
+
+    /// Spawn a task to load a different collection file. Once it's loaded,
+    /// we'll switch over to it.
+    fn select_collection(&self, path: PathBuf) {
+        // Paths from the DB are canonicalized, so compare against that
+        if self.database.collection_path().ok().as_ref() == Some(&path) {
+            return;
+        }
+        let messages_tx = self.messages_tx();
+        self.spawn(async move {
+            // If this fails, the current collection stays open
+            let collection_file = CollectionFile::load(path).await?;
+            messages_tx.send(Message::CollectionEndSelect(collection_file));
+            Ok(())
+        });
+    }
+
+    /// Replace the current collection with a different one. Everything tied
+    /// to the collection file (database handle, file watcher, view) is
+    /// rebuilt for the new one.
+    fn switch_collection(
+        &mut self,
+        collection_file: CollectionFile,
+    ) -> anyhow::Result<()> {
+        info!(path = ?collection_file.path(), "Switching collection");
+        // The new collection may have its own project config
+        TuiContext::reload(Config::load(Some(collection_file.path()))?);
+        self.database = self
+            .database
+            .root()
+            .clone()
+            .into_collection(collection_file.path())?
+            .with_retention(TuiContext::get().config.history.clone());
+        self.collection_file = collection_file;
+        self.enforce_retention();
+        self.watch_collection()?;
+        self.watch_config()?;
+        self.chain_watcher = None;
+
+        // Drop the old view *first* so its UI state is saved to the old
+        // collection before the new one is loaded
+        let database = self.database.clone();
+        let messages_tx = self.messages_tx();
+        let collection_file = &self.collection_file;
+        self.view.replace(move |old| {
+            drop(old);
+            View::new(collection_file, database, messages_tx)
+        });
+        Ok(())
     }
 
     /// Reload state with a new collection
     fn reload_collection(&mut self, collection: Collection) {
         self.collection_file.collection = collection;
+        // Rebuild the whole view, because tons of things can change
+        self.rebuild_view();
+    }
 
-        // Rebuild the whole view, because tons of things can change. Drop the
-        // old one *first* to make sure UI state is saved before being restored
+    /// Reload the global and project config files, and rebuild the view so the
+    /// new theme, key bindings, etc. take effect. If either file is invalid,
+    /// the current config is kept.
+    fn reload_config(&mut self) -> anyhow::Result<()> {
+        let config = Config::load(Some(self.collection_file.path()))?;
+        TuiContext::reload(config);
+        self.rebuild_view();
+        self.view
+            .notify(format!("Reloaded configuration from {}", Config::path()));
+        Ok(())
+    }
+
+    /// Replace the view with a new one for the current collection. Drop the
+    /// old one *first* to make sure UI state is saved before being restored
+    fn rebuild_view(&mut self) {
         let database = self.database.clone();
         let messages_tx = self.messages_tx();
         let collection_file = &self.collection_file;
@@ -344,12 +694,102 @@ impl Tui {
         Ok(())
     }
 
-    /// Open the collection file in the user's configured editor. **This will
-    /// block the main thread**, because we assume we're opening a terminal
-    /// editor and therefore should yield the terminal to the editor.
+    /// Open the collection file in the user's configured editor
     fn edit_collection(&mut self) -> anyhow::Result<()> {
-        let path = self.collection_file.path();
-        let mut command = get_editor_command(path)?;
+        let command = get_editor_command(self.collection_file.path())?;
+        self.run_external(command)
+    }
+
+    /// Write data to a temporary file, then open it in the viewer configured
+    /// for its content type, or the user's editor. The file is left behind,
+    /// because some viewers (e.g. browsers) load it after the command exits.
+    fn view_file(
+        &mut self,
+        data: Vec<u8>,
+        extension: Option<String>,
+        content_type: Option<String>,
+    ) -> anyhow::Result<()> {
+        let mut path =
+            env::temp_dir().join(format!("slumber-{}", Uuid::new_v4()));
+        if let Some(extension) = extension {
+            path.set_extension(extension);
+        }
+        fs::write(&path, data)
+            .with_context(|| format!("Error writing to {path:?}"))?;
+        let command = TuiContext::get()
+            .config
+            .viewer_command(&path, content_type.as_deref())?;
+        self.run_external(command)
+    }
+
+    /// Render the body for a request, then hand it back to the main thread to
+    /// be opened in the user's editor. Form bodies aren't supported, because
+    /// editing the encoded form would be miserable.
+    fn edit_request_body(
+        &self,
+        request_config: RequestConfig,
+    ) -> anyhow::Result<()> {
+        let recipe = self.get_recipe(&request_config.recipe_id)?;
+        let extension = match &recipe.body {
+            None => bail!("Request has no body"),
+            Some(RecipeBody::Raw(_)) => None,
+            Some(RecipeBody::Json(_)) => Some("json"),
+            Some(
+                RecipeBody::FormUrlencoded(_) | RecipeBody::FormMultipart(_),
+            ) => bail!("Form bodies cannot be edited before sending"),
+        };
+        let seed = RequestSeed::new(recipe, request_config.options.clone());
+        let template_context =
+            self.template_context(request_config.profile_id.clone(), true)?;
+        let messages_tx = self.messages_tx();
+        self.spawn(async move {
+            let body = TuiContext::get()
+                .http_engine
+                .build_body(seed, &template_context)
+                .await?
+                .ok_or(anyhow!("Request has no body"))?;
+            messages_tx.send(Message::HttpEditBody {
+                request_config,
+                body,
+                extension,
+            });
+            Ok(())
+        });
+        Ok(())
+    }
+
+    /// Open a rendered request body in the user's editor, then send the
+    /// request with the edited body. The temporary file is deleted afterward
+    fn send_edited_request(
+        &mut self,
+        mut request_config: RequestConfig,
+        body: Bytes,
+        extension: Option<&str>,
+    ) -> anyhow::Result<()> {
+        let mut path =
+            env::temp_dir().join(format!("slumber-{}", Uuid::new_v4()));
+        if let Some(extension) = extension {
+            path.set_extension(extension);
+        }
+        fs::write(&path, &body)
+            .with_context(|| format!("Error writing to {path:?}"))?;
+        let result = get_editor_command(&path)
+            .and_then(|command| self.run_external(command))
+            .and_then(|()| {
+                fs::read(&path)
+                    .with_context(|| format!("Error reading {path:?}"))
+            });
+        let _ = fs::remove_file(&path)
+            .with_context(|| format!("Error deleting {path:?}"))
+            .traced();
+        request_config.options.body = Some(result?.into());
+        self.send_request(request_config)
+    }
+
+    /// Run an external program (e.g. an editor) and wait for it to exit.
+    /// **This will block the main thread**, because we assume it's a terminal
+    /// program and therefore should yield the terminal to it.
+    fn run_external(&mut self, mut command: Command) -> anyhow::Result<()> {
         let error_context =
             format!("Error spawning editor with command `{command:?}`");
 
@@ -427,10 +867,14 @@ impl Tui {
         Ok(())
     }
 
-    /// Render a request, then copy the equivalent curl command to the clipboard
-    fn copy_request_curl(
+    /// Render a request, then convert it to some text (e.g. a curl command)
+    /// and copy that to the clipboard
+    fn copy_request_as(
         &self,
         request_config: RequestConfig,
+        convert: impl FnOnce(&RequestRecord) -> anyhow::Result<String>
+            + Send
+            + 'static,
     ) -> anyhow::Result<()> {
         let seed = RequestSeed::new(
             self.get_recipe(&request_config.recipe_id)?,
@@ -445,8 +889,8 @@ impl Tui {
                 .http_engine
                 .build(seed, &template_context)
                 .await?;
-            let command = ticket.record().to_curl()?;
-            messages_tx.send(Message::CopyText(command));
+            let text = convert(ticket.record())?;
+            messages_tx.send(Message::CopyText(text));
             Ok(())
         });
         Ok(())
@@ -469,28 +913,85 @@ impl Tui {
         let messages_tx = self.messages_tx();
 
         // Mark request state as building
-        let initialized =
-            RequestSeed::new(self.get_recipe(&recipe_id)?, options);
+        let recipe = self.get_recipe(&recipe_id)?;
+        let initialized = RequestSeed::new(recipe.clone(), options.clone());
+        let id = initialized.id;
         self.view.set_request_state(RequestState::Building {
-            id: initialized.id,
+            id,
             start_time: Utc::now(),
-            profile_id,
-            recipe_id,
+            profile_id: profile_id.clone(),
+            recipe_id: recipe_id.clone(),
         });
 
+        // If the build is slow, show the user what it's waiting on and let
+        // them cancel it
+        let done = Arc::new(AtomicBool::new(false));
+        let (cancel_tx, cancel_rx) = oneshot::channel();
+        {
+            let progress = template_context.progress.clone();
+            let done = Arc::clone(&done);
+            let messages_tx = messages_tx.clone();
+            tokio::spawn(async move {
+                time::sleep(Self::BUILD_PROGRESS_DELAY).await;
+                if !done.load(Ordering::Relaxed) {
+                    messages_tx.send(Message::HttpBuildProgress(
+                        BuildProgress {
+                            progress,
+                            done,
+                            cancel: cancel_tx,
+                        },
+                    ));
+                }
+            });
+        }
+
         // We can't use self.spawn here because HTTP errors are handled
         // differently from all other error types
         let database = self.database.clone();
         tokio::spawn(async move {
-            // Build the request
-            let ticket = TuiContext::get()
-                .http_engine
-                .build(initialized, &template_context)
-                .await
-                .map_err(|error| {
-                    // Report the error, but don't actually return anything
-                    messages_tx.send(Message::HttpBuildError { error });
-                })?;
+            // Build the request. If the progress modal was never shown, the
+            // cancel sender is dropped and that branch is disabled
+            let build = async {
+                let tui_context = TuiContext::get();
+                let http_engine = &tui_context.http_engine;
+                match http_engine.build(initialized, &template_context).await {
+                    // If the host isn't allowed for this profile, the user can
+                    // still send it, but they have to say so. The allowlist
+                    // is checked before anything else is rendered, so no
+                    // commands have run yet
+                    Err(error) if error.error.is::<HostNotAllowedError>() => {
+                        let message = format!("{}. Send anyway?", error.error);
+                        if !confirm(&messages_tx, message).await {
+                            return Err(error);
+                        }
+                        let seed = RequestSeed {
+                            id,
+                            recipe,
+                            options: BuildOptions {
+                                allow_any_host: true,
+                                ..options
+                            },
+                        };
+                        http_engine.build(seed, &template_context).await
+                    }
+                    result => result,
+                }
+            };
+            let result = select! {
+                result = build => result,
+                Ok(()) = cancel_rx => Err(RequestBuildError {
+                    error: anyhow!("Cancelled"),
+                    profile_id,
+                    recipe_id,
+                    id,
+                    time: Utc::now(),
+                }),
+            };
+            done.store(true, Ordering::Relaxed);
+            let ticket = result.map_err(|error| {
+                // Report the error, but don't actually return anything
+                messages_tx.send(Message::HttpBuildError { error });
+            })?;
 
             // Report liftoff
             messages_tx.send(Message::HttpLoading {
@@ -498,7 +999,12 @@ impl Tui {
             });
 
             // Send the request and report the result to the main thread
-            let result = ticket.send(&database).await;
+            let result = ticket.send(&database).await.map(|sent| {
+                if let Some(error) = sent.save_error {
+                    messages_tx.send(Message::Error { error });
+                }
+                sent.exchange
+            });
             messages_tx.send(Message::HttpComplete(result));
 
             // By returning an empty result, we can use `?` to break out early.
@@ -525,17 +1031,34 @@ impl Tui {
 
     /// Spawn a task to render a template, storing the result in a pre-defined
     /// lock. As this is a preview, the user will *not* be prompted for any
-    /// input. A placeholder value will be used for any prompts.
+    /// input. A placeholder value will be used for any prompts. If triggers
+    /// are disabled, chained requests will only be loaded from history.
     fn render_template_preview(
         &self,
         template: Template,
         profile_id: Option<ProfileId>,
+        recipe_id: Option<RecipeId>,
         destination: Arc<OnceLock<Vec<TemplateChunk>>>,
+        can_trigger: bool,
     ) -> anyhow::Result<()> {
-        let context = self.template_context(profile_id, false)?;
+        let mut context = self.template_context(profile_id, false)?;
+        if !can_trigger {
+            context.http_engine = None;
+        }
+        let variables =
+            recipe_variables(&context.collection, recipe_id.as_ref());
+        let messages_tx = self.messages_tx();
         self.spawn(async move {
             // Render chunks, then write them to the output destination
-            let chunks = template.render_chunks(&context).await;
+            let chunks = with_recipe_variables(
+                variables,
+                template.render_chunks(&context),
+            )
+            .await;
+            let watched_files = context.watched_files.files();
+            if !watched_files.is_empty() {
+                messages_tx.send(Message::WatchFiles(watched_files));
+            }
             // If this fails, it's a logic error somewhere. Only one task should
             // exist per lock
             destination.set(chunks).map_err(|_| {
@@ -572,13 +1095,17 @@ impl Tui {
         let collection = &self.collection_file.collection;
 
         Ok(TemplateContext {
-            selected_profile: profile_id,
-            collection: collection.clone(),
-            http_engine: Some(context.http_engine.clone()),
-            database: self.database.clone(),
-            overrides: Default::default(),
-            prompter,
-            recursion_count: Default::default(),
+            captures: self.captures.clone(),
+            // Reading stdin would compete with the terminal for key input
+            stdin_available: false,
+            ..TemplateContext::new(
+                collection.clone(),
+                profile_id,
+                Some(context.http_engine.clone()),
+                self.database.clone(),
+                prompter,
+                &context.config,
+            )
         })
     }
 }
@@ -621,3 +1148,15 @@ fn restore_terminal() -> anyhow::Result<()> {
     )?;
     Ok(())
 }
+
+/// Get a copy of a recipe's variables, for rendering templates in the scope of
+/// that recipe. Empty if there's no recipe or it's unknown
+fn recipe_variables(
+    collection: &Collection,
+    recipe_id: Option<&RecipeId>,
+) -> IndexMap<String, Template> {
+    recipe_id
+        .and_then(|recipe_id| collection.recipes.get_recipe(recipe_id))
+        .map(|recipe| recipe.variables.clone())
+        .unwrap_or_default()
+}
@@ -3,16 +3,22 @@ use crate::{
     http::HttpEngine,
     tui::{input::InputEngine, view::Styles},
 };
-use std::sync::OnceLock;
+use std::{
+    ops::DerefMut,
+    sync::{Arc, RwLock},
+};
 
 /// The singleton value for the context. Initialized once during startup, then
-/// freely available *read only* everywhere.
-static INSTANCE: OnceLock<TuiContext> = OnceLock::new();
+/// freely available *read only* everywhere. It's replaced when the config file
+/// is reloaded. Callers hold their own `Arc`, so a replaced context is dropped
+/// once the last user of it is done.
+static INSTANCE: RwLock<Option<Arc<TuiContext>>> = RwLock::new(None);
 
 /// Globally available context for the TUI. This is initialized once during
 /// **TUI** creation (not view creation), meaning there is only one per session.
-/// Data that can change through the lifespan of the process, e.g. by user input
-/// or collection reload, should *not* go in here.
+/// The only exception is the config file being reloaded, which replaces the
+/// whole context. Data that can change through the lifespan of the process,
+/// e.g. by user input or collection reload, should *not* go in here.
 ///
 /// The purpose of this is to make it easy for components in the view to access
 /// **read-only** global data without needing to drill it all down the tree.
@@ -32,21 +38,43 @@ pub struct TuiContext {
 impl TuiContext {
     /// Initialize global context. Should be called only once, during startup.
     pub fn init(config: Config) {
-        INSTANCE
-            .set(Self::new(config))
-            .expect("Global context is already initialized");
+        let mut instance = Self::instance_mut();
+        assert!(instance.is_none(), "Global context is already initialized");
+        *instance = Some(Arc::new(Self::new(config)));
     }
 
     /// Initialize the global context for tests. This will use a default config,
     /// and if the context is already initialized, do nothing.
     #[cfg(test)]
     pub fn init_test() {
-        INSTANCE.get_or_init(|| Self::new(Config::default()));
+        Self::instance_mut()
+            .get_or_insert_with(|| Arc::new(Self::new(Config::default())));
+    }
+
+    /// Replace the global context with one built from a new config. The view
+    /// should be rebuilt afterward, because components may hang onto values
+    /// derived from the old context (e.g. key bindings in help text).
+    pub fn reload(config: Config) {
+        let mut instance = Self::instance_mut();
+        let old = instance
+            .replace(Arc::new(Self::new(config)))
+            .expect("Global context is not initialized");
+        // The old context may stay alive for a while if a task is still using
+        // it, so don't wait on it to clean up. The new config may have
+        // different forwards anyway
+        old.http_engine.stop_port_forwards();
+    }
+
+    fn instance_mut() -> impl DerefMut<Target = Option<Arc<Self>>> {
+        INSTANCE.write().expect("Global context lock poisoned")
     }
 
     fn new(config: Config) -> Self {
         let styles = Styles::new(&config.theme);
-        let input_engine = InputEngine::new(config.input_bindings.clone());
+        let input_engine = InputEngine::new(
+            config.input_bindings.clone(),
+            config.vim_bindings,
+        );
         let http_engine = HttpEngine::new(&config);
         Self {
             config,
@@ -56,10 +84,14 @@ impl TuiContext {
         }
     }
 
-    /// Get a reference to the global context
-    pub fn get() -> &'static Self {
-        // Right now the theme isn't configurable so this is fine. To make it
-        // configurable we'll need to populate the static value during startup
-        INSTANCE.get().expect("Global context is not initialized")
+    /// Get a handle to the global context. Don't hold onto this longer than
+    /// needed, or you'll miss out on config reloads.
+    pub fn get() -> Arc<Self> {
+        let instance = INSTANCE.read().expect("Global context lock poisoned");
+        Arc::clone(
+            instance
+                .as_ref()
+                .expect("Global context is not initialized"),
+        )
     }
 }
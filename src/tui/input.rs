@@ -15,6 +15,7 @@ use std::{
     fmt::{self, Debug},
     iter,
     str::FromStr,
+    sync::atomic::{AtomicBool, Ordering},
 };
 use tracing::trace;
 
@@ -29,6 +30,13 @@ pub struct InputEngine {
     /// but keying by action at least allows us to look up action=>binding for
     /// help text.
     bindings: IndexMap<Action, InputBinding>,
+    /// Are vim-style bindings enabled? Most of the vim bindings are just
+    /// extra entries in the binding map, but `gg` is a two-key sequence that
+    /// needs special handling
+    vim: bool,
+    /// Was the previous key press a `g`? Used to detect `gg` in vim mode.
+    /// Atomic because the engine lives in the global context
+    pending_g: AtomicBool,
 }
 
 impl InputEngine {
@@ -102,13 +110,64 @@ impl InputEngine {
         // ^^^^^ If making changes, make sure to update the docs ^^^^^
     ]);
 
-    pub fn new(user_bindings: IndexMap<Action, InputBinding>) -> Self {
+    pub fn new(
+        user_bindings: IndexMap<Action, InputBinding>,
+        vim_bindings: bool,
+    ) -> Self {
         let mut new = Self::default();
+        if vim_bindings {
+            new.vim = true;
+            new.bindings.extend(Self::vim_bindings());
+        }
         // User bindings should overwrite any default ones
         new.bindings.extend(user_bindings);
         new
     }
 
+    /// Bindings that replace the defaults when vim mode is enabled. Arrow
+    /// keys are retained alongside the vim keys. Actions whose default key
+    /// is taken by a vim motion are moved to the uppercase version.
+    fn vim_bindings() -> IndexMap<Action, InputBinding> {
+        let ctrl = |c| KeyCombination {
+            code: KeyCode::Char(c),
+            modifiers: KeyModifiers::CONTROL,
+        };
+        indexmap! {
+            // vvvvv If making changes, make sure to update the docs vvvvv
+            Action::History => KeyCode::Char('H').into(),
+            Action::Up => InputBinding(vec![
+                KeyCode::Up.into(),
+                KeyCode::Char('k').into(),
+            ]),
+            Action::Down => InputBinding(vec![
+                KeyCode::Down.into(),
+                KeyCode::Char('j').into(),
+            ]),
+            Action::Left => InputBinding(vec![
+                KeyCode::Left.into(),
+                KeyCode::Char('h').into(),
+            ]),
+            Action::Right => InputBinding(vec![
+                KeyCode::Right.into(),
+                KeyCode::Char('l').into(),
+            ]),
+            Action::PageUp => InputBinding(vec![
+                KeyCode::PageUp.into(),
+                ctrl('u'),
+            ]),
+            Action::PageDown => InputBinding(vec![
+                KeyCode::PageDown.into(),
+                ctrl('d'),
+            ]),
+            Action::End => InputBinding(vec![
+                KeyCode::End.into(),
+                KeyCode::Char('G').into(),
+            ]),
+            Action::SelectRecipeList => KeyCode::Char('L').into(),
+            // ^^^^^ If making changes, make sure to update the docs ^^^^^
+        }
+    }
+
     /// Get a map of all available bindings
     pub fn bindings(&self) -> &IndexMap<Action, InputBinding> {
         &self.bindings
@@ -151,11 +210,13 @@ impl InputEngine {
             },
 
             Event::Key(key) => {
-                // Scan all bindings for a match
-                self.bindings
-                    .iter()
-                    .find(|(_, binding)| binding.matches(key))
-                    .map(|(action, _)| *action)
+                self.vim_sequence(key).or_else(|| {
+                    // Scan all bindings for a match
+                    self.bindings
+                        .iter()
+                        .find(|(_, binding)| binding.matches(key))
+                        .map(|(action, _)| *action)
+                })
             }
             _ => None,
         };
@@ -167,6 +228,21 @@ impl InputEngine {
         action
     }
 
+    /// Check for a multi-key vim sequence. Currently the only one is `gg`,
+    /// which maps to [Action::Home]. Any other key resets the sequence.
+    fn vim_sequence(&self, key: &KeyEvent) -> Option<Action> {
+        if !self.vim {
+            return None;
+        }
+        let is_g = key.code == KeyCode::Char('g') && key.modifiers.is_empty();
+        if is_g && self.pending_g.swap(false, Ordering::Relaxed) {
+            Some(Action::Home)
+        } else {
+            self.pending_g.store(is_g, Ordering::Relaxed);
+            None
+        }
+    }
+
     /// Given an input event, generate a corresponding message with mapped
     /// action. Some events will *not* generate a message, because they
     /// shouldn't get handled by components. This could be because they're just
@@ -184,9 +260,14 @@ impl InputEngine {
                     kind: KeyEventKind::Release,
                     ..
                 })
+                // Left button down/drag are needed for drag scrolling
                 | Event::Mouse(MouseEvent {
-                    kind: MouseEventKind::Down(_)
-                    | MouseEventKind::Drag(_)
+                    kind: MouseEventKind::Down(
+                        MouseButton::Right | MouseButton::Middle
+                    )
+                    | MouseEventKind::Drag(
+                        MouseButton::Right | MouseButton::Middle
+                    )
                     | MouseEventKind::Moved,
                     ..
                 })
@@ -219,9 +300,29 @@ impl Default for InputEngine {
                 }.into(),
                 Action::OpenActions => KeyCode::Char('x').into(),
                 Action::OpenHelp => KeyCode::Char('?').into(),
+                // Has to come before select_profile_list, because `p` would
+                // also match ctrl-p
+                Action::CommandPalette => InputBinding(vec![
+                    KeyCode::Char(':').into(),
+                    KeyCombination {
+                        code: KeyCode::Char('p'),
+                        modifiers: KeyModifiers::CONTROL,
+                    },
+                ]),
+                Action::CycleProfile => KeyCode::Char('P').into(),
                 Action::Fullscreen => KeyCode::Char('f').into(),
+                Action::CycleLayout => KeyCode::Char('v').into(),
+                Action::GrowPane => InputBinding(vec![
+                    KeyCode::Char('+').into(),
+                    KeyCode::Char('=').into(),
+                ]),
+                Action::ShrinkPane => KeyCode::Char('-').into(),
                 Action::ReloadCollection => KeyCode::F(5).into(),
                 Action::History => KeyCode::Char('h').into(),
+                Action::LastError => KeyCode::Char('e').into(),
+                Action::SwitchCollection => KeyCode::Char('C').into(),
+                Action::JumpToRequest => KeyCode::Char('o').into(),
+                Action::InspectChains => KeyCode::Char('i').into(),
                 Action::Search => KeyCode::Char('/').into(),
                 Action::PreviousPane => KeyCode::BackTab.into(),
                 Action::NextPane => KeyCode::Tab.into(),
@@ -241,6 +342,8 @@ impl Default for InputEngine {
                 Action::SelectResponse => KeyCode::Char('r').into(),
                 // ^^^^^ If making changes, make sure to update the docs ^^^^^
             },
+            vim: false,
+            pending_g: AtomicBool::new(false),
         }
     }
 }
@@ -303,6 +406,18 @@ pub enum Action {
     Cancel,
     /// Browse request history
     History,
+    /// Show the error from the most recent failed request
+    #[display("Last Error")]
+    LastError,
+    /// Open the collection switcher
+    #[display("Switch Collection")]
+    SwitchCollection,
+    /// Jump to the exchange from the most recent completion notification
+    #[display("Jump to Request")]
+    JumpToRequest,
+    /// Show the current value of every chain
+    #[display("Inspect Chains")]
+    InspectChains,
     /// Start a search/filter operation
     #[display("Search/Filter")]
     Search,
@@ -311,12 +426,27 @@ pub enum Action {
     ReloadCollection,
     /// Embiggen a pane
     Fullscreen,
+    /// Switch to the next pane layout preset
+    #[display("Change Layout")]
+    CycleLayout,
+    /// Make the selected pane bigger
+    #[display("Grow Pane")]
+    GrowPane,
+    /// Make the selected pane smaller
+    #[display("Shrink Pane")]
+    ShrinkPane,
     /// Open the actions modal
     #[display("Actions")]
     OpenActions,
     #[display("Help")]
     /// Open the help modal
     OpenHelp,
+    /// Open a fuzzy finder over actions, recipes, and profiles
+    #[display("Command Palette")]
+    CommandPalette,
+    /// Switch to the next profile, without opening the profile list
+    #[display("Next Profile")]
+    CycleProfile,
     /// Select profile list pane
     SelectProfileList,
     /// Select recipe list pane
@@ -364,6 +494,13 @@ impl InputBinding {
     fn matches(&self, event: &KeyEvent) -> bool {
         self.0.iter().any(|combo| combo.matches(event))
     }
+
+    /// Build a key event that would trigger this binding. Used to trigger an
+    /// action programmatically, e.g. from the command palette
+    pub fn key_event(&self) -> Option<KeyEvent> {
+        let combo = self.0.first()?;
+        Some(KeyEvent::new(combo.code, combo.modifiers))
+    }
 }
 
 impl Display for InputBinding {
@@ -612,12 +749,20 @@ mod tests {
         mouse_event(MouseEventKind::ScrollRight),
         Some(Action::ScrollRight)
     )]
+    #[case::mouse_down_left(
+        mouse_event(MouseEventKind::Down(MouseButton::Left)),
+        None
+    )]
+    #[case::mouse_drag_left(
+        mouse_event(MouseEventKind::Drag(MouseButton::Left)),
+        None
+    )]
     #[case::paste(Event::Paste("hello!".into()), None)]
     fn test_to_message_handled(
         #[case] event: Event,
         #[case] expected_action: Option<Action>,
     ) {
-        let engine = InputEngine::new(IndexMap::default());
+        let engine = InputEngine::new(IndexMap::default(), false);
         let (queued_event, queued_action) = assert_matches!(
             engine.event_to_message(event.clone()),
             Some(Message::Input { event, action }) => (event, action),
@@ -632,14 +777,67 @@ mod tests {
     #[case::focus_lost(Event::FocusLost)]
     #[case::resize(Event::Resize(10, 10))]
     #[case::key_release(key_event(KeyEventKind::Release, KeyCode::Enter))]
-    #[case::mouse_down(mouse_event(MouseEventKind::Down(MouseButton::Left)))]
-    #[case::mouse_drag(mouse_event(MouseEventKind::Drag(MouseButton::Left)))]
+    #[case::mouse_down(mouse_event(MouseEventKind::Down(MouseButton::Right)))]
+    #[case::mouse_drag(mouse_event(MouseEventKind::Drag(MouseButton::Right)))]
     #[case::mouse_move(mouse_event(MouseEventKind::Moved))]
     fn test_handle_event_killed(#[case] event: Event) {
-        let engine = InputEngine::new(IndexMap::default());
+        let engine = InputEngine::new(IndexMap::default(), false);
         assert_matches!(engine.event_to_message(event), None);
     }
 
+    /// Vim keys are added alongside the defaults, and displaced defaults move
+    /// to uppercase
+    #[rstest]
+    #[case::up(KeyCode::Char('k'), KeyModifiers::NONE, Some(Action::Up))]
+    #[case::up_arrow(KeyCode::Up, KeyModifiers::NONE, Some(Action::Up))]
+    #[case::left(KeyCode::Char('h'), KeyModifiers::NONE, Some(Action::Left))]
+    #[case::right(KeyCode::Char('l'), KeyModifiers::NONE, Some(Action::Right))]
+    #[case::page_down(
+        KeyCode::Char('d'),
+        KeyModifiers::CONTROL,
+        Some(Action::PageDown)
+    )]
+    #[case::end(KeyCode::Char('G'), KeyModifiers::SHIFT, Some(Action::End))]
+    #[case::history(
+        KeyCode::Char('H'),
+        KeyModifiers::SHIFT,
+        Some(Action::History)
+    )]
+    #[case::command_palette(
+        KeyCode::Char('p'),
+        KeyModifiers::CONTROL,
+        Some(Action::CommandPalette)
+    )]
+    #[case::unmapped(KeyCode::Char('d'), KeyModifiers::NONE, None)]
+    fn test_vim_bindings(
+        #[case] code: KeyCode,
+        #[case] modifiers: KeyModifiers,
+        #[case] expected: Option<Action>,
+    ) {
+        let engine = InputEngine::new(IndexMap::default(), true);
+        let event = Event::Key(KeyEvent::new(code, modifiers));
+        assert_eq!(engine.action(&event), expected);
+    }
+
+    /// `gg` is a sequence, so the first `g` does nothing
+    #[test]
+    fn test_vim_gg() {
+        let engine = InputEngine::new(IndexMap::default(), true);
+        let g = key_event(KeyEventKind::Press, KeyCode::Char('g'));
+        let j = key_event(KeyEventKind::Press, KeyCode::Char('j'));
+        assert_eq!(engine.action(&g), None);
+        assert_eq!(engine.action(&g), Some(Action::Home));
+        // Sequence is interrupted by another key
+        assert_eq!(engine.action(&g), None);
+        assert_eq!(engine.action(&j), Some(Action::Down));
+        assert_eq!(engine.action(&g), None);
+
+        // Disabled outside vim mode
+        let engine = InputEngine::new(IndexMap::default(), false);
+        assert_eq!(engine.action(&g), None);
+        assert_eq!(engine.action(&g), None);
+    }
+
     #[rstest]
     #[case::whitespace_stripped(" w ", KeyCode::Char('w'))]
     #[case::f_key("f2", KeyCode::F(2))]
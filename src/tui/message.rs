@@ -2,17 +2,28 @@
 //! state updates.
 
 use crate::{
-    collection::{Collection, ProfileId, RecipeId},
+    collection::{Collection, CollectionFile, NewRecipe, ProfileId, RecipeId},
     http::{
-        BuildOptions, Exchange, RequestBuildError, RequestError, RequestRecord,
+        BuildOptions, CodeLanguage, Exchange, RequestBuildError, RequestError,
+        RequestRecord, ResponseRecord,
+    },
+    template::{
+        Prompt, Prompter, Template, TemplateChunk, TemplateSourceChunk,
+        WatchedFile,
+    },
+    tui::{
+        input::Action,
+        view::{BuildProgress, Confirm},
     },
-    template::{Prompt, Prompter, Template, TemplateChunk},
-    tui::{input::Action, view::Confirm},
     util::ResultExt,
 };
 use anyhow::Context;
+use bytes::Bytes;
 use derive_more::From;
-use std::sync::{Arc, OnceLock};
+use std::{
+    path::PathBuf,
+    sync::{Arc, OnceLock},
+};
 use tokio::sync::mpsc::UnboundedSender;
 use tracing::trace;
 
@@ -54,10 +65,37 @@ impl Prompter for MessageSender {
 pub enum Message {
     /// Trigger collection reload
     CollectionStartReload,
-    /// Store a reloaded collection value in state
-    CollectionEndReload(Collection),
+    /// Store a reloaded collection value in state. If loading failed, the
+    /// current collection is kept and the error is shown
+    CollectionEndReload(anyhow::Result<Collection>),
     /// Open the collection in the user's editor
     CollectionEdit,
+    /// Switch to a different collection file. The file is loaded in the
+    /// background, and the current collection stays open if it fails
+    CollectionSelect(PathBuf),
+    /// Replace the current collection with a newly loaded one
+    CollectionEndSelect(CollectionFile),
+    /// Ask the user for the details of a new recipe, then add it to the
+    /// collection file
+    CollectionNewRecipe,
+    /// Ask the user for a name, then save a request from history to the
+    /// collection file as a new recipe
+    CollectionSaveRequest(Arc<RequestRecord>),
+    /// Append a recipe to the collection file, then reload
+    CollectionInsertRecipe(NewRecipe),
+
+    /// Ask the user for a JSONPath query and a name, then capture the query's
+    /// result from the response for use in templates as `{{captures.<name>}}`
+    CaptureStart {
+        response: Arc<ResponseRecord>,
+        /// Used to pre-populate the query prompt
+        query: Option<String>,
+    },
+    /// Store a captured value for the rest of the session
+    CaptureEnd { name: String, value: String },
+
+    /// Reload the global config file and apply it to the whole TUI
+    ConfigReload,
 
     /// Show a yes/no confirmation to the user. Use the included channel to
     /// return the value.
@@ -69,6 +107,12 @@ pub enum Message {
     CopyRequestBody(RequestConfig),
     /// Render request, then generate an equivalent cURL command and copy it
     CopyRequestCurl(RequestConfig),
+    /// Render request, then generate code in the given language that sends
+    /// it, and copy the code
+    CopyRequestCode {
+        request_config: RequestConfig,
+        language: CodeLanguage,
+    },
     /// Copy some text to the clipboard
     CopyText(String),
 
@@ -77,8 +121,21 @@ pub enum Message {
 
     /// Launch an HTTP request from the given recipe/profile.
     HttpBeginRequest(RequestConfig),
+    /// Render the request body, let the user edit it, then launch the request
+    /// with the edited body. The collection is not modified
+    HttpBeginRequestEdit(RequestConfig),
     /// Request failed to build
     HttpBuildError { error: RequestBuildError },
+    /// Request is taking a while to build. Show the user what it's waiting on
+    HttpBuildProgress(BuildProgress),
+    /// Request body has been rendered. Open it in the user's editor, then
+    /// launch the request with whatever they saved
+    HttpEditBody {
+        request_config: RequestConfig,
+        body: Bytes,
+        /// Extension for the temporary file, so the editor can detect its type
+        extension: Option<&'static str>,
+    },
     /// We launched the HTTP request
     HttpLoading { request: Arc<RequestRecord> },
     /// The HTTP request either succeeded or failed. We don't need to store the
@@ -123,8 +180,39 @@ pub enum Message {
     TemplatePreview {
         template: Template,
         profile_id: Option<ProfileId>,
+        /// Recipe whose `variables` are available to the template, if any
+        recipe_id: Option<RecipeId>,
         destination: Arc<OnceLock<Vec<TemplateChunk>>>,
+        /// Can chained requests be triggered during the render? If not, any
+        /// chain that needs to send a request will show an error instead
+        can_trigger: bool,
     },
+
+    /// Check a template's keys against the collection, to highlight mistakes
+    /// in the UI when previews are disabled. Like [Message::TemplatePreview],
+    /// the result is written back to the given cell.
+    TemplateValidate {
+        template: Template,
+        profile_id: Option<ProfileId>,
+        recipe_id: Option<RecipeId>,
+        destination: Arc<OnceLock<Vec<TemplateSourceChunk>>>,
+    },
+
+    /// Write data to a temporary file and open it in the viewer configured
+    /// for its content type, or the user's editor
+    ViewFile {
+        data: Vec<u8>,
+        /// Extension for the temporary file, so the viewer can detect its type
+        extension: Option<String>,
+        /// Used to select a viewer from the config
+        content_type: Option<String>,
+    },
+
+    /// Watch files that were read by file chains with `watch` enabled, so
+    /// previews can be refreshed when they change
+    WatchFiles(Vec<WatchedFile>),
+    /// A watched file changed, so all previews need to be re-rendered
+    WatchedFileChanged,
 }
 
 /// Configuration that defines how to render a request
@@ -2,18 +2,28 @@
 //! functionality is spun out into this module.
 
 use crate::{
-    template::Prompt,
+    collection::{Method, NewRecipe},
+    http::{Query, RequestRecord, ResponseRecord},
+    template::{Prompt, Template, WatchedFile},
     tui::{
         message::{Message, MessageSender},
         view::Confirm,
     },
-    util::ResultExt,
+    util::{is_glob, ResultExt},
 };
-use anyhow::{anyhow, Context};
+use anyhow::{anyhow, bail, Context};
 use futures::{future, FutureExt};
-use std::{env, io, path::Path, process::Command};
+use glob::Pattern;
+use notify::{
+    event::ModifyKind, EventKind, RecommendedWatcher, RecursiveMode, Watcher,
+};
+use std::{
+    env, io,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
 use tokio::{fs::OpenOptions, io::AsyncWriteExt, sync::oneshot};
-use tracing::{debug, info, warn};
+use tracing::{debug, error, info, warn};
 
 /// Listen for any exit signals, and return `Ok(())` when any signal is
 /// received. This can only fail during initialization.
@@ -135,20 +145,90 @@ pub async fn save_file(
     Ok(())
 }
 
-/// Get a command to open the given file in the user's configured editor. Return
-/// an error if the user has no editor configured
-pub fn get_editor_command(file: &Path) -> anyhow::Result<Command> {
-    let command = env::var("VISUAL").or(env::var("EDITOR")).map_err(|_| {
-        anyhow!(
-            "No editor configured. Please set the `VISUAL` or `EDITOR` \
-            environment variable"
-        )
-    })?;
-    let mut splits = command.split(' ');
-    let editor = splits.next().expect("`split` returns at least one value");
-    let mut command = Command::new(editor);
-    command.args(splits).arg(file);
-    Ok(command)
+/// Ask the user for the method, URL, and name of a new recipe, then send it
+/// off to be added to the collection file. If any prompt is closed, nothing
+/// is added.
+pub async fn new_recipe(messages_tx: MessageSender) -> anyhow::Result<()> {
+    let Some(method) = prompt(&messages_tx, "Method", Some("GET".into())).await
+    else {
+        return Ok(());
+    };
+    let method: Method = method
+        .parse()
+        .map_err(|_| anyhow!("Invalid HTTP method `{method}`"))?;
+    let Some(url) = prompt(&messages_tx, "URL", None).await else {
+        return Ok(());
+    };
+    let url: Template = url.parse().context("Invalid URL template")?;
+    let Some(name) = prompt(&messages_tx, "Name", None).await else {
+        return Ok(());
+    };
+
+    messages_tx.send(Message::CollectionInsertRecipe(NewRecipe {
+        name: Some(name).filter(|name| !name.is_empty()),
+        method,
+        url,
+        headers: Default::default(),
+        body: None,
+    }));
+    Ok(())
+}
+
+/// Ask the user for a name, then send a copy of a request from history off to
+/// be added to the collection file as a new recipe
+pub async fn save_request(
+    messages_tx: MessageSender,
+    request: Arc<RequestRecord>,
+) -> anyhow::Result<()> {
+    let Some(name) =
+        prompt(&messages_tx, "Name for the new recipe", None).await
+    else {
+        return Ok(());
+    };
+    let recipe = NewRecipe::from_record(
+        &request,
+        Some(name).filter(|name| !name.is_empty()),
+    )?;
+    messages_tx.send(Message::CollectionInsertRecipe(recipe));
+    Ok(())
+}
+
+/// Ask the user for a JSONPath query to run against a response, then a name to
+/// store the result under. The value can then be used in templates as
+/// `{{captures.<name>}}` for the rest of the session.
+pub async fn capture_value(
+    messages_tx: MessageSender,
+    response: Arc<ResponseRecord>,
+    query: Option<String>,
+) -> anyhow::Result<()> {
+    let Some(content) = response.body.parsed() else {
+        bail!("Response body can't be queried; it isn't in a known format");
+    };
+    let Some(query) = prompt(&messages_tx, "JSONPath to capture", query).await
+    else {
+        return Ok(());
+    };
+    let query: Query = query
+        .parse()
+        .with_context(|| format!("Invalid JSONPath `{query}`"))?;
+    let value = query.query_to_string(content)?;
+
+    let Some(name) = prompt(&messages_tx, "Capture as", None).await else {
+        return Ok(());
+    };
+    // Names have to be valid in a template key
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_alphanumeric() || "-_".contains(c))
+    {
+        bail!(
+            "Invalid capture name `{name}`; use only letters, numbers, `-`, \
+            and `_`"
+        );
+    }
+    messages_tx.send(Message::CaptureEnd { name, value });
+    Ok(())
 }
 
 /// Ask the user for some text input and wait for a response. Return `None` if
@@ -170,7 +250,10 @@ async fn prompt(
 }
 
 /// Ask the user a yes/no question and wait for a response
-async fn confirm(messages_tx: &MessageSender, message: impl ToString) -> bool {
+pub async fn confirm(
+    messages_tx: &MessageSender,
+    message: impl ToString,
+) -> bool {
     let (tx, rx) = oneshot::channel();
     let confirm = Confirm {
         message: message.to_string(),
@@ -181,71 +264,108 @@ async fn confirm(messages_tx: &MessageSender, message: impl ToString) -> bool {
     rx.await.unwrap_or_default()
 }
 
+/// Watches files read by file chains with `watch` enabled, and tells the
+/// controller to refresh previews when any of them changes
+#[derive(Debug)]
+pub struct ChainFileWatcher {
+    watcher: RecommendedWatcher,
+    /// Every watched path, as a pattern. The watcher callback needs these to
+    /// ignore events for unrelated files in the same directory
+    patterns: Arc<Mutex<Vec<Pattern>>>,
+}
+
+impl ChainFileWatcher {
+    pub fn new(messages_tx: MessageSender) -> anyhow::Result<Self> {
+        let patterns: Arc<Mutex<Vec<Pattern>>> = Default::default();
+        let f = {
+            let patterns = Arc::clone(&patterns);
+            move |result: notify::Result<notify::Event>| match result {
+                Ok(event) if is_content_change(&event.kind) => {
+                    let patterns =
+                        patterns.lock().expect("Watch patterns lock poisoned");
+                    let matches = event.paths.iter().any(|path| {
+                        patterns
+                            .iter()
+                            .any(|pattern| pattern.matches_path(path))
+                    });
+                    if matches {
+                        info!(?event, "Watched file changed");
+                        messages_tx.send(Message::WatchedFileChanged);
+                    }
+                }
+                // Do nothing for other event kinds
+                Ok(_) => {}
+                Err(err) => {
+                    error!(error = %err, "Error watching chain file");
+                }
+            }
+        };
+        let watcher = notify::recommended_watcher(f)?;
+        Ok(Self { watcher, patterns })
+    }
+
+    /// Start watching a file, if it isn't already watched. If the path is a
+    /// glob pattern, any matching file will trigger a refresh.
+    pub fn watch(&mut self, file: &WatchedFile) -> anyhow::Result<()> {
+        // Events always have absolute paths
+        let path = env::current_dir()?.join(&file.path);
+        let pattern = if file.glob {
+            Pattern::new(&path.to_string_lossy())?
+        } else {
+            Pattern::new(&Pattern::escape(&path.to_string_lossy()))?
+        };
+        let mut patterns =
+            self.patterns.lock().expect("Watch patterns lock poisoned");
+        if patterns.contains(&pattern) {
+            return Ok(());
+        }
+
+        // Watch the parent directory rather than the file itself, so we still
+        // get events if the file is replaced (which many editors do) or
+        // doesn't exist yet. For globs, watch the deepest directory that has
+        // no wildcards, and recurse if the pattern spans subdirectories.
+        let base: PathBuf = if file.glob {
+            path.components()
+                .take_while(|component| !is_glob(component.as_ref()))
+                .collect()
+        } else {
+            path.clone()
+        };
+        let depth = path.components().count() - base.components().count();
+        let (directory, mode) = if depth == 0 {
+            (path.parent().unwrap_or(&path), RecursiveMode::NonRecursive)
+        } else if depth == 1 {
+            (base.as_path(), RecursiveMode::NonRecursive)
+        } else {
+            (base.as_path(), RecursiveMode::Recursive)
+        };
+        self.watcher.watch(directory, mode)?;
+        info!(?path, ?directory, "Watching chain file for changes");
+        patterns.push(pattern);
+        Ok(())
+    }
+}
+
+/// Does a file event indicate that a file's content may have changed?
+fn is_content_change(kind: &EventKind) -> bool {
+    matches!(
+        kind,
+        EventKind::Create(_)
+            | EventKind::Remove(_)
+            | EventKind::Modify(ModifyKind::Data(_) | ModifyKind::Name(_))
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::{
-        test_util::{assert_err, assert_matches, temp_dir, TempDir},
-        tui::test_util::{harness, EnvGuard, TestHarness},
+        test_util::{assert_matches, temp_dir, TempDir},
+        tui::test_util::{harness, TestHarness},
     };
-    use itertools::Itertools;
     use rstest::rstest;
-    use std::ffi::OsStr;
     use tokio::fs;
 
-    /// Test reading editor command from VISUAL/EDITOR env vars
-    #[rstest]
-    #[case::visual(Some("ted"), Some("fred"), "ted", &[])]
-    #[case::editor(None, Some("fred"), "fred", &[])]
-    #[case::with_args(None, Some("ned --wait 60s"), "ned", &["--wait", "60s"])]
-    // This case is actually a bug, but I don't think it's worth the effort of
-    // engineering around. I added this test case for completeness
-    #[case::with_args_quoted(
-        None, Some("ned '--wait 60s'"), "ned", &["'--wait", "60s'"],
-    )]
-    fn test_get_editor(
-        #[case] env_visual: Option<&str>,
-        #[case] env_editor: Option<&str>,
-        #[case] expected_program: &str,
-        #[case] expected_args: &[&str],
-    ) {
-        let file_name = "file.yml";
-        // Make sure we're not competing with the other tests that want to set
-        // these env vars
-        let command = {
-            let _guard = EnvGuard::lock([
-                ("VISUAL", env_visual),
-                ("EDITOR", env_editor),
-            ]);
-            get_editor_command(Path::new(file_name))
-        }
-        .unwrap();
-        let mut expected_args = expected_args.to_owned();
-        expected_args.push(file_name);
-        assert_eq!(command.get_program(), expected_program);
-        assert_eq!(
-            command
-                .get_args()
-                .filter_map(OsStr::to_str)
-                .collect_vec()
-                .as_slice(),
-            expected_args
-        );
-    }
-
-    /// Test when VISUAL/EDITOR env vars are empty
-    #[test]
-    fn test_get_editor_error() {
-        // Make sure we're not competing with the other tests that want to set
-        // these env vars
-        let result = {
-            let _guard =
-                EnvGuard::lock([("VISUAL", None::<String>), ("EDITOR", None)]);
-            get_editor_command(Path::new("file.yml"))
-        };
-        assert_err!(result, "No editor configured");
-    }
-
     /// Test various cases of save_file
     #[rstest]
     #[case::new_file(false, false)]
@@ -13,7 +13,7 @@ pub use common::modal::{IntoModal, ModalPriority};
 pub use context::ViewContext;
 pub use state::RequestState;
 pub use theme::{Styles, Theme};
-pub use util::{Confirm, PreviewPrompter};
+pub use util::{BuildProgress, Confirm, PreviewPrompter};
 
 use crate::{
     collection::CollectionFile,
@@ -66,6 +66,29 @@ impl View {
         view
     }
 
+    /// Create a placeholder view to show while the collection file is loaded
+    /// in the background. The given collection is typically empty, so UI state
+    /// is *not* saved when this view is dropped; otherwise it would overwrite
+    /// the state of the real collection.
+    pub fn loading(
+        collection_file: &CollectionFile,
+        database: CollectionDatabase,
+        messages_tx: MessageSender,
+    ) -> Self {
+        ViewContext::init(database, messages_tx);
+        ViewContext::disable_persistence();
+        let mut view = Self {
+            root: Root::new(&collection_file.collection).into(),
+        };
+        view.set_loading(true);
+        view
+    }
+
+    /// Queue an event to show or hide the collection loading indicator
+    pub fn set_loading(&mut self, loading: bool) {
+        ViewContext::push_event(Event::CollectionLoading(loading));
+    }
+
     /// Draw the view to screen. This needs access to the input engine in order
     /// to render input bindings as help messages to the user.
     pub fn draw<'a>(&'a self, frame: &'a mut Frame) {
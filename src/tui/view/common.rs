@@ -3,6 +3,7 @@
 
 pub mod actions;
 pub mod button;
+pub mod filter;
 pub mod header_table;
 pub mod list;
 pub mod modal;
@@ -18,7 +19,7 @@ use crate::{
     http::{RequestBuildError, RequestError},
     tui::{
         context::TuiContext,
-        view::{draw::Generate, state::Notification},
+        view::{draw::Generate, state::Notification, theme::Theme},
     },
     util::{format_duration, format_time, MaybeStr},
 };
@@ -170,10 +171,11 @@ impl Generate for StatusCode {
     where
         Self: 'this,
     {
-        let styles = &TuiContext::get().styles.status_code;
+        let context = TuiContext::get();
+        let styles = &context.styles.status_code;
         let is_error = self.is_client_error() || self.is_server_error();
         Span::styled(
-            self.to_string(),
+            status_label(self, &context.config.theme),
             if is_error {
                 styles.error
             } else {
@@ -183,6 +185,28 @@ impl Generate for StatusCode {
     }
 }
 
+/// Get display text for a status code, with a badge and/or symbol prefix as
+/// enabled in the theme. These make the status class clear without having to
+/// rely on color.
+fn status_label(status: StatusCode, theme: &Theme) -> String {
+    let mut label = String::new();
+    if theme.status_symbols {
+        let symbol = match status.as_u16() / 100 {
+            1 => "…",
+            2 => "✔",
+            3 => "↪",
+            _ => "✘",
+        };
+        label.push_str(symbol);
+        label.push(' ');
+    }
+    if theme.status_badges {
+        label.push_str(&format!("[{}xx] ", status.as_u16() / 100));
+    }
+    label.push_str(&status.to_string());
+    label
+}
+
 /// Not all header values are UTF-8; use a placeholder if not
 impl Generate for &HeaderValue {
     type Output<'this> = Span<'this> where Self: 'this;
@@ -250,3 +274,32 @@ impl Generate for &RequestError {
         self.error.generate()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::plain(200, false, false, "200 OK")]
+    #[case::badge(404, true, false, "[4xx] 404 Not Found")]
+    #[case::symbol(201, false, true, "✔ 201 Created")]
+    #[case::both(500, true, true, "✘ [5xx] 500 Internal Server Error")]
+    #[case::redirect(302, true, true, "↪ [3xx] 302 Found")]
+    fn test_status_label(
+        #[case] status: u16,
+        #[case] status_badges: bool,
+        #[case] status_symbols: bool,
+        #[case] expected: &str,
+    ) {
+        let theme = Theme {
+            status_badges,
+            status_symbols,
+            ..Theme::default()
+        };
+        assert_eq!(
+            status_label(StatusCode::from_u16(status).unwrap(), &theme),
+            expected
+        );
+    }
+}
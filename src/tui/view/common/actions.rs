@@ -96,6 +96,10 @@ pub enum GlobalAction {
     #[default]
     #[display("Edit Collection")]
     EditCollection,
+    #[display("Switch Collection")]
+    SwitchCollection,
+    #[display("New Recipe")]
+    NewRecipe,
 }
 impl FixedSelect for GlobalAction {}
 impl ToStringGenerate for GlobalAction {}
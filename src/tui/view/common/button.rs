@@ -33,7 +33,8 @@ impl<'a> Generate for Button<'a> {
     where
         Self: 'this,
     {
-        let styles = &TuiContext::get().styles;
+        let tui_context = TuiContext::get();
+        let styles = &tui_context.styles;
         Span {
             content: self.text.into(),
             style: if self.has_focus {
@@ -0,0 +1,161 @@
+//! Inline text box to narrow down a list
+
+use crate::tui::view::{
+    common::text_box::TextBox,
+    event::{Event, EventHandler},
+    Component, ViewContext,
+};
+use ratatui::{layout::Rect, Frame};
+use uuid::Uuid;
+
+/// A filter box that sits above a list. It's hidden until the user starts
+/// searching, and stays visible as long as there's filter text. The owner is
+/// responsible for applying the filter text to its list, and for passing
+/// events in via [Self::update].
+#[derive(Debug)]
+pub struct FilterBox {
+    /// Callback events are tagged with this ID, so that multiple filter boxes
+    /// can be on screen at once (e.g. the recipe list behind the profile
+    /// modal) without handling each other's events
+    id: Uuid,
+    text_box: Component<TextBox>,
+    /// Are we currently typing in the filter box?
+    focused: bool,
+}
+
+impl FilterBox {
+    pub fn new(placeholder: &str, default: String) -> Self {
+        let id = Uuid::new_v4();
+        // Callbacks trigger events, so we can modify our own state
+        let emit = move |event: FilterEvent| {
+            move |_: &TextBox| {
+                ViewContext::push_event(Event::new_local(FilterCallback {
+                    id,
+                    event,
+                }))
+            }
+        };
+        let text_box = TextBox::default()
+            .with_placeholder(placeholder)
+            .with_default(default)
+            .with_on_click(emit(FilterEvent::Focus))
+            .with_on_change(emit(FilterEvent::Change))
+            .with_on_cancel(emit(FilterEvent::Cancel))
+            .with_on_submit(emit(FilterEvent::Submit));
+        Self {
+            id,
+            text_box: text_box.into(),
+            focused: false,
+        }
+    }
+
+    /// Current filter text
+    pub fn text(&self) -> &str {
+        self.text_box.data().text()
+    }
+
+    /// Start typing in the filter box
+    pub fn focus(&mut self) {
+        self.focused = true;
+    }
+
+    /// Clear the filter text and exit the filter box
+    pub fn clear(&mut self) {
+        self.text_box.data_mut().set_text(String::new());
+        self.focused = false;
+    }
+
+    /// Is the filter box in use? It's shown while typing, or whenever there's
+    /// a filter applied
+    pub fn is_active(&self) -> bool {
+        self.focused || !self.text().is_empty()
+    }
+
+    /// Height to reserve for the filter box in the owner's layout
+    pub fn height(&self) -> u16 {
+        if self.is_active() {
+            1
+        } else {
+            0
+        }
+    }
+
+    /// Handle a callback event from this filter's text box. Focus changes are
+    /// handled here, and cancelling clears the filter entirely. Return the
+    /// event if it was ours, so the owner can react to it (e.g. rebuild its
+    /// list). The owner should consume the event in that case.
+    pub fn update(&mut self, event: &Event) -> Option<FilterEvent> {
+        let callback = event.local::<FilterCallback>()?;
+        if callback.id != self.id {
+            return None;
+        }
+        match callback.event {
+            FilterEvent::Focus => self.focused = true,
+            FilterEvent::Change => {}
+            FilterEvent::Cancel => self.clear(),
+            FilterEvent::Submit => self.focused = false,
+        }
+        Some(callback.event)
+    }
+
+    /// Get the text box as a child component. The owner should return this
+    /// *before* its list, so it gets first dibs on key events while typing
+    pub fn as_child(&mut self) -> Component<&mut dyn EventHandler> {
+        self.text_box.as_child()
+    }
+
+    /// Draw the filter box, if it's in use
+    pub fn draw(&self, frame: &mut Frame, area: Rect) {
+        if self.is_active() {
+            self.text_box.draw(frame, (), area, self.focused);
+        }
+    }
+}
+
+/// Callback events from the filter text box
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum FilterEvent {
+    Focus,
+    Change,
+    Cancel,
+    Submit,
+}
+
+/// Local event emitted by the text box, tagged with the owning filter's ID
+#[derive(Debug)]
+struct FilterCallback {
+    id: Uuid,
+    event: FilterEvent,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tui::test_util::{harness, TestHarness};
+    use rstest::rstest;
+
+    /// Each filter should only handle events from its own text box
+    #[rstest]
+    fn test_update(_harness: TestHarness) {
+        let mut filter = FilterBox::new("", "text".into());
+        let mut other = FilterBox::new("", String::new());
+        let event = |id, event| Event::new_local(FilterCallback { id, event });
+
+        assert_eq!(other.update(&event(filter.id, FilterEvent::Focus)), None);
+        assert!(!other.is_active());
+
+        assert_eq!(
+            filter.update(&event(filter.id, FilterEvent::Focus)),
+            Some(FilterEvent::Focus)
+        );
+        assert!(filter.focused);
+
+        // Cancel clears the text
+        assert_eq!(
+            filter.update(&event(filter.id, FilterEvent::Cancel)),
+            Some(FilterEvent::Cancel)
+        );
+        assert_eq!(filter.text(), "");
+        assert!(!filter.is_active());
+    }
+}
@@ -8,9 +8,15 @@ use ratatui::{
     text::Text,
     widgets::{ListItem, ListState, StatefulWidget, Widget},
 };
-use std::marker::PhantomData;
+use std::{cmp, marker::PhantomData};
 
-/// A sequence of items, with a scrollbar and optional surrounding pane
+/// A sequence of items, with a scrollbar and optional surrounding pane.
+///
+/// The list is virtualized: only the items that fit in the drawn area are
+/// generated, so the cost of a draw doesn't scale with the length of the list.
+/// To take advantage of this, callers should pass a lazy iterator and defer
+/// any expensive work to the item's [Generate] impl. This assumes each item is
+/// a single line.
 pub struct List<'a, Item, Iter: 'a + IntoIterator<Item = Item>> {
     items: Iter,
     _phantom: PhantomData<&'a ()>,
@@ -30,27 +36,102 @@ where
     T: Into<Text<'a>>,
     Item: 'a + Generate<Output<'a> = T>,
     Iter: 'a + IntoIterator<Item = Item>,
+    Iter::IntoIter: ExactSizeIterator,
 {
     type State = ListState;
 
     fn render(self, area: Rect, buf: &mut Buffer, state: &mut ListState) {
-        // Draw list
-        let items: Vec<ListItem<'_>> = self
-            .items
-            .into_iter()
+        let items = self.items.into_iter();
+        let num_items = items.len();
+        let height = area.height as usize;
+
+        // Figure out which slice of the list is visible, then only generate
+        // those items
+        let offset =
+            visible_offset(state.offset(), state.selected(), height, num_items);
+        *state.offset_mut() = offset;
+        let items: Vec<ListItem<'_>> = items
+            .skip(offset)
+            .take(height)
             .map(|i| ListItem::new(i.generate()))
             .collect();
-        let num_items = items.len();
+
+        // Ratatui only sees the visible window, so translate the selection
+        let mut window_state = ListState::default().with_selected(
+            state
+                .selected()
+                .and_then(|selected| selected.checked_sub(offset))
+                .filter(|selected| *selected < items.len()),
+        );
         let list = ratatui::widgets::List::new(items)
             .highlight_style(TuiContext::get().styles.list.highlight);
-        StatefulWidget::render(list, area, buf, state);
+        StatefulWidget::render(list, area, buf, &mut window_state);
 
         // Draw scrollbar
         Scrollbar {
             content_length: num_items,
-            offset: state.offset(),
+            offset,
             ..Default::default()
         }
         .render(area, buf);
     }
 }
+
+/// Get the index of the first visible item in the list. The previous offset is
+/// retained if possible, and adjusted just enough to keep the selected item
+/// in view. This mirrors the scrolling behavior of Ratatui's list.
+fn visible_offset(
+    offset: usize,
+    selected: Option<usize>,
+    height: usize,
+    num_items: usize,
+) -> usize {
+    let offset = cmp::min(offset, num_items.saturating_sub(1));
+    match selected {
+        Some(selected) if selected >= offset + height => {
+            (selected + 1).saturating_sub(height)
+        }
+        Some(selected) if selected < offset => selected,
+        _ => offset,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tui::test_util::{harness, TestHarness};
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::empty(0, None, 0)]
+    #[case::no_scroll(0, Some(3), 0)]
+    #[case::scroll_down(0, Some(12), 3)]
+    #[case::scroll_up(8, Some(5), 5)]
+    #[case::keep_offset(4, Some(6), 4)]
+    #[case::list_shrunk(50, Some(2), 2)]
+    fn test_visible_offset(
+        #[case] offset: usize,
+        #[case] selected: Option<usize>,
+        #[case] expected: usize,
+    ) {
+        assert_eq!(visible_offset(offset, selected, 10, 20), expected);
+    }
+
+    /// Only the visible window should be rendered
+    #[rstest]
+    fn test_render_window(_harness: TestHarness) {
+        let area = Rect::new(0, 0, 4, 3);
+        let mut buffer = Buffer::empty(area);
+        let items = (0..1000).map(|i| i.to_string());
+        let mut state = ListState::default().with_selected(Some(500));
+        StatefulWidget::render(List::new(items), area, &mut buffer, &mut state);
+
+        assert_eq!(state.offset(), 498);
+        let line = |y| {
+            (0..3)
+                .map(|x| buffer.get(x, y).symbol().to_owned())
+                .collect::<String>()
+        };
+        assert_eq!([line(0), line(1), line(2)], ["498", "499", "500"]);
+    }
+}
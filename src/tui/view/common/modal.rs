@@ -141,7 +141,8 @@ impl EventHandler for ModalQueue {
 impl Draw for ModalQueue {
     fn draw(&self, frame: &mut Frame, _: (), metadata: DrawMetadata) {
         if let Some(modal) = self.queue.front() {
-            let styles = &TuiContext::get().styles;
+            let tui_context = TuiContext::get();
+            let styles = &tui_context.styles;
             let (width, height) = modal.data().dimensions();
 
             // The child gave us the content dimensions, we need to add one cell
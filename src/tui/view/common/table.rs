@@ -75,7 +75,8 @@ impl<'a, const COLS: usize> Generate for Table<'a, COLS, Row<'a>> {
     where
         Self: 'this,
     {
-        let styles = &TuiContext::get().styles;
+        let tui_context = TuiContext::get();
+        let styles = &tui_context.styles;
         let rows = self.rows.into_iter().enumerate().map(|(i, row)| {
             // Apply theme styles, but let the row's individual styles override
             let base_style = if self.alternate_row_style && i % 2 == 1 {
@@ -141,7 +142,8 @@ where
     where
         Self: 'this,
     {
-        let styles = &TuiContext::get().styles;
+        let tui_context = TuiContext::get();
+        let styles = &tui_context.styles;
         // Include the given cells, then tack on the checkbox for enabled state
         Row::new(
             iter::once(
@@ -8,19 +8,37 @@ use crate::tui::{
     },
 };
 use persisted::PersistedContainer;
-use ratatui::Frame;
-use std::fmt::Debug;
+use ratatui::{layout::Rect, text::Line, Frame};
+use std::{cell::Cell, fmt::Debug};
 
 /// Multi-tab display. Generic parameter defines the available tabs.
 #[derive(Debug, Default)]
 pub struct Tabs<T: FixedSelect> {
     tabs: FixedSelectState<T, usize>,
+    /// Area the tabs were last drawn to, for mapping clicks to tabs
+    area: Cell<Rect>,
 }
 
 impl<T: FixedSelect> Tabs<T> {
     pub fn selected(&self) -> T {
         self.tabs.selected()
     }
+
+    /// Get the tab drawn at the given column, if any. This mirrors the layout
+    /// of Ratatui's tabs: each title is padded by a space on either side, and
+    /// tabs are separated by a one-cell divider.
+    fn tab_at(&self, column: u16) -> Option<T> {
+        let mut x = self.area.get().x;
+        for tab in T::iter() {
+            let width = Line::from(tab.to_string()).width() as u16 + 2;
+            if column < x + width {
+                // Clicks on a divider don't count
+                return (column >= x).then_some(tab);
+            }
+            x += width + 1;
+        }
+        None
+    }
 }
 
 impl<T: FixedSelect> EventHandler for Tabs<T> {
@@ -31,6 +49,19 @@ impl<T: FixedSelect> EventHandler for Tabs<T> {
         match action {
             Action::Left => self.tabs.previous(),
             Action::Right => self.tabs.next(),
+            Action::LeftClick => {
+                if let Event::Input {
+                    event: crossterm::event::Event::Mouse(mouse_event),
+                    ..
+                } = &event
+                {
+                    if let Some(tab) = self.tab_at(mouse_event.column) {
+                        self.tabs.select(&tab);
+                    }
+                }
+                // Let the parent see the click too, so it can grab focus
+                return Update::Propagate(event);
+            }
             _ => return Update::Propagate(event),
         }
         Update::Consumed
@@ -39,6 +70,7 @@ impl<T: FixedSelect> EventHandler for Tabs<T> {
 
 impl<T: FixedSelect> Draw for Tabs<T> {
     fn draw(&self, frame: &mut Frame, _: (), metadata: DrawMetadata) {
+        self.area.set(metadata.area());
         frame.render_widget(
             ratatui::widgets::Tabs::new(T::iter().map(|e| e.to_string()))
                 .select(self.tabs.selected_index())
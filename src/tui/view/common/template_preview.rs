@@ -1,12 +1,13 @@
 use crate::{
-    collection::ProfileId,
-    template::{Template, TemplateChunk},
+    collection::{ProfileId, RecipeId},
+    template::{Template, TemplateChunk, TemplateSourceChunk},
     tui::{
         context::TuiContext,
         message::Message,
-        view::{draw::Generate, ViewContext},
+        view::{common::text_window::WindowText, draw::Generate, ViewContext},
     },
 };
+use itertools::Itertools;
 use ratatui::{
     buffer::Buffer,
     prelude::Rect,
@@ -15,7 +16,9 @@ use ratatui::{
     widgets::{Paragraph, Widget},
 };
 use std::{
-    mem,
+    error::Error,
+    iter, mem,
+    ops::Range,
     sync::{Arc, OnceLock},
 };
 
@@ -24,8 +27,15 @@ use std::{
 /// changed globally.
 #[derive(Debug)]
 pub enum TemplatePreview {
-    /// Template previewing is disabled, just show the raw text
-    Disabled { template: Template },
+    /// Template previewing is disabled, just show the raw text. Keys are
+    /// highlighted, and any that refer to unknown fields or chains are marked
+    /// as errors
+    Disabled {
+        template: Template,
+        /// Validated source chunks. Like rendering, this is done by the
+        /// controller, because it has access to the collection
+        chunks: Arc<OnceLock<Vec<TemplateSourceChunk>>>,
+    },
     /// Template previewing is enabled, render the template
     Enabled {
         template: Template,
@@ -39,20 +49,53 @@ pub enum TemplatePreview {
 impl TemplatePreview {
     /// Create a new template preview. This will spawn a background task to
     /// render the template, *if* template preview is enabled. Profile ID
-    /// defines which profile to use for the render.
-    pub fn new(template: Template, profile_id: Option<ProfileId>) -> Self {
+    /// defines which profile to use for the render. Recipe ID defines whose
+    /// `variables` are available to the template.
+    pub fn new(
+        template: Template,
+        profile_id: Option<ProfileId>,
+        recipe_id: RecipeId,
+    ) -> Self {
+        Self::build(template, profile_id, Some(recipe_id), true)
+    }
+
+    /// Create a new template preview that will never trigger a chained
+    /// request. Chains that would need to send a request show an error
+    /// instead. Use this for previews that the user didn't ask for directly.
+    pub fn untriggered(
+        template: Template,
+        profile_id: Option<ProfileId>,
+    ) -> Self {
+        Self::build(template, profile_id, None, false)
+    }
+
+    fn build(
+        template: Template,
+        profile_id: Option<ProfileId>,
+        recipe_id: Option<RecipeId>,
+        can_trigger: bool,
+    ) -> Self {
         if TuiContext::get().config.preview_templates {
             let chunks = Arc::new(OnceLock::new());
             ViewContext::send_message(Message::TemplatePreview {
                 // If this is a bottleneck we can Arc it
                 template: template.clone(),
                 profile_id: profile_id.clone(),
+                recipe_id: recipe_id.clone(),
                 destination: Arc::clone(&chunks),
+                can_trigger,
             });
 
             Self::Enabled { template, chunks }
         } else {
-            Self::Disabled { template }
+            let chunks = Arc::new(OnceLock::new());
+            ViewContext::send_message(Message::TemplateValidate {
+                template: template.clone(),
+                profile_id,
+                recipe_id,
+                destination: Arc::clone(&chunks),
+            });
+            Self::Disabled { template, chunks }
         }
     }
 }
@@ -67,8 +110,12 @@ impl Generate for &TemplatePreview {
         Self: 'this,
     {
         match self {
-            TemplatePreview::Disabled { template } => {
-                template.to_string().into()
+            TemplatePreview::Disabled { template, chunks } => {
+                match chunks.get() {
+                    Some(chunks) => TextStitcher::stitch_source(chunks),
+                    // Validation isn't done yet
+                    None => template.to_string().into(),
+                }
             }
             // If the preview render is ready, show it. Otherwise fall back
             // to the raw
@@ -90,6 +137,23 @@ impl Widget for &TemplatePreview {
     }
 }
 
+/// Templates are small, so it's fine to generate the entire text each time
+impl WindowText for TemplatePreview {
+    fn dimensions(&self) -> (usize, usize) {
+        let text = self.generate();
+        (text.height(), text.width())
+    }
+
+    fn lines(&self, range: Range<usize>) -> Vec<Line<'_>> {
+        self.generate()
+            .lines
+            .into_iter()
+            .skip(range.start)
+            .take(range.len())
+            .collect()
+    }
+}
+
 /// A helper for stitching rendered template chunks into ratatui `Text`. This
 /// requires some effort because ratatui *loves* line breaks, so we have to
 /// very manually construct the text to make sure the structure reflects the
@@ -105,7 +169,8 @@ struct TextStitcher<'a> {
 impl<'a> TextStitcher<'a> {
     /// Convert chunks into a series of spans, which can be turned into a line
     fn stitch_chunks(chunks: &'a [TemplateChunk]) -> Text<'a> {
-        let styles = &TuiContext::get().styles;
+        let tui_context = TuiContext::get();
+        let styles = &tui_context.styles;
 
         // Each chunk will get its own styling, but we can't just make each
         // chunk a Span, because one chunk might have multiple lines. And we
@@ -126,6 +191,47 @@ impl<'a> TextStitcher<'a> {
         stitcher.into_text()
     }
 
+    /// Convert validated source chunks into highlighted text. Each invalid
+    /// key gets an extra line at the end explaining the problem.
+    fn stitch_source(chunks: &'a [TemplateSourceChunk]) -> Text<'a> {
+        let tui_context = TuiContext::get();
+        let styles = &tui_context.styles;
+
+        let mut stitcher = Self::default();
+        let mut errors = Vec::new();
+        for chunk in chunks {
+            match chunk {
+                TemplateSourceChunk::Raw(text) => {
+                    stitcher.add_chunk(text, Style::default())
+                }
+                TemplateSourceChunk::Key {
+                    source,
+                    error: None,
+                } => stitcher.add_chunk(source, styles.template_preview.key),
+                TemplateSourceChunk::Key {
+                    source,
+                    error: Some(error),
+                } => {
+                    stitcher.add_chunk(source, styles.template_preview.error);
+                    // Include the whole cause chain, e.g. for chain errors
+                    let message =
+                        iter::successors(Some(error as &dyn Error), |&error| {
+                            error.source()
+                        })
+                        .join(": ");
+                    errors.push(Line::styled(
+                        format!("↳ {source}: {message}"),
+                        styles.text.error,
+                    ));
+                }
+            }
+        }
+
+        let mut text = stitcher.into_text();
+        text.lines.extend(errors);
+        text
+    }
+
     /// Add one chunk to the text. This will recursively split on any line
     /// breaks in the text until it reaches the end.
     fn add_chunk(&mut self, chunk_text: &'a str, style: Style) {
@@ -192,7 +298,7 @@ mod tests {
     use super::*;
     use crate::{
         collection::{Chain, ChainSource, Collection, Profile},
-        template::TemplateContext,
+        template::{ChainError, TemplateContext, TemplateError},
         test_util::{by_id, Factory},
         tui::test_util::{harness, TestHarness},
     };
@@ -257,6 +363,47 @@ mod tests {
         assert_eq!(text, Text::from(expected));
     }
 
+    /// Test that keys are highlighted and invalid keys get an explanation
+    #[rstest]
+    fn test_source_stitch(_harness: TestHarness) {
+        let chunks = vec![
+            TemplateSourceChunk::Raw("user: ".into()),
+            TemplateSourceChunk::Key {
+                source: "{{user_id}}".into(),
+                error: None,
+            },
+            TemplateSourceChunk::Raw("\nchain: ".into()),
+            TemplateSourceChunk::Key {
+                source: "{{chains.bogus}}".into(),
+                error: Some(TemplateError::Chain {
+                    chain_id: "bogus".into(),
+                    error: ChainError::ChainUnknown("bogus".into()),
+                }),
+            },
+        ];
+        let text = TextStitcher::stitch_source(&chunks);
+        let tui_context = TuiContext::get();
+        let styles = &tui_context.styles;
+        assert_eq!(
+            text,
+            Text::from(vec![
+                Line::from(vec![
+                    Span::raw("user: "),
+                    Span::styled("{{user_id}}", styles.template_preview.key),
+                ]),
+                Line::from(vec![
+                    Span::raw("chain: "),
+                    error("{{chains.bogus}}"),
+                ]),
+                Line::styled(
+                    "↳ {{chains.bogus}}: Resolving chain `bogus`: \
+                    Unknown chain: bogus",
+                    styles.text.error,
+                ),
+            ])
+        );
+    }
+
     /// Style some text as rendered
     fn rendered(text: &str) -> Span {
         Span::styled(text, TuiContext::get().styles.template_preview.text)
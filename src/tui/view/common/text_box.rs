@@ -39,6 +39,9 @@ pub struct TextBox {
     /// Called when user exits without saving (e.g. Escape)
     #[debug(skip)]
     on_cancel: Option<Callback>,
+    /// Called whenever the text is modified by the user
+    #[debug(skip)]
+    on_change: Option<Callback>,
 }
 
 type Callback = Box<dyn Fn(&TextBox)>;
@@ -98,6 +101,15 @@ impl TextBox {
         self
     }
 
+    /// Set the callback to be called whenever the user modifies the text
+    pub fn with_on_change(
+        mut self,
+        on_change: impl 'static + Fn(&Self),
+    ) -> Self {
+        self.on_change = Some(Box::new(on_change));
+        self
+    }
+
     /// Get current text
     pub fn text(&self) -> &str {
         &self.state.text
@@ -149,8 +161,16 @@ impl TextBox {
         }
     }
 
+    /// Call parent's on_change callback
+    fn change(&mut self) {
+        if let Some(on_change) = &self.on_change {
+            on_change(self);
+        }
+    }
+
     /// Handle input key event to modify text/cursor state
     fn handle_key_event(&mut self, key_event: KeyEvent) {
+        let text_len = self.state.text.len();
         match key_event.code {
             KeyCode::Char(c) => self.state.insert(c),
             KeyCode::Backspace => self.state.delete_left(),
@@ -173,6 +193,11 @@ impl TextBox {
             KeyCode::End => self.state.end(),
             _ => {}
         }
+        // Every edit either inserts or deletes, so a length check is enough to
+        // detect changes
+        if self.state.text.len() != text_len {
+            self.change();
+        }
     }
 }
 
@@ -203,7 +228,8 @@ impl EventHandler for TextBox {
 
 impl Draw for TextBox {
     fn draw(&self, frame: &mut Frame, _: (), metadata: DrawMetadata) {
-        let styles = &TuiContext::get().styles;
+        let tui_context = TuiContext::get();
+        let styles = &tui_context.styles;
 
         // Hide top secret data
         let text: Text = if self.state.text.is_empty() {
@@ -408,12 +434,14 @@ mod tests {
         let click_count = Counter::default();
         let submit_count = Counter::default();
         let cancel_count = Counter::default();
+        let change_count = Counter::default();
         let mut component = TestComponent::new(
             harness,
             TextBox::default()
                 .with_on_click(click_count.callback())
                 .with_on_submit(submit_count.callback())
-                .with_on_cancel(cancel_count.callback()),
+                .with_on_cancel(cancel_count.callback())
+                .with_on_change(change_count.callback()),
             (),
         );
 
@@ -424,12 +452,18 @@ mod tests {
         // Type some text
         component.send_text("hello!").assert_empty();
         assert_state(&component.data().state, "hello!", 6);
+        assert_eq!(change_count, 6);
         component.assert_buffer_lines([vec![
             text("hello!"),
             cursor(" "),
             text("   "),
         ]]);
 
+        // Cursor movement isn't a change
+        component.send_key(KeyCode::Left).assert_empty();
+        component.send_key(KeyCode::Right).assert_empty();
+        assert_eq!(change_count, 6);
+
         // Test callbacks
         component.click(0, 0).assert_empty();
         assert_eq!(click_count, 1);
@@ -494,7 +528,8 @@ mod tests {
         );
 
         assert_state(&component.data().state, "", 0);
-        let styles = &TuiContext::get().styles.text_box;
+        let tui_context = TuiContext::get();
+        let styles = &tui_context.styles.text_box;
         component.assert_buffer_lines([vec![
             cursor("h"),
             Span::styled("ello", styles.text.patch(styles.placeholder)),
@@ -1,36 +1,134 @@
-use crate::tui::{
-    context::TuiContext,
-    input::Action,
-    view::{
-        common::scrollbar::Scrollbar,
-        draw::{Draw, DrawMetadata, Generate},
-        event::{Event, EventHandler, Update},
+use crate::{
+    collection::RecipeId,
+    tui::{
+        context::TuiContext,
+        input::Action,
+        view::{
+            common::scrollbar::Scrollbar,
+            draw::{Draw, DrawMetadata},
+            event::{Event, EventHandler, Update},
+            ViewContext,
+        },
     },
 };
+use crossterm::event::{MouseButton, MouseEventKind};
+use persisted::{PersistedKey, PersistedStore};
 use ratatui::{
     layout::Layout,
     prelude::{Alignment, Constraint},
-    text::{Line, Text},
+    text::{Line, Span},
     widgets::{Paragraph, ScrollbarOrientation},
     Frame,
 };
-use std::{cell::Cell, cmp, fmt::Debug};
+use serde::{Deserialize, Serialize};
+use std::{cell::Cell, cmp, fmt::Debug, ops::Range};
 
 /// A scrollable (but not editable) block of text. Text is not externally
 /// mutable. If you need to update the text, store this in a `StateCell` and
 /// reconstruct the entire component.
 ///
-/// The generic parameter allows for any type that can be converted to ratatui's
-/// `Text`, e.g. `String` or `TemplatePreview`.
+/// The generic parameter allows for any type that implements [WindowText],
+/// e.g. [IndexedText] or `TemplatePreview`. Only the visible lines are pulled
+/// out of the text on each draw, so large text doesn't slow down the UI.
+///
+/// The scroll position can optionally be persisted, so it's restored the next
+/// time the same content is shown. See [Self::with_scroll_key].
 #[derive(Debug, Default)]
 pub struct TextWindow<T> {
     text: T,
-    offset_x: u16,
-    offset_y: u16,
+    /// Scroll offsets are in cells so they can be clamped during the draw,
+    /// once we know the dimensions of the text. This is necessary for restored
+    /// offsets, which may be out of bounds if the text has changed.
+    offset_x: Cell<u16>,
+    offset_y: Cell<usize>,
+    /// Key to persist the scroll position under. `None` disables persistence
+    scroll_key: Option<ScrollKey>,
     text_width: Cell<u16>,
-    text_height: Cell<u16>,
+    text_height: Cell<usize>,
     window_width: Cell<u16>,
     window_height: Cell<u16>,
+    /// Row of the cursor during a mouse drag, so we can scroll by the
+    /// distance it moves. `None` when not dragging
+    drag_row: Option<u16>,
+}
+
+/// Persisted key for the scroll position of a text window
+#[derive(Clone, Debug, Serialize, PersistedKey)]
+#[persisted(ScrollOffset)]
+pub enum ScrollKey {
+    RequestBody(RecipeId),
+    ResponseBody(RecipeId),
+}
+
+/// Persisted scroll position of a text window
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ScrollOffset {
+    pub x: u16,
+    pub y: usize,
+}
+
+/// Text that can be shown in a [TextWindow]. The window only asks for the
+/// lines it's going to draw, so implementors can avoid building the entire
+/// text on every draw.
+pub trait WindowText {
+    /// Get the number of lines, and the width of the widest line in cells
+    fn dimensions(&self) -> (usize, usize);
+
+    /// Get the lines in the given range. The range may extend past the end of
+    /// the text, in which case only the lines that exist are returned
+    fn lines(&self, range: Range<usize>) -> Vec<Line<'_>>;
+}
+
+/// Plain text, indexed by line so any range of lines can be pulled out
+/// without walking the whole text. Building the index is linear in the size
+/// of the text, so for large text it should be done off the main thread.
+#[derive(Debug, Default)]
+pub struct IndexedText {
+    text: String,
+    /// Byte range of each line, excluding the line ending. Lines are split
+    /// the same way as [str::lines]
+    lines: Vec<Range<usize>>,
+    /// Width of the widest line, in cells
+    width: usize,
+}
+
+impl IndexedText {
+    pub fn new(text: String) -> Self {
+        let mut lines = Vec::new();
+        let mut width = 0;
+        let mut start = 0;
+        for line in text.split_inclusive('\n') {
+            let end = start + line.len();
+            // Like str::lines, \r is only stripped as part of \r\n
+            let content = match line.strip_suffix('\n') {
+                Some(line) => line.strip_suffix('\r').unwrap_or(line),
+                None => line,
+            };
+            width = cmp::max(width, Span::raw(content).width());
+            lines.push(start..start + content.len());
+            start = end;
+        }
+        Self { text, lines, width }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+}
+
+impl WindowText for IndexedText {
+    fn dimensions(&self) -> (usize, usize) {
+        (self.lines.len(), self.width)
+    }
+
+    fn lines(&self, range: Range<usize>) -> Vec<Line<'_>> {
+        self.lines
+            .get(range.start..cmp::min(range.end, self.lines.len()))
+            .unwrap_or_default()
+            .iter()
+            .map(|line| self.text[line.clone()].into())
+            .collect()
+    }
 }
 
 #[derive(Default)]
@@ -44,25 +142,46 @@ impl<T> TextWindow<T> {
     pub fn new(text: T) -> Self {
         Self {
             text,
-            offset_x: 0,
-            offset_y: 0,
+            offset_x: Cell::default(),
+            offset_y: Cell::default(),
+            scroll_key: None,
             text_width: Cell::default(),
             text_height: Cell::default(),
             window_width: Cell::default(),
             window_height: Cell::default(),
+            drag_row: None,
+        }
+    }
+
+    /// Persist the scroll position under the given key, and restore the
+    /// previously persisted position, if any
+    pub fn with_scroll_key(mut self, key: ScrollKey) -> Self {
+        if let Some(offset) = ViewContext::load_persisted(&key) {
+            self.offset_x.set(offset.x);
+            self.offset_y.set(offset.y);
         }
+        self.scroll_key = Some(key);
+        self
     }
 
     pub fn text(&self) -> &T {
         &self.text
     }
 
+    /// Get the current scroll position
+    pub fn scroll_offset(&self) -> ScrollOffset {
+        ScrollOffset {
+            x: self.offset_x.get(),
+            y: self.offset_y.get(),
+        }
+    }
+
     /// Get the final line that we can't scroll past. This will be the first
     /// line of the last page of text
-    fn max_scroll_line(&self) -> u16 {
+    fn max_scroll_line(&self) -> usize {
         self.text_height
             .get()
-            .saturating_sub(self.window_height.get())
+            .saturating_sub(self.window_height.get() as usize)
     }
 
     /// Get the final column that we can't scroll (horizontally) past. This will
@@ -73,32 +192,72 @@ impl<T> TextWindow<T> {
             .saturating_sub(self.window_width.get())
     }
 
-    fn scroll_up(&mut self, lines: u16) {
-        self.offset_y = self.offset_y.saturating_sub(lines);
+    fn scroll_up(&mut self, lines: usize) {
+        self.offset_y.set(self.offset_y.get().saturating_sub(lines));
     }
 
-    fn scroll_down(&mut self, lines: u16) {
-        self.offset_y = cmp::min(self.offset_y + lines, self.max_scroll_line());
+    fn scroll_down(&mut self, lines: usize) {
+        self.offset_y.set(cmp::min(
+            self.offset_y.get().saturating_add(lines),
+            self.max_scroll_line(),
+        ));
     }
 
     /// Scroll to a specific line number. The target line will end up as close
     /// to the top of the page as possible
-    fn scroll_to(&mut self, line: u16) {
-        self.offset_y = cmp::min(line, self.max_scroll_line());
+    fn scroll_to(&mut self, line: usize) {
+        self.offset_y.set(cmp::min(line, self.max_scroll_line()));
     }
 
     fn scroll_left(&mut self, columns: u16) {
-        self.offset_x = self.offset_x.saturating_sub(columns);
+        self.offset_x
+            .set(self.offset_x.get().saturating_sub(columns));
     }
 
     fn scroll_right(&mut self, columns: u16) {
-        self.offset_x =
-            cmp::min(self.offset_x + columns, self.max_scroll_column());
+        self.offset_x.set(cmp::min(
+            self.offset_x.get().saturating_add(columns),
+            self.max_scroll_column(),
+        ));
+    }
+
+    /// Save the current scroll position, if persistence is enabled
+    fn persist_scroll(&self) {
+        if let Some(key) = &self.scroll_key {
+            ViewContext::store_persisted(key, self.scroll_offset());
+        }
     }
 }
 
 impl<T: Debug> EventHandler for TextWindow<T> {
     fn update(&mut self, event: Event) -> Update {
+        // Dragging scrolls the content along with the cursor
+        if let Event::Input {
+            event: crossterm::event::Event::Mouse(mouse_event),
+            ..
+        } = &event
+        {
+            match mouse_event.kind {
+                MouseEventKind::Down(MouseButton::Left) => {
+                    self.drag_row = Some(mouse_event.row);
+                }
+                MouseEventKind::Drag(MouseButton::Left) => {
+                    let row = mouse_event.row;
+                    if let Some(previous) = self.drag_row.replace(row) {
+                        if row > previous {
+                            self.scroll_up((row - previous) as usize);
+                        } else {
+                            self.scroll_down((previous - row) as usize);
+                        }
+                        self.persist_scroll();
+                    }
+                    return Update::Consumed;
+                }
+                MouseEventKind::Up(MouseButton::Left) => self.drag_row = None,
+                _ => {}
+            }
+        }
+
         let Some(action) = event.action() else {
             return Update::Propagate(event);
         };
@@ -107,32 +266,30 @@ impl<T: Debug> EventHandler for TextWindow<T> {
             Action::Down | Action::ScrollDown => self.scroll_down(1),
             Action::ScrollLeft => self.scroll_left(1),
             Action::ScrollRight => self.scroll_right(1),
-            Action::PageUp => self.scroll_up(self.window_height.get()),
-            Action::PageDown => self.scroll_down(self.window_height.get()),
+            Action::PageUp => self.scroll_up(self.window_height.get() as usize),
+            Action::PageDown => {
+                self.scroll_down(self.window_height.get() as usize)
+            }
             Action::Home => self.scroll_to(0),
-            Action::End => self.scroll_to(u16::MAX),
+            Action::End => self.scroll_to(usize::MAX),
             _ => return Update::Propagate(event),
         }
+        self.persist_scroll();
         Update::Consumed
     }
 }
 
-/// `T` has to be convertible to text to be drawn
-impl<T> Draw<TextWindowProps> for TextWindow<T>
-where
-    T: 'static,
-    for<'a> &'a T: Generate<Output<'a> = Text<'a>>,
-{
+impl<T: WindowText> Draw<TextWindowProps> for TextWindow<T> {
     fn draw(
         &self,
         frame: &mut Frame,
         props: TextWindowProps,
         metadata: DrawMetadata,
     ) {
-        let styles = &TuiContext::get().styles;
-        let text = Paragraph::new(self.text.generate());
+        let tui_context = TuiContext::get();
+        let styles = &tui_context.styles;
         // Assume no line wrapping when calculating line count
-        let text_height = text.line_count(u16::MAX) as u16;
+        let (text_height, text_width) = self.text.dimensions();
 
         let [gutter_area, _, text_area] = Layout::horizontal([
             // Size gutter based on width of max line number
@@ -143,14 +300,23 @@ where
         .areas(metadata.area());
 
         // Store text and window sizes for calculations in the update code
-        self.text_width.set(text.line_width() as u16);
+        self.text_width
+            .set(cmp::min(text_width, u16::MAX as usize) as u16);
         self.text_height.set(text_height);
         self.window_width.set(text_area.width);
         self.window_height.set(text_area.height);
 
+        // Clamp offsets, in case they were restored from a larger text
+        self.offset_x
+            .set(cmp::min(self.offset_x.get(), self.max_scroll_column()));
+        self.offset_y
+            .set(cmp::min(self.offset_y.get(), self.max_scroll_line()));
+        let (offset_x, offset_y) = (self.offset_x.get(), self.offset_y.get());
+
         // Draw line numbers in the gutter
-        let first_line = self.offset_y + 1;
-        let last_line = cmp::min(first_line + text_area.height, text_height);
+        let first_line = offset_y + 1;
+        let last_line =
+            cmp::min(first_line + text_area.height as usize, text_height);
         frame.render_widget(
             Paragraph::new(
                 (first_line..=last_line)
@@ -162,17 +328,20 @@ where
             gutter_area,
         );
 
-        // Draw the text content
+        // Draw the text content. Only pull out the lines that are visible
+        let lines = self
+            .text
+            .lines(offset_y..offset_y + text_area.height as usize);
         frame.render_widget(
-            text.scroll((self.offset_y, self.offset_x)),
+            Paragraph::new(lines).scroll((0, offset_x)),
             text_area,
         );
 
         // Scrollbars
         frame.render_widget(
             Scrollbar {
-                content_length: self.text_height.get() as usize,
-                offset: self.offset_y as usize,
+                content_length: self.text_height.get(),
+                offset: offset_y,
                 ..Default::default()
             },
             text_area,
@@ -180,7 +349,7 @@ where
         frame.render_widget(
             Scrollbar {
                 content_length: self.text_width.get() as usize,
-                offset: self.offset_x as usize,
+                offset: offset_x as usize,
                 orientation: ScrollbarOrientation::HorizontalBottom,
                 margin: if props.has_search_box { 2 } else { 1 },
             },
@@ -188,3 +357,35 @@ where
         );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    /// Lines should be split the same way ratatui splits text
+    #[rstest]
+    #[case::empty("", &[])]
+    #[case::trailing_newline("a\nb\n", &["a", "b"])]
+    #[case::crlf("a\r\nb", &["a", "b"])]
+    #[case::lone_cr("a\rb\r", &["a\rb\r"])]
+    #[case::empty_lines("\n\na", &["", "", "a"])]
+    fn test_indexed_text(#[case] text: &str, #[case] expected: &[&str]) {
+        let indexed = IndexedText::new(text.to_owned());
+        let expected_width = expected
+            .iter()
+            .map(|line| Span::raw(*line).width())
+            .max()
+            .unwrap_or(0);
+        assert_eq!(indexed.dimensions(), (expected.len(), expected_width));
+        assert_eq!(
+            indexed.lines(0..usize::MAX),
+            expected
+                .iter()
+                .map(|line| Line::from(*line))
+                .collect::<Vec<_>>()
+        );
+        // Ranges past the end are cut off
+        assert!(indexed.lines(expected.len() + 1..usize::MAX).is_empty());
+    }
+}
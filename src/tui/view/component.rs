@@ -1,3 +1,8 @@
+mod build_progress;
+mod chain_inspector;
+mod code_select;
+mod collection_select;
+mod command_palette;
 mod exchange_pane;
 mod help;
 mod history;
@@ -11,6 +16,7 @@ mod recipe_pane;
 mod request_view;
 mod response_view;
 mod root;
+mod status_bar;
 
 pub use internal::Component;
 pub use root::Root;
@@ -0,0 +1,143 @@
+//! Progress display for slow request builds
+
+use crate::{
+    template::RenderProgress,
+    tui::{
+        context::TuiContext,
+        input::Action,
+        view::{
+            common::modal::{IntoModal, Modal},
+            draw::{Draw, DrawMetadata},
+            event::{Event, EventHandler, Update},
+            BuildProgress, ModalPriority, ViewContext,
+        },
+    },
+};
+use ratatui::{
+    layout::Constraint,
+    text::{Line, Text},
+    widgets::Paragraph,
+    Frame,
+};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+use tokio::sync::oneshot;
+
+/// Show which template keys a request build is still waiting on, with an
+/// option to cancel the build. The modal closes itself once the build is done.
+/// It also steps aside if another modal (e.g. a prompt from the same build)
+/// is opened behind it, since that's probably what the build is waiting on.
+#[derive(Debug)]
+pub struct BuildProgressModal {
+    progress: RenderProgress,
+    done: Arc<AtomicBool>,
+    /// Taken when the user cancels, so we only send once
+    cancel: Option<oneshot::Sender<()>>,
+    /// Have we already asked to be closed? Prevents closing another modal by
+    /// accident
+    closing: bool,
+}
+
+impl BuildProgressModal {
+    /// Maximum number of keys to show
+    const MAX_KEYS: usize = 10;
+
+    fn close(&mut self) {
+        if !self.closing {
+            self.closing = true;
+            ViewContext::push_event(Event::CloseModal);
+        }
+    }
+}
+
+impl Modal for BuildProgressModal {
+    fn title(&self) -> Line<'_> {
+        "Building Request".into()
+    }
+
+    fn dimensions(&self) -> (Constraint, Constraint) {
+        let keys = self.progress.active().len().clamp(1, Self::MAX_KEYS);
+        // One line per key, plus a gap and the help text
+        (
+            Constraint::Percentage(40),
+            Constraint::Length(keys as u16 + 2),
+        )
+    }
+}
+
+impl EventHandler for BuildProgressModal {
+    fn update(&mut self, event: Event) -> Update {
+        if self.done.load(Ordering::Relaxed) {
+            self.close();
+            return Update::Propagate(event);
+        }
+
+        match event {
+            Event::Input {
+                action: Some(Action::Submit),
+                ..
+            } => {
+                if let Some(cancel) = self.cancel.take() {
+                    let _ = cancel.send(());
+                }
+                self.close();
+                Update::Consumed
+            }
+            // High priority modals go in front of us anyway
+            Event::OpenModal {
+                priority: ModalPriority::Low,
+                ..
+            } => {
+                self.close();
+                Update::Propagate(event)
+            }
+            _ => Update::Propagate(event),
+        }
+    }
+}
+
+impl Draw for BuildProgressModal {
+    fn draw(&self, frame: &mut Frame, _: (), metadata: DrawMetadata) {
+        let tui_context = TuiContext::get();
+        let input_engine = &tui_context.input_engine;
+        let mut lines: Vec<Line> = if self.done.load(Ordering::Relaxed) {
+            vec!["Done".into()]
+        } else {
+            let active = self.progress.active();
+            if active.is_empty() {
+                vec!["Waiting...".into()]
+            } else {
+                active
+                    .into_iter()
+                    .take(Self::MAX_KEYS)
+                    .map(|key| format!("Rendering {{{{{key}}}}}").into())
+                    .collect()
+            }
+        };
+        lines.push("".into());
+        lines.push(
+            format!(
+                "{} / {}",
+                input_engine.add_hint("Cancel Request", Action::Submit),
+                input_engine.add_hint("Hide", Action::Cancel),
+            )
+            .into(),
+        );
+        frame.render_widget(Paragraph::new(Text::from(lines)), metadata.area());
+    }
+}
+
+impl IntoModal for BuildProgress {
+    type Target = BuildProgressModal;
+
+    fn into_modal(self) -> Self::Target {
+        BuildProgressModal {
+            progress: self.progress,
+            done: self.done,
+            cancel: Some(self.cancel),
+            closing: false,
+        }
+    }
+}
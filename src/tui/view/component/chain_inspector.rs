@@ -0,0 +1,182 @@
+//! Debugging view for chains
+
+use crate::{
+    collection::{Chain, ChainId, ChainSource, ProfileId},
+    template::{Template, TemplateChunk},
+    tui::{
+        context::TuiContext,
+        message::Message,
+        view::{
+            common::{list::List, modal::Modal},
+            draw::{Draw, DrawMetadata, Generate},
+            event::EventHandler,
+            state::select::SelectState,
+            Component, ViewContext,
+        },
+    },
+    util::doc_link,
+};
+use ratatui::{
+    layout::{Constraint, Layout},
+    text::{Line, Text},
+    widgets::{Paragraph, Wrap},
+    Frame,
+};
+use std::{
+    error::Error,
+    iter,
+    sync::{Arc, OnceLock},
+};
+
+/// Modal listing every chain in the collection, with the value each one
+/// currently resolves to. Values are rendered without triggering any
+/// requests, so a chain that depends on a request with no history will show
+/// an error rather than sending it.
+#[derive(Debug)]
+pub struct ChainInspectorModal {
+    select: Component<SelectState<ChainItem>>,
+}
+
+/// A single chain in the list
+#[derive(Debug)]
+struct ChainItem {
+    id: ChainId,
+    /// Short description of where the value comes from
+    source: String,
+    /// Rendered value. The controller renders it in the background and dumps
+    /// the result here
+    value: Arc<OnceLock<Vec<TemplateChunk>>>,
+}
+
+impl ChainInspectorModal {
+    pub fn new(chains: &[Chain], profile_id: Option<&ProfileId>) -> Self {
+        let items = chains
+            .iter()
+            .map(|chain| {
+                let value = Arc::new(OnceLock::new());
+                ViewContext::send_message(Message::TemplatePreview {
+                    template: Template::from_chain(&chain.id),
+                    profile_id: profile_id.cloned(),
+                    recipe_id: None,
+                    destination: Arc::clone(&value),
+                    can_trigger: false,
+                });
+                let source = match &chain.source {
+                    ChainSource::Request { recipe, .. } => {
+                        format!("request `{recipe}`")
+                    }
+                    source => source.kind().to_owned(),
+                };
+                ChainItem {
+                    id: chain.id.clone(),
+                    source,
+                    value,
+                }
+            })
+            .collect();
+        Self {
+            select: SelectState::builder(items).build().into(),
+        }
+    }
+}
+
+impl ChainItem {
+    /// Get the rendered value, or the render error
+    fn value_text(&self) -> Text<'_> {
+        let tui_context = TuiContext::get();
+        let styles = &tui_context.styles;
+        let Some(chunks) = self.value.get() else {
+            return "Loading...".into();
+        };
+
+        let mut lines: Vec<Line> = Vec::new();
+        for chunk in chunks {
+            match chunk {
+                TemplateChunk::Raw(text) => {
+                    lines.extend(text.lines().map(Line::from))
+                }
+                TemplateChunk::Rendered {
+                    sensitive: true, ..
+                } => lines.push("<sensitive>".into()),
+                TemplateChunk::Rendered { value, .. } => {
+                    match std::str::from_utf8(value) {
+                        Ok(value) => {
+                            lines.extend(value.lines().map(Line::from))
+                        }
+                        Err(_) => lines.push("<binary>".into()),
+                    }
+                }
+                // Show each cause on its own line, so the root cause is easy
+                // to pick out
+                TemplateChunk::Error(error) => lines.extend(
+                    iter::successors(Some(error as &dyn Error), |&error| {
+                        error.source()
+                    })
+                    .map(|error| {
+                        Line::styled(error.to_string(), styles.text.error)
+                    }),
+                ),
+            }
+        }
+        lines.into()
+    }
+}
+
+impl Modal for ChainInspectorModal {
+    fn title(&self) -> Line<'_> {
+        "Chains".into()
+    }
+
+    fn dimensions(&self) -> (Constraint, Constraint) {
+        (Constraint::Percentage(60), Constraint::Percentage(60))
+    }
+}
+
+impl EventHandler for ChainInspectorModal {
+    fn children(&mut self) -> Vec<Component<&mut dyn EventHandler>> {
+        vec![self.select.as_child()]
+    }
+}
+
+impl Draw for ChainInspectorModal {
+    fn draw(&self, frame: &mut Frame, _: (), metadata: DrawMetadata) {
+        let select = self.select.data();
+        if select.items().is_empty() {
+            frame.render_widget(
+                Text::from(vec![
+                    "No chains defined; add one to your collection.".into(),
+                    doc_link("api/request_collection/chain").into(),
+                ]),
+                metadata.area(),
+            );
+            return;
+        }
+
+        let [list_area, _, detail_area] = Layout::vertical([
+            Constraint::Length(select.items().len().min(10) as u16),
+            Constraint::Length(1), // Padding
+            Constraint::Min(0),
+        ])
+        .areas(metadata.area());
+
+        self.select
+            .draw(frame, List::new(select.items()), list_area, true);
+        if let Some(item) = select.selected() {
+            frame.render_widget(
+                Paragraph::new(item.value_text()).wrap(Wrap { trim: false }),
+                detail_area,
+            );
+        }
+    }
+}
+
+impl Generate for &ChainItem {
+    type Output<'this> = Line<'this> where Self: 'this;
+
+    fn generate<'this>(self) -> Self::Output<'this>
+    where
+        Self: 'this,
+    {
+        format!("{} ({})", self.id, self.source).into()
+    }
+}
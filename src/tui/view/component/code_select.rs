@@ -0,0 +1,116 @@
+use crate::{
+    http::CodeLanguage,
+    tui::{
+        message::{Message, RequestConfig},
+        view::{
+            common::{list::List, modal::Modal},
+            component::Component,
+            draw::{Draw, DrawMetadata, ToStringGenerate},
+            event::{Event, EventHandler},
+            state::select::SelectState,
+            ViewContext,
+        },
+    },
+};
+use ratatui::{layout::Constraint, text::Line, Frame};
+use strum::{EnumCount, IntoEnumIterator};
+
+/// Modal to pick a language for the "Copy as Code" action. Once a language is
+/// picked, the request is rendered and the generated code is copied to the
+/// clipboard.
+#[derive(Debug)]
+pub struct CodeLanguageModal {
+    select: Component<SelectState<CodeLanguage>>,
+}
+
+impl CodeLanguageModal {
+    pub fn new(request_config: RequestConfig) -> Self {
+        let select = SelectState::builder(CodeLanguage::iter().collect())
+            .on_submit(move |language| {
+                ViewContext::push_event(Event::CloseModal);
+                ViewContext::send_message(Message::CopyRequestCode {
+                    request_config: request_config.clone(),
+                    language: *language,
+                });
+            })
+            .build();
+        Self {
+            select: select.into(),
+        }
+    }
+}
+
+impl Modal for CodeLanguageModal {
+    fn title(&self) -> Line<'_> {
+        "Copy as Code".into()
+    }
+
+    fn dimensions(&self) -> (Constraint, Constraint) {
+        (
+            Constraint::Length(30),
+            Constraint::Length(CodeLanguage::COUNT as u16),
+        )
+    }
+}
+
+impl EventHandler for CodeLanguageModal {
+    fn children(&mut self) -> Vec<Component<&mut dyn EventHandler>> {
+        vec![self.select.as_child()]
+    }
+}
+
+impl Draw for CodeLanguageModal {
+    fn draw(&self, frame: &mut Frame, _: (), metadata: DrawMetadata) {
+        self.select.draw(
+            frame,
+            List::new(self.select.data().items()),
+            metadata.area(),
+            true,
+        );
+    }
+}
+
+impl ToStringGenerate for CodeLanguage {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        http::BuildOptions,
+        test_util::assert_matches,
+        tui::{
+            test_util::{harness, TestHarness},
+            view::test_util::TestComponent,
+        },
+    };
+    use crossterm::event::KeyCode;
+    use rstest::rstest;
+
+    /// Selecting a language sends a message to generate and copy the code
+    #[rstest]
+    fn test_submit(harness: TestHarness) {
+        let request_config = RequestConfig {
+            recipe_id: "recipe1".into(),
+            profile_id: Some("profile1".into()),
+            options: BuildOptions::default(),
+        };
+        let mut component = TestComponent::new(
+            harness,
+            CodeLanguageModal::new(request_config.clone()),
+            (),
+        );
+
+        component.send_key(KeyCode::Down).assert_empty();
+        assert_matches!(
+            component.send_key(KeyCode::Enter).events(),
+            [Event::CloseModal]
+        );
+        let (config, language) = assert_matches!(
+            component.harness_mut().pop_message_now(),
+            Message::CopyRequestCode { request_config, language } =>
+                (request_config, language),
+        );
+        assert_eq!(config, request_config);
+        assert_eq!(language, CodeLanguage::JavaScript);
+    }
+}
@@ -0,0 +1,96 @@
+use crate::tui::{
+    message::Message,
+    view::{
+        common::{list::List, modal::Modal},
+        component::Component,
+        draw::{Draw, DrawMetadata, Generate},
+        event::{Event, EventHandler},
+        state::select::SelectState,
+        ViewContext,
+    },
+};
+use ratatui::{
+    layout::Constraint,
+    text::{Line, Text},
+    Frame,
+};
+use std::path::PathBuf;
+
+/// Modal to switch to a different collection file. Lists every collection
+/// that's known to the database, i.e. everything that's ever been opened or
+/// added via `slumber collections add`.
+#[derive(Debug)]
+pub struct CollectionSelectModal {
+    select: Component<SelectState<PathBuf>>,
+}
+
+impl CollectionSelectModal {
+    /// Parent is responsible for loading the list from the database
+    pub fn new(collections: Vec<PathBuf>, current: Option<PathBuf>) -> Self {
+        let select = SelectState::builder(collections)
+            .preselect_opt(current.as_ref())
+            .on_submit(|path| {
+                ViewContext::push_event(Event::CloseModal);
+                // The whole view gets rebuilt for the new collection, so this
+                // has to be handled at the top level
+                ViewContext::send_message(Message::CollectionSelect(
+                    path.clone(),
+                ));
+            })
+            .build();
+        Self {
+            select: select.into(),
+        }
+    }
+}
+
+impl Modal for CollectionSelectModal {
+    fn title(&self) -> Line<'_> {
+        "Collections".into()
+    }
+
+    fn dimensions(&self) -> (Constraint, Constraint) {
+        (
+            Constraint::Percentage(60),
+            Constraint::Length(
+                self.select.data().items().len().clamp(1, 20) as u16
+            ),
+        )
+    }
+}
+
+impl EventHandler for CollectionSelectModal {
+    fn children(&mut self) -> Vec<Component<&mut dyn EventHandler>> {
+        vec![self.select.as_child()]
+    }
+}
+
+impl Draw for CollectionSelectModal {
+    fn draw(&self, frame: &mut Frame, _: (), metadata: DrawMetadata) {
+        let select = self.select.data();
+        if select.items().is_empty() {
+            frame.render_widget(
+                Text::from("No known collections"),
+                metadata.area(),
+            );
+            return;
+        }
+        self.select.draw(
+            frame,
+            List::new(select.items()),
+            metadata.area(),
+            true,
+        );
+    }
+}
+
+impl Generate for &PathBuf {
+    type Output<'this> = Line<'this> where Self: 'this;
+
+    fn generate<'this>(self) -> Self::Output<'this>
+    where
+        Self: 'this,
+    {
+        self.to_string_lossy().into_owned().into()
+    }
+}
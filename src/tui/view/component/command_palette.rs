@@ -0,0 +1,243 @@
+//! Fuzzy finder for actions, recipes, and profiles
+
+use crate::{
+    collection::{Profile, ProfileId, RecipeId, RecipeTree},
+    tui::{
+        context::TuiContext,
+        input::{Action, InputBinding},
+        message::Message,
+        view::{
+            common::{list::List, modal::Modal, text_box::TextBox},
+            component::{
+                profile_select::SelectProfile, recipe_list::SelectRecipe,
+            },
+            draw::{Draw, DrawMetadata, Generate},
+            event::{Event, EventHandler, Update},
+            state::select::SelectState,
+            util::matches_filter,
+            Component, ViewContext,
+        },
+    },
+};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{
+    layout::{Constraint, Layout},
+    text::{Line, Text},
+    Frame,
+};
+
+/// Modal to search all actions, recipes, and profiles by name, and run the
+/// selected one. The filter is always focused; arrow keys move the selection
+/// and Enter executes it.
+#[derive(Debug)]
+pub struct CommandPalette {
+    /// All items. The select state only holds the ones matching the filter
+    items: Vec<PaletteItem>,
+    select: Component<SelectState<PaletteItem>>,
+    filter: Component<TextBox>,
+}
+
+/// A single entry in the palette
+#[derive(Clone, Debug, PartialEq)]
+enum PaletteItem {
+    /// Trigger a bound action, as if its key had been pressed
+    Action(Action),
+    Recipe {
+        id: RecipeId,
+        name: String,
+    },
+    Profile {
+        id: ProfileId,
+        name: String,
+    },
+}
+
+impl CommandPalette {
+    pub fn new(recipes: &RecipeTree, profiles: &[Profile]) -> Self {
+        let tui_context = TuiContext::get();
+        let actions = tui_context
+            .input_engine
+            .bindings()
+            .keys()
+            .filter(|action| {
+                action.visible() && **action != Action::CommandPalette
+            })
+            .map(|action| PaletteItem::Action(*action));
+        let recipes = recipes.iter().filter_map(|(_, node)| node.recipe()).map(
+            |recipe| PaletteItem::Recipe {
+                id: recipe.id.clone(),
+                name: recipe.name().to_owned(),
+            },
+        );
+        let profiles = profiles.iter().map(|profile| PaletteItem::Profile {
+            id: profile.id.clone(),
+            name: profile.name().to_owned(),
+        });
+        let items: Vec<_> = actions.chain(recipes).chain(profiles).collect();
+
+        let filter = TextBox::default()
+            .with_placeholder("Search actions, recipes, and profiles")
+            .with_on_change(|_| {
+                ViewContext::push_event(Event::new_local(FilterChanged))
+            });
+        Self {
+            select: Self::build_select_state(&items, "").into(),
+            items,
+            filter: filter.into(),
+        }
+    }
+
+    /// Build the list of items that match the filter
+    fn build_select_state(
+        items: &[PaletteItem],
+        filter: &str,
+    ) -> SelectState<PaletteItem> {
+        let items = items
+            .iter()
+            .filter(|item| matches_filter(&item.label(), filter))
+            .cloned()
+            .collect();
+        SelectState::builder(items).build()
+    }
+
+    /// Close the palette and run the selected item
+    fn execute(&self) {
+        let Some(item) = self.select.data().selected() else {
+            return;
+        };
+        // Close the modal *first*, so the rest of the view can handle the
+        // follow-up event
+        ViewContext::push_event(Event::CloseModal);
+        match item {
+            PaletteItem::Action(action) => {
+                // Replay the action through the main loop. If we queued the
+                // view event directly, it would be handled before the modal
+                // closes and focus returns to the primary view
+                let key_event = TuiContext::get()
+                    .input_engine
+                    .binding(*action)
+                    .and_then(InputBinding::key_event);
+                if let Some(key_event) = key_event {
+                    ViewContext::send_message(Message::Input {
+                        event: crossterm::event::Event::Key(key_event),
+                        action: Some(*action),
+                    });
+                }
+            }
+            PaletteItem::Recipe { id, .. } => ViewContext::push_event(
+                Event::new_local(SelectRecipe(id.clone())),
+            ),
+            PaletteItem::Profile { id, .. } => ViewContext::push_event(
+                Event::new_local(SelectProfile(id.clone())),
+            ),
+        }
+    }
+}
+
+impl PaletteItem {
+    /// Text to display and match against
+    fn label(&self) -> String {
+        match self {
+            Self::Action(action) => format!("Action: {action}"),
+            Self::Recipe { name, .. } => format!("Recipe: {name}"),
+            Self::Profile { name, .. } => format!("Profile: {name}"),
+        }
+    }
+}
+
+impl Modal for CommandPalette {
+    fn title(&self) -> Line<'_> {
+        "Command Palette".into()
+    }
+
+    fn dimensions(&self) -> (Constraint, Constraint) {
+        let num_items = self.select.data().items().len().clamp(1, 15) as u16;
+        (
+            Constraint::Percentage(60),
+            // Extra line for the filter
+            Constraint::Length(num_items + 1),
+        )
+    }
+}
+
+impl EventHandler for CommandPalette {
+    fn update(&mut self, event: Event) -> Update {
+        if let Some(FilterChanged) = event.local() {
+            *self.select.data_mut() = Self::build_select_state(
+                &self.items,
+                self.filter.data().text(),
+            );
+            return Update::Consumed;
+        }
+
+        // The filter and list aren't children, because the filter would eat
+        // all key events. Route them by hand instead, so that characters are
+        // always typed even if they're bound to navigation (e.g. vim keys)
+        match event.action() {
+            Some(Action::Submit) => self.execute(),
+            Some(Action::Cancel) => return Update::Propagate(event),
+            Some(Action::Up | Action::ScrollUp) if !is_typed(&event) => {
+                self.select.data_mut().previous()
+            }
+            Some(Action::Down | Action::ScrollDown) if !is_typed(&event) => {
+                self.select.data_mut().next()
+            }
+            _ => return self.filter.data_mut().update(event),
+        }
+        Update::Consumed
+    }
+}
+
+impl Draw for CommandPalette {
+    fn draw(&self, frame: &mut Frame, _: (), metadata: DrawMetadata) {
+        let [filter_area, list_area] =
+            Layout::vertical([Constraint::Length(1), Constraint::Min(0)])
+                .areas(metadata.area());
+
+        self.filter.draw(frame, (), filter_area, true);
+        let select = self.select.data();
+        if select.items().is_empty() {
+            frame.render_widget(Text::from("No matches"), list_area);
+        } else {
+            self.select
+                .draw(frame, List::new(select.items()), list_area, true);
+        }
+    }
+}
+
+impl Generate for &PaletteItem {
+    type Output<'this> = Line<'this> where Self: 'this;
+
+    fn generate<'this>(self) -> Self::Output<'this>
+    where
+        Self: 'this,
+    {
+        match self {
+            PaletteItem::Action(action) => TuiContext::get()
+                .input_engine
+                .add_hint(self.label(), *action)
+                .into(),
+            _ => self.label().into(),
+        }
+    }
+}
+
+/// Local event from the filter text box
+#[derive(Debug)]
+struct FilterChanged;
+
+/// Is this a key press that should be typed into the filter, rather than
+/// treated as navigation?
+fn is_typed(event: &Event) -> bool {
+    matches!(
+        event,
+        Event::Input {
+            event: crossterm::event::Event::Key(KeyEvent {
+                code: KeyCode::Char(_),
+                modifiers,
+                ..
+            }),
+            ..
+        } if !modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+    )
+}
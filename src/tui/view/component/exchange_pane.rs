@@ -104,7 +104,8 @@ impl<'a> Draw<ExchangePaneProps<'a>> for ExchangePane {
         props: ExchangePaneProps<'a>,
         metadata: DrawMetadata,
     ) {
-        let input_engine = &TuiContext::get().input_engine;
+        let tui_context = TuiContext::get();
+        let input_engine = &tui_context.input_engine;
         let title =
             input_engine.add_hint("Request / Response", Action::SelectResponse);
         let mut block = Pane {
@@ -160,6 +161,9 @@ impl<'a> Draw<ExchangePaneProps<'a>> for ExchangePane {
                     metadata.start_time.generate(),
                     " / ".into(),
                     metadata.duration.generate(),
+                    " / ".into(),
+                    format!("{} sent", metadata.size.to_string_as(false))
+                        .into(),
                 ]),
                 metadata_area,
             );
@@ -170,6 +174,8 @@ impl<'a> Draw<ExchangePaneProps<'a>> for ExchangePane {
         {
             frame.render_widget(
                 Line::from(vec![
+                    // Version doesn't impl Display; Debug gives "HTTP/1.1"
+                    format!("{:?} ", metadata.version).into(),
                     metadata.status.generate(),
                     " ".into(),
                     metadata.size.to_string_as(false).into(),
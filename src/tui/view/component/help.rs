@@ -58,13 +58,14 @@ impl HelpModal {
     const GENERAL_LENGTH: u16 = 3;
 
     /// Get the list of bindings that will be shown in the modal
-    fn bindings() -> impl Iterator<Item = (Action, &'static InputBinding)> {
+    fn bindings() -> Vec<(Action, InputBinding)> {
         TuiContext::get()
             .input_engine
             .bindings()
             .iter()
             .filter(|(action, _)| action.visible())
-            .map(|(action, binding)| (*action, binding))
+            .map(|(action, binding)| (*action, binding.clone()))
+            .collect()
     }
 }
 
@@ -74,7 +75,7 @@ impl Modal for HelpModal {
     }
 
     fn dimensions(&self) -> (Constraint, Constraint) {
-        let num_bindings = Self::bindings().count() as u16;
+        let num_bindings = Self::bindings().len() as u16;
         (
             Constraint::Percentage(60),
             Constraint::Length(Self::GENERAL_LENGTH + 3 + num_bindings),
@@ -124,6 +125,7 @@ impl Draw for HelpModal {
         let keybindings = Table {
             title: Some("Keybindings"),
             rows: Self::bindings()
+                .into_iter()
                 .map(|(action, binding)| {
                     let action: Line = action.to_string().into();
                     let input: Line = binding.to_string().into();
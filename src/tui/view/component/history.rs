@@ -1,6 +1,6 @@
 use crate::{
     collection::Recipe,
-    http::RequestId,
+    http::{ExchangeSummary, RequestId},
     tui::{
         context::TuiContext,
         view::{
@@ -19,7 +19,9 @@ use ratatui::{
     Frame,
 };
 
-/// Browse request/response history for a recipe
+/// Browse request/response history for a recipe. This includes requests from
+/// all profiles, so each one is labelled with the profile it was sent with, as
+/// well as the recipe's rendered label (if any).
 #[derive(Debug)]
 pub struct History {
     recipe_name: String,
@@ -65,7 +67,7 @@ impl Modal for History {
 
     fn dimensions(&self) -> (Constraint, Constraint) {
         (
-            Constraint::Length(40),
+            Constraint::Length(80),
             Constraint::Length(self.select.data().items().len().min(20) as u16),
         )
     }
@@ -95,7 +97,8 @@ impl Generate for &RequestStateSummary {
     where
         Self: 'this,
     {
-        let styles = &TuiContext::get().styles;
+        let tui_context = TuiContext::get();
+        let styles = &tui_context.styles;
         let description: Span = match self {
             RequestStateSummary::Building { .. } => "Initializing...".into(),
             RequestStateSummary::BuildError { .. } => {
@@ -109,7 +112,30 @@ impl Generate for &RequestStateSummary {
                 Span::styled("Request error", styles.text.error)
             }
         };
-        vec![self.time().generate(), " ".into(), description].into()
+        let profile = match self.profile_id() {
+            Some(profile_id) => {
+                Span::styled(profile_id.to_string(), styles.text.primary)
+            }
+            None => "No profile".into(),
+        };
+        let mut spans = vec![
+            self.time().generate(),
+            " ".into(),
+            profile,
+            " ".into(),
+            description,
+        ];
+        if let RequestStateSummary::Response(ExchangeSummary {
+            response_size: Some(size),
+            ..
+        }) = self
+        {
+            spans.extend([" ".into(), size.to_string_as(false).into()]);
+        }
+        if let Some(label) = self.label() {
+            spans.extend([" ".into(), label.into()]);
+        }
+        spans.into()
     }
 }
 
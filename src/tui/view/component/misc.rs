@@ -2,28 +2,40 @@
 //! generic/utility, but don't fall into a clear category.
 
 use crate::{
+    collection::RecipeId,
+    http::RequestId,
     template::{Prompt, PromptChannel},
-    tui::view::{
-        common::{
-            button::ButtonGroup,
-            modal::{IntoModal, Modal},
-            text_box::TextBox,
+    tui::{
+        context::TuiContext,
+        input::Action,
+        view::{
+            common::{
+                button::ButtonGroup,
+                modal::{IntoModal, Modal},
+                text_box::TextBox,
+            },
+            component::Component,
+            draw::{Draw, DrawMetadata, Generate},
+            event::{Event, EventHandler, Update},
+            state::{fixed_select::FixedSelect, Notification},
+            Confirm, ViewContext,
         },
-        component::Component,
-        draw::{Draw, DrawMetadata, Generate},
-        event::{Event, EventHandler, Update},
-        state::{fixed_select::FixedSelect, Notification},
-        Confirm, ViewContext,
     },
 };
 use derive_more::Display;
 use ratatui::{
     prelude::Constraint,
-    text::Line,
-    widgets::{Paragraph, Wrap},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
     Frame,
 };
-use std::{cell::Cell, fmt::Debug, rc::Rc};
+use reqwest::StatusCode;
+use std::{
+    cell::Cell,
+    fmt::Debug,
+    rc::Rc,
+    time::{Duration, Instant},
+};
 use strum::{EnumCount, EnumIter};
 
 #[derive(Debug)]
@@ -240,3 +252,96 @@ impl Draw for NotificationText {
         );
     }
 }
+
+/// Short-lived popup announcing that a request finished for a recipe other
+/// than the one being viewed. Clicking it (or hitting the bound key) jumps to
+/// the exchange.
+#[derive(Debug)]
+pub struct Toast {
+    request_id: RequestId,
+    recipe_id: RecipeId,
+    recipe_name: String,
+    /// Response status, or `None` if the request failed
+    status: Option<StatusCode>,
+    created: Instant,
+}
+
+/// Local event emitted when the toast is clicked
+#[derive(Debug)]
+pub struct ToastClicked;
+
+impl Toast {
+    /// How long the toast stays on screen
+    const DURATION: Duration = Duration::from_secs(5);
+
+    pub fn new(
+        request_id: RequestId,
+        recipe_id: RecipeId,
+        recipe_name: String,
+        status: Option<StatusCode>,
+    ) -> Self {
+        Self {
+            request_id,
+            recipe_id,
+            recipe_name,
+            status,
+            created: Instant::now(),
+        }
+    }
+
+    pub fn request_id(&self) -> RequestId {
+        self.request_id
+    }
+
+    pub fn recipe_id(&self) -> &RecipeId {
+        &self.recipe_id
+    }
+
+    /// Has the toast been up long enough that it should disappear?
+    pub fn is_expired(&self) -> bool {
+        self.created.elapsed() >= Self::DURATION
+    }
+
+    /// Width and height needed to draw the toast, including its border
+    pub fn size(&self) -> (u16, u16) {
+        (self.text().width() as u16 + 2, 3)
+    }
+
+    fn text(&self) -> Line<'_> {
+        let tui_context = TuiContext::get();
+        let status = match self.status {
+            Some(status) => status.generate(),
+            None => Span::styled("Failed", tui_context.styles.text.error),
+        };
+        let hint = tui_context
+            .input_engine
+            .add_hint("View", Action::JumpToRequest);
+        Line::from(vec![
+            Span::raw(format!(" {} ", self.recipe_name)),
+            status,
+            Span::raw(format!(" │ {hint} ")),
+        ])
+    }
+}
+
+impl EventHandler for Toast {
+    fn update(&mut self, event: Event) -> Update {
+        if let Some(Action::LeftClick) = event.action() {
+            ViewContext::push_event(Event::new_local(ToastClicked));
+            Update::Consumed
+        } else {
+            Update::Propagate(event)
+        }
+    }
+}
+
+impl Draw for Toast {
+    fn draw(&self, frame: &mut Frame, _: (), metadata: DrawMetadata) {
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(TuiContext::get().styles.pane.border_selected);
+        let area = metadata.area();
+        frame.render_widget(Clear, area);
+        frame.render_widget(Paragraph::new(self.text()).block(block), area);
+    }
+}
@@ -8,10 +8,12 @@ use crate::{
         view::{
             common::actions::ActionsModal,
             component::{
+                code_select::CodeLanguageModal,
+                command_palette::CommandPalette,
                 exchange_pane::{ExchangePane, ExchangePaneProps},
                 help::HelpModal,
-                profile_select::ProfilePane,
-                recipe_list::RecipeListPane,
+                profile_select::{ProfilePane, ProfilePaneProps},
+                recipe_list::{RecipeListPane, RecipeListPaneProps},
                 recipe_pane::{RecipeMenuAction, RecipePane, RecipePaneProps},
             },
             context::{Persisted, PersistedLazy},
@@ -21,7 +23,7 @@ use crate::{
                 fixed_select::{FixedSelect, FixedSelectState},
                 RequestState,
             },
-            Component, ViewContext,
+            Component, ModalPriority, ViewContext,
         },
     },
 };
@@ -34,7 +36,8 @@ use ratatui::{
     Frame,
 };
 use serde::{Deserialize, Serialize};
-use strum::{EnumCount, EnumIter};
+use std::collections::HashSet;
+use strum::{EnumCount, EnumIter, IntoEnumIterator};
 
 /// Primary TUI view, which shows request/response panes
 #[derive(Debug)]
@@ -43,6 +46,8 @@ pub struct PrimaryView {
     selected_pane:
         PersistedLazy<SingletonKey<PrimaryPane>, FixedSelectState<PrimaryPane>>,
     fullscreen_mode: Persisted<FullscreenModeKey>,
+    layout: Persisted<SingletonKey<LayoutPreset>>,
+    pane_sizes: Persisted<SingletonKey<PaneSizes>>,
 
     // Children
     profile_pane: Component<ProfilePane>,
@@ -54,6 +59,8 @@ pub struct PrimaryView {
 #[cfg_attr(test, derive(Clone))]
 pub struct PrimaryViewProps<'a> {
     pub selected_request: Option<&'a RequestState>,
+    /// Recipes with at least one request building or loading
+    pub in_flight_recipes: HashSet<&'a RecipeId>,
 }
 
 /// Selectable panes in the primary view mode
@@ -92,6 +99,79 @@ enum FullscreenMode {
 #[persisted(Option<FullscreenMode>)]
 struct FullscreenModeKey;
 
+/// Arrangement of the primary panes. Cycled through with a keybinding
+#[derive(
+    Copy,
+    Clone,
+    Debug,
+    Default,
+    Display,
+    EnumIter,
+    PartialEq,
+    Serialize,
+    Deserialize,
+)]
+enum LayoutPreset {
+    /// Sidebar on the left, recipe and exchange stacked on the right
+    #[default]
+    Default,
+    /// Sidebar and recipe side-by-side on top, exchange across the bottom
+    #[display("Wide Response")]
+    WideResponse,
+    /// Every pane in a single column
+    Stacked,
+    /// Only the selected pane is shown
+    Zen,
+}
+
+impl LayoutPreset {
+    /// Get the next preset in the cycle
+    fn next(self) -> Self {
+        Self::iter()
+            .cycle()
+            .skip_while(|l| *l != self)
+            .nth(1)
+            .unwrap()
+    }
+}
+
+/// User-adjustable pane sizes, shared by all layout presets
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct PaneSizes {
+    /// Width of the sidebar (profile and recipe list), in cells
+    sidebar: u16,
+    /// Percentage of the recipe/exchange split given to the recipe pane.
+    /// `None` means the selected pane of the two is expanded
+    recipe: Option<u16>,
+}
+
+impl PaneSizes {
+    /// Sidebar width changes in steps of this many cells
+    const SIDEBAR_STEP: i32 = 4;
+    const SIDEBAR_BOUNDS: (u16, u16) = (20, 120);
+    /// Recipe/exchange split changes in steps of this many percentage points
+    const RECIPE_STEP: i32 = 5;
+    const RECIPE_BOUNDS: (u16, u16) = (10, 90);
+}
+
+impl Default for PaneSizes {
+    fn default() -> Self {
+        Self {
+            sidebar: 40,
+            recipe: None,
+        }
+    }
+}
+
+/// Area for each pane. `None` if the pane isn't shown in the current layout
+#[derive(Debug, Default)]
+struct PaneAreas {
+    profile: Option<Rect>,
+    recipe_list: Option<Rect>,
+    recipe: Option<Rect>,
+    exchange: Option<Rect>,
+}
+
 /// Event triggered when selected pane changes, so we can exit fullscreen
 #[derive(Debug)]
 struct PaneChanged;
@@ -116,6 +196,8 @@ impl PrimaryView {
                 selected_pane,
             ),
             fullscreen_mode: Persisted::default(),
+            layout: Persisted::default(),
+            pane_sizes: Persisted::default(),
 
             recipe_list_pane,
             profile_pane,
@@ -130,17 +212,51 @@ impl PrimaryView {
         self.recipe_list_pane.data().selected_recipe()
     }
 
+    /// Get a recipe by ID, if it exists in the collection
+    pub fn recipe(&self, id: &RecipeId) -> Option<&Recipe> {
+        self.recipe_list_pane.data().recipes().get_recipe(id)
+    }
+
     pub fn selected_recipe_id(&self) -> Option<&RecipeId> {
         self.selected_recipe().map(|recipe| &recipe.id)
     }
 
-    /// Which profile in the list is selected? `None` iff the list is empty
+    /// Which profile should be used for the selected recipe? If the recipe
+    /// pins a profile, that's used. Otherwise it's whichever profile is
+    /// selected in the list. `None` if the list is empty, or the pinned
+    /// profile doesn't exist
     pub fn selected_profile(&self) -> Option<&Profile> {
-        self.profile_pane.data().selected_profile()
+        let profile_pane = self.profile_pane.data();
+        match self.pinned_profile_id() {
+            Some(profile_id) => profile_pane
+                .profiles()
+                .iter()
+                .find(|profile| &profile.id == profile_id),
+            None => profile_pane.selected_profile(),
+        }
     }
-    /// ID of the selected profile. `None` iff the list is empty
+
+    /// ID of the profile to use for the selected recipe. See
+    /// [Self::selected_profile]. A pinned profile is returned even if it
+    /// doesn't exist, so that rendering fails instead of silently using no
+    /// profile
     pub fn selected_profile_id(&self) -> Option<&ProfileId> {
-        self.selected_profile().map(|profile| &profile.id)
+        self.pinned_profile_id().or_else(|| {
+            self.profile_pane
+                .data()
+                .selected_profile()
+                .map(|profile| &profile.id)
+        })
+    }
+
+    /// ID of the profile pinned by the selected recipe, if any
+    fn pinned_profile_id(&self) -> Option<&ProfileId> {
+        self.selected_recipe()?.profile.as_ref()
+    }
+
+    /// Open the modal to switch profiles
+    pub fn open_profile_select(&self) {
+        self.profile_pane.data().open_modal()
     }
 
     /// Draw the "normal" view, when nothing is fullscreened
@@ -150,47 +266,124 @@ impl PrimaryView {
         props: PrimaryViewProps,
         area: Rect,
     ) {
-        // Split the main pane horizontally
-        let [left_area, right_area] =
-            Layout::horizontal([Constraint::Max(40), Constraint::Min(40)])
-                .areas(area);
+        let areas = self.pane_areas(area);
 
-        let [profile_area, recipes_area] =
-            Layout::vertical([Constraint::Length(3), Constraint::Min(0)])
-                .areas(left_area);
-        let [recipe_area, request_response_area] =
-            self.get_right_column_layout(right_area);
-
-        self.profile_pane.draw(frame, (), profile_area, true);
-        self.recipe_list_pane.draw(
-            frame,
-            (),
-            recipes_area,
-            self.is_selected(PrimaryPane::RecipeList),
-        );
+        if let Some(area) = areas.profile {
+            self.profile_pane.draw(
+                frame,
+                ProfilePaneProps {
+                    pinned_profile_id: self.pinned_profile_id(),
+                },
+                area,
+                true,
+            );
+        }
+        if let Some(area) = areas.recipe_list {
+            self.recipe_list_pane.draw(
+                frame,
+                RecipeListPaneProps {
+                    in_flight_recipes: &props.in_flight_recipes,
+                },
+                area,
+                self.is_selected(PrimaryPane::RecipeList),
+            );
+        }
+        if let Some(area) = areas.recipe {
+            self.recipe_pane.draw(
+                frame,
+                RecipePaneProps {
+                    selected_recipe: self.selected_recipe(),
+                    selected_profile_id: self.selected_profile_id(),
+                },
+                area,
+                self.is_selected(PrimaryPane::Recipe),
+            );
+        }
+        if let Some(area) = areas.exchange {
+            self.exchange_pane.draw(
+                frame,
+                ExchangePaneProps {
+                    selected_recipe_node: self
+                        .recipe_list_pane
+                        .data()
+                        .selected_node(),
+                    request_state: props.selected_request,
+                },
+                area,
+                self.is_selected(PrimaryPane::Exchange),
+            );
+        }
+    }
 
-        self.recipe_pane.draw(
-            frame,
-            RecipePaneProps {
-                selected_recipe: self.selected_recipe(),
-                selected_profile_id: self.selected_profile_id(),
-            },
-            recipe_area,
-            self.is_selected(PrimaryPane::Recipe),
-        );
+    /// Get the area for each pane, based on the layout preset and pane sizes
+    fn pane_areas(&self, area: Rect) -> PaneAreas {
+        let sidebar = Constraint::Max(self.pane_sizes.sidebar);
+        match *self.layout {
+            LayoutPreset::Default => {
+                let [left_area, right_area] =
+                    Layout::horizontal([sidebar, Constraint::Min(40)])
+                        .areas(area);
+                let [profile, recipe_list] = Self::split_sidebar(left_area);
+                let [recipe, exchange] = self.split_recipe_exchange(right_area);
+                PaneAreas {
+                    profile: Some(profile),
+                    recipe_list: Some(recipe_list),
+                    recipe: Some(recipe),
+                    exchange: Some(exchange),
+                }
+            }
+            LayoutPreset::WideResponse => {
+                let [top_area, exchange] = self.split_recipe_exchange(area);
+                let [left_area, recipe] =
+                    Layout::horizontal([sidebar, Constraint::Min(40)])
+                        .areas(top_area);
+                let [profile, recipe_list] = Self::split_sidebar(left_area);
+                PaneAreas {
+                    profile: Some(profile),
+                    recipe_list: Some(recipe_list),
+                    recipe: Some(recipe),
+                    exchange: Some(exchange),
+                }
+            }
+            LayoutPreset::Stacked => {
+                let [profile, recipe_list, rest] = Layout::vertical([
+                    Constraint::Length(3),
+                    Constraint::Percentage(20),
+                    Constraint::Min(0),
+                ])
+                .areas(area);
+                let [recipe, exchange] = self.split_recipe_exchange(rest);
+                PaneAreas {
+                    profile: Some(profile),
+                    recipe_list: Some(recipe_list),
+                    recipe: Some(recipe),
+                    exchange: Some(exchange),
+                }
+            }
+            LayoutPreset::Zen => {
+                let area = Some(area);
+                match self.selected_pane.selected() {
+                    PrimaryPane::RecipeList => PaneAreas {
+                        recipe_list: area,
+                        ..Default::default()
+                    },
+                    PrimaryPane::Recipe => PaneAreas {
+                        recipe: area,
+                        ..Default::default()
+                    },
+                    PrimaryPane::Exchange => PaneAreas {
+                        exchange: area,
+                        ..Default::default()
+                    },
+                }
+            }
+        }
+    }
 
-        self.exchange_pane.draw(
-            frame,
-            ExchangePaneProps {
-                selected_recipe_node: self
-                    .recipe_list_pane
-                    .data()
-                    .selected_node(),
-                request_state: props.selected_request,
-            },
-            request_response_area,
-            self.is_selected(PrimaryPane::Exchange),
-        );
+    /// Split the sidebar into the profile and recipe list panes
+    fn split_sidebar(area: Rect) -> [Rect; 2] {
+        Layout::vertical([Constraint::Length(3), Constraint::Min(0)])
+            .areas(area)
     }
 
     /// Is the given pane selected?
@@ -218,47 +411,120 @@ impl PrimaryView {
         }
     }
 
-    /// Get layout for the right column of panes
-    fn get_right_column_layout(&self, area: Rect) -> [Rect; 2] {
-        // Split right column vertically. Expand the currently selected pane
-        let (top, bottom) = match self.selected_pane.selected() {
-            PrimaryPane::Recipe => (2, 1),
-            PrimaryPane::Exchange | PrimaryPane::RecipeList => (1, 2),
-        };
-        let denominator = top + bottom;
+    /// Split an area vertically between the recipe and exchange panes
+    fn split_recipe_exchange(&self, area: Rect) -> [Rect; 2] {
+        let recipe = self.recipe_percentage();
         Layout::vertical([
-            Constraint::Ratio(top, denominator),
-            Constraint::Ratio(bottom, denominator),
+            Constraint::Percentage(recipe),
+            Constraint::Percentage(100 - recipe),
         ])
         .areas(area)
     }
 
+    /// Percentage of the recipe/exchange split given to the recipe pane. If
+    /// the user hasn't resized it, expand the selected pane
+    fn recipe_percentage(&self) -> u16 {
+        self.pane_sizes
+            .recipe
+            .unwrap_or(match self.selected_pane.selected() {
+                PrimaryPane::Recipe => 67,
+                PrimaryPane::Exchange | PrimaryPane::RecipeList => 33,
+            })
+    }
+
+    /// Grow (positive) or shrink (negative) the selected pane by some number
+    /// of steps. The recipe list changes the sidebar width, and the recipe and
+    /// exchange panes trade space with each other.
+    fn resize_selected_pane(&mut self, steps: i32) {
+        /// Adjust a size by some amount, staying within bounds
+        fn adjust(value: u16, delta: i32, (min, max): (u16, u16)) -> u16 {
+            (value as i32 + delta).clamp(min as i32, max as i32) as u16
+        }
+
+        let recipe = self.recipe_percentage();
+        let mut sizes = *self.pane_sizes;
+        match self.selected_pane.selected() {
+            PrimaryPane::RecipeList => {
+                sizes.sidebar = adjust(
+                    sizes.sidebar,
+                    steps * PaneSizes::SIDEBAR_STEP,
+                    PaneSizes::SIDEBAR_BOUNDS,
+                );
+            }
+            PrimaryPane::Recipe => {
+                sizes.recipe = Some(adjust(
+                    recipe,
+                    steps * PaneSizes::RECIPE_STEP,
+                    PaneSizes::RECIPE_BOUNDS,
+                ));
+            }
+            PrimaryPane::Exchange => {
+                sizes.recipe = Some(adjust(
+                    recipe,
+                    -steps * PaneSizes::RECIPE_STEP,
+                    PaneSizes::RECIPE_BOUNDS,
+                ));
+            }
+        }
+        *self.pane_sizes = sizes;
+    }
+
+    /// Switch to the next layout preset
+    fn cycle_layout(&mut self) {
+        let layout = self.layout.next();
+        *self.layout = layout;
+        ViewContext::send_message(Message::Notify(format!("Layout: {layout}")));
+    }
+
     /// Handle menu actions for recipe list or detail panes. We handle this here
     /// for code de-duplication, and because we have access to all the needed
     /// context.
-    fn handle_recipe_menu_action(&self, action: RecipeMenuAction) {
-        // If no recipes are available, we can't do anything
-        let Some(recipe_id) = self.selected_recipe_id().cloned() else {
-            return;
+    fn handle_recipe_menu_action(&mut self, action: RecipeMenuAction) {
+        let message: fn(RequestConfig) -> Message = match action {
+            RecipeMenuAction::SendWithEdits => Message::HttpBeginRequestEdit,
+            RecipeMenuAction::CopyUrl => Message::CopyRequestUrl,
+            RecipeMenuAction::CopyBody => Message::CopyRequestBody,
+            RecipeMenuAction::CopyCurl => Message::CopyRequestCurl,
+            // The user has to pick a language before we can generate anything
+            RecipeMenuAction::CopyCode => {
+                if let Some(request_config) = self.request_config() {
+                    ViewContext::open_modal(
+                        CodeLanguageModal::new(request_config),
+                        ModalPriority::Low,
+                    );
+                }
+                return;
+            }
+            // These only modify the recipe list
+            RecipeMenuAction::ToggleFavorite => {
+                self.recipe_list_pane.data_mut().toggle_favorite();
+                return;
+            }
+            RecipeMenuAction::ToggleFavoritesOnly => {
+                self.recipe_list_pane.data_mut().toggle_favorites_only();
+                return;
+            }
+            RecipeMenuAction::ToggleSortByRecent => {
+                self.recipe_list_pane.data_mut().toggle_sort_by_recent();
+                return;
+            }
         };
 
-        let request_config = RequestConfig {
+        // If no recipes are available, we can't do anything
+        if let Some(request_config) = self.request_config() {
+            ViewContext::send_message(message(request_config));
+        }
+    }
+
+    /// Get the config needed to build a request for the selected recipe.
+    /// `None` if no recipe is selected
+    fn request_config(&self) -> Option<RequestConfig> {
+        let recipe_id = self.selected_recipe_id().cloned()?;
+        Some(RequestConfig {
             profile_id: self.selected_profile_id().cloned(),
             recipe_id,
             options: self.recipe_pane.data().build_options(),
-        };
-        let message = match action {
-            RecipeMenuAction::CopyUrl => {
-                Message::CopyRequestUrl(request_config)
-            }
-            RecipeMenuAction::CopyBody => {
-                Message::CopyRequestBody(request_config)
-            }
-            RecipeMenuAction::CopyCurl => {
-                Message::CopyRequestCurl(request_config)
-            }
-        };
-        ViewContext::send_message(message);
+        })
     }
 }
 
@@ -293,11 +559,16 @@ impl EventHandler for PrimaryView {
                 Action::OpenHelp => {
                     ViewContext::open_modal_default::<HelpModal>();
                 }
+                Action::CommandPalette => ViewContext::open_modal(
+                    CommandPalette::new(
+                        self.recipe_list_pane.data().recipes(),
+                        self.profile_pane.data().profiles(),
+                    ),
+                    ModalPriority::Low,
+                ),
 
                 // Pane hotkeys
-                Action::SelectProfileList => {
-                    self.profile_pane.data().open_modal()
-                }
+                Action::SelectProfileList => self.open_profile_select(),
                 Action::SelectRecipeList => {
                     self.selected_pane.select(&PrimaryPane::RecipeList)
                 }
@@ -322,6 +593,10 @@ impl EventHandler for PrimaryView {
                         PrimaryPane::RecipeList => {}
                     }
                 }
+                Action::CycleLayout => self.cycle_layout(),
+                Action::CycleProfile => self.profile_pane.data_mut().cycle(),
+                Action::GrowPane => self.resize_selected_pane(1),
+                Action::ShrinkPane => self.resize_selected_pane(-1),
                 // Exit fullscreen
                 Action::Cancel if self.fullscreen_mode.is_some() => {
                     *self.fullscreen_mode = None;
@@ -398,13 +673,14 @@ mod tests {
     use super::*;
     use crate::{
         http::BuildOptions,
-        test_util::{assert_matches, Factory},
+        test_util::{assert_matches, by_id, Factory},
         tui::{
             message::{Message, RequestConfig},
             test_util::{harness, TestHarness},
             view::test_util::TestComponent,
         },
     };
+    use crossterm::event::KeyCode;
     use persisted::PersistedStore;
     use rstest::{fixture, rstest};
 
@@ -419,6 +695,7 @@ mod tests {
             PrimaryView::new(&collection),
             PrimaryViewProps {
                 selected_request: None,
+                in_flight_recipes: HashSet::new(),
             },
         );
         // Clear template preview messages so we can test what we want
@@ -475,6 +752,30 @@ mod tests {
         );
     }
 
+    /// Test "Send with Edits" action, which is available via the Recipe List
+    /// or Recipe panes
+    #[rstest]
+    fn test_send_with_edits(
+        mut component: TestComponent<PrimaryView, PrimaryViewProps<'static>>,
+    ) {
+        component
+            .update_draw(Event::new_local(RecipeMenuAction::SendWithEdits))
+            .assert_empty();
+
+        let request_config = assert_matches!(
+            component.harness_mut().pop_message_now(),
+            Message::HttpBeginRequestEdit(request_config) => request_config,
+        );
+        assert_eq!(
+            request_config,
+            RequestConfig {
+                recipe_id: "recipe1".into(),
+                profile_id: Some("profile1".into()),
+                options: BuildOptions::default()
+            }
+        );
+    }
+
     /// Test "Copy Body" action, which is available via the Recipe List or
     /// Recipe panes
     #[rstest]
@@ -522,4 +823,118 @@ mod tests {
             }
         );
     }
+
+    /// Resizing changes the sidebar for the recipe list, and the
+    /// recipe/exchange split for the other panes
+    #[rstest]
+    fn test_resize(
+        mut component: TestComponent<PrimaryView, PrimaryViewProps<'static>>,
+    ) {
+        component.send_key(KeyCode::Char('+')).assert_empty();
+        assert_eq!(component.data().pane_sizes.sidebar, 44);
+
+        // Recipe pane starts expanded
+        component.send_key(KeyCode::Tab).assert_empty();
+        component.send_key(KeyCode::Char('-')).assert_empty();
+        assert_eq!(component.data().pane_sizes.recipe, Some(62));
+
+        // Growing the exchange pane shrinks the recipe pane
+        component.send_key(KeyCode::Tab).assert_empty();
+        component.send_key(KeyCode::Char('+')).assert_empty();
+        assert_eq!(component.data().pane_sizes.recipe, Some(57));
+    }
+
+    /// Cycle through layouts, wrapping back to the start
+    #[rstest]
+    fn test_cycle_layout(
+        mut component: TestComponent<PrimaryView, PrimaryViewProps<'static>>,
+    ) {
+        for expected in [
+            LayoutPreset::WideResponse,
+            LayoutPreset::Stacked,
+            LayoutPreset::Zen,
+            LayoutPreset::Default,
+        ] {
+            component.send_key(KeyCode::Char('v')).assert_empty();
+            assert_eq!(*component.data().layout, expected);
+            let message = assert_matches!(
+                component.harness_mut().pop_message_now(),
+                Message::Notify(message) => message,
+            );
+            assert_eq!(message, format!("Layout: {expected}"));
+            component.harness_mut().clear_messages();
+        }
+    }
+    /// Cycle through profiles, wrapping back to the start
+    #[rstest]
+    fn test_cycle_profile(harness: TestHarness) {
+        let collection = Collection {
+            profiles: by_id(["profile1", "profile2"].map(|id| Profile {
+                id: id.into(),
+                ..Profile::factory(())
+            })),
+            ..Collection::factory(())
+        };
+        let mut component = TestComponent::new(
+            harness,
+            PrimaryView::new(&collection),
+            PrimaryViewProps {
+                selected_request: None,
+                in_flight_recipes: HashSet::new(),
+            },
+        );
+
+        for expected in ["profile2", "profile1"] {
+            assert_matches!(
+                component.send_key(KeyCode::Char('P')).events(),
+                [Event::HttpSelectRequest(None)]
+            );
+            assert_eq!(
+                component.data().selected_profile_id(),
+                Some(&ProfileId::from(expected))
+            );
+        }
+    }
+
+    /// A recipe's pinned profile should be used instead of the selected one
+    #[rstest]
+    fn test_pinned_profile(harness: TestHarness) {
+        let collection = Collection {
+            recipes: by_id([Recipe {
+                profile: Some("profile2".into()),
+                ..Recipe::factory(())
+            }])
+            .into(),
+            profiles: by_id(["profile1", "profile2"].map(|id| Profile {
+                id: id.into(),
+                ..Profile::factory(())
+            })),
+            ..Collection::factory(())
+        };
+        let mut component = TestComponent::new(
+            harness,
+            PrimaryView::new(&collection),
+            PrimaryViewProps {
+                selected_request: None,
+                in_flight_recipes: HashSet::new(),
+            },
+        );
+        component.harness_mut().clear_messages();
+        assert_eq!(
+            component
+                .data()
+                .selected_profile()
+                .map(|profile| &profile.id),
+            Some(&ProfileId::from("profile2"))
+        );
+
+        component
+            .update_draw(Event::new_local(RecipeMenuAction::CopyUrl))
+            .assert_empty();
+        let request_config = assert_matches!(
+            component.harness_mut().pop_message_now(),
+            Message::CopyRequestUrl(request_config) => request_config,
+        );
+        assert_eq!(request_config.profile_id, Some("profile2".into()));
+    }
 }
@@ -1,19 +1,25 @@
 //! Components related to the selection of profiles
 
 use crate::{
-    collection::{Profile, ProfileId},
+    collection::{secrets::ENCRYPTED_MASK, Profile, ProfileId},
+    template::Template,
     tui::{
         context::TuiContext,
         input::Action,
         view::{
             common::{
-                list::List, modal::Modal, table::Table,
-                template_preview::TemplatePreview, Pane,
+                filter::{FilterBox, FilterEvent},
+                list::List,
+                modal::Modal,
+                table::Table,
+                template_preview::TemplatePreview,
+                Pane,
             },
             context::PersistedLazy,
             draw::{Draw, DrawMetadata, Generate},
             event::{Event, EventHandler, Update},
             state::{select::SelectState, StateCell},
+            util::matches_filter,
             Component, ModalPriority, ViewContext,
         },
     },
@@ -23,7 +29,7 @@ use itertools::Itertools;
 use persisted::PersistedKey;
 use ratatui::{
     layout::{Constraint, Layout},
-    text::{Line, Text},
+    text::{Line, Span, Text},
     Frame,
 };
 use serde::Serialize;
@@ -60,6 +66,9 @@ pub struct ProfilePane {
     /// into the modal, means duplicating SelectState and cloning the contents
     /// is the best way to go.
     profiles: PersistedLazy<SelectedProfileKey, SelectState<Profile>>,
+    /// Most recent filter text from the modal. The modal is rebuilt each time
+    /// it's opened, so we hang onto this to restore it for the session
+    filter: String,
 }
 
 /// Persisted key for the ID of the selected profile
@@ -79,13 +88,27 @@ impl ProfilePane {
         let profiles = SelectState::builder(profiles).build();
         Self {
             profiles: PersistedLazy::new(SelectedProfileKey, profiles),
+            filter: String::new(),
         }
     }
 
+    /// All profiles in the collection
+    pub fn profiles(&self) -> &[Profile] {
+        self.profiles.items()
+    }
+
     pub fn selected_profile(&self) -> Option<&Profile> {
         self.profiles.selected()
     }
 
+    /// Select the next profile in the list, wrapping around to the first
+    pub fn cycle(&mut self) {
+        self.profiles.next();
+        if let Some(profile) = self.profiles.selected() {
+            on_profile_selected(&profile.id);
+        }
+    }
+
     /// Open the profile list modal
     pub fn open_modal(&self) {
         ViewContext::open_modal(
@@ -93,6 +116,7 @@ impl ProfilePane {
                 // See self.profiles doc comment for why we need to clone
                 self.profiles.items().to_owned(),
                 self.profiles.selected().map(|profile| &profile.id),
+                self.filter.clone(),
             ),
             ModalPriority::Low,
         );
@@ -106,7 +130,9 @@ impl EventHandler for ProfilePane {
         } else if let Some(SelectProfile(profile_id)) = event.local() {
             // Handle message from the modal
             self.profiles.select(profile_id);
-            ViewContext::push_event(Event::HttpSelectRequest(None));
+            on_profile_selected(profile_id);
+        } else if let Some(ProfileFilter(filter)) = event.local() {
+            self.filter.clone_from(filter);
         } else {
             return Update::Propagate(event);
         }
@@ -114,8 +140,31 @@ impl EventHandler for ProfilePane {
     }
 }
 
-impl Draw for ProfilePane {
-    fn draw(&self, frame: &mut Frame, _: (), metadata: DrawMetadata) {
+/// Side effects of switching profiles, whether from the modal or by cycling
+fn on_profile_selected(profile_id: &ProfileId) {
+    // Keep a log of profile switches, so history can tell which profile was
+    // selected at any point in time
+    ViewContext::with_database(|database| {
+        database.insert_profile_selection(Some(profile_id))
+    })
+    // Error is already traced in the DB, nothing to do with it here
+    .ok();
+    ViewContext::push_event(Event::HttpSelectRequest(None));
+}
+
+pub struct ProfilePaneProps<'a> {
+    /// Profile pinned by the selected recipe, which is used instead of the
+    /// selected profile
+    pub pinned_profile_id: Option<&'a ProfileId>,
+}
+
+impl<'a> Draw<ProfilePaneProps<'a>> for ProfilePane {
+    fn draw(
+        &self,
+        frame: &mut Frame,
+        props: ProfilePaneProps<'a>,
+        metadata: DrawMetadata,
+    ) {
         let title = TuiContext::get()
             .input_engine
             .add_hint("Profile", Action::SelectProfileList);
@@ -127,34 +176,70 @@ impl Draw for ProfilePane {
         frame.render_widget(&block, metadata.area());
         let area = block.inner(metadata.area());
 
-        frame.render_widget(
-            if let Some(profile) = self.selected_profile() {
-                profile.name()
-            } else {
-                "No profiles defined"
-            },
-            area,
-        );
+        let text = if let Some(profile_id) = props.pinned_profile_id {
+            // Make it obvious that the profile list isn't being used
+            let name = self
+                .profiles()
+                .iter()
+                .find(|profile| &profile.id == profile_id)
+                .map(Profile::name)
+                .unwrap_or(profile_id.as_str());
+            Line::from(vec![
+                Span::styled(name, TuiContext::get().styles.text.primary),
+                " (pinned by recipe)".into(),
+            ])
+        } else if let Some(profile) = self.selected_profile() {
+            profile.name().into()
+        } else {
+            "No profiles defined".into()
+        };
+        frame.render_widget(text, area);
     }
 }
 
-/// Local event to pass selected profile ID from modal back to the parent
+/// Local event to pass selected profile ID from a modal back to the parent
 #[derive(Debug)]
-struct SelectProfile(ProfileId);
+pub struct SelectProfile(pub ProfileId);
+
+/// Local event to pass the modal's filter text back to the parent, so it can be
+/// restored next time the modal is opened
+#[derive(Debug)]
+struct ProfileFilter(String);
 
 /// Modal to allow user to select a profile from a list and preview profile
 /// fields
 #[derive(Debug)]
 pub struct ProfileListModal {
+    /// All profiles. The select state only holds the ones matching the filter
+    profiles: Vec<Profile>,
     select: Component<SelectState<Profile>>,
     detail: Component<ProfileDetail>,
+    /// Inline filter to narrow down the list
+    filter: FilterBox,
 }
 
 impl ProfileListModal {
     pub fn new(
         profiles: Vec<Profile>,
         selected_profile: Option<&ProfileId>,
+        filter: String,
     ) -> Self {
+        let select =
+            Self::build_select_state(&profiles, &filter, selected_profile);
+        Self {
+            profiles,
+            select: select.into(),
+            detail: Default::default(),
+            filter: FilterBox::new("Filter profiles", filter),
+        }
+    }
+
+    /// Build the list of profiles that match the filter
+    fn build_select_state(
+        profiles: &[Profile],
+        filter: &str,
+        selected_profile: Option<&ProfileId>,
+    ) -> SelectState<Profile> {
         // Loaded request depends on the profile, so refresh on change
         fn on_submit(profile: &mut Profile) {
             // Close the modal *first*, so the parent can handle the
@@ -165,14 +250,27 @@ impl ProfileListModal {
             )));
         }
 
-        let select = SelectState::builder(profiles)
+        let profiles = profiles
+            .iter()
+            .filter(|profile| matches_filter(profile.name(), filter))
+            .cloned()
+            .collect();
+        SelectState::builder(profiles)
             .preselect_opt(selected_profile)
             .on_submit(on_submit)
-            .build();
-        Self {
-            select: select.into(),
-            detail: Default::default(),
-        }
+            .build()
+    }
+
+    /// Rebuild the visible list after the filter changes, and notify the
+    /// parent of the new filter
+    fn apply_filter(&mut self) {
+        let filter = self.filter.text();
+        let selected = self.select.data().selected().map(|p| p.id.clone());
+        *self.select.data_mut() =
+            Self::build_select_state(&self.profiles, filter, selected.as_ref());
+        ViewContext::push_event(Event::new_local(ProfileFilter(
+            filter.to_owned(),
+        )));
     }
 }
 
@@ -187,8 +285,30 @@ impl Modal for ProfileListModal {
 }
 
 impl EventHandler for ProfileListModal {
+    fn update(&mut self, event: Event) -> Update {
+        if let Some(filter_event) = self.filter.update(&event) {
+            match filter_event {
+                FilterEvent::Focus => {}
+                FilterEvent::Change | FilterEvent::Cancel => {
+                    self.apply_filter()
+                }
+                // Enter picks whatever is highlighted, so the user can type
+                // a few characters and hit Enter to switch
+                FilterEvent::Submit => {
+                    self.select.data_mut().submit();
+                }
+            }
+        } else if let Some(Action::Search) = event.action() {
+            self.filter.focus();
+        } else {
+            return Update::Propagate(event);
+        }
+        Update::Consumed
+    }
+
     fn children(&mut self) -> Vec<Component<&mut dyn EventHandler>> {
-        vec![self.select.as_child()]
+        // Filter goes first so it gets first dibs on key events while typing
+        vec![self.filter.as_child(), self.select.as_child()]
     }
 }
 
@@ -196,7 +316,7 @@ impl Draw for ProfileListModal {
     fn draw(&self, frame: &mut Frame, _: (), metadata: DrawMetadata) {
         // Empty state
         let select = self.select.data();
-        if select.items().is_empty() {
+        if self.profiles.is_empty() {
             frame.render_widget(
                 Text::from(vec![
                     "No profiles defined; add one to your collection.".into(),
@@ -207,13 +327,15 @@ impl Draw for ProfileListModal {
             return;
         }
 
-        let [list_area, _, detail_area] = Layout::vertical([
+        let [filter_area, list_area, _, detail_area] = Layout::vertical([
+            Constraint::Length(self.filter.height()),
             Constraint::Length(select.items().len().min(5) as u16),
             Constraint::Length(1), // Padding
             Constraint::Min(0),
         ])
         .areas(metadata.area());
 
+        self.filter.draw(frame, filter_area);
         self.select
             .draw(frame, List::new(select.items()), list_area, true);
         if let Some(profile) = select.selected() {
@@ -227,10 +349,19 @@ impl Draw for ProfileListModal {
     }
 }
 
-/// Display the contents of a profile
+/// Display the contents of a profile. Each field shows its template source
+/// alongside the rendered value, so the user can see what will be sent
 #[derive(Debug, Default)]
 pub struct ProfileDetail {
-    fields: StateCell<ProfileId, Vec<(String, TemplatePreview)>>,
+    fields: StateCell<ProfileId, Vec<ProfileField>>,
+}
+
+/// A single row in the profile detail table
+#[derive(Debug)]
+struct ProfileField {
+    key: String,
+    source: String,
+    value: TemplatePreview,
 }
 
 pub struct ProfileDetailProps<'a> {
@@ -245,7 +376,9 @@ impl<'a> Draw<ProfileDetailProps<'a>> for ProfileDetail {
         metadata: DrawMetadata,
     ) {
         // Whenever the selected profile changes, rebuild the internal state.
-        // This is needed because the template preview rendering is async.
+        // This is needed because the template preview rendering is async. We
+        // only render the profile that's actually on screen, and never
+        // trigger chained requests, since the user is just browsing
         let fields =
             self.fields.get_or_update(props.profile.id.clone(), || {
                 props
@@ -253,24 +386,43 @@ impl<'a> Draw<ProfileDetailProps<'a>> for ProfileDetail {
                     .data
                     .iter()
                     .map(|(key, template)| {
-                        (
-                            key.clone(),
-                            TemplatePreview::new(
-                                template.clone(),
+                        // Don't put decrypted secrets on screen
+                        let template = if props.profile.encrypted.contains(key)
+                        {
+                            Template::raw(ENCRYPTED_MASK.to_owned())
+                        } else {
+                            template.clone()
+                        };
+                        ProfileField {
+                            key: key.clone(),
+                            source: template.to_string(),
+                            value: TemplatePreview::untriggered(
+                                template,
                                 Some(props.profile.id.clone()),
                             ),
-                        )
+                        }
                     })
                     .collect_vec()
             });
 
         let table = Table {
-            header: Some(["Field", "Value"]),
+            header: Some(["Field", "Template", "Value"]),
             rows: fields
                 .iter()
-                .map(|(key, value)| [key.as_str().into(), value.generate()])
+                .map(|field| {
+                    [
+                        field.key.as_str().into(),
+                        field.source.as_str().into(),
+                        field.value.generate(),
+                    ]
+                })
                 .collect_vec(),
             alternate_row_style: true,
+            column_widths: &[
+                Constraint::Percentage(20),
+                Constraint::Percentage(40),
+                Constraint::Percentage(40),
+            ],
             ..Default::default()
         };
         frame.render_widget(table.generate(), metadata.area());
@@ -1,13 +1,17 @@
 //! Request/response body display component
 
 use crate::{
-    http::{Query, ResponseBody},
+    http::{Query, ResponseBody, ResponseContent},
     tui::{
+        context::TuiContext,
         input::Action,
         view::{
             common::{
                 text_box::TextBox,
-                text_window::{TextWindow, TextWindowProps},
+                text_window::{
+                    IndexedText, ScrollKey, TextWindow, TextWindowProps,
+                    WindowText,
+                },
             },
             draw::{Draw, DrawMetadata},
             event::{Event, EventHandler, Update},
@@ -15,26 +19,40 @@ use crate::{
             Component, ViewContext,
         },
     },
-    util::{MaybeStr, ResultExt},
+    util::{truncate_bytes, MaybeStr, ResultExt},
 };
 use anyhow::Context;
+use bytesize::ByteSize;
 use persisted::PersistedContainer;
 use ratatui::{
     layout::{Constraint, Layout},
+    text::{Line, Text},
     Frame,
 };
 use serde_json_path::JsonPath;
-use std::cell::Cell;
+use std::{
+    cell::Cell,
+    ops::Range,
+    sync::{Arc, OnceLock},
+};
 use Debug;
 
+/// Bodies larger than this are queried and prettified in a background task, so
+/// they don't freeze the UI. Smaller bodies are processed during the draw, so
+/// they show up immediately
+const BACKGROUND_THRESHOLD: usize = 100_000;
+
 /// Display response body as text, with a query box to filter it if the body has
 /// been parsed. The query state can be persisted by persisting this entire
 /// container.
 #[derive(Debug)]
 pub struct QueryableBody {
     /// Body text content. State cell allows us to reset this whenever the
-    /// request changes
-    text_window: StateCell<Option<Query>, Component<TextWindow<String>>>,
+    /// query changes or the full body is loaded
+    text_window:
+        StateCell<(Option<Query>, bool), Component<TextWindow<BodyText>>>,
+    /// Show the entire body, even if it's larger than the preview size
+    full_body: bool,
     /// Store whether the body can be queried. True only if it's a recognized
     /// and parsed format
     query_available: Cell<bool>,
@@ -44,6 +62,8 @@ pub struct QueryableBody {
     query: Option<Query>,
     /// Where the user enters their body query
     query_text_box: Component<TextBox>,
+    /// Key to persist the body's scroll position under, if any
+    scroll_key: Option<ScrollKey>,
 }
 
 #[derive(Clone)]
@@ -73,18 +93,37 @@ impl QueryableBody {
             });
         Self {
             text_window: Default::default(),
+            full_body: false,
             query_available: Cell::new(false),
             query_focused: false,
             query: Default::default(),
             query_text_box: text_box.into(),
+            scroll_key: None,
         }
     }
 
-    /// Get visible body text
+    /// Persist the scroll position of the body text under the given key
+    pub fn with_scroll_key(mut self, key: ScrollKey) -> Self {
+        self.scroll_key = Some(key);
+        self
+    }
+
+    /// Show the entire body, even if it's larger than the configured preview
+    /// size. This can be slow for very large bodies
+    pub fn load_full_body(&mut self) {
+        self.full_body = true;
+    }
+
+    /// Get the query currently filtering the body, if any
+    pub fn query(&self) -> Option<&Query> {
+        self.query.as_ref()
+    }
+
+    /// Get visible body text. `None` if the body is still being processed
     pub fn text(&self) -> Option<String> {
-        self.text_window
-            .get()
-            .map(|text_window| text_window.data().text().to_owned())
+        self.text_window.get().and_then(|text_window| {
+            Some(text_window.data().text().get()?.as_str().to_owned())
+        })
     }
 }
 
@@ -134,7 +173,12 @@ impl EventHandler for QueryableBody {
     fn children(&mut self) -> Vec<Component<&mut dyn EventHandler>> {
         [
             Some(self.query_text_box.as_child()),
-            self.text_window.get_mut().map(Component::as_child),
+            // Don't scroll until there's something to scroll, otherwise the
+            // offset would be clamped to the placeholder text
+            self.text_window
+                .get_mut()
+                .filter(|text_window| text_window.data().text().is_ready())
+                .map(Component::as_child),
         ]
         .into_iter()
         .flatten()
@@ -147,30 +191,68 @@ impl<'a> Draw<QueryableBodyProps<'a>> for QueryableBody {
         &self,
         frame: &mut Frame,
         props: QueryableBodyProps,
-        metadata: DrawMetadata,
+        draw_metadata: DrawMetadata,
     ) {
-        // Body can only be queried if it's been parsed
-        let query_available = props.body.parsed().is_some();
+        let context = TuiContext::get();
+        let preview_size = context.config.body_preview_size;
+        let truncated = !self.full_body && props.body.size() > preview_size;
+        // Body can only be queried if it's been parsed, and we're showing all
+        // of it
+        let query_available = props.body.parsed().is_some() && !truncated;
         self.query_available.set(query_available);
 
-        let [body_area, query_area] = Layout::vertical([
+        // Binary bodies get a header describing what's in the hex dump, and
+        // truncated bodies get a notice
+        let mut header: Vec<Line> = Vec::new();
+        if let Some(metadata) = props.body.metadata() {
+            header.push(metadata.to_string().into());
+        }
+        if truncated {
+            header.push(
+                format!(
+                    "Showing first {} of {}; select \"View Full Body\" in \
+                    the actions menu to load everything",
+                    preview_size.to_string_as(false),
+                    props.body.size().to_string_as(false),
+                )
+                .into(),
+            );
+        }
+
+        let [header_area, body_area, query_area] = Layout::vertical([
+            Constraint::Length(header.len() as u16),
             Constraint::Min(0),
             Constraint::Length(if query_available { 1 } else { 0 }),
         ])
-        .areas(metadata.area());
+        .areas(draw_metadata.area());
+
+        frame.render_widget(
+            Text::from(header).style(context.styles.text.highlight),
+            header_area,
+        );
 
         // Draw the body
-        let text = self.text_window.get_or_update(self.query.clone(), || {
-            init_text_window(props.body, self.query.as_ref())
+        let key = (self.query.clone(), self.full_body);
+        let text = self.text_window.get_or_update(key, || {
+            init_text_window(
+                props.body,
+                self.query.as_ref(),
+                truncated.then_some(preview_size),
+                self.scroll_key.clone(),
+            )
         });
-        text.draw(
-            frame,
-            TextWindowProps {
-                has_search_box: query_available,
-            },
-            body_area,
-            true,
-        );
+        if text.data().text().is_ready() {
+            text.draw(
+                frame,
+                TextWindowProps {
+                    has_search_box: query_available,
+                },
+                body_area,
+                true,
+            );
+        } else {
+            frame.render_widget("Loading...", body_area);
+        }
 
         if query_available {
             self.query_text_box
@@ -200,27 +282,91 @@ enum QueryCallback {
     Submit(String),
 }
 
+/// Body text for the text window. For large bodies, this is filled in by a
+/// background task
+#[derive(Debug, Default)]
+struct BodyText(Arc<OnceLock<IndexedText>>);
+
+impl BodyText {
+    /// Text that's available immediately
+    fn ready(text: String) -> Self {
+        Self(Arc::new(OnceLock::from(IndexedText::new(text))))
+    }
+
+    fn get(&self) -> Option<&IndexedText> {
+        self.0.get()
+    }
+
+    fn is_ready(&self) -> bool {
+        self.get().is_some()
+    }
+}
+
+impl WindowText for BodyText {
+    fn dimensions(&self) -> (usize, usize) {
+        self.get().map(IndexedText::dimensions).unwrap_or_default()
+    }
+
+    fn lines(&self, range: Range<usize>) -> Vec<Line<'_>> {
+        self.get().map(|text| text.lines(range)).unwrap_or_default()
+    }
+}
+
+/// Build the text window for a body. If `preview_size` is given, the body is
+/// too large to show in full, so only that much of it will be shown
 fn init_text_window(
     body: &ResponseBody,
     query: Option<&Query>,
-) -> Component<TextWindow<String>> {
-    // Query and prettify text if possible. This involves a lot of cloning
-    // because it makes stuff easier. If it becomes a bottleneck on large
-    // responses it's fixable.
-    let body = body
-        .parsed()
-        .map(|parsed_body| {
-            // Body is a known content type so we parsed it - apply a query if
-            // necessary and prettify the output
-            query
-                .map(|query| query.query(parsed_body).prettify())
-                .unwrap_or_else(|| parsed_body.prettify())
-        })
-        // Content couldn't be parsed, fall back to the raw text
-        // If the text isn't UTF-8, we'll show a placeholder instead
-        .unwrap_or_else(|| format!("{:#}", MaybeStr(body.bytes())));
+    preview_size: Option<ByteSize>,
+    scroll_key: Option<ScrollKey>,
+) -> Component<TextWindow<BodyText>> {
+    let text = if let Some(preview_size) = preview_size {
+        // Skip querying and prettification, because they're what makes large
+        // bodies slow
+        let preview =
+            truncate_bytes(body.bytes(), preview_size.as_u64() as usize);
+        BodyText::ready(format!("{:#}", MaybeStr(preview)))
+    } else if body.bytes().len() > BACKGROUND_THRESHOLD {
+        // Share the raw and parsed body with a background task, so querying
+        // and prettifying don't lock up the UI
+        let bytes = body.shared_bytes();
+        let parsed = body.shared_parsed();
+        let query = query.cloned();
+        let text = BodyText::default();
+        let destination = Arc::clone(&text.0);
+        tokio::task::spawn_blocking(move || {
+            let text = body_text(&bytes, parsed.as_deref(), query.as_ref());
+            // This is the only writer, so this can't fail
+            let _ = destination.set(IndexedText::new(text));
+        });
+        text
+    } else {
+        BodyText::ready(body_text(body.bytes(), body.parsed(), query))
+    };
+
+    let text_window = TextWindow::new(text);
+    match scroll_key {
+        Some(key) => text_window.with_scroll_key(key),
+        None => text_window,
+    }
+    .into()
+}
 
-    TextWindow::new(body).into()
+/// Query and prettify a body if possible. If it couldn't be parsed, fall back
+/// to the raw text. If the text isn't UTF-8, we'll show a placeholder instead
+fn body_text(
+    bytes: &[u8],
+    parsed: Option<&dyn ResponseContent>,
+    query: Option<&Query>,
+) -> String {
+    match parsed {
+        // Body is a known content type so we parsed it - apply a query if
+        // necessary and prettify the output
+        Some(parsed) => query
+            .map(|query| query.query(parsed).prettify())
+            .unwrap_or_else(|| parsed.prettify()),
+        None => format!("{:#}", MaybeStr(bytes)),
+    }
 }
 
 #[cfg(test)]
@@ -230,15 +376,17 @@ mod tests {
         http::ResponseRecord,
         test_util::header_map,
         tui::{
-            context::TuiContext,
             test_util::{harness, TestHarness},
-            view::{context::PersistedLazy, test_util::TestComponent},
+            view::{
+                common::text_window::ScrollOffset, context::PersistedLazy,
+                test_util::TestComponent,
+            },
         },
     };
     use crossterm::event::KeyCode;
     use persisted::{PersistedKey, PersistedStore};
     use ratatui::text::Span;
-    use reqwest::StatusCode;
+    use reqwest::{StatusCode, Version};
     use rstest::{fixture, rstest};
     use serde::Serialize;
 
@@ -246,7 +394,8 @@ mod tests {
 
     /// Style text to match the text window gutter
     fn gutter(text: &str) -> Span {
-        let styles = &TuiContext::get().styles;
+        let tui_context = TuiContext::get();
+        let styles = &tui_context.styles;
         Span::styled(text, styles.text_window.gutter)
     }
 
@@ -254,10 +403,12 @@ mod tests {
     fn json_response() -> ResponseRecord {
         let response = ResponseRecord {
             status: StatusCode::OK,
+            version: Version::HTTP_11,
             headers: header_map([("Content-Type", "application/json")]),
             body: ResponseBody::new(TEXT.into()),
+            redirects: Vec::new(),
         };
-        response.parse_body();
+        response.parse_body(None);
         response
     }
 
@@ -309,7 +460,8 @@ mod tests {
             data.text().as_deref(),
             Some("{\n  \"greeting\": \"hello\"\n}")
         );
-        let styles = &TuiContext::get().styles.text_box;
+        let tui_context = TuiContext::get();
+        let styles = &tui_context.styles.text_box;
         component.assert_buffer_lines([
             vec![gutter("1"), " {                        ".into()],
             vec![gutter("2"), "   \"greeting\": \"hello\"".into()],
@@ -378,4 +530,35 @@ mod tests {
         );
         assert_eq!(component.data().query, Some("$.greeting".parse().unwrap()));
     }
+
+    /// Scroll position should be restored from the DB, and saved on change
+    #[rstest]
+    fn test_persist_scroll(#[with(10, 2)] harness: TestHarness) {
+        let key = ScrollKey::ResponseBody("recipe1".into());
+        ViewContext::store_persisted(&key, ScrollOffset { x: 0, y: 2 });
+
+        let body = ResponseBody::new(b"1\n2\n3\n4\n5".as_slice().into());
+        let mut component = TestComponent::new(
+            harness,
+            QueryableBody::new().with_scroll_key(key.clone()),
+            QueryableBodyProps { body: &body },
+        );
+        let offset = |component: &TestComponent<QueryableBody, _>| {
+            component
+                .data()
+                .text_window
+                .get()
+                .unwrap()
+                .data()
+                .scroll_offset()
+        };
+        assert_eq!(offset(&component), ScrollOffset { x: 0, y: 2 });
+
+        component.send_key(KeyCode::Down).assert_empty();
+        assert_eq!(offset(&component), ScrollOffset { x: 0, y: 3 });
+        assert_eq!(
+            ViewContext::load_persisted(&key),
+            Some(ScrollOffset { x: 0, y: 3 })
+        );
+    }
 }
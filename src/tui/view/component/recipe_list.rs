@@ -6,22 +6,37 @@ use crate::{
         context::TuiContext,
         input::Action,
         view::{
-            common::{actions::ActionsModal, list::List, Pane},
+            common::{
+                actions::ActionsModal,
+                filter::{FilterBox, FilterEvent},
+                list::List,
+                Pane,
+            },
             component::{primary::PrimaryPane, recipe_pane::RecipeMenuAction},
             context::{Persisted, PersistedLazy},
             draw::{Draw, DrawMetadata, Generate},
             event::{Event, EventHandler, Update},
             state::select::SelectState,
-            Component, ViewContext,
+            util::{matches_filter, spinner},
+            Component, RequestState, ViewContext,
         },
     },
+    util::ResultExt,
 };
+use chrono::{DateTime, Utc};
 use derive_more::{Deref, DerefMut};
 use itertools::Itertools;
 use persisted::{PersistedKey, SingletonKey};
-use ratatui::Frame;
+use ratatui::{
+    layout::{Constraint, Layout},
+    text::Text,
+    Frame,
+};
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, HashSet},
+};
 
 /// List/tree of recipes and folders. This is mostly just a list, but with some
 /// extra logic to allow expanding/collapsing nodes. This could be made into a
@@ -50,6 +65,21 @@ pub struct RecipeListPane {
     /// issue though, it just means it'll be pre-collapsed if the user ever
     /// adds the folder back. Not worth working around.
     collapsed: Persisted<SingletonKey<Collapsed>>,
+    /// Set of recipes marked as favorites. Like the collapsed set, this may
+    /// accrue recipes that have since been removed from the collection.
+    favorites: Persisted<SingletonKey<Favorites>>,
+    /// When enabled, only favorite recipes (and their parent folders) are
+    /// visible
+    favorites_only: Persisted<SingletonKey<FavoritesOnly>>,
+    /// When enabled, nodes are ordered by when they were last sent, instead
+    /// of collection order
+    sort_by_recent: Persisted<SingletonKey<SortByRecent>>,
+    /// Start time of the most recent request for each recipe. Loaded from the
+    /// DB on startup, then kept up to date as responses come in
+    send_times: HashMap<RecipeId, DateTime<Utc>>,
+    /// Inline filter to narrow down the visible nodes. The filter is retained
+    /// for the life of the pane, but not persisted between sessions.
+    filter: FilterBox,
 }
 
 /// Persisted key for the ID of the selected recipe
@@ -70,17 +100,44 @@ impl RecipeListPane {
         // recipes
         let collapsed: Persisted<SingletonKey<Collapsed>> =
             Persisted::default();
+        let favorites: Persisted<SingletonKey<Favorites>> =
+            Persisted::default();
+        let favorites_only: Persisted<SingletonKey<FavoritesOnly>> =
+            Persisted::default();
+        let sort_by_recent: Persisted<SingletonKey<SortByRecent>> =
+            Persisted::default();
+        let send_times = ViewContext::with_database(|database| {
+            database.get_recipe_send_times()
+        })
+        .reported(&ViewContext::messages_tx())
+        .unwrap_or_default();
         let persistent = PersistedLazy::new(
             SelectedRecipeKey,
-            collapsed.build_select_state(recipes),
+            collapsed.build_select_state(
+                recipes,
+                "",
+                favorites_only.0.then_some(&*favorites),
+                sort_by_recent.0.then_some(&send_times),
+                None,
+            ),
         );
         Self {
             recipes: recipes.clone(),
             select: persistent.into(),
             collapsed,
+            favorites,
+            favorites_only,
+            sort_by_recent,
+            send_times,
+            filter: FilterBox::new("Filter recipes", String::new()),
         }
     }
 
+    /// All recipes in the collection, regardless of filter/collapse state
+    pub fn recipes(&self) -> &RecipeTree {
+        &self.recipes
+    }
+
     /// Which recipe/folder in the list is selected? `None` iff the list is
     /// empty
     pub fn selected_node(&self) -> Option<&RecipeNode> {
@@ -119,25 +176,104 @@ impl RecipeListPane {
 
         // If we changed the set of what is visible, rebuild the list state
         if changed {
-            let mut new_select_state =
-                self.collapsed.build_select_state(&self.recipes);
-            // Carry over the selection
-            if let Some(selected) = select.selected() {
-                new_select_state.select(selected.id());
-            }
-            **select = new_select_state;
+            self.rebuild_select_state();
         }
 
         changed
     }
+
+    /// Rebuild the list of visible nodes, e.g. after a folder is collapsed or
+    /// the filter changes. The current selection is retained if it's still
+    /// visible.
+    fn rebuild_select_state(&mut self) {
+        let select = self.select.data_mut();
+        // Carry over the selection. If it's been filtered out, the first
+        // visible node is selected instead
+        let selected = select.selected().map(|node| node.id().clone());
+        **select = self.collapsed.build_select_state(
+            &self.recipes,
+            self.filter.text(),
+            self.favorites_only.0.then_some(&*self.favorites),
+            self.sort_by_recent.0.then_some(&self.send_times),
+            selected.as_ref(),
+        );
+    }
+
+    /// Mark the selected recipe as a favorite, or unmark it if it already is
+    /// one. If a folder is selected, do nothing.
+    pub fn toggle_favorite(&mut self) {
+        let Some(recipe_id) = self.selected_recipe().map(|r| r.id.clone())
+        else {
+            return;
+        };
+        if !self.favorites.remove(&recipe_id) {
+            self.favorites.insert(recipe_id);
+        }
+        // Removing a favorite hides it in favorites-only mode
+        if self.favorites_only.0 {
+            self.rebuild_select_state();
+        }
+    }
+
+    /// Toggle between showing all recipes and only favorites
+    pub fn toggle_favorites_only(&mut self) {
+        self.favorites_only.0 = !self.favorites_only.0;
+        self.rebuild_select_state();
+    }
+
+    /// Toggle between collection order and most-recently-sent order
+    pub fn toggle_sort_by_recent(&mut self) {
+        self.sort_by_recent.0 = !self.sort_by_recent.0;
+        self.rebuild_select_state();
+    }
+
+    /// Select a node by ID. The filter is cleared and all of the node's
+    /// ancestors are expanded, to make sure it's visible
+    fn select_node(&mut self, id: &RecipeId) {
+        if let Some(lookup_key) = self.recipes.get_lookup_key(id) {
+            for ancestor in lookup_key.as_slice() {
+                self.collapsed.remove(ancestor);
+            }
+        }
+        self.filter.clear();
+        self.rebuild_select_state();
+        self.select.data_mut().select(id);
+    }
 }
 
 impl EventHandler for RecipeListPane {
     fn update(&mut self, event: Event) -> Update {
+        if let Some(filter_event) = self.filter.update(&event) {
+            if let FilterEvent::Change | FilterEvent::Cancel = filter_event {
+                self.rebuild_select_state();
+            }
+            return Update::Consumed;
+        }
+        // Track send times for sorting. The root needs this event too, so
+        // don't consume it
+        if let Event::HttpSetState(RequestState::Response { exchange }) = &event
+        {
+            self.send_times.insert(
+                exchange.request.recipe_id.clone(),
+                exchange.start_time,
+            );
+            if self.sort_by_recent.0 {
+                self.rebuild_select_state();
+            }
+            return Update::Propagate(event);
+        }
+        if let Some(SelectRecipe(id)) = event.local() {
+            let id = id.clone();
+            self.select_node(&id);
+            ViewContext::push_event(Event::new_local(PrimaryPane::RecipeList));
+            return Update::Consumed;
+        }
+
         let Some(action) = event.action() else {
             return Update::Propagate(event);
         };
         match action {
+            Action::Search => self.filter.focus(),
             Action::LeftClick => {
                 ViewContext::push_event(Event::new_local(
                     PrimaryPane::RecipeList,
@@ -163,18 +299,42 @@ impl EventHandler for RecipeListPane {
     }
 
     fn children(&mut self) -> Vec<Component<&mut dyn EventHandler>> {
-        vec![self.select.as_child()]
+        // Filter goes first so it gets first dibs on key events while typing
+        vec![self.filter.as_child(), self.select.as_child()]
     }
 }
 
-impl Draw for RecipeListPane {
-    fn draw(&self, frame: &mut Frame, _: (), metadata: DrawMetadata) {
+pub struct RecipeListPaneProps<'a> {
+    /// Recipes with a request building or loading, which get a spinner
+    pub in_flight_recipes: &'a HashSet<&'a RecipeId>,
+}
+
+impl<'a> Draw<RecipeListPaneProps<'a>> for RecipeListPane {
+    fn draw(
+        &self,
+        frame: &mut Frame,
+        props: RecipeListPaneProps<'a>,
+        metadata: DrawMetadata,
+    ) {
         let select = self.select.data();
         let context = TuiContext::get();
 
-        let title = context
-            .input_engine
-            .add_hint("Recipes", Action::SelectRecipeList);
+        let modes = [
+            (self.favorites_only.0, "Favorites"),
+            (self.sort_by_recent.0, "Recent"),
+        ]
+        .into_iter()
+        .filter(|(enabled, _)| *enabled)
+        .map(|(_, mode)| mode)
+        .join(", ");
+        let title = context.input_engine.add_hint(
+            if modes.is_empty() {
+                "Recipes".to_owned()
+            } else {
+                format!("Recipes ({modes})")
+            },
+            Action::SelectRecipeList,
+        );
         let block = Pane {
             title: &title,
             has_focus: metadata.has_focus(),
@@ -183,52 +343,114 @@ impl Draw for RecipeListPane {
         let area = block.inner(metadata.area());
         frame.render_widget(block, metadata.area());
 
-        // We have to build this manually instead of using our own List type,
-        // because we need outside context during the render
-        let items = select
-            .items()
-            .iter()
-            .map(|node| {
-                let (icon, name) = match node {
-                    RecipeNode::Folder(folder) => {
-                        let icon = if self.collapsed.is_collapsed(&folder.id) {
-                            "▶"
-                        } else {
-                            "▼"
-                        };
-                        (icon, folder.name())
-                    }
-                    RecipeNode::Recipe(recipe) => ("", recipe.name()),
+        let [filter_area, list_area] = Layout::vertical([
+            Constraint::Length(self.filter.height()),
+            Constraint::Min(0),
+        ])
+        .areas(area);
+        let filtering = !self.filter.text().is_empty();
+
+        // Items are generated lazily, so only the visible ones do any work
+        let items = select.items().iter().map(|node| RecipeListItem {
+            node,
+            recipes: &self.recipes,
+            collapsed: &self.collapsed,
+            favorite: self.favorites.contains(node.id()),
+            filtering,
+            in_flight: props.in_flight_recipes.contains(node.id()),
+        });
+
+        self.select.draw(frame, List::new(items), list_area, true);
+        self.filter.draw(frame, filter_area);
+    }
+}
+
+/// A single row in the recipe list. This holds references to everything needed
+/// to render the row, so the work can be deferred until we know the row is
+/// visible.
+struct RecipeListItem<'a> {
+    node: &'a RecipeNode,
+    recipes: &'a RecipeTree,
+    collapsed: &'a Collapsed,
+    /// Is this recipe marked as a favorite?
+    favorite: bool,
+    /// Is a filter applied? Collapse state is ignored while filtering
+    filtering: bool,
+    /// Does this recipe have a request building or loading?
+    in_flight: bool,
+}
+
+impl<'a> Generate for RecipeListItem<'a> {
+    type Output<'this> = Text<'this> where Self: 'this;
+
+    fn generate<'this>(self) -> Self::Output<'this>
+    where
+        Self: 'this,
+    {
+        let node = self.node;
+        let (icon, name) = match node {
+            RecipeNode::Folder(folder) => {
+                let icon = if !self.filtering
+                    && self.collapsed.is_collapsed(&folder.id)
+                {
+                    "▶"
+                } else {
+                    "▼"
                 };
-                let depth = self
-                    .recipes
-                    .get_lookup_key(node.id())
-                    .unwrap_or_else(|| {
-                        panic!("Recipe node {} is not in tree", node.id())
-                    })
-                    .as_slice()
-                    .len()
-                    - 1;
-
-                // Apply indentation
-                format!(
-                    "{indent:width$}{icon}{name}",
-                    indent = "",
-                    width = depth
-                )
+                (icon, folder.name())
+            }
+            RecipeNode::Recipe(recipe) => ("", recipe.name()),
+        };
+        let depth = self
+            .recipes
+            .get_lookup_key(node.id())
+            .unwrap_or_else(|| {
+                panic!("Recipe node {} is not in tree", node.id())
             })
-            .collect_vec();
+            .as_slice()
+            .len()
+            - 1;
 
-        self.select.draw(frame, List::new(items), area, true);
+        // Apply indentation
+        let mut text =
+            format!("{indent:width$}{icon}{name}", indent = "", width = depth);
+        if self.favorite {
+            text.push_str(" ★");
+        }
+        if self.in_flight {
+            text.push(' ');
+            text.push(spinner());
+        }
+        text.into()
     }
 }
 
+/// Local event to select a recipe from outside the list, e.g. from the command
+/// palette
+#[derive(Debug)]
+pub struct SelectRecipe(pub RecipeId);
+
 /// Set of collapsed folders. Newtype allows us to encapsulate some extra
 /// functionality
 #[derive(Debug, Default, Deref, DerefMut, Serialize, Deserialize)]
 #[serde(transparent)]
 struct Collapsed(HashSet<RecipeId>);
 
+/// Set of recipes marked as favorites
+#[derive(Debug, Default, Deref, DerefMut, Serialize, Deserialize)]
+#[serde(transparent)]
+struct Favorites(HashSet<RecipeId>);
+
+/// Are only favorite recipes visible in the list?
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+struct FavoritesOnly(bool);
+
+/// Is the recipe list sorted by most recently sent?
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+struct SortByRecent(bool);
+
 /// Ternary state for modifying node collapse state
 enum CollapseState {
     Expand,
@@ -252,10 +474,17 @@ impl Collapsed {
         !ancestors.iter().any(|id| self.is_collapsed(id))
     }
 
-    /// Construct select list based on which nodes are currently visible
+    /// Construct select list based on which nodes are currently visible. If a
+    /// filter or set of favorites is given, collapse state is ignored and only
+    /// nodes related to a match are visible. If send times are given, nodes
+    /// are sorted by them. The given node is preselected if it's visible.
     fn build_select_state(
         &self,
         recipes: &RecipeTree,
+        filter: &str,
+        favorites: Option<&Favorites>,
+        send_times: Option<&HashMap<RecipeId, DateTime<Utc>>>,
+        selected: Option<&RecipeId>,
     ) -> SelectState<RecipeNode> {
         // When highlighting a new recipe, load it from the repo
         fn on_select(_: &mut RecipeNode) {
@@ -263,12 +492,241 @@ impl Collapsed {
             ViewContext::push_event(Event::HttpSelectRequest(None));
         }
 
-        let items = recipes
-            .iter()
-            // Filter out hidden nodes
-            .filter(|(lookup_key, _)| self.is_visible(lookup_key))
-            .map(|(_, node)| node.clone())
-            .collect();
-        SelectState::builder(items).on_select(on_select).build()
+        let items = if let Some(favorites) = favorites {
+            filter_favorites(recipes, favorites, filter)
+        } else if filter.trim().is_empty() {
+            recipes
+                .iter()
+                // Filter out hidden nodes
+                .filter(|(lookup_key, _)| self.is_visible(lookup_key))
+                .map(|(_, node)| node.clone())
+                .collect()
+        } else {
+            filter_nodes(recipes, filter)
+        };
+        let items = match send_times {
+            Some(send_times) => sort_by_recent(recipes, send_times, items),
+            None => items,
+        };
+        SelectState::builder(items)
+            .preselect_opt(selected)
+            .on_select(on_select)
+            .build()
+    }
+}
+
+/// Get all nodes that match a filter. Ancestors of each match are included so
+/// it's shown in context, and descendants are included so that matching a
+/// folder shows its contents.
+fn filter_nodes(recipes: &RecipeTree, filter: &str) -> Vec<RecipeNode> {
+    filter_nodes_by(recipes, |node| node_matches(node, filter))
+}
+
+/// Does a node match a filter? The filter is split into whitespace-separated
+/// terms. Terms starting with `#` match a recipe tag exactly (but
+/// case-insensitive), and the rest are fuzzy-matched against the name. A node
+/// must match every tag, so folders only match a filter without tags.
+fn node_matches(node: &RecipeNode, filter: &str) -> bool {
+    let (tags, terms): (Vec<&str>, Vec<&str>) = filter
+        .split_whitespace()
+        .partition(|term| term.starts_with('#'));
+    let has_tags = tags
+        .iter()
+        .map(|tag| &tag[1..])
+        // Don't filter anything out while the user is starting to type a tag
+        .filter(|tag| !tag.is_empty())
+        .all(|tag| {
+            node.recipe().is_some_and(|recipe| {
+                recipe
+                    .tags
+                    .iter()
+                    .any(|recipe_tag| recipe_tag.eq_ignore_ascii_case(tag))
+            })
+        });
+    has_tags && matches_filter(node_name(node), &terms.concat())
+}
+
+/// Get all favorite recipes, along with their ancestors. If a filter is given,
+/// only favorites whose name matches it are included.
+fn filter_favorites(
+    recipes: &RecipeTree,
+    favorites: &Favorites,
+    filter: &str,
+) -> Vec<RecipeNode> {
+    filter_nodes_by(recipes, |node| {
+        node.recipe().is_some()
+            && favorites.contains(node.id())
+            && node_matches(node, filter)
+    })
+}
+
+/// Get all nodes matching a predicate, plus the ancestors and descendants of
+/// each match
+fn filter_nodes_by(
+    recipes: &RecipeTree,
+    is_match: impl Fn(&RecipeNode) -> bool,
+) -> Vec<RecipeNode> {
+    let matched: HashSet<&RecipeId> = recipes
+        .iter()
+        .filter(|(_, node)| is_match(node))
+        .map(|(_, node)| node.id())
+        .collect();
+    // Lookup keys include the node itself, so this has the matches too
+    let ancestors: HashSet<RecipeId> = recipes
+        .iter()
+        .filter(|(_, node)| matched.contains(node.id()))
+        .flat_map(|(lookup_key, _)| lookup_key.as_slice().to_vec())
+        .collect();
+    recipes
+        .iter()
+        .filter(|(lookup_key, node)| {
+            ancestors.contains(node.id())
+                || lookup_key.as_slice().iter().any(|id| matched.contains(id))
+        })
+        .map(|(_, node)| node.clone())
+        .collect()
+}
+
+/// Sort nodes so the most recently sent come first. Each folder is as recent as
+/// its most recently sent descendant, and children stay grouped under their
+/// parent. Nodes that have never been sent keep collection order, after
+/// everything that has.
+fn sort_by_recent(
+    recipes: &RecipeTree,
+    send_times: &HashMap<RecipeId, DateTime<Utc>>,
+    mut nodes: Vec<RecipeNode>,
+) -> Vec<RecipeNode> {
+    let mut latest: HashMap<RecipeId, DateTime<Utc>> = HashMap::new();
+    let mut positions: HashMap<RecipeId, usize> = HashMap::new();
+    for (i, (lookup_key, node)) in recipes.iter().enumerate() {
+        positions.insert(node.id().clone(), i);
+        if let Some(&time) = send_times.get(node.id()) {
+            // Lookup keys include the node itself
+            for id in lookup_key.as_slice() {
+                let entry = latest.entry(id.clone()).or_insert(time);
+                *entry = (*entry).max(time);
+            }
+        }
+    }
+
+    // Sort by each step in the node's path, so that nodes are compared to
+    // their siblings
+    nodes.sort_by_cached_key(|node| {
+        recipes
+            .get_lookup_key(node.id())
+            .map(|lookup_key| {
+                lookup_key
+                    .as_slice()
+                    .iter()
+                    .map(|id| (Reverse(latest.get(id).copied()), positions[id]))
+                    .collect_vec()
+            })
+            .unwrap_or_default()
+    });
+    nodes
+}
+
+/// Get the display name of a recipe or folder
+fn node_name(node: &RecipeNode) -> &str {
+    match node {
+        RecipeNode::Folder(folder) => folder.name(),
+        RecipeNode::Recipe(recipe) => recipe.name(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        collection::{Folder, Recipe},
+        test_util::{by_id, Factory},
+    };
+    use rstest::rstest;
+
+    /// Build a tree:
+    /// - users
+    ///   - get_user
+    ///   - delete_user
+    /// - login
+    fn tree() -> RecipeTree {
+        by_id::<RecipeNode>([
+            Folder {
+                id: "users".into(),
+                children: by_id([
+                    Recipe {
+                        id: "get_user".into(),
+                        tags: vec!["admin".into()],
+                        ..Recipe::factory(())
+                    }
+                    .into(),
+                    Recipe {
+                        id: "delete_user".into(),
+                        tags: vec!["admin".into(), "danger".into()],
+                        ..Recipe::factory(())
+                    }
+                    .into(),
+                ]),
+                ..Folder::factory(())
+            }
+            .into(),
+            Recipe {
+                id: "login".into(),
+                tags: vec!["auth".into()],
+                ..Recipe::factory(())
+            }
+            .into(),
+        ])
+        .into()
+    }
+
+    #[rstest]
+    #[case::recipe("GET", &["users", "get_user"])]
+    #[case::many("user", &["users", "get_user", "delete_user"])]
+    #[case::folder("users", &["users", "get_user", "delete_user"])]
+    #[case::top_level("log", &["login"])]
+    #[case::fuzzy("gusr", &["users", "get_user"])]
+    #[case::none("nothing", &[])]
+    #[case::tag("#admin", &["users", "get_user", "delete_user"])]
+    #[case::tags("#ADMIN #danger", &["users", "delete_user"])]
+    #[case::tag_and_name("#admin get", &["users", "get_user"])]
+    #[case::tag_no_match("#nope", &[])]
+    #[case::tag_empty("# log", &["login"])]
+    fn test_filter_nodes(#[case] filter: &str, #[case] expected: &[&str]) {
+        let nodes = filter_nodes(&tree(), filter);
+        let ids = nodes.iter().map(|node| node.id().as_str()).collect_vec();
+        assert_eq!(ids, expected);
+    }
+    /// Favorites-only mode should show favorite recipes in context, and the
+    /// text filter should narrow within favorites
+    #[rstest]
+    #[case::all("", &["users", "get_user", "login"])]
+    #[case::filter("get", &["users", "get_user"])]
+    #[case::folder_not_favorite("users", &[])]
+    #[case::none("delete", &[])]
+    fn test_filter_favorites(#[case] filter: &str, #[case] expected: &[&str]) {
+        // Folders can't be favorites, so they're ignored
+        let favorites = Favorites(
+            ["get_user".into(), "login".into(), "users".into()]
+                .into_iter()
+                .collect(),
+        );
+        let nodes = filter_favorites(&tree(), &favorites, filter);
+        let ids = nodes.iter().map(|node| node.id().as_str()).collect_vec();
+        assert_eq!(ids, expected);
+    }
+    /// Recently sent recipes should be first, and folders should be sorted by
+    /// their most recent child
+    #[test]
+    fn test_sort_by_recent() {
+        let recipes = tree();
+        let time = |seconds| DateTime::from_timestamp(seconds, 0).unwrap();
+        let send_times = HashMap::from([
+            ("delete_user".into(), time(100)),
+            ("login".into(), time(200)),
+        ]);
+        let nodes = recipes.iter().map(|(_, node)| node.clone()).collect();
+        let nodes = sort_by_recent(&recipes, &send_times, nodes);
+        let ids = nodes.iter().map(|node| node.id().as_str()).collect_vec();
+        assert_eq!(ids, ["login", "users", "delete_user", "get_user"]);
     }
 }
@@ -37,6 +37,7 @@ use ratatui::{
     Frame,
 };
 use serde::{Deserialize, Serialize};
+use std::iter;
 use strum::{EnumCount, EnumIter};
 
 /// Display a request recipe
@@ -191,12 +192,22 @@ where
 #[derive(Copy, Clone, Debug, Display, EnumCount, EnumIter, PartialEq)]
 #[allow(clippy::enum_variant_names)]
 pub enum RecipeMenuAction {
+    #[display("Send with Edits")]
+    SendWithEdits,
     #[display("Copy URL")]
     CopyUrl,
     #[display("Copy Body")]
     CopyBody,
     #[display("Copy as cURL")]
     CopyCurl,
+    #[display("Copy as Code")]
+    CopyCode,
+    #[display("Toggle Favorite")]
+    ToggleFavorite,
+    #[display("Toggle Favorites Only")]
+    ToggleFavoritesOnly,
+    #[display("Toggle Sort by Recent")]
+    ToggleSortByRecent,
 }
 
 impl ToStringGenerate for RecipeMenuAction {}
@@ -235,6 +246,8 @@ impl RecipePane {
                     state.query.data(),
                 ),
                 disabled_form_fields,
+                allow_any_host: false,
+                body: None,
             }
         } else {
             // Shouldn't be possible, because state is initialized on first
@@ -390,6 +403,7 @@ impl RecipeState {
                     TemplatePreview::new(
                         value.clone(),
                         selected_profile_id.cloned(),
+                        recipe.id.clone(),
                     ),
                     QueryRowToggleKey {
                         recipe_id: recipe.id.clone(),
@@ -407,6 +421,7 @@ impl RecipeState {
                     TemplatePreview::new(
                         value.clone(),
                         selected_profile_id.cloned(),
+                        recipe.id.clone(),
                     ),
                     HeaderRowToggleKey {
                         recipe_id: recipe.id.clone(),
@@ -420,6 +435,7 @@ impl RecipeState {
             url: TemplatePreview::new(
                 recipe.url.clone(),
                 selected_profile_id.cloned(),
+                recipe.id.clone(),
             ),
             query: PersistedLazy::new(
                 QueryRowKey(recipe.id.clone()),
@@ -449,6 +465,7 @@ impl RecipeState {
                     AuthenticationDisplay::new(
                         authentication,
                         selected_profile_id,
+                        &recipe.id,
                     )
                     .into()
                 },
@@ -464,6 +481,7 @@ impl AuthenticationDisplay {
     fn new(
         authentication: &Authentication<Template>,
         selected_profile_id: Option<&ProfileId>,
+        recipe_id: &RecipeId,
     ) -> Self {
         match authentication {
             Authentication::Basic { username, password } => {
@@ -471,11 +489,13 @@ impl AuthenticationDisplay {
                     username: TemplatePreview::new(
                         username.clone(),
                         selected_profile_id.cloned(),
+                        recipe_id.clone(),
                     ),
                     password: password.clone().map(|password| {
                         TemplatePreview::new(
                             password,
                             selected_profile_id.cloned(),
+                            recipe_id.clone(),
                         )
                     }),
                 }
@@ -484,8 +504,36 @@ impl AuthenticationDisplay {
                 AuthenticationDisplay::Bearer(TemplatePreview::new(
                     token.clone(),
                     selected_profile_id.cloned(),
+                    recipe_id.clone(),
                 ))
             }
+            Authentication::ApiKey {
+                name,
+                value,
+                location,
+            } => AuthenticationDisplay::ApiKey {
+                name: name.clone(),
+                value: TemplatePreview::new(
+                    value.clone(),
+                    selected_profile_id.cloned(),
+                    recipe_id.clone(),
+                ),
+                location: *location,
+            },
+            Authentication::Command { command } => {
+                AuthenticationDisplay::Command {
+                    command: command
+                        .iter()
+                        .map(|arg| {
+                            TemplatePreview::new(
+                                arg.clone(),
+                                selected_profile_id.cloned(),
+                                recipe_id.clone(),
+                            )
+                        })
+                        .collect(),
+                }
+            }
         }
     }
 }
@@ -522,6 +570,38 @@ impl Draw for AuthenticationDisplay {
                 };
                 frame.render_widget(table.generate(), metadata.area())
             }
+            AuthenticationDisplay::ApiKey {
+                name,
+                value,
+                location,
+            } => {
+                let table = Table {
+                    rows: vec![
+                        ["Type".into(), "API Key".into()],
+                        ["Name".into(), name.as_str().into()],
+                        ["Location".into(), location.to_string().into()],
+                        ["Value".into(), value.generate()],
+                    ],
+                    column_widths: &[Constraint::Length(8), Constraint::Min(0)],
+                    ..Default::default()
+                };
+                frame.render_widget(table.generate(), metadata.area())
+            }
+            AuthenticationDisplay::Command { command } => {
+                // One argument per row, so each can be previewed separately
+                let rows = iter::once(["Type".into(), "Command".into()])
+                    .chain(command.iter().enumerate().map(|(i, arg)| {
+                        let label = if i == 0 { "Command" } else { "" };
+                        [label.into(), arg.generate()]
+                    }))
+                    .collect();
+                let table = Table {
+                    rows,
+                    column_widths: &[Constraint::Length(7), Constraint::Min(0)],
+                    ..Default::default()
+                };
+                frame.render_widget(table.generate(), metadata.area())
+            }
         }
     }
 }
@@ -546,6 +626,7 @@ impl RecipeBodyDisplay {
                 TextWindow::new(TemplatePreview::new(
                     body.clone(),
                     selected_profile_id,
+                    recipe_id.clone(),
                 ))
                 .into(),
             ),
@@ -569,6 +650,7 @@ impl RecipeBodyDisplay {
                     TextWindow::new(TemplatePreview::new(
                         template,
                         selected_profile_id,
+                        recipe_id.clone(),
                     ))
                     .into(),
                 )
@@ -583,6 +665,7 @@ impl RecipeBodyDisplay {
                             TemplatePreview::new(
                                 value.clone(),
                                 selected_profile_id.clone(),
+                                recipe_id.clone(),
                             ),
                             FormRowToggleKey {
                                 recipe_id: recipe_id.clone(),
@@ -1,13 +1,17 @@
 use crate::{
     http::{RequestId, RequestRecord},
     tui::{
+        context::TuiContext,
         input::Action,
         message::Message,
         view::{
             common::{
                 actions::ActionsModal,
                 header_table::HeaderTable,
-                text_window::{TextWindow, TextWindowProps},
+                table::Table,
+                text_window::{
+                    IndexedText, ScrollKey, TextWindow, TextWindowProps,
+                },
             },
             draw::{Draw, DrawMetadata, Generate, ToStringGenerate},
             event::{Event, EventHandler, Update},
@@ -15,10 +19,14 @@ use crate::{
             Component, ViewContext,
         },
     },
-    util::MaybeStr,
+    util::{truncate_bytes, MaybeStr},
 };
+use base64::{prelude::BASE64_STANDARD, Engine};
+use bytesize::ByteSize;
 use derive_more::Display;
-use ratatui::{layout::Layout, prelude::Constraint, Frame};
+use itertools::Itertools;
+use ratatui::{layout::Layout, prelude::Constraint, text::Span, Frame};
+use reqwest::header::{HeaderMap, AUTHORIZATION};
 use std::sync::Arc;
 use strum::{EnumCount, EnumIter};
 
@@ -40,7 +48,9 @@ struct State {
     request: Arc<RequestRecord>,
     /// Persist the request body to track view state. `None` only if request
     /// doesn't have a body
-    body: Option<Component<TextWindow<String>>>,
+    body: Option<Component<TextWindow<IndexedText>>>,
+    /// Is the body larger than the preview size, and only partially shown?
+    truncated: bool,
 }
 
 /// Items in the actions popup menu
@@ -50,6 +60,10 @@ enum MenuAction {
     CopyUrl,
     #[display("Copy Body")]
     CopyBody,
+    #[display("View Full Body")]
+    ViewFullBody,
+    #[display("Save as Recipe")]
+    SaveAsRecipe,
 }
 
 impl ToStringGenerate for MenuAction {}
@@ -72,11 +86,36 @@ impl EventHandler for RequestView {
                     // don't support formatting/querying but that could
                     // change
                     if let Some(body) = self.state.get().and_then(|state| {
-                        Some(state.body.as_ref()?.data().text().clone())
+                        Some(
+                            state
+                                .body
+                                .as_ref()?
+                                .data()
+                                .text()
+                                .as_str()
+                                .to_owned(),
+                        )
                     }) {
                         ViewContext::send_message(Message::CopyText(body));
                     }
                 }
+                MenuAction::ViewFullBody => {
+                    if let Some(state) =
+                        self.state.get_mut().filter(|state| state.truncated)
+                    {
+                        state.body = init_body(&state.request, None);
+                        state.truncated = false;
+                    }
+                }
+                MenuAction::SaveAsRecipe => {
+                    if let Some(state) = self.state.get() {
+                        ViewContext::send_message(
+                            Message::CollectionSaveRequest(Arc::clone(
+                                &state.request,
+                            )),
+                        )
+                    }
+                }
             }
         } else {
             return Update::Propagate(event);
@@ -102,23 +141,80 @@ impl Draw<RequestViewProps> for RequestView {
         props: RequestViewProps,
         metadata: DrawMetadata,
     ) {
-        let state = self.state.get_or_update(props.request.id, || State {
-            request: Arc::clone(&props.request),
-            body: props.request.body.as_ref().map(|body| {
-                TextWindow::new(format!("{:#}", MaybeStr(body))).into()
-            }),
+        let preview_size = TuiContext::get().config.body_preview_size;
+        let state = self.state.get_or_update(props.request.id, || {
+            let truncated = props.request.body_size() > preview_size;
+            State {
+                request: Arc::clone(&props.request),
+                body: init_body(
+                    &props.request,
+                    truncated.then_some(preview_size),
+                ),
+                truncated,
+            }
         });
 
-        let [url_area, headers_area, body_area] = Layout::vertical([
-            Constraint::Length(2),
-            Constraint::Length(props.request.headers.len() as u16 + 2),
+        // Query params, authentication, and template values are only shown if
+        // there are any, so they don't take up space for simple requests
+        let query = props.request.url.query_pairs().collect_vec();
+        let authentication = authentication_summary(&props.request.headers);
+        let template_values = &props.request.template_values;
+        let table_height = |len: usize| {
+            if len == 0 {
+                0
+            } else {
+                len as u16 + 2
+            }
+        };
+        let [url_area, query_area, authentication_area, area] =
+            Layout::vertical([
+                Constraint::Length(2),
+                Constraint::Length(table_height(query.len())),
+                Constraint::Length(if authentication.is_some() {
+                    2
+                } else {
+                    0
+                }),
+                Constraint::Min(0),
+            ])
+            .areas(metadata.area());
+        let [headers_area, template_values_area, body_area] =
+            Layout::vertical([
+                Constraint::Length(props.request.headers.len() as u16 + 2),
+                Constraint::Length(table_height(template_values.len())),
+                Constraint::Min(0),
+            ])
+            .areas(area);
+        let [notice_area, body_area] = Layout::vertical([
+            Constraint::Length(if state.truncated { 1 } else { 0 }),
             Constraint::Min(0),
         ])
-        .areas(metadata.area());
+        .areas(body_area);
 
         // This can get cut off which is jank but there isn't a good fix. User
         // can copy the URL to see the full thing
         frame.render_widget(props.request.url.to_string(), url_area);
+        if !query.is_empty() {
+            frame.render_widget(
+                Table {
+                    rows: query
+                        .iter()
+                        .map(|(param, value)| [param.as_ref(), value.as_ref()])
+                        .collect_vec(),
+                    header: Some(["Query Parameter", "Value"]),
+                    alternate_row_style: true,
+                    ..Default::default()
+                }
+                .generate(),
+                query_area,
+            );
+        }
+        if let Some(authentication) = authentication {
+            frame.render_widget(
+                format!("Authentication: {authentication}"),
+                authentication_area,
+            );
+        }
         frame.render_widget(
             HeaderTable {
                 headers: &props.request.headers,
@@ -126,8 +222,105 @@ impl Draw<RequestViewProps> for RequestView {
             .generate(),
             headers_area,
         );
+        if !template_values.is_empty() {
+            frame.render_widget(
+                Table {
+                    rows: template_values
+                        .iter()
+                        .map(|(key, value)| [key.as_str(), value.as_str()])
+                        .collect_vec(),
+                    header: Some(["Template Key", "Value"]),
+                    alternate_row_style: true,
+                    ..Default::default()
+                }
+                .generate(),
+                template_values_area,
+            );
+        }
+        if state.truncated {
+            let tui_context = TuiContext::get();
+            let styles = &tui_context.styles;
+            frame.render_widget(
+                Span::styled(
+                    format!(
+                        "Showing first {} of {}; select \"View Full Body\" \
+                        in the actions menu to load everything",
+                        preview_size.to_string_as(false),
+                        props.request.body_size().to_string_as(false),
+                    ),
+                    styles.text.highlight,
+                ),
+                notice_area,
+            );
+        }
         if let Some(body) = &state.body {
             body.draw(frame, TextWindowProps::default(), body_area, true);
         }
     }
 }
+
+/// Build the text window for a request body. If `preview_size` is given, only
+/// that much of the body is shown
+fn init_body(
+    request: &RequestRecord,
+    preview_size: Option<ByteSize>,
+) -> Option<Component<TextWindow<IndexedText>>> {
+    let body: &[u8] = request.body.as_ref()?;
+    let body = match preview_size {
+        Some(preview_size) => {
+            truncate_bytes(body, preview_size.as_u64() as usize)
+        }
+        None => body,
+    };
+    Some(
+        TextWindow::new(IndexedText::new(format!("{:#}", MaybeStr(body))))
+            .with_scroll_key(ScrollKey::RequestBody(request.recipe_id.clone()))
+            .into(),
+    )
+}
+
+/// Summarize the `Authorization` header, without showing any secrets. Basic
+/// auth shows the username, and anything else just shows the scheme. `None` if
+/// there's no `Authorization` header.
+fn authentication_summary(headers: &HeaderMap) -> Option<String> {
+    let value = headers.get(AUTHORIZATION)?.to_str().ok()?;
+    let (scheme, credentials) = value.split_once(' ').unwrap_or((value, ""));
+    if scheme.eq_ignore_ascii_case("basic") {
+        let username = BASE64_STANDARD
+            .decode(credentials.trim())
+            .ok()
+            .and_then(|decoded| String::from_utf8(decoded).ok());
+        if let Some(username) = username {
+            // Password is everything after the first colon
+            let username = username.split(':').next().unwrap_or_default();
+            return Some(format!("{scheme} (username `{username}`)"));
+        }
+    }
+    Some(scheme.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::header_map;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::none(&[], None)]
+    #[case::basic(
+        &[("Authorization", "Basic dXNlcjpodW50ZXIy")],
+        Some("Basic (username `user`)")
+    )]
+    #[case::bearer(&[("Authorization", "Bearer hunter2")], Some("Bearer"))]
+    #[case::invalid_basic(&[("Authorization", "Basic !!!")], Some("Basic"))]
+    fn test_authentication_summary(
+        #[case] headers: &[(&str, &str)],
+        #[case] expected: Option<&str>,
+    ) {
+        assert_eq!(
+            authentication_summary(&header_map(headers.iter().copied()))
+                .as_deref(),
+            expected
+        );
+    }
+}
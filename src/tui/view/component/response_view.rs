@@ -7,7 +7,10 @@ use crate::{
         input::Action,
         message::Message,
         view::{
-            common::{actions::ActionsModal, header_table::HeaderTable},
+            common::{
+                actions::ActionsModal, header_table::HeaderTable, table::Table,
+                text_window::ScrollKey,
+            },
             component::queryable_body::{QueryableBody, QueryableBodyProps},
             context::PersistedLazy,
             draw::{Draw, DrawMetadata, Generate, ToStringGenerate},
@@ -18,8 +21,14 @@ use crate::{
     },
 };
 use derive_more::Display;
+use itertools::Itertools;
 use persisted::PersistedKey;
-use ratatui::Frame;
+use ratatui::{
+    layout::{Constraint, Layout},
+    text::Text,
+    Frame,
+};
+use reqwest::header;
 use serde::Serialize;
 use std::sync::Arc;
 use strum::{EnumCount, EnumIter};
@@ -41,11 +50,18 @@ pub struct ResponseBodyViewProps<'a> {
 
 /// Items in the actions popup menu for the Body
 #[derive(Copy, Clone, Debug, Display, EnumCount, EnumIter, PartialEq)]
+#[allow(clippy::enum_variant_names)]
 enum BodyMenuAction {
     #[display("Copy Body")]
     CopyBody,
     #[display("Save Body as File")]
     SaveBody,
+    #[display("View Full Body")]
+    ViewFullBody,
+    #[display("Open Body in Viewer")]
+    OpenBody,
+    #[display("Capture Value")]
+    CaptureValue,
 }
 
 impl ToStringGenerate for BodyMenuAction {}
@@ -110,6 +126,42 @@ impl EventHandler for ResponseBodyView {
                         });
                     }
                 }
+                BodyMenuAction::ViewFullBody => {
+                    if let Some(state) = self.state.get_mut() {
+                        state.body.data_mut().load_full_body();
+                    }
+                }
+                BodyMenuAction::OpenBody => {
+                    // Always use the raw body, because the viewer can
+                    // probably do more with it than we can. This clones the
+                    // whole body, but it's about to be written to disk anyway
+                    if let Some(state) = self.state.get() {
+                        let response = &state.response;
+                        ViewContext::send_message(Message::ViewFile {
+                            data: response.body.bytes().to_vec(),
+                            extension: response.extension(),
+                            content_type: response
+                                .headers
+                                .get(header::CONTENT_TYPE)
+                                .and_then(|value| value.to_str().ok())
+                                .map(String::from),
+                        });
+                    }
+                }
+                BodyMenuAction::CaptureValue => {
+                    // Pre-fill the prompt with the current filter, since
+                    // that's probably what the user was looking at
+                    if let Some(state) = self.state.get() {
+                        ViewContext::send_message(Message::CaptureStart {
+                            response: Arc::clone(&state.response),
+                            query: state
+                                .body
+                                .data()
+                                .query()
+                                .map(ToString::to_string),
+                        });
+                    }
+                }
             }
         } else {
             return Update::Propagate(event);
@@ -138,7 +190,9 @@ impl<'a> Draw<ResponseBodyViewProps<'a>> for ResponseBodyView {
             response: Arc::clone(&props.response),
             body: PersistedLazy::new(
                 ResponseQueryPersistedKey(props.recipe_id.clone()),
-                QueryableBody::new(),
+                QueryableBody::new().with_scroll_key(ScrollKey::ResponseBody(
+                    props.recipe_id.clone(),
+                )),
             )
             .into(),
         });
@@ -168,13 +222,47 @@ impl<'a> Draw<ResponseHeadersViewProps<'a>> for ResponseHeadersView {
         props: ResponseHeadersViewProps,
         metadata: DrawMetadata,
     ) {
-        frame.render_widget(
-            HeaderTable {
-                headers: &props.response.headers,
-            }
-            .generate(),
-            metadata.area(),
-        )
+        let response = props.response;
+        let headers = HeaderTable {
+            headers: &response.headers,
+        }
+        .generate();
+        if response.redirects.is_empty() {
+            frame.render_widget(headers, metadata.area());
+            return;
+        }
+
+        // Show each hop of the redirect chain above the final headers. Add
+        // room for the title, column header, and a blank spacer line
+        let [redirects_area, headers_area] = Layout::vertical([
+            Constraint::Length(response.redirects.len() as u16 + 3),
+            Constraint::Min(0),
+        ])
+        .areas(metadata.area());
+        let redirects = Table {
+            title: Some("Redirects"),
+            rows: response
+                .redirects
+                .iter()
+                .map(|redirect| {
+                    [
+                        Text::from(redirect.status.generate()),
+                        redirect.url.as_str().into(),
+                        redirect.location.as_str().into(),
+                    ]
+                })
+                .collect_vec(),
+            header: Some(["Status", "URL", "Location"]),
+            column_widths: &[
+                Constraint::Percentage(20),
+                Constraint::Percentage(40),
+                Constraint::Percentage(40),
+            ],
+            ..Default::default()
+        }
+        .generate();
+        frame.render_widget(redirects, redirects_area);
+        frame.render_widget(headers, headers_area);
     }
 }
 
@@ -217,7 +305,7 @@ mod tests {
         #[case] response: ResponseRecord,
         #[case] expected_body: &str,
     ) {
-        response.parse_body(); // Normally the view does this
+        response.parse_body(None); // Normally the view does this
         let exchange = Exchange {
             response: response.into(),
             ..Exchange::factory(())
@@ -283,7 +371,7 @@ mod tests {
         #[case] expected_body: &[u8],
         #[case] expected_path: &str,
     ) {
-        response.parse_body(); // Normally the view does this
+        response.parse_body(None); // Normally the view does this
         let exchange = Exchange {
             response: response.into(),
             ..Exchange::factory(())
@@ -309,4 +397,73 @@ mod tests {
         assert_eq!(data, expected_body);
         assert_eq!(default_path.as_deref(), Some(expected_path));
     }
+
+    /// Test "Open Body in Viewer" menu action. The raw body should be sent,
+    /// even if it's been prettified for display
+    #[rstest]
+    #[tokio::test]
+    async fn test_open_body(harness: TestHarness) {
+        let response = ResponseRecord {
+            headers: header_map(
+                indexmap! {"content-type" => "application/json"},
+            ),
+            body: br#"{"hello":"world"}"#.to_vec().into(),
+            ..ResponseRecord::factory(())
+        };
+        response.parse_body(None); // Normally the view does this
+        let exchange = Exchange {
+            response: response.into(),
+            ..Exchange::factory(())
+        };
+        let mut component = TestComponent::new(
+            harness,
+            ResponseBodyView::default(),
+            ResponseBodyViewProps {
+                request_id: exchange.id,
+                recipe_id: &exchange.request.recipe_id,
+                response: exchange.response,
+            },
+        );
+
+        component
+            .update_draw(Event::new_local(BodyMenuAction::OpenBody))
+            .assert_empty();
+
+        let (data, extension, content_type) = assert_matches!(
+            component.harness_mut().pop_message_now(),
+            Message::ViewFile { data, extension, content_type } =>
+                (data, extension, content_type),
+        );
+        assert_eq!(data, br#"{"hello":"world"}"#);
+        assert_eq!(extension.as_deref(), Some("json"));
+        assert_eq!(content_type.as_deref(), Some("application/json"));
+    }
+
+    /// Test "Capture Value" menu action. The response should be sent along
+    /// to be queried
+    #[rstest]
+    #[tokio::test]
+    async fn test_capture_value(harness: TestHarness) {
+        let exchange = Exchange::factory(());
+        let mut component = TestComponent::new(
+            harness,
+            ResponseBodyView::default(),
+            ResponseBodyViewProps {
+                request_id: exchange.id,
+                recipe_id: &exchange.request.recipe_id,
+                response: Arc::clone(&exchange.response),
+            },
+        );
+
+        component
+            .update_draw(Event::new_local(BodyMenuAction::CaptureValue))
+            .assert_empty();
+
+        let (response, query) = assert_matches!(
+            component.harness_mut().pop_message_now(),
+            Message::CaptureStart { response, query } => (response, query),
+        );
+        assert!(Arc::ptr_eq(&response, &exchange.response));
+        assert_eq!(query, None);
+    }
 }
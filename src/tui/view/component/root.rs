@@ -1,16 +1,23 @@
 use crate::{
-    collection::Collection,
+    collection::{Chain, Collection},
     http::RequestId,
     tui::{
         input::Action,
         message::Message,
         view::{
-            common::{actions::GlobalAction, modal::ModalQueue},
+            common::{
+                actions::GlobalAction,
+                modal::{IntoModal, ModalQueue},
+            },
             component::{
+                chain_inspector::ChainInspectorModal,
+                collection_select::CollectionSelectModal,
                 help::HelpFooter,
                 history::History,
-                misc::NotificationText,
+                misc::{NotificationText, Toast, ToastClicked},
                 primary::{PrimaryView, PrimaryViewProps},
+                recipe_list::SelectRecipe,
+                status_bar::{StatusBar, StatusBarProps, StatusSegment},
             },
             context::PersistedLazy,
             draw::{Draw, DrawMetadata, Generate},
@@ -23,10 +30,16 @@ use crate::{
     },
     util::ResultExt,
 };
+use anyhow::anyhow;
 use derive_more::From;
 use persisted::{PersistedContainer, PersistedKey};
-use ratatui::{layout::Layout, prelude::Constraint, Frame};
+use ratatui::{
+    layout::{Layout, Rect},
+    prelude::Constraint,
+    Frame,
+};
 use serde::Serialize;
+use std::path::PathBuf;
 
 /// The root view component
 #[derive(Debug)]
@@ -36,6 +49,16 @@ pub struct Root {
     request_store: RequestStore,
     /// Which request are we showing in the request/response panel?
     selected_request: PersistedLazy<SelectedRequestKey, SelectedRequestId>,
+    /// Path to the loaded collection file, for the status bar. `None` if it
+    /// couldn't be loaded from the DB
+    collection_path: Option<PathBuf>,
+    /// Error message from the most recent failed request
+    last_error: Option<String>,
+    /// All chains in the collection, for the chain inspector
+    chains: Vec<Chain>,
+    /// Is the collection file being loaded in the background? The view is
+    /// rebuilt once it's done, so this is only ever cleared if loading fails
+    loading: bool,
 
     // ==== Children =====
     /// We hold onto the primary view even when it's not visible, because we
@@ -43,6 +66,9 @@ pub struct Root {
     primary_view: Component<PrimaryView>,
     modal_queue: Component<ModalQueue>,
     notification_text: Option<Component<NotificationText>>,
+    status_bar: Component<StatusBar>,
+    /// Popup for a request that completed in the background
+    toast: Option<Component<Toast>>,
 }
 
 impl Root {
@@ -51,15 +77,26 @@ impl Root {
         // the event that attempts to load the latest request for the recipe
         let primary_view = PrimaryView::new(collection);
         let selected_request = PersistedLazy::new_default(SelectedRequestKey);
+        let collection_path =
+            ViewContext::with_database(|database| database.collection_path())
+                .ok();
         Self {
             // State
-            request_store: RequestStore::default(),
+            request_store: RequestStore::new(
+                collection.protobuf_registry.clone(),
+            ),
             selected_request,
+            collection_path,
+            last_error: None,
+            chains: collection.chains.values().cloned().collect(),
+            loading: false,
 
             // Children
             primary_view: primary_view.into(),
             modal_queue: Component::default(),
             notification_text: None,
+            status_bar: Component::default(),
+            toast: None,
         }
     }
 
@@ -107,15 +144,15 @@ impl Root {
             .and_then(|request_id| self.request_store.get(request_id))
     }
 
-    /// Open the history modal for current recipe+profile. Return an error if
-    /// the harness.database load failed.
+    /// Open the history modal for current recipe, across all profiles. Return
+    /// an error if the database load failed.
     fn open_history(&mut self) -> anyhow::Result<()> {
         let primary_view = self.primary_view.data();
         if let Some(recipe) = primary_view.selected_recipe() {
-            // Make sure all requests for this profile+recipe are loaded
+            // Make sure all requests for this recipe are loaded
             let requests = self
                 .request_store
-                .load_summaries(primary_view.selected_profile_id(), &recipe.id)?
+                .load_summaries(&recipe.id)?
                 .map(RequestStateSummary::from)
                 .collect();
 
@@ -126,10 +163,119 @@ impl Root {
         }
         Ok(())
     }
+
+    /// Open the modal to switch to another collection. Return an error if the
+    /// database load failed.
+    fn open_collection_select(&self) -> anyhow::Result<()> {
+        let (collections, current) = ViewContext::with_database(|database| {
+            anyhow::Ok((
+                database.root().collections()?,
+                database.collection_path()?,
+            ))
+        })?;
+        ViewContext::open_modal(
+            CollectionSelectModal::new(collections, Some(current)),
+            ModalPriority::Low,
+        );
+        Ok(())
+    }
+
+    /// If a request finished for a recipe other than the one being viewed,
+    /// pop up a toast so the user knows it's done
+    fn notify_completion(&mut self, state: &RequestState) {
+        let status = match state {
+            RequestState::Response { exchange } => {
+                Some(exchange.response.status)
+            }
+            RequestState::RequestError { .. } => None,
+            _ => return,
+        };
+        let primary_view = self.primary_view.data();
+        let recipe_id = state.recipe_id();
+        if primary_view.selected_recipe_id() == Some(recipe_id) {
+            return;
+        }
+        let recipe_name = primary_view
+            .recipe(recipe_id)
+            .map(|recipe| recipe.name().to_owned())
+            .unwrap_or_else(|| recipe_id.to_string());
+        self.toast = Some(
+            Toast::new(state.id(), recipe_id.clone(), recipe_name, status)
+                .into(),
+        );
+    }
+
+    /// Jump to the exchange from the current toast, if it's still showing
+    fn open_toast(&mut self) {
+        let Some(toast) = self.toast.take() else {
+            return;
+        };
+        let toast = toast.into_data();
+        if toast.is_expired() {
+            return;
+        }
+        ViewContext::push_event(Event::new_local(SelectRecipe(
+            toast.recipe_id().clone(),
+        )));
+        // Selecting the recipe queues a request of its own to load the latest
+        // exchange. Defer ours so it's handled after that one
+        ViewContext::push_event(Event::new_local(SelectRequestLater(
+            toast.request_id(),
+        )));
+    }
+
+    /// Show the error from the most recent failed request, if any
+    fn open_last_error(&self) {
+        if let Some(error) = &self.last_error {
+            ViewContext::open_modal(
+                anyhow!("{error}").into_modal(),
+                ModalPriority::Low,
+            );
+        }
+    }
+
+    /// Open the chain inspector, rendering each chain with the current profile
+    fn open_chain_inspector(&self) {
+        ViewContext::open_modal(
+            ChainInspectorModal::new(
+                &self.chains,
+                self.primary_view.data().selected_profile_id(),
+            ),
+            ModalPriority::Low,
+        );
+    }
 }
 
 impl EventHandler for Root {
     fn update(&mut self, event: Event) -> Update {
+        // Status bar was clicked
+        if let Some(segment) = event.local::<StatusSegment>() {
+            match segment {
+                StatusSegment::Collection => {
+                    self.open_collection_select()
+                        .reported(&ViewContext::messages_tx());
+                }
+                StatusSegment::Profile => {
+                    self.primary_view.data().open_profile_select()
+                }
+                StatusSegment::InFlight => {
+                    self.open_history().reported(&ViewContext::messages_tx());
+                }
+                StatusSegment::Error => self.open_last_error(),
+            }
+            return Update::Consumed;
+        }
+        if let Some(ToastClicked) = event.local() {
+            self.open_toast();
+            return Update::Consumed;
+        }
+        if let Some(SelectRequestLater(request_id)) = event.local() {
+            ViewContext::push_event(Event::HttpSelectRequest(Some(
+                *request_id,
+            )));
+            return Update::Consumed;
+        }
+
         match event {
             // Set selected request, and load it from the DB if needed
             Event::HttpSelectRequest(request_id) => {
@@ -139,12 +285,24 @@ impl EventHandler for Root {
             // Update state of in-progress HTTP request
             Event::HttpSetState(state) => {
                 let id = state.id();
+                self.notify_completion(&state);
+                match &state {
+                    RequestState::BuildError { error } => {
+                        self.last_error = Some(format!("{:#}", error.error));
+                    }
+                    RequestState::RequestError { error } => {
+                        self.last_error = Some(format!("{:#}", error.error));
+                    }
+                    _ => {}
+                }
                 // If this request is *new*, select it
                 if self.request_store.update(state) {
                     *self.selected_request = Some(id).into();
                 }
             }
 
+            Event::CollectionLoading(loading) => self.loading = loading,
+
             Event::Notify(notification) => {
                 self.notification_text =
                     Some(NotificationText::new(notification).into())
@@ -158,6 +316,13 @@ impl EventHandler for Root {
                 Action::History => {
                     self.open_history().reported(&ViewContext::messages_tx());
                 }
+                Action::LastError => self.open_last_error(),
+                Action::JumpToRequest => self.open_toast(),
+                Action::InspectChains => self.open_chain_inspector(),
+                Action::SwitchCollection => {
+                    self.open_collection_select()
+                        .reported(&ViewContext::messages_tx());
+                }
                 Action::Quit => ViewContext::send_message(Message::Quit),
                 Action::ReloadCollection => {
                     ViewContext::send_message(Message::CollectionStartReload)
@@ -174,6 +339,13 @@ impl EventHandler for Root {
                     Some(GlobalAction::EditCollection) => {
                         ViewContext::send_message(Message::CollectionEdit)
                     }
+                    Some(GlobalAction::NewRecipe) => {
+                        ViewContext::send_message(Message::CollectionNewRecipe)
+                    }
+                    Some(GlobalAction::SwitchCollection) => {
+                        self.open_collection_select()
+                            .reported(&ViewContext::messages_tx());
+                    }
                     None => return Update::Propagate(event),
                 }
             }
@@ -185,7 +357,15 @@ impl EventHandler for Root {
     }
 
     fn children(&mut self) -> Vec<Component<&mut dyn EventHandler>> {
-        vec![self.modal_queue.as_child(), self.primary_view.as_child()]
+        let mut children = vec![self.modal_queue.as_child()];
+        // Toast sits on top of the primary view, so it gets first dibs on
+        // clicks
+        if let Some(toast) = self.toast.as_mut() {
+            children.push(toast.as_child());
+        }
+        children.push(self.primary_view.as_child());
+        children.push(self.status_bar.as_child());
+        children
     }
 }
 
@@ -201,18 +381,48 @@ impl Draw for Root {
             frame,
             PrimaryViewProps {
                 selected_request: self.selected_request(),
+                in_flight_recipes: self.request_store.in_flight_recipes(),
             },
             main_area,
             !self.modal_queue.data().is_open(),
         );
 
+        // Toast goes in the bottom-right of the main area, over the panes
+        if let Some(toast) = self.toast.as_ref() {
+            if !toast.data().is_expired() {
+                let (width, height) = toast.data().size();
+                let width = width.min(main_area.width);
+                let height = height.min(main_area.height);
+                let area = Rect {
+                    x: main_area.right() - width,
+                    y: main_area.bottom() - height,
+                    width,
+                    height,
+                };
+                toast.draw(frame, (), area, false);
+            }
+        }
+
         // Footer
+        let status_props = StatusBarProps {
+            collection_path: self.collection_path.as_deref(),
+            loading: self.loading,
+            profile: self.primary_view.data().selected_profile(),
+            in_flight: self.request_store.in_flight(),
+            has_error: self.last_error.is_some(),
+        };
         let footer = HelpFooter.generate();
-        let [notification_area, help_area] = Layout::horizontal([
-            Constraint::Min(10),
-            Constraint::Length(footer.width() as u16),
-        ])
-        .areas(footer_area);
+        let [status_area, _, notification_area, help_area] =
+            Layout::horizontal([
+                Constraint::Length(status_props.width()),
+                // Gap between the status bar and notifications
+                Constraint::Length(2),
+                Constraint::Min(10),
+                Constraint::Length(footer.width() as u16),
+            ])
+            .areas(footer_area);
+        self.status_bar
+            .draw(frame, status_props, status_area, false);
         if let Some(notification_text) = &self.notification_text {
             notification_text.draw(frame, (), notification_area, false);
         }
@@ -223,6 +433,11 @@ impl Draw for Root {
     }
 }
 
+/// Local event to select a request, queued behind other events that may also
+/// change the selected request
+#[derive(Debug)]
+struct SelectRequestLater(RequestId);
+
 /// Persistence key for the selected request
 #[derive(Debug, Serialize, PersistedKey)]
 #[persisted(Option<RequestId>)]
@@ -354,6 +569,60 @@ mod tests {
         );
     }
 
+    /// Test that a request completing for another recipe pops up a toast,
+    /// which can be used to jump to the exchange
+    #[rstest]
+    fn test_toast(harness: TestHarness) {
+        let collection = Collection::factory(());
+        let mut component =
+            TestComponent::new(harness, Root::new(&collection), ());
+
+        // Completion for the selected recipe doesn't need a toast
+        let exchange = Exchange::factory((
+            Some(collection.first_profile_id().clone()),
+            collection.first_recipe_id().clone(),
+        ));
+        component
+            .update_draw(Event::HttpSetState(RequestState::response(
+                exchange, None,
+            )))
+            .assert_empty();
+        assert!(component.data().toast.is_none());
+
+        // Request for another recipe is started, then the user moves on
+        let exchange = Exchange::factory((None, "other".into()));
+        let id = exchange.id;
+        component
+            .update_draw(Event::HttpSetState(RequestState::Building {
+                id,
+                start_time: exchange.start_time,
+                profile_id: None,
+                recipe_id: "other".into(),
+            }))
+            .assert_empty();
+        component
+            .update_draw(Event::HttpSelectRequest(None))
+            .assert_empty();
+        assert_ne!(
+            component.data().selected_request().map(RequestState::id),
+            Some(id)
+        );
+
+        component
+            .update_draw(Event::HttpSetState(RequestState::response(
+                exchange, None,
+            )))
+            .assert_empty();
+        assert!(component.data().toast.is_some());
+
+        component.send_key(KeyCode::Char('o')).assert_empty();
+        assert!(component.data().toast.is_none());
+        assert_eq!(
+            component.data().selected_request().map(RequestState::id),
+            Some(id)
+        );
+    }
+
     #[rstest]
     fn test_edit_collection(harness: TestHarness) {
         let collection = Collection::factory(());
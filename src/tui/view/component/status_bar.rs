@@ -0,0 +1,224 @@
+//! Persistent bar at the bottom of the screen, showing high-level app state
+
+use crate::{
+    collection::Profile,
+    tui::{
+        context::TuiContext,
+        input::Action,
+        view::{
+            draw::{Draw, DrawMetadata},
+            event::{Event, EventHandler, Update},
+            ViewContext,
+        },
+    },
+};
+use ratatui::{layout::Rect, text::Span, Frame};
+use std::{cell::RefCell, path::Path};
+
+/// Show the current collection and profile, the number of in-flight requests,
+/// and whether any request has failed. Each segment can be clicked to open
+/// the related modal. The click is emitted as a [StatusSegment] local event,
+/// to be handled by the parent.
+#[derive(Debug, Default)]
+pub struct StatusBar {
+    /// Where each segment was last drawn, for mapping clicks to segments
+    segments: RefCell<Vec<(StatusSegment, Rect)>>,
+}
+
+#[derive(Clone)]
+pub struct StatusBarProps<'a> {
+    pub collection_path: Option<&'a Path>,
+    /// Is the collection file being loaded in the background?
+    pub loading: bool,
+    pub profile: Option<&'a Profile>,
+    /// Number of requests that are building or loading
+    pub in_flight: usize,
+    /// Has any request failed since launch?
+    pub has_error: bool,
+}
+
+/// A clickable section of the status bar
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum StatusSegment {
+    Collection,
+    Profile,
+    InFlight,
+    Error,
+}
+
+impl StatusBar {
+    /// Text between segments
+    const SEPARATOR: &'static str = " │ ";
+
+    /// Get the segment drawn at the given column, if any
+    fn segment_at(&self, column: u16) -> Option<StatusSegment> {
+        self.segments
+            .borrow()
+            .iter()
+            .find(|(_, area)| column >= area.x && column < area.right())
+            .map(|(segment, _)| *segment)
+    }
+}
+
+impl<'a> StatusBarProps<'a> {
+    /// Width needed to draw every segment
+    pub fn width(&self) -> u16 {
+        let segments = self.segments();
+        let separators = segments.len().saturating_sub(1)
+            * Span::raw(StatusBar::SEPARATOR).width();
+        let text: usize = segments.iter().map(|(_, span)| span.width()).sum();
+        (separators + text) as u16
+    }
+
+    /// Get the text for each segment, in order
+    fn segments(&self) -> Vec<(StatusSegment, Span<'static>)> {
+        let collection = self
+            .collection_path
+            .and_then(Path::file_name)
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "<unknown>".into());
+        let collection = if self.loading {
+            format!("{collection} (loading...)")
+        } else {
+            collection
+        };
+        let profile = self.profile.map(Profile::name).unwrap_or("None");
+
+        let mut segments = vec![
+            (StatusSegment::Collection, Span::raw(collection)),
+            (
+                StatusSegment::Profile,
+                Span::raw(format!("Profile: {profile}")),
+            ),
+            (
+                StatusSegment::InFlight,
+                Span::raw(format!("{} in flight", self.in_flight)),
+            ),
+        ];
+        if self.has_error {
+            segments.push((
+                StatusSegment::Error,
+                Span::styled("Error", TuiContext::get().styles.text.error),
+            ));
+        }
+        segments
+    }
+}
+
+impl EventHandler for StatusBar {
+    fn update(&mut self, event: Event) -> Update {
+        if let Event::Input {
+            event: crossterm::event::Event::Mouse(mouse_event),
+            action: Some(Action::LeftClick),
+        } = &event
+        {
+            if let Some(segment) = self.segment_at(mouse_event.column) {
+                ViewContext::push_event(Event::new_local(segment));
+                return Update::Consumed;
+            }
+        }
+        Update::Propagate(event)
+    }
+}
+
+impl<'a> Draw<StatusBarProps<'a>> for StatusBar {
+    fn draw(
+        &self,
+        frame: &mut Frame,
+        props: StatusBarProps<'a>,
+        metadata: DrawMetadata,
+    ) {
+        let area = metadata.area();
+        let separator = Span::raw(Self::SEPARATOR);
+        let separator_width = separator.width() as u16;
+        let mut drawn = self.segments.borrow_mut();
+        drawn.clear();
+        let mut x = area.x;
+        for (segment, span) in props.segments() {
+            let width = span.width() as u16;
+            let gap = if drawn.is_empty() { 0 } else { separator_width };
+            // Don't draw anything that would hang off the edge
+            if x + gap + width > area.right() {
+                break;
+            }
+            if gap > 0 {
+                let separator_area = Rect {
+                    x,
+                    width: gap,
+                    ..area
+                };
+                frame.render_widget(separator.clone(), separator_area);
+                x += gap;
+            }
+            let segment_area = Rect { x, width, ..area };
+            frame.render_widget(span, segment_area);
+            drawn.push((segment, segment_area));
+            x += width;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_util::{assert_matches, Factory},
+        tui::{
+            test_util::{harness, TestHarness},
+            view::test_util::TestComponent,
+        },
+    };
+    use rstest::rstest;
+
+    /// Test drawing, and that clicking a segment emits an event for the
+    /// parent to handle
+    #[rstest]
+    fn test_click(#[with(50, 1)] harness: TestHarness) {
+        let profile = Profile::factory(());
+        let props = StatusBarProps {
+            collection_path: Some(Path::new("/tmp/slumber.yml")),
+            loading: false,
+            profile: Some(&profile),
+            in_flight: 2,
+            has_error: true,
+        };
+        let mut component =
+            TestComponent::new(harness, StatusBar::default(), props);
+
+        // Error segment doesn't fit, so it's left off
+        component.assert_buffer_lines([
+            "slumber.yml │ Profile: profile1 │ 2 in flight     ",
+        ]);
+
+        let propagated = component.click(16, 0);
+        let event = assert_matches!(propagated.events(), [event] => event);
+        assert_eq!(event.local(), Some(&StatusSegment::Profile));
+
+        // Separators aren't clickable
+        let propagated = component.click(12, 0);
+        assert_matches!(
+            propagated.events(),
+            [Event::Input {
+                action: Some(Action::LeftClick),
+                ..
+            }]
+        );
+    }
+
+    /// Collection segment shows when the collection is still loading
+    #[rstest]
+    fn test_loading(#[with(50, 1)] harness: TestHarness) {
+        let props = StatusBarProps {
+            collection_path: Some(Path::new("/tmp/slumber.yml")),
+            loading: true,
+            profile: None,
+            in_flight: 0,
+            has_error: false,
+        };
+        let component =
+            TestComponent::new(harness, StatusBar::default(), props);
+        component.assert_buffer_lines([
+            "slumber.yml (loading...) │ Profile: None          ",
+        ]);
+    }
+}
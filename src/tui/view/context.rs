@@ -32,6 +32,9 @@ pub struct ViewContext {
     /// Sender to the async message queue, which is used to transmit data and
     /// trigger callbacks that require additional threading/background work.
     messages_tx: MessageSender,
+    /// Should UI state be saved to the database? This is disabled for
+    /// placeholder views, so they don't clobber the real state
+    persist: bool,
 }
 
 impl ViewContext {
@@ -58,10 +61,17 @@ impl ViewContext {
                 database,
                 event_queue: EventQueue::default(),
                 messages_tx,
+                persist: true,
             })
         })
     }
 
+    /// Stop saving UI state to the database, until the context is
+    /// re-initialized. Loading state is unaffected
+    pub fn disable_persistence() {
+        Self::with_mut(|context| context.persist = false)
+    }
+
     /// Execute a function with read-only access to the context
     fn with<T>(f: impl FnOnce(&ViewContext) -> T) -> T {
         Self::INSTANCE.with_borrow(|context| {
@@ -160,6 +170,9 @@ where
     }
 
     fn store_persisted(key: &K, value: K::Value) {
+        if !Self::with(|context| context.persist) {
+            return;
+        }
         Self::with_database(|database| {
             database.set_ui((K::type_name(), key), value)
         })
@@ -115,6 +115,9 @@ pub enum Event {
     /// Update the state of an in-progress HTTP request
     HttpSetState(RequestState),
 
+    /// The collection file started or stopped loading in the background
+    CollectionLoading(bool),
+
     /// Show a modal to the user
     OpenModal {
         #[debug(skip)]
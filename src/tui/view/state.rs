@@ -7,14 +7,14 @@ pub mod select;
 use crate::{
     collection::{ProfileId, RecipeId},
     http::{
-        Exchange, ExchangeSummary, RequestBuildError, RequestError, RequestId,
-        RequestRecord,
+        Exchange, ExchangeSummary, ProtobufRegistry, RequestBuildError,
+        RequestError, RequestId, RequestRecord,
     },
 };
 use bytesize::ByteSize;
 use chrono::{DateTime, Duration, Utc};
 use derive_more::Deref;
-use reqwest::StatusCode;
+use reqwest::{StatusCode, Version};
 use std::{
     cell::{Ref, RefCell},
     sync::Arc,
@@ -132,6 +132,8 @@ pub struct RequestMetadata {
     /// Elapsed time for the active request. If pending, this is a running
     /// total. Otherwise end time - start time.
     pub duration: Duration,
+    /// Size of the request *body*
+    pub size: ByteSize,
 }
 
 /// Metadata derived from a response. This is only available for requests that
@@ -139,6 +141,8 @@ pub struct RequestMetadata {
 #[derive(Debug)]
 pub struct ResponseMetadata {
     pub status: StatusCode,
+    /// Negotiated HTTP version
+    pub version: Version,
     /// Size of the response *body*
     pub size: ByteSize,
 }
@@ -180,22 +184,33 @@ impl RequestState {
         }
     }
 
+    /// Is the request still building or waiting on a response?
+    pub fn is_in_flight(&self) -> bool {
+        matches!(self, Self::Building { .. } | Self::Loading { .. })
+    }
+
     /// Get metadata about a request. Return `None` if the request hasn't been
     /// successfully built (yet)
     pub fn request_metadata(&self) -> Option<RequestMetadata> {
         match self {
             Self::Building { .. } | Self::BuildError { .. } => None,
-            Self::Loading { start_time, .. } => Some(RequestMetadata {
+            Self::Loading {
+                request,
+                start_time,
+            } => Some(RequestMetadata {
                 start_time: *start_time,
                 duration: Utc::now() - start_time,
+                size: request.body_size(),
             }),
             Self::Response { exchange, .. } => Some(RequestMetadata {
                 start_time: exchange.start_time,
                 duration: exchange.duration(),
+                size: exchange.request.body_size(),
             }),
             Self::RequestError { error } => Some(RequestMetadata {
                 start_time: error.start_time,
                 duration: error.end_time - error.start_time,
+                size: error.request.body_size(),
             }),
         }
     }
@@ -206,6 +221,7 @@ impl RequestState {
         if let RequestState::Response { exchange } = self {
             Some(ResponseMetadata {
                 status: exchange.response.status,
+                version: exchange.response.version,
                 size: exchange.response.body.size(),
             })
         } else {
@@ -225,12 +241,16 @@ impl RequestState {
     }
 
     /// Create a request state from a completed response. This is **expensive**,
-    /// don't call it unless you need the value.
-    pub fn response(exchange: Exchange) -> Self {
+    /// don't call it unless you need the value. Protobuf bodies are decoded
+    /// with the collection's compiled schema.
+    pub fn response(
+        exchange: Exchange,
+        protobuf: Option<&ProtobufRegistry>,
+    ) -> Self {
         // Pre-parse the body so the view doesn't have to do it. We're in the
         // main thread still here though so large bodies may take a while. Maybe
         // we want to punt this into a separate task?
-        exchange.response.parse_body();
+        exchange.response.parse_body(protobuf);
         Self::Response { exchange }
     }
 }
@@ -242,19 +262,25 @@ impl RequestState {
 pub enum RequestStateSummary {
     Building {
         id: RequestId,
+        profile_id: Option<ProfileId>,
         start_time: DateTime<Utc>,
     },
     BuildError {
         id: RequestId,
+        profile_id: Option<ProfileId>,
         time: DateTime<Utc>,
     },
     Loading {
         id: RequestId,
+        profile_id: Option<ProfileId>,
+        label: Option<String>,
         start_time: DateTime<Utc>,
     },
     Response(ExchangeSummary),
     RequestError {
         id: RequestId,
+        profile_id: Option<ProfileId>,
+        label: Option<String>,
         time: DateTime<Utc>,
     },
 }
@@ -270,6 +296,29 @@ impl RequestStateSummary {
         }
     }
 
+    /// The profile that was selected when the request was built
+    pub fn profile_id(&self) -> Option<&ProfileId> {
+        match self {
+            Self::Building { profile_id, .. }
+            | Self::BuildError { profile_id, .. }
+            | Self::Loading { profile_id, .. }
+            | Self::RequestError { profile_id, .. } => profile_id.as_ref(),
+            Self::Response(exchange) => exchange.profile_id.as_ref(),
+        }
+    }
+
+    /// The rendered label of the request. Only available once the request
+    /// has been built
+    pub fn label(&self) -> Option<&str> {
+        match self {
+            Self::Building { .. } | Self::BuildError { .. } => None,
+            Self::Loading { label, .. } | Self::RequestError { label, .. } => {
+                label.as_deref()
+            }
+            Self::Response(exchange) => exchange.label.as_deref(),
+        }
+    }
+
     /// Get the time of the request state. For in-flight or completed requests,
     /// this is when it *started*.
     pub fn time(&self) -> DateTime<Utc> {
@@ -289,13 +338,16 @@ impl RequestStateSummary {
 
 impl From<&RequestState> for RequestStateSummary {
     fn from(state: &RequestState) -> Self {
+        let profile_id = state.profile_id().cloned();
         match state {
             RequestState::Building { id, start_time, .. } => Self::Building {
                 id: *id,
+                profile_id,
                 start_time: *start_time,
             },
             RequestState::BuildError { error } => Self::BuildError {
                 id: error.id,
+                profile_id,
                 time: error.time,
             },
             RequestState::Loading {
@@ -304,6 +356,8 @@ impl From<&RequestState> for RequestStateSummary {
                 ..
             } => Self::Loading {
                 id: request.id,
+                profile_id,
+                label: request.label.clone(),
                 start_time: *start_time,
             },
             RequestState::Response { exchange } => {
@@ -311,6 +365,8 @@ impl From<&RequestState> for RequestStateSummary {
             }
             RequestState::RequestError { error } => Self::RequestError {
                 id: error.request.id,
+                profile_id,
+                label: error.request.label.clone(),
                 time: error.start_time,
             },
         }
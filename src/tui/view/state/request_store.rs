@@ -1,12 +1,12 @@
 use crate::{
     collection::{ProfileId, RecipeId},
-    http::RequestId,
+    http::{ProtobufRegistry, RequestId},
     tui::view::{
         context::ViewContext, state::RequestStateSummary, RequestState,
     },
 };
 use itertools::Itertools;
-use std::collections::{hash_map::Entry, HashMap};
+use std::collections::{hash_map::Entry, HashMap, HashSet};
 
 /// Simple in-memory "database" for request state. This serves a few purposes:
 ///
@@ -23,9 +23,19 @@ use std::collections::{hash_map::Entry, HashMap};
 #[derive(Debug, Default)]
 pub struct RequestStore {
     requests: HashMap<RequestId, RequestState>,
+    /// Compiled protobuf schema from the collection, for parsing bodies of
+    /// requests loaded from the DB
+    protobuf: Option<ProtobufRegistry>,
 }
 
 impl RequestStore {
+    pub fn new(protobuf: Option<ProtobufRegistry>) -> Self {
+        Self {
+            requests: HashMap::new(),
+            protobuf,
+        }
+    }
+
     /// Get request state by ID
     pub fn get(&self, id: RequestId) -> Option<&RequestState> {
         self.requests.get(&id)
@@ -37,6 +47,23 @@ impl RequestStore {
         self.requests.insert(state.id(), state).is_none()
     }
 
+    /// Number of requests that are currently building or loading
+    pub fn in_flight(&self) -> usize {
+        self.requests
+            .values()
+            .filter(|state| state.is_in_flight())
+            .count()
+    }
+
+    /// Get the IDs of all recipes that have a request building or loading
+    pub fn in_flight_recipes(&self) -> HashSet<&RecipeId> {
+        self.requests
+            .values()
+            .filter(|state| state.is_in_flight())
+            .map(RequestState::recipe_id)
+            .collect()
+    }
+
     /// Load a request from the database by ID. If already present in the store,
     /// do *not* update it. Only go to the DB if it's missing. Return the loaded
     /// request. Return `None` only if the ID is not present in the store *or*
@@ -50,14 +77,19 @@ impl RequestStore {
             Entry::Vacant(entry) => {
                 ViewContext::with_database(|database| database.get_request(id))?
                     .map(|exchange| {
-                        entry.insert(RequestState::response(exchange))
+                        entry.insert(RequestState::response(
+                            exchange,
+                            self.protobuf.as_ref(),
+                        ))
                     })
             }
         };
         Ok(request.map(|r| &*r))
     }
 
-    /// Get the latest request for a specific profile+recipe combo
+    /// Get the latest request for a specific profile+recipe combo. This
+    /// includes requests from the current session that aren't in the DB, e.g.
+    /// ones that are still in flight.
     pub fn load_latest(
         &mut self,
         profile_id: Option<&ProfileId>,
@@ -66,8 +98,29 @@ impl RequestStore {
         let exchange = ViewContext::with_database(|database| {
             database.get_latest_request(profile_id, recipe_id)
         })?;
+
+        // If anything in memory is newer than the DB's latest, use that
+        let latest_in_memory = self
+            .requests
+            .values()
+            .filter(|state| {
+                state.profile_id() == profile_id
+                    && state.recipe_id() == recipe_id
+            })
+            .map(RequestStateSummary::from)
+            .max_by_key(RequestStateSummary::time)
+            .filter(|summary| {
+                exchange.as_ref().map_or(true, |exchange| {
+                    summary.time() > exchange.start_time
+                })
+            });
+        if let Some(summary) = latest_in_memory {
+            return Ok(self.requests.get(&summary.id()));
+        }
+
         let state = exchange.map(|exchange| {
-            let state = RequestState::response(exchange);
+            let state =
+                RequestState::response(exchange, self.protobuf.as_ref());
             // Insert into the map, get a reference back
             // unstable: https://doc.rust-lang.org/std/collections/hash_map/enum.Entry.html#method.insert_entry
             match self.requests.entry(state.id()) {
@@ -81,30 +134,27 @@ impl RequestStore {
         Ok(state)
     }
 
-    /// Load all historical requests for a recipe+profile, then return the
-    /// *entire* set of requests, including in-progress ones. Returned requests
-    /// are just summaries, not the full request. This is intended for list
-    /// views, so we don't need to load the entire request/response for each
-    /// one. Results are sorted by request *start* time, descending.
+    /// Load all historical requests for a recipe, across all profiles, then
+    /// return the *entire* set of requests, including in-progress ones.
+    /// Returned requests are just summaries, not the full request. This is
+    /// intended for list views, so we don't need to load the entire
+    /// request/response for each one. Results are sorted by request *start*
+    /// time, descending.
     pub fn load_summaries<'a>(
         &'a self,
-        profile_id: Option<&'a ProfileId>,
         recipe_id: &'a RecipeId,
     ) -> anyhow::Result<impl 'a + Iterator<Item = RequestStateSummary>> {
         // Load summaries from the DB. We do *not* want to insert these into the
         // store, because they don't include request/response data
         let loaded = ViewContext::with_database(|database| {
-            database.get_all_requests(profile_id, recipe_id)
+            database.get_recipe_requests(recipe_id)
         })?;
 
         // Find what we have in memory already
         let iter = self
             .requests
             .values()
-            .filter(move |state| {
-                state.profile_id() == profile_id
-                    && state.recipe_id() == recipe_id
-            })
+            .filter(move |state| state.recipe_id() == recipe_id)
             .map(RequestStateSummary::from)
             // Add what we loaded from the DB
             .chain(loaded.into_iter().map(RequestStateSummary::Response))
@@ -137,7 +187,7 @@ mod tests {
         let mut store = RequestStore::default();
         store
             .requests
-            .insert(exchange.id, RequestState::response(exchange));
+            .insert(exchange.id, RequestState::response(exchange, None));
 
         // This is a bit jank, but since we can't clone exchanges, the only way
         // to get the value back for comparison is to access the map directly
@@ -166,7 +216,7 @@ mod tests {
         }));
         assert_matches!(store.get(id), Some(RequestState::Loading { .. }));
 
-        assert!(!store.update(RequestState::response(exchange)));
+        assert!(!store.update(RequestState::response(exchange, None)));
         assert_matches!(store.get(id), Some(RequestState::Response { .. }));
 
         // Insert a new request, just to make sure it's independent
@@ -180,6 +230,7 @@ mod tests {
         }));
         assert_matches!(store.get(id), Some(RequestState::Response { .. }));
         assert_matches!(store.get(id2), Some(RequestState::Building { .. }));
+        assert_eq!(store.in_flight(), 1);
     }
 
     #[rstest]
@@ -198,7 +249,7 @@ mod tests {
         // Already in store, don't fetch
         store
             .requests
-            .insert(present_id, RequestState::response(present_exchange));
+            .insert(present_id, RequestState::response(present_exchange, None));
         assert_matches!(
             store.get(present_id),
             Some(RequestState::Response { .. })
@@ -242,7 +293,7 @@ mod tests {
         let mut store = RequestStore::default();
         assert_eq!(
             store.load_latest(Some(&profile_id), &recipe_id).unwrap(),
-            Some(&RequestState::response(expected_exchange))
+            Some(&RequestState::response(expected_exchange, None))
         );
 
         // Non-match
@@ -250,6 +301,23 @@ mod tests {
             store.load_latest(Some(&profile_id), &("other".into())),
             Ok(None)
         );
+
+        // A request in flight is newer than anything in the DB
+        let loading_id = RequestId::new();
+        store.update(RequestState::Building {
+            id: loading_id,
+            start_time: Utc::now(),
+            profile_id: Some(profile_id.clone()),
+            recipe_id: recipe_id.clone(),
+        });
+        assert_eq!(
+            store
+                .load_latest(Some(&profile_id), &recipe_id)
+                .unwrap()
+                .map(RequestState::id),
+            Some(loading_id)
+        );
+        assert_eq!(store.in_flight_recipes(), HashSet::from_iter([&recipe_id]));
     }
 
     #[rstest]
@@ -262,8 +330,10 @@ mod tests {
                 create_exchange(&harness, Some(&profile_id), Some(&recipe_id))
             })
             .collect_vec();
-        // Create some confounders
-        create_exchange(&harness, None, Some(&recipe_id));
+        // Requests from other profiles should be included
+        let other_profile_exchange_id =
+            create_exchange(&harness, None, Some(&recipe_id)).id;
+        // Confounder
         create_exchange(&harness, Some(&profile_id), None);
 
         // Add one request of each possible state. We expect to get em all back
@@ -272,7 +342,7 @@ mod tests {
         // Pre-load one from the DB, to make sure it gets de-duped
         let exchange = exchanges.pop().unwrap();
         let response_id = exchange.id;
-        store.update(RequestState::response(exchange));
+        store.update(RequestState::response(exchange, None));
 
         let building_id = RequestId::new();
         store.update(RequestState::Building {
@@ -317,13 +387,15 @@ mod tests {
             },
         });
 
-        // Neither of these should appear
+        // Different profile, but the same recipe, so it should appear
+        let other_building_id = RequestId::new();
         store.update(RequestState::Building {
-            id: RequestId::new(),
+            id: other_building_id,
             start_time: Utc::now(),
             profile_id: Some(ProfileId::factory(())),
             recipe_id: recipe_id.clone(),
         });
+        // Different recipe, should not appear
         store.update(RequestState::Building {
             id: RequestId::new(),
             start_time: Utc::now(),
@@ -334,13 +406,11 @@ mod tests {
         // It's really annoying to do a full equality comparison because we'd
         // have to re-create each piece of data (they don't impl Clone), so
         // instead do a pattern match, then check the IDs
-        let loaded = store
-            .load_summaries(Some(&profile_id), &recipe_id)
-            .unwrap()
-            .collect_vec();
+        let loaded = store.load_summaries(&recipe_id).unwrap().collect_vec();
         assert_matches!(
             loaded.as_slice(),
             &[
+                RequestStateSummary::Building { .. },
                 RequestStateSummary::RequestError { .. },
                 RequestStateSummary::Loading { .. },
                 RequestStateSummary::BuildError { .. },
@@ -350,6 +420,7 @@ mod tests {
                 RequestStateSummary::Response { .. },
                 RequestStateSummary::Response { .. },
                 RequestStateSummary::Response { .. },
+                RequestStateSummary::Response { .. },
             ]
         );
 
@@ -358,10 +429,12 @@ mod tests {
         assert_eq!(
             ids.as_slice(),
             &[
+                other_building_id,
                 request_error_id,
                 loading_id,
                 build_error_id,
                 building_id,
+                other_profile_exchange_id,
                 response_id, // This one got de-duped
                 exchanges[3].id,
                 exchanges[2].id,
@@ -8,12 +8,18 @@ use crate::{
         },
     },
 };
+use crossterm::event::MouseEvent;
 use persisted::PersistedContainer;
 use ratatui::{
+    layout::Rect,
     widgets::{ListState, StatefulWidget, TableState},
     Frame,
 };
-use std::{cell::RefCell, fmt::Debug, marker::PhantomData};
+use std::{
+    cell::{Cell, RefCell},
+    fmt::Debug,
+    marker::PhantomData,
+};
 
 /// State manager for a dynamic list of items.
 ///
@@ -30,6 +36,8 @@ where
     /// rendering without a mutable reference.
     state: RefCell<State>,
     items: Vec<Item>,
+    /// Area the list was last drawn to, for mapping clicks to items
+    area: Cell<Rect>,
     /// Callback when an item is highlighted
     #[debug(skip)]
     on_select: Option<Callback<Item>>,
@@ -105,6 +113,7 @@ impl<Item, State> SelectStateBuilder<Item, State> {
         let mut select = SelectState {
             state: RefCell::default(),
             items: self.items,
+            area: Cell::default(),
             on_select: self.on_select,
             on_submit: self.on_submit,
         };
@@ -156,6 +165,23 @@ impl<Item, State: SelectStateData> SelectState<Item, State> {
         }
     }
 
+    /// Call the submission callback on the selected item, if there is one.
+    /// Return whether there's a submission callback at all.
+    pub fn submit(&mut self) -> bool {
+        let Some(on_submit) = &self.on_submit else {
+            return false;
+        };
+        let selected = self
+            .state
+            .get_mut()
+            .selected()
+            .and_then(|index| self.items.get_mut(index));
+        if let Some(selected) = selected {
+            on_submit(selected);
+        }
+        true
+    }
+
     /// Select the previous item in the list
     pub fn previous(&mut self) {
         self.select_delta(-1);
@@ -185,6 +211,21 @@ impl<Item, State: SelectStateData> SelectState<Item, State> {
         }
     }
 
+    /// Select whichever item was drawn under a mouse click, if any
+    fn click(&mut self, mouse_event: &MouseEvent) {
+        let Some(row) = mouse_event.row.checked_sub(self.area.get().y) else {
+            return;
+        };
+        let index = self
+            .state
+            .get_mut()
+            .index_at(row)
+            .filter(|index| *index < self.items.len());
+        if let Some(index) = index {
+            self.select_index(index);
+        }
+    }
+
     /// Move some number of items up or down the list. Selection will wrap if
     /// it underflows/overflows. Context is required for callbacks.
     fn select_delta(&mut self, delta: isize) {
@@ -229,19 +270,21 @@ where
         match action {
             Action::Up | Action::ScrollUp => self.previous(),
             Action::Down | Action::ScrollDown => self.next(),
+            Action::LeftClick => {
+                if let Event::Input {
+                    event: crossterm::event::Event::Mouse(mouse_event),
+                    ..
+                } = &event
+                {
+                    self.click(mouse_event);
+                }
+                // Let the parent see the click too, so it can grab focus
+                return Update::Propagate(event);
+            }
             Action::Submit => {
                 // If we have an on_submit, our parent wants us to handle
                 // submit events so consume it even if nothing is selected
-                if let Some(on_submit) = &self.on_submit {
-                    let selected = self
-                        .state
-                        .get_mut()
-                        .selected()
-                        .and_then(|index| self.items.get_mut(index));
-                    if let Some(selected) = selected {
-                        on_submit(selected);
-                    }
-                } else {
+                if !self.submit() {
                     return Update::Propagate(event);
                 }
             }
@@ -261,6 +304,7 @@ where
     W: StatefulWidget<State = State>,
 {
     fn draw(&self, frame: &mut Frame, props: W, metadata: DrawMetadata) {
+        self.area.set(metadata.area());
         frame.render_stateful_widget(
             props,
             metadata.area(),
@@ -304,6 +348,11 @@ pub trait SelectStateData: Default {
 
     /// Visual offset into the list, for scrolling
     fn offset(&self) -> usize;
+
+    /// Index of the element drawn at the given row, relative to the top of
+    /// the drawn area. The index may be out of bounds for the list. `None` if
+    /// the row doesn't correspond to an element.
+    fn index_at(&self, row: u16) -> Option<usize>;
 }
 
 impl SelectStateData for ListState {
@@ -318,6 +367,10 @@ impl SelectStateData for ListState {
     fn offset(&self) -> usize {
         self.offset()
     }
+
+    fn index_at(&self, row: u16) -> Option<usize> {
+        Some(self.offset() + row as usize)
+    }
 }
 
 impl SelectStateData for TableState {
@@ -332,6 +385,12 @@ impl SelectStateData for TableState {
     fn offset(&self) -> usize {
         self.offset()
     }
+
+    fn index_at(&self, row: u16) -> Option<usize> {
+        // Our tables are always drawn with a header row
+        let row = row.checked_sub(1)?;
+        Some(self.offset() + row as usize)
+    }
 }
 
 impl SelectStateData for usize {
@@ -346,6 +405,10 @@ impl SelectStateData for usize {
     fn offset(&self) -> usize {
         0 // Assume all elements are always visible
     }
+
+    fn index_at(&self, _: u16) -> Option<usize> {
+        None // Not drawn as a vertical list
+    }
 }
 
 /// Find the index of a value in the list
@@ -361,7 +424,7 @@ mod tests {
     use super::*;
     use crate::{
         collection::{Profile, ProfileId},
-        test_util::Factory,
+        test_util::{assert_matches, Factory},
         tui::{
             test_util::{harness, TestHarness},
             view::{
@@ -390,6 +453,27 @@ mod tests {
         assert_eq!(component.data().selected(), Some(&'a'));
     }
 
+    /// Clicking an item selects it, and the click is passed up to the parent
+    #[rstest]
+    fn test_click(harness: TestHarness) {
+        let select = SelectState::builder(vec!['a', 'b', 'c']).build();
+        let mut component =
+            TestComponent::new(harness, select, List::new(["a", "b", "c"]));
+        let propagated = component.click(0, 2);
+        assert_matches!(
+            propagated.events(),
+            [Event::Input {
+                action: Some(Action::LeftClick),
+                ..
+            }]
+        );
+        assert_eq!(component.data().selected(), Some(&'c'));
+
+        // Clicking below the list does nothing
+        let _ = component.click(0, 5);
+        assert_eq!(component.data().selected(), Some(&'c'));
+    }
+
     /// Test on_select callback
     #[rstest]
     fn test_on_select(harness: TestHarness) {
@@ -225,4 +225,9 @@ impl PropagatedEvents {
             self.0
         )
     }
+
+    /// Get the propagated events, for tests that expect some
+    pub fn events(&self) -> &[Event] {
+        &self.0
+    }
 }
@@ -18,6 +18,14 @@ pub struct Theme {
     pub secondary_color: Color,
     pub success_color: Color,
     pub error_color: Color,
+    /// Show the status class (e.g. `[4xx]`) next to response status codes
+    pub status_badges: bool,
+    /// Show a symbol (e.g. `✘`) next to response status codes
+    pub status_symbols: bool,
+    /// Disable this for terminals without color support, or if the colors are
+    /// hard to tell apart. Emphasis is then shown with text modifiers (bold,
+    /// reverse video, etc.) only, and all color fields are ignored.
+    pub color: bool,
 }
 
 impl Default for Theme {
@@ -28,6 +36,9 @@ impl Default for Theme {
             secondary_color: Color::Yellow,
             success_color: Color::Green,
             error_color: Color::Red,
+            status_badges: false,
+            status_symbols: false,
+            color: true,
         }
     }
 }
@@ -117,7 +128,10 @@ pub struct TableStyles {
 /// Styles for TemplatePreview component
 #[derive(Debug)]
 pub struct TemplatePreviewStyles {
+    /// Rendered values
     pub text: Style,
+    /// Template keys in unrendered source text
+    pub key: Style,
     pub error: Style,
 }
 
@@ -150,6 +164,10 @@ pub struct TextWindowStyle {
 
 impl Styles {
     pub fn new(theme: &Theme) -> Self {
+        if !theme.color {
+            return Self::monochrome();
+        }
+
         Self {
             list: ListStyles {
                 highlight: Style::default()
@@ -197,6 +215,7 @@ impl Styles {
             },
             template_preview: TemplatePreviewStyles {
                 text: Style::default().fg(theme.secondary_color),
+                key: Style::default().fg(theme.primary_color),
                 error: Style::default().bg(theme.error_color),
             },
             text: TextStyle {
@@ -217,4 +236,71 @@ impl Styles {
             },
         }
     }
+
+    /// Styles that don't use any color, for when color is disabled in the
+    /// theme. Anything that relies on color to stand out gets a modifier
+    /// instead.
+    fn monochrome() -> Self {
+        Self {
+            list: ListStyles {
+                highlight: Style::default()
+                    .add_modifier(Modifier::REVERSED)
+                    .add_modifier(Modifier::BOLD),
+            },
+            modal: ModalStyles {
+                border: Style::default(),
+                border_type: BorderType::Double,
+            },
+            pane: PaneStyles {
+                border: Style::default(),
+                border_selected: Style::default().add_modifier(Modifier::BOLD),
+                border_type: BorderType::Plain,
+                border_type_selected: BorderType::Double,
+            },
+            status_code: StatusCodeStyles {
+                success: Style::default().add_modifier(Modifier::BOLD),
+                error: Style::default()
+                    .add_modifier(Modifier::REVERSED)
+                    .add_modifier(Modifier::BOLD),
+            },
+            tab: TabStyles {
+                highlight: Style::default()
+                    .add_modifier(Modifier::BOLD)
+                    .add_modifier(Modifier::UNDERLINED),
+            },
+            table: TableStyles {
+                header: Style::default()
+                    .add_modifier(Modifier::BOLD)
+                    .add_modifier(Modifier::UNDERLINED),
+                text: Style::default(),
+                alt: Style::default(),
+                disabled: Style::default().add_modifier(Modifier::DIM),
+                highlight: Style::default()
+                    .add_modifier(Modifier::REVERSED)
+                    .add_modifier(Modifier::BOLD),
+                title: Style::default().add_modifier(Modifier::BOLD),
+            },
+            template_preview: TemplatePreviewStyles {
+                text: Style::default().add_modifier(Modifier::ITALIC),
+                key: Style::default().add_modifier(Modifier::UNDERLINED),
+                error: Style::default().add_modifier(Modifier::REVERSED),
+            },
+            text: TextStyle {
+                highlight: Style::default().add_modifier(Modifier::REVERSED),
+                primary: Style::default().add_modifier(Modifier::BOLD),
+                error: Style::default().add_modifier(Modifier::REVERSED),
+            },
+            text_box: TextBoxStyle {
+                text: Style::default().add_modifier(Modifier::UNDERLINED),
+                cursor: Style::default().add_modifier(Modifier::REVERSED),
+                placeholder: Style::default().add_modifier(Modifier::DIM),
+                invalid: Style::default()
+                    .add_modifier(Modifier::UNDERLINED)
+                    .add_modifier(Modifier::CROSSED_OUT),
+            },
+            text_window: TextWindowStyle {
+                gutter: Style::default().add_modifier(Modifier::DIM),
+            },
+        }
+    }
 }
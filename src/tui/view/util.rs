@@ -1,7 +1,10 @@
 //! Helper structs and functions for building components
 
-use crate::template::{Prompt, PromptChannel, Prompter};
+use crate::template::{Prompt, PromptChannel, Prompter, RenderProgress};
+use chrono::Utc;
 use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use std::sync::{atomic::AtomicBool, Arc};
+use tokio::sync::oneshot;
 
 /// A data structure for representation a yes/no confirmation. This is similar
 /// to [Prompt], but it only asks a yes/no question.
@@ -13,6 +16,18 @@ pub struct Confirm {
     pub channel: PromptChannel<bool>,
 }
 
+/// A request build that's taking a while. Shown to the user so they can see
+/// what it's waiting on, and cancel it if it's stuck.
+#[derive(Debug)]
+pub struct BuildProgress {
+    /// Template keys being rendered for the build
+    pub progress: RenderProgress,
+    /// Set once the build is finished (successfully or not)
+    pub done: Arc<AtomicBool>,
+    /// Send a value to abort the build
+    pub cancel: oneshot::Sender<()>,
+}
+
 /// A prompter that returns a static value; used for template previews, where
 /// user interaction isn't possible
 #[derive(Debug)]
@@ -55,3 +70,48 @@ pub fn centered_rect(
         .constraints([buffer_x, width, buffer_x].as_ref())
         .split(columns[1])[1]
 }
+
+/// Does the given text match a list filter? Matching is fuzzy: the text must
+/// contain every character of the filter in order, but not necessarily next to
+/// each other. Matching is case-insensitive and whitespace in the filter is
+/// ignored. An empty filter matches everything.
+pub fn matches_filter(text: &str, filter: &str) -> bool {
+    let mut text = text.chars().flat_map(char::to_lowercase);
+    filter
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .all(|f| text.any(|c| c == f))
+}
+
+/// Get the current frame of a loading spinner. Frames are derived from the
+/// clock, so all spinners on screen stay in sync.
+pub fn spinner() -> char {
+    const FRAMES: &[char] = &['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+    // Advance once per TUI tick
+    const FRAME_MILLIS: i64 = 250;
+    let frame = Utc::now().timestamp_millis() / FRAME_MILLIS;
+    FRAMES[frame as usize % FRAMES.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::empty("Get User", "", true)]
+    #[case::substring("Get User", "user", true)]
+    #[case::subsequence("Get User", "gusr", true)]
+    #[case::whitespace("Get User", " get user ", true)]
+    #[case::case_insensitive("Get User", "GETUSER", true)]
+    #[case::out_of_order("Get User", "user get", false)]
+    #[case::missing("Get User", "login", false)]
+    fn test_matches_filter(
+        #[case] text: &str,
+        #[case] filter: &str,
+        #[case] expected: bool,
+    ) {
+        assert_eq!(matches_filter(text, filter), expected);
+    }
+}
@@ -5,6 +5,7 @@ use crate::{
     template::ChainError,
     tui::message::{Message, MessageSender},
 };
+use anyhow::anyhow;
 use chrono::{
     format::{DelayedFormat, StrftimeItems},
     DateTime, Duration, Local, Utc,
@@ -14,9 +15,12 @@ use dialoguer::console::Style;
 use reqwest::header::HeaderMap;
 use serde::de::DeserializeOwned;
 use std::{
+    env,
     fmt::{self, Debug, Formatter},
     iter::FusedIterator,
     ops::Deref,
+    path::Path,
+    process::Command,
 };
 use strum::{EnumCount, IntoEnumIterator};
 use tracing::error;
@@ -36,6 +40,94 @@ pub fn doc_link(path: &str) -> String {
     format!("{WEBSITE}/book/{path}.html")
 }
 
+/// Get the OS keyring service name under which secrets for a collection are
+/// stored, when the user doesn't specify a service explicitly. The path should
+/// be canonicalized, so the name is stable regardless of working directory.
+pub fn keyring_service(collection_path: &Path) -> String {
+    format!("slumber:{}", collection_path.display())
+}
+
+/// Does this path contain glob metacharacters? Useful for finding the literal
+/// prefix of a glob pattern.
+pub fn is_glob(path: &Path) -> bool {
+    path.to_string_lossy().contains(['*', '?', '['])
+}
+
+/// Does a `Content-Type` value match a MIME type pattern such as
+/// `application/json`? Either half of the pattern can be `*` (e.g. `text/*`)
+/// to match anything. Parameters such as `charset` are ignored, and matching
+/// is case-insensitive.
+pub fn mime_matches(pattern: &str, content_type: &str) -> bool {
+    let essence = content_type.split(';').next().unwrap_or_default();
+    let (Some((type_, subtype)), Some((pattern_type, pattern_subtype))) =
+        (essence.trim().split_once('/'), pattern.split_once('/'))
+    else {
+        return false;
+    };
+    let matches = |pattern: &str, value: &str| {
+        pattern == "*" || pattern.eq_ignore_ascii_case(value)
+    };
+    matches(pattern_type, type_) && matches(pattern_subtype, subtype)
+}
+
+/// Match text against a wildcard pattern, where `*` matches any sequence of
+/// characters (including none) and `?` matches exactly one. The whole text
+/// must match, and matching is case-sensitive.
+pub fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Position of the last `*` in the pattern, and the text position it was
+    // matched at. On mismatch, backtrack to it and let it consume one more
+    // character.
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star_p, star_t)) => {
+                    backtrack = Some((star_p, star_t + 1));
+                    p = star_p + 1;
+                    t = star_t + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    // Any trailing stars can match nothing
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Get a command to open the given file in the user's configured editor. Return
+/// an error if the user has no editor configured
+pub fn get_editor_command(file: &Path) -> anyhow::Result<Command> {
+    let command = env::var("VISUAL").or(env::var("EDITOR")).map_err(|_| {
+        anyhow!(
+            "No editor configured. Please set the `VISUAL` or `EDITOR` \
+            environment variable"
+        )
+    })?;
+    Ok(file_command(&command, file))
+}
+
+/// Build a command from a string of space-separated program and arguments,
+/// with a file path as the final argument. Quoting is not supported.
+pub fn file_command(command: &str, file: &Path) -> Command {
+    let mut splits = command.split(' ');
+    let program = splits.next().expect("`split` returns at least one value");
+    let mut command = Command::new(program);
+    command.args(splits).arg(file);
+    command
+}
+
 /// Parse bytes (probably from a file) into YAML. This will merge any
 /// anchors/aliases.
 pub fn parse_yaml<T: DeserializeOwned>(bytes: &[u8]) -> serde_yaml::Result<T> {
@@ -186,6 +278,20 @@ impl<'a> Display for MaybeStr<'a> {
     }
 }
 
+/// Cut bytes down to at most `max_len`, for previewing large content. If the
+/// cut would split a UTF-8 character, it's moved back to the start of that
+/// character, so valid text stays valid.
+pub fn truncate_bytes(bytes: &[u8], max_len: usize) -> &[u8] {
+    let truncated = &bytes[..max_len.min(bytes.len())];
+    match std::str::from_utf8(truncated) {
+        // error_len is None only when the input ends mid-character
+        Err(error) if error.error_len().is_none() => {
+            &truncated[..error.valid_up_to()]
+        }
+        _ => truncated,
+    }
+}
+
 /// Wrapper making it easy to print a header map
 pub struct HeaderDisplay<'a>(pub &'a HeaderMap);
 
@@ -370,6 +476,10 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{test_util::assert_err, tui::test_util::EnvGuard};
+    use itertools::Itertools;
+    use rstest::rstest;
+    use std::ffi::OsStr;
     use strum::EnumIter;
 
     #[derive(Clone, Debug, PartialEq, EnumIter)]
@@ -385,6 +495,38 @@ mod tests {
         Five,
     }
 
+    #[rstest]
+    #[case::short(b"hello", 10, b"hello")]
+    #[case::ascii(b"hello", 3, b"hel")]
+    #[case::char_boundary("héllo".as_bytes(), 2, b"h")]
+    #[case::binary(b"\xff\xfe\xfd", 2, b"\xff\xfe")]
+    fn test_truncate_bytes(
+        #[case] bytes: &[u8],
+        #[case] max_len: usize,
+        #[case] expected: &[u8],
+    ) {
+        assert_eq!(truncate_bytes(bytes, max_len), expected);
+    }
+
+    #[rstest]
+    #[case::exact("abc", "abc", true)]
+    #[case::mismatch("abc", "abd", false)]
+    #[case::star("a*c", "abbbc", true)]
+    #[case::star_empty("a*c", "ac", true)]
+    #[case::star_backtrack("*.com", "a.com.evil.com", true)]
+    #[case::question("a?c", "abc", true)]
+    #[case::question_empty("a?c", "ac", false)]
+    #[case::unicode("h?llo", "héllo", true)]
+    #[case::anchored("abc", "xabc", false)]
+    #[case::trailing_stars("abc**", "abc", true)]
+    fn test_wildcard_match(
+        #[case] pattern: &str,
+        #[case] text: &str,
+        #[case] expected: bool,
+    ) {
+        assert_eq!(wildcard_match(pattern, text), expected);
+    }
+
     /// Forward iteration
     #[test]
     fn test_enum_chain_iter() {
@@ -430,4 +572,57 @@ mod tests {
         iter.next();
         assert_eq!(iter.len(), 0);
     }
+
+    /// Test reading editor command from VISUAL/EDITOR env vars
+    #[rstest]
+    #[case::visual(Some("ted"), Some("fred"), "ted", &[])]
+    #[case::editor(None, Some("fred"), "fred", &[])]
+    #[case::with_args(None, Some("ned --wait 60s"), "ned", &["--wait", "60s"])]
+    // This case is actually a bug, but I don't think it's worth the effort of
+    // engineering around. I added this test case for completeness
+    #[case::with_args_quoted(
+        None, Some("ned '--wait 60s'"), "ned", &["'--wait", "60s'"],
+    )]
+    fn test_get_editor(
+        #[case] env_visual: Option<&str>,
+        #[case] env_editor: Option<&str>,
+        #[case] expected_program: &str,
+        #[case] expected_args: &[&str],
+    ) {
+        let file_name = "file.yml";
+        // Make sure we're not competing with the other tests that want to set
+        // these env vars
+        let command = {
+            let _guard = EnvGuard::lock([
+                ("VISUAL", env_visual),
+                ("EDITOR", env_editor),
+            ]);
+            get_editor_command(Path::new(file_name))
+        }
+        .unwrap();
+        let mut expected_args = expected_args.to_owned();
+        expected_args.push(file_name);
+        assert_eq!(command.get_program(), expected_program);
+        assert_eq!(
+            command
+                .get_args()
+                .filter_map(OsStr::to_str)
+                .collect_vec()
+                .as_slice(),
+            expected_args
+        );
+    }
+
+    /// Test when VISUAL/EDITOR env vars are empty
+    #[test]
+    fn test_get_editor_error() {
+        // Make sure we're not competing with the other tests that want to set
+        // these env vars
+        let result = {
+            let _guard =
+                EnvGuard::lock([("VISUAL", None::<String>), ("EDITOR", None)]);
+            get_editor_command(Path::new("file.yml"))
+        };
+        assert_err!(result, "No editor configured");
+    }
 }